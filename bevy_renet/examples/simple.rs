@@ -138,7 +138,7 @@ fn server_update_system(
 ) {
     for event in server_events.read() {
         match event {
-            ServerEvent::ClientConnected { client_id } => {
+            ServerEvent::ClientConnected { client_id, .. } => {
                 println!("Player {} connected.", client_id);
                 // Spawn player cube
                 let player_entity = commands
@@ -164,7 +164,7 @@ fn server_update_system(
                 let message = bincode::serialize(&ServerMessages::PlayerConnected { id: *client_id }).unwrap();
                 server.broadcast_message(DefaultChannel::ReliableOrdered, message);
             }
-            ServerEvent::ClientDisconnected { client_id, reason } => {
+            ServerEvent::ClientDisconnected { client_id, reason, .. } => {
                 println!("Player {} disconnected: {}", client_id, reason);
                 if let Some(player_entity) = lobby.players.remove(client_id) {
                     commands.entity(player_entity).despawn();
@@ -173,6 +173,12 @@ fn server_update_system(
                 let message = bincode::serialize(&ServerMessages::PlayerDisconnected { id: *client_id }).unwrap();
                 server.broadcast_message(DefaultChannel::ReliableOrdered, message);
             }
+            ServerEvent::ClientQueued { addr, position, .. } => {
+                println!("Player {} queued at position {}", addr, position);
+            }
+            ServerEvent::ClientQuotaWarning { client_id, kind, .. } => {
+                println!("Player {} exceeded its quota of {}", client_id, kind);
+            }
         }
     }
 
@@ -140,7 +140,7 @@ fn server_update_system(
 ) {
     for event in server_events.read() {
         match event {
-            ServerEvent::ClientConnected { client_id } => {
+            ServerEvent::ClientConnected { client_id, .. } => {
                 println!("Player {} connected.", client_id);
                 visualizer.add_client(*client_id);
 
@@ -181,7 +181,7 @@ fn server_update_system(
                 .unwrap();
                 server.broadcast_message(ServerChannel::ServerMessages, message);
             }
-            ServerEvent::ClientDisconnected { client_id, reason } => {
+            ServerEvent::ClientDisconnected { client_id, reason, .. } => {
                 println!("Player {} disconnected: {}", client_id, reason);
                 visualizer.remove_client(*client_id);
                 if let Some(player_entity) = lobby.players.remove(client_id) {
@@ -191,6 +191,12 @@ fn server_update_system(
                 let message = bincode::serialize(&ServerMessages::PlayerRemove { id: *client_id }).unwrap();
                 server.broadcast_message(ServerChannel::ServerMessages, message);
             }
+            ServerEvent::ClientQueued { addr, position, .. } => {
+                println!("Player {} queued at position {}", addr, position);
+            }
+            ServerEvent::ClientQuotaWarning { client_id, kind, .. } => {
+                println!("Player {} exceeded its quota of {}", client_id, kind);
+            }
         }
     }
 
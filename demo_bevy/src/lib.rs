@@ -1,7 +1,7 @@
 use std::{f32::consts::PI, time::Duration};
 
 use bevy::prelude::{shape::Icosphere, *};
-use bevy_renet::renet::{ChannelConfig, ClientId, ConnectionConfig, SendType};
+use bevy_renet::renet::{ChannelConfig, ChannelId, ClientId, ConnectionConfig, SendType};
 use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "transport")]
@@ -63,11 +63,11 @@ pub struct NetworkedEntities {
     pub translations: Vec<[f32; 3]>,
 }
 
-impl From<ClientChannel> for u8 {
+impl From<ClientChannel> for ChannelId {
     fn from(channel_id: ClientChannel) -> Self {
         match channel_id {
-            ClientChannel::Command => 0,
-            ClientChannel::Input => 1,
+            ClientChannel::Command => ChannelId::from_raw(0),
+            ClientChannel::Input => ChannelId::from_raw(1),
         }
     }
 }
@@ -76,28 +76,34 @@ impl ClientChannel {
     pub fn channels_config() -> Vec<ChannelConfig> {
         vec![
             ChannelConfig {
-                channel_id: Self::Input.into(),
-                max_memory_usage_bytes: 5 * 1024 * 1024,
-                send_type: SendType::ReliableOrdered {
-                    resend_time: Duration::ZERO,
-                },
+                warning_threshold: None,
+                ..ChannelConfig::new(
+                    Self::Input,
+                    5 * 1024 * 1024,
+                    SendType::ReliableOrdered {
+                        resend_time: Duration::ZERO,
+                    },
+                )
             },
             ChannelConfig {
-                channel_id: Self::Command.into(),
-                max_memory_usage_bytes: 5 * 1024 * 1024,
-                send_type: SendType::ReliableOrdered {
-                    resend_time: Duration::ZERO,
-                },
+                warning_threshold: None,
+                ..ChannelConfig::new(
+                    Self::Command,
+                    5 * 1024 * 1024,
+                    SendType::ReliableOrdered {
+                        resend_time: Duration::ZERO,
+                    },
+                )
             },
         ]
     }
 }
 
-impl From<ServerChannel> for u8 {
+impl From<ServerChannel> for ChannelId {
     fn from(channel_id: ServerChannel) -> Self {
         match channel_id {
-            ServerChannel::NetworkedEntities => 0,
-            ServerChannel::ServerMessages => 1,
+            ServerChannel::NetworkedEntities => ChannelId::from_raw(0),
+            ServerChannel::ServerMessages => ChannelId::from_raw(1),
         }
     }
 }
@@ -106,16 +112,18 @@ impl ServerChannel {
     pub fn channels_config() -> Vec<ChannelConfig> {
         vec![
             ChannelConfig {
-                channel_id: Self::NetworkedEntities.into(),
-                max_memory_usage_bytes: 10 * 1024 * 1024,
-                send_type: SendType::Unreliable,
+                warning_threshold: None,
+                ..ChannelConfig::new(Self::NetworkedEntities, 10 * 1024 * 1024, SendType::Unreliable)
             },
             ChannelConfig {
-                channel_id: Self::ServerMessages.into(),
-                max_memory_usage_bytes: 10 * 1024 * 1024,
-                send_type: SendType::ReliableOrdered {
-                    resend_time: Duration::from_millis(200),
-                },
+                warning_threshold: None,
+                ..ChannelConfig::new(
+                    Self::ServerMessages,
+                    10 * 1024 * 1024,
+                    SendType::ReliableOrdered {
+                        resend_time: Duration::from_millis(200),
+                    },
+                )
             },
         ]
     }
@@ -126,6 +134,7 @@ pub fn connection_config() -> ConnectionConfig {
         available_bytes_per_tick: 1024 * 1024,
         client_channels_config: ClientChannel::channels_config(),
         server_channels_config: ServerChannel::channels_config(),
+        ..Default::default()
     }
 }
 
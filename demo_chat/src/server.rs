@@ -13,7 +13,7 @@ use renet_visualizer::RenetServerVisualizer;
 
 use crate::{ClientMessages, Message, ServerMessages, Username, PROTOCOL_ID};
 use bincode::Options;
-use log::info;
+use log::{info, warn};
 
 pub const SYSTEM_MESSAGE_CLIENT_ID: ClientId = ClientId::from_raw(0);
 pub const HOST_CLIENT_ID: ClientId = ClientId::from_raw(1);
@@ -62,7 +62,7 @@ impl ChatServer {
 
         while let Some(event) = self.server.get_event() {
             match event {
-                ServerEvent::ClientConnected { client_id } => {
+                ServerEvent::ClientConnected { client_id, .. } => {
                     let user_data = self.transport.user_data(client_id).unwrap();
                     self.visualizer.add_client(client_id);
                     let username = Username::from_user_data(&user_data).0;
@@ -77,7 +77,7 @@ impl ChatServer {
                     let init_message = bincode::options().serialize(&init_message).unwrap();
                     self.server.send_message(client_id, DefaultChannel::ReliableOrdered, init_message);
                 }
-                ServerEvent::ClientDisconnected { client_id, reason: _ } => {
+                ServerEvent::ClientDisconnected { client_id, .. } => {
                     self.visualizer.remove_client(client_id);
                     self.usernames.remove(&client_id);
                     let message = bincode::options()
@@ -85,6 +85,12 @@ impl ChatServer {
                         .unwrap();
                     self.server.broadcast_message(DefaultChannel::ReliableOrdered, message);
                 }
+                ServerEvent::ClientQueued { addr, position, .. } => {
+                    info!("Client {} queued at position {}", addr, position);
+                }
+                ServerEvent::ClientQuotaWarning { client_id, kind, .. } => {
+                    warn!("Client {} exceeded its quota of {}", client_id, kind);
+                }
             }
         }
 
@@ -95,7 +95,7 @@ fn server(public_addr: SocketAddr) {
 
         while let Some(event) = server.get_event() {
             match event {
-                ServerEvent::ClientConnected { client_id } => {
+                ServerEvent::ClientConnected { client_id, .. } => {
                     let user_data = transport.user_data(client_id).unwrap();
                     let username = Username::from_user_data(&user_data);
                     server.broadcast_message_except(
@@ -106,7 +106,7 @@ fn server(public_addr: SocketAddr) {
                     usernames.insert(client_id, username.0);
                     println!("Client {} connected.", client_id)
                 }
-                ServerEvent::ClientDisconnected { client_id, reason } => {
+                ServerEvent::ClientDisconnected { client_id, reason, .. } => {
                     println!("Client {} disconnected: {}", client_id, reason);
                     if let Some(username) = usernames.remove(&client_id) {
                         server.broadcast_message_except(
@@ -116,6 +116,12 @@ fn server(public_addr: SocketAddr) {
                         );
                     }
                 }
+                ServerEvent::ClientQueued { addr, position, .. } => {
+                    println!("Client {} queued at position {}.", addr, position);
+                }
+                ServerEvent::ClientQuotaWarning { client_id, kind, .. } => {
+                    println!("Client {} exceeded its quota of {}.", client_id, kind);
+                }
             }
         }
 
@@ -166,7 +172,9 @@ fn client(server_addr: SocketAddr, username: Username) {
 
         if client.is_connected() {
             match stdin_channel.try_recv() {
-                Ok(text) => client.send_message(DefaultChannel::ReliableOrdered, text.as_bytes().to_vec()),
+                Ok(text) => {
+                    client.send_message(DefaultChannel::ReliableOrdered, text.as_bytes().to_vec());
+                }
                 Err(TryRecvError::Empty) => {}
                 Err(TryRecvError::Disconnected) => panic!("Channel disconnected"),
             }
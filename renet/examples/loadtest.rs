@@ -0,0 +1,130 @@
+//! Ramps up simulated clients against a local echo server and prints a latency and throughput report.
+//! Usage: loadtest [CLIENTS]
+
+use std::{
+    net::{SocketAddr, UdpSocket},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use renet::{
+    transport::{ClientAuthentication, ClientSwarm, NetcodeServerTransport, ServerAuthentication, ServerConfig},
+    ConnectionConfig, DefaultChannel, RenetServer,
+};
+
+const PROTOCOL_ID: u64 = 7;
+const TICK: Duration = Duration::from_millis(16);
+// New clients added every tick while ramping up
+const CLIENTS_PER_TICK: usize = 4;
+// Time with all the clients added before the final report
+const STEADY_DURATION: Duration = Duration::from_secs(5);
+
+fn main() {
+    env_logger::init();
+    let args: Vec<String> = std::env::args().collect();
+    let clients: usize = args.get(1).map(|clients| clients.parse().unwrap()).unwrap_or(200);
+
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let server_addr = socket.local_addr().unwrap();
+    thread::spawn(move || server(socket, clients));
+
+    let current_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap();
+    let mut swarm = ClientSwarm::new(current_time, ConnectionConfig::default(), server_addr);
+    let start = Instant::now();
+    let mut ramped_up_at = None;
+    let mut last_report = start;
+    let mut last_updated = start;
+    let mut echoed: u64 = 0;
+    loop {
+        let now = Instant::now();
+        swarm.update(now - last_updated);
+        last_updated = now;
+
+        if swarm.len() < clients {
+            for _ in 0..CLIENTS_PER_TICK.min(clients - swarm.len()) {
+                swarm.add_client(authentication(swarm.len() as u64, server_addr)).unwrap();
+            }
+        } else if ramped_up_at.is_none() {
+            ramped_up_at = Some(now);
+        }
+
+        // Every connected client sends its elapsed time, the server sends it back
+        swarm.broadcast_message(DefaultChannel::Unreliable, start.elapsed().as_micros().to_le_bytes().to_vec());
+        for index in 0..swarm.len() {
+            while swarm.receive_message(index, DefaultChannel::Unreliable).is_some() {
+                echoed += 1;
+            }
+        }
+
+        if last_report.elapsed() >= Duration::from_secs(1) {
+            print_report(&swarm, echoed as f64 / last_report.elapsed().as_secs_f64());
+            last_report = Instant::now();
+            echoed = 0;
+        }
+        if matches!(ramped_up_at, Some(ramped_up_at) if ramped_up_at.elapsed() >= STEADY_DURATION) {
+            break;
+        }
+
+        thread::sleep(TICK);
+    }
+}
+
+fn print_report(swarm: &ClientSwarm, echoes_per_second: f64) {
+    let report = swarm.report();
+    println!(
+        "clients {}/{} connected ({:.1}% success, {} disconnected, {:?} to connect) | rtt p50 {:.1}ms p99 {:.1}ms max {:.1}ms | loss {:.2}% | up {:.1} KB/s down {:.1} KB/s | {:.0} echoes/s",
+        report.connected,
+        report.clients,
+        report.connect_success_rate * 100.0,
+        report.disconnected,
+        report.average_connect_time,
+        report.rtt_percentile(0.5) * 1000.0,
+        report.rtt_percentile(0.99) * 1000.0,
+        report.rtt_percentile(1.0) * 1000.0,
+        report.average_packet_loss * 100.0,
+        report.bytes_sent_per_second / 1000.0,
+        report.bytes_received_per_second / 1000.0,
+        echoes_per_second,
+    );
+}
+
+fn authentication(client_id: u64, server_addr: SocketAddr) -> ClientAuthentication {
+    ClientAuthentication::Unsecure {
+        protocol_id: PROTOCOL_ID,
+        client_id,
+        server_addr,
+        user_data: None,
+    }
+}
+
+fn server(socket: UdpSocket, max_clients: usize) {
+    let mut server = RenetServer::new(ConnectionConfig::default());
+    let server_config = ServerConfig {
+        current_time: SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap(),
+        max_clients,
+        protocol_id: PROTOCOL_ID,
+        public_addresses: vec![socket.local_addr().unwrap()],
+        authentication: ServerAuthentication::Unsecure,
+    };
+    let mut transport = NetcodeServerTransport::new(server_config, socket).unwrap();
+
+    let mut last_updated = Instant::now();
+    loop {
+        let now = Instant::now();
+        let duration = now - last_updated;
+        last_updated = now;
+
+        server.update(duration);
+        transport.update(duration, &mut server).unwrap();
+        while server.get_event().is_some() {}
+
+        for client_id in server.clients_id() {
+            while let Some(message) = server.receive_message(client_id, DefaultChannel::Unreliable) {
+                server.send_message(client_id, DefaultChannel::Unreliable, message);
+            }
+        }
+
+        transport.send_packets(&mut server);
+        thread::sleep(TICK);
+    }
+}
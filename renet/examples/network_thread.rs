@@ -0,0 +1,185 @@
+//! Runs the server on a dedicated network thread, driven by the game thread with commands.
+//! `RenetServer`, `RenetClient` and the transports are `Send`: they can be moved to another thread,
+//! but they are not shared, a single thread owns and updates them.
+//! The game thread sends commands to the network thread and receives the events and messages back.
+//! A client connects from a third thread over localhost.
+use std::{
+    net::{SocketAddr, UdpSocket},
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use renet::{
+    transport::{ClientAuthentication, NetcodeClientTransport, NetcodeServerTransport, ServerAuthentication, ServerConfig},
+    Bytes, ClientId, ConnectionConfig, DefaultChannel, DisconnectReason, RenetClient, RenetServer, ServerEvent,
+};
+
+const PROTOCOL_ID: u64 = 7;
+const TICK: Duration = Duration::from_millis(16);
+
+// Sent by the game thread to the network thread
+enum Command {
+    SendMessage { client_id: ClientId, message: Bytes },
+    Broadcast { message: Bytes },
+    Disconnect { client_id: ClientId },
+}
+
+// Sent by the network thread to the game thread
+enum NetworkEvent {
+    Server(ServerEvent),
+    Message { client_id: ClientId, message: Bytes },
+}
+
+fn spawn_network_thread(public_addr: SocketAddr) -> (Sender<Command>, Receiver<NetworkEvent>) {
+    let (command_sender, commands) = mpsc::channel::<Command>();
+    let (event_sender, events) = mpsc::channel::<NetworkEvent>();
+
+    let socket = UdpSocket::bind(public_addr).unwrap();
+    let server_config = ServerConfig {
+        current_time: SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap(),
+        max_clients: 64,
+        protocol_id: PROTOCOL_ID,
+        public_addresses: vec![public_addr],
+        authentication: ServerAuthentication::Unsecure,
+    };
+    let mut transport = NetcodeServerTransport::new(server_config, socket).unwrap();
+    let mut server = RenetServer::new(ConnectionConfig::default());
+
+    // The server and its transport are moved to the network thread
+    thread::spawn(move || {
+        let mut last_updated = Instant::now();
+        loop {
+            let now = Instant::now();
+            let duration = now - last_updated;
+            last_updated = now;
+
+            server.update(duration);
+            transport.update(duration, &mut server).unwrap();
+
+            loop {
+                match commands.try_recv() {
+                    Ok(Command::SendMessage { client_id, message }) => {
+                        server.send_message(client_id, DefaultChannel::ReliableOrdered, message);
+                    }
+                    Ok(Command::Broadcast { message }) => server.broadcast_message(DefaultChannel::ReliableOrdered, message),
+                    Ok(Command::Disconnect { client_id }) => server.disconnect(client_id),
+                    Err(TryRecvError::Empty) => break,
+                    // The game thread is gone, stop the server
+                    Err(TryRecvError::Disconnected) => {
+                        transport.disconnect_all(&mut server);
+                        return;
+                    }
+                }
+            }
+
+            while let Some(event) = server.get_event() {
+                let _ = event_sender.send(NetworkEvent::Server(event));
+            }
+            for client_id in server.clients_id() {
+                while let Some(message) = server.receive_message(client_id, DefaultChannel::ReliableOrdered) {
+                    let _ = event_sender.send(NetworkEvent::Message { client_id, message });
+                }
+            }
+
+            transport.send_packets(&mut server);
+            thread::sleep(TICK);
+        }
+    });
+
+    (command_sender, events)
+}
+
+fn spawn_client_thread(server_addr: SocketAddr) -> thread::JoinHandle<Option<DisconnectReason>> {
+    thread::spawn(move || {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let current_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap();
+        let authentication = ClientAuthentication::Unsecure {
+            server_addr,
+            client_id: current_time.as_millis() as u64,
+            user_data: None,
+            protocol_id: PROTOCOL_ID,
+        };
+        let mut transport = NetcodeClientTransport::new(current_time, authentication, socket).unwrap();
+        let mut client = RenetClient::new(ConnectionConfig::default());
+
+        let mut greeted = false;
+        let mut last_updated = Instant::now();
+        while !client.is_disconnected() {
+            let now = Instant::now();
+            let duration = now - last_updated;
+            last_updated = now;
+
+            client.update(duration);
+            if transport.update(duration, &mut client).is_err() {
+                break;
+            }
+
+            if client.is_connected() {
+                if !greeted {
+                    client.send_message(DefaultChannel::ReliableOrdered, "hello");
+                    greeted = true;
+                }
+                while let Some(message) = client.receive_message(DefaultChannel::ReliableOrdered) {
+                    println!("Client received: {}", String::from_utf8_lossy(&message));
+                }
+            }
+
+            let _ = transport.send_packets(&mut client);
+            thread::sleep(TICK);
+        }
+
+        client.disconnect_reason()
+    })
+}
+
+fn main() {
+    env_logger::init();
+    let server_addr: SocketAddr = "127.0.0.1:5000".parse().unwrap();
+    let (commands, events) = spawn_network_thread(server_addr);
+    let client = spawn_client_thread(server_addr);
+
+    // Game loop, it never touches the server directly
+    let mut disconnect_at: Option<(ClientId, Instant)> = None;
+    loop {
+        if let Some((client_id, at)) = disconnect_at {
+            if Instant::now() >= at {
+                commands.send(Command::Disconnect { client_id }).unwrap();
+                disconnect_at = None;
+            }
+        }
+
+        let event = match events.recv_timeout(TICK) {
+            Ok(event) => event,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        match event {
+            NetworkEvent::Server(ServerEvent::ClientConnected { client_id, .. }) => {
+                println!("Client {client_id} connected");
+                commands.send(Command::Broadcast { message: "welcome".into() }).unwrap();
+            }
+            NetworkEvent::Server(ServerEvent::ClientDisconnected { client_id, reason, .. }) => {
+                println!("Client {client_id} disconnected: {reason}");
+                break;
+            }
+            NetworkEvent::Server(_) => {}
+            NetworkEvent::Message { client_id, message } => {
+                println!("Server received from {client_id}: {}", String::from_utf8_lossy(&message));
+                let reply = format!("echo: {}", String::from_utf8_lossy(&message));
+                commands
+                    .send(Command::SendMessage {
+                        client_id,
+                        message: reply.into(),
+                    })
+                    .unwrap();
+                // Leave some time for the reply to be delivered
+                disconnect_at = Some((client_id, Instant::now() + Duration::from_millis(500)));
+            }
+        }
+    }
+
+    println!("Client disconnect reason: {:?}", client.join().unwrap());
+    // Dropping the command sender stops the network thread
+    drop(commands);
+}
@@ -0,0 +1,130 @@
+//! Small request/response layer on top of a reliable channel.
+//! The client knows when each request was delivered to the server (acked message ids),
+//! separately from when the response arrives.
+//! Client and server run in the same process and exchange packets directly, dropping some of them.
+use std::{collections::HashMap, time::Duration};
+
+use renet::{Bytes, ClientId, ConnectionConfig, DefaultChannel, MessageId, RenetClient, RenetServer};
+
+const CHANNEL: DefaultChannel = DefaultChannel::ReliableOrdered;
+
+#[derive(Debug, PartialEq, Eq)]
+enum RequestState {
+    Sent,
+    Delivered,
+}
+
+struct RpcClient {
+    next_request_id: u64,
+    // Maps the message carrying the request to the request id
+    in_flight: HashMap<MessageId, u64>,
+    requests: HashMap<u64, RequestState>,
+}
+
+impl RpcClient {
+    fn new() -> Self {
+        Self {
+            next_request_id: 0,
+            in_flight: HashMap::new(),
+            requests: HashMap::new(),
+        }
+    }
+
+    fn call(&mut self, client: &mut RenetClient, method: &str) -> u64 {
+        let request_id = self.next_request_id;
+        self.next_request_id += 1;
+
+        let message_id = client
+            .send_message(CHANNEL, encode(request_id, method.as_bytes()))
+            .expect("reliable channels return message ids");
+        self.in_flight.insert(message_id, request_id);
+        self.requests.insert(request_id, RequestState::Sent);
+
+        request_id
+    }
+
+    fn update(&mut self, client: &mut RenetClient) {
+        for message_id in client.drain_acked_messages(CHANNEL) {
+            if let Some(request_id) = self.in_flight.remove(&message_id) {
+                println!("Request {request_id} delivered");
+                self.requests.insert(request_id, RequestState::Delivered);
+            }
+        }
+
+        while let Some(message) = client.receive_message(CHANNEL) {
+            let (request_id, response) = decode(&message);
+            let state = self.requests.remove(&request_id);
+            println!(
+                "Request {request_id} got response {:?} (state before response: {state:?})",
+                String::from_utf8_lossy(response)
+            );
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.requests.is_empty()
+    }
+}
+
+fn encode(request_id: u64, payload: &[u8]) -> Bytes {
+    let mut message = request_id.to_le_bytes().to_vec();
+    message.extend_from_slice(payload);
+    message.into()
+}
+
+fn decode(message: &[u8]) -> (u64, &[u8]) {
+    let (request_id, payload) = message.split_at(8);
+    (u64::from_le_bytes(request_id.try_into().unwrap()), payload)
+}
+
+fn main() {
+    let client_id = ClientId::from_raw(0);
+    let mut server = RenetServer::new(ConnectionConfig::default());
+    let mut client = RenetClient::new(ConnectionConfig::default());
+    server.add_connection(client_id);
+
+    let mut rpc = RpcClient::new();
+    for method in ["ping", "time", "echo hello"] {
+        rpc.call(&mut client, method);
+    }
+
+    let delta = Duration::from_millis(16);
+    let mut packet_count = 0;
+    for _ in 0..100 {
+        client.update(delta);
+        server.update(delta);
+
+        // Drop every third packet in both directions
+        for packet in client.get_packets_to_send() {
+            packet_count += 1;
+            if packet_count % 3 != 0 {
+                server.process_packet_from(&packet, client_id).unwrap();
+            }
+        }
+        for packet in server.get_packets_to_send(client_id).unwrap() {
+            packet_count += 1;
+            if packet_count % 3 != 0 {
+                client.process_packet(&packet);
+            }
+        }
+
+        // Server handles the requests
+        while let Some(message) = server.receive_message(client_id, CHANNEL) {
+            let (request_id, method) = decode(&message);
+            let response = match method {
+                b"ping" => b"pong".to_vec(),
+                b"time" => b"12:00".to_vec(),
+                _ => method.to_ascii_uppercase(),
+            };
+            server.send_message(client_id, CHANNEL, encode(request_id, &response));
+        }
+
+        rpc.update(&mut client);
+        if rpc.is_done() {
+            println!("All requests completed");
+            return;
+        }
+    }
+
+    panic!("Requests did not complete");
+}
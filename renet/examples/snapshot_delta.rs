@@ -0,0 +1,145 @@
+//! Delta compressed state replication using the acked snapshots as baselines.
+//! Each snapshot is sent as a XOR of the state against the latest snapshot acked by the client,
+//! or the whole state when there is no baseline yet.
+//! Client and server run in the same process and exchange packets directly, dropping some of them,
+//! the client also reconnects in the middle of the run.
+use std::{collections::BTreeMap, time::Duration};
+
+use renet::{
+    snapshot::{SnapshotId, SnapshotTracker},
+    Bytes, ClientId, ConnectionConfig, DefaultChannel, RenetClient, RenetServer,
+};
+
+const CHANNEL: DefaultChannel = DefaultChannel::ReliableOrdered;
+const STATE_SIZE: usize = 64;
+const HISTORY: usize = 32;
+const NO_BASELINE: u64 = u64::MAX;
+
+fn encode(snapshot_id: u64, state: &[u8], baseline: Option<(u64, &[u8])>) -> Bytes {
+    let mut message = snapshot_id.to_le_bytes().to_vec();
+    match baseline {
+        Some((baseline_id, baseline)) => {
+            message.extend_from_slice(&baseline_id.to_le_bytes());
+            message.extend(state.iter().zip(baseline).map(|(a, b)| a ^ b));
+        }
+        None => {
+            message.extend_from_slice(&NO_BASELINE.to_le_bytes());
+            message.extend_from_slice(state);
+        }
+    }
+    message.into()
+}
+
+#[derive(Default)]
+struct SnapshotReceiver {
+    // Received snapshots, the older ones are removed once a newer baseline is used.
+    // The channel is ordered so the baselines used by the server only move forward.
+    snapshots: BTreeMap<u64, Vec<u8>>,
+}
+
+impl SnapshotReceiver {
+    fn receive(&mut self, message: &[u8]) -> (u64, &[u8]) {
+        let snapshot_id = u64::from_le_bytes(message[..8].try_into().unwrap());
+        let baseline_id = u64::from_le_bytes(message[8..16].try_into().unwrap());
+        let payload = &message[16..];
+
+        let state = if baseline_id == NO_BASELINE {
+            payload.to_vec()
+        } else {
+            let baseline = self.snapshots.get(&baseline_id).expect("acked baselines are kept by the client");
+            let state = payload.iter().zip(baseline).map(|(a, b)| a ^ b).collect();
+            self.snapshots.retain(|&id, _| id >= baseline_id);
+            state
+        };
+
+        self.snapshots.insert(snapshot_id, state);
+        (snapshot_id, &self.snapshots[&snapshot_id])
+    }
+}
+
+fn main() {
+    let client_id = ClientId::from_raw(0);
+    let mut server = RenetServer::new(ConnectionConfig::default());
+    let mut client = RenetClient::new(ConnectionConfig::default());
+    server.add_connection(client_id);
+    client.set_connected();
+
+    let mut tracker = SnapshotTracker::new(CHANNEL, HISTORY);
+    let mut receiver = SnapshotReceiver::default();
+    let mut state = vec![0u8; STATE_SIZE];
+    let mut history: BTreeMap<u64, Vec<u8>> = BTreeMap::new();
+
+    let delta = Duration::from_millis(16);
+    let mut packet_count = 0;
+    let mut full_snapshots = 0;
+    let mut received_snapshots = 0;
+    for tick in 0..300u64 {
+        // Only a few bytes of the state change every tick
+        state[(tick as usize * 7) % STATE_SIZE] = state[(tick as usize * 7) % STATE_SIZE].wrapping_add(1);
+        history.insert(tick, state.clone());
+        while history.len() > HISTORY {
+            history.pop_first();
+        }
+
+        // The client is disconnected for a few ticks, the tracker forgets it
+        if tick == 150 {
+            println!("Client disconnected");
+            server.remove_connection(client_id);
+        }
+        if (150..160).contains(&tick) {
+            tracker.update(&mut server);
+            assert_eq!(tracker.latest_acked_snapshot(client_id), None);
+            continue;
+        }
+        if tick == 160 {
+            println!("Client reconnected");
+            server.add_connection(client_id);
+            client = RenetClient::new(ConnectionConfig::default());
+            client.set_connected();
+            receiver = SnapshotReceiver::default();
+        }
+
+        client.update(delta);
+        server.update(delta);
+
+        let baseline = tracker
+            .latest_acked_snapshot(client_id)
+            .and_then(|snapshot_id| Some((snapshot_id.raw(), history.get(&snapshot_id.raw())?.as_slice())));
+        if baseline.is_none() {
+            full_snapshots += 1;
+        }
+        let message_id = server
+            .send_message(client_id, CHANNEL, encode(tick, &state, baseline))
+            .expect("reliable channels return message ids");
+        tracker.register_sent(client_id, SnapshotId::from_raw(tick), message_id);
+
+        // Drop every fourth packet in both directions
+        for packet in client.get_packets_to_send() {
+            packet_count += 1;
+            if packet_count % 4 != 0 {
+                server.process_packet_from(&packet, client_id).unwrap();
+            }
+        }
+        for packet in server.get_packets_to_send(client_id).unwrap() {
+            packet_count += 1;
+            if packet_count % 4 != 0 {
+                client.process_packet(&packet);
+            }
+        }
+        tracker.update(&mut server);
+
+        while let Some(message) = client.receive_message(CHANNEL) {
+            let (snapshot_id, received) = receiver.receive(&message);
+            if let Some(expected) = history.get(&snapshot_id) {
+                assert_eq!(received, expected.as_slice(), "snapshot {snapshot_id} decoded incorrectly");
+            }
+            received_snapshots += 1;
+        }
+    }
+
+    println!(
+        "Received {received_snapshots} snapshots, {full_snapshots} sent without baseline, latest acked: {:?}",
+        tracker.latest_acked_snapshot(client_id)
+    );
+    assert!(tracker.latest_acked_snapshot(client_id).is_some());
+}
@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+use crate::packet::SLICE_SIZE;
+
+// Bytes that can be saved up while the channel has nothing to send, as time at the channel rate
+const MAX_BURST: Duration = Duration::from_millis(100);
+
+// Outgoing bandwidth cap of a channel, see `ChannelConfig::max_kbps`.
+//
+// The channel earns bytes at its rate as time passes, starting with none, and spends them on the messages it sends.
+// The saved up bytes are capped to a burst, but always allow at least a full slice so sliced messages still go through.
+#[derive(Debug)]
+pub(crate) struct ChannelBandwidth {
+    bytes_per_second: f64,
+    max_allowance: f64,
+    allowance: f64,
+    last_refill: Duration,
+}
+
+impl ChannelBandwidth {
+    pub fn new(max_kbps: u32) -> Self {
+        let bytes_per_second = max_kbps as f64 * 1000.0 / 8.0;
+        let max_allowance = (bytes_per_second * MAX_BURST.as_secs_f64()).max(SLICE_SIZE as f64);
+
+        Self {
+            bytes_per_second,
+            max_allowance,
+            allowance: 0.0,
+            last_refill: Duration::ZERO,
+        }
+    }
+
+    // Bytes the channel can send at the current time
+    pub fn available_bytes(&mut self, current_time: Duration) -> u64 {
+        let elapsed = current_time.saturating_sub(self.last_refill);
+        self.last_refill = current_time;
+        self.allowance = (self.allowance + elapsed.as_secs_f64() * self.bytes_per_second).min(self.max_allowance);
+
+        self.allowance as u64
+    }
+
+    pub fn consume(&mut self, bytes: u64) {
+        self.allowance = (self.allowance - bytes as f64).max(0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allowance_refills_at_the_channel_rate() {
+        // 80 kbps is 10000 bytes per second, the burst is 1000 bytes but a slice is 1200
+        let mut bandwidth = ChannelBandwidth::new(80);
+        assert_eq!(bandwidth.available_bytes(Duration::ZERO), 0);
+        assert_eq!(bandwidth.available_bytes(Duration::from_millis(50)), 500);
+
+        bandwidth.consume(200);
+        assert_eq!(bandwidth.available_bytes(Duration::from_millis(60)), 400);
+        assert_eq!(bandwidth.available_bytes(Duration::from_secs(10)), SLICE_SIZE as u64);
+
+        bandwidth.consume(SLICE_SIZE as u64);
+        assert_eq!(bandwidth.available_bytes(Duration::from_secs(10)), 0);
+    }
+}
@@ -0,0 +1,175 @@
+use crate::error::ChannelsConfigError;
+
+use super::{ChannelConfig, ChannelId, DefaultChannel, SendType};
+
+/// Builds a channels configuration from the default channels, see [`DefaultChannel::config`].
+///
+/// The channels can be tweaked or new ones added, the result is checked by [`ChannelsBuilder::build`]
+/// and goes in the `server_channels_config` or `client_channels_config` of the [`ConnectionConfig`][crate::ConnectionConfig].
+///
+/// ```
+/// # use std::time::Duration;
+/// # use renet::{ChannelsBuilder, ConnectionConfig, DefaultChannel, SendType};
+/// let channels = ChannelsBuilder::new()
+///     .channel(DefaultChannel::ReliableOrdered, |config| {
+///         config.send_type = SendType::ReliableOrdered {
+///             resend_time: Duration::from_millis(100),
+///         }
+///     })
+///     .build()
+///     .unwrap();
+///
+/// let config = ConnectionConfig {
+///     server_channels_config: channels.clone(),
+///     client_channels_config: channels,
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone)]
+pub struct ChannelsBuilder {
+    channels: Vec<ChannelConfig>,
+    memory_budget: Option<usize>,
+    // First channel tweaked without being in the channels
+    unknown_channel: Option<u8>,
+}
+
+impl Default for ChannelsBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChannelsBuilder {
+    /// Starts from the default channels.
+    pub fn new() -> Self {
+        Self {
+            channels: DefaultChannel::config(),
+            memory_budget: None,
+            unknown_channel: None,
+        }
+    }
+
+    /// Starts without any channel.
+    pub fn empty() -> Self {
+        Self {
+            channels: vec![],
+            ..Self::new()
+        }
+    }
+
+    /// Changes the configuration of a channel already added.
+    pub fn channel<I: Into<ChannelId>>(mut self, channel_id: I, tweak: impl FnOnce(&mut ChannelConfig)) -> Self {
+        let channel_id = channel_id.into();
+        match self.channels.iter_mut().find(|config| config.channel_id == channel_id) {
+            Some(config) => tweak(config),
+            None => {
+                self.unknown_channel.get_or_insert(channel_id.raw());
+            }
+        }
+        self
+    }
+
+    /// Adds a channel after the ones already added, it gets the bytes left by them each tick,
+    /// see [`ConnectionConfig::server_channels_config`][crate::ConnectionConfig::server_channels_config].
+    pub fn add_channel(mut self, config: ChannelConfig) -> Self {
+        self.channels.push(config);
+        self
+    }
+
+    /// Maximum of the `max_memory_usage_bytes` of all the channels together, the memory a connection can use
+    /// in this direction when every channel is full.
+    pub fn memory_budget(mut self, bytes: usize) -> Self {
+        self.memory_budget = Some(bytes);
+        self
+    }
+
+    /// Returns the channels, or the first problem found in them.
+    pub fn build(self) -> Result<Vec<ChannelConfig>, ChannelsConfigError> {
+        if let Some(channel_id) = self.unknown_channel {
+            return Err(ChannelsConfigError::UnknownChannel { channel_id });
+        }
+
+        check_channels_config(&self.channels)?;
+
+        if let Some(budget_bytes) = self.memory_budget {
+            let total_bytes: usize = self.channels.iter().map(|config| config.max_memory_usage_bytes).sum();
+            if total_bytes > budget_bytes {
+                return Err(ChannelsConfigError::OverMemoryBudget { total_bytes, budget_bytes });
+            }
+        }
+
+        Ok(self.channels)
+    }
+}
+
+// Checks the channels sent in one direction, the problems that would panic when creating the connection
+pub(crate) fn check_channels_config(channels: &[ChannelConfig]) -> Result<(), ChannelsConfigError> {
+    let reliable = |send_type: &SendType| !matches!(send_type, SendType::Unreliable | SendType::UnreliableSequenced);
+    for (index, config) in channels.iter().enumerate() {
+        let channel_id = config.channel_id.raw();
+        if channels[..index].iter().any(|other| other.channel_id == config.channel_id) {
+            return Err(ChannelsConfigError::DuplicateChannel { channel_id });
+        }
+        if matches!(config.send_type, SendType::ReliableSequenced { .. }) && config.group.is_some() {
+            return Err(ChannelsConfigError::GroupedSequencedChannel { channel_id });
+        }
+        if let Some(overflow) = config.overflow {
+            let overflow_config = channels.iter().find(|other| other.channel_id == overflow.channel_id);
+            let valid = reliable(&config.send_type)
+                && overflow_config.is_some_and(|overflow_config| {
+                    reliable(&overflow_config.send_type) && config.group.is_some() && overflow_config.group == config.group
+                });
+            if !valid {
+                return Err(ChannelsConfigError::InvalidOverflowRoute { channel_id });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::SendType;
+
+    #[test]
+    fn build_channels() {
+        let resend_time = Duration::from_millis(100);
+        let extra = ChannelConfig {
+            channel_id: 3.into(),
+            ..DefaultChannel::config().remove(0)
+        };
+        let channels = ChannelsBuilder::new()
+            .channel(DefaultChannel::ReliableOrdered, |config| {
+                config.send_type = SendType::ReliableOrdered { resend_time }
+            })
+            .add_channel(extra.clone())
+            .build()
+            .unwrap();
+
+        assert_eq!(channels.len(), 4);
+        let reliable = channels
+            .iter()
+            .find(|config| config.channel_id == DefaultChannel::ReliableOrdered.into());
+        assert!(matches!(reliable.unwrap().send_type, SendType::ReliableOrdered { resend_time: time } if time == resend_time));
+        assert_eq!(channels[3].channel_id, extra.channel_id);
+
+        let duplicate = ChannelsBuilder::new().add_channel(extra.clone()).add_channel(extra).build();
+        assert_eq!(duplicate.unwrap_err(), ChannelsConfigError::DuplicateChannel { channel_id: 3 });
+
+        let unknown = ChannelsBuilder::empty().channel(0, |config| config.priority = 2).build();
+        assert_eq!(unknown.unwrap_err(), ChannelsConfigError::UnknownChannel { channel_id: 0 });
+
+        let over_budget = ChannelsBuilder::new().memory_budget(1024).build();
+        assert_eq!(
+            over_budget.unwrap_err(),
+            ChannelsConfigError::OverMemoryBudget {
+                total_bytes: 15 * 1024 * 1024,
+                budget_bytes: 1024
+            }
+        );
+    }
+}
@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::error::ChannelError;
+
+use super::ChannelConfig;
+
+// First byte of every message of a compressed channel
+const UNCOMPRESSED: u8 = 0;
+const COMPRESSED: u8 = 1;
+
+/// Compression of the messages of a channel, see [`ChannelConfig::compression`].
+/// Each algorithm is behind its own feature, without them no compression is available.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompressionKind {
+    /// LZ4 block compression, requires the `lz4` feature.
+    /// Messages smaller than `min_message_bytes` are sent uncompressed.
+    #[cfg(feature = "lz4")]
+    Lz4 { min_message_bytes: usize },
+    /// LZ4 block compression with a pre-shared dictionary, requires the `lz4` feature.
+    /// The dictionary holds bytes common to the messages, like the field names of a structured format,
+    /// so even tiny messages shrink. Both ends must use the same dictionary, it's not sent but its hash is
+    /// compared with the channels configuration, a different dictionary is a channel config mismatch.
+    /// Messages smaller than `min_message_bytes` are sent uncompressed.
+    #[cfg(feature = "lz4")]
+    Lz4WithDictionary { min_message_bytes: usize, dictionary: Bytes },
+}
+
+impl CompressionKind {
+    // Identifies the algorithm in the channel descriptor, 0 is no compression
+    pub(crate) fn id(&self) -> u8 {
+        match *self {
+            #[cfg(feature = "lz4")]
+            CompressionKind::Lz4 { .. } => 1,
+            #[cfg(feature = "lz4")]
+            CompressionKind::Lz4WithDictionary { .. } => 2,
+        }
+    }
+
+    // Identifies the dictionary in the channel descriptor, 0 without one.
+    // FNV-1a, the hash must be the same for every build of renet.
+    #[cfg_attr(not(feature = "lz4"), allow(unreachable_code, unused_variables))]
+    pub(crate) fn dictionary_hash(&self) -> u64 {
+        let dictionary: &[u8] = match *self {
+            #[cfg(feature = "lz4")]
+            CompressionKind::Lz4 { .. } => return 0,
+            #[cfg(feature = "lz4")]
+            CompressionKind::Lz4WithDictionary { ref dictionary, .. } => dictionary,
+        };
+
+        dictionary.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+        })
+    }
+
+    // Returns None when the message is not worth compressing
+    #[cfg_attr(not(feature = "lz4"), allow(unused_variables))]
+    fn compress(&self, message: &[u8]) -> Option<Vec<u8>> {
+        match *self {
+            #[cfg(feature = "lz4")]
+            CompressionKind::Lz4 { min_message_bytes } => {
+                if message.len() < min_message_bytes {
+                    return None;
+                }
+                Some(lz4_flex::block::compress_prepend_size(message))
+            }
+            #[cfg(feature = "lz4")]
+            CompressionKind::Lz4WithDictionary {
+                min_message_bytes,
+                ref dictionary,
+            } => {
+                if message.len() < min_message_bytes {
+                    return None;
+                }
+                Some(lz4_flex::block::compress_prepend_size_with_dict(message, dictionary))
+            }
+        }
+    }
+
+    #[cfg_attr(not(feature = "lz4"), allow(unused_variables))]
+    fn decompress(&self, compressed: &[u8], max_size: usize) -> Result<Vec<u8>, ChannelError> {
+        #[cfg(feature = "lz4")]
+        let dictionary: &[u8] = match self {
+            CompressionKind::Lz4 { .. } => &[],
+            CompressionKind::Lz4WithDictionary { dictionary, .. } => dictionary,
+        };
+
+        match *self {
+            #[cfg(feature = "lz4")]
+            CompressionKind::Lz4 { .. } | CompressionKind::Lz4WithDictionary { .. } => {
+                let (size, compressed) =
+                    lz4_flex::block::uncompressed_size(compressed).map_err(|_| ChannelError::InvalidCompressedMessage)?;
+                if size > max_size {
+                    return Err(ChannelError::InvalidCompressedMessage);
+                }
+
+                let mut message = vec![0u8; size];
+                match lz4_flex::block::decompress_into_with_dict(compressed, &mut message, dictionary) {
+                    Ok(len) if len == size => Ok(message),
+                    _ => Err(ChannelError::InvalidCompressedMessage),
+                }
+            }
+        }
+    }
+}
+
+// Compression of the messages of the channels configured with it.
+//
+// Messages are compressed before being queued in their channel, so big messages need fewer slices.
+// Every message of a compressed channel is prefixed with a byte telling if the rest is compressed:
+// small messages, and messages that don't shrink, are sent as is.
+#[derive(Debug, Default)]
+pub(crate) struct ChannelCompression {
+    // Compression and memory limit of each compressed channel
+    send_channels: HashMap<u8, (CompressionKind, usize)>,
+    receive_channels: HashMap<u8, (CompressionKind, usize)>,
+}
+
+impl ChannelCompression {
+    pub fn new(send_channels_config: &[ChannelConfig], receive_channels_config: &[ChannelConfig]) -> Self {
+        let compressions = |configs: &[ChannelConfig]| {
+            configs
+                .iter()
+                .filter_map(|config| {
+                    Some((
+                        config.channel_id.raw(),
+                        (config.compression.clone()?, config.max_memory_usage_bytes),
+                    ))
+                })
+                .collect::<HashMap<u8, (CompressionKind, usize)>>()
+        };
+
+        Self {
+            send_channels: compressions(send_channels_config),
+            receive_channels: compressions(receive_channels_config),
+        }
+    }
+
+    // Adds a channel used in both directions
+    #[cfg_attr(not(feature = "lz4"), allow(unreachable_code))]
+    pub fn add_channel(&mut self, config: &ChannelConfig) {
+        if let Some(compression) = config.compression.clone() {
+            let channel_compression = (compression, config.max_memory_usage_bytes);
+            self.send_channels.insert(config.channel_id.raw(), channel_compression.clone());
+            self.receive_channels.insert(config.channel_id.raw(), channel_compression);
+        }
+    }
+
+    pub fn is_send_channel(&self, channel_id: u8) -> bool {
+        self.send_channels.contains_key(&channel_id)
+    }
+
+    // Messages of channels without compression are returned as is.
+    // Messages above the channel memory are left uncompressed, the channel refuses them as usual.
+    pub fn compress(&self, channel_id: u8, message: Bytes) -> Bytes {
+        let Some((compression, max_memory_usage_bytes)) = self.send_channels.get(&channel_id) else {
+            return message;
+        };
+
+        let compressed = match message.len() <= *max_memory_usage_bytes {
+            true => compression.compress(&message).filter(|compressed| compressed.len() < message.len()),
+            false => None,
+        };
+
+        let (header, payload) = match &compressed {
+            Some(compressed) => (COMPRESSED, compressed.as_slice()),
+            None => (UNCOMPRESSED, message.as_ref()),
+        };
+        let mut prefixed = BytesMut::with_capacity(1 + payload.len());
+        prefixed.put_u8(header);
+        prefixed.put_slice(payload);
+        prefixed.freeze()
+    }
+
+    // The uncompressed message can't be bigger than the memory of the receive channel.
+    pub fn decompress(&self, channel_id: u8, message: Bytes) -> Result<Bytes, ChannelError> {
+        let Some((compression, max_memory_usage_bytes)) = self.receive_channels.get(&channel_id) else {
+            return Ok(message);
+        };
+
+        match message.first() {
+            Some(&UNCOMPRESSED) => Ok(message.slice(1..)),
+            Some(&COMPRESSED) => Ok(compression.decompress(&message[1..], *max_memory_usage_bytes)?.into()),
+            _ => Err(ChannelError::InvalidCompressedMessage),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for CompressionKind {
+    #[cfg_attr(not(feature = "lz4"), allow(unused_variables, unused_imports))]
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStructVariant;
+
+        match *self {
+            #[cfg(feature = "lz4")]
+            CompressionKind::Lz4 { min_message_bytes } => {
+                let mut state = serializer.serialize_struct_variant("CompressionKind", 0, "Lz4", 1)?;
+                state.serialize_field("min_message_bytes", &min_message_bytes)?;
+                state.end()
+            }
+            #[cfg(feature = "lz4")]
+            CompressionKind::Lz4WithDictionary {
+                min_message_bytes,
+                ref dictionary,
+            } => {
+                let mut state = serializer.serialize_struct_variant("CompressionKind", 1, "Lz4WithDictionary", 2)?;
+                state.serialize_field("min_message_bytes", &min_message_bytes)?;
+                state.serialize_field("dictionary", dictionary.as_ref())?;
+                state.end()
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "lz4"))]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{ConnectionConfig, RenetClient, SendType};
+
+    fn configs() -> Vec<ChannelConfig> {
+        vec![ChannelConfig {
+            warning_threshold: None,
+            compression: Some(CompressionKind::Lz4 { min_message_bytes: 64 }),
+            ..ChannelConfig::new(
+                0,
+                4096,
+                SendType::ReliableOrdered {
+                    resend_time: Duration::from_millis(300),
+                },
+            )
+        }]
+    }
+
+    #[test]
+    fn lz4_round_trip() {
+        let sender = ChannelCompression::new(&configs(), &[]);
+        let receiver = ChannelCompression::new(&[], &configs());
+
+        let snapshot = Bytes::from(vec![7u8; 2048]);
+        let compressed = sender.compress(0, snapshot.clone());
+        assert_eq!(compressed[0], COMPRESSED);
+        assert!(compressed.len() < 100);
+        assert_eq!(receiver.decompress(0, compressed).unwrap(), snapshot);
+
+        // Small messages are sent as is
+        let small = sender.compress(0, Bytes::from("small"));
+        assert_eq!(small, Bytes::from("\0small"));
+        assert_eq!(receiver.decompress(0, small).unwrap(), "small");
+
+        // Channels without compression are not touched
+        assert_eq!(sender.compress(1, snapshot.clone()), snapshot);
+    }
+
+    #[test]
+    fn lz4_dictionary() {
+        let fields = Bytes::from_static(br#"{"player":,"position":[,],"velocity":[,],"health":}"#);
+        let with_dictionary = |compression: CompressionKind| {
+            vec![ChannelConfig {
+                compression: Some(compression),
+                ..configs().remove(0)
+            }]
+        };
+        let plain = with_dictionary(CompressionKind::Lz4 { min_message_bytes: 16 });
+        let dictionary = with_dictionary(CompressionKind::Lz4WithDictionary {
+            min_message_bytes: 16,
+            dictionary: fields,
+        });
+        let sender = ChannelCompression::new(&dictionary, &[]);
+        let receiver = ChannelCompression::new(&[], &dictionary);
+
+        // Tiny structured messages only shrink with the dictionary
+        let message = Bytes::from_static(br#"{"player":12,"position":[100,200],"velocity":[1,0],"health":90}"#);
+        assert_eq!(ChannelCompression::new(&plain, &[]).compress(0, message.clone())[0], UNCOMPRESSED);
+        let compressed = sender.compress(0, message.clone());
+        assert_eq!(compressed[0], COMPRESSED);
+        assert!(compressed.len() * 4 < message.len() * 3, "{} bytes", compressed.len());
+        assert_eq!(receiver.decompress(0, compressed).unwrap(), message);
+
+        // Messages below the threshold are still sent as is on the same channel
+        let small = sender.compress(0, Bytes::from("small"));
+        assert_eq!(small[0], UNCOMPRESSED);
+        assert_eq!(receiver.decompress(0, small).unwrap(), "small");
+    }
+
+    #[test]
+    fn lz4_dictionary_mismatch() {
+        let config = |dictionary: &'static [u8]| {
+            let channels = vec![ChannelConfig {
+                compression: Some(CompressionKind::Lz4WithDictionary {
+                    min_message_bytes: 16,
+                    dictionary: Bytes::from_static(dictionary),
+                }),
+                ..configs().remove(0)
+            }];
+            ConnectionConfig {
+                server_channels_config: channels.clone(),
+                client_channels_config: channels,
+                ..Default::default()
+            }
+        };
+
+        // The dictionaries are compared by their hash when the connection starts
+        for (dictionary, mismatch) in [(&b"position"[..], false), (&b"velocity"[..], true)] {
+            let mut server = RenetClient::new_from_server(config(b"position"));
+            let mut client = RenetClient::new(config(dictionary));
+            for packet in client.get_packets_to_send() {
+                server.process_packet(&packet);
+            }
+            assert_eq!(server.is_disconnected(), mismatch);
+        }
+    }
+
+    #[test]
+    fn lz4_invalid_messages() {
+        let receiver = ChannelCompression::new(&[], &configs());
+        assert_eq!(receiver.decompress(0, Bytes::new()), Err(ChannelError::InvalidCompressedMessage));
+        assert_eq!(
+            receiver.decompress(0, Bytes::from_static(&[COMPRESSED, 1, 2])),
+            Err(ChannelError::InvalidCompressedMessage)
+        );
+
+        // Decompressed size above the channel memory
+        let sender = ChannelCompression::new(
+            &[ChannelConfig {
+                max_memory_usage_bytes: 1024 * 1024,
+                ..configs().remove(0)
+            }],
+            &[],
+        );
+        let too_big = sender.compress(0, Bytes::from(vec![0u8; 8192]));
+        assert_eq!(receiver.decompress(0, too_big), Err(ChannelError::InvalidCompressedMessage));
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+// Delivered ids tracked above the contiguous ones, older ids are forgotten once exceeded
+const MAX_TRACKED_IDS: usize = 4096;
+
+// Verification is compiled out of release builds without the feature
+const ENABLED: bool = cfg!(any(debug_assertions, feature = "verify_delivery"));
+
+/// Message delivered twice by a reliable channel, see the `verify_delivery` feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct DuplicateDelivery {
+    pub message_id: u64,
+    // Sequence of the packet that completed the duplicated message
+    pub packet_sequence: Option<u64>,
+}
+
+// Verifies that a reliable receive channel delivers each message at most once.
+//
+// Keeps a compact window of the delivered ids: every id below `delivered_below` was delivered,
+// and the ids delivered above it are kept in a set. The set is capped, a duplicate of a forgotten id is missed.
+#[derive(Debug, Default)]
+pub(crate) struct DeliveryVerifier {
+    packet_sequence: u64,
+    // Sequence of the packet in which each pending message was completed
+    received_in: BTreeMap<u64, u64>,
+    delivered_below: u64,
+    delivered: BTreeSet<u64>,
+    duplicate: Option<DuplicateDelivery>,
+}
+
+impl DeliveryVerifier {
+    // Sequence of the packet being processed by the channel
+    pub fn set_packet_sequence(&mut self, packet_sequence: u64) {
+        self.packet_sequence = packet_sequence;
+    }
+
+    pub fn received(&mut self, message_id: u64) {
+        if !ENABLED {
+            return;
+        }
+
+        self.received_in.insert(message_id, self.packet_sequence);
+        // Messages superseded or dropped by the receive policy are never delivered
+        if self.received_in.len() > MAX_TRACKED_IDS {
+            self.received_in.pop_first();
+        }
+    }
+
+    pub fn delivered(&mut self, message_id: u64) {
+        if !ENABLED {
+            return;
+        }
+
+        let packet_sequence = self.received_in.remove(&message_id);
+        if message_id < self.delivered_below || !self.delivered.insert(message_id) {
+            self.duplicate.get_or_insert(DuplicateDelivery {
+                message_id,
+                packet_sequence,
+            });
+            return;
+        }
+
+        while self.delivered.first() == Some(&self.delivered_below) {
+            self.delivered.pop_first();
+            self.delivered_below += 1;
+        }
+        if self.delivered.len() > MAX_TRACKED_IDS {
+            self.delivered.pop_first();
+        }
+    }
+
+    pub fn take_duplicate(&mut self) -> Option<DuplicateDelivery> {
+        self.duplicate.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg_attr(not(any(debug_assertions, feature = "verify_delivery")), ignore)]
+    fn detect_duplicate_delivery() {
+        let mut verifier = DeliveryVerifier::default();
+        for message_id in [0, 2, 1, 5] {
+            verifier.set_packet_sequence(message_id + 10);
+            verifier.received(message_id);
+            verifier.delivered(message_id);
+        }
+        assert_eq!(verifier.take_duplicate(), None);
+        assert_eq!(verifier.delivered_below, 3);
+
+        verifier.set_packet_sequence(20);
+        verifier.received(1);
+        verifier.delivered(1);
+        assert_eq!(
+            verifier.take_duplicate(),
+            Some(DuplicateDelivery {
+                message_id: 1,
+                packet_sequence: Some(20)
+            })
+        );
+
+        verifier.delivered(5);
+        assert_eq!(
+            verifier.take_duplicate(),
+            Some(DuplicateDelivery {
+                message_id: 5,
+                packet_sequence: None
+            })
+        );
+        assert_eq!(verifier.take_duplicate(), None);
+    }
+}
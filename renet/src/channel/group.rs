@@ -0,0 +1,184 @@
+use std::collections::{HashMap, VecDeque};
+
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::error::ChannelError;
+
+use super::ChannelConfig;
+
+// Ordering of the messages across the channels of a group, see `ChannelConfig::group`.
+//
+// Every message of a grouped channel is prefixed with the number of reliable messages sent in the group before it,
+// reliable messages are also numbered this way. A received message is delivered once all the reliable messages of
+// the group numbered before it were delivered. Lost unreliable messages are not waited for.
+#[derive(Debug, Default)]
+pub(crate) struct ChannelGroups {
+    // Group of each grouped channel
+    send_channels: HashMap<u8, u8>,
+    receive_channels: HashMap<u8, u8>,
+    // Reliable messages sent in each group
+    reliable_sent: HashMap<u8, u64>,
+    // Reliable messages delivered to the application in each group
+    reliable_delivered: HashMap<u8, u64>,
+    // Received messages of each grouped channel with their number, waiting for earlier reliable messages of the group
+    withheld: HashMap<u8, VecDeque<(u64, Bytes)>>,
+}
+
+impl ChannelGroups {
+    pub fn new(send_channels_config: &[ChannelConfig], receive_channels_config: &[ChannelConfig]) -> Self {
+        let groups = |configs: &[ChannelConfig]| {
+            configs
+                .iter()
+                .filter_map(|config| Some((config.channel_id.raw(), config.group?)))
+                .collect::<HashMap<u8, u8>>()
+        };
+
+        Self {
+            send_channels: groups(send_channels_config),
+            receive_channels: groups(receive_channels_config),
+            ..Default::default()
+        }
+    }
+
+    // Adds a channel used in both directions
+    pub fn add_channel(&mut self, config: &ChannelConfig) {
+        if let Some(group) = config.group {
+            self.send_channels.insert(config.channel_id.raw(), group);
+            self.receive_channels.insert(config.channel_id.raw(), group);
+        }
+    }
+
+    pub fn is_send_grouped(&self, channel_id: u8) -> bool {
+        self.send_channels.contains_key(&channel_id)
+    }
+
+    pub fn is_receive_grouped(&self, channel_id: u8) -> bool {
+        self.receive_channels.contains_key(&channel_id)
+    }
+
+    // Receive channels of the group of the channel, empty for channels without group
+    pub fn receive_group_channels(&self, channel_id: u8) -> Vec<u8> {
+        let Some(&group) = self.receive_channels.get(&channel_id) else {
+            return vec![];
+        };
+        self.receive_channels
+            .iter()
+            .filter(|&(_, &other_group)| other_group == group)
+            .map(|(&other_channel_id, _)| other_channel_id)
+            .collect()
+    }
+
+    // Size of the header the next message of the channel will be prefixed with, 0 for channels without group
+    pub fn header_len(&self, channel_id: u8) -> usize {
+        match self.send_channels.get(&channel_id) {
+            Some(group) => octets::varint_len(self.reliable_sent.get(group).copied().unwrap_or(0)),
+            None => 0,
+        }
+    }
+
+    // Prefixes the message with its number in the group, messages of channels without group are returned as is.
+    pub fn stamp(&mut self, channel_id: u8, message: Bytes, reliable: bool) -> Bytes {
+        let Some(group) = self.send_channels.get(&channel_id) else {
+            return message;
+        };
+
+        let reliable_sent = self.reliable_sent.entry(*group).or_default();
+        let number = *reliable_sent;
+        if reliable {
+            *reliable_sent += 1;
+        }
+
+        let mut header = [0u8; 8];
+        let mut octets = octets::OctetsMut::with_slice(&mut header);
+        octets.put_varint(number).expect("varint fits in 8 bytes");
+        let header_len = octets.off();
+
+        let mut stamped = BytesMut::with_capacity(header_len + message.len());
+        stamped.put_slice(&header[..header_len]);
+        stamped.put_slice(&message);
+        stamped.freeze()
+    }
+
+    pub fn push_received(&mut self, channel_id: u8, message: Bytes) -> Result<(), ChannelError> {
+        let mut octets = octets::Octets::with_slice(&message);
+        let number = octets.get_varint().map_err(|_| ChannelError::InvalidGroupHeader)?;
+        let header_len = octets.off();
+
+        self.withheld
+            .entry(channel_id)
+            .or_default()
+            .push_back((number, message.slice(header_len..)));
+        Ok(())
+    }
+
+    // Returns the first withheld message of the channel that can be delivered.
+    pub fn pop_deliverable(&mut self, channel_id: u8, reliable: bool) -> Option<Bytes> {
+        let group = self.receive_channels[&channel_id];
+        let withheld = self.withheld.get_mut(&channel_id)?;
+        let reliable_delivered = self.reliable_delivered.entry(group).or_default();
+
+        // A reliable message is numbered after the reliable messages sent before it, so it's delivered in its turn.
+        // For ordered channels the first message always has the lowest number.
+        let index = withheld.iter().position(|&(number, _)| match reliable {
+            true => number == *reliable_delivered,
+            false => number <= *reliable_delivered,
+        })?;
+
+        let (_, message) = withheld.remove(index)?;
+        if reliable {
+            *reliable_delivered += 1;
+        }
+        Some(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::SendType;
+
+    fn configs() -> Vec<ChannelConfig> {
+        let channel = |channel_id: u8, send_type: SendType| ChannelConfig {
+            warning_threshold: None,
+            group: Some(0),
+            ..ChannelConfig::new(channel_id, 1024, send_type)
+        };
+        vec![
+            channel(0, SendType::Unreliable),
+            channel(
+                1,
+                SendType::ReliableOrdered {
+                    resend_time: Duration::from_millis(300),
+                },
+            ),
+        ]
+    }
+
+    #[test]
+    fn reliable_before_later_messages() {
+        let mut sender = ChannelGroups::new(&configs(), &[]);
+        let mut receiver = ChannelGroups::new(&[], &configs());
+
+        let spawn = sender.stamp(1, Bytes::from("spawn"), true);
+        let _lost_update = sender.stamp(0, Bytes::from("lost"), false);
+        let update = sender.stamp(0, Bytes::from("update"), false);
+        let despawn = sender.stamp(1, Bytes::from("despawn"), true);
+
+        // The update waits for the spawn, the lost update is not waited for
+        receiver.push_received(0, update).unwrap();
+        assert_eq!(receiver.pop_deliverable(0, false), None);
+
+        receiver.push_received(1, despawn).unwrap();
+        assert_eq!(receiver.pop_deliverable(1, true), None);
+
+        receiver.push_received(1, spawn).unwrap();
+        assert_eq!(receiver.pop_deliverable(1, true).unwrap(), "spawn");
+        assert_eq!(receiver.pop_deliverable(0, false).unwrap(), "update");
+        assert_eq!(receiver.pop_deliverable(1, true).unwrap(), "despawn");
+        assert_eq!(receiver.pop_deliverable(1, true), None);
+
+        assert_eq!(receiver.push_received(0, Bytes::new()), Err(ChannelError::InvalidGroupHeader));
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::{BTreeMap, HashSet};
+
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::error::ChannelError;
+
+use super::ChannelConfig;
+
+/// Messages and bytes of a message kind in a channel with [`ChannelConfig::message_kinds`],
+/// see [`RenetClient::message_kind_stats`][crate::RenetClient::message_kind_stats].
+/// Bytes are the size of the messages given to `send_message_kind` and returned by `receive_message_with_kind`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MessageKindStats {
+    /// Messages queued in the channel, messages dropped because the channel was full are not counted.
+    pub messages_sent: u64,
+    pub bytes_sent: u64,
+    /// Messages received by the application.
+    pub messages_received: u64,
+    pub bytes_received: u64,
+}
+
+// Message kinds of the channels configured with them, and the statistics of each kind.
+//
+// The kind is prefixed to the message before it is compressed, channels without kinds are left as is.
+#[derive(Debug, Default)]
+pub(crate) struct MessageKinds {
+    send_channels: HashSet<u8>,
+    receive_channels: HashSet<u8>,
+    // Statistics by channel and kind, ordered to list the kinds of a channel
+    stats: BTreeMap<(u8, u8), MessageKindStats>,
+}
+
+impl MessageKinds {
+    pub fn new(send_channels_config: &[ChannelConfig], receive_channels_config: &[ChannelConfig]) -> Self {
+        let kind_channels = |configs: &[ChannelConfig]| {
+            configs
+                .iter()
+                .filter(|config| config.message_kinds)
+                .map(|config| config.channel_id.raw())
+                .collect::<HashSet<u8>>()
+        };
+
+        Self {
+            send_channels: kind_channels(send_channels_config),
+            receive_channels: kind_channels(receive_channels_config),
+            stats: BTreeMap::new(),
+        }
+    }
+
+    // Adds a channel used in both directions
+    pub fn add_channel(&mut self, config: &ChannelConfig) {
+        if config.message_kinds {
+            self.send_channels.insert(config.channel_id.raw());
+            self.receive_channels.insert(config.channel_id.raw());
+        }
+    }
+
+    pub fn is_send_channel(&self, channel_id: u8) -> bool {
+        self.send_channels.contains(&channel_id)
+    }
+
+    // Messages of channels without kinds are returned as is
+    pub fn prefix(&self, channel_id: u8, kind: u8, message: Bytes) -> Bytes {
+        if !self.send_channels.contains(&channel_id) {
+            return message;
+        }
+
+        let mut prefixed = BytesMut::with_capacity(1 + message.len());
+        prefixed.put_u8(kind);
+        prefixed.put_slice(&message);
+        prefixed.freeze()
+    }
+
+    // Messages of channels without kinds are returned as is with the kind 0
+    pub fn split(&self, channel_id: u8, message: Bytes) -> Result<(Bytes, u8), ChannelError> {
+        if !self.receive_channels.contains(&channel_id) {
+            return Ok((message, 0));
+        }
+
+        let kind = *message.first().ok_or(ChannelError::InvalidMessageKind)?;
+        Ok((message.slice(1..), kind))
+    }
+
+    pub fn sent(&mut self, channel_id: u8, kind: u8, bytes: usize) {
+        if self.send_channels.contains(&channel_id) {
+            let stats = self.stats.entry((channel_id, kind)).or_default();
+            stats.messages_sent += 1;
+            stats.bytes_sent += bytes as u64;
+        }
+    }
+
+    pub fn received(&mut self, channel_id: u8, kind: u8, bytes: usize) {
+        if self.receive_channels.contains(&channel_id) {
+            let stats = self.stats.entry((channel_id, kind)).or_default();
+            stats.messages_received += 1;
+            stats.bytes_received += bytes as u64;
+        }
+    }
+
+    // Returns the kinds of the channel that sent or received messages, ordered by kind
+    pub fn stats(&self, channel_id: u8) -> Vec<(u8, MessageKindStats)> {
+        self.stats
+            .range((channel_id, 0)..=(channel_id, u8::MAX))
+            .map(|(&(_, kind), stats)| (kind, *stats))
+            .collect()
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DefaultChannel;
+
+    #[test]
+    fn prefix_and_split_kind() {
+        let mut config = DefaultChannel::config()[0].clone();
+        config.message_kinds = true;
+        let mut kinds = MessageKinds::new(&[config.clone()], &[config]);
+
+        let message = kinds.prefix(0, 7, Bytes::from_static(b"hello"));
+        assert_eq!(message.len(), 6);
+        let (message, kind) = kinds.split(0, message).unwrap();
+        assert_eq!(kind, 7);
+        assert_eq!(&message[..], b"hello");
+        assert_eq!(kinds.split(0, Bytes::new()), Err(ChannelError::InvalidMessageKind));
+
+        // Channels without kinds pay nothing
+        let message = kinds.prefix(1, 7, Bytes::from_static(b"hello"));
+        assert_eq!(kinds.split(1, message), Ok((Bytes::from_static(b"hello"), 0)));
+
+        kinds.sent(0, 7, 5);
+        kinds.sent(0, 7, 3);
+        kinds.received(0, 2, 4);
+        kinds.sent(1, 7, 5);
+        let expected = vec![
+            (
+                2,
+                MessageKindStats {
+                    messages_received: 1,
+                    bytes_received: 4,
+                    ..Default::default()
+                },
+            ),
+            (
+                7,
+                MessageKindStats {
+                    messages_sent: 2,
+                    bytes_sent: 8,
+                    ..Default::default()
+                },
+            ),
+        ];
+        assert_eq!(kinds.stats(0), expected);
+        assert!(kinds.stats(1).is_empty());
+
+        kinds.reset_stats();
+        assert!(kinds.stats(0).is_empty());
+    }
+}
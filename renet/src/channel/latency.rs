@@ -0,0 +1,67 @@
+use std::time::Duration;
+
+use super::MessageId;
+
+/// Aggregated latencies of the messages of a channel.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LatencyStats {
+    /// Number of messages measured.
+    pub samples: u64,
+    /// Sum of the latencies of the measured messages.
+    pub total: Duration,
+    pub max: Duration,
+}
+
+impl LatencyStats {
+    /// Returns the average latency, zero when there are no samples.
+    pub fn average(&self) -> Duration {
+        if self.samples == 0 {
+            return Duration::ZERO;
+        }
+
+        Duration::from_secs_f64(self.total.as_secs_f64() / self.samples as f64)
+    }
+
+    pub(crate) fn record(&mut self, latency: Duration) {
+        self.samples += 1;
+        self.total += latency;
+        self.max = self.max.max(latency);
+    }
+}
+
+/// Latency statistics of a send channel, see [`RenetClient::channel_latency`][crate::RenetClient::channel_latency].
+/// Latencies are measured in update ticks: messages are timestamped with the time of the last update.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChannelLatency {
+    /// Time from a message being queued to its first transmission.
+    pub queue: LatencyStats,
+    /// Time from a message being queued to its ack, only measured in reliable channels.
+    pub delivery: LatencyStats,
+}
+
+/// Latencies of a single reliable message, see [`RenetClient::drain_message_latency_records`][crate::RenetClient::drain_message_latency_records].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageLatencyRecord {
+    pub message_id: MessageId,
+    /// Time from the message being queued to its first transmission.
+    pub queue_latency: Duration,
+    /// Time from the message being queued to its ack.
+    pub delivery_latency: Duration,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latency_stats() {
+        let mut stats = LatencyStats::default();
+        assert_eq!(stats.average(), Duration::ZERO);
+
+        stats.record(Duration::from_millis(10));
+        stats.record(Duration::from_millis(30));
+        assert_eq!(stats.samples, 2);
+        assert_eq!(stats.average(), Duration::from_millis(20));
+        assert_eq!(stats.max, Duration::from_millis(30));
+    }
+}
@@ -1,16 +1,124 @@
+pub(crate) mod bandwidth;
+pub(crate) mod builder;
+pub(crate) mod compression;
+pub(crate) mod delivery;
+pub(crate) mod group;
+pub(crate) mod kind;
+pub(crate) mod latency;
 pub(crate) mod reliable;
 pub(crate) mod slice_constructor;
+pub(crate) mod trace;
 pub(crate) mod unreliable;
+pub(crate) mod warning;
 
 use std::time::Duration;
 
+use crate::packet::ChannelDescriptor;
+
+pub use builder::ChannelsBuilder;
+pub use compression::CompressionKind;
+pub use kind::MessageKindStats;
+pub use latency::{ChannelLatency, LatencyStats, MessageLatencyRecord};
 pub(crate) use slice_constructor::SliceConstructor;
+pub use trace::TRACE_ID_BYTES;
+pub use warning::{ChannelWarning, ChannelWarningKind};
+
+/// Identifier of a message sent in a reliable channel, or in an unreliable channel with [`ChannelConfig::delivery_feedback`].
+/// Ids are sequential and unique within the channel.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Ord, PartialOrd)]
+pub struct MessageId(u64);
+
+impl MessageId {
+    /// Creates a [`MessageId`] from a raw 64 bit value.
+    pub const fn from_raw(value: u64) -> Self {
+        Self(value)
+    }
+
+    /// Returns the raw 64 bit value of the [`MessageId`]
+    pub fn raw(&self) -> u64 {
+        self.0
+    }
+}
+
+impl std::fmt::Display for MessageId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Outcome of a message sent in an unreliable channel with [`ChannelConfig::delivery_feedback`],
+/// see [`RenetClient::drain_message_deliveries`][crate::RenetClient::drain_message_deliveries].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageDelivery {
+    /// The packets carrying the message were acked by the remote.
+    Delivered(MessageId),
+    /// The message was dropped before being sent, or a packet carrying it was not acked in time.
+    Lost(MessageId),
+}
+
+/// Identifier of a channel, unique within the channels of each side of a connection.
+/// Methods taking a channel accept anything convertible to it: a raw `u8`, [`DefaultChannel`],
+/// or an application enum implementing `From<MyChannel> for ChannelId`.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Ord, PartialOrd)]
+pub struct ChannelId(u8);
+
+impl ChannelId {
+    /// Creates a [`ChannelId`] from a raw 8 bit value.
+    pub const fn from_raw(value: u8) -> Self {
+        Self(value)
+    }
+
+    /// Returns the raw 8 bit value of the [`ChannelId`]
+    pub fn raw(&self) -> u8 {
+        self.0
+    }
+}
+
+impl From<u8> for ChannelId {
+    fn from(value: u8) -> Self {
+        Self(value)
+    }
+}
+
+impl std::fmt::Display for ChannelId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for ChannelId {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+/// A point in the send queue of a channel, see [`RenetClient::insert_fence`][crate::RenetClient::insert_fence].
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct FenceId {
+    pub(crate) channel_id: u8,
+    // Id of the next message sent after the fence
+    pub(crate) message_id: u64,
+}
+
+impl FenceId {
+    /// Returns the channel the fence was inserted in.
+    pub fn channel_id(&self) -> u8 {
+        self.channel_id
+    }
+}
 
 /// Delivery garantee of a channel
 #[derive(Debug, Clone)]
 pub enum SendType {
     // Messages can be lost or received out of order.
     Unreliable,
+    /// Messages can be lost, a message older than the last one read is dropped instead of being received out of order.
+    /// Useful for state snapshots where only the newest one matters. Every message carries a 2 bytes sequence number.
+    UnreliableSequenced,
     /// Messages are guaranteed to be received and in the same order they were sent.
     ReliableOrdered {
         resend_time: Duration,
@@ -19,6 +127,119 @@ pub enum SendType {
     ReliableUnordered {
         resend_time: Duration,
     },
+    /// Only the newest message is guaranteed to be received: sending a message stops the resends of the older ones,
+    /// and a message older than the last one received is dropped. Useful for state where only the latest value matters.
+    /// Superseded messages are never acked, and the channel cannot be in a group.
+    ReliableSequenced {
+        resend_time: Duration,
+    },
+}
+
+/// What happens to the received messages of a channel that the application does not read.
+/// Dropped messages are counted in [`NetworkCounters::unread_messages_dropped`][crate::NetworkCounters::unread_messages_dropped],
+/// they were received and dropped locally, they are not lost on the network.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReceivePolicy {
+    /// Messages are kept until read, the channel memory limit applies:
+    /// unreliable channels drop new messages and reliable channels disconnect the client when it is reached.
+    #[default]
+    Unbounded,
+    /// Keeps at most the given number of unread messages, the oldest ones are dropped.
+    DropOldest { max_messages: usize },
+    /// Drops the messages not read after the given age, and emits a
+    /// [`ChannelWarningKind::UnreadMessagesDropped`] warning the first time.
+    WarnAndDrop { max_age: Duration },
+}
+
+/// What `send_message` does with a message sent over a full reliable channel, see [`ChannelConfig::queue_policy`].
+/// A reliable channel is full when the message doesn't fit in its `max_memory_usage_bytes`,
+/// or when it holds [`ChannelConfig::max_queued_messages`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QueuePolicy {
+    /// Disconnects with [`ChannelError::ReliableChannelMaxMemoryReached`][crate::ChannelError::ReliableChannelMaxMemoryReached],
+    /// the remote would miss the message otherwise.
+    #[default]
+    ErrorAndDisconnect,
+    /// Refuses the message and keeps the connection up: `send_message` returns `None` instead of the message id,
+    /// and the message is counted as [`DropReason::ChannelFull`][crate::DropReason::ChannelFull].
+    /// Use `try_send_message` to get the error instead.
+    RejectSend,
+}
+
+/// Which messages a full unreliable channel drops, see [`ChannelConfig::full_queue_policy`].
+/// An unreliable channel is full when the message doesn't fit in its `max_memory_usage_bytes`,
+/// or when it holds [`ChannelConfig::max_queued_messages`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FullQueuePolicy {
+    /// Drops the message sent and keeps the queued ones, for messages that must arrive in order like inputs.
+    #[default]
+    DropNewest,
+    /// Drops the oldest queued messages until the message sent fits, for messages superseding the older ones
+    /// like state snapshots. A message that doesn't fit in the channel even when empty is dropped instead.
+    DropOldest,
+}
+
+/// Route of the messages of a reliable channel above a size, see [`ChannelConfig::overflow`].
+///
+/// The overflow channel must be a reliable channel in the same group as the channel, so the messages are still
+/// delivered in the order they were sent: the later messages of the channel are held by the remote until the
+/// overflowed message is read from the overflow channel. The ids returned by `send_message` for overflowed
+/// messages are ids of the overflow channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OverflowRoute {
+    pub channel_id: ChannelId,
+    /// Messages bigger than this are sent over the overflow channel.
+    pub threshold_bytes: usize,
+}
+
+/// Room left in a send channel, see [`RenetClient::channel_capacity`][crate::RenetClient::channel_capacity].
+///
+/// Reliable channels hold their messages until acked, so the messages sent and waiting for their ack
+/// take memory from the channel as much as the ones waiting to be sent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChannelCapacity {
+    /// Bytes left in the channel before reaching its `max_memory_usage_bytes`.
+    pub bytes_free: usize,
+    /// Bytes of the messages waiting to be sent.
+    pub bytes_queued: usize,
+    /// Bytes of the reliable messages sent and waiting for their ack, freed once acked. Always 0 for unreliable channels.
+    pub bytes_unacked: usize,
+    /// Size of the biggest message the channel accepts now, within the bytes free and the `max_message_size` of the channel.
+    /// The bytes the channel adds to each message, like the trace id or the message kind, are already subtracted.
+    pub max_message_bytes: usize,
+}
+
+/// Counters of a channel since the connection was created or the last call to
+/// [`RenetClient::reset_channel_stats`][crate::RenetClient::reset_channel_stats].
+///
+/// Bytes are counted as they are put in the packets or taken from them, with the headers added by the
+/// channel features, so they show how much of the bandwidth the channel uses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChannelStats {
+    /// Messages queued in the channel, messages dropped or refused when sent are not counted.
+    pub messages_sent: u64,
+    /// Bytes of the channel messages in the packets sent, resends included.
+    pub bytes_sent: u64,
+    /// Messages received by the application.
+    pub messages_received: u64,
+    /// Bytes of the channel messages in the packets received, duplicates included.
+    pub bytes_received: u64,
+    /// Messages and slices of messages resent by a reliable channel.
+    pub resends: u64,
+    /// Messages dropped by the channel, when sent for the reasons of [`DropReason`][crate::DropReason],
+    /// or when received because the channel was full or by its [`ReceivePolicy`].
+    pub dropped: u64,
+}
+
+impl ReceivePolicy {
+    // Returns true if the next message to read, received at the given time, should be dropped
+    pub(crate) fn expires(&self, unread_messages: usize, received_at: Duration, current_time: Duration) -> bool {
+        match *self {
+            ReceivePolicy::Unbounded => false,
+            ReceivePolicy::DropOldest { max_messages } => unread_messages > max_messages,
+            ReceivePolicy::WarnAndDrop { max_age } => current_time.saturating_sub(received_at) > max_age,
+        }
+    }
 }
 
 /// Configuration of a channel for a server or client
@@ -27,17 +248,348 @@ pub enum SendType {
 pub struct ChannelConfig {
     /// Channel identifier, must be unique within its own list,
     /// but it can be repeated between the server and client lists.
-    pub channel_id: u8,
+    pub channel_id: ChannelId,
     /// Maximum number of bytes that the channel may hold without acknowledgement of messages before becoming full.
     /// Unreliable channels will drop new messages when this value is reached.
     /// Reliable channels will cause a disconnect when this value is reached.
     pub max_memory_usage_bytes: usize,
     /// Delivery garantee of the channel.
     pub send_type: SendType,
+    /// Fraction of the channel limits at which a [`ChannelWarning`] is emitted, None disables the warnings.
+    /// The default channels use 0.75.
+    pub warning_threshold: Option<f64>,
+    /// Channels with the same group keep the relative order of their messages:
+    /// a received message is only returned after the reliable messages sent before it in the group,
+    /// in any of its channels, were returned. Lost unreliable messages are not waited for.
+    /// Every message of a grouped channel carries a group header of 1 to 8 bytes.
+    ///
+    /// A message is delivered once the earlier reliable messages were read with `receive_message`,
+    /// so all the channels of a group must be read. A reliable message that is not read,
+    /// or that is slow to arrive, stalls the delivery of all the later messages of the group,
+    /// and they are kept in memory meanwhile.
+    /// The group must be the same on both ends of the channel.
+    /// Default channels have no group.
+    pub group: Option<u8>,
+    /// Policy for the received messages that are not read, see [`ReceivePolicy`].
+    /// Only applies to the receiving side, it doesn't need to match the remote.
+    /// Grouped channels keep their messages until read, dropping them would stall the group.
+    /// Default channels use `ReceivePolicy::Unbounded`.
+    pub receive_policy: ReceivePolicy,
+    /// Compresses the messages before they are sliced, and decompresses them in `receive_message`.
+    /// Every message carries 1 extra byte, and compressed messages are decompressed up to `max_memory_usage_bytes`.
+    /// The compression must be the same on both ends of the channel, or the connection is terminated
+    /// with a channel config mismatch.
+    /// Default channels have no compression.
+    pub compression: Option<CompressionKind>,
+    /// Weight of the channel when packets are assembled: each tick, every channel can first use its share of
+    /// `available_bytes_per_tick`, proportional to its priority. The bytes left are then given to the channels in order.
+    /// Channels with priority 0 are only given the bytes left once the other channels sent everything they could.
+    /// A large sliced message can't hold back the messages of the other channels, they still get their share every tick.
+    /// Default channels use 1.
+    pub priority: u8,
+    /// Every message carries a trace id of [`TRACE_ID_BYTES`], set with `send_message_traced`
+    /// and returned by `receive_message_traced`, to correlate the message across the network.
+    /// With the `tracing` feature, events are recorded when the message is packed, resent and delivered.
+    /// The tracing context must be the same on both ends of the channel.
+    /// Default channels have no tracing context.
+    pub tracing_context: bool,
+    /// Maximum outgoing rate of the channel in kilobits per second, counting the bytes of its messages.
+    /// Once the rate is reached the channel sends nothing more in the tick, its messages stay queued.
+    /// Only applies to the sending side, it doesn't need to match the remote.
+    /// Default channels have no limit.
+    pub max_kbps: Option<u32>,
+    /// Every message carries a 1 byte kind, set with `send_message_kind` and returned by `receive_message_with_kind`,
+    /// to multiplex several message types in the channel. The messages and bytes of each kind are counted,
+    /// see `message_kind_stats`. The message kinds must be the same on both ends of the channel.
+    /// Default channels have no message kinds.
+    pub message_kinds: bool,
+    /// Maximum size of a message in the channel, checked on both sides.
+    /// When receiving, a remote sending a bigger message is disconnected
+    /// with [`ChannelError::ReceivedMessageAboveLimit`][crate::ChannelError::ReceivedMessageAboveLimit].
+    /// Sliced messages are refused on their first slice, from the number of slices they announce,
+    /// before any memory is allocated to reassemble them.
+    /// When sending, a bigger message is refused with [`SendError::MessageTooLarge`][crate::SendError::MessageTooLarge],
+    /// the size includes the bytes the channel adds to the message, and [`ChannelCapacity::max_message_bytes`] stays within it.
+    /// The sender and the remote should use the same limit, a remote with a smaller one disconnects the sender.
+    /// Default channels have no limit other than `max_memory_usage_bytes`.
+    pub max_message_size: Option<usize>,
+    /// Sends the messages above a size over another channel, for example a reliable channel
+    /// with more memory for the occasional big message, see [`OverflowRoute`].
+    /// Only applies to the sending side, the remote receives the messages in the overflow channel.
+    /// Default channels have no overflow route.
+    pub overflow: Option<OverflowRoute>,
+    /// Backs off the resends of the reliable channels: each resend of a message doubles the time before the next one,
+    /// from the `resend_time` of the [`SendType`] up to this one, so a congested link is not flooded with resends.
+    /// The backoff of a message is reset when any of its slices is acked.
+    /// Only applies to the sending side, it doesn't need to match the remote.
+    /// Default channels resend at a fixed `resend_time`.
+    pub max_resend_time: Option<Duration>,
+    /// Drops the messages of the unreliable channels still queued this long after `send_message`,
+    /// instead of sending them late when the channel is held back by its bandwidth or paused.
+    /// Measured with the time of the connection updates, the drops are counted as [`DropReason::Expired`][crate::DropReason::Expired],
+    /// see [`RenetClient::expired_messages`][crate::RenetClient::expired_messages].
+    /// Only applies to the sending side, ignored by the reliable channels.
+    /// Default channels never expire messages.
+    pub message_ttl: Option<Duration>,
+    /// Reports the delivery of the messages of the unreliable channels: `send_message` returns a [`MessageId`] for them,
+    /// then [`RenetClient::drain_message_deliveries`][crate::RenetClient::drain_message_deliveries] tells whether
+    /// the packet carrying the message was acked, see [`MessageDelivery`]. A packet not acked within 3 seconds is lost,
+    /// sliced messages are delivered once all their slices are acked.
+    /// Only applies to the sending side, ignored by the reliable channels, see `drain_acked_messages` for them.
+    /// Default channels have no delivery feedback.
+    pub delivery_feedback: bool,
+    /// Holds back the new messages of the channel until this long after the first of them was queued, or until
+    /// they fill a packet, so small messages sent over several updates share a packet instead of sending one each.
+    /// The resends of the reliable channels are not held back.
+    /// Only applies to the sending side, it doesn't need to match the remote.
+    /// Default channels send their messages in the next `get_packets_to_send`.
+    pub coalesce_delay: Option<Duration>,
+    /// Maximum number of messages held by the channel: waiting to be sent and, for reliable channels, waiting for their ack.
+    /// A channel at this limit is full, like one at its `max_memory_usage_bytes`, see `queue_policy`.
+    /// Ignored by the reliable sequenced channels, sending a message drops the older ones.
+    /// Only applies to the sending side, it doesn't need to match the remote.
+    /// Default channels only limit their bytes.
+    pub max_queued_messages: Option<usize>,
+    /// What happens when a message is sent over a full reliable channel, see [`QueuePolicy`].
+    /// Full unreliable channels always drop the message.
+    /// Only applies to the sending side, it doesn't need to match the remote.
+    /// Default channels disconnect.
+    pub queue_policy: QueuePolicy,
+    /// Which messages a full unreliable channel drops when a message is sent, see [`FullQueuePolicy`].
+    /// The drops are counted as [`DropReason::ChannelFull`][crate::DropReason::ChannelFull], and per channel
+    /// in [`ChannelStats::dropped`].
+    /// Only applies to the sending side, ignored by the reliable channels, see `queue_policy` for them.
+    /// Default channels drop the message sent.
+    pub full_queue_policy: FullQueuePolicy,
+    /// Maximum number of packets of a reliable channel sent and not acked yet: once reached, the channel stops
+    /// packing new messages and only resends the unacked ones until acks come back, so a lossy link is not flooded
+    /// with new data while older data is missing. A packet not acked within the `resend_time` stops counting.
+    /// Only applies to the sending side, ignored by the unreliable channels.
+    /// Default channels have no limit.
+    pub max_packets_in_flight: Option<usize>,
+    /// Maximum number of slices of a message in an unreliable channel, messages above 1200 bytes are sent in slices
+    /// of 1200 bytes and reassembled by the remote. The sending side refuses bigger messages like above `max_message_size`,
+    /// the receiving side ignores the slices of the messages announcing more, before allocating memory for them.
+    /// Ignored by the reliable channels, see `max_message_size` for them.
+    /// Default channels have no limit other than `max_memory_usage_bytes`.
+    pub max_fragments: Option<usize>,
+    /// Time an incomplete sliced message of an unreliable channel waits for its missing slices: the message is dropped
+    /// and its memory reclaimed when no slice of it was received for this long, a partial message is never received.
+    /// Only applies to the receiving side, ignored by the reliable channels.
+    /// Default channels wait 3 seconds.
+    pub reassembly_timeout: Option<Duration>,
+    /// Drops the repeats of the messages of an unreliable channel, delivered twice when the network duplicates a packet.
+    /// Every message is sent with a 2 bytes sequence number, the receiver remembers the sequences of this many
+    /// messages last read and drops a message with one of them.
+    /// Must be set on both ends of the channel, or the connection is terminated with a channel config mismatch.
+    /// The window size only applies to the receiving side, it doesn't need to match the remote.
+    /// Ignored by the reliable and unreliable sequenced channels, they already never deliver a message twice.
+    /// Default channels are not deduplicated.
+    pub deduplicate: Option<u16>,
+    /// Assembles the packets of the channel first every tick, ahead of the other channels, for time-critical data
+    /// like inputs. Only the order of the packets changes: the channel still uses its share of the bytes by `priority`,
+    /// and is the first given the bytes left. A large sliced message in an urgent channel also sends its slices first.
+    /// Channels with priority 0 still come after the others, the urgent ones first among them.
+    /// Only applies to the sending side, it doesn't need to match the remote.
+    /// Default channels are not urgent.
+    pub urgent: bool,
+}
+
+impl ChannelConfig {
+    /// Creates a channel with the options of the default channels, documented on each field.
+    /// The other options are changed on the returned config, or with the struct update syntax:
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use renet::{ChannelConfig, SendType};
+    /// let channel = ChannelConfig {
+    ///     priority: 2,
+    ///     ..ChannelConfig::new(3, 1024 * 1024, SendType::Unreliable)
+    /// };
+    /// ```
+    pub fn new<I: Into<ChannelId>>(channel_id: I, max_memory_usage_bytes: usize, send_type: SendType) -> Self {
+        Self {
+            channel_id: channel_id.into(),
+            max_memory_usage_bytes,
+            send_type,
+            warning_threshold: Some(0.75),
+            group: None,
+            receive_policy: ReceivePolicy::Unbounded,
+            compression: None,
+            priority: 1,
+            tracing_context: false,
+            max_kbps: None,
+            message_kinds: false,
+            max_message_size: None,
+            overflow: None,
+            max_resend_time: None,
+            message_ttl: None,
+            delivery_feedback: false,
+            coalesce_delay: None,
+            max_queued_messages: None,
+            queue_policy: QueuePolicy::ErrorAndDisconnect,
+            full_queue_policy: FullQueuePolicy::DropNewest,
+            max_packets_in_flight: None,
+            max_fragments: None,
+            reassembly_timeout: None,
+            deduplicate: None,
+            urgent: false,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for SendType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStructVariant;
+
+        let (index, variant, resend_time) = match self {
+            SendType::Unreliable => return serializer.serialize_unit_variant("SendType", 0, "Unreliable"),
+            SendType::UnreliableSequenced => return serializer.serialize_unit_variant("SendType", 3, "UnreliableSequenced"),
+            SendType::ReliableOrdered { resend_time } => (1, "ReliableOrdered", resend_time),
+            SendType::ReliableUnordered { resend_time } => (2, "ReliableUnordered", resend_time),
+            SendType::ReliableSequenced { resend_time } => (4, "ReliableSequenced", resend_time),
+        };
+        let mut state = serializer.serialize_struct_variant("SendType", index, variant, 1)?;
+        state.serialize_field("resend_time", resend_time)?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for ReceivePolicy {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStructVariant;
+
+        match *self {
+            ReceivePolicy::Unbounded => serializer.serialize_unit_variant("ReceivePolicy", 0, "Unbounded"),
+            ReceivePolicy::DropOldest { max_messages } => {
+                let mut state = serializer.serialize_struct_variant("ReceivePolicy", 1, "DropOldest", 1)?;
+                state.serialize_field("max_messages", &max_messages)?;
+                state.end()
+            }
+            ReceivePolicy::WarnAndDrop { max_age } => {
+                let mut state = serializer.serialize_struct_variant("ReceivePolicy", 2, "WarnAndDrop", 1)?;
+                state.serialize_field("max_age", &max_age)?;
+                state.end()
+            }
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for QueuePolicy {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match *self {
+            QueuePolicy::ErrorAndDisconnect => serializer.serialize_unit_variant("QueuePolicy", 0, "ErrorAndDisconnect"),
+            QueuePolicy::RejectSend => serializer.serialize_unit_variant("QueuePolicy", 1, "RejectSend"),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for FullQueuePolicy {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match *self {
+            FullQueuePolicy::DropNewest => serializer.serialize_unit_variant("FullQueuePolicy", 0, "DropNewest"),
+            FullQueuePolicy::DropOldest => serializer.serialize_unit_variant("FullQueuePolicy", 1, "DropOldest"),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for OverflowRoute {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("OverflowRoute", 2)?;
+        state.serialize_field("channel_id", &self.channel_id)?;
+        state.serialize_field("threshold_bytes", &self.threshold_bytes)?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for ChannelConfig {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("ChannelConfig", 25)?;
+        state.serialize_field("channel_id", &self.channel_id)?;
+        state.serialize_field("max_memory_usage_bytes", &self.max_memory_usage_bytes)?;
+        state.serialize_field("send_type", &self.send_type)?;
+        state.serialize_field("warning_threshold", &self.warning_threshold)?;
+        state.serialize_field("group", &self.group)?;
+        state.serialize_field("receive_policy", &self.receive_policy)?;
+        state.serialize_field("compression", &self.compression)?;
+        state.serialize_field("priority", &self.priority)?;
+        state.serialize_field("tracing_context", &self.tracing_context)?;
+        state.serialize_field("max_kbps", &self.max_kbps)?;
+        state.serialize_field("message_kinds", &self.message_kinds)?;
+        state.serialize_field("max_message_size", &self.max_message_size)?;
+        state.serialize_field("overflow", &self.overflow)?;
+        state.serialize_field("max_resend_time", &self.max_resend_time)?;
+        state.serialize_field("message_ttl", &self.message_ttl)?;
+        state.serialize_field("delivery_feedback", &self.delivery_feedback)?;
+        state.serialize_field("coalesce_delay", &self.coalesce_delay)?;
+        state.serialize_field("max_queued_messages", &self.max_queued_messages)?;
+        state.serialize_field("queue_policy", &self.queue_policy)?;
+        state.serialize_field("full_queue_policy", &self.full_queue_policy)?;
+        state.serialize_field("max_packets_in_flight", &self.max_packets_in_flight)?;
+        state.serialize_field("max_fragments", &self.max_fragments)?;
+        state.serialize_field("reassembly_timeout", &self.reassembly_timeout)?;
+        state.serialize_field("deduplicate", &self.deduplicate)?;
+        state.serialize_field("urgent", &self.urgent)?;
+        state.end()
+    }
+}
+
+impl From<&ChannelConfig> for ChannelDescriptor {
+    fn from(config: &ChannelConfig) -> Self {
+        let send_type = match config.send_type {
+            SendType::Unreliable => 0,
+            SendType::ReliableOrdered { .. } => 1,
+            SendType::ReliableUnordered { .. } => 2,
+            SendType::UnreliableSequenced => 3,
+            SendType::ReliableSequenced { .. } => 4,
+        };
+
+        Self {
+            channel_id: config.channel_id.raw(),
+            send_type,
+            max_memory_usage_bytes: config.max_memory_usage_bytes as u64,
+            group: config.group,
+            compression: config.compression.as_ref().map_or(0, |compression| compression.id()),
+            compression_dictionary: config.compression.as_ref().map_or(0, |compression| compression.dictionary_hash()),
+            tracing_context: config.tracing_context,
+            message_kinds: config.message_kinds,
+            // Only unreliable channels add the sequence number, the setting is ignored by the others
+            deduplicate: matches!(config.send_type, SendType::Unreliable) && config.deduplicate.is_some(),
+        }
+    }
 }
 
 /// Utility enumerator when using the default channels configuration.
 /// The default configuration has 3 channels: unreliable, reliable ordered, and reliable unordered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DefaultChannel {
     Unreliable,
     ReliableOrdered,
@@ -54,28 +606,27 @@ impl From<DefaultChannel> for u8 {
     }
 }
 
+impl From<DefaultChannel> for ChannelId {
+    fn from(channel: DefaultChannel) -> Self {
+        Self(channel.into())
+    }
+}
+
 impl DefaultChannel {
+    /// Every default channel, see [`ConnectionConfig::check_channels`][crate::ConnectionConfig::check_channels].
+    pub const ALL: [DefaultChannel; 3] = [
+        DefaultChannel::Unreliable,
+        DefaultChannel::ReliableOrdered,
+        DefaultChannel::ReliableUnordered,
+    ];
+
+    /// Configuration of the default channels, start from [`ChannelsBuilder`] to change them.
     pub fn config() -> Vec<ChannelConfig> {
+        let resend_time = Duration::from_millis(300);
         vec![
-            ChannelConfig {
-                channel_id: 0,
-                max_memory_usage_bytes: 5 * 1024 * 1024,
-                send_type: SendType::Unreliable,
-            },
-            ChannelConfig {
-                channel_id: 1,
-                max_memory_usage_bytes: 5 * 1024 * 1024,
-                send_type: SendType::ReliableUnordered {
-                    resend_time: Duration::from_millis(300),
-                },
-            },
-            ChannelConfig {
-                channel_id: 2,
-                max_memory_usage_bytes: 5 * 1024 * 1024,
-                send_type: SendType::ReliableOrdered {
-                    resend_time: Duration::from_millis(300),
-                },
-            },
+            ChannelConfig::new(0, 5 * 1024 * 1024, SendType::Unreliable),
+            ChannelConfig::new(1, 5 * 1024 * 1024, SendType::ReliableUnordered { resend_time }),
+            ChannelConfig::new(2, 5 * 1024 * 1024, SendType::ReliableOrdered { resend_time }),
         ]
     }
 }
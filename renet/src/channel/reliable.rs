@@ -1,29 +1,44 @@
 use std::{
-    collections::{btree_map, BTreeMap, BTreeSet, HashMap},
+    collections::{btree_map, BTreeMap, BTreeSet, HashMap, VecDeque},
     time::Duration,
 };
 
 use bytes::Bytes;
 
-use super::SliceConstructor;
+use super::{
+    delivery::DeliveryVerifier, delivery::DuplicateDelivery, slice_constructor, trace::MessageTracer, ChannelCapacity, ChannelLatency,
+    LatencyStats, MessageId, MessageLatencyRecord, ReceivePolicy, SliceConstructor,
+};
 use crate::{
     error::ChannelError,
-    packet::{Packet, Slice, SLICE_SIZE},
+    packet::{is_sliced, num_slices, small_message_size, Packet, Slice, SLICE_SIZE},
 };
 
 #[derive(Debug)]
 enum UnackedMessage {
     Small {
         message: Bytes,
+        created_at: Duration,
         last_sent: Option<Duration>,
+        // Resends without an ack, they back off the next resend
+        resends: u32,
     },
     Sliced {
         message: Bytes,
+        created_at: Duration,
         num_slices: usize,
         num_acked_slices: usize,
         next_slice_to_send: usize,
         acked: Vec<bool>,
         last_sent: Vec<Option<Duration>>,
+        // Rounds of resends without any slice acked
+        resends: u32,
+    },
+    // Cancelled sliced message, the cancel is resent until acked
+    Cancelled {
+        created_at: Duration,
+        last_sent: Option<Duration>,
+        resends: u32,
     },
 }
 
@@ -33,8 +48,35 @@ pub struct SendChannelReliable {
     unacked_messages: BTreeMap<u64, UnackedMessage>,
     next_reliable_message_id: u64,
     resend_time: Duration,
+    // Resends of an unacked message double the resend time up to this one, fixed resend time when None
+    max_resend_time: Option<Duration>,
     max_memory_usage_bytes: usize,
     memory_usage_bytes: usize,
+    acked_messages: Vec<u64>,
+    paused: bool,
+    // Unacked messages only age since the channel was last resumed
+    resumed_at: Duration,
+    latency: ChannelLatency,
+    // Queue latency of the sent messages waiting for an ack, only kept while recording latencies
+    queue_latencies: Option<HashMap<u64, Duration>>,
+    latency_records: Vec<MessageLatencyRecord>,
+    // Sending a message cancels the unacked older ones, for sequenced channels
+    sequenced: bool,
+    // Records the events of the messages, for channels with tracing context
+    tracer: Option<MessageTracer>,
+    // Messages and slices resent since the last call to take_resends
+    resends: u64,
+    // New messages wait this long after the first of them, unless they fill a packet
+    coalesce_delay: Option<Duration>,
+    // The channel is full when holding this many unacked messages
+    max_queued_messages: Option<usize>,
+    // New messages are only packed while fewer packets than this are in flight
+    max_packets_in_flight: Option<usize>,
+    // Packets sent with their send time, until acked or older than the resend time, with max_packets_in_flight
+    packets_in_flight: BTreeMap<u64, Duration>,
+    // Messages and slices of the packets presumed lost, resent without waiting for their resend time.
+    // Small messages and cancels use the slice index 0.
+    fast_resends: BTreeSet<(u64, usize)>,
 }
 
 #[derive(Debug)]
@@ -44,29 +86,52 @@ enum ReliableOrder {
         most_recent_message_id: u64,
         received_messages: BTreeSet<u64>,
     },
+    // Only the newest message is kept, older ones received later are discarded.
+    // The oldest pending message id is the one after the newest message received.
+    Sequenced,
 }
 
 #[derive(Debug)]
 pub struct ReceiveChannelReliable {
     slices: HashMap<u64, SliceConstructor>,
-    messages: BTreeMap<u64, Bytes>,
+    // Received messages with their receive time
+    messages: BTreeMap<u64, (Bytes, Duration)>,
+    // Messages cancelled by the sender, skipped in place of being received
+    cancelled: BTreeSet<u64>,
     oldest_pending_message_id: u64,
     reliable_order: ReliableOrder,
     memory_usage_bytes: usize,
     max_memory_usage_bytes: usize,
+    max_message_size: usize,
+    verifier: DeliveryVerifier,
+    // Head-of-line blocking times of the last blocks, ordered channels only
+    blocking_samples: VecDeque<Duration>,
 }
 
+// Blocks kept to compute the head-of-line blocking statistics
+const HEAD_OF_LINE_SAMPLES: usize = 64;
+
 impl UnackedMessage {
-    fn new_sliced(payload: Bytes) -> Self {
-        let num_slices = (payload.len() + SLICE_SIZE - 1) / SLICE_SIZE;
+    fn created_at(&self) -> Duration {
+        match self {
+            UnackedMessage::Small { created_at, .. }
+            | UnackedMessage::Sliced { created_at, .. }
+            | UnackedMessage::Cancelled { created_at, .. } => *created_at,
+        }
+    }
+
+    fn new_sliced(payload: Bytes, created_at: Duration) -> Self {
+        let num_slices = num_slices(payload.len());
 
         Self::Sliced {
             message: payload,
+            created_at,
             num_slices,
             num_acked_slices: 0,
             next_slice_to_send: 0,
             acked: vec![false; num_slices],
             last_sent: vec![None; num_slices],
+            resends: 0,
         }
     }
 }
@@ -78,8 +143,161 @@ impl SendChannelReliable {
             unacked_messages: BTreeMap::new(),
             next_reliable_message_id: 0,
             resend_time,
+            max_resend_time: None,
             max_memory_usage_bytes,
             memory_usage_bytes: 0,
+            acked_messages: Vec::new(),
+            paused: false,
+            resumed_at: Duration::ZERO,
+            latency: ChannelLatency::default(),
+            queue_latencies: None,
+            latency_records: Vec::new(),
+            sequenced: false,
+            tracer: None,
+            resends: 0,
+            coalesce_delay: None,
+            max_queued_messages: None,
+            max_packets_in_flight: None,
+            packets_in_flight: BTreeMap::new(),
+            fast_resends: BTreeSet::new(),
+        }
+    }
+
+    pub fn new_sequenced(channel_id: u8, resend_time: Duration, max_memory_usage_bytes: usize) -> Self {
+        Self {
+            sequenced: true,
+            ..Self::new(channel_id, resend_time, max_memory_usage_bytes)
+        }
+    }
+
+    pub fn set_tracer(&mut self, tracer: MessageTracer) {
+        self.tracer = Some(tracer);
+    }
+
+    pub fn set_max_resend_time(&mut self, max_resend_time: Duration) {
+        self.max_resend_time = Some(max_resend_time.max(self.resend_time));
+    }
+
+    pub fn set_max_queued_messages(&mut self, max_queued_messages: usize) {
+        self.max_queued_messages = Some(max_queued_messages);
+    }
+
+    // Sending a message on a sequenced channel drops the older ones, they are never full of messages
+    fn is_queue_full(&self) -> bool {
+        !self.sequenced && self.max_queued_messages.is_some_and(|max| self.unacked_messages.len() >= max)
+    }
+
+    pub fn set_max_packets_in_flight(&mut self, max_packets_in_flight: usize) {
+        self.max_packets_in_flight = Some(max_packets_in_flight);
+    }
+
+    pub fn process_packet_ack(&mut self, packet_sequence: u64) {
+        self.packets_in_flight.remove(&packet_sequence);
+    }
+
+    // The packet was skipped by later acks, its messages are resent on the next call to get_packets_to_send
+    pub fn process_packet_nack(&mut self, packet_sequence: u64, message_ids: &[u64]) {
+        self.packets_in_flight.remove(&packet_sequence);
+        self.fast_resends.extend(message_ids.iter().map(|&message_id| (message_id, 0)));
+    }
+
+    pub fn process_slice_nack(&mut self, packet_sequence: u64, message_id: u64, slice_index: usize) {
+        self.packets_in_flight.remove(&packet_sequence);
+        self.fast_resends.insert((message_id, slice_index));
+    }
+
+    pub fn set_coalesce_delay(&mut self, coalesce_delay: Duration) {
+        self.coalesce_delay = Some(coalesce_delay);
+    }
+
+    // Returns true while the messages never sent are held back by the coalesce delay
+    fn is_coalescing(&self, current_time: Duration) -> bool {
+        let Some(coalesce_delay) = self.coalesce_delay else {
+            return false;
+        };
+
+        let mut oldest_unsent: Option<Duration> = None;
+        let mut unsent_bytes = 0;
+        for unacked_message in self.unacked_messages.values() {
+            match unacked_message {
+                UnackedMessage::Small {
+                    message,
+                    created_at,
+                    last_sent: None,
+                    ..
+                } => {
+                    oldest_unsent.get_or_insert(*created_at);
+                    unsent_bytes += message.len();
+                }
+                // Sliced messages fill packets on their own
+                UnackedMessage::Sliced { last_sent, .. } if last_sent.iter().all(Option::is_none) => return false,
+                _ => {}
+            }
+            if unsent_bytes >= SLICE_SIZE {
+                return false;
+            }
+        }
+
+        oldest_unsent.is_some_and(|oldest_unsent| current_time.saturating_sub(oldest_unsent) < coalesce_delay)
+    }
+
+    // Time before resending a message resent the given times without an ack
+    fn resend_interval(resend_time: Duration, max_resend_time: Option<Duration>, resends: u32) -> Duration {
+        match max_resend_time {
+            Some(max_resend_time) => resend_time.saturating_mul(1 << resends.min(16)).min(max_resend_time),
+            None => resend_time,
+        }
+    }
+
+    pub fn latency(&self) -> ChannelLatency {
+        self.latency
+    }
+
+    pub fn take_resends(&mut self) -> u64 {
+        std::mem::take(&mut self.resends)
+    }
+
+    pub fn reset_latency(&mut self) {
+        self.latency = ChannelLatency::default();
+    }
+
+    /// When enabled, a [`MessageLatencyRecord`] is kept for each acked message.
+    pub fn set_record_latencies(&mut self, enabled: bool) {
+        if !enabled {
+            self.queue_latencies = None;
+            self.latency_records.clear();
+        } else if self.queue_latencies.is_none() {
+            self.queue_latencies = Some(HashMap::new());
+        }
+    }
+
+    pub fn latency_records(&mut self) -> std::vec::Drain<'_, MessageLatencyRecord> {
+        self.latency_records.drain(..)
+    }
+
+    fn record_queue_latency(
+        latency: &mut ChannelLatency,
+        queue_latencies: &mut Option<HashMap<u64, Duration>>,
+        message_id: u64,
+        queue_latency: Duration,
+    ) {
+        latency.queue.record(queue_latency);
+        if let Some(queue_latencies) = queue_latencies {
+            queue_latencies.insert(message_id, queue_latency);
+        }
+    }
+
+    fn record_delivery(&mut self, message_id: u64, created_at: Duration, current_time: Duration) {
+        let delivery_latency = current_time.saturating_sub(created_at);
+        self.latency.delivery.record(delivery_latency);
+        if let Some(queue_latencies) = &mut self.queue_latencies {
+            if let Some(queue_latency) = queue_latencies.remove(&message_id) {
+                self.latency_records.push(MessageLatencyRecord {
+                    message_id: MessageId::from_raw(message_id),
+                    queue_latency,
+                    delivery_latency,
+                });
+            }
         }
     }
 
@@ -87,8 +305,88 @@ impl SendChannelReliable {
         self.max_memory_usage_bytes - self.memory_usage_bytes
     }
 
+    pub fn memory_usage(&self) -> usize {
+        self.memory_usage_bytes
+    }
+
     pub fn can_send_message(&self, size_bytes: usize) -> bool {
-        size_bytes + self.memory_usage_bytes <= self.max_memory_usage_bytes
+        !self.is_queue_full() && size_bytes <= self.capacity().bytes_free
+    }
+
+    pub fn capacity(&self) -> ChannelCapacity {
+        let bytes_unacked = self
+            .unacked_messages
+            .values()
+            .map(|unacked_message| match unacked_message {
+                UnackedMessage::Small {
+                    message,
+                    last_sent: Some(_),
+                    ..
+                } => message.len(),
+                UnackedMessage::Sliced { message, last_sent, .. } if last_sent.iter().any(Option::is_some) => message.len(),
+                _ => 0,
+            })
+            .sum();
+        // Sending a message on a sequenced channel drops the older ones
+        let bytes_free = if self.sequenced { self.max_memory_usage_bytes } else { self.max_memory_usage_bytes - self.memory_usage_bytes };
+
+        ChannelCapacity {
+            bytes_free,
+            bytes_queued: self.memory_usage_bytes - bytes_unacked,
+            bytes_unacked,
+            max_message_bytes: if self.is_queue_full() { 0 } else { bytes_free },
+        }
+    }
+
+    /// Returns how long the oldest message waiting for an ack has been in the channel.
+    /// Time spent paused is not counted, a paused channel always returns zero.
+    pub fn oldest_unacked_age(&self, current_time: Duration) -> Duration {
+        if self.paused {
+            return Duration::ZERO;
+        }
+
+        match self.unacked_messages.values().next() {
+            // Message ids are sequential, the first unacked message is the oldest one
+            Some(message) => current_time.saturating_sub(message.created_at().max(self.resumed_at)),
+            None => Duration::ZERO,
+        }
+    }
+
+    /// A paused channel keeps accepting messages but sends none of them until resumed,
+    /// the messages already sent are still resent until acked.
+    pub fn set_paused(&mut self, paused: bool, current_time: Duration) {
+        if self.paused && !paused {
+            self.resumed_at = current_time;
+        }
+        self.paused = paused;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Returns the id that will be assigned to the next message sent.
+    pub fn next_message_id(&self) -> u64 {
+        self.next_reliable_message_id
+    }
+
+    /// Returns whether all messages with an id lower than the given one were acked.
+    pub fn all_acked_before(&self, message_id: u64) -> bool {
+        match self.unacked_messages.keys().next() {
+            Some(oldest_unacked) => *oldest_unacked >= message_id,
+            None => true,
+        }
+    }
+
+    /// Returns the ids of the messages that were fully acked since the last clear, in ack order.
+    pub fn acked_messages(&mut self) -> std::vec::Drain<'_, u64> {
+        self.acked_messages.drain(..)
+    }
+
+    /// Clears the acked messages ids, the allocated memory is kept for reuse.
+    pub fn clear_acked_messages(&mut self) {
+        self.acked_messages.clear();
+        self.latency_records.clear();
     }
 
     pub fn get_packets_to_send(&mut self, packet_sequence: &mut u64, available_bytes: &mut u64, current_time: Duration) -> Vec<Packet> {
@@ -100,26 +398,69 @@ impl SendChannelReliable {
 
         let mut small_messages: Vec<(u64, Bytes)> = vec![];
         let mut small_messages_bytes = 0;
+        // Paused channels only resend, like coalescing ones
+        let coalescing = self.paused || self.is_coalescing(current_time);
+        let (resend_time, max_resend_time) = (self.resend_time, self.max_resend_time);
+        let resend_interval = |resends: u32| Self::resend_interval(resend_time, max_resend_time, resends);
+        // Packets not acked within the resend time are presumed lost, their messages are resent
+        self.packets_in_flight
+            .retain(|_, sent_at| current_time.saturating_sub(*sent_at) < resend_time);
+        let window = self
+            .max_packets_in_flight
+            .map(|max_packets_in_flight| max_packets_in_flight.saturating_sub(self.packets_in_flight.len()));
+        let first_sequence = *packet_sequence;
+        if !self.fast_resends.is_empty() {
+            let unacked_messages = &self.unacked_messages;
+            self.fast_resends
+                .retain(|(message_id, _)| unacked_messages.contains_key(message_id));
+        }
 
         'messages: for (&message_id, unacked_message) in self.unacked_messages.iter_mut() {
             match unacked_message {
-                UnackedMessage::Small { message, last_sent } => {
+                UnackedMessage::Small {
+                    message,
+                    created_at,
+                    last_sent,
+                    resends,
+                } => {
                     if *available_bytes < message.len() as u64 {
                         // Skip message, no bytes available to send this message
                         continue;
                     }
+                    let window_full = window.is_some_and(|window| packets.len() >= window);
+                    if (coalescing || window_full) && last_sent.is_none() {
+                        continue;
+                    }
 
-                    if let Some(last_sent) = last_sent {
-                        if current_time - *last_sent < self.resend_time {
-                            continue;
+                    let fast_resend = self.fast_resends.contains(&(message_id, 0));
+                    match last_sent {
+                        Some(last_sent) if !fast_resend && current_time.saturating_sub(*last_sent) < resend_interval(*resends) => continue,
+                        Some(_) => {
+                            self.fast_resends.remove(&(message_id, 0));
+                            *resends += 1;
+                            self.resends += 1;
+                            if let Some(tracer) = &self.tracer {
+                                tracer.record("message resent", message);
+                            }
+                        }
+                        None => {
+                            Self::record_queue_latency(
+                                &mut self.latency,
+                                &mut self.queue_latencies,
+                                message_id,
+                                current_time.saturating_sub(*created_at),
+                            );
+                            if let Some(tracer) = &self.tracer {
+                                tracer.record("message packed", message);
+                            }
                         }
                     }
 
                     *available_bytes -= message.len() as u64;
 
                     // Generate packet with small messages if you cannot fit
-                    let serialized_size = message.len() + octets::varint_len(message.len() as u64) + octets::varint_len(message_id);
-                    if small_messages_bytes + serialized_size > SLICE_SIZE {
+                    let serialized_size = small_message_size(message.len(), Some(message_id));
+                    if !small_messages.is_empty() && small_messages_bytes + serialized_size > SLICE_SIZE {
                         packets.push(Packet::SmallReliable {
                             sequence: *packet_sequence,
                             channel_id: self.channel_id,
@@ -137,13 +478,17 @@ impl SendChannelReliable {
                 }
                 UnackedMessage::Sliced {
                     message,
+                    created_at,
                     num_slices,
                     acked,
                     last_sent,
                     next_slice_to_send,
+                    resends,
                     ..
                 } => {
                     let start_index = *next_slice_to_send;
+                    let interval = resend_interval(*resends);
+                    let mut resent = false;
                     for i in 0..*num_slices {
                         if *available_bytes < SLICE_SIZE as u64 {
                             // Skip message, no bytes available to send a slice
@@ -156,9 +501,17 @@ impl SendChannelReliable {
                         }
 
                         if let Some(last_sent) = last_sent[i] {
-                            if current_time - last_sent < self.resend_time {
+                            if !self.fast_resends.remove(&(message_id, i)) && current_time.saturating_sub(last_sent) < interval {
                                 continue;
                             }
+                            if !resent {
+                                resent = true;
+                                *resends += 1;
+                            }
+                            self.resends += 1;
+                        } else if self.paused || window.is_some_and(|window| packets.len() >= window) {
+                            // Only resends while paused or the window is full
+                            continue;
                         }
 
                         let start = i * SLICE_SIZE;
@@ -181,10 +534,41 @@ impl SendChannelReliable {
                         });
 
                         *packet_sequence += 1;
+                        if last_sent.iter().all(Option::is_none) {
+                            Self::record_queue_latency(
+                                &mut self.latency,
+                                &mut self.queue_latencies,
+                                message_id,
+                                current_time.saturating_sub(*created_at),
+                            );
+                            if let Some(tracer) = &self.tracer {
+                                tracer.record("message packed", message);
+                            }
+                        } else if last_sent[i].is_some() {
+                            if let Some(tracer) = &self.tracer {
+                                tracer.record("message resent", message);
+                            }
+                        }
                         last_sent[i] = Some(current_time);
                         *next_slice_to_send = i + 1 % *num_slices;
                     }
                 }
+                UnackedMessage::Cancelled { last_sent, resends, .. } => {
+                    let fast_resend = self.fast_resends.remove(&(message_id, 0));
+                    match last_sent {
+                        Some(last_sent) if !fast_resend && current_time.saturating_sub(*last_sent) < resend_interval(*resends) => continue,
+                        Some(_) => *resends += 1,
+                        None => {}
+                    }
+
+                    packets.push(Packet::CancelReliable {
+                        sequence: *packet_sequence,
+                        channel_id: self.channel_id,
+                        message_id,
+                    });
+                    *packet_sequence += 1;
+                    *last_sent = Some(current_time);
+                }
             }
         }
 
@@ -198,51 +582,132 @@ impl SendChannelReliable {
             *packet_sequence += 1;
         }
 
+        if self.max_packets_in_flight.is_some() {
+            for sequence in first_sequence..*packet_sequence {
+                self.packets_in_flight.insert(sequence, current_time);
+            }
+        }
+
         packets
     }
 
-    pub fn send_message(&mut self, message: Bytes) -> Result<(), ChannelError> {
-        if self.memory_usage_bytes + message.len() > self.max_memory_usage_bytes {
+    /// Queues the message to be sent, returns the id assigned to it.
+    /// In sequenced channels the unacked older messages are superseded, they are no longer sent nor acked.
+    pub fn send_message(&mut self, message: Bytes, current_time: Duration) -> Result<u64, ChannelError> {
+        if self.sequenced {
+            for (message_id, _) in std::mem::take(&mut self.unacked_messages) {
+                if let Some(queue_latencies) = &mut self.queue_latencies {
+                    queue_latencies.remove(&message_id);
+                }
+            }
+            self.memory_usage_bytes = 0;
+        }
+
+        if self.memory_usage_bytes + message.len() > self.max_memory_usage_bytes || self.is_queue_full() {
             return Err(ChannelError::ReliableChannelMaxMemoryReached);
         }
 
         self.memory_usage_bytes += message.len();
-        let unacked_message = if message.len() > SLICE_SIZE {
-            UnackedMessage::new_sliced(message)
+        let unacked_message = if is_sliced(message.len()) {
+            UnackedMessage::new_sliced(message, current_time)
         } else {
-            UnackedMessage::Small { message, last_sent: None }
+            UnackedMessage::Small {
+                message,
+                created_at: current_time,
+                last_sent: None,
+                resends: 0,
+            }
         };
 
-        self.unacked_messages.insert(self.next_reliable_message_id, unacked_message);
+        let message_id = self.next_reliable_message_id;
+        self.unacked_messages.insert(message_id, unacked_message);
         self.next_reliable_message_id += 1;
 
-        Ok(())
+        Ok(message_id)
     }
 
-    pub fn process_message_ack(&mut self, message_id: u64) {
-        if self.unacked_messages.contains_key(&message_id) {
-            let unacked_message = self.unacked_messages.remove(&message_id).unwrap();
-            let UnackedMessage::Small { message: payload, .. } = unacked_message else {
-                unreachable!("called ack on small message but found sliced");
+    // Acked and total bytes of the sliced messages waiting for their acks, None without any
+    pub fn sliced_progress(&self) -> Option<(u64, u64)> {
+        let mut progress = None;
+        for unacked_message in self.unacked_messages.values() {
+            let UnackedMessage::Sliced { message, acked, .. } = unacked_message else {
+                continue;
             };
-            self.memory_usage_bytes -= payload.len();
+
+            let acked_bytes: usize = acked
+                .iter()
+                .enumerate()
+                .filter(|(_, acked)| **acked)
+                .map(|(slice_index, _)| SLICE_SIZE.min(message.len() - slice_index * SLICE_SIZE))
+                .sum();
+            let (total_acked, total) = progress.get_or_insert((0, 0));
+            *total_acked += acked_bytes as u64;
+            *total += message.len() as u64;
         }
+
+        progress
     }
 
-    pub fn process_slice_message_ack(&mut self, message_id: u64, slice_index: usize) {
+    /// Cancels a sliced message that was not fully acked: its remaining slices are no longer sent,
+    /// and the remote discards the slices it received. Returns false if there is no such message.
+    pub fn cancel_sliced_message(&mut self, message_id: u64) -> bool {
+        let Some(unacked_message) = self.unacked_messages.get_mut(&message_id) else {
+            return false;
+        };
+        let UnackedMessage::Sliced { message, created_at, .. } = unacked_message else {
+            return false;
+        };
+
+        if let Some(tracer) = &self.tracer {
+            tracer.record("message cancelled", message);
+        }
+        self.memory_usage_bytes -= message.len();
+        *unacked_message = UnackedMessage::Cancelled {
+            created_at: *created_at,
+            last_sent: None,
+            resends: 0,
+        };
+        if let Some(queue_latencies) = &mut self.queue_latencies {
+            queue_latencies.remove(&message_id);
+        }
+
+        true
+    }
+
+    // Also acks the cancel of sliced messages, they are not reported as acked messages
+    pub fn process_message_ack(&mut self, message_id: u64, current_time: Duration) {
+        match self.unacked_messages.remove(&message_id) {
+            Some(UnackedMessage::Small {
+                message: payload,
+                created_at,
+                ..
+            }) => {
+                self.memory_usage_bytes -= payload.len();
+                self.acked_messages.push(message_id);
+                self.record_delivery(message_id, created_at, current_time);
+            }
+            Some(UnackedMessage::Sliced { .. }) => unreachable!("called ack on small message but found sliced"),
+            Some(UnackedMessage::Cancelled { .. }) | None => {}
+        }
+    }
+
+    pub fn process_slice_message_ack(&mut self, message_id: u64, slice_index: usize, current_time: Duration) {
         let Some(unacked_message) = self.unacked_messages.get_mut(&message_id) else {
             return;
         };
 
         let UnackedMessage::Sliced {
             message,
+            created_at,
             num_slices,
             num_acked_slices,
             acked,
+            resends,
             ..
         } = unacked_message
         else {
-            unreachable!("called ack on sliced message but found small");
+            // Slices sent before the message was cancelled
+            return;
         };
 
         if acked[slice_index] {
@@ -251,10 +716,15 @@ impl SendChannelReliable {
 
         acked[slice_index] = true;
         *num_acked_slices += 1;
+        // The link delivers the message again, its resends don't back off anymore
+        *resends = 0;
 
         if *num_acked_slices == *num_slices {
+            let created_at = *created_at;
             self.memory_usage_bytes -= message.len();
             self.unacked_messages.remove(&message_id);
+            self.acked_messages.push(message_id);
+            self.record_delivery(message_id, created_at, current_time);
         }
     }
 }
@@ -271,18 +741,51 @@ impl ReceiveChannelReliable {
         Self {
             slices: HashMap::new(),
             messages: BTreeMap::new(),
+            cancelled: BTreeSet::new(),
             oldest_pending_message_id: 0,
             reliable_order,
             memory_usage_bytes: 0,
             max_memory_usage_bytes,
+            max_message_size: usize::MAX,
+            verifier: DeliveryVerifier::default(),
+            blocking_samples: VecDeque::new(),
+        }
+    }
+
+    // Messages above the size are refused, their slices before being allocated
+    pub fn set_max_message_size(&mut self, max_message_size: usize) {
+        self.max_message_size = max_message_size;
+    }
+
+    // Sequence of the packet whose messages are processed next, reported with duplicate deliveries
+    pub fn set_packet_sequence(&mut self, packet_sequence: u64) {
+        self.verifier.set_packet_sequence(packet_sequence);
+    }
+
+    // Message delivered twice since the last call, only verified in debug builds or with the `verify_delivery` feature
+    pub fn take_duplicate_delivery(&mut self) -> Option<DuplicateDelivery> {
+        self.verifier.take_duplicate()
+    }
+
+    pub fn new_sequenced(max_memory_usage_bytes: usize) -> Self {
+        Self {
+            reliable_order: ReliableOrder::Sequenced,
+            ..Self::new(max_memory_usage_bytes, true)
         }
     }
 
-    pub fn process_message(&mut self, message: Bytes, message_id: u64) -> Result<(), ChannelError> {
-        if message_id < self.oldest_pending_message_id {
+    pub fn memory_usage(&self) -> usize {
+        self.memory_usage_bytes
+    }
+
+    pub fn process_message(&mut self, message: Bytes, message_id: u64, current_time: Duration) -> Result<(), ChannelError> {
+        if message_id < self.oldest_pending_message_id || self.cancelled.contains(&message_id) {
             // Discard old message already received
             return Ok(());
         }
+        if message.len() > self.max_message_size {
+            return Err(ChannelError::ReceivedMessageAboveLimit);
+        }
 
         match &mut self.reliable_order {
             ReliableOrder::Ordered => {
@@ -292,7 +795,9 @@ impl ReceiveChannelReliable {
                     }
                     self.memory_usage_bytes += message.len();
 
-                    entry.insert(message);
+                    entry.insert((message, current_time));
+                    self.verifier.received(message_id);
+                    self.record_head_of_line_blocking(message_id, current_time);
                 }
             }
             ReliableOrder::Unordered {
@@ -310,21 +815,50 @@ impl ReceiveChannelReliable {
                     self.memory_usage_bytes += message.len();
 
                     received_messages.insert(message_id);
-                    self.messages.insert(message_id, message);
+                    self.messages.insert(message_id, (message, current_time));
+                    self.verifier.received(message_id);
                 }
             }
+            ReliableOrder::Sequenced => {
+                // The newer message supersedes the unread one and the older partial messages
+                for (_, (unread, _)) in std::mem::take(&mut self.messages) {
+                    self.memory_usage_bytes -= unread.len();
+                }
+                let memory_usage_bytes = &mut self.memory_usage_bytes;
+                self.slices.retain(|&slices_message_id, slices| {
+                    let older = slices_message_id < message_id;
+                    if older {
+                        *memory_usage_bytes -= slices.num_slices * SLICE_SIZE;
+                    }
+                    !older
+                });
+
+                if self.memory_usage_bytes + message.len() > self.max_memory_usage_bytes {
+                    return Err(ChannelError::ReliableChannelMaxMemoryReached);
+                }
+                self.memory_usage_bytes += message.len();
+                self.messages.insert(message_id, (message, current_time));
+                self.verifier.received(message_id);
+                self.oldest_pending_message_id = message_id + 1;
+            }
         }
 
         Ok(())
     }
 
-    pub fn process_slice(&mut self, slice: Slice) -> Result<(), ChannelError> {
-        if self.messages.contains_key(&slice.message_id) || slice.message_id < self.oldest_pending_message_id {
-            // Message already assembled
+    pub fn process_slice(&mut self, slice: Slice, current_time: Duration) -> Result<(), ChannelError> {
+        if self.messages.contains_key(&slice.message_id)
+            || slice.message_id < self.oldest_pending_message_id
+            || self.cancelled.contains(&slice.message_id)
+        {
+            // Message already assembled or cancelled
             return Ok(());
         }
 
         if !self.slices.contains_key(&slice.message_id) {
+            if slice_constructor::min_message_len(slice.num_slices) > self.max_message_size {
+                return Err(ChannelError::ReceivedMessageAboveLimit);
+            }
             let message_len = slice.num_slices * SLICE_SIZE;
             if self.memory_usage_bytes + message_len > self.max_memory_usage_bytes {
                 return Err(ChannelError::ReliableChannelMaxMemoryReached);
@@ -340,28 +874,149 @@ impl ReceiveChannelReliable {
         if let Some(message) = slice_constructor.process_slice(slice.slice_index, &slice.payload)? {
             // Memory usage is re-added with the exactly message size
             self.memory_usage_bytes -= slice.num_slices * SLICE_SIZE;
-            self.process_message(message, slice.message_id)?;
+            self.process_message(message, slice.message_id, current_time)?;
             self.slices.remove(&slice.message_id);
         }
 
         Ok(())
     }
 
+    // Received and total slices of the sliced messages being assembled, None without any
+    pub fn sliced_progress(&self) -> Option<(usize, usize)> {
+        slice_constructor::sliced_progress(self.slices.values())
+    }
+
+    // Discards the slices received of the message, the messages after it are received as usual.
+    // A message already assembled is kept, the sender cancelled it too late.
+    pub fn process_cancel(&mut self, message_id: u64) {
+        if self.messages.contains_key(&message_id) || message_id < self.oldest_pending_message_id {
+            return;
+        }
+        if let Some(slices) = self.slices.remove(&message_id) {
+            self.memory_usage_bytes -= slices.num_slices * SLICE_SIZE;
+        }
+
+        match &mut self.reliable_order {
+            ReliableOrder::Ordered => {
+                self.cancelled.insert(message_id);
+                self.skip_cancelled();
+            }
+            ReliableOrder::Unordered {
+                most_recent_message_id,
+                received_messages,
+            } => {
+                if received_messages.contains(&message_id) {
+                    // Received and already read
+                    return;
+                }
+                *most_recent_message_id = (*most_recent_message_id).max(message_id);
+                received_messages.insert(message_id);
+                self.cancelled.insert(message_id);
+                self.skip_cancelled();
+            }
+            ReliableOrder::Sequenced => {
+                // Like a newer message, the cancel supersedes the older partial messages
+                self.oldest_pending_message_id = message_id + 1;
+                let memory_usage_bytes = &mut self.memory_usage_bytes;
+                self.slices.retain(|&slices_message_id, slices| {
+                    let older = slices_message_id < message_id;
+                    if older {
+                        *memory_usage_bytes -= slices.num_slices * SLICE_SIZE;
+                    }
+                    !older
+                });
+            }
+        }
+    }
+
+    // When the message was the first one missing, the messages received after it waited for it:
+    // the time since the earliest of them that it unblocks arrived is recorded.
+    fn record_head_of_line_blocking(&mut self, message_id: u64, current_time: Duration) {
+        let oldest_pending = self.oldest_pending_message_id;
+        let present = self.messages.range(oldest_pending..message_id).count() + self.cancelled.range(oldest_pending..message_id).count();
+        if present as u64 != message_id - oldest_pending {
+            // An earlier message is still missing
+            return;
+        }
+
+        let mut blocked_since: Option<Duration> = None;
+        let mut next_message_id = message_id + 1;
+        loop {
+            if let Some(&(_, received_at)) = self.messages.get(&next_message_id) {
+                blocked_since = Some(blocked_since.map_or(received_at, |since| since.min(received_at)));
+            } else if !self.cancelled.contains(&next_message_id) {
+                break;
+            }
+            next_message_id += 1;
+        }
+
+        if let Some(blocked_since) = blocked_since {
+            if self.blocking_samples.len() == HEAD_OF_LINE_SAMPLES {
+                self.blocking_samples.pop_front();
+            }
+            self.blocking_samples.push_back(current_time.saturating_sub(blocked_since));
+        }
+    }
+
+    // Head-of-line blocking times of the last blocks of the channel
+    pub fn head_of_line_blocking(&self) -> LatencyStats {
+        let mut stats = LatencyStats::default();
+        for &sample in self.blocking_samples.iter() {
+            stats.record(sample);
+        }
+        stats
+    }
+
+    pub fn reset_head_of_line_blocking(&mut self) {
+        self.blocking_samples.clear();
+    }
+
+    // Moves the oldest pending message past the cancelled ones
+    fn skip_cancelled(&mut self) {
+        match &mut self.reliable_order {
+            ReliableOrder::Ordered => {
+                while self.cancelled.remove(&self.oldest_pending_message_id) {
+                    self.oldest_pending_message_id += 1;
+                }
+            }
+            ReliableOrder::Unordered { received_messages, .. } => {
+                if !self.cancelled.contains(&self.oldest_pending_message_id) {
+                    return;
+                }
+                while received_messages.remove(&self.oldest_pending_message_id) {
+                    self.oldest_pending_message_id += 1;
+                }
+                let oldest_pending_message_id = self.oldest_pending_message_id;
+                self.cancelled.retain(|&message_id| message_id >= oldest_pending_message_id);
+            }
+            ReliableOrder::Sequenced => {}
+        }
+    }
+
     pub fn receive_message(&mut self) -> Option<Bytes> {
         match &mut self.reliable_order {
             ReliableOrder::Ordered => {
-                let Some(message) = self.messages.remove(&self.oldest_pending_message_id) else {
+                let Some((message, _)) = self.messages.remove(&self.oldest_pending_message_id) else {
                     return None;
                 };
+                self.verifier.delivered(self.oldest_pending_message_id);
 
                 self.oldest_pending_message_id += 1;
                 self.memory_usage_bytes -= message.len();
+                self.skip_cancelled();
+                Some(message)
+            }
+            ReliableOrder::Sequenced => {
+                let (message_id, (message, _)) = self.messages.pop_first()?;
+                self.verifier.delivered(message_id);
+                self.memory_usage_bytes -= message.len();
                 Some(message)
             }
             ReliableOrder::Unordered { received_messages, .. } => {
-                let Some((message_id, message)) = self.messages.pop_first() else {
+                let Some((message_id, (message, _))) = self.messages.pop_first() else {
                     return None;
                 };
+                self.verifier.delivered(message_id);
 
                 if self.oldest_pending_message_id == message_id {
                     // Remove all next items that could have been received out of order,
@@ -370,6 +1025,8 @@ impl ReceiveChannelReliable {
                         received_messages.remove(&self.oldest_pending_message_id);
                         self.oldest_pending_message_id += 1;
                     }
+                    let oldest_pending_message_id = self.oldest_pending_message_id;
+                    self.cancelled.retain(|&message_id| message_id >= oldest_pending_message_id);
                 }
 
                 self.memory_usage_bytes -= message.len();
@@ -377,6 +1034,28 @@ impl ReceiveChannelReliable {
             }
         }
     }
+
+    // Drops the unread messages expired by the policy, returns the number of dropped messages.
+    // Only messages that can be read are dropped, ordered messages waiting for an earlier one are kept.
+    pub fn apply_receive_policy(&mut self, policy: ReceivePolicy, current_time: Duration) -> u64 {
+        let mut dropped = 0;
+        loop {
+            let next_message = match self.reliable_order {
+                ReliableOrder::Ordered => self.messages.get(&self.oldest_pending_message_id),
+                ReliableOrder::Unordered { .. } | ReliableOrder::Sequenced => self.messages.values().next(),
+            };
+            let Some(&(_, received_at)) = next_message else {
+                break;
+            };
+            if !policy.expires(self.messages.len(), received_at, current_time) {
+                break;
+            }
+            self.receive_message();
+            dropped += 1;
+        }
+
+        dropped
+    }
 }
 
 #[cfg(test)]
@@ -398,8 +1077,8 @@ mod tests {
         let message1 = vec![1, 2, 3];
         let message2 = vec![3, 4, 5];
 
-        send.send_message(message1.clone().into()).unwrap();
-        send.send_message(message2.clone().into()).unwrap();
+        send.send_message(message1.clone().into(), Duration::ZERO).unwrap();
+        send.send_message(message2.clone().into(), Duration::ZERO).unwrap();
 
         let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, current_time);
         for packet in packets {
@@ -412,7 +1091,7 @@ mod tests {
                 unreachable!();
             };
             for (message, message_id) in messages {
-                recv.process_message(message_id, message).unwrap();
+                recv.process_message(message_id, message, Duration::ZERO).unwrap();
             }
         }
 
@@ -433,8 +1112,8 @@ mod tests {
 
         // Should not resend after ack
         current_time += resend_time;
-        send.process_message_ack(0);
-        send.process_message_ack(1);
+        send.process_message_ack(0, Duration::ZERO);
+        send.process_message_ack(1, Duration::ZERO);
 
         let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, current_time);
         assert!(packets.is_empty());
@@ -454,9 +1133,9 @@ mod tests {
         let message2 = vec![3, 4, 5];
         let message3 = vec![6, 7, 8];
 
-        send.send_message(message1.clone().into()).unwrap();
-        send.send_message(message2.clone().into()).unwrap();
-        send.send_message(message3.clone().into()).unwrap();
+        send.send_message(message1.clone().into(), Duration::ZERO).unwrap();
+        send.send_message(message2.clone().into(), Duration::ZERO).unwrap();
+        send.send_message(message3.clone().into(), Duration::ZERO).unwrap();
 
         let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, current_time);
         assert_eq!(packets.len(), 1);
@@ -467,13 +1146,13 @@ mod tests {
         assert_eq!(messages.len(), 3);
 
         // Process and receive out of order
-        recv.process_message(messages[2].1.clone(), messages[2].0).unwrap();
+        recv.process_message(messages[2].1.clone(), messages[2].0, Duration::ZERO).unwrap();
         let new_message3 = recv.receive_message().unwrap();
 
-        recv.process_message(messages[1].1.clone(), messages[1].0).unwrap();
+        recv.process_message(messages[1].1.clone(), messages[1].0, Duration::ZERO).unwrap();
         let new_message2 = recv.receive_message().unwrap();
 
-        recv.process_message(messages[0].1.clone(), messages[0].0).unwrap();
+        recv.process_message(messages[0].1.clone(), messages[0].0, Duration::ZERO).unwrap();
         let new_message1 = recv.receive_message().unwrap();
 
         assert_eq!(message1, new_message1);
@@ -481,7 +1160,7 @@ mod tests {
         assert_eq!(message3, new_message3);
 
         match &recv.reliable_order {
-            ReliableOrder::Ordered => unreachable!(),
+            ReliableOrder::Ordered | ReliableOrder::Sequenced => unreachable!(),
             ReliableOrder::Unordered {
                 most_recent_message_id,
                 received_messages,
@@ -502,9 +1181,9 @@ mod tests {
 
         // Should not resend after ack
         current_time += resend_time;
-        send.process_message_ack(0);
-        send.process_message_ack(1);
-        send.process_message_ack(2);
+        send.process_message_ack(0, Duration::ZERO);
+        send.process_message_ack(1, Duration::ZERO);
+        send.process_message_ack(2, Duration::ZERO);
 
         let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, current_time);
         assert!(packets.is_empty());
@@ -522,14 +1201,14 @@ mod tests {
 
         let message = vec![5; SLICE_SIZE * 3];
 
-        send.send_message(message.clone().into()).unwrap();
+        send.send_message(message.clone().into(), Duration::ZERO).unwrap();
 
         let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, current_time);
         for packet in packets {
             let Packet::ReliableSlice { channel_id: 0, slice, .. } = packet else {
                 unreachable!();
             };
-            recv.process_slice(slice).unwrap();
+            recv.process_slice(slice, current_time).unwrap();
         }
 
         let new_message = recv.receive_message().unwrap();
@@ -546,14 +1225,128 @@ mod tests {
 
         // Should not resend after ack
         current_time += resend_time;
-        send.process_slice_message_ack(0, 0);
-        send.process_slice_message_ack(0, 1);
-        send.process_slice_message_ack(0, 2);
+        send.process_slice_message_ack(0, 0, Duration::ZERO);
+        send.process_slice_message_ack(0, 1, Duration::ZERO);
+        send.process_slice_message_ack(0, 2, Duration::ZERO);
 
         let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, current_time);
         assert!(packets.is_empty());
     }
 
+    #[test]
+    fn acked_messages() {
+        let mut send = SendChannelReliable::new(0, Duration::from_millis(100), usize::MAX);
+
+        let small_id = send.send_message(vec![1, 2, 3].into(), Duration::ZERO).unwrap();
+        let sliced_id = send.send_message(vec![5; SLICE_SIZE * 2].into(), Duration::ZERO).unwrap();
+        assert_eq!(small_id, 0);
+        assert_eq!(sliced_id, 1);
+
+        send.process_slice_message_ack(sliced_id, 0, Duration::ZERO);
+        assert_eq!(send.acked_messages().count(), 0);
+
+        // Acked in a different order than sent
+        send.process_slice_message_ack(sliced_id, 1, Duration::ZERO);
+        send.process_message_ack(small_id, Duration::ZERO);
+        // Duplicated acks are ignored
+        send.process_message_ack(small_id, Duration::ZERO);
+        assert_eq!(send.acked_messages().collect::<Vec<_>>(), vec![sliced_id, small_id]);
+        assert_eq!(send.acked_messages().count(), 0);
+    }
+
+    #[test]
+    fn capacity() {
+        let mut available_bytes = u64::MAX;
+        let mut sequence: u64 = 0;
+        let mut send = SendChannelReliable::new(0, Duration::from_millis(100), 1000);
+
+        let first_id = send.send_message(vec![1; 100].into(), Duration::ZERO).unwrap();
+        send.get_packets_to_send(&mut sequence, &mut available_bytes, Duration::ZERO);
+        send.send_message(vec![2; 200].into(), Duration::ZERO).unwrap();
+        let expected = ChannelCapacity {
+            bytes_free: 700,
+            bytes_queued: 200,
+            bytes_unacked: 100,
+            max_message_bytes: 700,
+        };
+        assert_eq!(send.capacity(), expected);
+        assert!(send.can_send_message(700));
+        assert!(!send.can_send_message(701));
+
+        // Acked messages free their memory
+        send.process_message_ack(first_id, Duration::ZERO);
+        assert_eq!(send.capacity().bytes_free, 800);
+        assert_eq!(send.capacity().bytes_unacked, 0);
+    }
+
+    #[test]
+    fn oldest_unacked_age() {
+        let mut send = SendChannelReliable::new(0, Duration::from_millis(100), usize::MAX);
+        assert_eq!(send.oldest_unacked_age(Duration::from_secs(1)), Duration::ZERO);
+
+        let first = send.send_message(vec![1, 2, 3].into(), Duration::from_millis(100)).unwrap();
+        let second = send
+            .send_message(vec![5; SLICE_SIZE * 2].into(), Duration::from_millis(200))
+            .unwrap();
+        assert_eq!(send.oldest_unacked_age(Duration::from_millis(300)), Duration::from_millis(200));
+
+        send.process_message_ack(first, Duration::ZERO);
+        assert_eq!(send.oldest_unacked_age(Duration::from_millis(300)), Duration::from_millis(100));
+
+        send.process_slice_message_ack(second, 0, Duration::ZERO);
+        send.process_slice_message_ack(second, 1, Duration::ZERO);
+        assert_eq!(send.oldest_unacked_age(Duration::from_millis(300)), Duration::ZERO);
+    }
+
+    #[test]
+    fn paused() {
+        let mut sequence = 0;
+        let mut available_bytes = u64::MAX;
+        let mut send = SendChannelReliable::new(0, Duration::from_millis(100), usize::MAX);
+        send.send_message(vec![1, 2, 3].into(), Duration::ZERO).unwrap();
+        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, Duration::ZERO);
+        assert_eq!(packets.len(), 1);
+
+        // Paused channels only resend the messages already sent
+        send.set_paused(true, Duration::ZERO);
+        send.send_message(vec![4, 5, 6].into(), Duration::from_millis(200)).unwrap();
+        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, Duration::from_millis(500));
+        let Packet::SmallReliable { messages, .. } = &packets[0] else {
+            unreachable!()
+        };
+        assert_eq!(messages.iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![0]);
+        send.process_message_ack(0, Duration::from_millis(500));
+        assert_eq!(send.oldest_unacked_age(Duration::from_secs(5)), Duration::ZERO);
+
+        send.set_paused(false, Duration::from_secs(5));
+        assert_eq!(send.oldest_unacked_age(Duration::from_secs(6)), Duration::from_secs(1));
+        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, Duration::from_secs(6));
+        let Packet::SmallReliable { messages, .. } = &packets[0] else {
+            unreachable!()
+        };
+        assert_eq!(messages.iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![1]);
+    }
+
+    #[test]
+    fn all_acked_before() {
+        let mut send = SendChannelReliable::new(0, Duration::from_millis(100), usize::MAX);
+        assert!(send.all_acked_before(send.next_message_id()));
+
+        let first = send.send_message(vec![1, 2, 3].into(), Duration::ZERO).unwrap();
+        let second = send.send_message(vec![4, 5, 6].into(), Duration::ZERO).unwrap();
+        let fence = send.next_message_id();
+        let after = send.send_message(vec![7, 8, 9].into(), Duration::ZERO).unwrap();
+
+        // Acks out of order do not reach the fence
+        send.process_message_ack(second, Duration::ZERO);
+        assert!(!send.all_acked_before(fence));
+        send.process_message_ack(first, Duration::ZERO);
+        assert!(send.all_acked_before(fence));
+
+        // Messages sent after the fence are not waited
+        assert!(!send.all_acked_before(after + 1));
+    }
+
     #[test]
     fn max_memory() {
         let mut available_bytes = u64::MAX;
@@ -566,7 +1359,7 @@ mod tests {
         let message = vec![5; 100];
 
         // Can send one message without reaching memory limit
-        send.send_message(message.clone().into()).unwrap();
+        send.send_message(message.clone().into(), Duration::ZERO).unwrap();
 
         let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, current_time);
         for packet in packets {
@@ -579,14 +1372,14 @@ mod tests {
                 unreachable!();
             };
             for (message, message_id) in messages {
-                let Err(e) = recv.process_message(message_id, message) else {
+                let Err(e) = recv.process_message(message_id, message, Duration::ZERO) else {
                     unreachable!();
                 };
                 assert_eq!(e, ChannelError::ReliableChannelMaxMemoryReached);
             }
         }
 
-        let Err(send_err) = send.send_message(message.into()) else {
+        let Err(send_err) = send.send_message(message.into(), Duration::ZERO) else {
             unreachable!()
         };
         assert_eq!(send_err, ChannelError::ReliableChannelMaxMemoryReached);
@@ -600,8 +1393,8 @@ mod tests {
         let mut send = SendChannelReliable::new(0, resend_time, usize::MAX);
 
         let message: Bytes = vec![0u8; 100].into();
-        send.send_message(message.clone()).unwrap();
-        send.send_message(message).unwrap();
+        send.send_message(message.clone(), Duration::ZERO).unwrap();
+        send.send_message(message, Duration::ZERO).unwrap();
 
         // No available bytes
         let mut available_bytes: u64 = 50;
@@ -637,7 +1430,7 @@ mod tests {
 
         // (4 + 1 + 2) * 300 = 2100 = 2 packets
         for _ in 0..300 {
-            send.send_message(message.clone()).unwrap();
+            send.send_message(message.clone(), Duration::ZERO).unwrap();
         }
 
         let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, current_time);
@@ -649,4 +1442,269 @@ mod tests {
             assert!(len < 1300);
         }
     }
+
+    #[test]
+    fn receive_policy_ordered() {
+        let mut recv = ReceiveChannelReliable::new(usize::MAX, true);
+        let policy = ReceivePolicy::DropOldest { max_messages: 1 };
+        for message_id in [0, 1, 3] {
+            recv.process_message(vec![message_id as u8].into(), message_id, Duration::ZERO)
+                .unwrap();
+        }
+
+        // Message 3 waits for message 2, it can't be dropped
+        assert_eq!(recv.apply_receive_policy(policy, Duration::ZERO), 2);
+        assert_eq!(recv.apply_receive_policy(policy, Duration::ZERO), 0);
+        assert!(recv.receive_message().is_none());
+
+        recv.process_message(vec![2].into(), 2, Duration::ZERO).unwrap();
+        assert_eq!(recv.apply_receive_policy(policy, Duration::ZERO), 1);
+        assert_eq!(recv.receive_message().unwrap(), vec![3]);
+        assert_eq!(recv.memory_usage(), 0);
+    }
+
+    #[test]
+    fn sliced_progress() {
+        let mut available_bytes = u64::MAX;
+        let mut sequence: u64 = 0;
+        let resend_time = Duration::from_millis(100);
+        let mut send = SendChannelReliable::new(0, resend_time, usize::MAX);
+        let mut recv = ReceiveChannelReliable::new(usize::MAX, true);
+        assert_eq!(send.sliced_progress(), None);
+        assert_eq!(recv.sliced_progress(), None);
+
+        let message_len = SLICE_SIZE * 2 + 100;
+        send.send_message(vec![0; message_len].into(), Duration::ZERO).unwrap();
+        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, Duration::ZERO);
+        assert_eq!(packets.len(), 3);
+        assert_eq!(send.sliced_progress(), Some((0, message_len as u64)));
+
+        send.process_slice_message_ack(0, 2, Duration::ZERO);
+        assert_eq!(send.sliced_progress(), Some((100, message_len as u64)));
+        send.process_slice_message_ack(0, 0, Duration::ZERO);
+        assert_eq!(send.sliced_progress(), Some((SLICE_SIZE as u64 + 100, message_len as u64)));
+
+        for packet in packets.into_iter().take(2) {
+            let Packet::ReliableSlice { slice, .. } = packet else {
+                unreachable!();
+            };
+            recv.process_slice(slice, Duration::ZERO).unwrap();
+        }
+        assert_eq!(recv.sliced_progress(), Some((2, 3)));
+
+        send.process_slice_message_ack(0, 1, Duration::ZERO);
+        assert_eq!(send.sliced_progress(), None);
+    }
+
+    #[test]
+    fn cancel_sliced_message() {
+        let mut available_bytes = u64::MAX;
+        let mut sequence: u64 = 0;
+        let resend_time = Duration::from_millis(100);
+        let mut send = SendChannelReliable::new(0, resend_time, usize::MAX);
+        let mut recv = ReceiveChannelReliable::new(usize::MAX, true);
+
+        let message_len = SLICE_SIZE * 3;
+        send.send_message(vec![0; message_len].into(), Duration::ZERO).unwrap();
+        send.send_message(vec![1].into(), Duration::ZERO).unwrap();
+        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, Duration::ZERO);
+        assert_eq!(packets.len(), 4);
+
+        // Only the first slice and the small message arrive
+        let mut packets = packets.into_iter();
+        let Some(Packet::ReliableSlice { slice: first_slice, .. }) = packets.next() else {
+            unreachable!();
+        };
+        let late_slice = Slice {
+            slice_index: 1,
+            payload: vec![0; SLICE_SIZE].into(),
+            ..first_slice.clone()
+        };
+        recv.process_slice(first_slice, Duration::ZERO).unwrap();
+        let Some(Packet::SmallReliable { messages, .. }) = packets.last() else {
+            unreachable!();
+        };
+        for (message_id, message) in messages {
+            recv.process_message(message, message_id, Duration::ZERO).unwrap();
+        }
+        assert!(recv.receive_message().is_none());
+
+        assert!(send.cancel_sliced_message(0));
+        assert!(!send.cancel_sliced_message(0));
+        assert!(!send.cancel_sliced_message(1));
+        assert_eq!(send.memory_usage(), 1);
+
+        // The cancel is resent until acked, the slices are not
+        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, resend_time);
+        let [Packet::CancelReliable { message_id: 0, .. }, Packet::SmallReliable { .. }] = &packets[..] else {
+            unreachable!("{packets:?}");
+        };
+        send.process_message_ack(0, resend_time);
+        send.process_slice_message_ack(0, 2, resend_time);
+        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, resend_time * 2);
+        let [Packet::SmallReliable { .. }] = &packets[..] else {
+            unreachable!("{packets:?}");
+        };
+        // The cancel is not reported as an acked message
+        assert!(send.acked_messages().next().is_none());
+
+        recv.process_cancel(0);
+        assert_eq!(recv.sliced_progress(), None);
+        recv.process_slice(late_slice, Duration::ZERO).unwrap();
+        assert_eq!(recv.receive_message().unwrap(), vec![1]);
+        assert!(recv.receive_message().is_none());
+        assert_eq!(recv.memory_usage(), 0);
+    }
+
+    #[test]
+    fn sequenced() {
+        let mut available_bytes = u64::MAX;
+        let mut sequence: u64 = 0;
+        let resend_time = Duration::from_millis(100);
+        let mut send = SendChannelReliable::new_sequenced(0, resend_time, usize::MAX);
+        let mut recv = ReceiveChannelReliable::new_sequenced(usize::MAX);
+
+        send.send_message(vec![0].into(), Duration::ZERO).unwrap();
+        let lost = send.get_packets_to_send(&mut sequence, &mut available_bytes, Duration::ZERO);
+        assert_eq!(lost.len(), 1);
+
+        // The new message supersedes the unacked one, only the new one is resent
+        send.send_message(vec![1].into(), Duration::ZERO).unwrap();
+        assert_eq!(send.memory_usage(), 1);
+        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, resend_time);
+        let [Packet::SmallReliable { messages, .. }] = &packets[..] else {
+            unreachable!();
+        };
+        assert_eq!(messages, &vec![(1, Bytes::from(vec![1]))]);
+        send.process_message_ack(1, resend_time);
+        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, resend_time * 2);
+        assert!(packets.is_empty());
+        assert_eq!(send.memory_usage(), 0);
+
+        // Only the newest unread message is kept, older ones received later are dropped
+        recv.process_message(vec![3].into(), 3, Duration::ZERO).unwrap();
+        recv.process_message(vec![5].into(), 5, Duration::ZERO).unwrap();
+        recv.process_message(vec![4].into(), 4, Duration::ZERO).unwrap();
+        assert_eq!(recv.receive_message().unwrap(), vec![5]);
+        assert!(recv.receive_message().is_none());
+
+        recv.process_message(vec![5].into(), 5, Duration::ZERO).unwrap();
+        assert!(recv.receive_message().is_none());
+        assert_eq!(recv.memory_usage(), 0);
+
+        // A newer message drops the partial older one
+        let slice = |message_id| Slice {
+            message_id,
+            slice_index: 0,
+            num_slices: 2,
+            payload: vec![0; SLICE_SIZE].into(),
+        };
+        recv.process_slice(slice(6), Duration::ZERO).unwrap();
+        recv.process_message(vec![7].into(), 7, Duration::ZERO).unwrap();
+        assert_eq!(recv.memory_usage(), 1);
+        recv.process_slice(slice(6), Duration::ZERO).unwrap();
+        assert_eq!(recv.receive_message().unwrap(), vec![7]);
+        assert_eq!(recv.memory_usage(), 0);
+    }
+
+    // Transmissions of the messages until all are acked, when half of the packets are lost
+    // and the acks take longer than the resend time to arrive
+    fn transmissions_with_loss(max_resend_time: Option<Duration>) -> usize {
+        let resend_time = Duration::from_millis(100);
+        let ack_delay = Duration::from_millis(250);
+        let mut send = SendChannelReliable::new(0, resend_time, 1024 * 1024);
+        if let Some(max_resend_time) = max_resend_time {
+            send.set_max_resend_time(max_resend_time);
+        }
+        for i in 0..20 {
+            send.send_message(vec![i; 500].into(), Duration::ZERO).unwrap();
+        }
+
+        let mut sequence = 0;
+        let mut current_time = Duration::ZERO;
+        let mut transmissions = 0;
+        let mut packets_sent = 0;
+        let mut pending_acks: Vec<(Duration, u64)> = vec![];
+        while !send.unacked_messages.is_empty() {
+            assert!(current_time < Duration::from_secs(60), "messages were not acked");
+            let mut available_bytes = u64::MAX;
+            for packet in send.get_packets_to_send(&mut sequence, &mut available_bytes, current_time) {
+                let Packet::SmallReliable { messages, .. } = packet else {
+                    unreachable!();
+                };
+                transmissions += messages.len();
+                packets_sent += 1;
+                if packets_sent % 2 == 0 {
+                    pending_acks.extend(messages.iter().map(|(message_id, _)| (current_time + ack_delay, *message_id)));
+                }
+            }
+            pending_acks.retain(|&(acked_at, message_id)| {
+                if acked_at > current_time {
+                    return true;
+                }
+                send.process_message_ack(message_id, current_time);
+                false
+            });
+            current_time += Duration::from_millis(16);
+        }
+
+        transmissions
+    }
+
+    #[test]
+    fn resend_backoff() {
+        assert_eq!(
+            SendChannelReliable::resend_interval(Duration::from_millis(100), None, 5),
+            Duration::from_millis(100)
+        );
+        let max_resend_time = Some(Duration::from_secs(1));
+        assert_eq!(
+            SendChannelReliable::resend_interval(Duration::from_millis(100), max_resend_time, 2),
+            Duration::from_millis(400)
+        );
+        assert_eq!(
+            SendChannelReliable::resend_interval(Duration::from_millis(100), max_resend_time, 40),
+            Duration::from_secs(1)
+        );
+
+        let fixed = transmissions_with_loss(None);
+        let backoff = transmissions_with_loss(max_resend_time);
+        assert!(backoff < fixed, "{backoff} transmissions with backoff, {fixed} with fixed resends");
+    }
+
+    #[test]
+    fn head_of_line_blocking() {
+        let mut recv = ReceiveChannelReliable::new(usize::MAX, true);
+        let message = Bytes::from(vec![1, 2, 3]);
+        let ms = Duration::from_millis;
+
+        recv.process_message(message.clone(), 0, ms(0)).unwrap();
+        recv.process_message(message.clone(), 3, ms(10)).unwrap();
+        recv.process_message(message.clone(), 2, ms(20)).unwrap();
+        assert_eq!(recv.head_of_line_blocking(), LatencyStats::default());
+
+        // Message 1 unblocks 2 and 3, waiting since 3 arrived
+        recv.process_message(message.clone(), 1, ms(50)).unwrap();
+        recv.process_message(message.clone(), 5, ms(60)).unwrap();
+        recv.process_message(message.clone(), 4, ms(70)).unwrap();
+        let stats = recv.head_of_line_blocking();
+        assert_eq!(stats.samples, 2);
+        assert_eq!(stats.max, ms(40));
+        assert_eq!(stats.average(), ms(25));
+        assert_eq!(recv.messages.len(), 6);
+
+        for block in 0..HEAD_OF_LINE_SAMPLES as u64 {
+            recv.process_message(message.clone(), 6 + block * 2 + 1, ms(100)).unwrap();
+            recv.process_message(message.clone(), 6 + block * 2, ms(100)).unwrap();
+        }
+        assert_eq!(recv.head_of_line_blocking().samples, HEAD_OF_LINE_SAMPLES as u64);
+
+        recv.reset_head_of_line_blocking();
+        assert_eq!(recv.head_of_line_blocking(), LatencyStats::default());
+
+        let mut unordered = ReceiveChannelReliable::new(usize::MAX, false);
+        unordered.process_message(message.clone(), 1, ms(0)).unwrap();
+        unordered.process_message(message, 0, ms(10)).unwrap();
+        assert_eq!(unordered.head_of_line_blocking(), LatencyStats::default());
+    }
 }
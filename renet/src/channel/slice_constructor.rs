@@ -22,6 +22,10 @@ impl SliceConstructor {
         }
     }
 
+    pub fn num_received_slices(&self) -> usize {
+        self.num_received_slices
+    }
+
     pub fn process_slice(&mut self, slice_index: usize, bytes: &[u8]) -> Result<Option<Bytes>, ChannelError> {
         let is_last_slice = slice_index == self.num_slices - 1;
         if is_last_slice {
@@ -73,3 +77,34 @@ impl SliceConstructor {
         Ok(None)
     }
 }
+
+// Smallest message that is sliced in the number of slices, only its last slice can be partial
+pub fn min_message_len(num_slices: usize) -> usize {
+    num_slices.saturating_sub(1) * SLICE_SIZE + 1
+}
+
+// Progress of the slice constructors of a receive channel
+pub fn sliced_progress<'a>(slices: impl Iterator<Item = &'a SliceConstructor>) -> Option<(usize, usize)> {
+    slices.fold(None, |progress, slices| {
+        let (received, total) = progress.unwrap_or((0, 0));
+        Some((received + slices.num_received_slices(), total + slices.num_slices))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assembled_message_is_not_copied() {
+        let mut constructor = SliceConstructor::new(0, 2);
+        let data = constructor.sliced_data.as_ptr();
+        assert!(constructor.process_slice(1, &[2; 10]).unwrap().is_none());
+        let message = constructor.process_slice(0, &[1; SLICE_SIZE]).unwrap().unwrap();
+
+        // The message is returned in the buffer the slices were copied to
+        assert_eq!(message.as_ptr(), data);
+        assert_eq!(message.len(), SLICE_SIZE + 10);
+        assert_eq!(message[SLICE_SIZE], 2);
+    }
+}
@@ -0,0 +1,67 @@
+/// Size of the trace id carried by the messages of the channels with [`ChannelConfig::tracing_context`][crate::ChannelConfig::tracing_context].
+pub const TRACE_ID_BYTES: usize = 16;
+
+// Finds the trace id of the messages queued in a channel with tracing context, to record their events.
+// The trace id follows the header of the channel, if any, and the group header for grouped channels.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(not(feature = "tracing"), allow(dead_code))]
+pub(crate) struct MessageTracer {
+    channel_id: u8,
+    header_bytes: usize,
+    grouped: bool,
+}
+
+impl MessageTracer {
+    pub fn new(channel_id: u8, header_bytes: usize, grouped: bool) -> Self {
+        Self {
+            channel_id,
+            header_bytes,
+            grouped,
+        }
+    }
+
+    // Records a tracing event for the message, does nothing without the tracing feature
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub fn record(&self, event: &str, message: &[u8]) {
+        #[cfg(feature = "tracing")]
+        if let Some(trace_id) = self.trace_id(message) {
+            tracing::trace!(channel_id = self.channel_id, trace_id = %format_trace_id(&trace_id), "{event}");
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    fn trace_id(&self, message: &[u8]) -> Option<[u8; TRACE_ID_BYTES]> {
+        let message = message.get(self.header_bytes..)?;
+        let mut start = 0;
+        if self.grouped {
+            let mut octets = octets::Octets::with_slice(message);
+            octets.get_varint().ok()?;
+            start = octets.off();
+        }
+
+        message.get(start..start + TRACE_ID_BYTES)?.try_into().ok()
+    }
+}
+
+#[cfg(feature = "tracing")]
+fn format_trace_id(trace_id: &[u8; TRACE_ID_BYTES]) -> String {
+    trace_id.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trace_id_after_headers() {
+        let trace_id = [7u8; TRACE_ID_BYTES];
+        let mut message = vec![0xAA, 0xBB, 0x05];
+        message.extend_from_slice(&trace_id);
+        message.extend_from_slice(b"payload");
+
+        assert_eq!(MessageTracer::new(0, 2, true).trace_id(&message), Some(trace_id));
+        assert_eq!(MessageTracer::new(0, 3, false).trace_id(&message), Some(trace_id));
+        assert_eq!(MessageTracer::new(0, 3, false).trace_id(&message[..10]), None);
+        assert_eq!(format_trace_id(&[0xab; TRACE_ID_BYTES]), "ab".repeat(TRACE_ID_BYTES));
+    }
+}
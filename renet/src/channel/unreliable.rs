@@ -1,33 +1,112 @@
 use std::{
-    collections::{BTreeMap, VecDeque},
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
     time::Duration,
 };
 
-use bytes::Bytes;
+use bytes::{BufMut, Bytes, BytesMut};
 
 use crate::{
-    channel::SliceConstructor,
+    channel::{
+        slice_constructor, trace::MessageTracer, ChannelCapacity, ChannelLatency, MessageDelivery, MessageId, ReceivePolicy,
+        SliceConstructor,
+    },
     error::ChannelError,
-    packet::{Packet, Slice, SLICE_SIZE},
+    packet::{is_sliced, num_slices, small_message_size, Packet, Slice, SLICE_SIZE},
 };
 
 #[derive(Debug)]
 pub struct SendChannelUnreliable {
     channel_id: u8,
-    unreliable_messages: VecDeque<Bytes>,
+    // Messages waiting to be sent with the time they were queued and their id
+    unreliable_messages: VecDeque<(Bytes, Duration, u64)>,
+    next_message_id: u64,
     sliced_message_id: u64,
     max_memory_usage_bytes: usize,
     memory_usage_bytes: usize,
+    paused: bool,
+    // Messages dropped for not fitting in the available bytes since the last call to take_over_budget_drops
+    over_budget_drops: u64,
+    // Messages older than this are dropped instead of sent
+    message_ttl: Option<Duration>,
+    // Messages wait this long after the first of them, unless they fill a packet
+    coalesce_delay: Option<Duration>,
+    // The channel is full when holding this many queued messages
+    max_queued_messages: Option<usize>,
+    // A full channel drops its oldest messages to make room for the new one, instead of dropping the new one
+    drop_oldest: bool,
+    // Messages dropped to make room for a new one since the last call to take_evicted_drops
+    evicted_drops: u64,
+    // Messages dropped for exceeding the ttl since the last call to take_expired_drops
+    expired_drops: u64,
+    latency: ChannelLatency,
+    // Next sequence number, for sequenced channels
+    sequence: Option<u16>,
+    // Records the events of the messages, for channels with tracing context
+    tracer: Option<MessageTracer>,
+    // Tracks the packets of the messages until acked or lost, for channels with delivery feedback
+    delivery_feedback: bool,
+    // Messages carried by each packet in flight
+    packet_messages: HashMap<u64, Vec<u64>>,
+    // Packets left to ack of each message in flight
+    unacked_packets: HashMap<u64, usize>,
+    // Outcomes of the messages since the last clear, and of the messages dropped since then
+    deliveries: Vec<MessageDelivery>,
+    dropped_messages: Vec<u64>,
 }
 
 #[derive(Debug)]
 pub struct ReceiveChannelUnreliable {
-    channel_id: u8,
-    messages: VecDeque<Bytes>,
+    // Received messages with their receive time
+    messages: VecDeque<(Bytes, Duration)>,
     slices: BTreeMap<u64, SliceConstructor>,
     slices_last_received: BTreeMap<u64, Duration>,
     max_memory_usage_bytes: usize,
+    max_message_size: usize,
+    // Slices of the messages with more slices are ignored
+    max_slices: usize,
+    // Incomplete sliced messages without a slice received for this long are dropped
+    reassembly_timeout: Duration,
     memory_usage_bytes: usize,
+    // Messages dropped for exceeding the memory limit since the last call to take_full_drops
+    full_drops: u64,
+    sequenced: bool,
+    // Sequence number of the last message read, for sequenced channels
+    last_read_sequence: Option<u16>,
+    // Sequence numbers of the last messages read, for deduplicated channels
+    deduplicate: Option<DeduplicateWindow>,
+}
+
+#[derive(Debug)]
+struct DeduplicateWindow {
+    size: usize,
+    order: VecDeque<u16>,
+    sequences: HashSet<u16>,
+}
+
+impl DeduplicateWindow {
+    // Returns false for a sequence already in the window. Sequences leave the window before wrapping around,
+    // the window is smaller than the sequence space.
+    fn insert(&mut self, sequence: u16) -> bool {
+        if !self.sequences.insert(sequence) {
+            return false;
+        }
+        self.order.push_back(sequence);
+        if self.order.len() > self.size {
+            let oldest = self.order.pop_front().unwrap();
+            self.sequences.remove(&oldest);
+        }
+        true
+    }
+}
+
+// Size of the sequence number prefixed to the messages of sequenced channels
+pub(crate) const SEQUENCE_HEADER_BYTES: usize = 2;
+
+const DEFAULT_REASSEMBLY_TIMEOUT: Duration = Duration::from_secs(3);
+
+// Returns true if s1 is more recent than s2, handling the wraparound
+fn sequence_greater_than(s1: u16, s2: u16) -> bool {
+    ((s1 > s2) && (s1 - s2 <= 32768)) || ((s1 < s2) && (s2 - s1 > 32768))
 }
 
 impl SendChannelUnreliable {
@@ -35,35 +114,196 @@ impl SendChannelUnreliable {
         Self {
             channel_id,
             unreliable_messages: VecDeque::new(),
+            next_message_id: 0,
             sliced_message_id: 0,
             max_memory_usage_bytes,
             memory_usage_bytes: 0,
+            paused: false,
+            over_budget_drops: 0,
+            message_ttl: None,
+            coalesce_delay: None,
+            max_queued_messages: None,
+            drop_oldest: false,
+            evicted_drops: 0,
+            expired_drops: 0,
+            latency: ChannelLatency::default(),
+            sequence: None,
+            tracer: None,
+            delivery_feedback: false,
+            packet_messages: HashMap::new(),
+            unacked_packets: HashMap::new(),
+            deliveries: Vec::new(),
+            dropped_messages: Vec::new(),
         }
     }
 
+    pub fn new_sequenced(channel_id: u8, max_memory_usage_bytes: usize) -> Self {
+        Self {
+            sequence: Some(0),
+            ..Self::new(channel_id, max_memory_usage_bytes)
+        }
+    }
+
+    // Next sequence number of a sequenced channel, does nothing for the other channels
+    pub fn set_sequence(&mut self, sequence: u16) {
+        if let Some(next_sequence) = self.sequence.as_mut() {
+            *next_sequence = sequence;
+        }
+    }
+
+    pub fn set_tracer(&mut self, tracer: MessageTracer) {
+        self.tracer = Some(tracer);
+    }
+
+    pub fn set_message_ttl(&mut self, message_ttl: Duration) {
+        self.message_ttl = Some(message_ttl);
+    }
+
+    pub fn set_max_queued_messages(&mut self, max_queued_messages: usize) {
+        self.max_queued_messages = Some(max_queued_messages);
+    }
+
+    pub fn set_drop_oldest(&mut self) {
+        self.drop_oldest = true;
+    }
+
+    fn is_queue_full(&self) -> bool {
+        self.max_queued_messages.is_some_and(|max| self.unreliable_messages.len() >= max)
+    }
+
+    pub fn set_coalesce_delay(&mut self, coalesce_delay: Duration) {
+        self.coalesce_delay = Some(coalesce_delay);
+    }
+
+    // Returns true while the queued messages are held back by the coalesce delay
+    fn is_coalescing(&self, current_time: Duration) -> bool {
+        let (Some(coalesce_delay), Some(&(_, queued_at, _))) = (self.coalesce_delay, self.unreliable_messages.front()) else {
+            return false;
+        };
+
+        self.memory_usage_bytes < SLICE_SIZE && current_time.saturating_sub(queued_at) < coalesce_delay
+    }
+
+    pub fn set_delivery_feedback(&mut self) {
+        self.delivery_feedback = true;
+    }
+
+    pub fn has_delivery_feedback(&self) -> bool {
+        self.delivery_feedback
+    }
+
+    pub fn latency(&self) -> ChannelLatency {
+        self.latency
+    }
+
+    pub fn reset_latency(&mut self) {
+        self.latency = ChannelLatency::default();
+    }
+
+    /// A paused channel keeps queuing messages, up to its memory limit, but sends nothing until resumed.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
     pub fn can_send_message(&self, size_bytes: usize) -> bool {
-        size_bytes + self.memory_usage_bytes <= self.max_memory_usage_bytes
+        if self.drop_oldest {
+            // The oldest messages are dropped to make room, the message only needs to fit in the empty channel
+            return self.max_queued_messages != Some(0) && size_bytes + self.header_bytes() <= self.max_memory_usage_bytes;
+        }
+
+        !self.is_queue_full() && size_bytes <= self.capacity().max_message_bytes
+    }
+
+    fn header_bytes(&self) -> usize {
+        if self.sequence.is_some() {
+            SEQUENCE_HEADER_BYTES
+        } else {
+            0
+        }
+    }
+
+    pub fn capacity(&self) -> ChannelCapacity {
+        let header_bytes = self.header_bytes();
+        let bytes_free = self.max_memory_usage_bytes - self.memory_usage_bytes;
+        ChannelCapacity {
+            bytes_free,
+            bytes_queued: self.memory_usage_bytes,
+            bytes_unacked: 0,
+            max_message_bytes: if self.is_queue_full() { 0 } else { bytes_free.saturating_sub(header_bytes) },
+        }
     }
 
     pub fn available_memory(&self) -> usize {
         self.max_memory_usage_bytes - self.memory_usage_bytes
     }
 
-    pub fn get_packets_to_send(&mut self, packet_sequence: &mut u64, available_bytes: &mut u64) -> Vec<Packet> {
+    pub fn memory_usage(&self) -> usize {
+        self.memory_usage_bytes
+    }
+
+    pub fn get_packets_to_send(&mut self, packet_sequence: &mut u64, available_bytes: &mut u64, current_time: Duration) -> Vec<Packet> {
+        self.take_packets(packet_sequence, available_bytes, current_time, true)
+    }
+
+    /// Same as `get_packets_to_send`, but stops at the first message that doesn't fit in the available bytes
+    /// instead of dropping it, the remaining messages can still be sent later in the tick.
+    pub fn get_packets_within_budget(
+        &mut self,
+        packet_sequence: &mut u64,
+        available_bytes: &mut u64,
+        current_time: Duration,
+    ) -> Vec<Packet> {
+        self.take_packets(packet_sequence, available_bytes, current_time, false)
+    }
+
+    fn take_packets(
+        &mut self,
+        packet_sequence: &mut u64,
+        available_bytes: &mut u64,
+        current_time: Duration,
+        drop_over_budget: bool,
+    ) -> Vec<Packet> {
+        if self.paused || self.is_coalescing(current_time) {
+            return vec![];
+        }
+
         let mut packets: Vec<Packet> = vec![];
         let mut small_messages: Vec<Bytes> = vec![];
+        let mut small_message_ids: Vec<u64> = vec![];
         let mut small_messages_bytes = 0;
 
-        while let Some(message) = self.unreliable_messages.pop_front() {
+        while let Some((message, queued_at, message_id)) = self.unreliable_messages.pop_front() {
+            if self.message_ttl.is_some_and(|ttl| current_time.saturating_sub(queued_at) > ttl) {
+                self.memory_usage_bytes -= message.len();
+                self.expired_drops += 1;
+                self.message_dropped(message_id);
+                continue;
+            }
+
+            if *available_bytes < message.len() as u64 && !drop_over_budget {
+                self.unreliable_messages.push_front((message, queued_at, message_id));
+                break;
+            }
+
             self.memory_usage_bytes -= message.len();
             if *available_bytes < message.len() as u64 {
                 // Drop message, no available bytes to send
+                self.over_budget_drops += 1;
+                self.message_dropped(message_id);
                 continue;
             }
 
             *available_bytes -= message.len() as u64;
-            if message.len() > SLICE_SIZE {
-                let num_slices = (message.len() + SLICE_SIZE - 1) / SLICE_SIZE;
+            self.latency.queue.record(current_time.saturating_sub(queued_at));
+            if let Some(tracer) = &self.tracer {
+                tracer.record("message packed", &message);
+            }
+            if is_sliced(message.len()) {
+                let num_slices = num_slices(message.len());
 
                 for slice_index in 0..num_slices {
                     let start = slice_index * SLICE_SIZE;
@@ -82,24 +322,29 @@ impl SendChannelUnreliable {
                         channel_id: self.channel_id,
                         slice,
                     });
+                    self.packet_sent(*packet_sequence, vec![message_id]);
                     *packet_sequence += 1;
                 }
 
+                self.message_sent(message_id, num_slices);
                 self.sliced_message_id += 1;
             } else {
-                let serialized_size = message.len() + octets::varint_len(message.len() as u64);
-                if small_messages_bytes + serialized_size > SLICE_SIZE {
+                let serialized_size = small_message_size(message.len(), None);
+                if !small_messages.is_empty() && small_messages_bytes + serialized_size > SLICE_SIZE {
                     packets.push(Packet::SmallUnreliable {
                         sequence: *packet_sequence,
                         channel_id: self.channel_id,
                         messages: std::mem::take(&mut small_messages),
                     });
+                    self.packet_sent(*packet_sequence, std::mem::take(&mut small_message_ids));
                     *packet_sequence += 1;
                     small_messages_bytes = 0;
                 }
 
                 small_messages_bytes += serialized_size;
                 small_messages.push(message);
+                small_message_ids.push(message_id);
+                self.message_sent(message_id, 1);
             }
         }
 
@@ -110,59 +355,211 @@ impl SendChannelUnreliable {
                 channel_id: self.channel_id,
                 messages: std::mem::take(&mut small_messages),
             });
+            self.packet_sent(*packet_sequence, small_message_ids);
             *packet_sequence += 1;
         }
 
         packets
     }
 
-    pub fn send_message(&mut self, message: Bytes) {
-        if self.memory_usage_bytes + message.len() > self.max_memory_usage_bytes {
-            log::warn!(
-                "dropped unreliable message sent because channel {} is memory limited",
-                self.channel_id
-            );
+    fn message_sent(&mut self, message_id: u64, num_packets: usize) {
+        if self.delivery_feedback {
+            self.unacked_packets.insert(message_id, num_packets);
+        }
+    }
+
+    fn packet_sent(&mut self, packet_sequence: u64, message_ids: Vec<u64>) {
+        if self.delivery_feedback {
+            self.packet_messages.insert(packet_sequence, message_ids);
+        }
+    }
+
+    fn message_dropped(&mut self, message_id: u64) {
+        if self.delivery_feedback {
+            self.dropped_messages.push(message_id);
+        }
+    }
+
+    /// A message is delivered once all of its packets are acked.
+    pub fn process_packet_ack(&mut self, packet_sequence: u64) {
+        let Some(message_ids) = self.packet_messages.remove(&packet_sequence) else {
+            return;
+        };
+
+        for message_id in message_ids {
+            if let Some(unacked_packets) = self.unacked_packets.get_mut(&message_id) {
+                *unacked_packets -= 1;
+                if *unacked_packets == 0 {
+                    self.unacked_packets.remove(&message_id);
+                    self.deliveries.push(MessageDelivery::Delivered(MessageId::from_raw(message_id)));
+                }
+            }
+        }
+    }
+
+    /// A message is lost with the first of its packets, the acks of its other packets are ignored.
+    pub fn process_packet_lost(&mut self, packet_sequence: u64) {
+        let Some(message_ids) = self.packet_messages.remove(&packet_sequence) else {
             return;
+        };
+
+        for message_id in message_ids {
+            if self.unacked_packets.remove(&message_id).is_some() {
+                self.deliveries.push(MessageDelivery::Lost(MessageId::from_raw(message_id)));
+            }
+        }
+    }
+
+    pub fn deliveries(&mut self) -> std::vec::Drain<'_, MessageDelivery> {
+        self.deliveries.drain(..)
+    }
+
+    /// Clears the outcomes, the messages dropped since the last clear are reported as lost from now on.
+    pub fn clear_deliveries(&mut self) {
+        self.deliveries.clear();
+        let dropped = self.dropped_messages.drain(..);
+        self.deliveries
+            .extend(dropped.map(|message_id| MessageDelivery::Lost(MessageId::from_raw(message_id))));
+    }
+
+    /// Drops the messages waiting to be sent, returns how many were dropped.
+    pub fn clear_messages(&mut self) -> u64 {
+        if self.delivery_feedback {
+            let message_ids = self.unreliable_messages.iter().map(|&(_, _, message_id)| message_id);
+            self.dropped_messages.extend(message_ids);
+        }
+        let dropped = self.unreliable_messages.len() as u64;
+        self.unreliable_messages.clear();
+        self.memory_usage_bytes = 0;
+        dropped
+    }
+
+    pub fn take_over_budget_drops(&mut self) -> u64 {
+        std::mem::take(&mut self.over_budget_drops)
+    }
+
+    pub fn take_evicted_drops(&mut self) -> u64 {
+        std::mem::take(&mut self.evicted_drops)
+    }
+
+    pub fn take_expired_drops(&mut self) -> u64 {
+        std::mem::take(&mut self.expired_drops)
+    }
+
+    // Returns the id of the message, None if it was dropped
+    pub fn send_message(&mut self, message: Bytes, current_time: Duration) -> Option<u64> {
+        let message = match self.sequence.as_mut() {
+            Some(sequence) => {
+                let mut sequenced = BytesMut::with_capacity(SEQUENCE_HEADER_BYTES + message.len());
+                sequenced.put_u16_le(*sequence);
+                sequenced.put_slice(&message);
+                *sequence = sequence.wrapping_add(1);
+                sequenced.freeze()
+            }
+            None => message,
+        };
+        if self.drop_oldest && message.len() <= self.max_memory_usage_bytes {
+            while self.memory_usage_bytes + message.len() > self.max_memory_usage_bytes || self.is_queue_full() {
+                let Some((evicted, _, evicted_id)) = self.unreliable_messages.pop_front() else {
+                    break;
+                };
+                self.memory_usage_bytes -= evicted.len();
+                self.evicted_drops += 1;
+                self.message_dropped(evicted_id);
+            }
+        }
+        if self.memory_usage_bytes + message.len() > self.max_memory_usage_bytes || self.is_queue_full() {
+            return None;
         }
 
+        let message_id = self.next_message_id;
+        self.next_message_id += 1;
         self.memory_usage_bytes += message.len();
-        self.unreliable_messages.push_back(message);
+        self.unreliable_messages.push_back((message, current_time, message_id));
+        Some(message_id)
     }
 }
 
 impl ReceiveChannelUnreliable {
-    pub fn new(channel_id: u8, max_memory_usage_bytes: usize) -> Self {
+    pub fn new(max_memory_usage_bytes: usize) -> Self {
         Self {
-            channel_id,
             slices: BTreeMap::new(),
             slices_last_received: BTreeMap::new(),
             messages: VecDeque::new(),
             memory_usage_bytes: 0,
             max_memory_usage_bytes,
+            full_drops: 0,
+            sequenced: false,
+            last_read_sequence: None,
+            max_message_size: usize::MAX,
+            max_slices: usize::MAX,
+            reassembly_timeout: DEFAULT_REASSEMBLY_TIMEOUT,
+            deduplicate: None,
         }
     }
 
-    pub fn process_message(&mut self, message: Bytes) {
+    // Messages above the size are refused, their slices before being allocated
+    pub fn set_max_message_size(&mut self, max_message_size: usize) {
+        self.max_message_size = max_message_size;
+    }
+
+    pub fn set_max_slices(&mut self, max_slices: usize) {
+        self.max_slices = max_slices;
+    }
+
+    pub fn set_reassembly_timeout(&mut self, reassembly_timeout: Duration) {
+        self.reassembly_timeout = reassembly_timeout;
+    }
+
+    // Messages start with their sequence number, the ones read within the last `window` messages are dropped
+    pub fn set_deduplicate(&mut self, window: u16) {
+        self.deduplicate = Some(DeduplicateWindow {
+            size: window as usize,
+            order: VecDeque::new(),
+            sequences: HashSet::new(),
+        });
+    }
+
+    pub fn new_sequenced(max_memory_usage_bytes: usize) -> Self {
+        Self {
+            sequenced: true,
+            ..Self::new(max_memory_usage_bytes)
+        }
+    }
+
+    pub fn memory_usage(&self) -> usize {
+        self.memory_usage_bytes
+    }
+
+    pub fn take_full_drops(&mut self) -> u64 {
+        std::mem::take(&mut self.full_drops)
+    }
+
+    pub fn process_message(&mut self, message: Bytes, current_time: Duration) -> Result<(), ChannelError> {
+        if message.len() > self.max_message_size {
+            return Err(ChannelError::ReceivedMessageAboveLimit);
+        }
         if self.memory_usage_bytes + message.len() > self.max_memory_usage_bytes {
-            log::warn!(
-                "dropped unreliable message received because channel {} is memory limited",
-                self.channel_id
-            );
-            return;
+            self.full_drops += 1;
+            return Ok(());
         }
 
         self.memory_usage_bytes += message.len();
-        self.messages.push_back(message);
+        self.messages.push_back((message, current_time));
+        Ok(())
     }
 
     pub fn process_slice(&mut self, slice: Slice, current_time: Duration) -> Result<(), ChannelError> {
+        if slice.num_slices > self.max_slices {
+            return Ok(());
+        }
         if !self.slices.contains_key(&slice.message_id) {
+            if slice_constructor::min_message_len(slice.num_slices) > self.max_message_size {
+                return Err(ChannelError::ReceivedMessageAboveLimit);
+            }
             let message_len = slice.num_slices * SLICE_SIZE;
             if self.memory_usage_bytes + message_len > self.max_memory_usage_bytes {
-                log::warn!(
-                    "dropped unreliable slice message received because channel {} is memory limited",
-                    self.channel_id
-                );
+                self.full_drops += 1;
                 return Ok(());
             }
 
@@ -175,11 +572,14 @@ impl ReceiveChannelUnreliable {
             .or_insert_with(|| SliceConstructor::new(slice.message_id, slice.num_slices));
 
         if let Some(message) = slice_constructor.process_slice(slice.slice_index, &slice.payload)? {
+            if message.len() > self.max_message_size {
+                return Err(ChannelError::ReceivedMessageAboveLimit);
+            }
             self.slices.remove(&slice.message_id);
             self.slices_last_received.remove(&slice.message_id);
             self.memory_usage_bytes -= slice.num_slices * SLICE_SIZE;
             self.memory_usage_bytes += message.len();
-            self.messages.push_back(message);
+            self.messages.push_back((message, current_time));
         } else {
             self.slices_last_received.insert(slice.message_id, current_time);
         }
@@ -187,11 +587,15 @@ impl ReceiveChannelUnreliable {
         Ok(())
     }
 
+    // Received and total slices of the sliced messages being assembled, None without any
+    pub fn sliced_progress(&self) -> Option<(usize, usize)> {
+        slice_constructor::sliced_progress(self.slices.values())
+    }
+
     pub fn discard_incomplete_old_slices(&mut self, current_time: Duration) {
         let mut lost_messages: Vec<u64> = Vec::new();
         for (&message_id, last_received) in self.slices_last_received.iter() {
-            const DISCARD_AFTER: Duration = Duration::from_secs(3);
-            if current_time - *last_received >= DISCARD_AFTER {
+            if current_time.saturating_sub(*last_received) >= self.reassembly_timeout {
                 lost_messages.push(message_id);
             } else {
                 // If the current message is not discard, the next ones will not be discarded
@@ -208,13 +612,50 @@ impl ReceiveChannelUnreliable {
     }
 
     pub fn receive_message(&mut self) -> Option<Bytes> {
-        if let Some(message) = self.messages.pop_front() {
+        while let Some((message, _)) = self.messages.pop_front() {
             self.memory_usage_bytes -= message.len();
-            return Some(message);
-        };
+            if !self.sequenced && self.deduplicate.is_none() {
+                return Some(message);
+            }
+
+            if message.len() < SEQUENCE_HEADER_BYTES {
+                continue;
+            }
+            let sequence = u16::from_le_bytes([message[0], message[1]]);
+            if let Some(deduplicate) = self.deduplicate.as_mut() {
+                // Repeats of a message already read are dropped
+                if !deduplicate.insert(sequence) {
+                    continue;
+                }
+                return Some(message.slice(SEQUENCE_HEADER_BYTES..));
+            }
+
+            // Messages older than the last one read are dropped
+            if let Some(last_read_sequence) = self.last_read_sequence {
+                if !sequence_greater_than(sequence, last_read_sequence) {
+                    continue;
+                }
+            }
+            self.last_read_sequence = Some(sequence);
+            return Some(message.slice(SEQUENCE_HEADER_BYTES..));
+        }
 
         None
     }
+
+    // Drops the unread messages expired by the policy, returns the number of dropped messages.
+    pub fn apply_receive_policy(&mut self, policy: ReceivePolicy, current_time: Duration) -> u64 {
+        let mut dropped = 0;
+        while let Some(&(_, received_at)) = self.messages.front() {
+            if !policy.expires(self.messages.len(), received_at, current_time) {
+                break;
+            }
+            self.receive_message();
+            dropped += 1;
+        }
+
+        dropped
+    }
 }
 
 #[cfg(test)]
@@ -228,22 +669,22 @@ mod tests {
         let max_memory: usize = 10000;
         let mut available_bytes = u64::MAX;
         let mut sequence: u64 = 0;
-        let mut recv = ReceiveChannelUnreliable::new(0, max_memory);
+        let mut recv = ReceiveChannelUnreliable::new(max_memory);
         let mut send = SendChannelUnreliable::new(0, max_memory);
 
         let message1 = vec![1, 2, 3];
         let message2 = vec![3, 4, 5];
 
-        send.send_message(message1.clone().into());
-        send.send_message(message2.clone().into());
+        send.send_message(message1.clone().into(), Duration::ZERO);
+        send.send_message(message2.clone().into(), Duration::ZERO);
 
-        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes);
+        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, Duration::ZERO);
         for packet in packets {
             let Packet::SmallUnreliable { messages, .. } = packet else {
                 unreachable!();
             };
             for message in messages {
-                recv.process_message(message);
+                recv.process_message(message, Duration::ZERO).unwrap();
             }
         }
 
@@ -254,7 +695,7 @@ mod tests {
         assert_eq!(message1, new_message1);
         assert_eq!(message2, new_message2);
 
-        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes);
+        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, Duration::ZERO);
         assert!(packets.is_empty());
     }
 
@@ -264,14 +705,14 @@ mod tests {
         let mut available_bytes = u64::MAX;
         let mut sequence: u64 = 0;
         let current_time = Duration::ZERO;
-        let mut recv = ReceiveChannelUnreliable::new(0, max_memory);
+        let mut recv = ReceiveChannelUnreliable::new(max_memory);
         let mut send = SendChannelUnreliable::new(0, max_memory);
 
         let message = vec![5; SLICE_SIZE * 3];
 
-        send.send_message(message.clone().into());
+        send.send_message(message.clone().into(), Duration::ZERO);
 
-        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes);
+        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, Duration::ZERO);
         for packet in packets {
             let Packet::UnreliableSlice { slice, .. } = packet else {
                 unreachable!();
@@ -284,7 +725,7 @@ mod tests {
 
         assert_eq!(message, new_message);
 
-        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes);
+        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, Duration::ZERO);
         assert!(packets.is_empty());
     }
 
@@ -292,15 +733,15 @@ mod tests {
     fn max_memory() {
         let mut sequence: u64 = 0;
         let mut available_bytes = u64::MAX;
-        let mut recv = ReceiveChannelUnreliable::new(0, 50);
+        let mut recv = ReceiveChannelUnreliable::new(50);
         let mut send = SendChannelUnreliable::new(0, 40);
 
         let message = vec![5; 50];
 
-        send.send_message(message.clone().into());
-        send.send_message(message.into());
+        send.send_message(message.clone().into(), Duration::ZERO);
+        send.send_message(message.into(), Duration::ZERO);
 
-        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes);
+        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, Duration::ZERO);
         for packet in packets {
             let Packet::SmallUnreliable { messages, .. } = packet else {
                 unreachable!();
@@ -309,12 +750,153 @@ mod tests {
             // Second message was dropped
             assert_eq!(messages.len(), 1);
             for message in messages {
-                recv.process_message(message);
+                recv.process_message(message, Duration::ZERO).unwrap();
             }
         }
 
         // The processed message was dropped because there was no memory available
         assert!(recv.receive_message().is_none());
+
+        recv.process_message(vec![0; 51].into(), Duration::ZERO).unwrap();
+        assert!(recv.receive_message().is_none());
+        assert_eq!(recv.take_full_drops(), 1);
+        assert_eq!(recv.take_full_drops(), 0);
+    }
+
+    #[test]
+    fn messages_within_budget() {
+        let mut sequence: u64 = 0;
+        let mut send = SendChannelUnreliable::new(0, usize::MAX);
+        for _ in 0..3 {
+            send.send_message(vec![0; 100].into(), Duration::ZERO);
+        }
+
+        // Messages that don't fit are kept for later in the tick
+        let mut share = 250;
+        let packets = send.get_packets_within_budget(&mut sequence, &mut share, Duration::ZERO);
+        assert_eq!(packets.len(), 1);
+        assert_eq!(share, 50);
+        assert_eq!(send.memory_usage(), 100);
+        assert_eq!(send.take_over_budget_drops(), 0);
+
+        let mut available_bytes = 0;
+        assert!(send
+            .get_packets_to_send(&mut sequence, &mut available_bytes, Duration::ZERO)
+            .is_empty());
+        assert_eq!(send.memory_usage(), 0);
+        assert_eq!(send.take_over_budget_drops(), 1);
+    }
+
+    #[test]
+    fn message_ttl() {
+        let mut sequence: u64 = 0;
+        let mut send = SendChannelUnreliable::new(0, usize::MAX);
+        send.set_message_ttl(Duration::from_millis(50));
+
+        // A burst queued faster than the budget allows, one message is sent each tick
+        for index in 0..10u8 {
+            send.send_message(vec![index; 100].into(), Duration::ZERO);
+        }
+
+        let tick = Duration::from_millis(16);
+        let mut sent = vec![];
+        for step in 0..10 {
+            let mut available_bytes = 150;
+            for packet in send.get_packets_within_budget(&mut sequence, &mut available_bytes, tick * step) {
+                let Packet::SmallUnreliable { messages, .. } = packet else {
+                    unreachable!();
+                };
+                sent.extend(messages.iter().map(|message| message[0]));
+            }
+        }
+
+        // Messages still queued after 50ms are never sent
+        assert_eq!(sent, vec![0, 1, 2, 3]);
+        assert_eq!(send.take_expired_drops(), 6);
+        assert_eq!(send.memory_usage(), 0);
+        assert_eq!(send.take_expired_drops(), 0);
+    }
+
+    #[test]
+    fn receive_policy() {
+        let mut recv = ReceiveChannelUnreliable::new(usize::MAX);
+        for i in 0..5u8 {
+            recv.process_message(vec![i].into(), Duration::from_secs(i as u64)).unwrap();
+        }
+        let current_time = Duration::from_secs(5);
+        assert_eq!(recv.apply_receive_policy(ReceivePolicy::Unbounded, current_time), 0);
+
+        let drop_oldest = ReceivePolicy::DropOldest { max_messages: 3 };
+        assert_eq!(recv.apply_receive_policy(drop_oldest, current_time), 2);
+        assert_eq!(recv.memory_usage(), 3);
+
+        // Messages received at 2s and 3s are older than the max age
+        let warn_and_drop = ReceivePolicy::WarnAndDrop {
+            max_age: Duration::from_millis(1500),
+        };
+        assert_eq!(recv.apply_receive_policy(warn_and_drop, current_time), 2);
+        assert_eq!(recv.receive_message().unwrap(), vec![4]);
+        assert!(recv.receive_message().is_none());
+    }
+
+    #[test]
+    fn sequenced_reordered_delivery() {
+        let mut sequence: u64 = 0;
+        let mut available_bytes = u64::MAX;
+        let mut recv = ReceiveChannelUnreliable::new_sequenced(usize::MAX);
+        let mut send = SendChannelUnreliable::new_sequenced(0, usize::MAX);
+
+        let mut packets = vec![];
+        for i in 0..20u8 {
+            send.send_message(vec![i].into(), Duration::ZERO);
+            packets.append(&mut send.get_packets_to_send(&mut sequence, &mut available_bytes, Duration::ZERO));
+        }
+
+        // Every third packet is lost and the others arrive in swapped pairs, the messages are read as they arrive
+        let mut received = vec![];
+        for pair in packets.chunks(2) {
+            for packet in pair.iter().rev() {
+                let Packet::SmallUnreliable { sequence, messages, .. } = packet else {
+                    unreachable!();
+                };
+                if sequence % 3 == 0 {
+                    continue;
+                }
+                for message in messages {
+                    recv.process_message(message.clone(), Duration::ZERO).unwrap();
+                }
+                while let Some(message) = recv.receive_message() {
+                    received.push(message[0]);
+                }
+            }
+        }
+
+        assert!(received.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(received, vec![1, 2, 5, 7, 8, 11, 13, 14, 17, 19]);
+        assert_eq!(recv.memory_usage(), 0);
+    }
+
+    #[test]
+    fn sequence_wraparound() {
+        let mut recv = ReceiveChannelUnreliable::new_sequenced(usize::MAX);
+        let mut send = SendChannelUnreliable::new_sequenced(0, usize::MAX);
+        send.sequence = Some(u16::MAX - 1);
+
+        let messages: Vec<Bytes> = (0..4u8)
+            .map(|i| {
+                send.send_message(vec![i].into(), Duration::ZERO);
+                send.unreliable_messages.pop_back().unwrap().0
+            })
+            .collect();
+
+        // Messages sent after the wraparound are newer
+        recv.process_message(messages[1].clone(), Duration::ZERO).unwrap();
+        recv.process_message(messages[3].clone(), Duration::ZERO).unwrap();
+        recv.process_message(messages[0].clone(), Duration::ZERO).unwrap();
+        recv.process_message(messages[2].clone(), Duration::ZERO).unwrap();
+        assert_eq!(recv.receive_message().unwrap(), vec![1]);
+        assert_eq!(recv.receive_message().unwrap(), vec![3]);
+        assert!(recv.receive_message().is_none());
     }
 
     #[test]
@@ -323,29 +905,29 @@ mod tests {
         let mut send = SendChannelUnreliable::new(0, usize::MAX);
 
         let message: Bytes = vec![0u8; 100].into();
-        send.send_message(message.clone());
+        send.send_message(message.clone(), Duration::ZERO);
 
         // No available bytes
         let mut available_bytes: u64 = 50;
-        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes);
+        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, Duration::ZERO);
         assert_eq!(packets.len(), 0);
 
         // Available space but message was dropped
         let mut available_bytes: u64 = u64::MAX;
-        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes);
+        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, Duration::ZERO);
         assert_eq!(packets.len(), 0);
 
-        send.send_message(message.clone());
-        send.send_message(message);
+        send.send_message(message.clone(), Duration::ZERO);
+        send.send_message(message, Duration::ZERO);
 
         // Space for 1 message
         let mut available_bytes: u64 = 100;
-        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes);
+        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, Duration::ZERO);
         assert_eq!(packets.len(), 1);
 
         // Second message was dropped
         let mut available_bytes: u64 = u64::MAX;
-        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes);
+        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, Duration::ZERO);
         assert_eq!(packets.len(), 0);
     }
 
@@ -360,10 +942,10 @@ mod tests {
 
         // (4 + 1) * 400 = 2000 = 2 packets
         for _ in 0..400 {
-            send.send_message(message.clone());
+            send.send_message(message.clone(), Duration::ZERO);
         }
 
-        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes);
+        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, Duration::ZERO);
         assert_eq!(packets.len(), 2);
         let mut buffer = [0u8; 1400];
         for packet in packets {
@@ -372,4 +954,71 @@ mod tests {
             assert!(len < 1300);
         }
     }
+
+    #[test]
+    fn delivery_feedback() {
+        let mut sequence: u64 = 0;
+        let mut available_bytes = u64::MAX;
+        let mut send = SendChannelUnreliable::new(0, usize::MAX);
+        send.set_delivery_feedback();
+
+        let small = send.send_message(vec![0; 100].into(), Duration::ZERO).unwrap();
+        let sliced = send.send_message(vec![1; SLICE_SIZE * 2].into(), Duration::ZERO).unwrap();
+        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, Duration::ZERO);
+        assert_eq!(packets.len(), 3);
+
+        // The slices are packed first, the sliced message waits for all of them
+        send.process_packet_ack(0);
+        send.process_packet_ack(2);
+        assert_eq!(
+            send.deliveries().collect::<Vec<_>>(),
+            [MessageDelivery::Delivered(MessageId::from_raw(small))]
+        );
+        send.process_packet_lost(1);
+        assert_eq!(
+            send.deliveries().collect::<Vec<_>>(),
+            [MessageDelivery::Lost(MessageId::from_raw(sliced))]
+        );
+        assert!(send.packet_messages.is_empty());
+        assert!(send.unacked_packets.is_empty());
+
+        // Dropped messages are reported lost after the clear
+        let dropped = send.send_message(vec![2; 100].into(), Duration::ZERO).unwrap();
+        assert_eq!(send.clear_messages(), 1);
+        assert_eq!(send.deliveries().count(), 0);
+        send.clear_deliveries();
+        assert_eq!(
+            send.deliveries().collect::<Vec<_>>(),
+            [MessageDelivery::Lost(MessageId::from_raw(dropped))]
+        );
+    }
+
+    #[test]
+    fn deduplicate_window() {
+        let mut recv = ReceiveChannelUnreliable::new(usize::MAX);
+        recv.set_deduplicate(2);
+        let mut send = SendChannelUnreliable::new_sequenced(0, usize::MAX);
+        send.set_sequence(u16::MAX);
+
+        let receive = |recv: &mut ReceiveChannelUnreliable, message: &Bytes| {
+            recv.process_message(message.clone(), Duration::ZERO).unwrap();
+            recv.receive_message()
+        };
+        let mut messages: Vec<Bytes> = vec![];
+        for index in 0..3u8 {
+            send.send_message(vec![index].into(), Duration::ZERO);
+            messages.push(send.unreliable_messages.back().unwrap().0.clone());
+        }
+
+        // Sequences wrap around from u16::MAX to 0
+        assert_eq!(receive(&mut recv, &messages[0]), Some(Bytes::from(vec![0])));
+        assert_eq!(receive(&mut recv, &messages[0]), None);
+        assert_eq!(receive(&mut recv, &messages[1]), Some(Bytes::from(vec![1])));
+        assert_eq!(receive(&mut recv, &messages[1]), None);
+        assert_eq!(receive(&mut recv, &messages[0]), None);
+
+        // The first message left the window
+        assert_eq!(receive(&mut recv, &messages[2]), Some(Bytes::from(vec![2])));
+        assert_eq!(receive(&mut recv, &messages[0]), Some(Bytes::from(vec![0])));
+    }
 }
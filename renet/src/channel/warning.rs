@@ -0,0 +1,211 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    time::Duration,
+};
+
+use crate::ClientId;
+
+use super::ChannelConfig;
+
+// A warning is emitted again only after the usage goes this far below the threshold,
+// avoids repeated warnings when the usage oscillates around it.
+const WARNING_HYSTERESIS: f64 = 0.1;
+
+/// Limit of a channel that is close to be reached, see [`ChannelWarning`].
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum ChannelWarningKind {
+    /// Bytes of the messages waiting to be sent or acked in a send channel.
+    /// The limit is the channel `max_memory_usage_bytes`.
+    SendQueue,
+    /// Bytes of the received messages not yet read from a receive channel.
+    /// The limit is the channel `max_memory_usage_bytes`.
+    ReceiveBacklog,
+    /// Age in milliseconds of the oldest unacked message in a reliable send channel.
+    /// The limit is the `unacked_message_timeout` of the connection.
+    UnackedAge,
+    /// Received messages dropped because they were not read in time, see [`ReceivePolicy::WarnAndDrop`][crate::ReceivePolicy::WarnAndDrop].
+    /// Emitted once per channel, the usage is the number of messages dropped and the limit is the max age in milliseconds.
+    UnreadMessagesDropped,
+}
+
+/// Advisory emitted when a channel usage crosses its warning threshold,
+/// before the channel limit is reached and messages are dropped or the client disconnected.
+/// The warning is emitted once per crossing, it is emitted again only after the usage went back down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelWarning {
+    /// Client of the channel, None for warnings returned by a [`RenetClient`][crate::RenetClient].
+    pub client_id: Option<ClientId>,
+    pub channel_id: u8,
+    pub kind: ChannelWarningKind,
+    pub usage: u64,
+    pub limit: u64,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct ChannelWarnings {
+    // Warning threshold and max memory usage of each channel
+    send_channels: HashMap<u8, (f64, usize)>,
+    receive_channels: HashMap<u8, (f64, usize)>,
+    unacked_message_timeout: Option<Duration>,
+    warned: HashSet<(u8, ChannelWarningKind)>,
+    warnings: VecDeque<ChannelWarning>,
+}
+
+impl ChannelWarnings {
+    pub fn new(
+        send_channels_config: &[ChannelConfig],
+        receive_channels_config: &[ChannelConfig],
+        unacked_message_timeout: Option<Duration>,
+    ) -> Self {
+        let thresholds = |configs: &[ChannelConfig]| {
+            configs
+                .iter()
+                .filter_map(|config| Some((config.channel_id.raw(), (config.warning_threshold?, config.max_memory_usage_bytes))))
+                .collect()
+        };
+
+        Self {
+            send_channels: thresholds(send_channels_config),
+            receive_channels: thresholds(receive_channels_config),
+            unacked_message_timeout,
+            ..Default::default()
+        }
+    }
+
+    // Adds a channel used in both directions
+    pub fn add_channel(&mut self, config: &ChannelConfig) {
+        if let Some(threshold) = config.warning_threshold {
+            let channel_threshold = (threshold, config.max_memory_usage_bytes);
+            self.send_channels.insert(config.channel_id.raw(), channel_threshold);
+            self.receive_channels.insert(config.channel_id.raw(), channel_threshold);
+        }
+    }
+
+    pub fn update_send_channel(&mut self, channel_id: u8, available_memory: usize, oldest_unacked_age: Option<Duration>) {
+        let Some(&(threshold, max_memory_usage_bytes)) = self.send_channels.get(&channel_id) else {
+            return;
+        };
+
+        let usage = max_memory_usage_bytes.saturating_sub(available_memory);
+        self.check(
+            channel_id,
+            ChannelWarningKind::SendQueue,
+            threshold,
+            usage as u64,
+            max_memory_usage_bytes as u64,
+        );
+
+        if let (Some(age), Some(timeout)) = (oldest_unacked_age, self.unacked_message_timeout) {
+            self.check(
+                channel_id,
+                ChannelWarningKind::UnackedAge,
+                threshold,
+                age.as_millis() as u64,
+                timeout.as_millis() as u64,
+            );
+        }
+    }
+
+    pub fn update_receive_channel(&mut self, channel_id: u8, memory_usage: usize) {
+        let Some(&(threshold, max_memory_usage_bytes)) = self.receive_channels.get(&channel_id) else {
+            return;
+        };
+
+        self.check(
+            channel_id,
+            ChannelWarningKind::ReceiveBacklog,
+            threshold,
+            memory_usage as u64,
+            max_memory_usage_bytes as u64,
+        );
+    }
+
+    fn check(&mut self, channel_id: u8, kind: ChannelWarningKind, threshold: f64, usage: u64, limit: u64) {
+        if limit == 0 {
+            return;
+        }
+
+        let ratio = usage as f64 / limit as f64;
+        if ratio >= threshold {
+            if self.warned.insert((channel_id, kind)) {
+                self.warnings.push_back(ChannelWarning {
+                    client_id: None,
+                    channel_id,
+                    kind,
+                    usage,
+                    limit,
+                });
+            }
+        } else if ratio < threshold - WARNING_HYSTERESIS {
+            self.warned.remove(&(channel_id, kind));
+        }
+    }
+
+    // Emits the warning the first time only, regardless of the channel warning threshold
+    pub fn warn_once(&mut self, channel_id: u8, kind: ChannelWarningKind, usage: u64, limit: u64) {
+        if self.warned.insert((channel_id, kind)) {
+            self.warnings.push_back(ChannelWarning {
+                client_id: None,
+                channel_id,
+                kind,
+                usage,
+                limit,
+            });
+        }
+    }
+
+    // Number of warnings not drained yet
+    pub fn pending(&self) -> usize {
+        self.warnings.len()
+    }
+
+    pub fn drain(&mut self) -> std::collections::vec_deque::Drain<'_, ChannelWarning> {
+        self.warnings.drain(..)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SendType;
+
+    use super::*;
+
+    #[test]
+    fn warning_hysteresis() {
+        let config = ChannelConfig::new(0, 1000, SendType::Unreliable);
+        let mut warnings = ChannelWarnings::new(std::slice::from_ref(&config), std::slice::from_ref(&config), None);
+
+        warnings.update_receive_channel(0, 700);
+        assert_eq!(warnings.drain().count(), 0);
+
+        warnings.update_receive_channel(0, 800);
+        let emitted: Vec<ChannelWarning> = warnings.drain().collect();
+        assert_eq!(
+            emitted,
+            vec![ChannelWarning {
+                client_id: None,
+                channel_id: 0,
+                kind: ChannelWarningKind::ReceiveBacklog,
+                usage: 800,
+                limit: 1000,
+            }]
+        );
+
+        // Only once per crossing, and not again when going slightly below the threshold
+        warnings.update_receive_channel(0, 900);
+        warnings.update_receive_channel(0, 700);
+        warnings.update_receive_channel(0, 800);
+        assert_eq!(warnings.drain().count(), 0);
+
+        warnings.update_receive_channel(0, 600);
+        warnings.update_receive_channel(0, 800);
+        assert_eq!(warnings.drain().count(), 1);
+
+        // The send channel is tracked separately
+        warnings.update_send_channel(0, 100, None);
+        let emitted: Vec<ChannelWarning> = warnings.drain().collect();
+        assert_eq!(emitted.len(), 1);
+        assert_eq!(emitted[0].kind, ChannelWarningKind::SendQueue);
+        assert_eq!(emitted[0].usage, 900);
+    }
+}
@@ -3,111 +3,358 @@ use std::time::Duration;
 const RESOLUTION: Duration = Duration::from_millis(300);
 const WINDOW: Duration = Duration::from_millis(6000);
 const SIZE: usize = (WINDOW.as_millis() / RESOLUTION.as_millis()) as usize;
+// Packets sent in the current and last 2 resolutions are not counted for packet loss,
+// because the packet or its ack could be in flight
+const LOSS_IN_FLIGHT_RESOLUTIONS: usize = 3;
+
+/// Cumulative packet and byte counters of a connection.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct NetworkCounters {
+    pub packets_sent: u64,
+    pub packets_received: u64,
+    pub packets_acked: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    /// Padding bytes sent, already included in `bytes_sent`
+    pub padding_bytes_sent: u64,
+    /// Padding bytes received, already included in `bytes_received`
+    pub padding_bytes_received: u64,
+    /// Bytes of the sent datagrams on the wire: encrypted by the transport plus the estimated UDP/IP headers.
+    /// Only counted when the transport reports them.
+    pub wire_bytes_sent: u64,
+    /// Bytes of the received datagrams on the wire: encrypted by the transport plus the estimated UDP/IP headers.
+    /// Only counted when the transport reports them.
+    pub wire_bytes_received: u64,
+    /// Outbound packets and messages dropped before reaching the network
+    pub dropped: DropCounters,
+    /// Received unreliable messages dropped because their channel was at its memory limit
+    pub received_messages_dropped: u64,
+    /// Received messages dropped by the receive policy of their channel because they were not read.
+    /// They were dropped locally, not lost on the network, see [`ReceivePolicy`][crate::ReceivePolicy].
+    pub unread_messages_dropped: u64,
+    /// Reliable messages delivered twice to the application, which would be a bug of renet.
+    /// Only verified in debug builds, that panic on the first one, or with the `verify_delivery` feature.
+    pub duplicate_deliveries: u64,
+}
+
+/// Why outbound data was dropped before reaching the network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DropReason {
+    /// A message was sent to a full channel, unreliable or with [`QueuePolicy::RejectSend`][crate::QueuePolicy::RejectSend],
+    /// counts messages.
+    ChannelFull,
+    /// A queued unreliable message did not fit in the bytes available for the tick, counts messages.
+    OverBudget,
+    /// A queued unreliable message was older than the `message_ttl` of its channel, counts messages.
+    Expired,
+    /// The transport failed to encrypt the packet, counts packets.
+    Encryption,
+    /// The socket failed to send the packet, counts packets.
+    Socket,
+}
+
+impl DropReason {
+    const ALL: [DropReason; 5] = [
+        DropReason::ChannelFull,
+        DropReason::OverBudget,
+        DropReason::Expired,
+        DropReason::Encryption,
+        DropReason::Socket,
+    ];
+}
+
+impl std::fmt::Display for DropReason {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        use DropReason::*;
+
+        match *self {
+            ChannelFull => write!(fmt, "channel is full"),
+            OverBudget => write!(fmt, "no bytes available in the tick"),
+            Expired => write!(fmt, "message expired in the queue"),
+            Encryption => write!(fmt, "failed to encrypt packet"),
+            Socket => write!(fmt, "failed to send packet"),
+        }
+    }
+}
+
+/// Number of dropped packets or messages for each [`DropReason`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DropCounters([u64; DropReason::ALL.len()]);
+
+impl DropCounters {
+    /// Returns the number of drops for the reason.
+    pub fn get(&self, reason: DropReason) -> u64 {
+        self.0[reason as usize]
+    }
+
+    /// Returns the number of drops for all reasons.
+    pub fn total(&self) -> u64 {
+        self.0.iter().sum()
+    }
+
+    /// Iterates over the reasons with their number of drops.
+    pub fn iter(&self) -> impl Iterator<Item = (DropReason, u64)> + '_ {
+        DropReason::ALL.into_iter().map(|reason| (reason, self.get(reason)))
+    }
+}
+
+/// Packet loss of a connection over its configured `loss_window`.
+///
+/// Only packets sent within the window are counted, excluding the ones sent in the last 600-900ms
+/// because they may still be acked. Packets not acked yet are counted as lost.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct PacketLossInfo {
+    /// Packets sent in the window
+    pub sent: u64,
+    /// Packets sent in the window that were acked
+    pub acked: u64,
+    /// Packets sent in the window that were not acked
+    pub lost: u64,
+    /// Fraction of the packets sent in the window that were lost.
+    /// None until at least `loss_min_samples` packets were sent in the window.
+    pub ratio: Option<f64>,
+}
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct ConnectionStats {
-    packets_sent: [u64; SIZE],
-    packets_acked: [u64; SIZE],
+    // Packet loss has its own window, sized from the configured loss window
+    packets_sent: Vec<u64>,
+    packets_acked: Vec<u64>,
+    loss_min_samples: u64,
     bytes_sent: [u64; SIZE],
     bytes_received: [u64; SIZE],
+    wire_bytes_sent: [u64; SIZE],
+    wire_bytes_received: [u64; SIZE],
     current_index: usize,
+    current_loss_index: usize,
+    // Number of resolutions elapsed at the last update
+    last_resolution: u64,
+    // Start of the measurements, moved when the stats are reset
+    start_time: Duration,
+    epoch: u32,
+    counters: NetworkCounters,
+    lifetime_counters: NetworkCounters,
+}
+
+impl Default for ConnectionStats {
+    fn default() -> Self {
+        Self::new(WINDOW, 0)
+    }
 }
 
 impl ConnectionStats {
-    pub fn new() -> Self {
+    pub fn new(loss_window: Duration, loss_min_samples: u64) -> Self {
+        let loss_resolutions = (loss_window.as_millis() / RESOLUTION.as_millis()).max(1) as usize;
+        let loss_size = loss_resolutions + LOSS_IN_FLIGHT_RESOLUTIONS;
         Self {
-            packets_sent: [0; SIZE],
-            packets_acked: [0; SIZE],
+            packets_sent: vec![0; loss_size],
+            packets_acked: vec![0; loss_size],
+            loss_min_samples,
             bytes_sent: [0; SIZE],
             bytes_received: [0; SIZE],
+            wire_bytes_sent: [0; SIZE],
+            wire_bytes_received: [0; SIZE],
             current_index: 0,
+            current_loss_index: 0,
+            last_resolution: 0,
+            start_time: Duration::ZERO,
+            epoch: 0,
+            counters: NetworkCounters::default(),
+            lifetime_counters: NetworkCounters::default(),
         }
     }
 
+    /// Clears the measurement windows and the counters, lifetime counters are kept.
+    pub fn reset(&mut self, current_time: Duration) {
+        self.packets_sent.fill(0);
+        self.packets_acked.fill(0);
+        self.bytes_sent = [0; SIZE];
+        self.bytes_received = [0; SIZE];
+        self.wire_bytes_sent = [0; SIZE];
+        self.wire_bytes_received = [0; SIZE];
+        self.current_index = Self::index(current_time);
+        self.current_loss_index = self.loss_index(current_time);
+        self.last_resolution = Self::resolution(current_time);
+        self.start_time = current_time;
+        self.counters = NetworkCounters::default();
+        self.epoch = self.epoch.wrapping_add(1);
+    }
+
+    pub fn epoch(&self) -> u32 {
+        self.epoch
+    }
+
+    pub fn counters(&self) -> NetworkCounters {
+        self.counters
+    }
+
+    pub fn lifetime_counters(&self) -> NetworkCounters {
+        self.lifetime_counters
+    }
+
+    fn resolution(time: Duration) -> u64 {
+        (time.as_millis() / RESOLUTION.as_millis()) as u64
+    }
+
     fn index(time: Duration) -> usize {
-        (time.as_millis() / RESOLUTION.as_millis()) as usize % SIZE
+        Self::resolution(time) as usize % SIZE
+    }
+
+    fn loss_index(&self, time: Duration) -> usize {
+        Self::resolution(time) as usize % self.packets_sent.len()
     }
 
     pub fn update(&mut self, current_time: Duration) {
-        let i = Self::index(current_time);
-        if self.current_index != i {
-            self.current_index = i;
-            self.packets_sent[i] = 0;
+        let resolution = Self::resolution(current_time);
+        // Clear every resolution started since the last update, a long update can skip several of them.
+        // Going past the size of the windows would clear the same slots again.
+        let window_resolutions = SIZE.max(self.packets_sent.len()) as u64;
+        let first = (self.last_resolution + 1).max(resolution.saturating_sub(window_resolutions - 1));
+        for passed in first..=resolution {
+            let i = passed as usize % SIZE;
             self.bytes_sent[i] = 0;
             self.bytes_received[i] = 0;
+            self.wire_bytes_sent[i] = 0;
+            self.wire_bytes_received[i] = 0;
+
+            let i = passed as usize % self.packets_sent.len();
+            self.packets_sent[i] = 0;
             self.packets_acked[i] = 0;
         }
+
+        self.last_resolution = self.last_resolution.max(resolution);
+        self.current_index = Self::index(current_time);
+        self.current_loss_index = self.loss_index(current_time);
     }
 
     pub fn sent_packets(&mut self, num_packets: u64, bytes: u64) {
-        self.packets_sent[self.current_index] += num_packets;
+        self.packets_sent[self.current_loss_index] += num_packets;
         self.bytes_sent[self.current_index] += bytes;
+        for counters in [&mut self.counters, &mut self.lifetime_counters] {
+            counters.packets_sent += num_packets;
+            counters.bytes_sent += bytes;
+        }
     }
 
     pub fn received_packet(&mut self, bytes: u64) {
         self.bytes_received[self.current_index] += bytes;
+        for counters in [&mut self.counters, &mut self.lifetime_counters] {
+            counters.packets_received += 1;
+            counters.bytes_received += bytes;
+        }
+    }
+
+    pub fn sent_wire_bytes(&mut self, bytes: u64) {
+        self.wire_bytes_sent[self.current_index] += bytes;
+        self.counters.wire_bytes_sent += bytes;
+        self.lifetime_counters.wire_bytes_sent += bytes;
+    }
+
+    pub fn received_wire_bytes(&mut self, bytes: u64) {
+        self.wire_bytes_received[self.current_index] += bytes;
+        self.counters.wire_bytes_received += bytes;
+        self.lifetime_counters.wire_bytes_received += bytes;
+    }
+
+    pub fn sent_padding(&mut self, bytes: u64) {
+        self.counters.padding_bytes_sent += bytes;
+        self.lifetime_counters.padding_bytes_sent += bytes;
+    }
+
+    pub fn received_padding(&mut self, bytes: u64) {
+        self.counters.padding_bytes_received += bytes;
+        self.lifetime_counters.padding_bytes_received += bytes;
+    }
+
+    pub fn dropped(&mut self, reason: DropReason, count: u64) {
+        self.counters.dropped.0[reason as usize] += count;
+        self.lifetime_counters.dropped.0[reason as usize] += count;
+    }
+
+    pub fn received_dropped(&mut self, count: u64) {
+        self.counters.received_messages_dropped += count;
+        self.lifetime_counters.received_messages_dropped += count;
+    }
+
+    pub fn unread_dropped(&mut self, count: u64) {
+        self.counters.unread_messages_dropped += count;
+        self.lifetime_counters.unread_messages_dropped += count;
+    }
+
+    pub fn duplicate_delivered(&mut self) {
+        self.counters.duplicate_deliveries += 1;
+        self.lifetime_counters.duplicate_deliveries += 1;
     }
 
     pub fn acked_packet(&mut self, sent_at: Duration, current_time: Duration) {
-        let delta = current_time - sent_at;
-        if delta > WINDOW {
-            // Out of the duration window, discard it
+        self.lifetime_counters.packets_acked += 1;
+        if sent_at < self.start_time {
+            // Sent before the last reset, it was not counted
+            return;
+        }
+        self.counters.packets_acked += 1;
+
+        let delta = current_time.saturating_sub(sent_at);
+        if delta > RESOLUTION * (self.packets_sent.len() as u32 - 1) {
+            // Out of the loss window, its resolution was already reused
             return;
         }
 
-        self.packets_acked[Self::index(sent_at)] += 1;
+        let i = self.loss_index(sent_at);
+        self.packets_acked[i] += 1;
     }
 
     pub fn bytes_sent_per_second(&self, current_time: Duration) -> f64 {
-        let mut total_bytes: u64 = self.bytes_sent.iter().sum();
+        self.per_second(&self.bytes_sent, current_time)
+    }
 
-        if current_time < WINDOW {
-            return total_bytes as f64 / current_time.as_secs_f64();
-        }
+    pub fn bytes_received_per_second(&self, current_time: Duration) -> f64 {
+        self.per_second(&self.bytes_received, current_time)
+    }
 
-        // Ignore the current incomplete resolution
-        total_bytes -= self.bytes_sent[self.current_index];
+    pub fn wire_bytes_sent_per_second(&self, current_time: Duration) -> f64 {
+        self.per_second(&self.wire_bytes_sent, current_time)
+    }
 
-        total_bytes as f64 / (WINDOW - RESOLUTION).as_secs_f64()
+    pub fn wire_bytes_received_per_second(&self, current_time: Duration) -> f64 {
+        self.per_second(&self.wire_bytes_received, current_time)
     }
 
-    pub fn bytes_received_per_second(&self, current_time: Duration) -> f64 {
-        let mut total_bytes: u64 = self.bytes_received.iter().sum();
+    fn per_second(&self, window: &[u64; SIZE], current_time: Duration) -> f64 {
+        let mut total_bytes: u64 = window.iter().sum();
 
-        if current_time < WINDOW {
-            return total_bytes as f64 / current_time.as_secs_f64();
+        let elapsed = current_time.saturating_sub(self.start_time);
+        if elapsed.is_zero() {
+            return 0.0;
+        }
+        if elapsed < WINDOW {
+            return total_bytes as f64 / elapsed.as_secs_f64();
         }
 
         // Ignore the current incomplete resolution
-        total_bytes -= self.bytes_received[self.current_index];
+        total_bytes -= window[self.current_index];
         total_bytes as f64 / (WINDOW - RESOLUTION).as_secs_f64()
     }
 
     pub fn packet_loss(&self) -> f64 {
-        let total_packets_sent = {
-            let mut sum: u64 = self.packets_sent.iter().sum();
-
-            // Ignore the current and last 2 resolutions,
-            // because the message or its ack could be in flight
-            sum -= self.packets_sent[self.current_index];
-            sum -= self.packets_sent[(self.current_index + SIZE - 1) % SIZE];
-            sum -= self.packets_sent[(self.current_index + SIZE - 2) % SIZE];
-            sum as f64
-        };
-
-        let total_packets_acked = {
-            let mut sum: u64 = self.packets_acked.iter().sum();
-            sum -= self.packets_acked[self.current_index];
-            sum -= self.packets_acked[(self.current_index + SIZE - 1) % SIZE];
-            sum -= self.packets_acked[(self.current_index + SIZE - 2) % SIZE];
-            sum as f64
-        };
-
-        if total_packets_sent == 0.0 {
-            return 0.0;
+        self.packet_loss_info().ratio.unwrap_or(0.0)
+    }
+
+    pub fn packet_loss_info(&self) -> PacketLossInfo {
+        let size = self.packets_sent.len();
+        let mut sent = 0;
+        let mut acked = 0;
+        // Sum all resolutions except the ones that can still be in flight
+        for offset in LOSS_IN_FLIGHT_RESOLUTIONS..size {
+            let i = (self.current_loss_index + size - offset) % size;
+            sent += self.packets_sent[i];
+            acked += self.packets_acked[i];
         }
 
-        (total_packets_sent - total_packets_acked) / total_packets_sent
+        let lost = sent.saturating_sub(acked);
+        let ratio = if sent == 0 || sent < self.loss_min_samples { None } else { Some(lost as f64 / sent as f64) };
+
+        PacketLossInfo { sent, acked, lost, ratio }
     }
 }
 
@@ -136,11 +383,30 @@ mod tests {
         }
 
         // Check after 6 seconds
-        assert_eq!(window.packets_sent, [30; SIZE]);
         assert_eq!(window.bytes_sent, [300; SIZE]);
         assert_eq!(window.bytes_sent_per_second(current_time), 1000.);
     }
 
+    #[test]
+    fn long_update_clears_windows() {
+        let mut current_time = Duration::ZERO;
+        let mut window = ConnectionStats::new(Duration::from_secs(3), 0);
+
+        for _ in 0..30 {
+            window.update(current_time);
+            window.sent_packets(1, 100);
+            current_time += Duration::from_millis(100);
+        }
+        assert!(window.bytes_sent_per_second(current_time) > 0.);
+
+        // No traffic during the stall, nothing from before it is left in the windows
+        current_time += Duration::from_secs(30);
+        window.update(current_time);
+        assert_eq!(window.bytes_sent_per_second(current_time), 0.);
+        assert_eq!(window.packet_loss_info().sent, 0);
+        assert_eq!(window.counters().bytes_sent, 3000);
+    }
+
     #[test]
     fn packet_loss() {
         let mut current_time = Duration::ZERO;
@@ -165,8 +431,105 @@ mod tests {
         }
 
         // Check after 6 seconds
-        assert_eq!(window.packets_sent, [6; SIZE]);
-        assert_eq!(window.packets_acked, [3; SIZE]);
+        let info = window.packet_loss_info();
+        assert_eq!(info.sent, 102);
+        assert_eq!(info.acked, 51);
         assert_eq!(window.packet_loss(), 0.5);
     }
+
+    #[test]
+    fn packet_loss_min_samples() {
+        let mut current_time = Duration::ZERO;
+        let mut window = ConnectionStats::new(Duration::from_secs(1), 10);
+        assert_eq!(window.packet_loss_info(), PacketLossInfo::default());
+
+        // Right after connecting, only a few packets are outside of the in flight resolutions
+        for _ in 0..4 {
+            window.update(current_time);
+            window.sent_packets(1, 100);
+            current_time += Duration::from_millis(300);
+        }
+        let info = window.packet_loss_info();
+        assert_eq!(info.sent, 1);
+        assert_eq!(info.lost, 1);
+        assert_eq!(info.ratio, None);
+        assert_eq!(window.packet_loss(), 0.0);
+    }
+
+    #[test]
+    fn packet_loss_window() {
+        let mut current_time = Duration::ZERO;
+        let mut window = ConnectionStats::new(Duration::from_millis(1500), 10);
+
+        // Steady loss, one of every 4 packets is lost
+        for _ in 0..30 {
+            window.update(current_time);
+            window.sent_packets(4, 400);
+            for _ in 0..3 {
+                window.acked_packet(current_time, current_time);
+            }
+            current_time += Duration::from_millis(100);
+        }
+        let info = window.packet_loss_info();
+        assert_eq!(info.sent, 60);
+        assert_eq!(info.acked, 45);
+        assert_eq!(info.lost, 15);
+        assert_eq!(info.ratio, Some(0.25));
+
+        // Loss stops, only counted until it goes out of the window
+        for _ in 0..10 {
+            window.update(current_time);
+            window.sent_packets(4, 400);
+            for _ in 0..4 {
+                window.acked_packet(current_time, current_time);
+            }
+            current_time += Duration::from_millis(100);
+        }
+        assert!(window.packet_loss() > 0.0 && window.packet_loss() < 0.25);
+
+        for _ in 0..15 {
+            window.update(current_time);
+            window.sent_packets(4, 400);
+            for _ in 0..4 {
+                window.acked_packet(current_time, current_time);
+            }
+            current_time += Duration::from_millis(100);
+        }
+        assert_eq!(window.packet_loss_info().ratio, Some(0.0));
+    }
+
+    #[test]
+    fn reset() {
+        let mut current_time = Duration::ZERO;
+        let mut window = ConnectionStats::default();
+
+        for _ in 0..20 {
+            window.update(current_time);
+            window.sent_packets(1, 100);
+            window.received_packet(50);
+            current_time += Duration::from_millis(100);
+        }
+
+        window.reset(current_time);
+        assert_eq!(window.epoch(), 1);
+        assert_eq!(window.counters(), NetworkCounters::default());
+        assert_eq!(window.lifetime_counters().packets_sent, 20);
+        assert_eq!(window.lifetime_counters().bytes_received, 1000);
+        assert_eq!(window.bytes_sent_per_second(current_time), 0.);
+
+        // Packet sent before the reset is only counted in the lifetime counters
+        window.acked_packet(current_time - Duration::from_millis(100), current_time);
+        assert_eq!(window.counters().packets_acked, 0);
+        assert_eq!(window.lifetime_counters().packets_acked, 1);
+
+        for _ in 0..10 {
+            window.update(current_time);
+            window.sent_packets(1, 100);
+            current_time += Duration::from_millis(100);
+        }
+
+        assert_eq!(window.counters().packets_sent, 10);
+        assert_eq!(window.lifetime_counters().packets_sent, 30);
+        assert_eq!(window.bytes_sent_per_second(current_time), 1000.);
+    }
 }
@@ -1,6 +1,7 @@
 use std::fmt;
 
 use crate::packet::SerializationError;
+use crate::QuotaKind;
 
 /// Possible reasons for a disconnection.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -15,21 +16,46 @@ pub enum DisconnectReason {
     PacketSerialization(SerializationError),
     /// Failed to deserialize packet
     PacketDeserialization(SerializationError),
-    /// Received message from channel with invalid id
-    ReceivedInvalidChannelId(u8),
     /// Error occurred in a send channel
     SendChannelError { channel_id: u8, error: ChannelError },
     /// Error occurred in a receive channel
     ReceiveChannelError { channel_id: u8, error: ChannelError },
+    /// A reliable message was not acked within the configured `unacked_message_timeout`
+    UnackedMessageTimeout { channel_id: u8 },
+    /// The channels configuration of the remote does not match the local one
+    ChannelConfigMismatch { first_mismatched_channel: u8 },
+    /// The remote didn't send its channels configuration within the `channel_config_timeout`
+    ChannelConfigTimeout,
+    /// The server transferred the client to another server,
+    /// the token to connect to it is returned by [`RenetClient::take_transfer_token`][crate::RenetClient::take_transfer_token]
+    Transferred,
+    /// The client stayed over its receive quota for longer than the grace period,
+    /// see [`ConnectionConfig::receive_quota`][crate::ConnectionConfig::receive_quota].
+    QuotaExceeded { kind: QuotaKind },
+    /// The remote uses another version of the packet encoding, see [`PROTOCOL_VERSION`][crate::PROTOCOL_VERSION]
+    ProtocolVersionMismatch { remote_version: u8 },
 }
 
 /// Possibles errors that can occur in a channel.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ChannelError {
-    /// Reliable channel reached maximum allowed memory
+    /// Reliable channel reached maximum allowed memory, or its `max_queued_messages`
     ReliableChannelMaxMemoryReached,
     /// Received an invalid slice message in the channel.
     InvalidSliceMessage,
+    /// Received a message without a valid group header in a grouped channel.
+    InvalidGroupHeader,
+    /// Received a message that could not be decompressed in a compressed channel.
+    InvalidCompressedMessage,
+    /// Received a message without its trace id in a channel with tracing context.
+    InvalidTracingContext,
+    /// Received an empty message, without its kind, in a channel with message kinds.
+    InvalidMessageKind,
+    /// Received a message, or the first slice of a message, above the `max_message_size` of the channel.
+    ReceivedMessageAboveLimit,
+    /// Received a packet for a channel that doesn't exist, it may not be added yet, see `RenetClient::add_channel`.
+    /// The packet is not acknowledged, so reliable messages are resent until the channel is added.
+    UnknownChannel,
 }
 
 impl fmt::Display for ChannelError {
@@ -39,6 +65,12 @@ impl fmt::Display for ChannelError {
         match *self {
             ReliableChannelMaxMemoryReached => write!(fmt, "reliable channel memory usage was exausted"),
             InvalidSliceMessage => write!(fmt, "received an invalid slice packet"),
+            InvalidGroupHeader => write!(fmt, "received a message without a valid group header"),
+            InvalidCompressedMessage => write!(fmt, "received a message that could not be decompressed"),
+            InvalidTracingContext => write!(fmt, "received a message without its trace id"),
+            InvalidMessageKind => write!(fmt, "received a message without its kind"),
+            ReceivedMessageAboveLimit => write!(fmt, "received a message above the maximum message size"),
+            UnknownChannel => write!(fmt, "received a packet for an unknown channel"),
         }
     }
 }
@@ -53,15 +85,132 @@ impl fmt::Display for DisconnectReason {
             DisconnectedByServer => write!(fmt, "connection terminated by the server"),
             PacketSerialization(err) => write!(fmt, "failed to serialize packet: {err}"),
             PacketDeserialization(err) => write!(fmt, "failed to deserialize packet: {err}"),
-            ReceivedInvalidChannelId(id) => write!(fmt, "received message with invalid channel {id}"),
             SendChannelError { channel_id, error } => write!(fmt, "send channel {channel_id} with error: {error}"),
             ReceiveChannelError { channel_id, error } => write!(fmt, "receive channel {channel_id} with error: {error}"),
+            UnackedMessageTimeout { channel_id } => write!(fmt, "send channel {channel_id} had a message unacked for too long"),
+            ChannelConfigMismatch { first_mismatched_channel } => {
+                write!(
+                    fmt,
+                    "channel {first_mismatched_channel} configuration does not match with the remote"
+                )
+            }
+            ChannelConfigTimeout => write!(fmt, "remote channels configuration not received"),
+            Transferred => write!(fmt, "client transferred to another server"),
+            QuotaExceeded { kind } => write!(fmt, "client exceeded its quota of {kind}"),
+            ProtocolVersionMismatch { remote_version } => write!(
+                fmt,
+                "remote uses protocol version {remote_version}, the local version is {}",
+                crate::PROTOCOL_VERSION
+            ),
         }
     }
 }
 
 impl std::error::Error for ChannelError {}
 
+/// Error when adding a channel at runtime with an id already in use,
+/// see [`RenetClient::add_channel`][crate::RenetClient::add_channel].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelAlreadyExists {
+    pub channel_id: u8,
+}
+
+impl std::error::Error for ChannelAlreadyExists {}
+
+impl fmt::Display for ChannelAlreadyExists {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "channel {} already exists", self.channel_id)
+    }
+}
+
+/// Problem found in the channels by [`ChannelsBuilder::build`][crate::ChannelsBuilder::build]
+/// or [`ConnectionConfig::validate`][crate::ConnectionConfig::validate].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelsConfigError {
+    /// Two channels have the same id.
+    DuplicateChannel { channel_id: u8 },
+    /// A channel was changed without being added.
+    UnknownChannel { channel_id: u8 },
+    /// The channels can use more memory together than the budget.
+    OverMemoryBudget { total_bytes: usize, budget_bytes: usize },
+    /// A reliable sequenced channel has a group, its superseded messages would block the group.
+    GroupedSequencedChannel { channel_id: u8 },
+    /// The overflow route of a channel is invalid: the channel or the overflow channel is not reliable,
+    /// or they are not in the same group, see [`OverflowRoute`][crate::OverflowRoute].
+    InvalidOverflowRoute { channel_id: u8 },
+}
+
+impl std::error::Error for ChannelsConfigError {}
+
+impl fmt::Display for ChannelsConfigError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        use ChannelsConfigError::*;
+
+        match *self {
+            DuplicateChannel { channel_id } => write!(fmt, "channel {channel_id} is configured more than once"),
+            UnknownChannel { channel_id } => write!(fmt, "channel {channel_id} was changed but not added"),
+            OverMemoryBudget { total_bytes, budget_bytes } => {
+                write!(
+                    fmt,
+                    "channels use up to {total_bytes} bytes, above the budget of {budget_bytes} bytes"
+                )
+            }
+            GroupedSequencedChannel { channel_id } => write!(fmt, "reliable sequenced channel {channel_id} cannot be grouped"),
+            InvalidOverflowRoute { channel_id } => write!(fmt, "channel {channel_id} has an invalid overflow route"),
+        }
+    }
+}
+
+/// Error when a channel used by the application is missing from the configuration,
+/// see [`ConnectionConfig::check_channels`][crate::ConnectionConfig::check_channels].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelNotConfigured {
+    pub channel_id: u8,
+}
+
+impl std::error::Error for ChannelNotConfigured {}
+
+impl fmt::Display for ChannelNotConfigured {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "channel {} is missing from the server or the client channels", self.channel_id)
+    }
+}
+
+/// Error when a message can't be sent, nothing is queued, see [`RenetClient::try_send_message`][crate::RenetClient::try_send_message].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendError {
+    /// The client does not exist.
+    ClientNotFound,
+    /// The message is bigger than the channel accepts, its `max_message_size`, for reliable channels
+    /// its `max_memory_usage_bytes` and for unreliable channels its `max_fragments`. The size includes the headers added by the channel features, like the group number.
+    MessageTooLarge { channel_id: u8, size: usize, limit: usize },
+    /// The channel has no memory left for the message. Unlike [`RenetClient::send_message`][crate::RenetClient::send_message],
+    /// the connection stays up for reliable channels and the drop is not counted for unreliable channels.
+    ChannelFull { channel_id: u8 },
+    /// The channel does not exist.
+    InvalidChannel { channel_id: u8 },
+}
+
+impl std::error::Error for SendError {}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        use SendError::*;
+
+        match *self {
+            ClientNotFound => write!(fmt, "client with given id was not found"),
+            MessageTooLarge { channel_id, size, limit } => {
+                write!(
+                    fmt,
+                    "message of {size} bytes is above the limit of {limit} bytes of channel {channel_id}"
+                )
+            }
+            ChannelFull { channel_id } => write!(fmt, "channel {channel_id} has no memory left for the message"),
+            InvalidChannel { channel_id } => write!(fmt, "channel {channel_id} does not exist"),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct ClientNotFound;
 
@@ -72,3 +221,32 @@ impl fmt::Display for ClientNotFound {
         write!(fmt, "client with given id was not found")
     }
 }
+
+/// Possible errors when transferring a client with [`RenetServer::transfer_client`][crate::RenetServer::transfer_client].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferError {
+    /// The client does not exist or is disconnected.
+    ClientNotFound,
+    /// The token is empty or bigger than [`MAX_TRANSFER_TOKEN_BYTES`][crate::MAX_TRANSFER_TOKEN_BYTES].
+    InvalidTokenSize { len: usize },
+    /// The client is already being transferred.
+    AlreadyTransferring,
+}
+
+impl std::error::Error for TransferError {}
+
+impl fmt::Display for TransferError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        use TransferError::*;
+
+        match *self {
+            ClientNotFound => write!(fmt, "client with given id was not found"),
+            InvalidTokenSize { len } => write!(
+                fmt,
+                "invalid transfer token of {len} bytes, it must have between 1 and {} bytes",
+                crate::MAX_TRANSFER_TOKEN_BYTES
+            ),
+            AlreadyTransferring => write!(fmt, "client is already being transferred"),
+        }
+    }
+}
@@ -1,17 +1,40 @@
 mod channel;
 mod connection_stats;
 mod error;
+mod log_limiter;
 mod packet;
+mod packet_arrival;
+mod ping;
+mod quota;
 mod remote_connection;
 mod server;
+mod transfer;
+mod usage;
 
+pub mod lockstep;
+pub mod snapshot;
 #[cfg(feature = "transport")]
 pub mod transport;
 
-pub use channel::{ChannelConfig, DefaultChannel, SendType};
-pub use error::{ChannelError, ClientNotFound, DisconnectReason};
-pub use remote_connection::{ConnectionConfig, NetworkInfo, RenetClient, RenetConnectionStatus};
-pub use server::{RenetServer, ServerEvent};
+pub use channel::{
+    ChannelCapacity, ChannelConfig, ChannelId, ChannelLatency, ChannelStats, ChannelWarning, ChannelWarningKind, ChannelsBuilder,
+    CompressionKind, DefaultChannel, FenceId, FullQueuePolicy, LatencyStats, MessageDelivery, MessageId, MessageKindStats,
+    MessageLatencyRecord, OverflowRoute, QueuePolicy, ReceivePolicy, SendType, TRACE_ID_BYTES,
+};
+pub use connection_stats::{DropCounters, DropReason, NetworkCounters, PacketLossInfo};
+pub use error::{
+    ChannelAlreadyExists, ChannelError, ChannelNotConfigured, ChannelsConfigError, ClientNotFound, DisconnectReason, SendError,
+    TransferError,
+};
+pub use remote_connection::{
+    ConnectionConfig, EffectiveConfig, NetworkInfo, PacketPadding, RenetClient, RenetConnectionStatus, TransmissionPlan,
+};
+pub use packet::PROTOCOL_VERSION;
+pub use ping::PingId;
+pub use quota::{QuotaKind, ReceiveQuota};
+pub use server::{BroadcastPolicy, BroadcastSummary, RenetServer, ServerEvent};
+pub use transfer::MAX_TRANSFER_TOKEN_BYTES;
+pub use usage::UsageWarning;
 
 pub use bytes::Bytes;
 
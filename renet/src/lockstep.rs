@@ -0,0 +1,488 @@
+//! Helpers for deterministic lockstep games, layered on top of a reliable ordered channel.
+//!
+//! Clients queue their inputs with [`LockstepSession::queue_local_input`], the inputs are applied
+//! `input_delay` ticks later. The [`LockstepServer`] bundles the inputs of all the participants
+//! for each tick and sends the bundle to every participant once it is complete.
+//! Ticks are then polled in order with [`LockstepSession::poll_tick`].
+//!
+//! The channel must be used only for lockstep messages, in both directions.
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::{ClientId, DefaultChannel, RenetClient, RenetServer};
+
+const BUNDLE_MESSAGE: u8 = 0;
+const WAITING_MESSAGE: u8 = 1;
+
+/// Configuration shared by the [`LockstepServer`] and the [`LockstepSession`] of the clients.
+#[derive(Debug, Clone)]
+pub struct LockstepConfig {
+    /// Reliable ordered channel used for the inputs and the bundles, in both directions.
+    /// Default: [`DefaultChannel::ReliableOrdered`]
+    pub channel_id: u8,
+    /// Number of ticks between the tick an input is queued and the tick it is applied.
+    /// Hides the round trip to the server, a tick is stalled when an input takes longer to be bundled.
+    /// Default: 3
+    pub input_delay: u64,
+    /// Number of the most recent bundles kept by the server, they are sent to late joining clients to catch up.
+    /// Default: 1024
+    pub history_ticks: usize,
+    /// Number of ticks after the next tick to bundle the server accepts inputs for, inputs for later ticks
+    /// are discarded so a client can't make the server hold inputs for arbitrarily distant ticks.
+    /// Should be larger than `input_delay`.
+    /// Default: 64
+    pub max_ticks_ahead: u64,
+}
+
+impl Default for LockstepConfig {
+    fn default() -> Self {
+        Self {
+            channel_id: DefaultChannel::ReliableOrdered.into(),
+            input_delay: 3,
+            history_ticks: 1024,
+            max_ticks_ahead: 64,
+        }
+    }
+}
+
+/// Status of a tick, see [`LockstepSession::poll_tick`] and [`LockstepServer::poll_tick`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TickStatus {
+    /// Inputs of the tick, sorted by client id. Participants without input for the tick are not included.
+    Ready(Vec<(ClientId, Bytes)>),
+    /// The inputs of these clients are missing for the tick, or an earlier one.
+    /// Empty when the client has not been informed of the missing inputs yet.
+    WaitingOn(Vec<ClientId>),
+}
+
+/// Client side of a lockstep game.
+#[derive(Debug)]
+pub struct LockstepSession {
+    config: LockstepConfig,
+    outgoing: Vec<(u64, Bytes)>,
+    bundles: BTreeMap<u64, Vec<(ClientId, Bytes)>>,
+    // Oldest tick not bundled by the server and the clients it is waiting on
+    waiting: Option<(u64, Vec<ClientId>)>,
+}
+
+impl LockstepSession {
+    pub fn new(config: LockstepConfig) -> Self {
+        Self {
+            config,
+            outgoing: vec![],
+            bundles: BTreeMap::new(),
+            waiting: None,
+        }
+    }
+
+    /// Queues the local input generated at the tick, it will be applied at `tick + input_delay`.
+    /// Queued inputs are sent on [`LockstepSession::update`].
+    pub fn queue_local_input(&mut self, tick: u64, input: impl Into<Bytes>) {
+        self.outgoing.push((tick + self.config.input_delay, input.into()));
+    }
+
+    /// Sends the queued inputs and receives the bundles from the server.
+    /// Should be called every tick, before polling the tick.
+    pub fn update(&mut self, client: &mut RenetClient) {
+        for (tick, input) in self.outgoing.drain(..) {
+            let mut message = BytesMut::with_capacity(8 + input.len());
+            message.put_u64_le(tick);
+            message.put_slice(&input);
+            client.send_message(self.config.channel_id, message.freeze());
+        }
+
+        while let Some(message) = client.receive_message(self.config.channel_id) {
+            match decode_server_message(message) {
+                Some(ServerMessage::Bundle { tick, inputs }) => {
+                    if matches!(self.waiting, Some((waiting_tick, _)) if waiting_tick <= tick) {
+                        self.waiting = None;
+                    }
+                    self.bundles.insert(tick, inputs);
+                }
+                Some(ServerMessage::Waiting { tick, missing }) => self.waiting = Some((tick, missing)),
+                None => log::error!("Received invalid lockstep message in channel {}", self.config.channel_id),
+            }
+        }
+    }
+
+    /// Returns the inputs of all the participants for the tick once they are received.
+    /// A ready tick is returned only once, ticks should be polled in order.
+    pub fn poll_tick(&mut self, tick: u64) -> TickStatus {
+        if let Some(inputs) = self.bundles.remove(&tick) {
+            return TickStatus::Ready(inputs);
+        }
+
+        match &self.waiting {
+            Some((waiting_tick, missing)) if *waiting_tick <= tick => TickStatus::WaitingOn(missing.clone()),
+            _ => TickStatus::WaitingOn(vec![]),
+        }
+    }
+
+    /// Returns the most recent tick received from the server.
+    pub fn last_received_tick(&self) -> Option<u64> {
+        self.bundles.keys().next_back().copied()
+    }
+}
+
+/// Server side of a lockstep game, bundles the inputs of the participants for each tick.
+#[derive(Debug)]
+pub struct LockstepServer {
+    config: LockstepConfig,
+    // First tick each participant is expected to send an input for
+    participants: HashMap<ClientId, u64>,
+    inputs: BTreeMap<u64, HashMap<ClientId, Bytes>>,
+    next_tick: u64,
+    history: VecDeque<(u64, Vec<(ClientId, Bytes)>)>,
+    last_waiting: Option<(u64, Vec<ClientId>)>,
+}
+
+impl LockstepServer {
+    pub fn new(config: LockstepConfig) -> Self {
+        Self {
+            config,
+            participants: HashMap::new(),
+            inputs: BTreeMap::new(),
+            next_tick: 0,
+            history: VecDeque::new(),
+            last_waiting: None,
+        }
+    }
+
+    /// Adds a participant to the game, its inputs are expected from `input_delay` ticks after the next tick.
+    /// The bundles kept in the history are sent to the client so it can catch up with the game.
+    pub fn add_participant(&mut self, client_id: ClientId, server: &mut RenetServer) {
+        if self.participants.contains_key(&client_id) {
+            return;
+        }
+
+        for (tick, inputs) in self.history.iter() {
+            server.send_message(client_id, self.config.channel_id, encode_bundle(*tick, inputs));
+        }
+        self.participants.insert(client_id, self.next_tick + self.config.input_delay);
+    }
+
+    /// Removes a participant from the game, the next ticks don't wait for its inputs.
+    pub fn remove_participant(&mut self, client_id: ClientId) {
+        self.participants.remove(&client_id);
+        for inputs in self.inputs.values_mut() {
+            inputs.remove(&client_id);
+        }
+    }
+
+    /// Returns the next tick to be bundled.
+    pub fn next_tick(&self) -> u64 {
+        self.next_tick
+    }
+
+    /// Receives the inputs of the participants, and sends the bundles of the completed ticks.
+    /// When the next tick is stalled, the participants are informed of the missing inputs.
+    pub fn update(&mut self, server: &mut RenetServer) {
+        let mut participants: Vec<(ClientId, u64)> = self.participants.iter().map(|(&id, &first_tick)| (id, first_tick)).collect();
+        participants.sort();
+
+        for &(client_id, first_tick) in participants.iter() {
+            while let Some(message) = server.receive_message(client_id, self.config.channel_id) {
+                if message.len() < 8 {
+                    log::error!("Received invalid lockstep input from client {client_id}");
+                    continue;
+                }
+                let tick = u64::from_le_bytes(message[..8].try_into().unwrap());
+                if tick < self.next_tick.max(first_tick) {
+                    log::warn!("Discarded lockstep input from client {client_id} for tick {tick}, it arrived too late");
+                    continue;
+                }
+                if tick > self.next_tick.saturating_add(self.config.max_ticks_ahead) {
+                    log::warn!("Discarded lockstep input from client {client_id} for tick {tick}, it is too far ahead");
+                    continue;
+                }
+                self.inputs.entry(tick).or_default().insert(client_id, message.slice(8..));
+            }
+        }
+
+        if participants.is_empty() {
+            return;
+        }
+
+        loop {
+            let missing = self.missing_inputs(&participants);
+            if !missing.is_empty() {
+                let waiting = Some((self.next_tick, missing));
+                if self.last_waiting != waiting {
+                    let (tick, missing) = waiting.as_ref().unwrap();
+                    let message = encode_waiting(*tick, missing);
+                    for &(client_id, _) in participants.iter() {
+                        server.send_message(client_id, self.config.channel_id, message.clone());
+                    }
+                    self.last_waiting = waiting;
+                }
+                break;
+            }
+
+            let mut inputs: Vec<(ClientId, Bytes)> = self.inputs.remove(&self.next_tick).unwrap_or_default().into_iter().collect();
+            inputs.sort_by_key(|(client_id, _)| *client_id);
+            let message = encode_bundle(self.next_tick, &inputs);
+            for &(client_id, _) in participants.iter() {
+                server.send_message(client_id, self.config.channel_id, message.clone());
+            }
+
+            self.history.push_back((self.next_tick, inputs));
+            while self.history.len() > self.config.history_ticks {
+                self.history.pop_front();
+            }
+            self.next_tick += 1;
+        }
+    }
+
+    /// Returns the inputs of a bundled tick still in the history, or the participants with missing inputs.
+    pub fn poll_tick(&self, tick: u64) -> TickStatus {
+        if tick < self.next_tick {
+            if let Some((_, inputs)) = self.history.iter().find(|(t, _)| *t == tick) {
+                return TickStatus::Ready(inputs.clone());
+            }
+        }
+
+        let mut participants: Vec<(ClientId, u64)> = self.participants.iter().map(|(&id, &first_tick)| (id, first_tick)).collect();
+        participants.sort();
+        TickStatus::WaitingOn(self.missing_inputs(&participants))
+    }
+
+    fn missing_inputs(&self, participants: &[(ClientId, u64)]) -> Vec<ClientId> {
+        let inputs = self.inputs.get(&self.next_tick);
+        participants
+            .iter()
+            .filter(|(client_id, first_tick)| *first_tick <= self.next_tick && !inputs.is_some_and(|inputs| inputs.contains_key(client_id)))
+            .map(|(client_id, _)| *client_id)
+            .collect()
+    }
+}
+
+enum ServerMessage {
+    Bundle { tick: u64, inputs: Vec<(ClientId, Bytes)> },
+    Waiting { tick: u64, missing: Vec<ClientId> },
+}
+
+fn encode_bundle(tick: u64, inputs: &[(ClientId, Bytes)]) -> Bytes {
+    let len = 9 + inputs.iter().map(|(_, input)| 12 + input.len()).sum::<usize>();
+    let mut message = BytesMut::with_capacity(len);
+    message.put_u8(BUNDLE_MESSAGE);
+    message.put_u64_le(tick);
+    for (client_id, input) in inputs {
+        message.put_u64_le(client_id.raw());
+        message.put_u32_le(input.len() as u32);
+        message.put_slice(input);
+    }
+
+    message.freeze()
+}
+
+fn encode_waiting(tick: u64, missing: &[ClientId]) -> Bytes {
+    let mut message = BytesMut::with_capacity(9 + missing.len() * 8);
+    message.put_u8(WAITING_MESSAGE);
+    message.put_u64_le(tick);
+    for client_id in missing {
+        message.put_u64_le(client_id.raw());
+    }
+
+    message.freeze()
+}
+
+fn decode_server_message(message: Bytes) -> Option<ServerMessage> {
+    let read_u64 = |offset: usize| Some(u64::from_le_bytes(message.get(offset..offset + 8)?.try_into().unwrap()));
+    let tick = read_u64(1)?;
+    let mut offset = 9;
+    match *message.first()? {
+        BUNDLE_MESSAGE => {
+            let mut inputs = vec![];
+            while offset < message.len() {
+                let client_id = ClientId::from_raw(read_u64(offset)?);
+                let len = u32::from_le_bytes(message.get(offset + 8..offset + 12)?.try_into().unwrap()) as usize;
+                let end = offset + 12 + len;
+                if end > message.len() {
+                    return None;
+                }
+                inputs.push((client_id, message.slice(offset + 12..end)));
+                offset = end;
+            }
+            Some(ServerMessage::Bundle { tick, inputs })
+        }
+        WAITING_MESSAGE => {
+            let mut missing = vec![];
+            while offset < message.len() {
+                missing.push(ClientId::from_raw(read_u64(offset)?));
+                offset += 8;
+            }
+            Some(ServerMessage::Waiting { tick, missing })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ConnectionConfig;
+
+    struct Peer {
+        id: ClientId,
+        client: RenetClient,
+        session: LockstepSession,
+    }
+
+    fn add_peer(server: &mut RenetServer, lockstep: &mut LockstepServer, id: u64, config: &LockstepConfig) -> Peer {
+        let id = ClientId::from_raw(id);
+        server.add_connection(id);
+        lockstep.add_participant(id, server);
+        let mut client = RenetClient::new(ConnectionConfig::default());
+        client.set_connected();
+        Peer {
+            id,
+            client,
+            session: LockstepSession::new(config.clone()),
+        }
+    }
+
+    // Runs the lockstep server and exchanges the packets with the peers
+    fn step(server: &mut RenetServer, lockstep: &mut LockstepServer, peers: &mut [Peer]) {
+        for peer in peers.iter_mut() {
+            peer.session.update(&mut peer.client);
+            for packet in peer.client.get_packets_to_send() {
+                server.process_packet_from(&packet, peer.id).unwrap();
+            }
+        }
+
+        lockstep.update(server);
+
+        for peer in peers.iter_mut() {
+            for packet in server.get_packets_to_send(peer.id).unwrap() {
+                peer.client.process_packet(&packet);
+            }
+            peer.session.update(&mut peer.client);
+        }
+    }
+
+    #[test]
+    fn bundles_and_stalls() {
+        let config = LockstepConfig {
+            input_delay: 2,
+            ..Default::default()
+        };
+        let mut server = RenetServer::new(ConnectionConfig::default());
+        let mut lockstep = LockstepServer::new(config.clone());
+        let mut peers = vec![
+            add_peer(&mut server, &mut lockstep, 1, &config),
+            add_peer(&mut server, &mut lockstep, 2, &config),
+        ];
+
+        // Ticks before the input delay have no inputs
+        step(&mut server, &mut lockstep, &mut peers);
+        for tick in 0..2 {
+            assert_eq!(peers[0].session.poll_tick(tick), TickStatus::Ready(vec![]));
+        }
+
+        // Only the first peer sent its input for tick 2
+        peers[0].session.queue_local_input(0, "a0");
+        step(&mut server, &mut lockstep, &mut peers);
+        let waiting_on_second = TickStatus::WaitingOn(vec![peers[1].id]);
+        assert_eq!(peers[0].session.poll_tick(2), waiting_on_second);
+        assert_eq!(peers[1].session.poll_tick(3), waiting_on_second);
+        assert_eq!(lockstep.poll_tick(2), waiting_on_second);
+
+        peers[1].session.queue_local_input(0, "b0");
+        step(&mut server, &mut lockstep, &mut peers);
+        let expected = TickStatus::Ready(vec![(peers[0].id, Bytes::from("a0")), (peers[1].id, Bytes::from("b0"))]);
+        assert_eq!(lockstep.poll_tick(2), expected);
+        for peer in peers.iter_mut() {
+            assert_eq!(peer.session.poll_tick(2), expected);
+            // Returned only once
+            assert_eq!(peer.session.poll_tick(2), TickStatus::WaitingOn(vec![]));
+        }
+    }
+
+    #[test]
+    fn late_join_and_leave() {
+        let config = LockstepConfig {
+            input_delay: 1,
+            ..Default::default()
+        };
+        let mut server = RenetServer::new(ConnectionConfig::default());
+        let mut lockstep = LockstepServer::new(config.clone());
+        let mut peers = vec![add_peer(&mut server, &mut lockstep, 1, &config)];
+
+        for tick in 0..5 {
+            peers[0].session.queue_local_input(tick, format!("a{tick}"));
+            step(&mut server, &mut lockstep, &mut peers);
+        }
+        assert_eq!(lockstep.next_tick(), 6);
+
+        // The late joiner receives the previous bundles and is expected after the input delay
+        let late = add_peer(&mut server, &mut lockstep, 2, &config);
+        peers.push(late);
+        step(&mut server, &mut lockstep, &mut peers);
+        assert_eq!(peers[1].session.poll_tick(0), TickStatus::Ready(vec![]));
+        assert_eq!(
+            peers[1].session.poll_tick(3),
+            TickStatus::Ready(vec![(peers[0].id, Bytes::from("a2"))])
+        );
+
+        // Tick 6 only waits for the first peer, tick 7 for both
+        peers[0].session.queue_local_input(5, "a5");
+        peers[0].session.queue_local_input(6, "a6");
+        step(&mut server, &mut lockstep, &mut peers);
+        assert_eq!(
+            peers[1].session.poll_tick(6),
+            TickStatus::Ready(vec![(peers[0].id, Bytes::from("a5"))])
+        );
+        assert_eq!(peers[0].session.poll_tick(7), TickStatus::WaitingOn(vec![peers[1].id]));
+
+        // Inputs too late for their tick are discarded
+        peers[1].session.queue_local_input(4, "late");
+        step(&mut server, &mut lockstep, &mut peers);
+        assert_eq!(lockstep.poll_tick(7), TickStatus::WaitingOn(vec![peers[1].id]));
+
+        // Leaving unblocks the tick
+        lockstep.remove_participant(peers[1].id);
+        step(&mut server, &mut lockstep, &mut peers);
+        assert_eq!(
+            peers[0].session.poll_tick(7),
+            TickStatus::Ready(vec![(peers[0].id, Bytes::from("a6"))])
+        );
+    }
+
+    #[test]
+    fn far_future_inputs() {
+        let config = LockstepConfig::default();
+        let mut server = RenetServer::new(ConnectionConfig::default());
+        let mut lockstep = LockstepServer::new(config.clone());
+        let mut peers = vec![add_peer(&mut server, &mut lockstep, 1, &config)];
+
+        // Only the input at the limit is kept until its tick
+        let last_tick = config.max_ticks_ahead - config.input_delay;
+        peers[0].session.queue_local_input(0, "a0");
+        peers[0].session.queue_local_input(last_tick, "limit");
+        peers[0].session.queue_local_input(last_tick + 1, "ahead");
+        peers[0].session.queue_local_input(u64::MAX - config.input_delay, "max");
+        step(&mut server, &mut lockstep, &mut peers);
+        assert_eq!(
+            peers[0].session.poll_tick(config.input_delay),
+            TickStatus::Ready(vec![(peers[0].id, Bytes::from("a0"))])
+        );
+        assert_eq!(lockstep.inputs.keys().copied().collect::<Vec<_>>(), vec![config.max_ticks_ahead]);
+    }
+
+    #[test]
+    fn invalid_server_messages() {
+        assert!(decode_server_message(Bytes::from_static(&[0; 4])).is_none());
+        assert!(decode_server_message(Bytes::from_static(&[9; 9])).is_none());
+
+        let mut bundle = encode_bundle(3, &[(ClientId::from_raw(1), Bytes::from("input"))]).to_vec();
+        bundle.pop();
+        assert!(decode_server_message(bundle.into()).is_none());
+
+        let waiting = encode_waiting(3, &[ClientId::from_raw(1), ClientId::from_raw(2)]);
+        assert!(matches!(
+            decode_server_message(waiting),
+            Some(ServerMessage::Waiting { tick: 3, missing }) if missing.len() == 2
+        ));
+    }
+}
@@ -0,0 +1,124 @@
+use std::{collections::HashMap, fmt::Display, hash::Hash, time::Duration};
+
+// Interval over which the repeated occurrences of a log are coalesced
+pub(crate) const LOG_INTERVAL: Duration = Duration::from_secs(5);
+// Maximum number of keys tracked in an interval, bounds the memory when the keys come from the network
+const MAX_KEYS: usize = 256;
+
+// Rate limiter for logs that can be triggered by every received or sent packet.
+// The first occurrence of a key is logged right away, the following ones are counted until
+// its interval ends, then the count is returned by `update` to be logged as a summary.
+#[derive(Debug)]
+pub(crate) struct LogLimiter<K> {
+    current_time: Duration,
+    // Start of the interval of each key, and the occurrences suppressed in it
+    keys: HashMap<K, (Duration, u64)>,
+    // Occurrences of keys not tracked because too many keys were tracked, since the given time
+    untracked: (Duration, u64),
+}
+
+impl<K: Hash + Eq> Default for LogLimiter<K> {
+    fn default() -> Self {
+        Self {
+            current_time: Duration::ZERO,
+            keys: HashMap::new(),
+            untracked: (Duration::ZERO, 0),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone> LogLimiter<K> {
+    // Returns true if the occurrence should be logged, only the first one of each interval is.
+    pub fn should_log(&mut self, key: K) -> bool {
+        if let Some((_, suppressed)) = self.keys.get_mut(&key) {
+            *suppressed += 1;
+            return false;
+        }
+
+        if self.keys.len() >= MAX_KEYS {
+            self.untracked.1 += 1;
+            return false;
+        }
+
+        self.keys.insert(key, (self.current_time, 0));
+        true
+    }
+
+    // Advances the time and returns the number of suppressed occurrences of the keys whose interval ended,
+    // occurrences of untracked keys are returned without key.
+    pub fn update(&mut self, duration: Duration) -> Vec<(Option<K>, u64)> {
+        self.current_time += duration;
+        let current_time = self.current_time;
+        let mut summaries = vec![];
+
+        self.keys.retain(|key, (start, suppressed)| {
+            if current_time.saturating_sub(*start) < LOG_INTERVAL {
+                return true;
+            }
+            if *suppressed > 0 {
+                summaries.push((Some(key.clone()), *suppressed));
+            }
+            false
+        });
+
+        if current_time.saturating_sub(self.untracked.0) >= LOG_INTERVAL {
+            if self.untracked.1 > 0 {
+                summaries.push((None, self.untracked.1));
+            }
+            self.untracked = (current_time, 0);
+        }
+
+        summaries
+    }
+}
+
+impl<K: Hash + Eq + Clone + Display> LogLimiter<K> {
+    // Advances the time and logs the summary of the suppressed occurrences of the keys whose interval ended.
+    pub fn update_and_log(&mut self, duration: Duration, level: log::Level) {
+        for (key, suppressed) in self.update(duration) {
+            match key {
+                Some(key) => log::log!(level, "{key}: {suppressed} more times in the last {}s", LOG_INTERVAL.as_secs()),
+                None => log::log!(level, "{suppressed} more similar logs in the last {}s", LOG_INTERVAL.as_secs()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coalesce_repeated_logs() {
+        let mut limiter: LogLimiter<u8> = LogLimiter::default();
+        assert!(limiter.should_log(0));
+        assert!(!limiter.should_log(0));
+        assert!(!limiter.should_log(0));
+        assert!(limiter.should_log(1));
+
+        assert!(limiter.update(Duration::from_secs(1)).is_empty());
+        assert!(!limiter.should_log(0));
+
+        // Keys without suppressed occurrences are not summarized
+        let summaries = limiter.update(LOG_INTERVAL);
+        assert_eq!(summaries, vec![(Some(0), 3)]);
+
+        // A new interval starts with the next occurrence
+        assert!(limiter.should_log(0));
+        assert!(limiter.should_log(1));
+    }
+
+    #[test]
+    fn untracked_keys() {
+        let mut limiter: LogLimiter<usize> = LogLimiter::default();
+        for key in 0..MAX_KEYS {
+            assert!(limiter.should_log(key));
+        }
+        assert!(!limiter.should_log(MAX_KEYS));
+        assert!(!limiter.should_log(MAX_KEYS + 1));
+
+        let summaries = limiter.update(LOG_INTERVAL);
+        assert_eq!(summaries, vec![(None, 2)]);
+        assert!(limiter.should_log(MAX_KEYS));
+    }
+}
@@ -1,11 +1,77 @@
 use bytes::Bytes;
 use std::{fmt, ops::Range};
 
+use crate::transfer::MAX_TRANSFER_SLICES;
+use crate::QuotaKind;
+
 pub type Payload = Vec<u8>;
 
 // Sliced messages are split into SLICE_SIZE bytes chunks
 pub const SLICE_SIZE: usize = 1200;
 
+// Maximum size of a serialized packet, including padding
+pub const MAX_PACKET_SIZE: usize = 1300;
+
+/// Version of the packet encoding, sent with the channels configuration at the start of the connection.
+/// Peers with different versions can't parse each other's packets, the connection is terminated with
+/// [`DisconnectReason::ProtocolVersionMismatch`][crate::DisconnectReason::ProtocolVersionMismatch] instead.
+///
+/// Version 2 changed the packet types of the small message packets, their number of messages is a varint
+/// and the ids of reliable messages are encoded as the difference with the previous one.
+/// Packets from version 1 are rejected as an unsupported protocol version.
+pub const PROTOCOL_VERSION: u8 = 2;
+
+// Packet types, the first byte of every packet.
+// The Wireshark dissector is generated from them, see transport/dissector.rs.
+pub(crate) const RELIABLE_SLICE_PACKET: u8 = 2;
+pub(crate) const UNRELIABLE_SLICE_PACKET: u8 = 3;
+pub(crate) const ACK_PACKET: u8 = 4;
+pub(crate) const PING_PACKET: u8 = 6;
+pub(crate) const PONG_PACKET: u8 = 7;
+pub(crate) const TRANSFER_SLICE_PACKET: u8 = 8;
+pub(crate) const QUOTA_EXCEEDED_PACKET: u8 = 9;
+pub(crate) const CANCEL_RELIABLE_PACKET: u8 = 10;
+pub(crate) const SMALL_RELIABLE_PACKET: u8 = 11;
+pub(crate) const SMALL_UNRELIABLE_PACKET: u8 = 12;
+pub(crate) const CHANNEL_CONFIG_PACKET: u8 = 13;
+// Small messages and channels configuration of protocol version 1
+pub(crate) const V1_SMALL_RELIABLE_PACKET: u8 = 0;
+pub(crate) const V1_SMALL_UNRELIABLE_PACKET: u8 = 1;
+pub(crate) const V1_CHANNEL_CONFIG_PACKET: u8 = 5;
+
+// Messages above SLICE_SIZE bytes are sent in slices
+pub fn is_sliced(message_len: usize) -> bool {
+    message_len > SLICE_SIZE
+}
+
+pub fn num_slices(message_len: usize) -> usize {
+    (message_len + SLICE_SIZE - 1) / SLICE_SIZE
+}
+
+// Maximum serialized size of a message aggregated in a small packet,
+// messages from reliable channels are prefixed with their id, or its difference with the previous one.
+pub fn small_message_size(message_len: usize, message_id: Option<u64>) -> usize {
+    let id_len = message_id.map_or(0, octets::varint_len);
+    id_len + octets::varint_len(message_len as u64) + message_len
+}
+
+// Maximum serialized size of a small packet before its messages
+pub fn small_packet_header_size(sequence: u64) -> usize {
+    // Packet type, sequence, channel id and number of messages, a packet has less than 16384 messages
+    1 + octets::varint_len(sequence) + 1 + 2
+}
+
+// Serialized size of a slice packet before its payload
+pub fn slice_packet_header_size(sequence: u64, message_id: u64, slice_index: usize, num_slices: usize, payload_len: usize) -> usize {
+    // Packet type, sequence and channel id
+    1 + octets::varint_len(sequence)
+        + 1
+        + octets::varint_len(message_id)
+        + octets::varint_len(slice_index as u64)
+        + octets::varint_len(num_slices as u64)
+        + octets::varint_len(payload_len as u64)
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Slice {
     pub message_id: u64,
@@ -14,6 +80,22 @@ pub struct Slice {
     pub payload: Bytes,
 }
 
+// Describes a send channel so the remote can compare it with its receive channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelDescriptor {
+    pub channel_id: u8,
+    pub send_type: u8,
+    pub max_memory_usage_bytes: u64,
+    pub group: Option<u8>,
+    pub compression: u8,
+    // Hash of the compression dictionary, 0 without one
+    pub compression_dictionary: u64,
+    pub tracing_context: bool,
+    pub message_kinds: bool,
+    // Unreliable channels with deduplication send a sequence number before each message
+    pub deduplicate: bool,
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum Packet {
     // Small messages in a reliable channel are aggregated and sent in this packet
@@ -46,6 +128,37 @@ pub enum Packet {
         sequence: u64,
         ack_ranges: Vec<Range<u64>>,
     },
+    // Sent at the start of the connection with the description of all the send channels
+    ChannelConfig {
+        sequence: u64,
+        protocol_version: u8,
+        channels: Vec<ChannelDescriptor>,
+    },
+    // Application level ping, the remote answers with a pong with the same id
+    Ping {
+        sequence: u64,
+        ping_id: u64,
+    },
+    Pong {
+        sequence: u64,
+        ping_id: u64,
+    },
+    // Slice of the token to connect to another server, sent before the server disconnects the client
+    TransferSlice {
+        sequence: u64,
+        slice: Slice,
+    },
+    // Sent by the server when it disconnects the client for exceeding its quota
+    QuotaExceeded {
+        sequence: u64,
+        kind: QuotaKind,
+    },
+    // Cancels a sliced message of a reliable channel, the slices received of it are discarded
+    CancelReliable {
+        sequence: u64,
+        channel_id: u8,
+        message_id: u64,
+    },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -56,6 +169,9 @@ pub enum SerializationError {
     EmptySlice,
     InvalidAckRange,
     InvalidPacketType,
+    InvalidQuotaKind,
+    InvalidChannelConfig,
+    UnsupportedProtocolVersion,
 }
 
 impl std::error::Error for SerializationError {}
@@ -69,6 +185,9 @@ impl fmt::Display for SerializationError {
             InvalidNumSlices => write!(fmt, "invalid number of slices"),
             InvalidAckRange => write!(fmt, "invalid ack range"),
             InvalidPacketType => write!(fmt, "invalid packet type"),
+            InvalidQuotaKind => write!(fmt, "invalid quota kind"),
+            InvalidChannelConfig => write!(fmt, "invalid channel configuration"),
+            UnsupportedProtocolVersion => write!(fmt, "packet from an older protocol version"),
             SliceSizeAboveLimit => write!(fmt, "invalid slice size, it's above the limit of {} bytes", SLICE_SIZE),
             EmptySlice => write!(fmt, "invalid slice, slices cannot be empty"),
         }
@@ -88,7 +207,30 @@ impl Packet {
             | Packet::SmallUnreliable { sequence, .. }
             | Packet::UnreliableSlice { sequence, .. }
             | Packet::ReliableSlice { sequence, .. }
-            | Packet::Ack { sequence, .. } => *sequence,
+            | Packet::Ack { sequence, .. }
+            | Packet::ChannelConfig { sequence, .. }
+            | Packet::Ping { sequence, .. }
+            | Packet::Pong { sequence, .. }
+            | Packet::TransferSlice { sequence, .. }
+            | Packet::QuotaExceeded { sequence, .. }
+            | Packet::CancelReliable { sequence, .. } => *sequence,
+        }
+    }
+
+    #[cfg(feature = "log_dropped_packets")]
+    pub fn channel_id(&self) -> Option<u8> {
+        match self {
+            Packet::SmallReliable { channel_id, .. }
+            | Packet::SmallUnreliable { channel_id, .. }
+            | Packet::UnreliableSlice { channel_id, .. }
+            | Packet::ReliableSlice { channel_id, .. }
+            | Packet::CancelReliable { channel_id, .. } => Some(*channel_id),
+            Packet::Ack { .. }
+            | Packet::ChannelConfig { .. }
+            | Packet::Ping { .. }
+            | Packet::Pong { .. }
+            | Packet::TransferSlice { .. }
+            | Packet::QuotaExceeded { .. } => None,
         }
     }
 
@@ -101,12 +243,18 @@ impl Packet {
                 channel_id,
                 messages,
             } => {
-                b.put_u8(0)?;
+                b.put_u8(SMALL_RELIABLE_PACKET)?;
                 b.put_varint(*sequence)?;
                 b.put_u8(*channel_id)?;
-                b.put_u16(messages.len() as u16)?;
+                b.put_varint(messages.len() as u64)?;
+                // The first id is sent whole, the next ones as the difference with the previous one
+                let mut previous_id = None;
                 for (message_id, message) in messages {
-                    b.put_varint(*message_id)?;
+                    match previous_id {
+                        None => b.put_varint(*message_id)?,
+                        Some(previous_id) => b.put_varint(zigzag_encode(message_id.wrapping_sub(previous_id) as i64))?,
+                    };
+                    previous_id = Some(*message_id);
                     b.put_varint(message.len() as u64)?;
                     b.put_bytes(message)?;
                 }
@@ -116,10 +264,10 @@ impl Packet {
                 channel_id,
                 messages,
             } => {
-                b.put_u8(1)?;
+                b.put_u8(SMALL_UNRELIABLE_PACKET)?;
                 b.put_varint(*sequence)?;
                 b.put_u8(*channel_id)?;
-                b.put_u16(messages.len() as u16)?;
+                b.put_varint(messages.len() as u64)?;
                 for message in messages {
                     b.put_varint(message.len() as u64)?;
                     b.put_bytes(message)?;
@@ -130,7 +278,7 @@ impl Packet {
                 channel_id,
                 slice,
             } => {
-                b.put_u8(2)?;
+                b.put_u8(RELIABLE_SLICE_PACKET)?;
                 b.put_varint(*sequence)?;
                 b.put_u8(*channel_id)?;
                 b.put_varint(slice.message_id)?;
@@ -144,7 +292,7 @@ impl Packet {
                 channel_id,
                 slice,
             } => {
-                b.put_u8(3)?;
+                b.put_u8(UNRELIABLE_SLICE_PACKET)?;
                 b.put_varint(*sequence)?;
                 b.put_u8(*channel_id)?;
                 b.put_varint(slice.message_id)?;
@@ -154,7 +302,7 @@ impl Packet {
                 b.put_bytes(&slice.payload)?;
             }
             Packet::Ack { sequence, ack_ranges } => {
-                b.put_u8(4)?;
+                b.put_u8(ACK_PACKET)?;
                 b.put_varint(*sequence)?;
 
                 // Consider this ranges:
@@ -200,25 +348,111 @@ impl Packet {
                     previous_range_start = range.start;
                 }
             }
+            Packet::ChannelConfig {
+                sequence,
+                protocol_version,
+                channels,
+            } => {
+                b.put_u8(CHANNEL_CONFIG_PACKET)?;
+                b.put_varint(*sequence)?;
+                b.put_u8(*protocol_version)?;
+                b.put_varint(channels.len() as u64)?;
+                for channel in channels {
+                    b.put_u8(channel.channel_id)?;
+                    b.put_u8(channel.send_type)?;
+                    b.put_varint(channel.max_memory_usage_bytes)?;
+                    match channel.group {
+                        None => b.put_u8(0)?,
+                        Some(group) => {
+                            b.put_u8(1)?;
+                            b.put_u8(group)?
+                        }
+                    };
+                    b.put_u8(channel.compression)?;
+                    b.put_u64(channel.compression_dictionary)?;
+                    b.put_u8(channel.tracing_context as u8)?;
+                    b.put_u8(channel.message_kinds as u8)?;
+                    b.put_u8(channel.deduplicate as u8)?;
+                }
+            }
+            Packet::Ping { sequence, ping_id } => {
+                b.put_u8(PING_PACKET)?;
+                b.put_varint(*sequence)?;
+                b.put_varint(*ping_id)?;
+            }
+            Packet::Pong { sequence, ping_id } => {
+                b.put_u8(PONG_PACKET)?;
+                b.put_varint(*sequence)?;
+                b.put_varint(*ping_id)?;
+            }
+            Packet::TransferSlice { sequence, slice } => {
+                b.put_u8(TRANSFER_SLICE_PACKET)?;
+                b.put_varint(*sequence)?;
+                b.put_varint(slice.slice_index as u64)?;
+                b.put_varint(slice.num_slices as u64)?;
+                b.put_varint(slice.payload.len() as u64)?;
+                b.put_bytes(&slice.payload)?;
+            }
+            Packet::QuotaExceeded { sequence, kind } => {
+                b.put_u8(QUOTA_EXCEEDED_PACKET)?;
+                b.put_varint(*sequence)?;
+                match *kind {
+                    QuotaKind::ReceivedBytes => b.put_u8(0)?,
+                    QuotaKind::ReceivedMessages { channel_id } => {
+                        b.put_u8(1)?;
+                        b.put_u8(channel_id)?
+                    }
+                };
+            }
+            Packet::CancelReliable {
+                sequence,
+                channel_id,
+                message_id,
+            } => {
+                b.put_u8(CANCEL_RELIABLE_PACKET)?;
+                b.put_varint(*sequence)?;
+                b.put_u8(*channel_id)?;
+                b.put_varint(*message_id)?;
+            }
         }
 
         Ok(before - b.cap())
     }
 
+    /// Deserializes a packet, message payloads are copied out of the buffer.
+    #[cfg(test)]
     pub fn from_bytes(b: &mut octets::Octets) -> Result<Packet, SerializationError> {
+        Self::read(b, None)
+    }
+
+    /// Deserializes a packet, message payloads are slices sharing the memory of `packet`.
+    /// The octets must be reading from `packet`.
+    pub fn from_shared_bytes(packet: &Bytes, b: &mut octets::Octets) -> Result<Packet, SerializationError> {
+        debug_assert_eq!(packet.as_ptr(), b.buf().as_ptr());
+        Self::read(b, Some(packet))
+    }
+
+    fn read(b: &mut octets::Octets, source: Option<&Bytes>) -> Result<Packet, SerializationError> {
         let packet_type = b.get_u8()?;
         match packet_type {
-            0 => {
-                // SmallReliable
+            V1_SMALL_RELIABLE_PACKET | V1_SMALL_UNRELIABLE_PACKET | V1_CHANNEL_CONFIG_PACKET => {
+                Err(SerializationError::UnsupportedProtocolVersion)
+            }
+            SMALL_RELIABLE_PACKET => {
                 let sequence = b.get_varint()?;
                 let channel_id = b.get_u8()?;
-                let messages_len = b.get_u16()?;
+                let messages_len = b.get_varint()?;
                 let mut messages: Vec<(u64, Bytes)> = Vec::with_capacity(64);
+                let mut previous_id: Option<u64> = None;
                 for _ in 0..messages_len {
-                    let message_id = b.get_varint()?;
-                    let payload = b.get_bytes_with_varint_length()?;
+                    let message_id = match previous_id {
+                        None => b.get_varint()?,
+                        Some(previous_id) => previous_id.wrapping_add(zigzag_decode(b.get_varint()?) as u64),
+                    };
+                    previous_id = Some(message_id);
+                    let payload = read_payload(b, source)?;
 
-                    messages.push((message_id, payload.to_vec().into()));
+                    messages.push((message_id, payload));
                 }
 
                 Ok(Packet::SmallReliable {
@@ -227,15 +461,13 @@ impl Packet {
                     messages,
                 })
             }
-            1 => {
-                // SmallUnreliable
+            SMALL_UNRELIABLE_PACKET => {
                 let sequence = b.get_varint()?;
                 let channel_id = b.get_u8()?;
-                let messages_len = b.get_u16()?;
+                let messages_len = b.get_varint()?;
                 let mut messages: Vec<Bytes> = Vec::with_capacity(64);
                 for _ in 0..messages_len {
-                    let payload = b.get_bytes_with_varint_length()?;
-                    messages.push(payload.to_vec().into());
+                    messages.push(read_payload(b, source)?);
                 }
 
                 Ok(Packet::SmallUnreliable {
@@ -244,8 +476,7 @@ impl Packet {
                     messages,
                 })
             }
-            2 => {
-                // ReliableSlice
+            RELIABLE_SLICE_PACKET => {
                 let sequence = b.get_varint()?;
                 let channel_id = b.get_u8()?;
                 let message_id = b.get_varint()?;
@@ -255,7 +486,7 @@ impl Packet {
                     return Err(SerializationError::InvalidNumSlices);
                 }
 
-                let payload = b.get_bytes_with_varint_length()?;
+                let payload = read_payload(b, source)?;
 
                 if payload.is_empty() {
                     return Err(SerializationError::EmptySlice);
@@ -269,7 +500,7 @@ impl Packet {
                     message_id,
                     slice_index,
                     num_slices,
-                    payload: payload.to_vec().into(),
+                    payload,
                 };
                 Ok(Packet::ReliableSlice {
                     sequence,
@@ -277,8 +508,7 @@ impl Packet {
                     slice,
                 })
             }
-            3 => {
-                // UnreliableSlice
+            UNRELIABLE_SLICE_PACKET => {
                 let sequence = b.get_varint()?;
                 let channel_id = b.get_u8()?;
                 let message_id = b.get_varint()?;
@@ -288,13 +518,13 @@ impl Packet {
                     return Err(SerializationError::InvalidNumSlices);
                 }
 
-                let payload = b.get_bytes_with_varint_length()?;
+                let payload = read_payload(b, source)?;
 
                 let slice = Slice {
                     message_id,
                     slice_index,
                     num_slices,
-                    payload: payload.to_vec().into(),
+                    payload,
                 };
                 Ok(Packet::UnreliableSlice {
                     sequence,
@@ -302,8 +532,7 @@ impl Packet {
                     slice,
                 })
             }
-            4 => {
-                // Ack
+            ACK_PACKET => {
                 let sequence = b.get_varint()?;
 
                 let first_range_end = b.get_varint()?;
@@ -346,11 +575,138 @@ impl Packet {
 
                 Ok(Packet::Ack { sequence, ack_ranges })
             }
+            CHANNEL_CONFIG_PACKET => {
+                let sequence = b.get_varint()?;
+                let protocol_version = b.get_u8()?;
+                let num_channels = b.get_varint()?;
+                // Channel ids are u8, there can't be more channels than that
+                if num_channels > 256 {
+                    return Err(SerializationError::InvalidChannelConfig);
+                }
+
+                let mut channels = Vec::with_capacity(num_channels as usize);
+                for _ in 0..num_channels {
+                    let channel_id = b.get_u8()?;
+                    let send_type = b.get_u8()?;
+                    let max_memory_usage_bytes = b.get_varint()?;
+                    let group = match b.get_u8()? {
+                        0 => None,
+                        1 => Some(b.get_u8()?),
+                        _ => return Err(SerializationError::InvalidChannelConfig),
+                    };
+                    let compression = b.get_u8()?;
+                    let compression_dictionary = b.get_u64()?;
+                    let tracing_context = match b.get_u8()? {
+                        0 => false,
+                        1 => true,
+                        _ => return Err(SerializationError::InvalidChannelConfig),
+                    };
+                    let message_kinds = match b.get_u8()? {
+                        0 => false,
+                        1 => true,
+                        _ => return Err(SerializationError::InvalidChannelConfig),
+                    };
+                    let deduplicate = match b.get_u8()? {
+                        0 => false,
+                        1 => true,
+                        _ => return Err(SerializationError::InvalidChannelConfig),
+                    };
+                    channels.push(ChannelDescriptor {
+                        channel_id,
+                        send_type,
+                        max_memory_usage_bytes,
+                        group,
+                        compression,
+                        compression_dictionary,
+                        tracing_context,
+                        message_kinds,
+                        deduplicate,
+                    });
+                }
+
+                Ok(Packet::ChannelConfig {
+                    sequence,
+                    protocol_version,
+                    channels,
+                })
+            }
+            PING_PACKET => {
+                let sequence = b.get_varint()?;
+                let ping_id = b.get_varint()?;
+                Ok(Packet::Ping { sequence, ping_id })
+            }
+            PONG_PACKET => {
+                let sequence = b.get_varint()?;
+                let ping_id = b.get_varint()?;
+                Ok(Packet::Pong { sequence, ping_id })
+            }
+            TRANSFER_SLICE_PACKET => {
+                let sequence = b.get_varint()?;
+                let slice_index = b.get_varint()? as usize;
+                let num_slices = b.get_varint()? as usize;
+                if num_slices == 0 || num_slices > MAX_TRANSFER_SLICES || slice_index >= num_slices {
+                    return Err(SerializationError::InvalidNumSlices);
+                }
+
+                let payload = read_payload(b, source)?;
+                if payload.is_empty() {
+                    return Err(SerializationError::EmptySlice);
+                }
+                if payload.len() > SLICE_SIZE {
+                    return Err(SerializationError::SliceSizeAboveLimit);
+                }
+
+                let slice = Slice {
+                    message_id: 0,
+                    slice_index,
+                    num_slices,
+                    payload,
+                };
+                Ok(Packet::TransferSlice { sequence, slice })
+            }
+            QUOTA_EXCEEDED_PACKET => {
+                let sequence = b.get_varint()?;
+                let kind = match b.get_u8()? {
+                    0 => QuotaKind::ReceivedBytes,
+                    1 => QuotaKind::ReceivedMessages { channel_id: b.get_u8()? },
+                    _ => return Err(SerializationError::InvalidQuotaKind),
+                };
+                Ok(Packet::QuotaExceeded { sequence, kind })
+            }
+            CANCEL_RELIABLE_PACKET => {
+                let sequence = b.get_varint()?;
+                let channel_id = b.get_u8()?;
+                let message_id = b.get_varint()?;
+                Ok(Packet::CancelReliable {
+                    sequence,
+                    channel_id,
+                    message_id,
+                })
+            }
             _ => Err(SerializationError::InvalidPacketType),
         }
     }
 }
 
+// Maps signed differences to unsigned ones, small in absolute value stay small: 0, -1, 1, -2... to 0, 1, 2, 3...
+// Ids are ascending in the packets built by the channels, their differences are small and positive.
+fn zigzag_encode(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn zigzag_decode(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+// Reads a payload prefixed by its length, sharing the memory of the source packet when available.
+fn read_payload(b: &mut octets::Octets, source: Option<&Bytes>) -> Result<Bytes, SerializationError> {
+    let payload = b.get_bytes_with_varint_length()?;
+    match source {
+        Some(source) => Ok(source.slice(b.off() - payload.len()..b.off())),
+        None => Ok(payload.to_vec().into()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -372,6 +728,168 @@ mod tests {
         assert_eq!(packet, recv_packet);
     }
 
+    #[test]
+    fn packet_size_math() {
+        let mut buffer = [0u8; 1300];
+        let messages: Vec<(u64, Bytes)> = vec![(1, vec![0; 10].into()), (100, vec![1; 100].into())];
+        let packet = Packet::SmallReliable {
+            sequence: 70_000,
+            channel_id: 0,
+            messages: messages.clone(),
+        };
+        let len = packet.to_bytes(&mut octets::OctetsMut::with_slice(&mut buffer)).unwrap();
+        let expected =
+            small_packet_header_size(70_000) + messages.iter().map(|(id, m)| small_message_size(m.len(), Some(*id))).sum::<usize>();
+        // The number of messages takes one byte of the two estimated
+        assert_eq!(len, expected - 1);
+
+        let slice = Slice {
+            message_id: 20_000,
+            slice_index: 3,
+            num_slices: 4,
+            payload: vec![0; SLICE_SIZE].into(),
+        };
+        let expected = slice_packet_header_size(5, slice.message_id, slice.slice_index, slice.num_slices, SLICE_SIZE) + SLICE_SIZE;
+        let packet = Packet::ReliableSlice {
+            sequence: 5,
+            channel_id: 0,
+            slice,
+        };
+        let len = packet.to_bytes(&mut octets::OctetsMut::with_slice(&mut buffer)).unwrap();
+        assert_eq!(len, expected);
+
+        assert!(!is_sliced(SLICE_SIZE));
+        assert_eq!(num_slices(SLICE_SIZE * 2 + 1), 3);
+    }
+
+    #[test]
+    fn delta_message_ids() {
+        let mut buffer = [0u8; 1300];
+        let messages: Vec<(u64, Bytes)> = (0..50).map(|i| (100_000 + i, vec![i as u8; 10].into())).collect();
+        let packet = Packet::SmallReliable {
+            sequence: 70_000,
+            channel_id: 0,
+            messages: messages.clone(),
+        };
+        let len = packet.to_bytes(&mut octets::OctetsMut::with_slice(&mut buffer)).unwrap();
+        let recv_packet = Packet::from_bytes(&mut octets::Octets::with_slice(&buffer)).unwrap();
+        assert_eq!(packet, recv_packet);
+
+        // Version 1 had a u16 number of messages and the full id of every message
+        let version_1_len =
+            1 + octets::varint_len(70_000) + 1 + 2 + messages.iter().map(|(id, _)| octets::varint_len(*id) + 1 + 10).sum::<usize>();
+        assert!(len * 100 <= version_1_len * 85, "{len} bytes, {version_1_len} bytes before");
+
+        // Out of order ids are still encoded correctly
+        let packet = Packet::SmallReliable {
+            sequence: 0,
+            channel_id: 0,
+            messages: vec![(5, vec![0].into()), (2, vec![1].into()), (20_000, vec![2].into())],
+        };
+        packet.to_bytes(&mut octets::OctetsMut::with_slice(&mut buffer)).unwrap();
+        assert_eq!(Packet::from_bytes(&mut octets::Octets::with_slice(&buffer)).unwrap(), packet);
+
+        buffer[0] = 0;
+        let legacy = Packet::from_bytes(&mut octets::Octets::with_slice(&buffer));
+        assert_eq!(legacy, Err(SerializationError::UnsupportedProtocolVersion));
+    }
+
+    #[test]
+    fn truncated_and_corrupted_packets() {
+        let packets = vec![
+            Packet::SmallReliable {
+                sequence: 300,
+                channel_id: 2,
+                messages: vec![(70_000, vec![1; 100].into()), (70_001, vec![2; 10].into())],
+            },
+            Packet::SmallUnreliable {
+                sequence: 0,
+                channel_id: 0,
+                messages: vec![vec![0; 3].into(), vec![1; 200].into()],
+            },
+            Packet::ReliableSlice {
+                sequence: 10,
+                channel_id: 1,
+                slice: Slice {
+                    message_id: 5,
+                    slice_index: 1,
+                    num_slices: 3,
+                    payload: vec![3; SLICE_SIZE].into(),
+                },
+            },
+            Packet::Ack {
+                sequence: 1,
+                ack_ranges: vec![3..7, 10..20, 30..100],
+            },
+            Packet::ChannelConfig {
+                sequence: 2,
+                protocol_version: PROTOCOL_VERSION,
+                channels: vec![ChannelDescriptor {
+                    channel_id: 0,
+                    send_type: 1,
+                    max_memory_usage_bytes: 1024,
+                    group: Some(1),
+                    compression: 0,
+                    compression_dictionary: 0,
+                    tracing_context: false,
+                    message_kinds: true,
+                    deduplicate: false,
+                }],
+            },
+        ];
+
+        let mut buffer = [0u8; MAX_PACKET_SIZE];
+        // Deterministic xorshift, the packets are corrupted the same way every run
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut random = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for packet in packets {
+            let len = packet.to_bytes(&mut octets::OctetsMut::with_slice(&mut buffer)).unwrap();
+            for truncated_len in 0..len {
+                let truncated = Packet::from_bytes(&mut octets::Octets::with_slice(&buffer[..truncated_len]));
+                assert!(truncated.is_err(), "{packet:?} truncated to {truncated_len} bytes");
+            }
+
+            // Corrupted packets may still parse, but never panic
+            for _ in 0..1000 {
+                let mut corrupted = buffer[..len].to_vec();
+                let index = 1 + random() as usize % (len - 1);
+                corrupted[index] = random() as u8;
+                let _ = Packet::from_bytes(&mut octets::Octets::with_slice(&corrupted));
+            }
+        }
+    }
+
+    #[test]
+    fn deserialize_shared_payloads() {
+        let mut buffer = [0u8; 1300];
+        let packet = Packet::SmallUnreliable {
+            sequence: 0,
+            channel_id: 0,
+            messages: vec![vec![0, 0, 0].into(), vec![1, 1, 1].into()],
+        };
+
+        let mut b = octets::OctetsMut::with_slice(&mut buffer);
+        let len = packet.to_bytes(&mut b).unwrap();
+
+        let bytes = Bytes::copy_from_slice(&buffer[..len]);
+        let mut b = octets::Octets::with_slice(&bytes);
+        let recv_packet = Packet::from_shared_bytes(&bytes, &mut b).unwrap();
+        assert_eq!(packet, recv_packet);
+
+        let Packet::SmallUnreliable { messages, .. } = recv_packet else {
+            unreachable!()
+        };
+        let packet_range = bytes.as_ptr_range();
+        for message in messages {
+            assert!(packet_range.contains(&message.as_ptr()));
+        }
+    }
+
     #[test]
     fn serialize_small_unreliable_packet() {
         let mut buffer = [0u8; 1300];
@@ -451,4 +969,129 @@ mod tests {
         let recv_packet = Packet::from_bytes(&mut b).unwrap();
         assert_eq!(packet, recv_packet);
     }
+
+    #[test]
+    fn serialize_channel_config_packet() {
+        let mut buffer = [0u8; 1300];
+
+        let packet = Packet::ChannelConfig {
+            sequence: 0,
+            protocol_version: PROTOCOL_VERSION,
+            channels: vec![
+                ChannelDescriptor {
+                    channel_id: 0,
+                    send_type: 0,
+                    max_memory_usage_bytes: 5 * 1024 * 1024,
+                    group: None,
+                    compression: 0,
+                    compression_dictionary: 0,
+                    tracing_context: false,
+                    message_kinds: true,
+                    deduplicate: false,
+                },
+                ChannelDescriptor {
+                    channel_id: 3,
+                    send_type: 1,
+                    max_memory_usage_bytes: 1024,
+                    group: Some(4),
+                    compression: 1,
+                    compression_dictionary: 42,
+                    tracing_context: true,
+                    message_kinds: false,
+                    deduplicate: true,
+                },
+            ],
+        };
+
+        let mut b = octets::OctetsMut::with_slice(&mut buffer);
+        packet.to_bytes(&mut b).unwrap();
+
+        let mut b = octets::Octets::with_slice(&buffer);
+        let recv_packet = Packet::from_bytes(&mut b).unwrap();
+        assert_eq!(packet, recv_packet);
+    }
+
+    #[test]
+    fn serialize_ping_packets() {
+        let mut buffer = [0u8; 1300];
+
+        for packet in [
+            Packet::Ping { sequence: 3, ping_id: 300 },
+            Packet::Pong { sequence: 4, ping_id: 300 },
+        ] {
+            let mut b = octets::OctetsMut::with_slice(&mut buffer);
+            packet.to_bytes(&mut b).unwrap();
+
+            let mut b = octets::Octets::with_slice(&buffer);
+            let recv_packet = Packet::from_bytes(&mut b).unwrap();
+            assert_eq!(packet, recv_packet);
+        }
+    }
+
+    #[test]
+    fn serialize_cancel_reliable_packet() {
+        let mut buffer = [0u8; 1300];
+        let packet = Packet::CancelReliable {
+            sequence: 5,
+            channel_id: 2,
+            message_id: 40,
+        };
+
+        let mut b = octets::OctetsMut::with_slice(&mut buffer);
+        packet.to_bytes(&mut b).unwrap();
+
+        let mut b = octets::Octets::with_slice(&buffer);
+        assert_eq!(Packet::from_bytes(&mut b).unwrap(), packet);
+    }
+
+    #[test]
+    fn serialize_quota_exceeded_packet() {
+        let mut buffer = [0u8; 1300];
+
+        for kind in [QuotaKind::ReceivedBytes, QuotaKind::ReceivedMessages { channel_id: 2 }] {
+            let packet = Packet::QuotaExceeded { sequence: 5, kind };
+            let mut b = octets::OctetsMut::with_slice(&mut buffer);
+            packet.to_bytes(&mut b).unwrap();
+
+            let mut b = octets::Octets::with_slice(&buffer);
+            assert_eq!(Packet::from_bytes(&mut b).unwrap(), packet);
+        }
+
+        let invalid = [9, 5, 2];
+        let mut b = octets::Octets::with_slice(&invalid);
+        assert_eq!(Packet::from_bytes(&mut b), Err(SerializationError::InvalidQuotaKind));
+    }
+
+    #[test]
+    fn serialize_transfer_slice_packet() {
+        let mut buffer = [0u8; 1300];
+        let packet = Packet::TransferSlice {
+            sequence: 5,
+            slice: Slice {
+                message_id: 0,
+                slice_index: 1,
+                num_slices: 2,
+                payload: vec![7; 100].into(),
+            },
+        };
+
+        let mut b = octets::OctetsMut::with_slice(&mut buffer);
+        packet.to_bytes(&mut b).unwrap();
+        let mut b = octets::Octets::with_slice(&buffer);
+        assert_eq!(Packet::from_bytes(&mut b).unwrap(), packet);
+
+        let packet = Packet::TransferSlice {
+            sequence: 5,
+            slice: Slice {
+                message_id: 0,
+                slice_index: 2,
+                num_slices: 2,
+                payload: vec![7; 100].into(),
+            },
+        };
+        let mut b = octets::OctetsMut::with_slice(&mut buffer);
+        packet.to_bytes(&mut b).unwrap();
+        let mut b = octets::Octets::with_slice(&buffer);
+        assert_eq!(Packet::from_bytes(&mut b), Err(SerializationError::InvalidNumSlices));
+    }
 }
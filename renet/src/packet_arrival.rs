@@ -0,0 +1,170 @@
+use std::time::Duration;
+
+// Weight of a new sample in the smoothed values
+const SMOOTHING: f64 = 0.1;
+// A gap this many times longer than the smoothed interval may be a stall of the peer
+const STALL_GAP_FACTOR: f64 = 4.0;
+// Shorter gaps are not considered stalls, they can come from the peer update rate
+const MIN_STALL_GAP: Duration = Duration::from_millis(100);
+// Packets arriving together after the gap, relative to the usual ones, for it to be a stall instead of packet loss
+const STALL_BURST_FACTOR: f64 = 2.0;
+const MIN_STALL_BURST_PACKETS: u32 = 3;
+// How long a detected stall is reported
+const STALL_REPORT_DURATION: Duration = Duration::from_secs(2);
+
+// Arrival statistics of the packets received from the peer.
+// Packets are received in arrivals: the packets processed at the same time, usually in the same update.
+//
+// A peer that stalls keeps the packets of its late ticks until it catches up and sends them together,
+// so a stall is seen as a gap followed by an arrival bigger than usual. Packets lost in the network also
+// leave a gap, but the next arrivals have their usual size.
+#[derive(Debug, Default)]
+pub(crate) struct PacketArrival {
+    last_arrival: Option<Duration>,
+    // Packets received in the last arrival
+    arrival_packets: u32,
+    // True if the last arrival came after a gap that could be a stall
+    after_gap: bool,
+    smoothed_interval: Option<f64>,
+    smoothed_arrival_packets: Option<f64>,
+    last_stall: Option<Duration>,
+    stalls: u64,
+}
+
+fn smooth(smoothed: &mut Option<f64>, sample: f64) {
+    *smoothed = Some(match *smoothed {
+        Some(value) => value + (sample - value) * SMOOTHING,
+        None => sample,
+    });
+}
+
+impl PacketArrival {
+    pub fn received_packet(&mut self, current_time: Duration) {
+        match self.last_arrival {
+            Some(last_arrival) if last_arrival == current_time => self.arrival_packets += 1,
+            Some(last_arrival) => {
+                // The burst after a gap is left out of the smoothed values, it would hide the next stalls
+                if !self.after_gap {
+                    smooth(&mut self.smoothed_arrival_packets, self.arrival_packets as f64);
+                }
+
+                let gap = current_time.saturating_sub(last_arrival);
+                self.after_gap = match self.smoothed_interval {
+                    Some(interval) => gap >= MIN_STALL_GAP && gap.as_secs_f64() > interval * STALL_GAP_FACTOR,
+                    None => false,
+                };
+                if !self.after_gap {
+                    smooth(&mut self.smoothed_interval, gap.as_secs_f64());
+                }
+
+                self.last_arrival = Some(current_time);
+                self.arrival_packets = 1;
+            }
+            None => {
+                self.last_arrival = Some(current_time);
+                self.arrival_packets = 1;
+            }
+        }
+
+        if self.after_gap {
+            let usual_packets = self.smoothed_arrival_packets.unwrap_or(1.0);
+            let burst_packets = (usual_packets * STALL_BURST_FACTOR).max(MIN_STALL_BURST_PACKETS as f64);
+            if self.arrival_packets as f64 >= burst_packets && self.last_stall != Some(current_time) {
+                self.last_stall = Some(current_time);
+                self.stalls += 1;
+            }
+        }
+    }
+
+    // Packets received per second, decreases while no packets are received.
+    pub fn packet_rate(&self, current_time: Duration) -> f64 {
+        let (Some(last_arrival), Some(interval), Some(arrival_packets)) =
+            (self.last_arrival, self.smoothed_interval, self.smoothed_arrival_packets)
+        else {
+            return 0.0;
+        };
+
+        let interval = interval.max(current_time.saturating_sub(last_arrival).as_secs_f64());
+        if interval == 0.0 {
+            return 0.0;
+        }
+        arrival_packets / interval
+    }
+
+    // Usual interval between the arrivals, None until two arrivals were received.
+    pub fn arrival_interval(&self) -> Option<Duration> {
+        self.smoothed_interval.map(Duration::from_secs_f64)
+    }
+
+    pub fn stall_detected(&self, current_time: Duration) -> bool {
+        match self.last_stall {
+            Some(last_stall) => current_time.saturating_sub(last_stall) < STALL_REPORT_DURATION,
+            None => false,
+        }
+    }
+
+    pub fn stalls(&self) -> u64 {
+        self.stalls
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK: Duration = Duration::from_millis(50);
+
+    fn receive_ticks(arrival: &mut PacketArrival, current_time: &mut Duration, ticks: u32, packets_per_tick: u32) {
+        for _ in 0..ticks {
+            *current_time += TICK;
+            for _ in 0..packets_per_tick {
+                arrival.received_packet(*current_time);
+            }
+        }
+    }
+
+    #[test]
+    fn packet_rate() {
+        let mut arrival = PacketArrival::default();
+        let mut current_time = Duration::ZERO;
+        assert_eq!(arrival.packet_rate(current_time), 0.0);
+
+        receive_ticks(&mut arrival, &mut current_time, 20, 2);
+        assert!((arrival.packet_rate(current_time) - 40.0).abs() < 0.01);
+
+        // The rate drops while nothing is received
+        current_time += Duration::from_secs(1);
+        assert!((arrival.packet_rate(current_time) - 2.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn gap_with_burst_is_stall() {
+        let mut arrival = PacketArrival::default();
+        let mut current_time = Duration::ZERO;
+        receive_ticks(&mut arrival, &mut current_time, 20, 2);
+
+        // The peer catches up and sends the packets of its late ticks together
+        current_time += Duration::from_millis(500);
+        for _ in 0..10 {
+            arrival.received_packet(current_time);
+        }
+        assert!(arrival.stall_detected(current_time));
+        assert_eq!(arrival.stalls(), 1);
+
+        current_time += STALL_REPORT_DURATION;
+        assert!(!arrival.stall_detected(current_time));
+    }
+
+    #[test]
+    fn gap_without_burst_is_not_stall() {
+        let mut arrival = PacketArrival::default();
+        let mut current_time = Duration::ZERO;
+        receive_ticks(&mut arrival, &mut current_time, 20, 3);
+
+        // Packets lost in the network, the next ones arrive as usual
+        current_time += Duration::from_millis(500);
+        receive_ticks(&mut arrival, &mut current_time, 20, 3);
+        assert!(!arrival.stall_detected(current_time));
+        assert_eq!(arrival.stalls(), 0);
+    }
+}
@@ -0,0 +1,144 @@
+use std::{collections::VecDeque, time::Duration};
+
+// Pings waiting for their pong, older ones are considered lost when more are sent
+pub(crate) const MAX_OUTSTANDING_PINGS: usize = 16;
+
+/// Identifier of a ping sent with [`RenetClient::send_ping`][crate::RenetClient::send_ping].
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Ord, PartialOrd)]
+pub struct PingId(u64);
+
+impl PingId {
+    /// Creates a [`PingId`] from a raw 64 bit value.
+    pub const fn from_raw(value: u64) -> Self {
+        Self(value)
+    }
+
+    /// Returns the raw 64 bit value of the [`PingId`]
+    pub fn raw(&self) -> u64 {
+        self.0
+    }
+}
+
+impl std::fmt::Display for PingId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// Application level pings: measured from the call to send_ping until the pong is processed,
+// including the time both ends take to send and process the packets.
+#[derive(Debug, Default)]
+pub(crate) struct Pings {
+    next_ping_id: u64,
+    // Pings to send in the next packets
+    pending: Vec<u64>,
+    // Sent pings and the time send_ping was called, oldest first
+    outstanding: VecDeque<(u64, Duration)>,
+    // Round trips of the answered pings not polled yet, oldest first
+    answered: VecDeque<(u64, Duration)>,
+    // Pongs to send in the next packets, for the pings of the remote
+    pongs: Vec<u64>,
+    smoothed_rtt: f64,
+}
+
+impl Pings {
+    pub fn send_ping(&mut self, current_time: Duration) -> PingId {
+        let ping_id = self.next_ping_id;
+        self.next_ping_id += 1;
+
+        if self.outstanding.len() >= MAX_OUTSTANDING_PINGS {
+            let (lost_ping_id, _) = self.outstanding.pop_front().unwrap();
+            self.pending.retain(|&pending| pending != lost_ping_id);
+        }
+        self.outstanding.push_back((ping_id, current_time));
+        self.pending.push(ping_id);
+
+        PingId(ping_id)
+    }
+
+    pub fn received_ping(&mut self, ping_id: u64) {
+        if self.pongs.len() < MAX_OUTSTANDING_PINGS {
+            self.pongs.push(ping_id);
+        }
+    }
+
+    pub fn received_pong(&mut self, ping_id: u64, current_time: Duration) {
+        let Some(index) = self.outstanding.iter().position(|&(id, _)| id == ping_id) else {
+            return;
+        };
+        let (_, sent_at) = self.outstanding.remove(index).unwrap();
+        let rtt = current_time.saturating_sub(sent_at);
+
+        if self.smoothed_rtt < f64::EPSILON {
+            self.smoothed_rtt = rtt.as_secs_f64();
+        } else {
+            self.smoothed_rtt = self.smoothed_rtt * 0.875 + rtt.as_secs_f64() * 0.125;
+        }
+
+        if self.answered.len() >= MAX_OUTSTANDING_PINGS {
+            self.answered.pop_front();
+        }
+        self.answered.push_back((ping_id, rtt));
+    }
+
+    pub fn poll_pong(&mut self, ping_id: PingId) -> Option<Duration> {
+        let index = self.answered.iter().position(|&(id, _)| id == ping_id.0)?;
+        self.answered.remove(index).map(|(_, rtt)| rtt)
+    }
+
+    // Pings and pongs to send, in that order
+    pub fn take_to_send(&mut self) -> (Vec<u64>, Vec<u64>) {
+        (std::mem::take(&mut self.pending), std::mem::take(&mut self.pongs))
+    }
+
+    pub fn rtt(&self) -> f64 {
+        self.smoothed_rtt
+    }
+
+    pub fn reset_rtt(&mut self) {
+        self.smoothed_rtt = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ping_round_trip() {
+        let mut local = Pings::default();
+        let mut remote = Pings::default();
+
+        let ping_id = local.send_ping(Duration::from_millis(10));
+        let (pings, _) = local.take_to_send();
+        for ping_id in pings {
+            remote.received_ping(ping_id);
+        }
+        let (_, pongs) = remote.take_to_send();
+        assert_eq!(local.poll_pong(ping_id), None);
+        for ping_id in pongs {
+            local.received_pong(ping_id, Duration::from_millis(60));
+        }
+
+        assert_eq!(local.poll_pong(ping_id), Some(Duration::from_millis(50)));
+        assert_eq!(local.poll_pong(ping_id), None);
+        assert!((local.rtt() - 0.05).abs() < 1e-9);
+    }
+
+    #[test]
+    fn outstanding_pings_are_capped() {
+        let mut pings = Pings::default();
+        let first = pings.send_ping(Duration::ZERO);
+        for _ in 0..MAX_OUTSTANDING_PINGS {
+            pings.send_ping(Duration::ZERO);
+        }
+
+        let (sent, _) = pings.take_to_send();
+        assert_eq!(sent.len(), MAX_OUTSTANDING_PINGS);
+        assert!(!sent.contains(&first.raw()));
+
+        // A late pong of a dropped ping is ignored
+        pings.received_pong(first.raw(), Duration::from_millis(10));
+        assert_eq!(pings.poll_pong(first), None);
+    }
+}
@@ -0,0 +1,166 @@
+use std::{collections::HashMap, time::Duration};
+
+/// Limits of what a client can send to the server, see [`ConnectionConfig::receive_quota`][crate::ConnectionConfig::receive_quota].
+///
+/// A client over a limit is first reported with [`ServerEvent::ClientQuotaWarning`][crate::ServerEvent::ClientQuotaWarning],
+/// if it's still over the limit after the grace period it's disconnected with [`DisconnectReason::QuotaExceeded`][crate::DisconnectReason::QuotaExceeded].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReceiveQuota {
+    /// Sustained bytes per second that can be received from the client, counting the whole packets.
+    pub max_bytes_per_sec: u64,
+    /// Bytes that can be received in a burst above the sustained rate.
+    pub burst_bytes: u64,
+    /// Messages per second that can be received in each channel, a sliced message counts once. None disables it.
+    pub max_messages_per_sec: Option<u32>,
+    /// Time a client can stay over a limit after the warning before being disconnected.
+    pub grace_period: Duration,
+}
+
+/// Limit of a [`ReceiveQuota`] exceeded by a client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QuotaKind {
+    /// Bytes per second, including the burst.
+    ReceivedBytes,
+    /// Messages per second in the channel.
+    ReceivedMessages { channel_id: u8 },
+}
+
+impl std::fmt::Display for QuotaKind {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            QuotaKind::ReceivedBytes => write!(fmt, "received bytes per second"),
+            QuotaKind::ReceivedMessages { channel_id } => write!(fmt, "received messages per second in channel {channel_id}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum QuotaEvent {
+    // A limit was just exceeded
+    Warning(QuotaKind),
+    // A limit is exceeded for longer than the grace period
+    Exceeded(QuotaKind),
+}
+
+// Received bytes and messages of a connection against its quota.
+//
+// Each limit is a bucket refilled at its rate up to its burst, received data is taken from it.
+// A bucket below zero is over its limit, the debt is capped at the burst so a client that
+// stops sending is back within its quota after at most the time to refill the burst.
+#[derive(Debug)]
+pub(crate) struct QuotaTracker {
+    quota: ReceiveQuota,
+    bytes: f64,
+    // Allowance of each channel that received messages
+    messages: HashMap<u8, f64>,
+    last_refill: Duration,
+    // Limit exceeded and the time it was first exceeded
+    exceeded_since: Option<(QuotaKind, Duration)>,
+}
+
+impl QuotaTracker {
+    pub fn new(quota: ReceiveQuota) -> Self {
+        Self {
+            quota,
+            bytes: quota.burst_bytes as f64,
+            messages: HashMap::new(),
+            last_refill: Duration::ZERO,
+            exceeded_since: None,
+        }
+    }
+
+    pub fn received_bytes(&mut self, bytes: usize) {
+        let burst = self.quota.burst_bytes as f64;
+        self.bytes = (self.bytes - bytes as f64).max(-burst);
+    }
+
+    pub fn received_messages(&mut self, channel_id: u8, count: usize) {
+        let Some(max_messages_per_sec) = self.quota.max_messages_per_sec else {
+            return;
+        };
+
+        let burst = max_messages_per_sec as f64;
+        let messages = self.messages.entry(channel_id).or_insert(burst);
+        *messages = (*messages - count as f64).max(-burst);
+    }
+
+    pub fn update(&mut self, current_time: Duration) -> Option<QuotaEvent> {
+        let elapsed = current_time.saturating_sub(self.last_refill).as_secs_f64();
+        self.last_refill = current_time;
+
+        let burst_bytes = self.quota.burst_bytes as f64;
+        self.bytes = (self.bytes + elapsed * self.quota.max_bytes_per_sec as f64).min(burst_bytes);
+        let mut exceeded = (self.bytes < 0.0).then_some(QuotaKind::ReceivedBytes);
+
+        if let Some(max_messages_per_sec) = self.quota.max_messages_per_sec {
+            let burst = max_messages_per_sec as f64;
+            let mut channel_ids: Vec<u8> = self.messages.keys().copied().collect();
+            channel_ids.sort_unstable();
+            for channel_id in channel_ids {
+                let messages = self.messages.get_mut(&channel_id).unwrap();
+                *messages = (*messages + elapsed * burst).min(burst);
+                if *messages < 0.0 && exceeded.is_none() {
+                    exceeded = Some(QuotaKind::ReceivedMessages { channel_id });
+                }
+            }
+        }
+
+        let Some(kind) = exceeded else {
+            self.exceeded_since = None;
+            return None;
+        };
+        match self.exceeded_since {
+            None => {
+                self.exceeded_since = Some((kind, current_time));
+                Some(QuotaEvent::Warning(kind))
+            }
+            Some((first_kind, since)) if current_time.saturating_sub(since) >= self.quota.grace_period => {
+                Some(QuotaEvent::Exceeded(first_kind))
+            }
+            Some(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warning_then_exceeded() {
+        let mut tracker = QuotaTracker::new(ReceiveQuota {
+            max_bytes_per_sec: 1000,
+            burst_bytes: 500,
+            max_messages_per_sec: Some(10),
+            grace_period: Duration::from_secs(1),
+        });
+
+        // The burst is allowed
+        tracker.received_bytes(500);
+        assert_eq!(tracker.update(Duration::ZERO), None);
+
+        tracker.received_bytes(600);
+        assert_eq!(
+            tracker.update(Duration::from_millis(100)),
+            Some(QuotaEvent::Warning(QuotaKind::ReceivedBytes))
+        );
+        for step in 2..11 {
+            tracker.received_bytes(1000);
+            assert_eq!(tracker.update(Duration::from_millis(100 * step)), None);
+        }
+        tracker.received_bytes(1000);
+        assert_eq!(
+            tracker.update(Duration::from_millis(1100)),
+            Some(QuotaEvent::Exceeded(QuotaKind::ReceivedBytes))
+        );
+
+        // Back within the quota once the client stops, the debt is capped at the burst
+        assert_eq!(tracker.update(Duration::from_millis(1600)), None);
+
+        tracker.received_messages(2, 25);
+        assert_eq!(
+            tracker.update(Duration::from_millis(1600)),
+            Some(QuotaEvent::Warning(QuotaKind::ReceivedMessages { channel_id: 2 }))
+        );
+    }
+}
@@ -1,16 +1,52 @@
+use crate::channel::bandwidth::ChannelBandwidth;
+use crate::channel::builder::check_channels_config;
+use crate::channel::compression::ChannelCompression;
+use crate::channel::group::ChannelGroups;
+use crate::channel::kind::MessageKinds;
 use crate::channel::reliable::{ReceiveChannelReliable, SendChannelReliable};
-use crate::channel::unreliable::{ReceiveChannelUnreliable, SendChannelUnreliable};
-use crate::channel::{ChannelConfig, DefaultChannel, SendType};
-use crate::connection_stats::ConnectionStats;
-use crate::error::DisconnectReason;
-use crate::packet::{Packet, Payload};
-use bytes::Bytes;
+use crate::channel::trace::{MessageTracer, TRACE_ID_BYTES};
+use crate::channel::unreliable::{ReceiveChannelUnreliable, SendChannelUnreliable, SEQUENCE_HEADER_BYTES};
+use crate::channel::warning::ChannelWarnings;
+use crate::channel::{
+    ChannelCapacity, ChannelConfig, ChannelId, ChannelLatency, ChannelStats, ChannelWarning, ChannelWarningKind, DefaultChannel, FenceId,
+    FullQueuePolicy, LatencyStats, MessageDelivery, MessageId, MessageKindStats, MessageLatencyRecord, OverflowRoute, QueuePolicy,
+    ReceivePolicy, SendType,
+};
+use crate::connection_stats::{ConnectionStats, DropReason, NetworkCounters, PacketLossInfo};
+use crate::error::{
+    ChannelAlreadyExists, ChannelError, ChannelNotConfigured, ChannelsConfigError, DisconnectReason, SendError, TransferError,
+};
+use crate::log_limiter::LogLimiter;
+use crate::packet::{
+    is_sliced, num_slices, slice_packet_header_size, small_message_size, small_packet_header_size, ChannelDescriptor, Packet, Payload,
+    MAX_PACKET_SIZE, PROTOCOL_VERSION, SLICE_SIZE,
+};
+use crate::packet_arrival::PacketArrival;
+use crate::ping::{PingId, Pings};
+use crate::quota::{QuotaEvent, QuotaKind, QuotaTracker, ReceiveQuota};
+use crate::transfer::{Transfer, MAX_TRANSFER_TOKEN_BYTES};
+use crate::usage::{UpdateClock, UsageWarning, UsageWatchdog, MAX_UNDRAINED_EVENTS, USAGE_CHECK_CALLS};
+use bytes::{BufMut, Bytes, BytesMut};
 use octets::OctetsMut;
 
-use std::collections::{BTreeMap, HashMap};
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
 use std::ops::Range;
 use std::time::Duration;
 
+// Maximum number of ack ranges kept while waiting for the remote to acknowledge our ack packets.
+// Acks are range based, so a single range can acknowledge any number of consecutive packets,
+// this only limits how many gaps (lost or reordered packets) can be tracked at once.
+const MAX_PENDING_ACK_RANGES: usize = 64;
+
+// Random initial packet sequences are below this, they keep fitting in 4 bytes varints for 2^29 packets.
+const MAX_INITIAL_PACKET_SEQUENCE: u64 = 1 << 29;
+
+// Time to wait before resending the channels configuration if it was not acked.
+const CHANNEL_CONFIG_RESEND_TIME: Duration = Duration::from_millis(100);
+
 /// Configuration for a renet connection and its channels.
 #[derive(Debug, Clone)]
 pub struct ConnectionConfig {
@@ -18,27 +54,146 @@ pub struct ConnectionConfig {
     /// Default: 60_000, at 60hz this is becomes 28.8 Mbps
     pub available_bytes_per_tick: u64,
     /// The channels that the server sends to the client.
-    /// The order of the channels in this Vec determines which channel has priority when generating packets.
-    /// Each tick, the first channel can consume up to `available_bytes_per_tick`,
-    /// used bytes are removed from it and passed to the next channel
+    /// Each tick, every channel first uses its share of `available_bytes_per_tick` given by `ChannelConfig::priority`,
+    /// then the order of the channels in this Vec determines which channel gets the bytes left:
+    /// the first channel can consume all of them, used bytes are removed and the rest passed to the next channel
     pub server_channels_config: Vec<ChannelConfig>,
     /// The channels that the client sends to the server.
-    /// The order of the channels in this Vec determines which channel has priority when generating packets.
-    /// Each tick, the first channel can consume up to `available_bytes_per_tick`,
-    /// used bytes are removed from it and passed to the next channel
+    /// Each tick, every channel first uses its share of `available_bytes_per_tick` given by `ChannelConfig::priority`,
+    /// then the order of the channels in this Vec determines which channel gets the bytes left:
+    /// the first channel can consume all of them, used bytes are removed and the rest passed to the next channel
     pub client_channels_config: Vec<ChannelConfig>,
+    /// Maximum time a reliable message can stay unacked before the connection is terminated
+    /// with [`DisconnectReason::UnackedMessageTimeout`].
+    /// Detects peers that still send packets but stopped acking ours.
+    /// Default: None, no timeout.
+    pub unacked_message_timeout: Option<Duration>,
+    /// The channels configuration is exchanged at the start of the connection,
+    /// by default the connection is terminated with [`DisconnectReason::ChannelConfigMismatch`] if they differ.
+    /// When enabled a warning is logged instead, for intentionally asymmetric setups.
+    /// Default: false
+    pub allow_channel_config_mismatch: bool,
+    /// Received messages are only returned once the remote channels configuration was received.
+    /// Maximum time to wait for it after the first packet from the remote, the connection is then terminated
+    /// with [`DisconnectReason::ChannelConfigTimeout`]: the remote is likely an older version not sending it.
+    /// The configuration is resent until acked, a lost packet only delays it.
+    /// Default: Some(5 seconds)
+    pub channel_config_timeout: Option<Duration>,
+    /// Padding added to outgoing packets, makes their size less correlated with their content.
+    /// Padding is ignored by the receiver and reported separately in [`NetworkInfo`] counters.
+    /// Default: PacketPadding::None
+    pub packet_padding: PacketPadding,
+    /// Duration over which the packet loss is measured, see [`PacketLossInfo`].
+    /// Default: 6 seconds
+    pub loss_window: Duration,
+    /// Minimum number of packets sent in the loss window before a packet loss ratio is reported,
+    /// avoids meaningless values right after connecting or on idle connections.
+    /// Default: 20
+    pub loss_min_samples: u64,
+    /// Longest duration applied by a single update, longer updates are clamped to it.
+    /// Avoids timing out the connection or resending every message at once when the game loop stalls,
+    /// like after a debugger pause or a laptop sleep. The discarded time is reported by `clamped_time`.
+    /// Timeouts only account for the clamped time, so they take longer to trigger after a stall.
+    /// Default: None, no clamping.
+    pub max_update_duration: Option<Duration>,
+    /// Number of rtt samples required, after the channels configuration was exchanged,
+    /// before the connection is considered warmed up, see [`RenetClient::is_warmed_up`].
+    /// Default: 4
+    pub warm_up_rtt_samples: u64,
+    /// When enabled, messages sent before the connection is warmed up stay queued in their channels
+    /// and are only transmitted once it is, so they don't compete with the connection setup.
+    /// Default: false
+    pub hold_messages_until_warmed_up: bool,
+    /// Estimated size of the headers added to every datagram below the transport, like UDP and IP,
+    /// added to the wire bytes reported by the transport, see [`NetworkInfo::wire_bytes_sent_per_second`].
+    /// Default: 28, IPv4 and UDP headers. Use 48 for IPv6.
+    pub packet_header_bytes: u64,
+    /// Fraction of the interval between the packets of the remote after which pending acks are flushed.
+    /// Acks are sent with the packets of `send_packets`, when it's called less often than the remote sends
+    /// they wait and inflate the rtt measured by the remote. Acks waiting longer than this fraction are sent
+    /// in an ack-only packet by the transport update, see [`RenetClient::get_ack_packets_to_send`].
+    /// Sending ack-only packets increases the packets sent, 0.5 is a good value when enabling it.
+    /// Default: None, acks are only sent with `send_packets`.
+    pub ack_flush_fraction: Option<f64>,
+    /// After 100 consecutive updates with a zero duration, [`UsageWarning::ZeroDurationUpdates`] is reported.
+    /// When enabled, the time elapsed since the previous update is then measured with `std::time::Instant`
+    /// and applied instead, also by the transport, so resends and keep-alives don't stall.
+    /// `Instant` is not available on every target, like wasm32-unknown-unknown.
+    /// Default: false
+    pub zero_duration_fallback: bool,
+    /// Starts the packet sequence of each connection, and the sequence of its unreliable sequenced channels,
+    /// at a random value instead of 0, so the number of packets sent can't be told from the traffic.
+    /// Packet sequences start below 2^30, so they take 4 bytes in the packets where they would take 1 or 2.
+    /// Reliable message ids always start at 0, the remote expects them to.
+    /// The random values come from the std hasher keys, they are not cryptographically secure.
+    /// Default: true
+    pub randomize_initial_sequences: bool,
+    /// Limits of the bytes and messages received from each client, see [`ReceiveQuota`].
+    /// Only applies to the server, clients don't check what they receive from it.
+    /// Default: None
+    pub receive_quota: Option<ReceiveQuota>,
+    /// Number of later packets acked after which an unacked packet is presumed lost: its reliable messages
+    /// are resent right away instead of after the `resend_time` of their channel. A packet is only fast
+    /// retransmitted once, its messages are then resent by the timer as usual.
+    /// Default: None, messages are only resent after their `resend_time`.
+    pub fast_retransmit_threshold: Option<u64>,
+}
+
+/// Padding policy for outgoing packets.
+/// Padded packets never exceed the maximum packet size.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PacketPadding {
+    #[default]
+    None,
+    /// Pad packets up to the next multiple of the given number of bytes.
+    Multiple(usize),
+    /// Pad packets up to the given number of bytes.
+    Fixed(usize),
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for PacketPadding {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match *self {
+            PacketPadding::None => serializer.serialize_unit_variant("PacketPadding", 0, "None"),
+            PacketPadding::Multiple(multiple) => serializer.serialize_newtype_variant("PacketPadding", 1, "Multiple", &multiple),
+            PacketPadding::Fixed(size) => serializer.serialize_newtype_variant("PacketPadding", 2, "Fixed", &size),
+        }
+    }
+}
+
+impl PacketPadding {
+    fn padded_len(&self, len: usize) -> usize {
+        let padded_len = match *self {
+            PacketPadding::None => len,
+            PacketPadding::Multiple(0) => len,
+            PacketPadding::Multiple(multiple) => (len + multiple - 1) / multiple * multiple,
+            PacketPadding::Fixed(size) => size,
+        };
+
+        padded_len.min(MAX_PACKET_SIZE).max(len)
+    }
 }
 
 #[derive(Debug, Clone)]
 struct PacketSent {
     sent_at: Duration,
     info: PacketSentInfo,
+    // Its reliable messages were already resent for being skipped by later acks
+    fast_retransmitted: bool,
 }
 
 #[derive(Debug, Clone)]
 enum PacketSentInfo {
     // No need to track info for unreliable messages
     None,
+    // Unreliable messages of a channel with delivery feedback, the channel knows the messages of the packet
+    UnreliableMessages {
+        channel_id: u8,
+    },
     ReliableMessages {
         channel_id: u8,
         message_ids: Vec<u64>,
@@ -53,29 +208,164 @@ enum PacketSentInfo {
     Ack {
         largest_acked_packet: u64,
     },
+    ChannelConfig,
+    TransferSlice {
+        slice_index: usize,
+    },
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 enum ChannelOrder {
     Reliable(u8),
     Unreliable(u8),
 }
 
+impl ChannelOrder {
+    fn channel_id(&self) -> u8 {
+        match *self {
+            ChannelOrder::Reliable(channel_id) | ChannelOrder::Unreliable(channel_id) => channel_id,
+        }
+    }
+}
+
+// Warnings that can repeat for every message, they are rate limited
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+enum RepeatedWarning {
+    SendChannelFull(u8),
+    ReceiveChannelFull(u8),
+}
+
+impl fmt::Display for RepeatedWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            RepeatedWarning::SendChannelFull(channel_id) => {
+                write!(f, "dropped message sent because channel {channel_id} is full")
+            }
+            RepeatedWarning::ReceiveChannelFull(channel_id) => {
+                write!(
+                    f,
+                    "dropped unreliable message received because channel {channel_id} is memory limited"
+                )
+            }
+        }
+    }
+}
+
 /// Describes the stats of a connection.
 pub struct NetworkInfo {
     /// Round-trip Time
     pub rtt: f64,
+    /// Round-trip time of the pings sent with [`RenetClient::send_ping`], 0 until a pong was received.
+    /// Unlike `rtt` it includes the time both ends take to send and process the packets, it's the latency felt by the players.
+    pub application_rtt: f64,
+    /// Packet loss ratio over the loss window, 0 while there are not enough samples
     pub packet_loss: f64,
+    /// Packet counters used to calculate the packet loss
+    pub packet_loss_info: PacketLossInfo,
+    /// Bytes of the renet packets sent per second, without the transport overhead.
     pub bytes_sent_per_second: f64,
+    /// Bytes of the renet packets received per second, without the transport overhead.
     pub bytes_received_per_second: f64,
+    /// Bytes sent per second on the wire: the datagrams encrypted by the transport,
+    /// plus the configured `packet_header_bytes` for each of them.
+    /// Zero if the transport does not report the wire bytes.
+    pub wire_bytes_sent_per_second: f64,
+    /// Bytes received per second on the wire, see `wire_bytes_sent_per_second`.
+    pub wire_bytes_received_per_second: f64,
+    /// Time elapsed since a packet sent by us was last acked
+    pub time_since_last_ack: Duration,
+    /// Smoothed delay between receiving a packet and sending its ack, in seconds.
+    /// Included in the rtt measured by the remote.
+    pub ack_delay: f64,
+    /// Age of the oldest unacked reliable message between all channels
+    pub oldest_unacked_age: Duration,
+    /// Incremented every time the statistics are reset
+    pub epoch: u32,
+    /// Counters since the last statistics reset
+    pub counters: NetworkCounters,
+    /// Counters since the connection was created, never reset
+    pub lifetime_counters: NetworkCounters,
+}
+
+/// How a message would be sent, see [`RenetServer::message_transmission_plan`][crate::RenetServer::message_transmission_plan].
+#[derive(Debug, Clone)]
+pub struct TransmissionPlan {
+    /// Number of packets used by the message, more than one when it is sent in slices.
+    /// Small messages may share their packet with other messages.
+    pub packets: u32,
+    /// Bytes added by renet to the message in its packets: headers and padding.
+    /// The transport overhead, like the netcode header and MAC, is not included.
+    pub overhead_bytes: u32,
+    /// Delivery garantee of the channel
+    pub send_type: SendType,
+}
+
+/// Values in effect for a connection, assembled from its live state, see [`RenetClient::effective_config`].
+/// Useful to display the connection settings, or to check that a configured value is applied.
+#[derive(Debug, Clone)]
+pub struct EffectiveConfig {
+    /// Maximum size of the packets generated by renet, before the transport overhead.
+    pub max_packet_size: usize,
+    /// Messages bigger than this are sent in slices of this size.
+    pub slice_size: usize,
+    pub available_bytes_per_tick: u64,
+    pub packet_padding: PacketPadding,
+    pub unacked_message_timeout: Option<Duration>,
+    /// Channels used to send messages, in priority order.
+    pub send_channels: Vec<ChannelConfig>,
+    /// Channels used to receive messages.
+    pub receive_channels: Vec<ChannelConfig>,
+    /// Whether the channels configuration of the remote was received and compared with ours.
+    pub channels_validated: bool,
+    /// Time without receiving packets after which the transport disconnects, None when disabled.
+    /// Only set by the transport, for example [`NetcodeClientTransport::effective_config`][crate::transport::NetcodeClientTransport::effective_config].
+    pub timeout: Option<Duration>,
+    /// Interval of the keep-alive packets sent by the transport. Only set by the transport.
+    pub keep_alive_interval: Option<Duration>,
+    /// Unix timestamp, in seconds, at which the connect token expires. Only set by the transport.
+    pub token_expire_timestamp: Option<u64>,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for EffectiveConfig {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("EffectiveConfig", 11)?;
+        state.serialize_field("max_packet_size", &self.max_packet_size)?;
+        state.serialize_field("slice_size", &self.slice_size)?;
+        state.serialize_field("available_bytes_per_tick", &self.available_bytes_per_tick)?;
+        state.serialize_field("packet_padding", &self.packet_padding)?;
+        state.serialize_field("unacked_message_timeout", &self.unacked_message_timeout)?;
+        state.serialize_field("send_channels", &self.send_channels)?;
+        state.serialize_field("receive_channels", &self.receive_channels)?;
+        state.serialize_field("channels_validated", &self.channels_validated)?;
+        state.serialize_field("timeout", &self.timeout)?;
+        state.serialize_field("keep_alive_interval", &self.keep_alive_interval)?;
+        state.serialize_field("token_expire_timestamp", &self.token_expire_timestamp)?;
+        state.end()
+    }
 }
 
 /// The connection status of a [`RenetClient`].
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RenetConnectionStatus {
     Connected,
-    Connecting,
-    Disconnected { reason: DisconnectReason },
+    /// The connection is being established.
+    /// `queued_position` is set while waiting in the connect queue of a full server.
+    Connecting {
+        queued_position: Option<u32>,
+    },
+    /// The server is transferring the client to another server, the token to connect to it
+    /// is returned by [`RenetClient::take_transfer_token`]. The server disconnects the client
+    /// with [`DisconnectReason::Transferred`] once the token is acked.
+    Transferring,
+    Disconnected {
+        reason: DisconnectReason,
+    },
 }
 
 #[derive(Debug)]
@@ -86,6 +376,19 @@ pub struct RenetClient {
     sent_packets: BTreeMap<u64, PacketSent>,
     pending_acks: Vec<Range<u64>>,
     channel_send_order: Vec<ChannelOrder>,
+    channel_priorities: HashMap<u8, u8>,
+    // Channels assembled first in their priority class
+    urgent_channels: HashSet<u8>,
+    // Outgoing rate of the channels with max_kbps
+    channel_bandwidths: HashMap<u8, ChannelBandwidth>,
+    overflow_routes: HashMap<u8, OverflowRoute>,
+    // Messages sent over the overflow channel of each channel
+    overflowed_messages: HashMap<u8, u64>,
+    // Messages of the unreliable channels dropped for exceeding their message_ttl
+    expired_messages: HashMap<u8, u64>,
+    // Biggest message accepted by each send channel, for the channels with a limit
+    send_message_limits: HashMap<u8, usize>,
+    channel_stats: HashMap<u8, ChannelStats>,
     send_unreliable_channels: HashMap<u8, SendChannelUnreliable>,
     receive_unreliable_channels: HashMap<u8, ReceiveChannelUnreliable>,
     send_reliable_channels: HashMap<u8, SendChannelReliable>,
@@ -94,6 +397,63 @@ pub struct RenetClient {
     available_bytes_per_tick: u64,
     connection_status: RenetConnectionStatus,
     rtt: f64,
+    last_ack_received: Duration,
+    unacked_message_timeout: Option<Duration>,
+    send_channels_descriptor: Vec<ChannelDescriptor>,
+    receive_channels_descriptor: Vec<ChannelDescriptor>,
+    channel_config_acked: bool,
+    channel_config_last_sent: Option<Duration>,
+    remote_channel_config_received: bool,
+    // Time of the first packet received, while waiting for the remote channels configuration
+    first_packet_received_at: Option<Duration>,
+    channel_config_timeout: Option<Duration>,
+    allow_channel_config_mismatch: bool,
+    packet_padding: PacketPadding,
+    channel_warnings: ChannelWarnings,
+    // Recoverable errors of the receive channels not drained yet, oldest first
+    channel_errors: VecDeque<(u8, ChannelError)>,
+    channel_groups: ChannelGroups,
+    channel_compression: ChannelCompression,
+    message_kinds: MessageKinds,
+    // Channels whose messages carry a trace id
+    traced_send_channels: HashSet<u8>,
+    // Reliable channels refusing the messages sent when full instead of disconnecting
+    reject_send_channels: HashSet<u8>,
+    traced_receive_channels: HashSet<u8>,
+    log_limiter: LogLimiter<RepeatedWarning>,
+    packet_arrival: PacketArrival,
+    usage: UsageWatchdog,
+    update_clock: UpdateClock,
+    // Consecutive updates with the receive channel more than half full and not read
+    unread_updates: HashMap<u8, u32>,
+    // Receive channels that may have messages to read, in the order receive_any_message visits them
+    ready_receive_channels: VecDeque<u8>,
+    ready_receive_channel_ids: HashSet<u8>,
+    // Receive policy of the channels that drop unread messages
+    receive_policies: HashMap<u8, ReceivePolicy>,
+    pings: Pings,
+    transfer: Transfer,
+    // Connections of the server don't accept transfers from their client
+    accept_transfer: bool,
+    send_channels_config: Vec<ChannelConfig>,
+    receive_channels_config: Vec<ChannelConfig>,
+    max_update_duration: Option<Duration>,
+    clamped_time: Duration,
+    rtt_samples: u64,
+    warm_up_rtt_samples: u64,
+    hold_messages_until_warmed_up: bool,
+    packet_header_bytes: u64,
+    ack_flush_fraction: Option<f64>,
+    // Time the oldest packet whose ack was not sent yet was received
+    unsent_acks_since: Option<Duration>,
+    ack_delay: f64,
+    randomize_initial_sequences: bool,
+    fast_retransmit_threshold: Option<u64>,
+    // Only for the connections of the server
+    quota: Option<QuotaTracker>,
+    quota_warning: Option<QuotaKind>,
+    // Sent to the client in place of the packets once disconnected for exceeding the quota
+    quota_exceeded_notice: Option<QuotaKind>,
 }
 
 impl Default for ConnectionConfig {
@@ -103,93 +463,378 @@ impl Default for ConnectionConfig {
             available_bytes_per_tick: 60_000,
             server_channels_config: DefaultChannel::config(),
             client_channels_config: DefaultChannel::config(),
+            unacked_message_timeout: None,
+            allow_channel_config_mismatch: false,
+            channel_config_timeout: Some(Duration::from_secs(5)),
+            packet_padding: PacketPadding::None,
+            loss_window: Duration::from_secs(6),
+            loss_min_samples: 20,
+            max_update_duration: None,
+            warm_up_rtt_samples: 4,
+            hold_messages_until_warmed_up: false,
+            packet_header_bytes: 28,
+            ack_flush_fraction: None,
+            zero_duration_fallback: false,
+            randomize_initial_sequences: true,
+            receive_quota: None,
+            fast_retransmit_threshold: None,
+        }
+    }
+}
+
+impl ConnectionConfig {
+    /// Checks that the channels used by the application exist in both the server and the client channels,
+    /// for example every variant of an enum of channels, so a missing one is found when the configuration is built
+    /// instead of panicking on the first send.
+    ///
+    /// ```
+    /// # use renet::{ConnectionConfig, DefaultChannel};
+    /// let config = ConnectionConfig::default();
+    /// assert!(config.check_channels(DefaultChannel::ALL).is_ok());
+    /// ```
+    pub fn check_channels<I: Into<ChannelId>>(&self, channels: impl IntoIterator<Item = I>) -> Result<(), ChannelNotConfigured> {
+        let configured = |configs: &[ChannelConfig], channel_id: ChannelId| configs.iter().any(|config| config.channel_id == channel_id);
+        for channel_id in channels.into_iter().map(Into::into) {
+            if !configured(&self.server_channels_config, channel_id) || !configured(&self.client_channels_config, channel_id) {
+                return Err(ChannelNotConfigured {
+                    channel_id: channel_id.raw(),
+                });
+            }
         }
+
+        Ok(())
+    }
+
+    /// Checks the server and the client channels, returning the first problem that would make creating
+    /// a [`RenetClient`] or adding a connection to a [`RenetServer`][crate::RenetServer] panic:
+    /// duplicated channel ids, grouped reliable sequenced channels and invalid overflow routes.
+    pub fn validate(&self) -> Result<(), ChannelsConfigError> {
+        check_channels_config(&self.server_channels_config)?;
+        check_channels_config(&self.client_channels_config)
     }
 }
 
 impl RenetClient {
     pub fn new(config: ConnectionConfig) -> Self {
-        Self::from_channels(
-            config.available_bytes_per_tick,
-            config.client_channels_config,
-            config.server_channels_config,
-        )
+        Self::from_channels(&config.client_channels_config, &config.server_channels_config, &config)
     }
 
     // When creating a client from the server, the server_channels_config are used as send channels,
     // and the client_channels_config is used as recv channels.
     pub(crate) fn new_from_server(config: ConnectionConfig) -> Self {
-        Self::from_channels(
-            config.available_bytes_per_tick,
-            config.server_channels_config,
-            config.client_channels_config,
-        )
+        let mut connection = Self::from_channels(&config.server_channels_config, &config.client_channels_config, &config);
+        // The server reports the misuses of its connections, and measures the zero duration updates for them
+        connection.usage = UsageWatchdog::new(false);
+        connection.update_clock = UpdateClock::new(false);
+        connection.accept_transfer = false;
+        connection.quota = config.receive_quota.map(QuotaTracker::new);
+        connection
     }
 
-    fn from_channels(
-        available_bytes_per_tick: u64,
-        send_channels_config: Vec<ChannelConfig>,
-        receive_channels_config: Vec<ChannelConfig>,
-    ) -> Self {
-        let mut send_unreliable_channels = HashMap::new();
-        let mut send_reliable_channels = HashMap::new();
-        let mut channel_send_order: Vec<ChannelOrder> = Vec::with_capacity(send_channels_config.len());
-        for channel_config in send_channels_config.iter() {
-            match channel_config.send_type {
-                SendType::Unreliable => {
-                    let channel = SendChannelUnreliable::new(channel_config.channel_id, channel_config.max_memory_usage_bytes);
-                    let old = send_unreliable_channels.insert(channel_config.channel_id, channel);
-                    assert!(old.is_none(), "already exists send channel {}", channel_config.channel_id);
+    fn from_channels(send_channels_config: &[ChannelConfig], receive_channels_config: &[ChannelConfig], config: &ConnectionConfig) -> Self {
+        let mut connection = Self {
+            packet_sequence: 0,
+            current_time: Duration::ZERO,
+            sent_packets: BTreeMap::new(),
+            pending_acks: Vec::new(),
+            channel_send_order: Vec::with_capacity(send_channels_config.len()),
+            channel_priorities: HashMap::new(),
+            urgent_channels: HashSet::new(),
+            channel_bandwidths: HashMap::new(),
+            overflow_routes: HashMap::new(),
+            overflowed_messages: HashMap::new(),
+            expired_messages: HashMap::new(),
+            send_message_limits: HashMap::new(),
+            channel_stats: HashMap::new(),
+            send_unreliable_channels: HashMap::new(),
+            receive_unreliable_channels: HashMap::new(),
+            send_reliable_channels: HashMap::new(),
+            receive_reliable_channels: HashMap::new(),
+            stats: ConnectionStats::new(config.loss_window, config.loss_min_samples),
+            rtt: 0.0,
+            available_bytes_per_tick: config.available_bytes_per_tick,
+            connection_status: RenetConnectionStatus::Connecting { queued_position: None },
+            last_ack_received: Duration::ZERO,
+            unacked_message_timeout: config.unacked_message_timeout,
+            send_channels_descriptor: channels_descriptor(send_channels_config),
+            receive_channels_descriptor: channels_descriptor(receive_channels_config),
+            channel_config_acked: false,
+            channel_config_last_sent: None,
+            remote_channel_config_received: false,
+            first_packet_received_at: None,
+            channel_config_timeout: config.channel_config_timeout,
+            allow_channel_config_mismatch: config.allow_channel_config_mismatch,
+            packet_padding: config.packet_padding,
+            channel_warnings: ChannelWarnings::new(send_channels_config, receive_channels_config, config.unacked_message_timeout),
+            channel_errors: VecDeque::new(),
+            channel_groups: ChannelGroups::new(send_channels_config, receive_channels_config),
+            channel_compression: ChannelCompression::new(send_channels_config, receive_channels_config),
+            message_kinds: MessageKinds::new(send_channels_config, receive_channels_config),
+            traced_send_channels: HashSet::new(),
+            reject_send_channels: HashSet::new(),
+            traced_receive_channels: HashSet::new(),
+            log_limiter: LogLimiter::default(),
+            packet_arrival: PacketArrival::default(),
+            usage: UsageWatchdog::new(true),
+            update_clock: UpdateClock::new(config.zero_duration_fallback),
+            unread_updates: HashMap::new(),
+            ready_receive_channels: VecDeque::new(),
+            ready_receive_channel_ids: HashSet::new(),
+            receive_policies: HashMap::new(),
+            pings: Pings::default(),
+            transfer: Transfer::default(),
+            accept_transfer: true,
+            send_channels_config: Vec::with_capacity(send_channels_config.len()),
+            receive_channels_config: Vec::with_capacity(receive_channels_config.len()),
+            max_update_duration: config.max_update_duration,
+            clamped_time: Duration::ZERO,
+            rtt_samples: 0,
+            warm_up_rtt_samples: config.warm_up_rtt_samples,
+            hold_messages_until_warmed_up: config.hold_messages_until_warmed_up,
+            packet_header_bytes: config.packet_header_bytes,
+            ack_flush_fraction: config.ack_flush_fraction,
+            unsent_acks_since: None,
+            ack_delay: 0.0,
+            randomize_initial_sequences: config.randomize_initial_sequences,
+            fast_retransmit_threshold: config.fast_retransmit_threshold,
+            quota: None,
+            quota_warning: None,
+            quota_exceeded_notice: None,
+        };
+        if config.randomize_initial_sequences {
+            connection.packet_sequence = random_u64() % MAX_INITIAL_PACKET_SEQUENCE;
+        }
 
-                    channel_send_order.push(ChannelOrder::Unreliable(channel_config.channel_id));
-                }
-                SendType::ReliableOrdered { resend_time } | SendType::ReliableUnordered { resend_time } => {
-                    let channel = SendChannelReliable::new(channel_config.channel_id, resend_time, channel_config.max_memory_usage_bytes);
-                    let old = send_reliable_channels.insert(channel_config.channel_id, channel);
-                    assert!(old.is_none(), "already exists send channel {}", channel_config.channel_id);
+        for channel_config in send_channels_config {
+            connection.insert_send_channel(channel_config);
+        }
+        for channel_config in send_channels_config {
+            connection.check_overflow_route(channel_config);
+        }
+        for channel_config in receive_channels_config {
+            connection.insert_receive_channel(channel_config);
+        }
+        connection
+    }
+
+    // The overflow channel must keep the order of the messages of the channel, see `OverflowRoute`
+    fn check_overflow_route(&self, channel_config: &ChannelConfig) {
+        let Some(overflow) = channel_config.overflow else {
+            return;
+        };
+
+        let channel_id = channel_config.channel_id.raw();
+        let overflow_channel_id = overflow.channel_id.raw();
+        let reliable = |channel_id: u8| self.send_reliable_channels.contains_key(&channel_id);
+        assert!(
+            reliable(channel_id),
+            "unreliable channel {channel_id} cannot have an overflow route"
+        );
+        assert!(
+            reliable(overflow_channel_id),
+            "overflow channel {overflow_channel_id} of channel {channel_id} is not a reliable send channel"
+        );
+        let overflow_group = self
+            .send_channels_config
+            .iter()
+            .find(|config| config.channel_id == overflow.channel_id)
+            .and_then(|config| config.group);
+        assert!(
+            channel_config.group.is_some() && overflow_group == channel_config.group,
+            "overflow channel {overflow_channel_id} of channel {channel_id} is not in the same group"
+        );
+    }
+
+    fn insert_send_channel(&mut self, channel_config: &ChannelConfig) {
+        let channel_id = channel_config.channel_id.raw();
+        let header_bytes = match has_sequence_header(channel_config) {
+            true => SEQUENCE_HEADER_BYTES,
+            false => 0,
+        };
+        let mut tracer = None;
+        if channel_config.tracing_context {
+            tracer = Some(MessageTracer::new(channel_id, header_bytes, channel_config.group.is_some()));
+            self.traced_send_channels.insert(channel_id);
+        }
 
-                    channel_send_order.push(ChannelOrder::Reliable(channel_config.channel_id));
+        match channel_config.send_type {
+            SendType::Unreliable | SendType::UnreliableSequenced => {
+                // Deduplicated channels send the sequence number like the sequenced ones
+                let mut channel = match has_sequence_header(channel_config) {
+                    true => SendChannelUnreliable::new_sequenced(channel_id, channel_config.max_memory_usage_bytes),
+                    false => SendChannelUnreliable::new(channel_id, channel_config.max_memory_usage_bytes),
+                };
+                if self.randomize_initial_sequences {
+                    channel.set_sequence(random_u64() as u16);
+                }
+                if let Some(tracer) = tracer {
+                    channel.set_tracer(tracer);
+                }
+                if let Some(message_ttl) = channel_config.message_ttl {
+                    channel.set_message_ttl(message_ttl);
+                }
+                if channel_config.delivery_feedback {
+                    channel.set_delivery_feedback();
+                }
+                if channel_config.full_queue_policy == FullQueuePolicy::DropOldest {
+                    channel.set_drop_oldest();
+                }
+                if let Some(coalesce_delay) = channel_config.coalesce_delay {
+                    channel.set_coalesce_delay(coalesce_delay);
                 }
+                if let Some(max_queued_messages) = channel_config.max_queued_messages {
+                    channel.set_max_queued_messages(max_queued_messages);
+                }
+                let old = self.send_unreliable_channels.insert(channel_id, channel);
+                assert!(old.is_none(), "already exists send channel {channel_id}");
+
+                self.channel_send_order.push(ChannelOrder::Unreliable(channel_id));
             }
-        }
+            SendType::ReliableOrdered { resend_time } | SendType::ReliableUnordered { resend_time } => {
+                let mut channel = SendChannelReliable::new(channel_id, resend_time, channel_config.max_memory_usage_bytes);
+                if let Some(tracer) = tracer {
+                    channel.set_tracer(tracer);
+                }
+                if let Some(max_resend_time) = channel_config.max_resend_time {
+                    channel.set_max_resend_time(max_resend_time);
+                }
+                if let Some(coalesce_delay) = channel_config.coalesce_delay {
+                    channel.set_coalesce_delay(coalesce_delay);
+                }
+                if let Some(max_queued_messages) = channel_config.max_queued_messages {
+                    channel.set_max_queued_messages(max_queued_messages);
+                }
+                if let Some(max_packets_in_flight) = channel_config.max_packets_in_flight {
+                    channel.set_max_packets_in_flight(max_packets_in_flight);
+                }
+                let old = self.send_reliable_channels.insert(channel_id, channel);
+                assert!(old.is_none(), "already exists send channel {channel_id}");
 
-        let mut receive_unreliable_channels = HashMap::new();
-        let mut receive_reliable_channels = HashMap::new();
-        for channel_config in receive_channels_config.iter() {
-            match channel_config.send_type {
-                SendType::Unreliable => {
-                    let channel = ReceiveChannelUnreliable::new(channel_config.channel_id, channel_config.max_memory_usage_bytes);
-                    let old = receive_unreliable_channels.insert(channel_config.channel_id, channel);
-                    assert!(old.is_none(), "already exists receive channel {}", channel_config.channel_id);
+                self.channel_send_order.push(ChannelOrder::Reliable(channel_id));
+            }
+            SendType::ReliableSequenced { resend_time } => {
+                // Superseded messages are never sent, the group would wait for them
+                assert!(
+                    channel_config.group.is_none(),
+                    "reliable sequenced channel {channel_id} cannot be grouped"
+                );
+                let mut channel = SendChannelReliable::new_sequenced(channel_id, resend_time, channel_config.max_memory_usage_bytes);
+                if let Some(tracer) = tracer {
+                    channel.set_tracer(tracer);
+                }
+                if let Some(max_resend_time) = channel_config.max_resend_time {
+                    channel.set_max_resend_time(max_resend_time);
                 }
-                SendType::ReliableOrdered { .. } => {
-                    let channel = ReceiveChannelReliable::new(channel_config.max_memory_usage_bytes, true);
-                    let old = receive_reliable_channels.insert(channel_config.channel_id, channel);
-                    assert!(old.is_none(), "already exists receive channel {}", channel_config.channel_id);
+                if let Some(coalesce_delay) = channel_config.coalesce_delay {
+                    channel.set_coalesce_delay(coalesce_delay);
                 }
-                SendType::ReliableUnordered { .. } => {
-                    let channel = ReceiveChannelReliable::new(channel_config.max_memory_usage_bytes, false);
-                    let old = receive_reliable_channels.insert(channel_config.channel_id, channel);
-                    assert!(old.is_none(), "already exists receive channel {}", channel_config.channel_id);
+                if let Some(max_queued_messages) = channel_config.max_queued_messages {
+                    channel.set_max_queued_messages(max_queued_messages);
                 }
+                if let Some(max_packets_in_flight) = channel_config.max_packets_in_flight {
+                    channel.set_max_packets_in_flight(max_packets_in_flight);
+                }
+                let old = self.send_reliable_channels.insert(channel_id, channel);
+                assert!(old.is_none(), "already exists send channel {channel_id}");
+
+                self.channel_send_order.push(ChannelOrder::Reliable(channel_id));
             }
         }
 
-        Self {
-            packet_sequence: 0,
-            current_time: Duration::ZERO,
-            sent_packets: BTreeMap::new(),
-            pending_acks: Vec::new(),
-            channel_send_order,
-            send_unreliable_channels,
-            receive_unreliable_channels,
-            send_reliable_channels,
-            receive_reliable_channels,
-            stats: ConnectionStats::new(),
-            rtt: 0.0,
-            available_bytes_per_tick,
-            connection_status: RenetConnectionStatus::Connecting,
+        // Channels without priority only get the bytes left by the others, urgent channels come first
+        self.channel_priorities.insert(channel_id, channel_config.priority);
+        if channel_config.urgent {
+            self.urgent_channels.insert(channel_id);
+        }
+        let (channel_priorities, urgent_channels) = (&self.channel_priorities, &self.urgent_channels);
+        self.channel_send_order.sort_by_key(|order| {
+            let channel_id = order.channel_id();
+            (channel_priorities[&channel_id] == 0, !urgent_channels.contains(&channel_id))
+        });
+        if let Some(max_kbps) = channel_config.max_kbps {
+            self.channel_bandwidths.insert(channel_id, ChannelBandwidth::new(max_kbps));
+        }
+        if let Some(overflow) = channel_config.overflow {
+            self.overflow_routes.insert(channel_id, overflow);
+        }
+        if channel_config.queue_policy == QueuePolicy::RejectSend {
+            self.reject_send_channels.insert(channel_id);
+        }
+        // The remote receives the sequence number of sequenced channels with the message,
+        // a reliable message above the memory limit would never fit in the channel
+        let max_message_size = channel_config.max_message_size.map(|size| size.saturating_sub(header_bytes));
+        let channel_limit = match channel_config.send_type {
+            SendType::Unreliable | SendType::UnreliableSequenced => channel_config
+                .max_fragments
+                .map(|max_fragments| (max_fragments * SLICE_SIZE).saturating_sub(header_bytes)),
+            _ => Some(channel_config.max_memory_usage_bytes),
+        };
+        if let Some(limit) = max_message_size.into_iter().chain(channel_limit).min() {
+            self.send_message_limits.insert(channel_id, limit);
+        }
+        self.send_channels_config.push(channel_config.clone());
+    }
+
+    fn insert_receive_channel(&mut self, channel_config: &ChannelConfig) {
+        let channel_id = channel_config.channel_id.raw();
+        match channel_config.send_type {
+            SendType::Unreliable => {
+                let mut channel = ReceiveChannelUnreliable::new(channel_config.max_memory_usage_bytes);
+                if let Some(window) = channel_config.deduplicate {
+                    channel.set_deduplicate(window);
+                }
+                let old = self.receive_unreliable_channels.insert(channel_id, channel);
+                assert!(old.is_none(), "already exists receive channel {channel_id}");
+            }
+            SendType::UnreliableSequenced => {
+                let channel = ReceiveChannelUnreliable::new_sequenced(channel_config.max_memory_usage_bytes);
+                let old = self.receive_unreliable_channels.insert(channel_id, channel);
+                assert!(old.is_none(), "already exists receive channel {channel_id}");
+            }
+            SendType::ReliableOrdered { .. } => {
+                let channel = ReceiveChannelReliable::new(channel_config.max_memory_usage_bytes, true);
+                let old = self.receive_reliable_channels.insert(channel_id, channel);
+                assert!(old.is_none(), "already exists receive channel {channel_id}");
+            }
+            SendType::ReliableUnordered { .. } => {
+                let channel = ReceiveChannelReliable::new(channel_config.max_memory_usage_bytes, false);
+                let old = self.receive_reliable_channels.insert(channel_id, channel);
+                assert!(old.is_none(), "already exists receive channel {channel_id}");
+            }
+            SendType::ReliableSequenced { .. } => {
+                assert!(
+                    channel_config.group.is_none(),
+                    "reliable sequenced channel {channel_id} cannot be grouped"
+                );
+                let channel = ReceiveChannelReliable::new_sequenced(channel_config.max_memory_usage_bytes);
+                let old = self.receive_reliable_channels.insert(channel_id, channel);
+                assert!(old.is_none(), "already exists receive channel {channel_id}");
+            }
         }
+
+        if let Some(max_message_size) = channel_config.max_message_size {
+            if let Some(reliable_channel) = self.receive_reliable_channels.get_mut(&channel_id) {
+                reliable_channel.set_max_message_size(max_message_size);
+            } else if let Some(unreliable_channel) = self.receive_unreliable_channels.get_mut(&channel_id) {
+                unreliable_channel.set_max_message_size(max_message_size);
+            }
+        }
+        if let Some(unreliable_channel) = self.receive_unreliable_channels.get_mut(&channel_id) {
+            if let Some(max_fragments) = channel_config.max_fragments {
+                unreliable_channel.set_max_slices(max_fragments);
+            }
+            if let Some(reassembly_timeout) = channel_config.reassembly_timeout {
+                unreliable_channel.set_reassembly_timeout(reassembly_timeout);
+            }
+        }
+        if channel_config.tracing_context {
+            self.traced_receive_channels.insert(channel_id);
+        }
+        // Grouped channels are left out, dropping their messages would stall the group
+        if channel_config.receive_policy != ReceivePolicy::Unbounded && channel_config.group.is_none() {
+            self.receive_policies.insert(channel_id, channel_config.receive_policy);
+        }
+        self.receive_channels_config.push(channel_config.clone());
     }
 
     /// Returns the round-time trip for the connection.
@@ -197,11 +842,30 @@ impl RenetClient {
         self.rtt
     }
 
-    /// Returns the packet loss for the connection.
+    /// Sends a ping that the remote answers automatically, without involving the application.
+    /// The round trip is returned by [`RenetClient::poll_pong`] once the pong is received. Pings are unreliable,
+    /// at most 16 are outstanding: sending more drops the oldest, their pong is never returned.
+    pub fn send_ping(&mut self) -> PingId {
+        self.pings.send_ping(self.current_time)
+    }
+
+    /// Returns the round trip of the ping once its pong was received, measured from the call to [`RenetClient::send_ping`]
+    /// to the update that processed the pong. Returns None while the pong is not received, and after it was returned once.
+    pub fn poll_pong(&mut self, ping_id: PingId) -> Option<Duration> {
+        self.pings.poll_pong(ping_id)
+    }
+
+    /// Returns the fraction of packets lost over the configured `loss_window`.
+    /// Returns 0 until `loss_min_samples` packets were sent in the window.
     pub fn packet_loss(&self) -> f64 {
         self.stats.packet_loss()
     }
 
+    /// Returns the packets sent, acked and lost over the configured `loss_window`.
+    pub fn packet_loss_info(&self) -> PacketLossInfo {
+        self.stats.packet_loss_info()
+    }
+
     /// Returns the bytes sent per second in the connection.
     pub fn bytes_sent_per_sec(&self) -> f64 {
         self.stats.bytes_sent_per_second(self.current_time)
@@ -216,22 +880,161 @@ impl RenetClient {
     pub fn network_info(&self) -> NetworkInfo {
         NetworkInfo {
             rtt: self.rtt,
+            application_rtt: self.pings.rtt(),
             packet_loss: self.stats.packet_loss(),
+            packet_loss_info: self.stats.packet_loss_info(),
             bytes_sent_per_second: self.stats.bytes_sent_per_second(self.current_time),
             bytes_received_per_second: self.stats.bytes_received_per_second(self.current_time),
+            wire_bytes_sent_per_second: self.stats.wire_bytes_sent_per_second(self.current_time),
+            wire_bytes_received_per_second: self.stats.wire_bytes_received_per_second(self.current_time),
+            time_since_last_ack: self.time_since_last_ack(),
+            ack_delay: self.ack_delay,
+            oldest_unacked_age: self.oldest_unacked_age(),
+            epoch: self.stats.epoch(),
+            counters: self.stats.counters(),
+            lifetime_counters: self.stats.lifetime_counters(),
+        }
+    }
+
+    /// Returns the values in effect for the connection.
+    /// The transport values are not set, see [`EffectiveConfig`].
+    pub fn effective_config(&self) -> EffectiveConfig {
+        EffectiveConfig {
+            max_packet_size: MAX_PACKET_SIZE,
+            slice_size: SLICE_SIZE,
+            available_bytes_per_tick: self.available_bytes_per_tick,
+            packet_padding: self.packet_padding,
+            unacked_message_timeout: self.unacked_message_timeout,
+            send_channels: self.send_channels_config.clone(),
+            receive_channels: self.receive_channels_config.clone(),
+            channels_validated: self.remote_channel_config_received,
+            timeout: None,
+            keep_alive_interval: None,
+            token_expire_timestamp: None,
+        }
+    }
+
+    /// Resets the statistics of the connection: counters, bandwidth, packet loss, rtt, channel latencies, head-of-line blocking and message kinds.
+    /// The connection and channels state are not affected, lifetime counters are kept.
+    /// Increments the statistics epoch so consumers can detect the reset.
+    pub fn reset_statistics(&mut self) {
+        self.stats.reset(self.current_time);
+        self.rtt = 0.0;
+        self.ack_delay = 0.0;
+        self.pings.reset_rtt();
+        for reliable_channel in self.send_reliable_channels.values_mut() {
+            reliable_channel.reset_latency();
+        }
+        for unreliable_channel in self.send_unreliable_channels.values_mut() {
+            unreliable_channel.reset_latency();
+        }
+        for reliable_channel in self.receive_reliable_channels.values_mut() {
+            reliable_channel.reset_head_of_line_blocking();
+        }
+        self.message_kinds.reset_stats();
+    }
+
+    /// Returns the time elapsed since a packet sent by us was last acked.
+    /// Counts from the creation of the connection if no packet was acked yet.
+    pub fn time_since_last_ack(&self) -> Duration {
+        self.current_time.saturating_sub(self.last_ack_received)
+    }
+
+    /// Returns the age of the oldest unacked reliable message between all channels.
+    pub fn oldest_unacked_age(&self) -> Duration {
+        self.send_reliable_channels
+            .values()
+            .map(|channel| channel.oldest_unacked_age(self.current_time))
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Returns the age of the oldest unacked message in the given channel.
+    /// Always zero for unreliable channels.
+    pub fn channel_oldest_unacked_age<I: Into<ChannelId>>(&self, channel_id: I) -> Duration {
+        let channel_id = channel_id.into().raw();
+        if let Some(reliable_channel) = self.send_reliable_channels.get(&channel_id) {
+            reliable_channel.oldest_unacked_age(self.current_time)
+        } else if self.send_unreliable_channels.contains_key(&channel_id) {
+            Duration::ZERO
+        } else {
+            panic!("Called 'channel_oldest_unacked_age' with invalid channel {channel_id}");
+        }
+    }
+
+    /// Returns the progress of the sliced messages sent in the channel and waiting for their acks,
+    /// as the acked bytes and the total bytes of those messages. None when no sliced message is in flight.
+    /// Always None for unreliable channels, their slices are not acked.
+    pub fn sliced_send_progress<I: Into<ChannelId>>(&self, channel_id: I) -> Option<(u64, u64)> {
+        let channel_id = channel_id.into().raw();
+        if let Some(reliable_channel) = self.send_reliable_channels.get(&channel_id) {
+            reliable_channel.sliced_progress()
+        } else if self.send_unreliable_channels.contains_key(&channel_id) {
+            None
+        } else {
+            panic!("Called 'sliced_send_progress' with invalid channel {channel_id}");
+        }
+    }
+
+    /// Returns the progress of the sliced messages being received in the channel,
+    /// as the received slices and the total slices of those messages. None when no sliced message is in flight.
+    pub fn sliced_receive_progress<I: Into<ChannelId>>(&self, channel_id: I) -> Option<(usize, usize)> {
+        let channel_id = channel_id.into().raw();
+        if let Some(reliable_channel) = self.receive_reliable_channels.get(&channel_id) {
+            reliable_channel.sliced_progress()
+        } else if let Some(unreliable_channel) = self.receive_unreliable_channels.get(&channel_id) {
+            unreliable_channel.sliced_progress()
+        } else {
+            panic!("Called 'sliced_receive_progress' with invalid channel {channel_id}");
         }
     }
 
+    /// Returns the smoothed number of packets received per second from the server.
+    /// Decreases while no packets are received.
+    pub fn server_packet_rate(&self) -> f64 {
+        self.packet_arrival.packet_rate(self.current_time)
+    }
+
+    /// Returns true if the server stalled in the last 2 seconds.
+    ///
+    /// A stalled server sends the packets of its late ticks together when it catches up, so a stall is detected
+    /// when no packets are received for several times the usual interval, and then a burst of packets arrives.
+    /// Packet loss leaves the same gap without the burst: unlike [`RenetClient::packet_loss`],
+    /// a stall means the network is fine but the server is lagging.
+    pub fn server_stall_detected(&self) -> bool {
+        self.packet_arrival.stall_detected(self.current_time)
+    }
+
+    /// Returns the number of server stalls detected since the connection was created.
+    pub fn server_stalls(&self) -> u64 {
+        self.packet_arrival.stalls()
+    }
+
+    /// Returns the connection status of the client.
+    pub fn connection_status(&self) -> RenetConnectionStatus {
+        self.connection_status
+    }
+
     /// Returns whether the client is connected.
     #[inline]
     pub fn is_connected(&self) -> bool {
         matches!(self.connection_status, RenetConnectionStatus::Connected)
     }
 
+    /// Returns whether the connection is warmed up: connected, with the channels configuration
+    /// exchanged in both directions and at least `warm_up_rtt_samples` rtt samples measured.
+    /// Useful to wait on a loading screen, so gameplay traffic starts on a characterized connection.
+    pub fn is_warmed_up(&self) -> bool {
+        self.is_connected()
+            && self.channel_config_acked
+            && self.remote_channel_config_received
+            && self.rtt_samples >= self.warm_up_rtt_samples
+    }
+
     /// Returns whether the client is connecting.
     #[inline]
     pub fn is_connecting(&self) -> bool {
-        matches!(self.connection_status, RenetConnectionStatus::Connecting)
+        matches!(self.connection_status, RenetConnectionStatus::Connecting { .. })
     }
 
     /// Returns whether the client is disconnected.
@@ -257,7 +1060,7 @@ impl RenetClient {
     /// <strong>Note:</strong> This should only be called by the transport layer.
     /// </p>
     pub fn set_connected(&mut self) {
-        if !self.is_disconnected() {
+        if !self.is_disconnected() && !self.is_transferring() {
             self.connection_status = RenetConnectionStatus::Connected;
         }
     }
@@ -271,7 +1074,22 @@ impl RenetClient {
     /// </p>
     pub fn set_connecting(&mut self) {
         if !self.is_disconnected() {
-            self.connection_status = RenetConnectionStatus::Connecting;
+            self.connection_status = RenetConnectionStatus::Connecting { queued_position: None };
+        }
+    }
+
+    /// Set the client connection status to connecting while waiting in the server connect queue.
+    ///
+    /// Does nothing if the client is disconnected. A disconnected client must be reconstructed.
+    ///
+    /// <p style="background:rgba(77,220,255,0.16);padding:0.5em;">
+    /// <strong>Note:</strong> This should only be called by the transport layer.
+    /// </p>
+    pub fn set_queued(&mut self, position: u32) {
+        if !self.is_disconnected() {
+            self.connection_status = RenetConnectionStatus::Connecting {
+                queued_position: Some(position),
+            };
         }
     }
 
@@ -289,12 +1107,48 @@ impl RenetClient {
     /// <strong>Note:</strong> This should only be called by the transport layer.
     /// </p>
     pub fn disconnect_due_to_transport(&mut self) {
-        self.disconnect_with_reason(DisconnectReason::Transport);
+        // The server disconnects the transferred client through the transport
+        match self.transfer.token_received() {
+            true => self.disconnect_with_reason(DisconnectReason::Transferred),
+            false => self.disconnect_with_reason(DisconnectReason::Transport),
+        }
+    }
+
+    /// Returns whether the server is transferring the client to another server,
+    /// see [`RenetConnectionStatus::Transferring`].
+    #[inline]
+    pub fn is_transferring(&self) -> bool {
+        matches!(self.connection_status, RenetConnectionStatus::Transferring)
+    }
+
+    /// Returns the token sent by the server to connect to another server,
+    /// once the client is transferring or was disconnected with [`DisconnectReason::Transferred`].
+    /// With the netcode transport the token is a serialized `ConnectToken`, it can be read with `ConnectToken::read`
+    /// and passed to [`NetcodeClientTransport::connect`][crate::transport::NetcodeClientTransport::connect]
+    /// with a new client.
+    pub fn take_transfer_token(&mut self) -> Option<Bytes> {
+        self.transfer.take_token()
+    }
+
+    // Sends the token to the remote, the connection is disconnected once the token is acked.
+    pub(crate) fn transfer(&mut self, token: Bytes) -> Result<(), TransferError> {
+        if self.is_disconnected() {
+            return Err(TransferError::ClientNotFound);
+        }
+        if token.is_empty() || token.len() > MAX_TRANSFER_TOKEN_BYTES {
+            return Err(TransferError::InvalidTokenSize { len: token.len() });
+        }
+        if self.transfer.is_started() {
+            return Err(TransferError::AlreadyTransferring);
+        }
+
+        self.transfer.start(token);
+        Ok(())
     }
 
     /// Returns the available memory in bytes for the given channel.
-    pub fn channel_available_memory<I: Into<u8>>(&self, channel_id: I) -> usize {
-        let channel_id = channel_id.into();
+    pub fn channel_available_memory<I: Into<ChannelId>>(&self, channel_id: I) -> usize {
+        let channel_id = channel_id.into().raw();
         if let Some(reliable_channel) = self.send_reliable_channels.get(&channel_id) {
             reliable_channel.available_memory()
         } else if let Some(unreliable_channel) = self.send_unreliable_channels.get(&channel_id) {
@@ -304,77 +1158,908 @@ impl RenetClient {
         }
     }
 
+    /// Returns the bytes of the messages held by all the channels of the connection:
+    /// messages queued or waiting for their ack, and received messages not read yet.
+    pub fn memory_usage(&self) -> usize {
+        let send: usize = self
+            .send_reliable_channels
+            .values()
+            .map(|channel| channel.memory_usage())
+            .sum::<usize>()
+            + self
+                .send_unreliable_channels
+                .values()
+                .map(|channel| channel.memory_usage())
+                .sum::<usize>();
+        let receive: usize = self
+            .receive_reliable_channels
+            .values()
+            .map(|channel| channel.memory_usage())
+            .sum::<usize>()
+            + self
+                .receive_unreliable_channels
+                .values()
+                .map(|channel| channel.memory_usage())
+                .sum::<usize>();
+        send + receive
+    }
+
     /// Checks if the channel can send a message with the given size in bytes.
-    pub fn can_send_message<I: Into<u8>>(&self, channel_id: I, size_bytes: usize) -> bool {
-        let channel_id = channel_id.into();
-        if let Some(reliable_channel) = self.send_reliable_channels.get(&channel_id) {
-            reliable_channel.can_send_message(size_bytes)
+    /// Messages above the overflow threshold of the channel are checked against its overflow channel.
+    pub fn can_send_message<I: Into<ChannelId>>(&self, channel_id: I, size_bytes: usize) -> bool {
+        let channel_id = channel_id.into().raw();
+        let channel_id = match self.overflow_routes.get(&channel_id) {
+            Some(overflow) if size_bytes > overflow.threshold_bytes => overflow.channel_id.raw(),
+            _ => channel_id,
+        };
+        size_bytes <= self.channel_capacity(channel_id).max_message_bytes
+    }
+
+    /// Returns the room left in the channel, to fill it without going over its limits.
+    /// The bytes of reliable channels are freed once the messages are acked.
+    pub fn channel_capacity<I: Into<ChannelId>>(&self, channel_id: I) -> ChannelCapacity {
+        let channel_id = channel_id.into().raw();
+        let mut capacity = if let Some(reliable_channel) = self.send_reliable_channels.get(&channel_id) {
+            reliable_channel.capacity()
         } else if let Some(unreliable_channel) = self.send_unreliable_channels.get(&channel_id) {
-            unreliable_channel.can_send_message(size_bytes)
+            unreliable_channel.capacity()
         } else {
-            panic!("Called 'can_send_message' with invalid channel {channel_id}");
+            panic!("Called 'channel_capacity' with invalid channel {channel_id}");
+        };
+        if let Some(&limit) = self.send_message_limits.get(&channel_id) {
+            capacity.max_message_bytes = capacity.max_message_bytes.min(limit);
         }
+        capacity.max_message_bytes = capacity.max_message_bytes.saturating_sub(self.framing_bytes(channel_id));
+        capacity
+    }
+
+    // Bytes added to the messages of the channel before they are charged to it:
+    // the kind, the compression flag, the trace id and the group header
+    fn framing_bytes(&self, channel_id: u8) -> usize {
+        let kind_bytes = usize::from(self.message_kinds.is_send_channel(channel_id));
+        let compression_bytes = usize::from(self.channel_compression.is_send_channel(channel_id));
+        let trace_bytes = match self.traced_send_channels.contains(&channel_id) {
+            true => TRACE_ID_BYTES,
+            false => 0,
+        };
+        kind_bytes + compression_bytes + trace_bytes + self.channel_groups.header_len(channel_id)
     }
 
     /// Send a message to the server over a channel.
-    pub fn send_message<I: Into<u8>, B: Into<Bytes>>(&mut self, channel_id: I, message: B) {
-        if self.is_disconnected() {
-            return;
-        }
+    /// Returns the id of the message when sent over a reliable channel,
+    /// it can be matched with the ids from [`RenetClient::drain_acked_messages`].
+    /// For unreliable channels with [`ChannelConfig::delivery_feedback`], see [`RenetClient::drain_message_deliveries`].
+    ///
+    /// Failures are handled by the connection: a full reliable channel disconnects with
+    /// [`ChannelError::ReliableChannelMaxMemoryReached`], since the remote would miss a message, unless its
+    /// [`ChannelConfig::queue_policy`] is [`QueuePolicy::RejectSend`], a full unreliable channel drops the message,
+    /// and a message too large for the channel is not sent and an error is logged.
+    /// Use [`RenetClient::try_send_message`] to handle them instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the channel does not exist.
+    pub fn send_message<I: Into<ChannelId>, B: Into<Bytes>>(&mut self, channel_id: I, message: B) -> Option<MessageId> {
+        let result = self.send_tagged_message(channel_id.into().raw(), message.into(), 0, [0; TRACE_ID_BYTES]);
+        self.handle_send_error(result)
+    }
 
-        let channel_id = channel_id.into();
-        if let Some(reliable_channel) = self.send_reliable_channels.get_mut(&channel_id) {
-            if let Err(error) = reliable_channel.send_message(message.into()) {
-                self.disconnect_with_reason(DisconnectReason::SendChannelError { channel_id, error });
+    /// Same as [`RenetClient::send_message`], but returns the failures instead of handling them,
+    /// the connection is left as is, for example to skip an optional update when the channel is full.
+    ///
+    /// The message is refused when above the [`ChannelConfig::max_message_size`] of the channel,
+    /// for reliable channels when above its `max_memory_usage_bytes`, and for unreliable channels when above its
+    /// [`ChannelConfig::max_fragments`].
+    /// Messages above the overflow threshold of the channel are checked against its overflow channel.
+    pub fn try_send_message<I: Into<ChannelId>, B: Into<Bytes>>(
+        &mut self,
+        channel_id: I,
+        message: B,
+    ) -> Result<Option<MessageId>, SendError> {
+        self.send_tagged_message(channel_id.into().raw(), message.into(), 0, [0; TRACE_ID_BYTES])
+    }
+
+    // Failures of send_message: fatal for the reliable channels, the message is dropped otherwise
+    pub(crate) fn handle_send_error(&mut self, result: Result<Option<MessageId>, SendError>) -> Option<MessageId> {
+        match result {
+            Ok(message_id) => message_id,
+            Err(SendError::ChannelFull { channel_id })
+                if self.send_reliable_channels.contains_key(&channel_id) && !self.reject_send_channels.contains(&channel_id) =>
+            {
+                self.disconnect_with_reason(DisconnectReason::SendChannelError {
+                    channel_id,
+                    error: ChannelError::ReliableChannelMaxMemoryReached,
+                });
+                None
+            }
+            Err(SendError::ChannelFull { channel_id }) => {
+                self.dropped_messages(channel_id, DropReason::ChannelFull, 1);
+                let warning = RepeatedWarning::SendChannelFull(channel_id);
+                if self.log_limiter.should_log(warning) {
+                    log::warn!("{warning}");
+                }
+                None
+            }
+            Err(SendError::InvalidChannel { channel_id }) => panic!("Called 'send_message' with invalid channel {channel_id}"),
+            Err(error) => {
+                log::error!("failed to send message: {error}");
+                None
             }
-        } else if let Some(unreliable_channel) = self.send_unreliable_channels.get_mut(&channel_id) {
-            unreliable_channel.send_message(message.into());
-        } else {
-            panic!("Called 'send_message' with invalid channel {channel_id}");
         }
     }
 
-    /// Receive a message from the server over a channel.
-    pub fn receive_message<I: Into<u8>>(&mut self, channel_id: I) -> Option<Bytes> {
-        if self.is_disconnected() {
-            return None;
-        }
+    /// Returns the number of messages of the channel sent over its overflow channel, see [`ChannelConfig::overflow`].
+    pub fn overflowed_messages<I: Into<ChannelId>>(&self, channel_id: I) -> u64 {
+        let channel_id = channel_id.into().raw();
+        self.overflowed_messages.get(&channel_id).copied().unwrap_or(0)
+    }
 
-        let channel_id = channel_id.into();
-        if let Some(reliable_channel) = self.receive_reliable_channels.get_mut(&channel_id) {
-            reliable_channel.receive_message()
-        } else if let Some(unreliable_channel) = self.receive_unreliable_channels.get_mut(&channel_id) {
-            unreliable_channel.receive_message()
+    /// Returns the number of messages of the channel dropped for being queued longer than its
+    /// [`ChannelConfig::message_ttl`], they are also counted as [`DropReason::Expired`].
+    pub fn expired_messages<I: Into<ChannelId>>(&self, channel_id: I) -> u64 {
+        let channel_id = channel_id.into().raw();
+        self.expired_messages.get(&channel_id).copied().unwrap_or(0)
+    }
+
+    /// Cancels a sliced message sent over a reliable channel that was not fully acked yet, for example
+    /// a big asset the remote no longer needs. Its remaining slices are not sent, and the remote
+    /// discards the slices it received and receives the next messages of the channel as usual.
+    /// A message the remote already assembled is still received by it.
+    ///
+    /// Returns false if the message is not an unacked sliced message, or if the channel has a group:
+    /// the later messages of the group would wait for the cancelled one.
+    pub fn cancel_message<I: Into<ChannelId>>(&mut self, channel_id: I, message_id: MessageId) -> bool {
+        let channel_id = channel_id.into().raw();
+        if let Some(reliable_channel) = self.send_reliable_channels.get_mut(&channel_id) {
+            !self.channel_groups.is_send_grouped(channel_id) && reliable_channel.cancel_sliced_message(message_id.raw())
+        } else if self.send_unreliable_channels.contains_key(&channel_id) {
+            false
         } else {
-            panic!("Called 'receive_message' with invalid channel {channel_id}");
+            panic!("Called 'cancel_message' with invalid channel {channel_id}");
         }
     }
 
-    /// Advances the client by the duration.
-    /// Should be called every tick
-    pub fn update(&mut self, duration: Duration) {
-        self.current_time += duration;
-        self.stats.update(self.current_time);
+    /// Send a message with a trace id over a channel with [`ChannelConfig::tracing_context`],
+    /// the remote receives it with `receive_message_traced`.
+    /// Messages sent with [`RenetClient::send_message`] on these channels have a zero trace id.
+    pub fn send_message_traced<I: Into<ChannelId>, B: Into<Bytes>>(
+        &mut self,
+        channel_id: I,
+        message: B,
+        trace_id: [u8; TRACE_ID_BYTES],
+    ) -> Option<MessageId> {
+        let channel_id = channel_id.into().raw();
+        assert!(
+            self.traced_send_channels.contains(&channel_id),
+            "Called 'send_message_traced' with channel {channel_id} without tracing context"
+        );
+        let result = self.send_tagged_message(channel_id, message.into(), 0, trace_id);
+        self.handle_send_error(result)
+    }
 
-        for unreliable_channel in self.receive_unreliable_channels.values_mut() {
-            unreliable_channel.discard_incomplete_old_slices(self.current_time);
-        }
+    /// Send a message of the given kind over a channel with [`ChannelConfig::message_kinds`],
+    /// the remote receives it with `receive_message_with_kind`.
+    /// Messages sent with [`RenetClient::send_message`] on these channels have the kind 0.
+    pub fn send_message_kind<I: Into<ChannelId>, B: Into<Bytes>>(&mut self, channel_id: I, kind: u8, message: B) -> Option<MessageId> {
+        let channel_id = channel_id.into().raw();
+        assert!(
+            self.message_kinds.is_send_channel(channel_id),
+            "Called 'send_message_kind' with channel {channel_id} without message kinds"
+        );
+        let result = self.send_tagged_message(channel_id, message.into(), kind, [0; TRACE_ID_BYTES]);
+        self.handle_send_error(result)
+    }
 
-        // Discard lost packets
-        let mut lost_packets: Vec<u64> = Vec::new();
-        for (&sequence, sent_packet) in self.sent_packets.iter() {
-            const DISCARD_AFTER: Duration = Duration::from_secs(3);
-            if self.current_time - sent_packet.sent_at >= DISCARD_AFTER {
-                lost_packets.push(sequence);
-            } else {
-                // If the current packet is not lost, the next ones will not be lost
-                // since all the next packets were sent after this one.
-                break;
-            }
+    fn send_tagged_message(
+        &mut self,
+        channel_id: u8,
+        message: Bytes,
+        kind: u8,
+        trace_id: [u8; TRACE_ID_BYTES],
+    ) -> Result<Option<MessageId>, SendError> {
+        if self.is_disconnected() {
+            return Ok(None);
         }
 
-        for sequence in lost_packets.iter() {
-            self.sent_packets.remove(sequence);
+        let (channel_id, overflowed) = match self.overflow_routes.get(&channel_id) {
+            Some(overflow) if message.len() > overflow.threshold_bytes => (overflow.channel_id.raw(), Some(channel_id)),
+            _ => (channel_id, None),
+        };
+        let reliable = if self.send_reliable_channels.contains_key(&channel_id) {
+            true
+        } else if self.send_unreliable_channels.contains_key(&channel_id) {
+            false
+        } else {
+            return Err(SendError::InvalidChannel { channel_id });
+        };
+
+        let message_len = message.len();
+        let message = self.message_kinds.prefix(channel_id, kind, message);
+        let message = self.channel_compression.compress(channel_id, message);
+        let message = self.prefix_trace_id(channel_id, message, trace_id);
+        // Checked before the message is numbered in its group, a refused message must not leave a gap
+        let size = self.channel_groups.header_len(channel_id) + message.len();
+        if let Some(&limit) = self.send_message_limits.get(&channel_id) {
+            if size > limit {
+                return Err(SendError::MessageTooLarge { channel_id, size, limit });
+            }
+        }
+        let can_send = if reliable {
+            self.send_reliable_channels[&channel_id].can_send_message(size)
+        } else {
+            self.send_unreliable_channels[&channel_id].can_send_message(size)
+        };
+        if !can_send {
+            return Err(SendError::ChannelFull { channel_id });
+        }
+        if let Some(overflowed) = overflowed {
+            *self.overflowed_messages.entry(overflowed).or_default() += 1;
+        }
+
+        let message = self.channel_groups.stamp(channel_id, message, reliable);
+        self.message_kinds.sent(channel_id, kind, message_len);
+        self.channel_stats.entry(channel_id).or_default().messages_sent += 1;
+        if reliable {
+            let reliable_channel = self.send_reliable_channels.get_mut(&channel_id).unwrap();
+            let message_id = reliable_channel
+                .send_message(message, self.current_time)
+                .expect("reliable channel has memory for the message");
+            Ok(Some(MessageId::from_raw(message_id)))
+        } else {
+            let unreliable_channel = self.send_unreliable_channels.get_mut(&channel_id).unwrap();
+            let message_id = unreliable_channel.send_message(message, self.current_time);
+            let delivery_feedback = unreliable_channel.has_delivery_feedback();
+            let evicted_drops = unreliable_channel.take_evicted_drops();
+            if evicted_drops > 0 {
+                self.dropped_messages(channel_id, DropReason::ChannelFull, evicted_drops);
+            }
+            match delivery_feedback {
+                true => Ok(message_id.map(MessageId::from_raw)),
+                false => Ok(None),
+            }
+        }
+    }
+
+    // Replaces the messages waiting to be sent in the channel with the given one.
+    // Only unreliable channels can drop queued messages, returns false for reliable channels
+    // or if the message does not fit in the channel even when empty.
+    pub(crate) fn replace_queued_messages(&mut self, channel_id: u8, message: Bytes) -> bool {
+        if self.is_disconnected() {
+            return false;
+        }
+
+        if !self.send_unreliable_channels.contains_key(&channel_id) {
+            return false;
+        }
+        let message_len = message.len();
+        let message = self.message_kinds.prefix(channel_id, 0, message);
+        let message = self.channel_compression.compress(channel_id, message);
+        let message = self.prefix_trace_id(channel_id, message, [0; TRACE_ID_BYTES]);
+        let size = self.channel_groups.header_len(channel_id) + message.len();
+        if self.send_message_limits.get(&channel_id).is_some_and(|&limit| size > limit) {
+            return false;
+        }
+        let message = self.channel_groups.stamp(channel_id, message, false);
+        let unreliable_channel = self.send_unreliable_channels.get_mut(&channel_id).unwrap();
+        let cleared = unreliable_channel.clear_messages();
+        let fits = unreliable_channel.can_send_message(message.len());
+        if fits {
+            unreliable_channel.send_message(message, self.current_time);
+        }
+        if cleared > 0 {
+            self.dropped_messages(channel_id, DropReason::ChannelFull, cleared);
+        }
+        if !fits {
+            return false;
+        }
+        self.message_kinds.sent(channel_id, 0, message_len);
+        self.channel_stats.entry(channel_id).or_default().messages_sent += 1;
+
+        true
+    }
+
+    // Messages of the channels with tracing context carry the trace id before the message
+    fn prefix_trace_id(&self, channel_id: u8, message: Bytes, trace_id: [u8; TRACE_ID_BYTES]) -> Bytes {
+        if !self.traced_send_channels.contains(&channel_id) {
+            return message;
+        }
+
+        let mut prefixed = BytesMut::with_capacity(TRACE_ID_BYTES + message.len());
+        prefixed.put_slice(&trace_id);
+        prefixed.put_slice(&message);
+        prefixed.freeze()
+    }
+
+    fn dropped_messages(&mut self, channel_id: u8, reason: DropReason, count: u64) {
+        self.stats.dropped(reason, count);
+        self.channel_stats.entry(channel_id).or_default().dropped += count;
+        #[cfg(feature = "log_dropped_packets")]
+        log::warn!("dropped {count} messages of channel {channel_id}: {reason}");
+    }
+
+    fn received_messages_dropped(&mut self, channel_id: u8, count: u64) {
+        self.stats.received_dropped(count);
+        self.channel_stats.entry(channel_id).or_default().dropped += count;
+        let warning = RepeatedWarning::ReceiveChannelFull(channel_id);
+        if self.log_limiter.should_log(warning) {
+            log::warn!("{warning}");
+        }
+    }
+
+    /// Counts a packet from [`RenetClient::get_packets_to_send`] that could not be sent,
+    /// see [`NetworkCounters::dropped`].
+    /// <p style="background:rgba(77,220,255,0.16);padding:0.5em;">
+    /// <strong>Note:</strong> This should only be called by the transport layer.
+    /// </p>
+    pub fn record_dropped_packet(&mut self, reason: DropReason, _packet: &[u8]) {
+        self.stats.dropped(reason, 1);
+        #[cfg(feature = "log_dropped_packets")]
+        log::warn!("dropped packet with messages of channel {:?}: {reason}", packet_channel_id(_packet));
+    }
+
+    /// Counts a datagram sent on the wire, of the given size after the transport encryption.
+    /// The configured `packet_header_bytes` are added to it.
+    /// <p style="background:rgba(77,220,255,0.16);padding:0.5em;">
+    /// <strong>Note:</strong> This should only be called by the transport layer.
+    /// </p>
+    pub fn record_wire_packet_sent(&mut self, len: usize) {
+        self.stats.sent_wire_bytes(len as u64 + self.packet_header_bytes);
+    }
+
+    /// Counts a datagram received from the wire, of the given size before the transport decryption.
+    /// The configured `packet_header_bytes` are added to it.
+    /// <p style="background:rgba(77,220,255,0.16);padding:0.5em;">
+    /// <strong>Note:</strong> This should only be called by the transport layer.
+    /// </p>
+    pub fn record_wire_packet_received(&mut self, len: usize) {
+        self.stats.received_wire_bytes(len as u64 + self.packet_header_bytes);
+    }
+
+    /// Pauses or resumes sending on a channel.
+    /// A paused channel still accepts messages, within its memory limit, but sends none of them:
+    /// reliable channels keep resending the messages sent before the pause until they are acked,
+    /// and don't count the paused time for the `unacked_message_timeout`.
+    /// Resuming sends the queued messages in order as usual, within the channel's bandwidth.
+    /// Messages still queued in a paused channel are dropped when the connection is disconnected.
+    pub fn set_channel_paused<I: Into<ChannelId>>(&mut self, channel_id: I, paused: bool) {
+        let channel_id = channel_id.into().raw();
+        if let Some(reliable_channel) = self.send_reliable_channels.get_mut(&channel_id) {
+            reliable_channel.set_paused(paused, self.current_time);
+        } else if let Some(unreliable_channel) = self.send_unreliable_channels.get_mut(&channel_id) {
+            unreliable_channel.set_paused(paused);
+        } else {
+            panic!("Called 'set_channel_paused' with invalid channel {channel_id}");
+        }
+    }
+
+    /// Returns whether sending on the channel is paused, see [`RenetClient::set_channel_paused`].
+    pub fn is_channel_paused<I: Into<ChannelId>>(&self, channel_id: I) -> bool {
+        let channel_id = channel_id.into().raw();
+        if let Some(reliable_channel) = self.send_reliable_channels.get(&channel_id) {
+            reliable_channel.is_paused()
+        } else if let Some(unreliable_channel) = self.send_unreliable_channels.get(&channel_id) {
+            unreliable_channel.is_paused()
+        } else {
+            panic!("Called 'is_channel_paused' with invalid channel {channel_id}");
+        }
+    }
+
+    /// Drains the warnings emitted when a channel crossed its warning threshold,
+    /// see [`ChannelConfig::warning_threshold`]. Warnings are checked on [`RenetClient::update`].
+    pub fn drain_channel_warnings(&mut self) -> impl Iterator<Item = ChannelWarning> + '_ {
+        self.channel_warnings.drain()
+    }
+
+    /// Drains the errors that didn't disconnect the client, with the id of their channel, oldest first.
+    /// Only the last errors are kept when they are not drained.
+    pub fn drain_channel_errors(&mut self) -> impl Iterator<Item = (u8, ChannelError)> + '_ {
+        self.channel_errors.drain(..)
+    }
+
+    fn received_channel_error(&mut self, channel_id: u8, error: ChannelError) {
+        if self.channel_errors.len() >= MAX_UNDRAINED_EVENTS {
+            self.channel_errors.pop_front();
+        }
+        self.channel_errors.push_back((channel_id, error));
+    }
+
+    /// Sets the next packet sequence, and the next sequence of the unreliable sequenced channels.
+    /// Used by tests to start near the sequence limits, the packet sequence must stay below 2^62.
+    #[doc(hidden)]
+    pub fn __set_sequences(&mut self, packet_sequence: u64, channel_sequence: u16) {
+        self.packet_sequence = packet_sequence;
+        for channel in self.send_unreliable_channels.values_mut() {
+            channel.set_sequence(channel_sequence);
+        }
+    }
+
+    // Quota exceeded since the last call, only for the connections of the server
+    pub(crate) fn take_quota_warning(&mut self) -> Option<QuotaKind> {
+        self.quota_warning.take()
+    }
+
+    /// Adds a channel to the connection, used to send and receive messages.
+    /// The remote must add the same channel, packets of the channel received before are not acknowledged,
+    /// and reported with [`ChannelError::UnknownChannel`] by [`RenetClient::drain_channel_errors`].
+    /// Channels added this way are not part of the channels configuration verified when connecting.
+    ///
+    /// # Panics
+    /// Panics if the channel is reliable sequenced and grouped, or if its overflow route is invalid, see [`OverflowRoute`].
+    pub fn add_channel(&mut self, config: ChannelConfig) -> Result<(), ChannelAlreadyExists> {
+        let channel_id = config.channel_id.raw();
+        let exists = self
+            .send_channels_config
+            .iter()
+            .chain(self.receive_channels_config.iter())
+            .any(|channel| channel.channel_id == config.channel_id);
+        if exists {
+            return Err(ChannelAlreadyExists { channel_id });
+        }
+
+        self.insert_send_channel(&config);
+        self.check_overflow_route(&config);
+        self.insert_receive_channel(&config);
+        self.channel_warnings.add_channel(&config);
+        self.channel_groups.add_channel(&config);
+        self.channel_compression.add_channel(&config);
+        self.message_kinds.add_channel(&config);
+        Ok(())
+    }
+
+    /// Returns the ids of the reliable messages acknowledged by the remote since the last update, in ack order.
+    /// Acked ids not drained are discarded in the next [`RenetClient::update`].
+    /// Always empty for unreliable channels.
+    pub fn drain_acked_messages<I: Into<ChannelId>>(&mut self, channel_id: I) -> impl Iterator<Item = MessageId> + '_ {
+        let channel_id = channel_id.into().raw();
+        let acked_messages = match self.send_reliable_channels.get_mut(&channel_id) {
+            Some(reliable_channel) => Some(reliable_channel.acked_messages()),
+            None if self.send_unreliable_channels.contains_key(&channel_id) => None,
+            None => panic!("Called 'drain_acked_messages' with invalid channel {channel_id}"),
+        };
+
+        acked_messages.into_iter().flatten().map(MessageId::from_raw)
+    }
+
+    /// Returns whether the messages sent over an unreliable channel with [`ChannelConfig::delivery_feedback`] were delivered,
+    /// matching the ids returned by [`RenetClient::send_message`]. The outcomes are known since the last update:
+    /// messages are delivered when the remote acks them, and lost when dropped before being sent
+    /// or not acked within 3 seconds. Outcomes not drained are discarded in the next [`RenetClient::update`].
+    /// Always empty for reliable channels and unreliable channels without delivery feedback.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the channel does not exist.
+    pub fn drain_message_deliveries<I: Into<ChannelId>>(&mut self, channel_id: I) -> impl Iterator<Item = MessageDelivery> + '_ {
+        let channel_id = channel_id.into().raw();
+        let deliveries = match self.send_unreliable_channels.get_mut(&channel_id) {
+            Some(unreliable_channel) => Some(unreliable_channel.deliveries()),
+            None if self.send_reliable_channels.contains_key(&channel_id) => None,
+            None => panic!("Called 'drain_message_deliveries' with invalid channel {channel_id}"),
+        };
+
+        deliveries.into_iter().flatten()
+    }
+
+    /// Returns the counters of the channel, in both directions, see [`ChannelStats`].
+    pub fn channel_stats<I: Into<ChannelId>>(&self, channel_id: I) -> ChannelStats {
+        let channel_id = channel_id.into().raw();
+        let exists = self.send_reliable_channels.contains_key(&channel_id)
+            || self.send_unreliable_channels.contains_key(&channel_id)
+            || self.receive_reliable_channels.contains_key(&channel_id)
+            || self.receive_unreliable_channels.contains_key(&channel_id);
+        if !exists {
+            panic!("Called 'channel_stats' with invalid channel {channel_id}");
+        }
+        self.channel_stats.get(&channel_id).copied().unwrap_or_default()
+    }
+
+    /// Resets the counters of all the channels, see [`RenetClient::channel_stats`].
+    pub fn reset_channel_stats(&mut self) {
+        self.channel_stats.clear();
+    }
+
+    /// Returns the queue and delivery latencies of the messages sent in the channel.
+    pub fn channel_latency<I: Into<ChannelId>>(&self, channel_id: I) -> ChannelLatency {
+        let channel_id = channel_id.into().raw();
+        if let Some(reliable_channel) = self.send_reliable_channels.get(&channel_id) {
+            reliable_channel.latency()
+        } else if let Some(unreliable_channel) = self.send_unreliable_channels.get(&channel_id) {
+            unreliable_channel.latency()
+        } else {
+            panic!("Called 'channel_latency' with invalid channel {channel_id}");
+        }
+    }
+
+    /// Returns how long the messages received in the ordered channel waited for a missing earlier message,
+    /// over the last 64 times the channel was blocked. Each sample is the time from the first message received
+    /// after the gap to the missing message arriving.
+    /// Unordered, sequenced and unreliable channels are never blocked and return empty statistics.
+    pub fn head_of_line_blocking<I: Into<ChannelId>>(&self, channel_id: I) -> LatencyStats {
+        let channel_id = channel_id.into().raw();
+        if let Some(reliable_channel) = self.receive_reliable_channels.get(&channel_id) {
+            reliable_channel.head_of_line_blocking()
+        } else if self.receive_unreliable_channels.contains_key(&channel_id) {
+            LatencyStats::default()
+        } else {
+            panic!("Called 'head_of_line_blocking' with invalid channel {channel_id}");
+        }
+    }
+
+    /// Enables or disables the detailed latency records of the reliable messages,
+    /// see [`RenetClient::drain_message_latency_records`]. Disabled by default.
+    pub fn set_record_message_latencies(&mut self, enabled: bool) {
+        for reliable_channel in self.send_reliable_channels.values_mut() {
+            reliable_channel.set_record_latencies(enabled);
+        }
+    }
+
+    /// Returns the latencies of the reliable messages acknowledged since the last update, in ack order.
+    /// Only recorded when enabled with [`RenetClient::set_record_message_latencies`],
+    /// records not drained are discarded in the next [`RenetClient::update`].
+    /// Always empty for unreliable channels.
+    pub fn drain_message_latency_records<I: Into<ChannelId>>(&mut self, channel_id: I) -> impl Iterator<Item = MessageLatencyRecord> + '_ {
+        let channel_id = channel_id.into().raw();
+        let records = match self.send_reliable_channels.get_mut(&channel_id) {
+            Some(reliable_channel) => Some(reliable_channel.latency_records()),
+            None if self.send_unreliable_channels.contains_key(&channel_id) => None,
+            None => panic!("Called 'drain_message_latency_records' with invalid channel {channel_id}"),
+        };
+
+        records.into_iter().flatten()
+    }
+
+    /// Inserts a fence after the messages queued so far in the channel.
+    /// Use [`RenetClient::is_fence_reached`] to know when all of them were received by the remote.
+    /// Fences in unreliable channels are always reached.
+    pub fn insert_fence<I: Into<ChannelId>>(&mut self, channel_id: I) -> FenceId {
+        let channel_id = channel_id.into().raw();
+        let message_id = match self.send_reliable_channels.get(&channel_id) {
+            Some(reliable_channel) => reliable_channel.next_message_id(),
+            None if self.send_unreliable_channels.contains_key(&channel_id) => 0,
+            None => panic!("Called 'insert_fence' with invalid channel {channel_id}"),
+        };
+
+        FenceId { channel_id, message_id }
+    }
+
+    /// Returns whether all messages queued before the fence were acked by the remote.
+    pub fn is_fence_reached(&self, fence: FenceId) -> bool {
+        let channel_id = fence.channel_id;
+        match self.send_reliable_channels.get(&channel_id) {
+            Some(reliable_channel) => reliable_channel.all_acked_before(fence.message_id),
+            None if self.send_unreliable_channels.contains_key(&channel_id) => true,
+            None => panic!("Called 'is_fence_reached' with invalid channel {channel_id}"),
+        }
+    }
+
+    /// Receive a message from the server over a channel.
+    /// Messages are only available after the channels configuration of the remote has been verified.
+    pub fn receive_message<I: Into<ChannelId>>(&mut self, channel_id: I) -> Option<Bytes> {
+        self.receive_tagged_message(channel_id.into().raw()).map(|(message, _, _)| message)
+    }
+
+    /// Returns an iterator receiving the messages of the channel, in the order [`RenetClient::receive_message`] returns them.
+    /// Messages are only removed from the channel as the iterator advances, use `take` to bound the work
+    /// done in a tick: the messages not consumed stay in the channel for the next call.
+    pub fn receive_messages<I: Into<ChannelId>>(&mut self, channel_id: I) -> impl Iterator<Item = Bytes> + '_ {
+        let channel_id = channel_id.into();
+        std::iter::from_fn(move || self.receive_message(channel_id))
+    }
+
+    /// Receive a message from the server over a channel, with the trace id it was sent with.
+    /// The trace id is zero for channels without [`ChannelConfig::tracing_context`].
+    pub fn receive_message_traced<I: Into<ChannelId>>(&mut self, channel_id: I) -> Option<(Bytes, [u8; TRACE_ID_BYTES])> {
+        self.receive_tagged_message(channel_id.into().raw())
+            .map(|(message, _, trace_id)| (message, trace_id))
+    }
+
+    /// Receive a message from the server over a channel, with the kind it was sent with.
+    /// The kind is 0 for channels without [`ChannelConfig::message_kinds`].
+    pub fn receive_message_with_kind<I: Into<ChannelId>>(&mut self, channel_id: I) -> Option<(u8, Bytes)> {
+        self.receive_tagged_message(channel_id.into().raw())
+            .map(|(message, kind, _)| (kind, message))
+    }
+
+    /// Receive the next message from the server over any channel, with the id of its channel.
+    /// The channels with messages take turns, one message each, so a busy channel doesn't hold back the others.
+    /// Only the channels that received data since they were last found empty are checked,
+    /// the call is cheap when no message is pending.
+    pub fn receive_any_message(&mut self) -> Option<(u8, Bytes)> {
+        if self.is_disconnected() || !self.remote_channel_config_received {
+            return None;
+        }
+
+        while let Some(channel_id) = self.ready_receive_channels.pop_front() {
+            if let Some(message) = self.receive_message(channel_id) {
+                self.ready_receive_channels.push_back(channel_id);
+                return Some((channel_id, message));
+            }
+            self.ready_receive_channel_ids.remove(&channel_id);
+        }
+
+        None
+    }
+
+    fn mark_receive_channel_ready(&mut self, channel_id: u8) {
+        if self.ready_receive_channel_ids.insert(channel_id) {
+            self.ready_receive_channels.push_back(channel_id);
+        }
+    }
+
+    /// Returns the messages and bytes sent and received of each kind in a channel with [`ChannelConfig::message_kinds`],
+    /// ordered by kind. Only the kinds with messages are listed, empty for channels without message kinds.
+    pub fn message_kind_stats<I: Into<ChannelId>>(&self, channel_id: I) -> Vec<(u8, MessageKindStats)> {
+        self.message_kinds.stats(channel_id.into().raw())
+    }
+
+    fn receive_tagged_message(&mut self, channel_id: u8) -> Option<(Bytes, u8, [u8; TRACE_ID_BYTES])> {
+        if self.is_disconnected() || !self.remote_channel_config_received {
+            return None;
+        }
+
+        self.unread_updates.remove(&channel_id);
+        let message = if self.channel_groups.is_receive_grouped(channel_id) {
+            self.receive_grouped_message(channel_id)
+        } else if let Some(reliable_channel) = self.receive_reliable_channels.get_mut(&channel_id) {
+            reliable_channel.receive_message()
+        } else if let Some(unreliable_channel) = self.receive_unreliable_channels.get_mut(&channel_id) {
+            unreliable_channel.receive_message()
+        } else {
+            panic!("Called 'receive_message' with invalid channel {channel_id}");
+        };
+        self.check_duplicate_delivery(channel_id);
+        let message = message?;
+
+        let received = self.split_trace_id(channel_id, message).and_then(|(message, trace_id)| {
+            let message = self.channel_compression.decompress(channel_id, message)?;
+            let (message, kind) = self.message_kinds.split(channel_id, message)?;
+            Ok((message, kind, trace_id))
+        });
+        match received {
+            Ok((message, kind, trace_id)) => {
+                self.message_kinds.received(channel_id, kind, message.len());
+                self.channel_stats.entry(channel_id).or_default().messages_received += 1;
+                // The message can unblock the withheld messages of the other channels of its group
+                for group_channel_id in self.channel_groups.receive_group_channels(channel_id) {
+                    self.mark_receive_channel_ready(group_channel_id);
+                }
+                Some((message, kind, trace_id))
+            }
+            Err(error) => {
+                self.disconnect_with_reason(DisconnectReason::ReceiveChannelError { channel_id, error });
+                None
+            }
+        }
+    }
+
+    // Reliable channels must deliver each message once, a duplicate is a bug that would silently corrupt the application state
+    fn check_duplicate_delivery(&mut self, channel_id: u8) {
+        let Some(duplicate) = self
+            .receive_reliable_channels
+            .get_mut(&channel_id)
+            .and_then(|reliable_channel| reliable_channel.take_duplicate_delivery())
+        else {
+            return;
+        };
+
+        let packet_sequence = match duplicate.packet_sequence {
+            Some(sequence) => format!("completed in packet {sequence}"),
+            None => "not received again".to_string(),
+        };
+        let report = format!(
+            "reliable channel {channel_id} delivered message {} twice, {packet_sequence}",
+            duplicate.message_id
+        );
+        if cfg!(debug_assertions) {
+            panic!("{report}");
+        }
+        log::error!("{report}");
+        self.stats.duplicate_delivered();
+    }
+
+    fn split_trace_id(&self, channel_id: u8, message: Bytes) -> Result<(Bytes, [u8; TRACE_ID_BYTES]), ChannelError> {
+        if !self.traced_receive_channels.contains(&channel_id) {
+            return Ok((message, [0; TRACE_ID_BYTES]));
+        }
+
+        let trace_id: [u8; TRACE_ID_BYTES] = message
+            .get(..TRACE_ID_BYTES)
+            .and_then(|trace_id| trace_id.try_into().ok())
+            .ok_or(ChannelError::InvalidTracingContext)?;
+        MessageTracer::new(channel_id, 0, false).record("message delivered", &message);
+        Ok((message.slice(TRACE_ID_BYTES..), trace_id))
+    }
+
+    // Moves the received messages of the grouped channel to its group, and returns the first one that can be delivered
+    fn receive_grouped_message(&mut self, channel_id: u8) -> Option<Bytes> {
+        let reliable = self.receive_reliable_channels.contains_key(&channel_id);
+        loop {
+            let message = match self.receive_reliable_channels.get_mut(&channel_id) {
+                Some(reliable_channel) => reliable_channel.receive_message(),
+                None => self.receive_unreliable_channels.get_mut(&channel_id).unwrap().receive_message(),
+            };
+            let Some(message) = message else {
+                break;
+            };
+
+            if let Err(error) = self.channel_groups.push_received(channel_id, message) {
+                self.disconnect_with_reason(DisconnectReason::ReceiveChannelError { channel_id, error });
+                return None;
+            }
+        }
+
+        self.channel_groups.pop_deliverable(channel_id, reliable)
+    }
+
+    /// Returns the misuses of the API detected so far, empty when the client is used correctly.
+    /// Each misuse is also logged once as a warning when it is detected.
+    pub fn health_check(&self) -> Vec<UsageWarning> {
+        self.usage.detected().to_vec()
+    }
+
+    pub(crate) fn usage_warnings(&self) -> &[UsageWarning] {
+        self.usage.detected()
+    }
+
+    // Duration for the transport update, the measured one if the last update measured it
+    #[cfg(feature = "transport")]
+    pub(crate) fn transport_update_duration(&self, duration: Duration) -> Duration {
+        self.update_clock.transport_duration(duration)
+    }
+
+    // Called on every update while connected
+    fn check_usage(&mut self) {
+        self.usage.updated();
+
+        if self.channel_warnings.pending() >= MAX_UNDRAINED_EVENTS || self.channel_errors.len() >= MAX_UNDRAINED_EVENTS {
+            self.usage.detect(UsageWarning::EventsNotDrained);
+        }
+
+        let memory_usages = self
+            .receive_reliable_channels
+            .iter()
+            .map(|(&channel_id, channel)| (channel_id, channel.memory_usage()))
+            .chain(
+                self.receive_unreliable_channels
+                    .iter()
+                    .map(|(&channel_id, channel)| (channel_id, channel.memory_usage())),
+            );
+        for (channel_id, memory_usage) in memory_usages {
+            let max_memory_usage_bytes = self
+                .receive_channels_config
+                .iter()
+                .find(|config| config.channel_id.raw() == channel_id)
+                .map_or(usize::MAX, |config| config.max_memory_usage_bytes);
+            if memory_usage <= max_memory_usage_bytes / 2 {
+                self.unread_updates.remove(&channel_id);
+                continue;
+            }
+
+            let unread_updates = self.unread_updates.entry(channel_id).or_default();
+            *unread_updates += 1;
+            if *unread_updates >= USAGE_CHECK_CALLS {
+                self.usage.detect(UsageWarning::ReceiveBacklog { channel_id });
+            }
+        }
+    }
+
+    /// Returns the total time discarded from updates longer than the configured `max_update_duration`.
+    pub fn clamped_time(&self) -> Duration {
+        self.clamped_time
+    }
+
+    /// Advances the client by the duration.
+    /// Should be called every tick
+    pub fn update(&mut self, duration: Duration) {
+        let duration = self.update_clock.update(duration, &mut self.usage);
+        let duration = clamp_update_duration(duration, self.max_update_duration, &mut self.clamped_time);
+        self.current_time += duration;
+        self.stats.update(self.current_time);
+        self.log_limiter.update_and_log(duration, log::Level::Warn);
+
+        match self.quota.as_mut().and_then(|quota| quota.update(self.current_time)) {
+            Some(QuotaEvent::Warning(kind)) => self.quota_warning = Some(kind),
+            Some(QuotaEvent::Exceeded(kind)) if !self.is_disconnected() => {
+                self.disconnect_with_reason(DisconnectReason::QuotaExceeded { kind });
+                self.quota_exceeded_notice = Some(kind);
+            }
+            _ => {}
+        }
+
+        for unreliable_channel in self.receive_unreliable_channels.values_mut() {
+            unreliable_channel.discard_incomplete_old_slices(self.current_time);
+        }
+
+        for (&channel_id, &policy) in self.receive_policies.iter() {
+            let dropped = match (
+                self.receive_reliable_channels.get_mut(&channel_id),
+                self.receive_unreliable_channels.get_mut(&channel_id),
+            ) {
+                (Some(channel), _) => channel.apply_receive_policy(policy, self.current_time),
+                (None, Some(channel)) => channel.apply_receive_policy(policy, self.current_time),
+                (None, None) => 0,
+            };
+            if dropped == 0 {
+                continue;
+            }
+
+            self.stats.unread_dropped(dropped);
+            self.channel_stats.entry(channel_id).or_default().dropped += dropped;
+            if let ReceivePolicy::WarnAndDrop { max_age } = policy {
+                self.channel_warnings.warn_once(
+                    channel_id,
+                    ChannelWarningKind::UnreadMessagesDropped,
+                    dropped,
+                    max_age.as_millis() as u64,
+                );
+            }
+        }
+
+        for reliable_channel in self.send_reliable_channels.values_mut() {
+            reliable_channel.clear_acked_messages();
+        }
+        for unreliable_channel in self.send_unreliable_channels.values_mut() {
+            unreliable_channel.clear_deliveries();
+        }
+
+        if let (Some(timeout), Some(first_packet_received_at)) = (self.channel_config_timeout, self.first_packet_received_at) {
+            if !self.remote_channel_config_received && self.current_time.saturating_sub(first_packet_received_at) > timeout {
+                log::error!("Remote channels configuration not received after {timeout:?}, the remote may use an older version");
+                self.disconnect_with_reason(DisconnectReason::ChannelConfigTimeout);
+            }
+        }
+
+        if let Some(timeout) = self.unacked_message_timeout {
+            let timed_out_channel = self
+                .send_reliable_channels
+                .iter()
+                .find(|(_, channel)| channel.oldest_unacked_age(self.current_time) > timeout)
+                .map(|(&channel_id, _)| channel_id);
+
+            if let Some(channel_id) = timed_out_channel {
+                self.disconnect_with_reason(DisconnectReason::UnackedMessageTimeout { channel_id });
+            }
+        }
+
+        for (&channel_id, channel) in self.send_unreliable_channels.iter() {
+            self.channel_warnings
+                .update_send_channel(channel_id, channel.available_memory(), None);
+        }
+        for (&channel_id, channel) in self.send_reliable_channels.iter() {
+            let oldest_unacked_age = channel.oldest_unacked_age(self.current_time);
+            self.channel_warnings
+                .update_send_channel(channel_id, channel.available_memory(), Some(oldest_unacked_age));
+        }
+        for (&channel_id, channel) in self.receive_unreliable_channels.iter() {
+            self.channel_warnings.update_receive_channel(channel_id, channel.memory_usage());
+        }
+        for (&channel_id, channel) in self.receive_reliable_channels.iter() {
+            self.channel_warnings.update_receive_channel(channel_id, channel.memory_usage());
+        }
+        if self.is_connected() {
+            self.check_usage();
+        }
+
+        // Discard lost packets
+        let mut lost_packets: Vec<u64> = Vec::new();
+        for (&sequence, sent_packet) in self.sent_packets.iter() {
+            const DISCARD_AFTER: Duration = Duration::from_secs(3);
+            if self.current_time.saturating_sub(sent_packet.sent_at) >= DISCARD_AFTER {
+                lost_packets.push(sequence);
+            } else {
+                // If the current packet is not lost, the next ones will not be lost
+                // since all the next packets were sent after this one.
+                break;
+            }
+        }
+
+        for sequence in lost_packets.iter() {
+            let sent_packet = self.sent_packets.remove(sequence).unwrap();
+            if let PacketSentInfo::UnreliableMessages { channel_id } = sent_packet.info {
+                if let Some(unreliable_channel) = self.send_unreliable_channels.get_mut(&channel_id) {
+                    unreliable_channel.process_packet_lost(*sequence);
+                }
+            }
         }
     }
 
@@ -387,60 +2072,168 @@ impl RenetClient {
             return;
         }
 
+        self.process_packet_bytes(Bytes::copy_from_slice(packet));
+    }
+
+    /// Process a packet received from the server, the received messages share the memory of the packet.
+    /// Prefer it to [`RenetClient::process_packet`] when the packet is already in a [`Bytes`] buffer to avoid a copy.
+    /// <p style="background:rgba(77,220,255,0.16);padding:0.5em;">
+    /// <strong>Note:</strong> This should only be called by the transport layer.
+    /// </p>
+    pub fn process_packet_bytes(&mut self, packet: Bytes) {
+        if self.is_disconnected() {
+            return;
+        }
+
         self.stats.received_packet(packet.len() as u64);
-        let mut octets = octets::Octets::with_slice(packet);
-        let packet = match Packet::from_bytes(&mut octets) {
+        self.packet_arrival.received_packet(self.current_time);
+        self.first_packet_received_at.get_or_insert(self.current_time);
+        if let Some(quota) = self.quota.as_mut() {
+            quota.received_bytes(packet.len());
+        }
+        let mut octets = octets::Octets::with_slice(&packet);
+        let packet = match Packet::from_shared_bytes(&packet, &mut octets) {
             Err(err) => {
                 self.disconnect_with_reason(DisconnectReason::PacketDeserialization(err));
                 return;
             }
             Ok(packet) => packet,
         };
+        // Any remaining bytes are padding
+        self.stats.received_padding(octets.cap() as u64);
+
+        if let Some(quota) = self.quota.as_mut() {
+            match &packet {
+                Packet::SmallReliable { channel_id, messages, .. } => quota.received_messages(*channel_id, messages.len()),
+                Packet::SmallUnreliable { channel_id, messages, .. } => quota.received_messages(*channel_id, messages.len()),
+                Packet::ReliableSlice { channel_id, slice, .. } | Packet::UnreliableSlice { channel_id, slice, .. }
+                    if slice.slice_index == 0 =>
+                {
+                    quota.received_messages(*channel_id, 1);
+                }
+                _ => {}
+            }
+        }
+
+        // Not acknowledged, the reliable messages are resent once the channel is added
+        if let Some(channel_id) = self.unknown_receive_channel(&packet) {
+            self.received_channel_error(channel_id, ChannelError::UnknownChannel);
+            return;
+        }
 
         self.add_pending_ack(packet.sequence());
+        self.unsent_acks_since.get_or_insert(self.current_time);
 
-        match packet {
+        let received_bytes = match &packet {
             Packet::SmallReliable { channel_id, messages, .. } => {
-                let Some(channel) = self.receive_reliable_channels.get_mut(&channel_id) else {
-                    self.disconnect_with_reason(DisconnectReason::ReceivedInvalidChannelId(channel_id));
-                    return;
-                };
+                Some((*channel_id, messages.iter().map(|(_, message)| message.len()).sum()))
+            }
+            Packet::SmallUnreliable { channel_id, messages, .. } => Some((*channel_id, messages.iter().map(Bytes::len).sum())),
+            Packet::ReliableSlice { channel_id, slice, .. } | Packet::UnreliableSlice { channel_id, slice, .. } => {
+                Some((*channel_id, slice.payload.len()))
+            }
+            _ => None,
+        };
+        if let Some((channel_id, bytes)) = received_bytes {
+            self.channel_stats.entry(channel_id).or_default().bytes_received += bytes as u64;
+            self.mark_receive_channel_ready(channel_id);
+        }
+
+        match packet {
+            Packet::SmallReliable {
+                sequence,
+                channel_id,
+                messages,
+            } => {
+                let channel = self.receive_reliable_channels.get_mut(&channel_id).unwrap();
+                channel.set_packet_sequence(sequence);
 
                 for (message_id, message) in messages {
-                    if let Err(error) = channel.process_message(message, message_id) {
+                    if let Err(error) = channel.process_message(message, message_id, self.current_time) {
                         self.disconnect_with_reason(DisconnectReason::ReceiveChannelError { channel_id, error });
                         return;
                     }
                 }
             }
             Packet::SmallUnreliable { channel_id, messages, .. } => {
-                let Some(channel) = self.receive_unreliable_channels.get_mut(&channel_id) else {
-                    self.disconnect_with_reason(DisconnectReason::ReceivedInvalidChannelId(channel_id));
-                    return;
-                };
+                let channel = self.receive_unreliable_channels.get_mut(&channel_id).unwrap();
 
                 for message in messages {
-                    channel.process_message(message);
+                    if let Err(error) = channel.process_message(message, self.current_time) {
+                        self.disconnect_with_reason(DisconnectReason::ReceiveChannelError { channel_id, error });
+                        return;
+                    }
+                }
+                let full_drops = channel.take_full_drops();
+                if full_drops > 0 {
+                    self.received_messages_dropped(channel_id, full_drops);
                 }
             }
-            Packet::ReliableSlice { channel_id, slice, .. } => {
-                let Some(channel) = self.receive_reliable_channels.get_mut(&channel_id) else {
-                    self.disconnect_with_reason(DisconnectReason::ReceivedInvalidChannelId(channel_id));
-                    return;
-                };
+            Packet::ReliableSlice {
+                sequence,
+                channel_id,
+                slice,
+            } => {
+                let channel = self.receive_reliable_channels.get_mut(&channel_id).unwrap();
+                channel.set_packet_sequence(sequence);
 
-                if let Err(error) = channel.process_slice(slice) {
+                if let Err(error) = channel.process_slice(slice, self.current_time) {
                     self.disconnect_with_reason(DisconnectReason::ReceiveChannelError { channel_id, error });
                 }
             }
             Packet::UnreliableSlice { channel_id, slice, .. } => {
-                let Some(channel) = self.receive_unreliable_channels.get_mut(&channel_id) else {
-                    self.disconnect_with_reason(DisconnectReason::ReceivedInvalidChannelId(channel_id));
-                    return;
-                };
+                let channel = self.receive_unreliable_channels.get_mut(&channel_id).unwrap();
 
                 if let Err(error) = channel.process_slice(slice, self.current_time) {
                     self.disconnect_with_reason(DisconnectReason::ReceiveChannelError { channel_id, error });
+                    return;
+                }
+                let full_drops = channel.take_full_drops();
+                if full_drops > 0 {
+                    self.received_messages_dropped(channel_id, full_drops);
+                }
+            }
+            Packet::CancelReliable {
+                channel_id, message_id, ..
+            } => {
+                let channel = self.receive_reliable_channels.get_mut(&channel_id).unwrap();
+                channel.process_cancel(message_id);
+                // Skipping the cancelled message can unblock the next ones
+                self.mark_receive_channel_ready(channel_id);
+            }
+            Packet::QuotaExceeded { kind, .. } => self.disconnect_with_reason(DisconnectReason::QuotaExceeded { kind }),
+            Packet::Ping { ping_id, .. } => self.pings.received_ping(ping_id),
+            Packet::Pong { ping_id, .. } => self.pings.received_pong(ping_id, self.current_time),
+            Packet::TransferSlice { slice, .. } => {
+                if self.accept_transfer && self.transfer.received_slice(slice) {
+                    self.connection_status = RenetConnectionStatus::Transferring;
+                }
+            }
+            Packet::ChannelConfig {
+                protocol_version,
+                channels,
+                ..
+            } => {
+                if self.remote_channel_config_received {
+                    return;
+                }
+                self.remote_channel_config_received = true;
+
+                if protocol_version != PROTOCOL_VERSION {
+                    self.disconnect_with_reason(DisconnectReason::ProtocolVersionMismatch {
+                        remote_version: protocol_version,
+                    });
+                    return;
+                }
+
+                if let Some(channel_id) = first_mismatched_channel(&self.receive_channels_descriptor, &channels) {
+                    if self.allow_channel_config_mismatch {
+                        log::warn!("Channel {channel_id} configuration does not match with the remote");
+                    } else {
+                        self.disconnect_with_reason(DisconnectReason::ChannelConfigMismatch {
+                            first_mismatched_channel: channel_id,
+                        });
+                    }
                 }
             }
             Packet::Ack { ack_ranges, .. } => {
@@ -453,12 +2246,18 @@ impl RenetClient {
                     }
                 }
 
+                if !new_acks.is_empty() {
+                    self.last_ack_received = self.current_time;
+                }
+                let largest_new_ack = new_acks.iter().max().copied();
+
                 for packet_sequence in new_acks {
                     let sent_packet = self.sent_packets.remove(&packet_sequence).unwrap();
                     self.stats.acked_packet(sent_packet.sent_at, self.current_time);
 
                     // Update rtt
-                    let rtt = (self.current_time - sent_packet.sent_at).as_secs_f64();
+                    self.rtt_samples += 1;
+                    let rtt = self.current_time.saturating_sub(sent_packet.sent_at).as_secs_f64();
                     if self.rtt < f64::EPSILON {
                         self.rtt = rtt;
                     } else {
@@ -468,8 +2267,9 @@ impl RenetClient {
                     match sent_packet.info {
                         PacketSentInfo::ReliableMessages { channel_id, message_ids } => {
                             let reliable_channel = self.send_reliable_channels.get_mut(&channel_id).unwrap();
+                            reliable_channel.process_packet_ack(packet_sequence);
                             for message_id in message_ids {
-                                reliable_channel.process_message_ack(message_id);
+                                reliable_channel.process_message_ack(message_id, self.current_time);
                             }
                         }
                         PacketSentInfo::ReliableSliceMessage {
@@ -478,51 +2278,272 @@ impl RenetClient {
                             slice_index,
                         } => {
                             let reliable_channel = self.send_reliable_channels.get_mut(&channel_id).unwrap();
-                            reliable_channel.process_slice_message_ack(message_id, slice_index);
+                            reliable_channel.process_packet_ack(packet_sequence);
+                            reliable_channel.process_slice_message_ack(message_id, slice_index, self.current_time);
+                        }
+                        PacketSentInfo::UnreliableMessages { channel_id } => {
+                            if let Some(unreliable_channel) = self.send_unreliable_channels.get_mut(&channel_id) {
+                                unreliable_channel.process_packet_ack(packet_sequence);
+                            }
                         }
                         PacketSentInfo::Ack { largest_acked_packet } => {
                             self.acked_largest(largest_acked_packet);
                         }
+                        PacketSentInfo::ChannelConfig => {
+                            self.channel_config_acked = true;
+                        }
+                        PacketSentInfo::TransferSlice { slice_index } => {
+                            if self.transfer.slice_acked(slice_index) {
+                                self.disconnect_with_reason(DisconnectReason::Transferred);
+                            }
+                        }
                         PacketSentInfo::None => {}
                     }
                 }
+
+                if let (Some(threshold), Some(largest_new_ack)) = (self.fast_retransmit_threshold, largest_new_ack) {
+                    self.fast_retransmit(largest_new_ack, threshold);
+                }
+            }
+        }
+    }
+
+    // Presumes lost the packets sent before the acked one and skipped by at least `threshold` acked packets,
+    // their reliable messages are resent without waiting for the resend time, once
+    fn fast_retransmit(&mut self, largest_acked: u64, threshold: u64) {
+        // Packets left in sent_packets are not acked, the others were
+        for (unacked_after, (&sequence, sent_packet)) in self.sent_packets.range_mut(..largest_acked).rev().enumerate() {
+            let acked_after = largest_acked - sequence - unacked_after as u64;
+            if acked_after < threshold || sent_packet.fast_retransmitted {
+                continue;
+            }
+
+            sent_packet.fast_retransmitted = true;
+            match &sent_packet.info {
+                PacketSentInfo::ReliableMessages { channel_id, message_ids } => {
+                    if let Some(reliable_channel) = self.send_reliable_channels.get_mut(channel_id) {
+                        reliable_channel.process_packet_nack(sequence, message_ids);
+                    }
+                }
+                PacketSentInfo::ReliableSliceMessage {
+                    channel_id,
+                    message_id,
+                    slice_index,
+                } => {
+                    if let Some(reliable_channel) = self.send_reliable_channels.get_mut(channel_id) {
+                        reliable_channel.process_slice_nack(sequence, *message_id, *slice_index);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Channel of the packet if it doesn't have a receive channel of its kind
+    fn unknown_receive_channel(&self, packet: &Packet) -> Option<u8> {
+        let (channel_id, exists) = match *packet {
+            Packet::SmallReliable { channel_id, .. }
+            | Packet::ReliableSlice { channel_id, .. }
+            | Packet::CancelReliable { channel_id, .. } => (channel_id, self.receive_reliable_channels.contains_key(&channel_id)),
+            Packet::SmallUnreliable { channel_id, .. } | Packet::UnreliableSlice { channel_id, .. } => {
+                (channel_id, self.receive_unreliable_channels.contains_key(&channel_id))
+            }
+            _ => return None,
+        };
+
+        (!exists).then_some(channel_id)
+    }
+
+    /// Returns a list of packets to be sent to the server.
+    /// <p style="background:rgba(77,220,255,0.16);padding:0.5em;">
+    /// <strong>Note:</strong> This should only be called by the transport layer.
+    /// </p>
+    pub fn get_packets_to_send(&mut self) -> Vec<Payload> {
+        let mut packets: Vec<Packet> = vec![];
+        if self.is_disconnected() {
+            let Some(kind) = self.quota_exceeded_notice.take() else {
+                return vec![];
+            };
+            packets.push(Packet::QuotaExceeded {
+                sequence: self.packet_sequence,
+                kind,
+            });
+            self.packet_sequence += 1;
+            return self.serialize_packets(packets);
+        }
+        self.usage.sent();
+
+        if !self.channel_config_acked {
+            let should_send = match self.channel_config_last_sent {
+                Some(last_sent) => self.current_time.saturating_sub(last_sent) >= CHANNEL_CONFIG_RESEND_TIME,
+                None => true,
+            };
+            if should_send {
+                packets.push(Packet::ChannelConfig {
+                    sequence: self.packet_sequence,
+                    protocol_version: PROTOCOL_VERSION,
+                    channels: self.send_channels_descriptor.clone(),
+                });
+                self.packet_sequence += 1;
+                self.channel_config_last_sent = Some(self.current_time);
+            }
+        }
+
+        // Held messages stay queued in their channels, the channel config and acks still warm up the connection
+        if !self.hold_messages_until_warmed_up || self.is_warmed_up() {
+            // Every channel first uses its share of the bytes by priority, the bytes left are then given in order
+            let mut available_bytes = self.available_bytes_per_tick;
+            let total_priority: u64 = self.channel_priorities.values().map(|&priority| priority as u64).sum();
+            if let Some(bytes_per_priority) = self.available_bytes_per_tick.checked_div(total_priority) {
+                for index in 0..self.channel_send_order.len() {
+                    let order = self.channel_send_order[index];
+                    let priority = self.channel_priorities[&order.channel_id()] as u64;
+                    if priority == 0 {
+                        continue;
+                    }
+                    let share = bytes_per_priority * priority;
+                    let mut share_left = share;
+                    packets.append(&mut self.channel_packets_to_send(order, &mut share_left, false));
+                    available_bytes -= share - share_left;
+                }
+            }
+
+            for index in 0..self.channel_send_order.len() {
+                let order = self.channel_send_order[index];
+                packets.append(&mut self.channel_packets_to_send(order, &mut available_bytes, true));
+            }
+        }
+
+        for slice in self.transfer.slices_to_send(self.current_time) {
+            packets.push(Packet::TransferSlice {
+                sequence: self.packet_sequence,
+                slice,
+            });
+            self.packet_sequence += 1;
+        }
+
+        let (pings, pongs) = self.pings.take_to_send();
+        for ping_id in pings {
+            packets.push(Packet::Ping {
+                sequence: self.packet_sequence,
+                ping_id,
+            });
+            self.packet_sequence += 1;
+        }
+        for ping_id in pongs {
+            packets.push(Packet::Pong {
+                sequence: self.packet_sequence,
+                ping_id,
+            });
+            self.packet_sequence += 1;
+        }
+
+        if !self.pending_acks.is_empty() {
+            packets.push(self.ack_packet());
+        }
+
+        self.serialize_packets(packets)
+    }
+
+    // Packets of a send channel within the available bytes and the channel rate.
+    // Unreliable messages that don't fit are dropped if drop_over_budget is set, otherwise they stay queued.
+    // Messages over the channel rate always stay queued.
+    fn channel_packets_to_send(&mut self, order: ChannelOrder, available_bytes: &mut u64, drop_over_budget: bool) -> Vec<Packet> {
+        let Some(bandwidth) = self.channel_bandwidths.get_mut(&order.channel_id()) else {
+            return self.channel_packets_within(order, available_bytes, drop_over_budget);
+        };
+
+        let mut budget = bandwidth.available_bytes(self.current_time).min(*available_bytes);
+        let drop_over_budget = drop_over_budget && budget == *available_bytes;
+        let budget_before = budget;
+        let packets = self.channel_packets_within(order, &mut budget, drop_over_budget);
+
+        let used = budget_before - budget;
+        *available_bytes -= used;
+        self.channel_bandwidths.get_mut(&order.channel_id()).unwrap().consume(used);
+        packets
+    }
+
+    fn channel_packets_within(&mut self, order: ChannelOrder, available_bytes: &mut u64, drop_over_budget: bool) -> Vec<Packet> {
+        let available_before = *available_bytes;
+        let packets = self.channel_packets_within_budget(order, available_bytes, drop_over_budget);
+        let stats = self.channel_stats.entry(order.channel_id()).or_default();
+        stats.bytes_sent += available_before - *available_bytes;
+        packets
+    }
+
+    fn channel_packets_within_budget(&mut self, order: ChannelOrder, available_bytes: &mut u64, drop_over_budget: bool) -> Vec<Packet> {
+        match order {
+            ChannelOrder::Reliable(channel_id) => {
+                let channel = self.send_reliable_channels.get_mut(&channel_id).unwrap();
+                let packets = channel.get_packets_to_send(&mut self.packet_sequence, available_bytes, self.current_time);
+                let resends = channel.take_resends();
+                self.channel_stats.entry(channel_id).or_default().resends += resends;
+                packets
+            }
+            ChannelOrder::Unreliable(channel_id) => {
+                let channel = self.send_unreliable_channels.get_mut(&channel_id).unwrap();
+                let packets = if drop_over_budget {
+                    channel.get_packets_to_send(&mut self.packet_sequence, available_bytes, self.current_time)
+                } else {
+                    channel.get_packets_within_budget(&mut self.packet_sequence, available_bytes, self.current_time)
+                };
+                let over_budget_drops = channel.take_over_budget_drops();
+                let expired_drops = channel.take_expired_drops();
+                if over_budget_drops > 0 {
+                    self.dropped_messages(channel_id, DropReason::OverBudget, over_budget_drops);
+                }
+                if expired_drops > 0 {
+                    *self.expired_messages.entry(channel_id).or_default() += expired_drops;
+                    self.dropped_messages(channel_id, DropReason::Expired, expired_drops);
+                }
+                packets
             }
         }
     }
 
-    /// Returns a list of packets to be sent to the server.
+    /// Returns true when acks are pending for longer than the `ack_flush_fraction` of the interval between
+    /// the packets of the remote, they should be sent without waiting for the next `send_packets`.
+    pub fn ack_flush_due(&self) -> bool {
+        let (Some(fraction), Some(unsent_acks_since)) = (self.ack_flush_fraction, self.unsent_acks_since) else {
+            return false;
+        };
+        let Some(interval) = self.packet_arrival.arrival_interval() else {
+            return false;
+        };
+
+        self.current_time.saturating_sub(unsent_acks_since) >= interval.mul_f64(fraction)
+    }
+
+    /// Returns an ack-only packet if the acks are due, see [`RenetClient::ack_flush_due`].
     /// <p style="background:rgba(77,220,255,0.16);padding:0.5em;">
     /// <strong>Note:</strong> This should only be called by the transport layer.
     /// </p>
-    pub fn get_packets_to_send(&mut self) -> Vec<Payload> {
-        let mut packets: Vec<Packet> = vec![];
-        if self.is_disconnected() {
+    pub fn get_ack_packets_to_send(&mut self) -> Vec<Payload> {
+        if self.is_disconnected() || !self.ack_flush_due() {
             return vec![];
         }
 
-        let mut available_bytes = self.available_bytes_per_tick;
-        for order in self.channel_send_order.iter() {
-            match order {
-                ChannelOrder::Reliable(channel_id) => {
-                    let channel = self.send_reliable_channels.get_mut(channel_id).unwrap();
-                    packets.append(&mut channel.get_packets_to_send(&mut self.packet_sequence, &mut available_bytes, self.current_time));
-                }
-                ChannelOrder::Unreliable(channel_id) => {
-                    let channel = self.send_unreliable_channels.get_mut(channel_id).unwrap();
-                    packets.append(&mut channel.get_packets_to_send(&mut self.packet_sequence, &mut available_bytes));
-                }
-            }
-        }
+        let packets = vec![self.ack_packet()];
+        self.serialize_packets(packets)
+    }
 
-        if !self.pending_acks.is_empty() {
-            let ack_packet = Packet::Ack {
-                sequence: self.packet_sequence,
-                ack_ranges: self.pending_acks.clone(),
-            };
-            self.packet_sequence += 1;
-            packets.push(ack_packet);
+    fn ack_packet(&mut self) -> Packet {
+        if let Some(unsent_acks_since) = self.unsent_acks_since.take() {
+            let ack_delay = self.current_time.saturating_sub(unsent_acks_since).as_secs_f64();
+            self.ack_delay = self.ack_delay * 0.875 + ack_delay * 0.125;
         }
 
+        let ack_packet = Packet::Ack {
+            sequence: self.packet_sequence,
+            ack_ranges: self.pending_acks.clone(),
+        };
+        self.packet_sequence += 1;
+        ack_packet
+    }
+
+    // Records the packets as sent and serializes them
+    fn serialize_packets(&mut self, packets: Vec<Packet>) -> Vec<Payload> {
         let sent_at = self.current_time;
         for packet in packets.iter() {
             match packet {
@@ -535,6 +2556,7 @@ impl RenetClient {
                         *sequence,
                         PacketSent {
                             sent_at,
+                            fast_retransmitted: false,
                             info: PacketSentInfo::ReliableMessages {
                                 channel_id: *channel_id,
                                 message_ids: messages.iter().map(|(id, _)| *id).collect(),
@@ -542,6 +2564,23 @@ impl RenetClient {
                         },
                     );
                 }
+                Packet::CancelReliable {
+                    sequence,
+                    channel_id,
+                    message_id,
+                } => {
+                    self.sent_packets.insert(
+                        *sequence,
+                        PacketSent {
+                            sent_at,
+                            fast_retransmitted: false,
+                            info: PacketSentInfo::ReliableMessages {
+                                channel_id: *channel_id,
+                                message_ids: vec![*message_id],
+                            },
+                        },
+                    );
+                }
                 Packet::ReliableSlice {
                     sequence,
                     channel_id,
@@ -551,6 +2590,7 @@ impl RenetClient {
                         *sequence,
                         PacketSent {
                             sent_at,
+                            fast_retransmitted: false,
                             info: PacketSentInfo::ReliableSliceMessage {
                                 channel_id: *channel_id,
                                 message_id: slice.message_id,
@@ -559,24 +2599,56 @@ impl RenetClient {
                         },
                     );
                 }
-                Packet::SmallUnreliable { sequence, .. } => {
+                Packet::SmallUnreliable { sequence, channel_id, .. } | Packet::UnreliableSlice { sequence, channel_id, .. } => {
+                    let delivery_feedback = self
+                        .send_unreliable_channels
+                        .get(channel_id)
+                        .is_some_and(|channel| channel.has_delivery_feedback());
+                    let info = match delivery_feedback {
+                        true => PacketSentInfo::UnreliableMessages { channel_id: *channel_id },
+                        false => PacketSentInfo::None,
+                    };
                     self.sent_packets.insert(
                         *sequence,
                         PacketSent {
                             sent_at,
-                            info: PacketSentInfo::None,
+                            info,
+                            fast_retransmitted: false,
                         },
                     );
                 }
-                Packet::UnreliableSlice { sequence, .. } => {
+                Packet::Ping { sequence, .. } | Packet::Pong { sequence, .. } | Packet::QuotaExceeded { sequence, .. } => {
                     self.sent_packets.insert(
                         *sequence,
                         PacketSent {
                             sent_at,
+                            fast_retransmitted: false,
                             info: PacketSentInfo::None,
                         },
                     );
                 }
+                Packet::ChannelConfig { sequence, .. } => {
+                    self.sent_packets.insert(
+                        *sequence,
+                        PacketSent {
+                            sent_at,
+                            fast_retransmitted: false,
+                            info: PacketSentInfo::ChannelConfig,
+                        },
+                    );
+                }
+                Packet::TransferSlice { sequence, slice } => {
+                    self.sent_packets.insert(
+                        *sequence,
+                        PacketSent {
+                            sent_at,
+                            fast_retransmitted: false,
+                            info: PacketSentInfo::TransferSlice {
+                                slice_index: slice.slice_index,
+                            },
+                        },
+                    );
+                }
                 Packet::Ack { sequence, ack_ranges } => {
                     let last_range = ack_ranges.last().unwrap();
                     let largest_acked_packet = last_range.end - 1;
@@ -584,6 +2656,7 @@ impl RenetClient {
                         *sequence,
                         PacketSent {
                             sent_at,
+                            fast_retransmitted: false,
                             info: PacketSentInfo::Ack { largest_acked_packet },
                         },
                     );
@@ -594,6 +2667,7 @@ impl RenetClient {
         let mut buffer = [0u8; 1400];
         let mut serialized_packets = Vec::with_capacity(packets.len());
         let mut bytes_sent: u64 = 0;
+        let mut padding_bytes: u64 = 0;
         for packet in packets {
             let mut oct = OctetsMut::with_slice(&mut buffer);
             let len = match packet.to_bytes(&mut oct) {
@@ -604,11 +2678,17 @@ impl RenetClient {
                 Ok(len) => len,
             };
 
-            bytes_sent += len as u64;
-            serialized_packets.push(buffer[..len].to_vec());
+            // Padding is placed after the packet, the receiver stops reading before it
+            let padded_len = self.packet_padding.padded_len(len);
+            buffer[len..padded_len].fill(0);
+            padding_bytes += (padded_len - len) as u64;
+
+            bytes_sent += padded_len as u64;
+            serialized_packets.push(buffer[..padded_len].to_vec());
         }
 
         self.stats.sent_packets(serialized_packets.len() as u64, bytes_sent);
+        self.stats.sent_padding(padding_bytes);
 
         serialized_packets
     }
@@ -655,8 +2735,8 @@ impl RenetClient {
         // Add new range with only this sequence at the end
         self.pending_acks.push(sequence..sequence + 1);
 
-        // Limit to 64 pending ranges
-        if self.pending_acks.len() > 64 {
+        // Limit the number of pending ranges
+        if self.pending_acks.len() > MAX_PENDING_ACK_RANGES {
             self.pending_acks.remove(0);
         }
     }
@@ -694,9 +2774,113 @@ impl RenetClient {
     }
 }
 
+// Packet sequences and message ids are estimated to fit in 4 bytes varints
+const ESTIMATED_SEQUENCE: u64 = (1 << 30) - 1;
+
+// Returns how a message would be sent in the channel using the same size math as the packet assembly,
+// or None if the message would be rejected by the channel.
+#[cfg(feature = "log_dropped_packets")]
+fn packet_channel_id(packet: &[u8]) -> Option<u8> {
+    let packet = Bytes::copy_from_slice(packet);
+    let mut octets = octets::Octets::with_slice(&packet);
+    Packet::from_shared_bytes(&packet, &mut octets).ok()?.channel_id()
+}
+
+// Unreliable channels sending a sequence number before each message
+fn has_sequence_header(channel: &ChannelConfig) -> bool {
+    match channel.send_type {
+        SendType::UnreliableSequenced => true,
+        SendType::Unreliable => channel.deduplicate.is_some(),
+        _ => false,
+    }
+}
+
+pub(crate) fn transmission_plan(config: &ConnectionConfig, channel: &ChannelConfig, message_len: usize) -> Option<TransmissionPlan> {
+    // Messages of sequenced and deduplicated channels are sent with their sequence number
+    let wire_len = match has_sequence_header(channel) {
+        true => message_len + SEQUENCE_HEADER_BYTES,
+        false => message_len,
+    };
+    if wire_len > channel.max_memory_usage_bytes {
+        return None;
+    }
+
+    let reliable = !matches!(channel.send_type, SendType::Unreliable | SendType::UnreliableSequenced);
+    if !reliable && wire_len as u64 > config.available_bytes_per_tick {
+        // Unreliable messages without bytes available are dropped
+        return None;
+    }
+
+    let mut packet_lens: Vec<(usize, usize)> = vec![];
+    if is_sliced(wire_len) {
+        let num_slices = num_slices(wire_len);
+        for slice_index in 0..num_slices {
+            let payload_len = if slice_index == num_slices - 1 { wire_len - slice_index * SLICE_SIZE } else { SLICE_SIZE };
+            let header = slice_packet_header_size(ESTIMATED_SEQUENCE, ESTIMATED_SEQUENCE, slice_index, num_slices, payload_len);
+            packet_lens.push((header + payload_len, payload_len));
+        }
+    } else {
+        let message_id = reliable.then_some(ESTIMATED_SEQUENCE);
+        let len = small_packet_header_size(ESTIMATED_SEQUENCE) + small_message_size(wire_len, message_id);
+        packet_lens.push((len, wire_len));
+    }
+
+    let overhead_bytes = packet_lens
+        .iter()
+        .map(|&(len, payload_len)| config.packet_padding.padded_len(len) - payload_len)
+        .sum::<usize>()
+        + (wire_len - message_len);
+
+    Some(TransmissionPlan {
+        packets: packet_lens.len() as u32,
+        overhead_bytes: overhead_bytes as u32,
+        send_type: channel.send_type.clone(),
+    })
+}
+
+// Returns the duration limited to the maximum, the discarded time is added to the clamped time
+pub(crate) fn clamp_update_duration(duration: Duration, max_update_duration: Option<Duration>, clamped_time: &mut Duration) -> Duration {
+    match max_update_duration {
+        Some(max) if duration > max => {
+            *clamped_time = clamped_time.saturating_add(duration - max);
+            max
+        }
+        _ => duration,
+    }
+}
+
+fn channels_descriptor(channels_config: &[ChannelConfig]) -> Vec<ChannelDescriptor> {
+    let mut descriptors: Vec<ChannelDescriptor> = channels_config.iter().map(ChannelDescriptor::from).collect();
+    descriptors.sort_by_key(|descriptor| descriptor.channel_id);
+    descriptors
+}
+
+// Returns the lowest channel id that is different or missing between the descriptors.
+// Not cryptographically secure, it only needs to differ between connections
+fn random_u64() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+fn first_mismatched_channel(local: &[ChannelDescriptor], remote: &[ChannelDescriptor]) -> Option<u8> {
+    let mut remote: Vec<ChannelDescriptor> = remote.to_vec();
+    remote.sort_by_key(|descriptor| descriptor.channel_id);
+
+    let mut local = local.iter();
+    let mut remote = remote.iter();
+    loop {
+        match (local.next(), remote.next()) {
+            (None, None) => return None,
+            (Some(descriptor), None) | (None, Some(descriptor)) => return Some(descriptor.channel_id),
+            (Some(local), Some(remote)) if local != remote => return Some(local.channel_id.min(remote.channel_id)),
+            _ => {}
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::packet::SerializationError;
 
     #[test]
     fn pending_acks() {
@@ -726,6 +2910,72 @@ mod tests {
         assert_eq!(connection.pending_acks, vec![0..8]);
     }
 
+    #[test]
+    fn check_channels() {
+        let mut config = ConnectionConfig::default();
+        assert_eq!(config.check_channels(DefaultChannel::ALL), Ok(()));
+        assert_eq!(config.check_channels([3]), Err(ChannelNotConfigured { channel_id: 3 }));
+
+        config
+            .client_channels_config
+            .retain(|channel| channel.channel_id != DefaultChannel::ReliableUnordered.into());
+        assert_eq!(
+            config.check_channels(DefaultChannel::ALL),
+            Err(ChannelNotConfigured { channel_id: 1 })
+        );
+        assert_eq!(
+            config.check_channels([DefaultChannel::Unreliable, DefaultChannel::ReliableOrdered]),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn validate_config() {
+        let config = ConnectionConfig::default();
+        assert_eq!(config.validate(), Ok(()));
+
+        let mut duplicate = config.clone();
+        duplicate.client_channels_config.push(duplicate.client_channels_config[0].clone());
+        assert_eq!(duplicate.validate(), Err(ChannelsConfigError::DuplicateChannel { channel_id: 0 }));
+
+        let mut grouped = config.clone();
+        grouped.server_channels_config[1].send_type = SendType::ReliableSequenced {
+            resend_time: Duration::from_millis(300),
+        };
+        grouped.server_channels_config[1].group = Some(0);
+        assert_eq!(
+            grouped.validate(),
+            Err(ChannelsConfigError::GroupedSequencedChannel { channel_id: 1 })
+        );
+
+        let overflow = OverflowRoute {
+            channel_id: DefaultChannel::ReliableUnordered.into(),
+            threshold_bytes: 1024,
+        };
+        let mut ungrouped_overflow = config.clone();
+        ungrouped_overflow.server_channels_config[1].overflow = Some(overflow);
+        assert_eq!(
+            ungrouped_overflow.validate(),
+            Err(ChannelsConfigError::InvalidOverflowRoute { channel_id: 1 })
+        );
+
+        let mut unreliable_overflow = config.clone();
+        unreliable_overflow.server_channels_config[0].overflow = Some(overflow);
+        unreliable_overflow.server_channels_config[0].group = Some(0);
+        unreliable_overflow.server_channels_config[2].group = Some(0);
+        assert_eq!(
+            unreliable_overflow.validate(),
+            Err(ChannelsConfigError::InvalidOverflowRoute { channel_id: 0 })
+        );
+
+        let mut valid_overflow = config;
+        valid_overflow.server_channels_config[1].overflow = Some(overflow);
+        valid_overflow.server_channels_config[1].group = Some(0);
+        valid_overflow.server_channels_config[2].group = Some(0);
+        assert_eq!(valid_overflow.validate(), Ok(()));
+        RenetClient::new(valid_overflow);
+    }
+
     #[test]
     fn ack_pending_acks() {
         let mut connection = RenetClient::new(ConnectionConfig::default());
@@ -752,19 +3002,700 @@ mod tests {
         assert_eq!(connection.pending_acks, vec![]);
     }
 
+    #[test]
+    fn pending_acks_max_ranges() {
+        let mut connection = RenetClient::new(ConnectionConfig::default());
+        // Only even sequences, every one creates a new range
+        for i in 0..(MAX_PENDING_ACK_RANGES as u64 + 10) {
+            connection.add_pending_ack(i * 2);
+        }
+
+        assert_eq!(connection.pending_acks.len(), MAX_PENDING_ACK_RANGES);
+        assert_eq!(connection.pending_acks[0], 20..21);
+    }
+
+    #[test]
+    fn drain_acked_messages() {
+        let mut server = RenetClient::new_from_server(ConnectionConfig::default());
+        let mut client = RenetClient::new(ConnectionConfig::default());
+
+        let first = server
+            .send_message(DefaultChannel::ReliableOrdered, Bytes::from(vec![1; 10]))
+            .unwrap();
+        let first_packets = server.get_packets_to_send();
+        server.update(Duration::from_millis(1));
+
+        let second = server
+            .send_message(DefaultChannel::ReliableOrdered, Bytes::from(vec![2; 10]))
+            .unwrap();
+        let second_packets = server.get_packets_to_send();
+        assert!(server.send_message(DefaultChannel::Unreliable, Bytes::from(vec![3; 10])).is_none());
+
+        // Second message arrives first
+        for packet in second_packets.iter().chain(first_packets.iter()) {
+            client.process_packet(packet);
+        }
+        for packet in client.get_packets_to_send() {
+            server.process_packet(&packet);
+        }
+
+        let acked: Vec<MessageId> = server.drain_acked_messages(DefaultChannel::ReliableOrdered).collect();
+        assert_eq!(acked, vec![first, second]);
+        assert_eq!(server.drain_acked_messages(DefaultChannel::ReliableOrdered).count(), 0);
+        assert_eq!(server.drain_acked_messages(DefaultChannel::Unreliable).count(), 0);
+    }
+
+    #[test]
+    fn channel_latency() {
+        let mut server = RenetClient::new_from_server(ConnectionConfig::default());
+        let mut client = RenetClient::new(ConnectionConfig::default());
+        server.set_record_message_latencies(true);
+
+        let message_id = server.send_message(DefaultChannel::ReliableOrdered, vec![1; 10]).unwrap();
+        server.send_message(DefaultChannel::Unreliable, vec![2; 10]);
+        server.update(Duration::from_millis(20));
+        let packets = server.get_packets_to_send();
+        server.update(Duration::from_millis(30));
+
+        for packet in packets {
+            client.process_packet(&packet);
+        }
+        for packet in client.get_packets_to_send() {
+            server.process_packet(&packet);
+        }
+
+        let reliable = server.channel_latency(DefaultChannel::ReliableOrdered);
+        assert_eq!(reliable.queue.samples, 1);
+        assert_eq!(reliable.queue.max, Duration::from_millis(20));
+        assert_eq!(reliable.delivery.average(), Duration::from_millis(50));
+
+        let unreliable = server.channel_latency(DefaultChannel::Unreliable);
+        assert_eq!(unreliable.queue.max, Duration::from_millis(20));
+        assert_eq!(unreliable.delivery.samples, 0);
+
+        let records: Vec<MessageLatencyRecord> = server.drain_message_latency_records(DefaultChannel::ReliableOrdered).collect();
+        assert_eq!(
+            records,
+            vec![MessageLatencyRecord {
+                message_id,
+                queue_latency: Duration::from_millis(20),
+                delivery_latency: Duration::from_millis(50),
+            }]
+        );
+
+        server.reset_statistics();
+        assert_eq!(server.channel_latency(DefaultChannel::ReliableOrdered), ChannelLatency::default());
+    }
+
+    #[test]
+    fn effective_config() {
+        let config = ConnectionConfig {
+            available_bytes_per_tick: 1000,
+            packet_padding: PacketPadding::Multiple(64),
+            unacked_message_timeout: Some(Duration::from_secs(5)),
+            ..Default::default()
+        };
+        let mut server = RenetClient::new_from_server(config.clone());
+        let mut client = RenetClient::new(config);
+
+        let effective = client.effective_config();
+        assert_eq!(effective.max_packet_size, MAX_PACKET_SIZE);
+        assert_eq!(effective.available_bytes_per_tick, 1000);
+        assert_eq!(effective.packet_padding, PacketPadding::Multiple(64));
+        assert_eq!(effective.unacked_message_timeout, Some(Duration::from_secs(5)));
+        assert_eq!(effective.send_channels.len(), 3);
+        assert!(matches!(
+            effective.send_channels[1].send_type,
+            SendType::ReliableUnordered { resend_time } if resend_time == Duration::from_millis(300)
+        ));
+        assert!(!effective.channels_validated);
+        assert_eq!(effective.timeout, None);
+
+        for packet in server.get_packets_to_send() {
+            client.process_packet(&packet);
+        }
+        assert!(client.effective_config().channels_validated);
+    }
+
+    #[test]
+    fn fence() {
+        let mut server = RenetClient::new_from_server(ConnectionConfig::default());
+        let mut client = RenetClient::new(ConnectionConfig::default());
+
+        let empty_fence = server.insert_fence(DefaultChannel::ReliableOrdered);
+        assert!(server.is_fence_reached(empty_fence));
+        let unreliable_fence = server.insert_fence(DefaultChannel::Unreliable);
+        assert!(server.is_fence_reached(unreliable_fence));
+
+        server.send_message(DefaultChannel::ReliableOrdered, Bytes::from(vec![1; 10]));
+        server.send_message(DefaultChannel::ReliableOrdered, Bytes::from(vec![2; 10]));
+        let fence = server.insert_fence(DefaultChannel::ReliableOrdered);
+        let packets = server.get_packets_to_send();
+
+        server.send_message(DefaultChannel::ReliableOrdered, Bytes::from(vec![3; 10]));
+        assert!(!server.is_fence_reached(fence));
+
+        for packet in packets.iter() {
+            client.process_packet(packet);
+        }
+        for packet in client.get_packets_to_send() {
+            server.process_packet(&packet);
+        }
+
+        // Message sent after the fence is still unacked
+        assert!(server.is_fence_reached(fence));
+        let last_fence = server.insert_fence(DefaultChannel::ReliableOrdered);
+        assert!(!server.is_fence_reached(last_fence));
+    }
+
+    #[test]
+    fn transmission_plan_matches_packets() {
+        let config = ConnectionConfig {
+            packet_padding: PacketPadding::Fixed(MAX_PACKET_SIZE),
+            ..Default::default()
+        };
+        let mut connection = RenetClient::new_from_server(config.clone());
+        // Flush the channels configuration packet
+        connection.get_packets_to_send();
+
+        for channel in config.server_channels_config.iter() {
+            for message_len in [10, SLICE_SIZE, SLICE_SIZE * 3 + 1] {
+                let plan = transmission_plan(&config, channel, message_len).unwrap();
+                connection.send_message(channel.channel_id, vec![0; message_len]);
+                let packets = connection.get_packets_to_send();
+                assert_eq!(plan.packets as usize, packets.len());
+                let bytes_sent: usize = packets.iter().map(|p| p.len()).sum();
+                assert_eq!(plan.overhead_bytes as usize, bytes_sent - message_len);
+            }
+        }
+
+        let unreliable = config
+            .server_channels_config
+            .iter()
+            .find(|channel| matches!(channel.send_type, SendType::Unreliable))
+            .unwrap();
+        assert!(transmission_plan(&config, unreliable, unreliable.max_memory_usage_bytes + 1).is_none());
+    }
+
+    #[test]
+    fn channel_paused() {
+        let mut server = RenetClient::new_from_server(ConnectionConfig::default());
+        let mut client = RenetClient::new(ConnectionConfig::default());
+
+        server.set_channel_paused(DefaultChannel::ReliableOrdered, true);
+        assert!(server.is_channel_paused(DefaultChannel::ReliableOrdered));
+        assert!(!server.is_channel_paused(DefaultChannel::Unreliable));
+
+        server.send_message(DefaultChannel::ReliableOrdered, Bytes::from(vec![1; 10]));
+        server.send_message(DefaultChannel::ReliableOrdered, Bytes::from(vec![2; 10]));
+        server.send_message(DefaultChannel::Unreliable, Bytes::from(vec![3; 10]));
+        for packet in server.get_packets_to_send() {
+            client.process_packet(&packet);
+        }
+
+        // Only the unpaused channel was sent
+        assert_eq!(client.receive_message(DefaultChannel::Unreliable).unwrap(), vec![3; 10]);
+        assert!(client.receive_message(DefaultChannel::ReliableOrdered).is_none());
+
+        server.set_channel_paused(DefaultChannel::ReliableOrdered, false);
+        server.send_message(DefaultChannel::ReliableOrdered, Bytes::from(vec![4; 10]));
+        for packet in server.get_packets_to_send() {
+            client.process_packet(&packet);
+        }
+
+        for expected in [1, 2, 4] {
+            assert_eq!(client.receive_message(DefaultChannel::ReliableOrdered).unwrap(), vec![expected; 10]);
+        }
+    }
+
+    #[test]
+    fn process_packet_bytes_shares_memory() {
+        let mut server = RenetClient::new_from_server(ConnectionConfig::default());
+        let mut client = RenetClient::new(ConnectionConfig::default());
+
+        server.send_message(DefaultChannel::ReliableOrdered, Bytes::from(vec![1; 10]));
+        server.send_message(DefaultChannel::Unreliable, Bytes::from(vec![2; 10]));
+        let packets: Vec<Bytes> = server.get_packets_to_send().into_iter().map(Bytes::from).collect();
+        for packet in packets.iter() {
+            client.process_packet_bytes(packet.clone());
+        }
+
+        let in_packets = |message: &Bytes| packets.iter().any(|p| p.as_ptr_range().contains(&message.as_ptr()));
+        let reliable = client.receive_message(DefaultChannel::ReliableOrdered).unwrap();
+        assert_eq!(reliable, vec![1; 10]);
+        assert!(in_packets(&reliable));
+        let unreliable = client.receive_message(DefaultChannel::Unreliable).unwrap();
+        assert_eq!(unreliable, vec![2; 10]);
+        assert!(in_packets(&unreliable));
+    }
+
+    #[test]
+    fn forged_slice_above_max_message_size() {
+        let mut config = ConnectionConfig::default();
+        let channel_id: u8 = DefaultChannel::ReliableOrdered.into();
+        config.client_channels_config[channel_id as usize].max_message_size = Some(64 * 1024);
+        let mut server = RenetClient::new_from_server(config.clone());
+        let mut client = RenetClient::new(config);
+
+        // A message of the maximum size is received
+        client.send_message(channel_id, Bytes::from(vec![1; 64 * 1024]));
+        for _ in 0..10 {
+            for packet in client.get_packets_to_send() {
+                server.process_packet(&packet);
+            }
+            client.update(Duration::from_millis(16));
+        }
+        assert_eq!(server.receive_message(channel_id).unwrap().len(), 64 * 1024);
+
+        // The slice announces a message below the channel memory but above the maximum size
+        let packet = Packet::ReliableSlice {
+            sequence: 1000,
+            channel_id,
+            slice: crate::packet::Slice {
+                message_id: 1,
+                slice_index: 0,
+                num_slices: 1000,
+                payload: vec![0; SLICE_SIZE].into(),
+            },
+        };
+        let mut buffer = [0u8; 1400];
+        let mut octets = OctetsMut::with_slice(&mut buffer);
+        let len = packet.to_bytes(&mut octets).unwrap();
+        server.process_packet(&buffer[..len]);
+
+        assert_eq!(
+            server.disconnect_reason(),
+            Some(DisconnectReason::ReceiveChannelError {
+                channel_id,
+                error: ChannelError::ReceivedMessageAboveLimit
+            })
+        );
+        assert_eq!(server.receive_reliable_channels[&channel_id].memory_usage(), 0);
+    }
+
+    #[test]
+    fn unacked_message_timeout() {
+        let config = ConnectionConfig {
+            unacked_message_timeout: Some(Duration::from_secs(2)),
+            ..Default::default()
+        };
+        let mut server = RenetClient::new_from_server(config.clone());
+        let mut client = RenetClient::new(config);
+
+        server.send_message(DefaultChannel::ReliableOrdered, Bytes::from(vec![1; 10]));
+        for packet in server.get_packets_to_send() {
+            client.process_packet(&packet);
+        }
+        server.update(Duration::from_secs(1));
+        assert_eq!(server.oldest_unacked_age(), Duration::from_secs(1));
+        assert_eq!(server.channel_oldest_unacked_age(DefaultChannel::Unreliable), Duration::ZERO);
+        assert_eq!(server.time_since_last_ack(), Duration::from_secs(1));
+
+        for packet in client.get_packets_to_send() {
+            server.process_packet(&packet);
+        }
+        let network_info = server.network_info();
+        assert_eq!(network_info.time_since_last_ack, Duration::ZERO);
+        assert_eq!(network_info.oldest_unacked_age, Duration::ZERO);
+
+        // The client stops acking while the server keeps sending
+        server.send_message(DefaultChannel::ReliableOrdered, Bytes::from(vec![2; 10]));
+        server.update(Duration::from_secs(2));
+        assert!(!server.is_disconnected());
+        server.update(Duration::from_millis(1));
+        assert_eq!(
+            server.disconnect_reason(),
+            Some(DisconnectReason::UnackedMessageTimeout {
+                channel_id: DefaultChannel::ReliableOrdered.into()
+            })
+        );
+    }
+
+    #[test]
+    fn channel_config_mismatch() {
+        let server_config = ConnectionConfig::default();
+        let mut client_config = ConnectionConfig::default();
+        client_config.client_channels_config[1].send_type = SendType::Unreliable;
+
+        let mut server = RenetClient::new_from_server(server_config);
+        let mut client = RenetClient::new(client_config.clone());
+
+        for packet in client.get_packets_to_send() {
+            server.process_packet(&packet);
+        }
+        assert_eq!(
+            server.disconnect_reason(),
+            Some(DisconnectReason::ChannelConfigMismatch {
+                first_mismatched_channel: client_config.client_channels_config[1].channel_id.raw()
+            })
+        );
+    }
+
+    #[test]
+    fn channel_config_timeout() {
+        let mut server = RenetClient::new_from_server(ConnectionConfig::default());
+        let mut client = RenetClient::new(ConnectionConfig::default());
+
+        // The timeout starts with the first packet received
+        server.update(Duration::from_secs(10));
+        assert!(!server.is_disconnected());
+
+        // Packets from a remote that never sends its channels configuration
+        let mut buffer = [0u8; MAX_PACKET_SIZE];
+        let ping = Packet::Ping { sequence: 0, ping_id: 0 };
+        let len = ping.to_bytes(&mut octets::OctetsMut::with_slice(&mut buffer)).unwrap();
+        server.process_packet(&buffer[..len]);
+        server.update(Duration::from_secs(4));
+        assert!(!server.is_disconnected());
+        server.update(Duration::from_secs(2));
+        assert_eq!(server.disconnect_reason(), Some(DisconnectReason::ChannelConfigTimeout));
+
+        // A remote sending it is never disconnected
+        let mut server = RenetClient::new_from_server(ConnectionConfig::default());
+        for packet in client.get_packets_to_send() {
+            server.process_packet(&packet);
+        }
+        server.update(Duration::from_secs(10));
+        assert!(!server.is_disconnected());
+    }
+
+    #[test]
+    fn deduplicate_mismatch() {
+        let server_config = ConnectionConfig::default();
+        let mut client_config = ConnectionConfig::default();
+        let unreliable = client_config
+            .client_channels_config
+            .iter_mut()
+            .find(|config| matches!(config.send_type, SendType::Unreliable))
+            .unwrap();
+        unreliable.deduplicate = Some(64);
+        let channel_id = unreliable.channel_id.raw();
+
+        let mut server = RenetClient::new_from_server(server_config);
+        let mut client = RenetClient::new(client_config);
+        for packet in client.get_packets_to_send() {
+            server.process_packet(&packet);
+        }
+        assert_eq!(
+            server.disconnect_reason(),
+            Some(DisconnectReason::ChannelConfigMismatch {
+                first_mismatched_channel: channel_id
+            })
+        );
+
+        // Ignored by the reliable channels, it's not a mismatch
+        let mut client_config = ConnectionConfig::default();
+        client_config.client_channels_config[1].deduplicate = Some(64);
+        let mut server = RenetClient::new_from_server(ConnectionConfig::default());
+        let mut client = RenetClient::new(client_config);
+        for packet in client.get_packets_to_send() {
+            server.process_packet(&packet);
+        }
+        assert!(!server.is_disconnected());
+    }
+
+    #[test]
+    fn protocol_version_mismatch() {
+        let config = ConnectionConfig {
+            allow_channel_config_mismatch: true,
+            ..Default::default()
+        };
+        let mut server = RenetClient::new_from_server(config);
+
+        let mut buffer = [0u8; MAX_PACKET_SIZE];
+        let packet = Packet::ChannelConfig {
+            sequence: 0,
+            protocol_version: PROTOCOL_VERSION + 1,
+            channels: vec![],
+        };
+        let len = packet.to_bytes(&mut octets::OctetsMut::with_slice(&mut buffer)).unwrap();
+        server.process_packet(&buffer[..len]);
+        assert_eq!(
+            server.disconnect_reason(),
+            Some(DisconnectReason::ProtocolVersionMismatch {
+                remote_version: PROTOCOL_VERSION + 1
+            })
+        );
+
+        // Packets of the previous version fail to deserialize
+        let mut server = RenetClient::new_from_server(ConnectionConfig::default());
+        server.process_packet(&[5, 0, 0]);
+        assert_eq!(
+            server.disconnect_reason(),
+            Some(DisconnectReason::PacketDeserialization(
+                SerializationError::UnsupportedProtocolVersion
+            ))
+        );
+    }
+
+    #[test]
+    fn channel_config_allow_mismatch() {
+        let server_config = ConnectionConfig {
+            allow_channel_config_mismatch: true,
+            ..Default::default()
+        };
+        let mut client_config = ConnectionConfig::default();
+        client_config.client_channels_config[0].max_memory_usage_bytes = 1024;
+
+        let mut server = RenetClient::new_from_server(server_config);
+        let mut client = RenetClient::new(client_config);
+
+        client.send_message(DefaultChannel::ReliableOrdered, Bytes::from("test"));
+        for packet in client.get_packets_to_send() {
+            server.process_packet(&packet);
+        }
+        assert!(!server.is_disconnected());
+        assert_eq!(server.receive_message(DefaultChannel::ReliableOrdered).unwrap(), "test");
+    }
+
+    #[test]
+    fn receive_after_channel_config() {
+        let mut server = RenetClient::new_from_server(ConnectionConfig::default());
+        let mut client = RenetClient::new(ConnectionConfig::default());
+
+        client.send_message(DefaultChannel::ReliableOrdered, Bytes::from("test"));
+        let mut packets = client.get_packets_to_send();
+        let channel_config_packet = packets.remove(0);
+
+        // Messages are held until the channels configuration is received
+        for packet in packets {
+            server.process_packet(&packet);
+        }
+        assert!(server.receive_message(DefaultChannel::ReliableOrdered).is_none());
+
+        server.process_packet(&channel_config_packet);
+        assert_eq!(server.receive_message(DefaultChannel::ReliableOrdered).unwrap(), "test");
+
+        // Resent until acked
+        client.update(CHANNEL_CONFIG_RESEND_TIME);
+        let packets = client.get_packets_to_send();
+        assert_eq!(packets.len(), 1);
+        let mut octets = octets::Octets::with_slice(&packets[0]);
+        assert!(matches!(Packet::from_bytes(&mut octets), Ok(Packet::ChannelConfig { .. })));
+
+        for packet in server.get_packets_to_send() {
+            client.process_packet(&packet);
+        }
+        assert!(client.channel_config_acked);
+    }
+
+    #[test]
+    fn warm_up_holds_messages() {
+        let config = ConnectionConfig {
+            warm_up_rtt_samples: 2,
+            hold_messages_until_warmed_up: true,
+            ..Default::default()
+        };
+        let mut server = RenetClient::new_from_server(config.clone());
+        let mut client = RenetClient::new(config);
+        server.set_connected();
+        client.set_connected();
+
+        client.send_message(DefaultChannel::ReliableOrdered, Bytes::from("test"));
+        let mut ticks = 0;
+        while !client.is_warmed_up() {
+            assert!(ticks < 10, "connection did not warm up");
+            assert!(server.receive_message(DefaultChannel::ReliableOrdered).is_none());
+            client.update(Duration::from_millis(16));
+            server.update(Duration::from_millis(16));
+            for packet in client.get_packets_to_send() {
+                server.process_packet(&packet);
+            }
+            for packet in server.get_packets_to_send() {
+                client.process_packet(&packet);
+            }
+            ticks += 1;
+        }
+        assert!(client.rtt_samples >= 2);
+
+        for packet in client.get_packets_to_send() {
+            server.process_packet(&packet);
+        }
+        assert_eq!(server.receive_message(DefaultChannel::ReliableOrdered).unwrap(), "test");
+    }
+
+    #[test]
+    fn health_check() {
+        let mut server = RenetClient::new_from_server(ConnectionConfig::default());
+        let mut client = RenetClient::new(ConnectionConfig::default());
+        server.set_connected();
+        client.set_connected();
+
+        // Silent in correct usage
+        for _ in 0..USAGE_CHECK_CALLS * 2 {
+            client.send_message(DefaultChannel::ReliableOrdered, Bytes::from("test"));
+            client.update(Duration::from_millis(16));
+            server.update(Duration::from_millis(16));
+            for packet in client.get_packets_to_send() {
+                server.process_packet(&packet);
+            }
+            for packet in server.get_packets_to_send() {
+                client.process_packet(&packet);
+            }
+            while server.receive_message(DefaultChannel::ReliableOrdered).is_some() {}
+        }
+        assert!(client.health_check().is_empty());
+        assert!(server.health_check().is_empty());
+
+        // Messages are received but never read
+        for _ in 0..30 {
+            client.send_message(DefaultChannel::ReliableOrdered, vec![0; 100_000]);
+        }
+        for _ in 0..USAGE_CHECK_CALLS + 100 {
+            client.update(Duration::from_millis(16));
+            server.update(Duration::from_millis(16));
+            for packet in client.get_packets_to_send() {
+                server.process_packet(&packet);
+            }
+            for packet in server.get_packets_to_send() {
+                client.process_packet(&packet);
+            }
+        }
+        let channel_id = DefaultChannel::ReliableOrdered.into();
+        assert_eq!(server.health_check(), vec![UsageWarning::ReceiveBacklog { channel_id }]);
+
+        // Update without send, then send without update
+        for _ in 0..USAGE_CHECK_CALLS {
+            client.update(Duration::from_millis(16));
+        }
+        assert_eq!(client.health_check(), vec![UsageWarning::UpdateWithoutSend]);
+        for _ in 0..USAGE_CHECK_CALLS {
+            client.get_packets_to_send();
+        }
+        assert_eq!(
+            client.health_check(),
+            vec![UsageWarning::UpdateWithoutSend, UsageWarning::SendWithoutUpdate]
+        );
+    }
+
+    #[test]
+    fn packet_padding() {
+        assert_eq!(PacketPadding::Multiple(64).padded_len(10), 64);
+        assert_eq!(PacketPadding::Multiple(64).padded_len(64), 64);
+        assert_eq!(PacketPadding::Multiple(64).padded_len(65), 128);
+        assert_eq!(PacketPadding::Fixed(100).padded_len(150), 150);
+        assert_eq!(PacketPadding::Fixed(5000).padded_len(10), MAX_PACKET_SIZE);
+
+        let config = ConnectionConfig {
+            packet_padding: PacketPadding::Multiple(64),
+            ..Default::default()
+        };
+        let mut server = RenetClient::new_from_server(ConnectionConfig::default());
+        let mut client = RenetClient::new(config);
+
+        client.send_message(DefaultChannel::ReliableOrdered, Bytes::from("test"));
+        client.send_message(DefaultChannel::Unreliable, Bytes::from(vec![3; 2000]));
+        let packets = client.get_packets_to_send();
+        let mut total_bytes = 0;
+        for packet in packets.iter() {
+            assert!(packet.len() % 64 == 0 || packet.len() == MAX_PACKET_SIZE);
+            total_bytes += packet.len() as u64;
+            server.process_packet(packet);
+        }
+
+        assert_eq!(server.receive_message(DefaultChannel::ReliableOrdered).unwrap(), "test");
+        assert_eq!(server.receive_message(DefaultChannel::Unreliable).unwrap().len(), 2000);
+
+        let sent = client.network_info().counters;
+        let received = server.network_info().counters;
+        assert_eq!(sent.bytes_sent, total_bytes);
+        assert!(sent.padding_bytes_sent > 0);
+        assert_eq!(sent.padding_bytes_sent, received.padding_bytes_received);
+    }
+
+    #[test]
+    fn randomized_initial_sequences() {
+        let first = RenetClient::new(ConnectionConfig::default());
+        let second = RenetClient::new(ConnectionConfig::default());
+        assert_ne!(first.packet_sequence, second.packet_sequence);
+        assert!(first.packet_sequence < MAX_INITIAL_PACKET_SEQUENCE);
+
+        let connection = RenetClient::new(ConnectionConfig {
+            randomize_initial_sequences: false,
+            ..Default::default()
+        });
+        assert_eq!(connection.packet_sequence, 0);
+    }
+
+    #[test]
+    fn stalled_peer_no_spurious_resends() {
+        let mut server = RenetClient::new_from_server(ConnectionConfig::default());
+        let mut client = RenetClient::new(ConnectionConfig::default());
+        let channel_id: u8 = DefaultChannel::ReliableOrdered.into();
+        let max_memory = server.channel_available_memory(channel_id);
+        let first_sequence = server.packet_sequence;
+
+        // The client is stalled while the server sends 100 packets
+        let mut stalled_packets = vec![];
+        for _ in 0..100 {
+            server.send_message(channel_id, Bytes::from(vec![1; 10]));
+            stalled_packets.append(&mut server.get_packets_to_send());
+            server.update(Duration::from_millis(1));
+        }
+        // Messages and the channels configuration packet
+        assert_eq!(stalled_packets.len(), 101);
+
+        // Client catches up and acks everything at once
+        for packet in stalled_packets.iter() {
+            client.process_packet(packet);
+        }
+        assert_eq!(client.pending_acks, vec![first_sequence..first_sequence + 101]);
+
+        for packet in client.get_packets_to_send() {
+            server.process_packet(&packet);
+        }
+
+        for _ in 0..100 {
+            assert!(client.receive_message(channel_id).is_some());
+        }
+
+        // Every message was acked, nothing should be resent after the resend time
+        assert_eq!(server.channel_available_memory(channel_id), max_memory);
+        server.update(Duration::from_secs(1));
+        let packets = server.get_packets_to_send();
+        assert_eq!(packets.len(), 1, "only the ack for the client ack packet should be sent");
+    }
+
     #[test]
     fn discard_old_packets() {
         let mut connection = RenetClient::new(ConnectionConfig::default());
         let message: Bytes = vec![5; 5].into();
         connection.send_message(0, message);
 
+        // Message packet and the channels configuration packet
         connection.get_packets_to_send();
-        assert_eq!(connection.sent_packets.len(), 1);
+        assert_eq!(connection.sent_packets.len(), 2);
 
         connection.update(Duration::from_secs(1));
-        assert_eq!(connection.sent_packets.len(), 1);
+        assert_eq!(connection.sent_packets.len(), 2);
 
         connection.update(Duration::from_secs(4));
         assert_eq!(connection.sent_packets.len(), 0);
     }
+
+    #[test]
+    fn dropped_counters() {
+        let config = ConnectionConfig {
+            available_bytes_per_tick: 100,
+            ..Default::default()
+        };
+        let mut client = RenetClient::new(config);
+        let max_memory = client.channel_available_memory(DefaultChannel::Unreliable);
+
+        client.send_message(DefaultChannel::Unreliable, Bytes::from(vec![0; max_memory + 1]));
+        client.send_message(DefaultChannel::Unreliable, Bytes::from(vec![0; 200]));
+        let packets = client.get_packets_to_send();
+        client.record_dropped_packet(DropReason::Socket, &packets[0]);
+
+        let dropped = client.network_info().counters.dropped;
+        assert_eq!(dropped.get(DropReason::ChannelFull), 1);
+        assert_eq!(dropped.get(DropReason::OverBudget), 1);
+        assert_eq!(dropped.get(DropReason::Socket), 1);
+        assert_eq!(dropped.get(DropReason::Encryption), 0);
+        assert_eq!(dropped.total(), 3);
+
+        client.reset_statistics();
+        assert_eq!(client.network_info().counters.dropped.total(), 0);
+        assert_eq!(client.network_info().lifetime_counters.dropped.total(), 3);
+    }
 }
@@ -1,18 +1,102 @@
-use crate::error::{ClientNotFound, DisconnectReason};
+use crate::channel::{
+    ChannelCapacity, ChannelConfig, ChannelId, ChannelLatency, ChannelStats, ChannelWarning, FenceId, LatencyStats, MessageDelivery,
+    MessageId, MessageKindStats, MessageLatencyRecord, TRACE_ID_BYTES,
+};
+use crate::connection_stats::DropReason;
+use crate::error::{ChannelAlreadyExists, ChannelError, ClientNotFound, DisconnectReason, SendError, TransferError};
 use crate::packet::Payload;
-use crate::remote_connection::{ConnectionConfig, NetworkInfo, RenetClient};
-use crate::ClientId;
-use std::collections::{HashMap, VecDeque};
+use crate::remote_connection::{
+    clamp_update_duration, transmission_plan, ConnectionConfig, EffectiveConfig, NetworkInfo, RenetClient, TransmissionPlan,
+};
+use crate::usage::{UpdateClock, UsageWarning, UsageWatchdog, MAX_UNDRAINED_EVENTS};
+use crate::{ClientId, QuotaKind};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::SocketAddr;
 use std::time::Duration;
 
 use bytes::Bytes;
 
 /// Connection and disconnection events in the server.
+/// Every event has the server time it occurred at, see [`RenetServer::update`],
+/// and a sequence number incremented for each event, giving the exact order of events in the same update.
 #[derive(Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Event))]
 pub enum ServerEvent {
-    ClientConnected { client_id: ClientId },
-    ClientDisconnected { client_id: ClientId, reason: DisconnectReason },
+    ClientConnected {
+        client_id: ClientId,
+        occurred_at: Duration,
+        sequence: u64,
+    },
+    ClientDisconnected {
+        client_id: ClientId,
+        reason: DisconnectReason,
+        occurred_at: Duration,
+        sequence: u64,
+    },
+    /// The server was full and the connection request was queued, or its position in the queue changed.
+    /// Queued clients do not have a connection yet, so they are identified by address.
+    ClientQueued {
+        addr: SocketAddr,
+        position: u32,
+        occurred_at: Duration,
+        sequence: u64,
+    },
+    /// The client went over its [`ReceiveQuota`][crate::ReceiveQuota].
+    /// It's disconnected with [`DisconnectReason::QuotaExceeded`] if it stays over it for the grace period.
+    ClientQuotaWarning {
+        client_id: ClientId,
+        kind: QuotaKind,
+        occurred_at: Duration,
+        sequence: u64,
+    },
+}
+
+impl ServerEvent {
+    /// Returns the server time when the event occurred.
+    pub fn occurred_at(&self) -> Duration {
+        match self {
+            ServerEvent::ClientConnected { occurred_at, .. }
+            | ServerEvent::ClientDisconnected { occurred_at, .. }
+            | ServerEvent::ClientQueued { occurred_at, .. }
+            | ServerEvent::ClientQuotaWarning { occurred_at, .. } => *occurred_at,
+        }
+    }
+
+    /// Returns the sequence number of the event, events with a lower sequence occurred before.
+    pub fn sequence(&self) -> u64 {
+        match self {
+            ServerEvent::ClientConnected { sequence, .. }
+            | ServerEvent::ClientDisconnected { sequence, .. }
+            | ServerEvent::ClientQueued { sequence, .. }
+            | ServerEvent::ClientQuotaWarning { sequence, .. } => *sequence,
+        }
+    }
+}
+
+/// What to do with clients whose channel cannot hold a broadcasted message,
+/// see [`RenetServer::broadcast_message_with_policy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BroadcastPolicy {
+    /// Don't send the message to the client.
+    Skip,
+    /// Drop the messages still queued for the client and send only the latest one.
+    /// Only unreliable channels can drop queued messages, in reliable channels the client is skipped.
+    Replace,
+    /// Send the message anyway, like [`RenetServer::broadcast_message`].
+    /// Reliable channels that exceed their memory limit disconnect the client.
+    Force,
+}
+
+/// Result of a broadcast with [`RenetServer::broadcast_message_with_policy`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BroadcastSummary {
+    /// Number of clients the message was queued for, including the replaced ones.
+    /// Clients that refused a forced message, or were disconnected by it, are not counted.
+    pub sent: usize,
+    /// Number of saturated clients that had their queued messages replaced.
+    pub replaced: usize,
+    /// Saturated clients that did not receive the message.
+    pub skipped: Vec<ClientId>,
 }
 
 #[derive(Debug)]
@@ -21,17 +105,40 @@ pub struct RenetServer {
     connections: HashMap<ClientId, RenetClient>,
     connection_config: ConnectionConfig,
     events: VecDeque<ServerEvent>,
+    current_time: Duration,
+    next_event_sequence: u64,
+    channel_warnings: VecDeque<ChannelWarning>,
+    channel_errors: VecDeque<(ClientId, u8, ChannelError)>,
+    // Channels added with add_channel, they are also added to the new connections
+    added_channels: Vec<ChannelConfig>,
+    clamped_time: Duration,
+    usage: UsageWatchdog,
+    update_clock: UpdateClock,
 }
 
 impl RenetServer {
     pub fn new(connection_config: ConnectionConfig) -> Self {
         Self {
+            update_clock: UpdateClock::new(connection_config.zero_duration_fallback),
             connections: HashMap::new(),
             connection_config,
             events: VecDeque::new(),
+            current_time: Duration::ZERO,
+            next_event_sequence: 0,
+            channel_warnings: VecDeque::new(),
+            channel_errors: VecDeque::new(),
+            added_channels: Vec::new(),
+            clamped_time: Duration::ZERO,
+            usage: UsageWatchdog::new(true),
         }
     }
 
+    fn push_event(&mut self, event: impl FnOnce(Duration, u64) -> ServerEvent) {
+        let event = event(self.current_time, self.next_event_sequence);
+        self.next_event_sequence += 1;
+        self.events.push_back(event);
+    }
+
     /// Adds a new connection to the server. If a connection already exits it does nothing.
     /// <p style="background:rgba(77,220,255,0.16);padding:0.5em;">
     /// <strong>Note:</strong> This should only be called by the transport layer.
@@ -42,10 +149,47 @@ impl RenetServer {
         }
 
         let mut connection = RenetClient::new_from_server(self.connection_config.clone());
+        for channel_config in self.added_channels.iter() {
+            // The added channels were checked against the ones of the config
+            let _ = connection.add_channel(channel_config.clone());
+        }
         // Consider newly added connections as connected
         connection.set_connected();
         self.connections.insert(client_id, connection);
-        self.events.push_back(ServerEvent::ClientConnected { client_id })
+        self.push_event(|occurred_at, sequence| ServerEvent::ClientConnected {
+            client_id,
+            occurred_at,
+            sequence,
+        });
+    }
+
+    /// Emits a queued event for an address waiting for a slot in a full server.
+    /// <p style="background:rgba(77,220,255,0.16);padding:0.5em;">
+    /// <strong>Note:</strong> This should only be called by the transport layer.
+    /// </p>
+    pub fn client_queued(&mut self, addr: SocketAddr, position: u32) {
+        self.push_event(|occurred_at, sequence| ServerEvent::ClientQueued {
+            addr,
+            position,
+            occurred_at,
+            sequence,
+        });
+    }
+
+    #[cfg(feature = "transport")]
+    pub(crate) fn connection_config(&self) -> &ConnectionConfig {
+        &self.connection_config
+    }
+
+    // Removes pending events from the client, used to hide internal connections from the application.
+    #[cfg(feature = "transport")]
+    pub(crate) fn remove_events_of(&mut self, client_id: ClientId) {
+        self.events.retain(|event| match event {
+            ServerEvent::ClientConnected { client_id: id, .. }
+            | ServerEvent::ClientDisconnected { client_id: id, .. }
+            | ServerEvent::ClientQuotaWarning { client_id: id, .. } => *id != client_id,
+            ServerEvent::ClientQueued { .. } => true,
+        });
     }
 
     /// Returns a server event if available
@@ -56,12 +200,18 @@ impl RenetServer {
     /// # let mut server = RenetServer::new(ConnectionConfig::default());
     /// while let Some(event) = server.get_event() {
     ///     match event {
-    ///         ServerEvent::ClientConnected { client_id } => {
+    ///         ServerEvent::ClientConnected { client_id, .. } => {
     ///             println!("Client {client_id} connected.")
     ///         }
-    ///         ServerEvent::ClientDisconnected { client_id, reason } => {
+    ///         ServerEvent::ClientDisconnected { client_id, reason, .. } => {
     ///             println!("Client {client_id} disconnected: {reason}");
     ///         }
+    ///         ServerEvent::ClientQueued { addr, position, .. } => {
+    ///             println!("Client {addr} is waiting for a slot, position {position}");
+    ///         }
+    ///         ServerEvent::ClientQuotaWarning { client_id, kind, .. } => {
+    ///             println!("Client {client_id} exceeded its quota of {kind}");
+    ///         }
     ///     }
     /// }
     /// ```
@@ -69,6 +219,40 @@ impl RenetServer {
         self.events.pop_front()
     }
 
+    /// Drains the warnings emitted when a client channel crossed its warning threshold,
+    /// see [`ChannelConfig::warning_threshold`][crate::ChannelConfig::warning_threshold].
+    pub fn drain_channel_warnings(&mut self) -> impl Iterator<Item = ChannelWarning> + '_ {
+        self.channel_warnings.drain(..)
+    }
+
+    /// Drains the errors of the client channels that didn't disconnect them, see [`RenetClient::drain_channel_errors`].
+    pub fn drain_channel_errors(&mut self) -> impl Iterator<Item = (ClientId, u8, ChannelError)> + '_ {
+        self.channel_errors.drain(..)
+    }
+
+    /// Adds a channel to all the connections, current and future, see [`RenetClient::add_channel`].
+    /// The clients must add the same channel.
+    pub fn add_channel(&mut self, config: ChannelConfig) -> Result<(), ChannelAlreadyExists> {
+        let exists = self
+            .connection_config
+            .server_channels_config
+            .iter()
+            .chain(self.connection_config.client_channels_config.iter())
+            .chain(self.added_channels.iter())
+            .any(|channel| channel.channel_id == config.channel_id);
+        if exists {
+            return Err(ChannelAlreadyExists {
+                channel_id: config.channel_id.raw(),
+            });
+        }
+
+        for connection in self.connections.values_mut() {
+            let _ = connection.add_channel(config.clone());
+        }
+        self.added_channels.push(config);
+        Ok(())
+    }
+
     /// Returns whether or not the server has connections
     pub fn has_connections(&self) -> bool {
         !self.connections.is_empty()
@@ -123,6 +307,25 @@ impl RenetServer {
         }
     }
 
+    /// Returns the values in effect for the connection of the client, see [`RenetClient::effective_config`].
+    pub fn effective_config(&self, client_id: ClientId) -> Result<EffectiveConfig, ClientNotFound> {
+        match self.connections.get(&client_id) {
+            Some(connection) => Ok(connection.effective_config()),
+            None => Err(ClientNotFound),
+        }
+    }
+
+    /// Resets the statistics of the client, see [`RenetClient::reset_statistics`].
+    pub fn reset_client_statistics(&mut self, client_id: ClientId) -> Result<(), ClientNotFound> {
+        match self.connections.get_mut(&client_id) {
+            Some(connection) => {
+                connection.reset_statistics();
+                Ok(())
+            }
+            None => Err(ClientNotFound),
+        }
+    }
+
     /// Removes a connection from the server, emits an disconnect server event.
     /// It does nothing if the client does not exits.
     /// <p style="background:rgba(77,220,255,0.16);padding:0.5em;">
@@ -131,7 +334,12 @@ impl RenetServer {
     pub fn remove_connection(&mut self, client_id: ClientId) {
         if let Some(connection) = self.connections.remove(&client_id) {
             let reason = connection.disconnect_reason().unwrap_or(DisconnectReason::Transport);
-            self.events.push_back(ServerEvent::ClientDisconnected { client_id, reason });
+            self.push_event(|occurred_at, sequence| ServerEvent::ClientDisconnected {
+                client_id,
+                reason,
+                occurred_at,
+                sequence,
+            });
         }
     }
 
@@ -142,6 +350,21 @@ impl RenetServer {
         }
     }
 
+    /// Transfers the client to another server: the token to connect to it is sent to the client,
+    /// and the client is disconnected with [`DisconnectReason::Transferred`] once it acked the token.
+    /// The token is sent apart from the channels, it is resent until acked and the messages still queued
+    /// for the client are sent meanwhile. On the client the token is returned by
+    /// [`RenetClient::take_transfer_token`].
+    ///
+    /// The token can have up to [`MAX_TRANSFER_TOKEN_BYTES`][crate::MAX_TRANSFER_TOKEN_BYTES] bytes,
+    /// with the netcode transport it is usually a `ConnectToken` for the other server.
+    pub fn transfer_client<B: Into<Bytes>>(&mut self, client_id: ClientId, token: B) -> Result<(), TransferError> {
+        match self.connections.get_mut(&client_id) {
+            Some(connection) => connection.transfer(token.into()),
+            None => Err(TransferError::ClientNotFound),
+        }
+    }
+
     /// Disconnects all client.
     pub fn disconnect_all(&mut self) {
         for connection in self.connections.values_mut() {
@@ -150,8 +373,10 @@ impl RenetServer {
     }
 
     /// Send a message to all clients over a channel.
-    pub fn broadcast_message<I: Into<u8>, B: Into<Bytes>>(&mut self, channel_id: I, message: B) {
-        let channel_id = channel_id.into();
+    /// The clients share the memory of the message, it's only copied for each of them on channels with
+    /// compression, message kinds, tracing context or a group, where it's prefixed or compressed per client.
+    pub fn broadcast_message<I: Into<ChannelId>, B: Into<Bytes>>(&mut self, channel_id: I, message: B) {
+        let channel_id = channel_id.into().raw();
         let message = message.into();
         for connection in self.connections.values_mut() {
             connection.send_message(channel_id, message.clone());
@@ -159,8 +384,8 @@ impl RenetServer {
     }
 
     /// Send a message to all clients, except the specified one, over a channel.
-    pub fn broadcast_message_except<I: Into<u8>, B: Into<Bytes>>(&mut self, except_id: ClientId, channel_id: I, message: B) {
-        let channel_id = channel_id.into();
+    pub fn broadcast_message_except<I: Into<ChannelId>, B: Into<Bytes>>(&mut self, except_id: ClientId, channel_id: I, message: B) {
+        let channel_id = channel_id.into().raw();
         let message = message.into();
         for (connection_id, connection) in self.connections.iter_mut() {
             if except_id == *connection_id {
@@ -171,40 +396,465 @@ impl RenetServer {
         }
     }
 
+    /// Send a message to the given clients over a channel, the payload is shared by their queues instead of copied.
+    /// Returns the clients that were not found, are not connected, or refused the message, they don't receive it.
+    /// Refused messages are handled as in [`RenetClient::send_message`], a full reliable channel disconnects the client
+    /// unless its queue policy is [`QueuePolicy::RejectSend`]. A client listed more than once receives the message once.
+    ///
+    /// [`QueuePolicy::RejectSend`]: crate::QueuePolicy::RejectSend
+    pub fn send_message_to<I: Into<ChannelId>, B: Into<Bytes>>(
+        &mut self,
+        client_ids: &[ClientId],
+        channel_id: I,
+        message: B,
+    ) -> Vec<ClientId> {
+        let channel_id = channel_id.into().raw();
+        let message = message.into();
+        let mut visited = HashSet::with_capacity(client_ids.len());
+        let mut not_sent = vec![];
+        for client_id in client_ids {
+            if !visited.insert(*client_id) {
+                continue;
+            }
+
+            match self.connections.get_mut(client_id) {
+                Some(connection) if connection.is_connected() => {
+                    let result = connection.try_send_message(channel_id, message.clone());
+                    if result.is_err() {
+                        not_sent.push(*client_id);
+                    }
+                    connection.handle_send_error(result);
+                }
+                _ => not_sent.push(*client_id),
+            }
+        }
+
+        not_sent
+    }
+
+    /// Send a message built for each connected client over a channel, the closure is called once per client
+    /// and the clients it returns `None` for receive nothing. Useful to patch a shared payload for each client
+    /// without serializing it again.
+    pub fn broadcast_message_with<I: Into<ChannelId>>(&mut self, channel_id: I, mut message: impl FnMut(ClientId) -> Option<Bytes>) {
+        let channel_id = channel_id.into().raw();
+        for (client_id, connection) in self.connections.iter_mut() {
+            if !connection.is_connected() {
+                continue;
+            }
+
+            if let Some(message) = message(*client_id) {
+                connection.send_message(channel_id, message);
+            }
+        }
+    }
+
+    /// Send a message to all connected clients over a channel, applying the policy to clients
+    /// whose channel is saturated: it does not have enough memory available for the message.
+    /// Returns which clients were skipped, so they can be caught up later.
+    pub fn broadcast_message_with_policy<I: Into<ChannelId>, B: Into<Bytes>>(
+        &mut self,
+        channel_id: I,
+        message: B,
+        policy: BroadcastPolicy,
+    ) -> BroadcastSummary {
+        let channel_id = channel_id.into().raw();
+        let message = message.into();
+        let mut summary = BroadcastSummary::default();
+        for (client_id, connection) in self.connections.iter_mut() {
+            if !connection.is_connected() {
+                continue;
+            }
+
+            // The channel is checked with the framing of the message, the trace id, kind and group header
+            let result = connection.try_send_message(channel_id, message.clone());
+            match (policy, result) {
+                (_, Ok(_)) => summary.sent += 1,
+                (BroadcastPolicy::Skip, Err(SendError::ChannelFull { .. })) => summary.skipped.push(*client_id),
+                // The full channel can be the overflow channel of the message
+                (BroadcastPolicy::Replace, Err(SendError::ChannelFull { channel_id })) => {
+                    if connection.replace_queued_messages(channel_id, message.clone()) {
+                        summary.sent += 1;
+                        summary.replaced += 1;
+                    } else {
+                        summary.skipped.push(*client_id);
+                    }
+                }
+                (_, result) => {
+                    connection.handle_send_error(result);
+                }
+            }
+        }
+
+        summary
+    }
+
     /// Returns the available memory in bytes of a channel for the given client.
     /// Returns 0 if the client is not found.
-    pub fn channel_available_memory<I: Into<u8>>(&self, client_id: ClientId, channel_id: I) -> usize {
+    pub fn channel_available_memory<I: Into<ChannelId>>(&self, client_id: ClientId, channel_id: I) -> usize {
         match self.connections.get(&client_id) {
             Some(connection) => connection.channel_available_memory(channel_id),
             None => 0,
         }
     }
 
+    /// Returns the bytes of the messages held by the channels of the client, see [`RenetClient::memory_usage`].
+    /// Returns 0 if the client is not found.
+    pub fn memory_usage(&self, client_id: ClientId) -> usize {
+        match self.connections.get(&client_id) {
+            Some(connection) => connection.memory_usage(),
+            None => 0,
+        }
+    }
+
+    /// Returns the bytes of the messages held by the channels of all the clients.
+    pub fn total_memory_usage(&self) -> usize {
+        self.connections.values().map(|connection| connection.memory_usage()).sum()
+    }
+
+    /// Returns how a message with the given size would be sent by the server over a channel:
+    /// the number of packets and the bytes added to it, computed from the connection configuration.
+    /// Returns None if the channel would reject the message, because it exceeds the channel memory or,
+    /// for unreliable channels, the `available_bytes_per_tick`.
+    pub fn message_transmission_plan<I: Into<ChannelId>>(&self, channel_id: I, size_bytes: usize) -> Option<TransmissionPlan> {
+        let channel_id = channel_id.into().raw();
+        let Some(channel) = self
+            .connection_config
+            .server_channels_config
+            .iter()
+            .find(|channel| channel.channel_id.raw() == channel_id)
+        else {
+            panic!("Called 'message_transmission_plan' with invalid channel {channel_id}");
+        };
+
+        transmission_plan(&self.connection_config, channel, size_bytes)
+    }
+
     /// Checks if can send a message with the given size in bytes over a channel for the given client.
     /// Returns false if the client is not found.
-    pub fn can_send_message<I: Into<u8>>(&self, client_id: ClientId, channel_id: I, size_bytes: usize) -> bool {
+    pub fn can_send_message<I: Into<ChannelId>>(&self, client_id: ClientId, channel_id: I, size_bytes: usize) -> bool {
         match self.connections.get(&client_id) {
             Some(connection) => connection.can_send_message(channel_id, size_bytes),
             None => false,
         }
     }
 
+    /// Returns the room left in the channel for the client, see [`RenetClient::channel_capacity`].
+    pub fn channel_capacity<I: Into<ChannelId>>(&self, client_id: ClientId, channel_id: I) -> Result<ChannelCapacity, ClientNotFound> {
+        match self.connections.get(&client_id) {
+            Some(connection) => Ok(connection.channel_capacity(channel_id)),
+            None => Err(ClientNotFound),
+        }
+    }
+
     /// Send a message to a client over a channel.
-    pub fn send_message<I: Into<u8>, B: Into<Bytes>>(&mut self, client_id: ClientId, channel_id: I, message: B) {
+    /// Returns the id of the message when sent over a reliable channel, or an unreliable channel with delivery feedback.
+    pub fn send_message<I: Into<ChannelId>, B: Into<Bytes>>(
+        &mut self,
+        client_id: ClientId,
+        channel_id: I,
+        message: B,
+    ) -> Option<MessageId> {
         match self.connections.get_mut(&client_id) {
             Some(connection) => connection.send_message(channel_id, message),
-            None => log::error!("Tried to send a message to invalid client {:?}", client_id),
+            None => {
+                log::error!("Tried to send a message to invalid client {:?}", client_id);
+                None
+            }
+        }
+    }
+
+    /// Same as [`RenetServer::send_message`], but returns the failures instead of handling them,
+    /// the connection of the client is left as is, see [`RenetClient::try_send_message`].
+    pub fn try_send_message<I: Into<ChannelId>, B: Into<Bytes>>(
+        &mut self,
+        client_id: ClientId,
+        channel_id: I,
+        message: B,
+    ) -> Result<Option<MessageId>, SendError> {
+        match self.connections.get_mut(&client_id) {
+            Some(connection) => connection.try_send_message(channel_id, message),
+            None => Err(SendError::ClientNotFound),
+        }
+    }
+
+    /// Returns the ids of the reliable messages acknowledged by the client since the last update, in ack order.
+    /// Returns an empty iterator if the client is not found.
+    pub fn drain_acked_messages<I: Into<ChannelId>>(&mut self, client_id: ClientId, channel_id: I) -> impl Iterator<Item = MessageId> + '_ {
+        let channel_id = channel_id.into().raw();
+        self.connections
+            .get_mut(&client_id)
+            .into_iter()
+            .flat_map(move |connection| connection.drain_acked_messages(channel_id))
+    }
+
+    /// Returns whether the unreliable messages sent to the client were delivered, see [`RenetClient::drain_message_deliveries`].
+    /// Returns an empty iterator if the client is not found.
+    pub fn drain_message_deliveries<I: Into<ChannelId>>(
+        &mut self,
+        client_id: ClientId,
+        channel_id: I,
+    ) -> impl Iterator<Item = MessageDelivery> + '_ {
+        let channel_id = channel_id.into().raw();
+        self.connections
+            .get_mut(&client_id)
+            .into_iter()
+            .flat_map(move |connection| connection.drain_message_deliveries(channel_id))
+    }
+
+    /// Returns the counters of the channel for the client, see [`RenetClient::channel_stats`].
+    pub fn channel_stats<I: Into<ChannelId>>(&self, client_id: ClientId, channel_id: I) -> Result<ChannelStats, ClientNotFound> {
+        match self.connections.get(&client_id) {
+            Some(connection) => Ok(connection.channel_stats(channel_id)),
+            None => Err(ClientNotFound),
+        }
+    }
+
+    /// Resets the counters of all the channels of the client, see [`RenetClient::reset_channel_stats`].
+    pub fn reset_channel_stats(&mut self, client_id: ClientId) -> Result<(), ClientNotFound> {
+        match self.connections.get_mut(&client_id) {
+            Some(connection) => {
+                connection.reset_channel_stats();
+                Ok(())
+            }
+            None => Err(ClientNotFound),
+        }
+    }
+
+    /// Returns the queue and delivery latencies of the messages sent to the client in the channel,
+    /// see [`RenetClient::channel_latency`].
+    pub fn channel_latency<I: Into<ChannelId>>(&self, client_id: ClientId, channel_id: I) -> Result<ChannelLatency, ClientNotFound> {
+        match self.connections.get(&client_id) {
+            Some(connection) => Ok(connection.channel_latency(channel_id)),
+            None => Err(ClientNotFound),
+        }
+    }
+
+    /// Returns how long the messages received from the client in the ordered channel waited for a missing earlier message,
+    /// see [`RenetClient::head_of_line_blocking`].
+    pub fn head_of_line_blocking<I: Into<ChannelId>>(&self, client_id: ClientId, channel_id: I) -> Result<LatencyStats, ClientNotFound> {
+        match self.connections.get(&client_id) {
+            Some(connection) => Ok(connection.head_of_line_blocking(channel_id)),
+            None => Err(ClientNotFound),
+        }
+    }
+
+    /// Returns the messages and bytes of each kind sent to and received from the client in the channel,
+    /// see [`RenetClient::message_kind_stats`].
+    pub fn message_kind_stats<I: Into<ChannelId>>(
+        &self,
+        client_id: ClientId,
+        channel_id: I,
+    ) -> Result<Vec<(u8, MessageKindStats)>, ClientNotFound> {
+        match self.connections.get(&client_id) {
+            Some(connection) => Ok(connection.message_kind_stats(channel_id)),
+            None => Err(ClientNotFound),
+        }
+    }
+
+    /// Returns the number of messages sent to the client over the overflow channel of the channel,
+    /// see [`RenetClient::overflowed_messages`].
+    pub fn overflowed_messages<I: Into<ChannelId>>(&self, client_id: ClientId, channel_id: I) -> Result<u64, ClientNotFound> {
+        match self.connections.get(&client_id) {
+            Some(connection) => Ok(connection.overflowed_messages(channel_id)),
+            None => Err(ClientNotFound),
+        }
+    }
+
+    /// Returns the number of messages to the client dropped for being queued longer than the
+    /// `message_ttl` of the channel, see [`RenetClient::expired_messages`].
+    pub fn expired_messages<I: Into<ChannelId>>(&self, client_id: ClientId, channel_id: I) -> Result<u64, ClientNotFound> {
+        match self.connections.get(&client_id) {
+            Some(connection) => Ok(connection.expired_messages(channel_id)),
+            None => Err(ClientNotFound),
+        }
+    }
+
+    /// Cancels a sliced message sent to the client that was not fully acked yet, see [`RenetClient::cancel_message`].
+    pub fn cancel_message<I: Into<ChannelId>>(
+        &mut self,
+        client_id: ClientId,
+        channel_id: I,
+        message_id: MessageId,
+    ) -> Result<bool, ClientNotFound> {
+        match self.connections.get_mut(&client_id) {
+            Some(connection) => Ok(connection.cancel_message(channel_id, message_id)),
+            None => Err(ClientNotFound),
+        }
+    }
+
+    /// Returns the progress of the sliced messages sent to the client in the channel, see [`RenetClient::sliced_send_progress`].
+    pub fn sliced_send_progress<I: Into<ChannelId>>(
+        &self,
+        client_id: ClientId,
+        channel_id: I,
+    ) -> Result<Option<(u64, u64)>, ClientNotFound> {
+        match self.connections.get(&client_id) {
+            Some(connection) => Ok(connection.sliced_send_progress(channel_id)),
+            None => Err(ClientNotFound),
+        }
+    }
+
+    /// Returns the progress of the sliced messages received from the client in the channel,
+    /// see [`RenetClient::sliced_receive_progress`].
+    pub fn sliced_receive_progress<I: Into<ChannelId>>(
+        &self,
+        client_id: ClientId,
+        channel_id: I,
+    ) -> Result<Option<(usize, usize)>, ClientNotFound> {
+        match self.connections.get(&client_id) {
+            Some(connection) => Ok(connection.sliced_receive_progress(channel_id)),
+            None => Err(ClientNotFound),
+        }
+    }
+
+    /// Enables or disables the detailed latency records of the reliable messages sent to the client,
+    /// see [`RenetClient::set_record_message_latencies`].
+    pub fn set_record_message_latencies(&mut self, client_id: ClientId, enabled: bool) -> Result<(), ClientNotFound> {
+        match self.connections.get_mut(&client_id) {
+            Some(connection) => {
+                connection.set_record_message_latencies(enabled);
+                Ok(())
+            }
+            None => Err(ClientNotFound),
+        }
+    }
+
+    /// Returns the latencies of the reliable messages acknowledged by the client since the last update,
+    /// see [`RenetClient::drain_message_latency_records`]. Returns an empty iterator if the client is not found.
+    pub fn drain_message_latency_records<I: Into<ChannelId>>(
+        &mut self,
+        client_id: ClientId,
+        channel_id: I,
+    ) -> impl Iterator<Item = MessageLatencyRecord> + '_ {
+        let channel_id = channel_id.into().raw();
+        self.connections
+            .get_mut(&client_id)
+            .into_iter()
+            .flat_map(move |connection| connection.drain_message_latency_records(channel_id))
+    }
+
+    /// Inserts a fence after the messages queued so far in the channel for the given client,
+    /// see [`RenetClient::insert_fence`].
+    pub fn insert_fence<I: Into<ChannelId>>(&mut self, client_id: ClientId, channel_id: I) -> Result<FenceId, ClientNotFound> {
+        match self.connections.get_mut(&client_id) {
+            Some(connection) => Ok(connection.insert_fence(channel_id)),
+            None => Err(ClientNotFound),
+        }
+    }
+
+    /// Returns whether all messages queued before the fence were acked by the client.
+    /// Returns false if the client is not found.
+    pub fn is_fence_reached(&self, client_id: ClientId, fence: FenceId) -> bool {
+        match self.connections.get(&client_id) {
+            Some(connection) => connection.is_fence_reached(fence),
+            None => false,
+        }
+    }
+
+    /// Pauses or resumes sending on a channel for the given client, see [`RenetClient::set_channel_paused`].
+    pub fn set_channel_paused<I: Into<ChannelId>>(
+        &mut self,
+        client_id: ClientId,
+        channel_id: I,
+        paused: bool,
+    ) -> Result<(), ClientNotFound> {
+        match self.connections.get_mut(&client_id) {
+            Some(connection) => {
+                connection.set_channel_paused(channel_id, paused);
+                Ok(())
+            }
+            None => Err(ClientNotFound),
+        }
+    }
+
+    /// Returns whether sending on the channel is paused for the given client.
+    /// Returns false if the client is not found.
+    pub fn is_channel_paused<I: Into<ChannelId>>(&self, client_id: ClientId, channel_id: I) -> bool {
+        match self.connections.get(&client_id) {
+            Some(connection) => connection.is_channel_paused(channel_id),
+            None => false,
         }
     }
 
     /// Receive a message from a client over a channel.
-    pub fn receive_message<I: Into<u8>>(&mut self, client_id: ClientId, channel_id: I) -> Option<Bytes> {
+    /// Send a message with a trace id to a client over a channel with tracing context, see [`RenetClient::send_message_traced`].
+    pub fn send_message_traced<I: Into<ChannelId>, B: Into<Bytes>>(
+        &mut self,
+        client_id: ClientId,
+        channel_id: I,
+        message: B,
+        trace_id: [u8; TRACE_ID_BYTES],
+    ) -> Option<MessageId> {
+        match self.connections.get_mut(&client_id) {
+            Some(connection) => connection.send_message_traced(channel_id, message, trace_id),
+            None => {
+                log::error!("Tried to send a message to invalid client {:?}", client_id);
+                None
+            }
+        }
+    }
+
+    pub fn receive_message<I: Into<ChannelId>>(&mut self, client_id: ClientId, channel_id: I) -> Option<Bytes> {
         if let Some(connection) = self.connections.get_mut(&client_id) {
             return connection.receive_message(channel_id);
         }
         None
     }
 
+    /// Returns an iterator receiving the messages of the client in the channel, see [`RenetClient::receive_messages`].
+    /// The iterator is empty if the client is not found.
+    pub fn receive_messages<I: Into<ChannelId>>(&mut self, client_id: ClientId, channel_id: I) -> impl Iterator<Item = Bytes> + '_ {
+        let channel_id = channel_id.into();
+        let mut connection = self.connections.get_mut(&client_id);
+        std::iter::from_fn(move || connection.as_mut()?.receive_message(channel_id))
+    }
+
+    /// Receive a message from a client with its trace id, see [`RenetClient::receive_message_traced`].
+    pub fn receive_message_traced<I: Into<ChannelId>>(
+        &mut self,
+        client_id: ClientId,
+        channel_id: I,
+    ) -> Option<(Bytes, [u8; TRACE_ID_BYTES])> {
+        if let Some(connection) = self.connections.get_mut(&client_id) {
+            return connection.receive_message_traced(channel_id);
+        }
+        None
+    }
+
+    /// Send a message of the given kind to a client over a channel with message kinds, see [`RenetClient::send_message_kind`].
+    pub fn send_message_kind<I: Into<ChannelId>, B: Into<Bytes>>(
+        &mut self,
+        client_id: ClientId,
+        channel_id: I,
+        kind: u8,
+        message: B,
+    ) -> Option<MessageId> {
+        match self.connections.get_mut(&client_id) {
+            Some(connection) => connection.send_message_kind(channel_id, kind, message),
+            None => {
+                log::error!("Tried to send a message to invalid client {:?}", client_id);
+                None
+            }
+        }
+    }
+
+    /// Receive a message from a client with its kind, see [`RenetClient::receive_message_with_kind`].
+    pub fn receive_message_with_kind<I: Into<ChannelId>>(&mut self, client_id: ClientId, channel_id: I) -> Option<(u8, Bytes)> {
+        if let Some(connection) = self.connections.get_mut(&client_id) {
+            return connection.receive_message_with_kind(channel_id);
+        }
+        None
+    }
+
+    /// Receive the next message from a client over any channel, with the id of its channel,
+    /// see [`RenetClient::receive_any_message`].
+    pub fn receive_any_message(&mut self, client_id: ClientId) -> Option<(u8, Bytes)> {
+        if let Some(connection) = self.connections.get_mut(&client_id) {
+            return connection.receive_any_message();
+        }
+        None
+    }
+
     /// Return ids for all connected clients (iterator)
     pub fn clients_id_iter(&self) -> impl Iterator<Item = ClientId> + '_ {
         self.connections.iter().filter(|(_, c)| c.is_connected()).map(|(id, _)| *id)
@@ -238,12 +888,74 @@ impl RenetServer {
         false
     }
 
+    /// Returns whether the connection with the client is warmed up, see [`RenetClient::is_warmed_up`].
+    /// Returns false if the client is not found.
+    pub fn is_client_warmed_up(&self, client_id: ClientId) -> bool {
+        match self.connections.get(&client_id) {
+            Some(connection) => connection.is_warmed_up(),
+            None => false,
+        }
+    }
+
+    /// Returns the total time discarded from updates longer than the configured `max_update_duration`.
+    pub fn clamped_time(&self) -> Duration {
+        self.clamped_time
+    }
+
     /// Advances the server by the duration.
     /// Should be called every tick
     pub fn update(&mut self, duration: Duration) {
-        for connection in self.connections.values_mut() {
+        let duration = self.update_clock.update(duration, &mut self.usage);
+        let duration = clamp_update_duration(duration, self.connection_config.max_update_duration, &mut self.clamped_time);
+        self.current_time += duration;
+        let mut quota_warnings = vec![];
+        for (&client_id, connection) in self.connections.iter_mut() {
             connection.update(duration);
+            if let Some(kind) = connection.take_quota_warning() {
+                quota_warnings.push((client_id, kind));
+            }
+            self.channel_warnings
+                .extend(connection.drain_channel_warnings().map(|warning| ChannelWarning {
+                    client_id: Some(client_id),
+                    ..warning
+                }));
+            self.channel_errors.extend(
+                connection
+                    .drain_channel_errors()
+                    .map(|(channel_id, error)| (client_id, channel_id, error)),
+            );
+            for &warning in connection.usage_warnings() {
+                self.usage.detect(warning);
+            }
+        }
+
+        for (client_id, kind) in quota_warnings {
+            self.push_event(|occurred_at, sequence| ServerEvent::ClientQuotaWarning {
+                client_id,
+                kind,
+                occurred_at,
+                sequence,
+            });
         }
+
+        if self.events.len() >= MAX_UNDRAINED_EVENTS
+            || self.channel_warnings.len() >= MAX_UNDRAINED_EVENTS
+            || self.channel_errors.len() >= MAX_UNDRAINED_EVENTS
+        {
+            self.usage.detect(UsageWarning::EventsNotDrained);
+        }
+    }
+
+    /// Returns the misuses of the API detected so far, for the server or any of its clients.
+    /// Empty when the server is used correctly. Each misuse is also logged once as a warning when it is detected.
+    pub fn health_check(&self) -> Vec<UsageWarning> {
+        self.usage.detected().to_vec()
+    }
+
+    // Duration for the transport update, the measured one if the last update measured it
+    #[cfg(feature = "transport")]
+    pub(crate) fn transport_update_duration(&self, duration: Duration) -> Duration {
+        self.update_clock.transport_duration(duration)
     }
 
     /// Returns a list of packets to be sent to the client.
@@ -257,6 +969,59 @@ impl RenetServer {
         }
     }
 
+    /// Returns an ack-only packet for the client if its acks are due, see [`RenetClient::ack_flush_due`].
+    /// <p style="background:rgba(77,220,255,0.16);padding:0.5em;">
+    /// <strong>Note:</strong> This should only be called by the transport layer.
+    /// </p>
+    pub fn get_ack_packets_to_send(&mut self, client_id: ClientId) -> Result<Vec<Payload>, ClientNotFound> {
+        match self.connections.get_mut(&client_id) {
+            Some(connection) => Ok(connection.get_ack_packets_to_send()),
+            None => Err(ClientNotFound),
+        }
+    }
+
+    /// Counts a packet for the client that could not be sent, see [`RenetClient::record_dropped_packet`].
+    /// <p style="background:rgba(77,220,255,0.16);padding:0.5em;">
+    /// <strong>Note:</strong> This should only be called by the transport layer.
+    /// </p>
+    pub fn record_dropped_packet(&mut self, client_id: ClientId, reason: DropReason, packet: &[u8]) -> Result<(), ClientNotFound> {
+        match self.connections.get_mut(&client_id) {
+            Some(connection) => {
+                connection.record_dropped_packet(reason, packet);
+                Ok(())
+            }
+            None => Err(ClientNotFound),
+        }
+    }
+
+    /// Counts a datagram sent to the client on the wire, see [`RenetClient::record_wire_packet_sent`].
+    /// <p style="background:rgba(77,220,255,0.16);padding:0.5em;">
+    /// <strong>Note:</strong> This should only be called by the transport layer.
+    /// </p>
+    pub fn record_wire_packet_sent(&mut self, client_id: ClientId, len: usize) -> Result<(), ClientNotFound> {
+        match self.connections.get_mut(&client_id) {
+            Some(connection) => {
+                connection.record_wire_packet_sent(len);
+                Ok(())
+            }
+            None => Err(ClientNotFound),
+        }
+    }
+
+    /// Counts a datagram received from the client on the wire, see [`RenetClient::record_wire_packet_received`].
+    /// <p style="background:rgba(77,220,255,0.16);padding:0.5em;">
+    /// <strong>Note:</strong> This should only be called by the transport layer.
+    /// </p>
+    pub fn record_wire_packet_received(&mut self, client_id: ClientId, len: usize) -> Result<(), ClientNotFound> {
+        match self.connections.get_mut(&client_id) {
+            Some(connection) => {
+                connection.record_wire_packet_received(len);
+                Ok(())
+            }
+            None => Err(ClientNotFound),
+        }
+    }
+
     /// Process a packet received from the client.
     /// <p style="background:rgba(77,220,255,0.16);padding:0.5em;">
     /// <strong>Note:</strong> This should only be called by the transport layer.
@@ -270,4 +1035,18 @@ impl RenetServer {
             None => Err(ClientNotFound),
         }
     }
+
+    /// Process a packet received from the client, the received messages share the memory of the packet.
+    /// <p style="background:rgba(77,220,255,0.16);padding:0.5em;">
+    /// <strong>Note:</strong> This should only be called by the transport layer.
+    /// </p>
+    pub fn process_packet_bytes_from(&mut self, payload: Bytes, client_id: ClientId) -> Result<(), ClientNotFound> {
+        match self.connections.get_mut(&client_id) {
+            Some(connection) => {
+                connection.process_packet_bytes(payload);
+                Ok(())
+            }
+            None => Err(ClientNotFound),
+        }
+    }
 }
@@ -0,0 +1,190 @@
+//! Tracking of the snapshots acked by each client, for delta compressed state replication.
+//!
+//! The server sends snapshots of the game state in a reliable channel, encoded as a delta against
+//! the latest snapshot acked by the client, and registers each sent message in a [`SnapshotTracker`].
+//! Once the client acks a message, the snapshot it carried becomes the new baseline for that client.
+//!
+//! See the `snapshot_delta` example for a complete usage.
+use std::collections::{HashMap, VecDeque};
+
+use crate::{ChannelId, ClientId, MessageId, RenetServer};
+
+/// Identifier of a snapshot, chosen by the application.
+/// Ids must increase with each new snapshot.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Ord, PartialOrd)]
+pub struct SnapshotId(u64);
+
+impl SnapshotId {
+    /// Creates a [`SnapshotId`] from a raw 64 bit value.
+    pub const fn from_raw(value: u64) -> Self {
+        Self(value)
+    }
+
+    /// Returns the raw 64 bit value of the [`SnapshotId`]
+    pub fn raw(&self) -> u64 {
+        self.0
+    }
+}
+
+impl std::fmt::Display for SnapshotId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug, Default)]
+struct ClientSnapshots {
+    // Sent snapshots newer than the latest acked one, oldest first
+    pending: VecDeque<(SnapshotId, MessageId)>,
+    latest_acked: Option<SnapshotId>,
+    last_message_id: Option<MessageId>,
+}
+
+/// Keeps track of the latest snapshot acked by each client.
+///
+/// State of old snapshots is pruned once a newer one is acked, and when more than
+/// `max_pending_snapshots` are waiting for an ack. State of disconnected clients is removed on
+/// [`SnapshotTracker::update`], and when a new connection is detected for the same client id.
+#[derive(Debug)]
+pub struct SnapshotTracker {
+    channel_id: ChannelId,
+    max_pending_snapshots: usize,
+    clients: HashMap<ClientId, ClientSnapshots>,
+}
+
+impl SnapshotTracker {
+    /// Creates a tracker for the snapshots sent in the channel, it must be a reliable channel.
+    pub fn new<I: Into<ChannelId>>(channel_id: I, max_pending_snapshots: usize) -> Self {
+        Self {
+            channel_id: channel_id.into(),
+            max_pending_snapshots,
+            clients: HashMap::new(),
+        }
+    }
+
+    /// Registers the message carrying the snapshot sent to the client.
+    ///
+    /// A message id lower or equal than the previous one registered for the client means the
+    /// client reconnected, messages ids start again for a new connection, the state of the previous
+    /// connection is discarded.
+    pub fn register_sent(&mut self, client_id: ClientId, snapshot_id: SnapshotId, message_id: MessageId) {
+        let client = self.clients.entry(client_id).or_default();
+        if client.last_message_id.is_some_and(|last_message_id| message_id <= last_message_id) {
+            *client = ClientSnapshots::default();
+        }
+
+        client.last_message_id = Some(message_id);
+        client.pending.push_back((snapshot_id, message_id));
+        while client.pending.len() > self.max_pending_snapshots {
+            client.pending.pop_front();
+        }
+    }
+
+    /// Processes the acked message ids of the client, from
+    /// [`RenetServer::drain_acked_messages`] or [`RenetClient::drain_acked_messages`][crate::RenetClient::drain_acked_messages].
+    pub fn process_acks(&mut self, client_id: ClientId, acked_messages: impl IntoIterator<Item = MessageId>) {
+        let Some(client) = self.clients.get_mut(&client_id) else {
+            return;
+        };
+
+        for message_id in acked_messages {
+            let Some(index) = client.pending.iter().position(|&(_, id)| id == message_id) else {
+                continue;
+            };
+
+            let (snapshot_id, _) = client.pending[index];
+            if client.latest_acked < Some(snapshot_id) {
+                client.latest_acked = Some(snapshot_id);
+            }
+            client.pending.retain(|&(id, _)| id > snapshot_id);
+        }
+    }
+
+    /// Processes the acks of every tracked client and removes the clients no longer connected.
+    /// Should be called every tick after the received packets are processed,
+    /// the acked messages are cleared on the next server update.
+    pub fn update(&mut self, server: &mut RenetServer) {
+        let channel_id = self.channel_id;
+        self.clients.retain(|&client_id, _| server.is_connected(client_id));
+
+        let client_ids: Vec<ClientId> = self.clients.keys().copied().collect();
+        for client_id in client_ids {
+            let acked: Vec<MessageId> = server.drain_acked_messages(client_id, channel_id).collect();
+            self.process_acks(client_id, acked);
+        }
+    }
+
+    /// Returns the latest snapshot acked by the client, the baseline for the next delta.
+    pub fn latest_acked_snapshot(&self, client_id: ClientId) -> Option<SnapshotId> {
+        self.clients.get(&client_id)?.latest_acked
+    }
+
+    /// Returns the number of snapshots sent to the client and not acked yet.
+    pub fn pending_snapshots(&self, client_id: ClientId) -> usize {
+        self.clients.get(&client_id).map_or(0, |client| client.pending.len())
+    }
+
+    /// Removes the state of the client.
+    pub fn remove_client(&mut self, client_id: ClientId) {
+        self.clients.remove(&client_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: u64) -> MessageId {
+        MessageId::from_raw(id)
+    }
+
+    fn snapshot(id: u64) -> SnapshotId {
+        SnapshotId::from_raw(id)
+    }
+
+    #[test]
+    fn acks_out_of_order() {
+        let client_id = ClientId::from_raw(0);
+        let mut tracker = SnapshotTracker::new(0, 8);
+        for i in 0..4 {
+            tracker.register_sent(client_id, snapshot(10 + i), message(i));
+        }
+        assert_eq!(tracker.latest_acked_snapshot(client_id), None);
+
+        tracker.process_acks(client_id, [message(2)]);
+        assert_eq!(tracker.latest_acked_snapshot(client_id), Some(snapshot(12)));
+        assert_eq!(tracker.pending_snapshots(client_id), 1);
+
+        // Acks of older snapshots are ignored
+        tracker.process_acks(client_id, [message(0), message(1)]);
+        assert_eq!(tracker.latest_acked_snapshot(client_id), Some(snapshot(12)));
+
+        tracker.process_acks(client_id, [message(3)]);
+        assert_eq!(tracker.latest_acked_snapshot(client_id), Some(snapshot(13)));
+        assert_eq!(tracker.pending_snapshots(client_id), 0);
+    }
+
+    #[test]
+    fn prune_pending_and_reconnect() {
+        let client_id = ClientId::from_raw(0);
+        let mut tracker = SnapshotTracker::new(0, 2);
+        for i in 0..4 {
+            tracker.register_sent(client_id, snapshot(i), message(i));
+        }
+        assert_eq!(tracker.pending_snapshots(client_id), 2);
+
+        // The pruned snapshot is never acked
+        tracker.process_acks(client_id, [message(0)]);
+        assert_eq!(tracker.latest_acked_snapshot(client_id), None);
+        tracker.process_acks(client_id, [message(2)]);
+        assert_eq!(tracker.latest_acked_snapshot(client_id), Some(snapshot(2)));
+
+        tracker.process_acks(client_id, [message(3)]);
+        assert_eq!(tracker.pending_snapshots(client_id), 0);
+
+        // Message ids start over for a new connection
+        tracker.register_sent(client_id, snapshot(4), message(0));
+        assert_eq!(tracker.latest_acked_snapshot(client_id), None);
+        assert_eq!(tracker.pending_snapshots(client_id), 1);
+    }
+}
@@ -0,0 +1,154 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+
+use crate::{
+    channel::SliceConstructor,
+    packet::{num_slices, Slice, SLICE_SIZE},
+};
+
+/// Maximum size in bytes of the token of a transfer, see [`RenetServer::transfer_client`][crate::RenetServer::transfer_client].
+/// Netcode connect tokens are 2048 bytes.
+pub const MAX_TRANSFER_TOKEN_BYTES: usize = 4096;
+
+pub(crate) const MAX_TRANSFER_SLICES: usize = (MAX_TRANSFER_TOKEN_BYTES + SLICE_SIZE - 1) / SLICE_SIZE;
+
+// Time to wait before resending a slice of the transfer token if it was not acked.
+const TRANSFER_RESEND_TIME: Duration = Duration::from_millis(100);
+
+// Token sent to the remote, its slices are resent until all of them are acked
+#[derive(Debug)]
+struct OutgoingTransfer {
+    token: Bytes,
+    acked: Vec<bool>,
+    last_sent: Vec<Option<Duration>>,
+}
+
+// Transfer of the connection to another server: the server sends a token for the other server,
+// and disconnects the client once the token is fully acked.
+#[derive(Debug, Default)]
+pub(crate) struct Transfer {
+    outgoing: Option<OutgoingTransfer>,
+    incoming: Option<SliceConstructor>,
+    received_token: Option<Bytes>,
+    token_received: bool,
+}
+
+impl Transfer {
+    pub fn start(&mut self, token: Bytes) {
+        let num_slices = num_slices(token.len());
+        self.outgoing = Some(OutgoingTransfer {
+            token,
+            acked: vec![false; num_slices],
+            last_sent: vec![None; num_slices],
+        });
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.outgoing.is_some()
+    }
+
+    // Slices of the token never sent or not acked after the resend time
+    pub fn slices_to_send(&mut self, current_time: Duration) -> Vec<Slice> {
+        let Some(outgoing) = &mut self.outgoing else {
+            return vec![];
+        };
+
+        let num_slices = outgoing.acked.len();
+        let mut slices = vec![];
+        for slice_index in 0..num_slices {
+            if outgoing.acked[slice_index] {
+                continue;
+            }
+            if let Some(last_sent) = outgoing.last_sent[slice_index] {
+                if current_time.saturating_sub(last_sent) < TRANSFER_RESEND_TIME {
+                    continue;
+                }
+            }
+
+            let start = slice_index * SLICE_SIZE;
+            let end = (start + SLICE_SIZE).min(outgoing.token.len());
+            slices.push(Slice {
+                message_id: 0,
+                slice_index,
+                num_slices,
+                payload: outgoing.token.slice(start..end),
+            });
+            outgoing.last_sent[slice_index] = Some(current_time);
+        }
+
+        slices
+    }
+
+    // Returns true when the last slice of the token is acked
+    pub fn slice_acked(&mut self, slice_index: usize) -> bool {
+        let Some(outgoing) = &mut self.outgoing else {
+            return false;
+        };
+
+        let was_delivered = outgoing.acked.iter().all(|acked| *acked);
+        outgoing.acked[slice_index] = true;
+        !was_delivered && outgoing.acked.iter().all(|acked| *acked)
+    }
+
+    // Returns true when the token is complete, only the first token received is kept.
+    // Slices that don't match the ones already received are ignored.
+    pub fn received_slice(&mut self, slice: Slice) -> bool {
+        if self.token_received {
+            return false;
+        }
+
+        let incoming = self.incoming.get_or_insert_with(|| SliceConstructor::new(0, slice.num_slices));
+        if incoming.num_slices != slice.num_slices {
+            return false;
+        }
+
+        let Ok(Some(token)) = incoming.process_slice(slice.slice_index, &slice.payload) else {
+            return false;
+        };
+        self.incoming = None;
+        self.received_token = Some(token);
+        self.token_received = true;
+        true
+    }
+
+    pub fn token_received(&self) -> bool {
+        self.token_received
+    }
+
+    pub fn take_token(&mut self) -> Option<Bytes> {
+        self.received_token.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sliced_token() {
+        let token: Bytes = (0..2048).map(|i| i as u8).collect::<Vec<u8>>().into();
+        let mut sender = Transfer::default();
+        let mut receiver = Transfer::default();
+        sender.start(token.clone());
+
+        let slices = sender.slices_to_send(Duration::ZERO);
+        assert_eq!(slices.len(), 2);
+        assert!(sender.slices_to_send(Duration::ZERO).is_empty());
+
+        // The first slice is lost and resent
+        assert!(!receiver.received_slice(slices[1].clone()));
+        assert!(!sender.slice_acked(1));
+        let resent = sender.slices_to_send(TRANSFER_RESEND_TIME);
+        assert_eq!(resent, vec![slices[0].clone()]);
+
+        assert!(receiver.received_slice(resent[0].clone()));
+        assert!(sender.slice_acked(0));
+        assert!(!sender.slice_acked(0));
+        assert!(sender.slices_to_send(TRANSFER_RESEND_TIME * 2).is_empty());
+
+        assert!(receiver.token_received());
+        assert_eq!(receiver.take_token(), Some(token));
+        assert_eq!(receiver.take_token(), None);
+    }
+}
@@ -0,0 +1,345 @@
+use std::{
+    io::{self, Write},
+    net::{IpAddr, Ipv6Addr, SocketAddr},
+    time::{Duration, SystemTime},
+};
+
+// Block types of the pcapng format
+const SECTION_HEADER_BLOCK: u32 = 0x0A0D_0D0A;
+const INTERFACE_DESCRIPTION_BLOCK: u32 = 0x0000_0001;
+const ENHANCED_PACKET_BLOCK: u32 = 0x0000_0006;
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+// Packets start with their IPv4 or IPv6 header
+const LINKTYPE_RAW: u16 = 101;
+// Link type reserved for private use, the decrypted payloads read by the generated Wireshark dissector
+pub(crate) const LINKTYPE_USER0: u16 = 147;
+
+// Decrypted payloads start with their direction and the address of the peer,
+// its IPv6 or IPv4 mapped address and its port, followed by the renet packet
+pub(crate) const PAYLOAD_SENT: u8 = 0;
+pub(crate) const PAYLOAD_RECEIVED: u8 = 1;
+pub(crate) const PAYLOAD_HEADER_BYTES: usize = 1 + 16 + 2;
+
+const IPV4_HEADER_BYTES: usize = 20;
+const IPV6_HEADER_BYTES: usize = 40;
+const UDP_HEADER_BYTES: usize = 8;
+const UDP_PROTOCOL: u8 = 17;
+const HOP_LIMIT: u8 = 64;
+
+// Writes datagrams in the pcapng format, each one in an IP and UDP header built from its addresses,
+// so the capture can be opened with Wireshark or tcpdump.
+// With payloads, the decrypted renet packets are written on a second interface with the LINKTYPE_USER0 link type.
+// Timestamps are in microseconds, the default resolution of the format.
+#[derive(Debug)]
+pub(crate) struct PcapngWriter<W: Write> {
+    writer: W,
+    payloads: bool,
+}
+
+impl<W: Write> PcapngWriter<W> {
+    // Writes the section header and the description of the interfaces
+    pub fn new(mut writer: W, payloads: bool) -> io::Result<Self> {
+        let mut section_header = Vec::with_capacity(16);
+        section_header.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+        section_header.extend_from_slice(&1u16.to_le_bytes());
+        section_header.extend_from_slice(&0u16.to_le_bytes());
+        // Unknown section length
+        section_header.extend_from_slice(&(-1i64).to_le_bytes());
+        write_block(&mut writer, SECTION_HEADER_BLOCK, &section_header)?;
+
+        write_interface(&mut writer, LINKTYPE_RAW)?;
+        if payloads {
+            write_interface(&mut writer, LINKTYPE_USER0)?;
+        }
+
+        Ok(Self { writer, payloads })
+    }
+
+    pub fn has_payloads(&self) -> bool {
+        self.payloads
+    }
+
+    pub fn write_datagram(&mut self, timestamp: Duration, source: SocketAddr, destination: SocketAddr, datagram: &[u8]) -> io::Result<()> {
+        let packet = ip_packet(source, destination, datagram)?;
+        self.write_packet(0, timestamp, &packet)
+    }
+
+    // Writes a decrypted renet packet on the payload interface, does nothing when the writer has none
+    pub fn write_payload(&mut self, timestamp: Duration, direction: u8, peer: SocketAddr, payload: &[u8]) -> io::Result<()> {
+        if !self.payloads {
+            return Ok(());
+        }
+
+        let mut packet = Vec::with_capacity(PAYLOAD_HEADER_BYTES + payload.len());
+        packet.push(direction);
+        packet.extend_from_slice(&to_ipv6(peer.ip()).octets());
+        packet.extend_from_slice(&peer.port().to_be_bytes());
+        packet.extend_from_slice(payload);
+        self.write_packet(1, timestamp, &packet)
+    }
+
+    fn write_packet(&mut self, interface_id: u32, timestamp: Duration, packet: &[u8]) -> io::Result<()> {
+        let timestamp = timestamp.as_micros() as u64;
+        let mut block = Vec::with_capacity(20 + packet.len() + 3);
+        block.extend_from_slice(&interface_id.to_le_bytes());
+        block.extend_from_slice(&((timestamp >> 32) as u32).to_le_bytes());
+        block.extend_from_slice(&(timestamp as u32).to_le_bytes());
+        // Captured and original length
+        block.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        block.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        block.extend_from_slice(packet);
+        block.resize(block.len().next_multiple_of(4), 0);
+
+        write_block(&mut self.writer, ENHANCED_PACKET_BLOCK, &block)
+    }
+
+    #[cfg(test)]
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+fn write_interface<W: Write>(writer: &mut W, link_type: u16) -> io::Result<()> {
+    let mut interface = Vec::with_capacity(8);
+    interface.extend_from_slice(&link_type.to_le_bytes());
+    interface.extend_from_slice(&0u16.to_le_bytes());
+    // No snapshot length limit
+    interface.extend_from_slice(&0u32.to_le_bytes());
+    write_block(writer, INTERFACE_DESCRIPTION_BLOCK, &interface)
+}
+
+// The body must be padded to 32 bits
+fn write_block<W: Write>(writer: &mut W, block_type: u32, body: &[u8]) -> io::Result<()> {
+    let total_length = (12 + body.len()) as u32;
+    writer.write_all(&block_type.to_le_bytes())?;
+    writer.write_all(&total_length.to_le_bytes())?;
+    writer.write_all(body)?;
+    writer.write_all(&total_length.to_le_bytes())
+}
+
+fn ip_packet(source: SocketAddr, destination: SocketAddr, datagram: &[u8]) -> io::Result<Vec<u8>> {
+    let udp_len = UDP_HEADER_BYTES + datagram.len();
+    if udp_len > u16::MAX as usize - IPV6_HEADER_BYTES {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "datagram too big for an UDP packet"));
+    }
+
+    let mut udp = Vec::with_capacity(udp_len);
+    udp.extend_from_slice(&source.port().to_be_bytes());
+    udp.extend_from_slice(&destination.port().to_be_bytes());
+    udp.extend_from_slice(&(udp_len as u16).to_be_bytes());
+    udp.extend_from_slice(&[0, 0]);
+    udp.extend_from_slice(datagram);
+
+    let mut packet;
+    match (source.ip(), destination.ip()) {
+        (IpAddr::V4(source_ip), IpAddr::V4(destination_ip)) => {
+            packet = Vec::with_capacity(IPV4_HEADER_BYTES + udp_len);
+            packet.extend_from_slice(&[0x45, 0]);
+            packet.extend_from_slice(&((IPV4_HEADER_BYTES + udp_len) as u16).to_be_bytes());
+            // Identification, flags and fragment offset
+            packet.extend_from_slice(&[0, 0, 0, 0]);
+            packet.extend_from_slice(&[HOP_LIMIT, UDP_PROTOCOL, 0, 0]);
+            packet.extend_from_slice(&source_ip.octets());
+            packet.extend_from_slice(&destination_ip.octets());
+            let header_checksum = checksum(0, &packet);
+            packet[10..12].copy_from_slice(&header_checksum.to_be_bytes());
+
+            let mut pseudo_header = Vec::with_capacity(12);
+            pseudo_header.extend_from_slice(&source_ip.octets());
+            pseudo_header.extend_from_slice(&destination_ip.octets());
+            pseudo_header.extend_from_slice(&[0, UDP_PROTOCOL]);
+            pseudo_header.extend_from_slice(&(udp_len as u16).to_be_bytes());
+            set_udp_checksum(&pseudo_header, &mut udp);
+        }
+        (source_ip, destination_ip) => {
+            // Sockets with different families can only talk through IPv4 mapped addresses
+            let source_ip = to_ipv6(source_ip);
+            let destination_ip = to_ipv6(destination_ip);
+
+            packet = Vec::with_capacity(IPV6_HEADER_BYTES + udp_len);
+            packet.extend_from_slice(&[0x60, 0, 0, 0]);
+            packet.extend_from_slice(&(udp_len as u16).to_be_bytes());
+            packet.extend_from_slice(&[UDP_PROTOCOL, HOP_LIMIT]);
+            packet.extend_from_slice(&source_ip.octets());
+            packet.extend_from_slice(&destination_ip.octets());
+
+            let mut pseudo_header = Vec::with_capacity(40);
+            pseudo_header.extend_from_slice(&source_ip.octets());
+            pseudo_header.extend_from_slice(&destination_ip.octets());
+            pseudo_header.extend_from_slice(&(udp_len as u32).to_be_bytes());
+            pseudo_header.extend_from_slice(&[0, 0, 0, UDP_PROTOCOL]);
+            set_udp_checksum(&pseudo_header, &mut udp);
+        }
+    }
+
+    packet.extend_from_slice(&udp);
+    Ok(packet)
+}
+
+fn to_ipv6(ip: IpAddr) -> Ipv6Addr {
+    match ip {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+        IpAddr::V6(ip) => ip,
+    }
+}
+
+fn set_udp_checksum(pseudo_header: &[u8], udp: &mut [u8]) {
+    let udp_checksum = match checksum(checksum_sum(0, pseudo_header), udp) {
+        // A zero checksum means no checksum, it's sent as all ones instead
+        0 => 0xFFFF,
+        udp_checksum => udp_checksum,
+    };
+    udp[6..8].copy_from_slice(&udp_checksum.to_be_bytes());
+}
+
+// One's complement sum of the 16 bit words, an odd last byte is padded with zero
+fn checksum_sum(mut sum: u32, bytes: &[u8]) -> u32 {
+    for word in bytes.chunks(2) {
+        let word = match *word {
+            [high, low] => u16::from_be_bytes([high, low]),
+            [high] => u16::from_be_bytes([high, 0]),
+            _ => unreachable!(),
+        };
+        sum += word as u32;
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    sum
+}
+
+fn checksum(sum: u32, bytes: &[u8]) -> u16 {
+    !(checksum_sum(sum, bytes) as u16)
+}
+
+// Captures the datagrams of a transport as seen on its socket
+pub(crate) struct PacketCapture {
+    writer: PcapngWriter<Box<dyn Write + Send + Sync>>,
+    local_addr: SocketAddr,
+}
+
+impl PacketCapture {
+    pub fn new<W: Write + Send + Sync + 'static>(writer: W, local_addr: SocketAddr, payloads: bool) -> io::Result<Self> {
+        Ok(Self {
+            writer: PcapngWriter::new(Box::new(writer) as Box<dyn Write + Send + Sync>, payloads)?,
+            local_addr,
+        })
+    }
+
+    pub fn has_payloads(&self) -> bool {
+        self.writer.has_payloads()
+    }
+
+    pub fn sent(&mut self, addr: SocketAddr, datagram: &[u8]) -> io::Result<()> {
+        self.writer.write_datagram(now(), self.local_addr, addr, datagram)
+    }
+
+    pub fn received(&mut self, addr: SocketAddr, datagram: &[u8]) -> io::Result<()> {
+        self.writer.write_datagram(now(), addr, self.local_addr, datagram)
+    }
+
+    pub fn payload_sent(&mut self, addr: SocketAddr, payload: &[u8]) -> io::Result<()> {
+        self.writer.write_payload(now(), PAYLOAD_SENT, addr, payload)
+    }
+
+    pub fn payload_received(&mut self, addr: SocketAddr, payload: &[u8]) -> io::Result<()> {
+        self.writer.write_payload(now(), PAYLOAD_RECEIVED, addr, payload)
+    }
+}
+
+fn now() -> Duration {
+    SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn pcapng_blocks() {
+        let mut writer = PcapngWriter::new(Vec::new(), false).unwrap();
+        let source: SocketAddr = "127.0.0.1:5000".parse().unwrap();
+        let destination: SocketAddr = "10.0.0.2:6000".parse().unwrap();
+        writer
+            .write_datagram(Duration::from_micros(0x1_0000_0002), source, destination, b"hello")
+            .unwrap();
+        let capture = writer.into_inner();
+
+        assert_eq!(read_u32(&capture, 0), SECTION_HEADER_BLOCK);
+        assert_eq!(read_u32(&capture, 8), BYTE_ORDER_MAGIC);
+        let section_len = read_u32(&capture, 4) as usize;
+        assert_eq!(read_u32(&capture, section_len - 4) as usize, section_len);
+
+        let interface = &capture[section_len..];
+        assert_eq!(read_u32(interface, 0), INTERFACE_DESCRIPTION_BLOCK);
+        assert_eq!(u16::from_le_bytes([interface[8], interface[9]]), LINKTYPE_RAW);
+        let interface_len = read_u32(interface, 4) as usize;
+
+        let packet_block = &interface[interface_len..];
+        assert_eq!(read_u32(packet_block, 0), ENHANCED_PACKET_BLOCK);
+        let block_len = read_u32(packet_block, 4) as usize;
+        assert_eq!(block_len, packet_block.len());
+        assert_eq!(block_len % 4, 0);
+        assert_eq!(read_u32(packet_block, 12), 1);
+        assert_eq!(read_u32(packet_block, 16), 2);
+        let captured_len = read_u32(packet_block, 20) as usize;
+        assert_eq!(captured_len, IPV4_HEADER_BYTES + UDP_HEADER_BYTES + 5);
+
+        let packet = &packet_block[28..28 + captured_len];
+        // Valid checksums sum to zero
+        assert_eq!(checksum(0, &packet[..IPV4_HEADER_BYTES]), 0);
+        assert_eq!(&packet[12..16], &[127, 0, 0, 1]);
+        assert_eq!(&packet[16..20], &[10, 0, 0, 2]);
+        let udp = &packet[IPV4_HEADER_BYTES..];
+        assert_eq!(u16::from_be_bytes([udp[0], udp[1]]), 5000);
+        assert_eq!(u16::from_be_bytes([udp[2], udp[3]]), 6000);
+        assert_eq!(&udp[UDP_HEADER_BYTES..], b"hello");
+
+        // Payloads are only written with the payload interface
+        let len = PcapngWriter::new(Vec::new(), false).unwrap().into_inner().len();
+        let mut writer = PcapngWriter::new(Vec::new(), false).unwrap();
+        writer.write_payload(Duration::ZERO, PAYLOAD_SENT, destination, b"payload").unwrap();
+        assert_eq!(writer.into_inner().len(), len);
+    }
+
+    #[test]
+    fn pcapng_payload_interface() {
+        let mut writer = PcapngWriter::new(Vec::new(), true).unwrap();
+        let peer: SocketAddr = "10.0.0.2:6000".parse().unwrap();
+        writer.write_payload(Duration::ZERO, PAYLOAD_RECEIVED, peer, b"payload").unwrap();
+        let capture = writer.into_inner();
+
+        let section_len = read_u32(&capture, 4) as usize;
+        let interface_len = read_u32(&capture, section_len + 4) as usize;
+        let payload_interface = &capture[section_len + interface_len..];
+        assert_eq!(read_u32(payload_interface, 0), INTERFACE_DESCRIPTION_BLOCK);
+        assert_eq!(u16::from_le_bytes([payload_interface[8], payload_interface[9]]), LINKTYPE_USER0);
+
+        let packet_block = &payload_interface[read_u32(payload_interface, 4) as usize..];
+        assert_eq!(read_u32(packet_block, 0), ENHANCED_PACKET_BLOCK);
+        // Written on the second interface
+        assert_eq!(read_u32(packet_block, 8), 1);
+        let captured_len = read_u32(packet_block, 20) as usize;
+        let packet = &packet_block[28..28 + captured_len];
+        assert_eq!(packet[0], PAYLOAD_RECEIVED);
+        assert_eq!(&packet[1..17], &std::net::Ipv4Addr::new(10, 0, 0, 2).to_ipv6_mapped().octets());
+        assert_eq!(&packet[17..19], &6000u16.to_be_bytes());
+        assert_eq!(&packet[PAYLOAD_HEADER_BYTES..], b"payload");
+    }
+
+    #[test]
+    fn udp_checksum() {
+        let source: SocketAddr = "[::1]:5000".parse().unwrap();
+        let destination: SocketAddr = "127.0.0.1:6000".parse().unwrap();
+        let packet = ip_packet(source, destination, b"odd").unwrap();
+        assert_eq!(packet[0] >> 4, 6);
+        assert_eq!(&packet[24..40], &std::net::Ipv4Addr::LOCALHOST.to_ipv6_mapped().octets());
+
+        let mut pseudo_header = packet[8..40].to_vec();
+        pseudo_header.extend_from_slice(&((UDP_HEADER_BYTES + 3) as u32).to_be_bytes());
+        pseudo_header.extend_from_slice(&[0, 0, 0, UDP_PROTOCOL]);
+        assert_eq!(checksum(checksum_sum(0, &pseudo_header), &packet[IPV6_HEADER_BYTES..]), 0);
+    }
+}
@@ -1,35 +1,106 @@
 use std::{
-    io,
-    net::{SocketAddr, UdpSocket},
+    io::{self, Write},
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
     time::Duration,
 };
 
-use renetcode::{ClientAuthentication, DisconnectReason, NetcodeClient, NetcodeError, NETCODE_MAX_PACKET_BYTES};
+use bytes::Bytes;
+use renetcode::{ClientAuthentication, DisconnectReason, NetcodeClient, NetcodeError};
 
-use crate::{remote_connection::RenetClient, ClientId};
+use crate::{packet::Payload, remote_connection::RenetClient, ClientId, DropReason, EffectiveConfig};
 
-use super::NetcodeTransportError;
+use super::{
+    capture::PacketCapture, capture_payload, intercept_received, payload_range, send_to, shared_payload, ErrorLog, InterceptAction,
+    NetcodeTransportError, PacketDirection, PacketInterceptor, ReceiveBuffer, RepeatedError, TransportSocket,
+};
 
 #[derive(Debug)]
 #[cfg_attr(feature = "bevy", derive(bevy_ecs::system::Resource))]
 pub struct NetcodeClientTransport {
-    socket: UdpSocket,
+    socket: TransportSocket,
     netcode_client: NetcodeClient,
-    buffer: [u8; NETCODE_MAX_PACKET_BYTES],
+    buffer: ReceiveBuffer,
+    interceptor: Option<PacketInterceptor>,
+    error_log: ErrorLog,
 }
 
 impl NetcodeClientTransport {
     pub fn new(current_time: Duration, authentication: ClientAuthentication, socket: UdpSocket) -> Result<Self, NetcodeError> {
         socket.set_nonblocking(true)?;
+        Self::with_socket(current_time, authentication, TransportSocket::Udp(socket))
+    }
+
+    /// Creates a transport without socket, for applications that carry the datagrams themselves.
+    /// Received datagrams are passed with [`NetcodeClientTransport::process_incoming_packet`],
+    /// and the encrypted datagrams to send are taken with [`NetcodeClientTransport::drain_outgoing_packets`].
+    pub fn new_manual(current_time: Duration, authentication: ClientAuthentication) -> Result<Self, NetcodeError> {
+        Self::with_socket(current_time, authentication, TransportSocket::manual())
+    }
+
+    fn with_socket(current_time: Duration, authentication: ClientAuthentication, socket: TransportSocket) -> Result<Self, NetcodeError> {
         let netcode_client = NetcodeClient::new(current_time, authentication)?;
 
         Ok(Self {
-            buffer: [0u8; NETCODE_MAX_PACKET_BYTES],
+            buffer: ReceiveBuffer::new(),
             socket,
             netcode_client,
+            interceptor: None,
+            error_log: ErrorLog::default(),
         })
     }
 
+    /// Starts a new connection with the server after the transport was disconnected.
+    /// Fails if the transport is still connecting or connected, or if the token was already used by it.
+    /// A new [`RenetClient`] should be used for the new connection.
+    pub fn connect(&mut self, current_time: Duration, authentication: ClientAuthentication) -> Result<(), NetcodeError> {
+        self.netcode_client.connect(current_time, authentication)
+    }
+
+    /// Sets a hook called with every datagram right before it is sent to the socket
+    /// and right after it is read from it.
+    /// Netcode encryption happens first, the hook receives the already encrypted packet.
+    /// The hook can modify or replace the datagram, or drop it by returning [`InterceptAction::Drop`],
+    /// dropped datagrams are seen as packet loss by the rest of the stack.
+    /// Received datagrams larger than `NETCODE_MAX_PACKET_BYTES` after the hook are discarded.
+    pub fn set_packet_interceptor<F>(&mut self, interceptor: F)
+    where
+        F: FnMut(PacketDirection, SocketAddr, &mut Vec<u8>) -> InterceptAction + Send + Sync + 'static,
+    {
+        self.interceptor
+            .get_or_insert_with(PacketInterceptor::default)
+            .set_interceptor(interceptor);
+    }
+
+    /// Writes every datagram sent or received by the transport to `writer` in the pcapng format,
+    /// the capture can be opened with Wireshark or tcpdump.
+    /// Datagrams are written as they are on the socket: encrypted, with the IP and UDP headers rebuilt from their addresses,
+    /// and with the changes made by the packet interceptor.
+    /// The writer is not buffered by the transport, wrap files in a [`BufWriter`][std::io::BufWriter].
+    /// If writing fails the error is logged and the capture stops.
+    pub fn set_packet_capture<W: Write + Send + Sync + 'static>(&mut self, writer: W) -> io::Result<()> {
+        self.start_capture(writer, false)
+    }
+
+    /// Same as [`NetcodeClientTransport::set_packet_capture`], and also writes the renet packets before they are
+    /// encrypted and after they are decrypted, on a second interface with the `LINKTYPE_USER0` link type.
+    /// They are decoded by the Wireshark dissector in `renet/wireshark/renet.lua`.
+    ///
+    /// The capture exposes the contents of every message, only use it while developing.
+    #[cfg(feature = "capture_decrypted")]
+    pub fn set_packet_capture_with_payloads<W: Write + Send + Sync + 'static>(&mut self, writer: W) -> io::Result<()> {
+        self.start_capture(writer, true)
+    }
+
+    fn start_capture<W: Write + Send + Sync + 'static>(&mut self, writer: W, payloads: bool) -> io::Result<()> {
+        let local_addr = self
+            .socket
+            .local_addr()
+            .unwrap_or_else(|_| SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0));
+        let capture = PacketCapture::new(writer, local_addr, payloads)?;
+        self.interceptor.get_or_insert_with(PacketInterceptor::default).set_capture(capture);
+        Ok(())
+    }
+
     pub fn addr(&self) -> io::Result<SocketAddr> {
         self.socket.local_addr()
     }
@@ -44,6 +115,21 @@ impl NetcodeClientTransport {
         self.netcode_client.time_since_last_received_packet()
     }
 
+    /// Returns the number of received packets discarded by netcode because they could not be decoded.
+    pub fn invalid_packets(&self) -> u64 {
+        self.netcode_client.invalid_packets()
+    }
+
+    /// Returns the values in effect for the connection, including the transport ones.
+    pub fn effective_config(&self, client: &RenetClient) -> EffectiveConfig {
+        EffectiveConfig {
+            timeout: self.netcode_client.timeout(),
+            keep_alive_interval: Some(self.netcode_client.keep_alive_interval()),
+            token_expire_timestamp: Some(self.netcode_client.token_expire_timestamp()),
+            ..client.effective_config()
+        }
+    }
+
     /// Disconnect the client from the transport layer.
     /// This sends the disconnect packet instantly, use this when closing/exiting games,
     /// should use [RenetClient::disconnect][crate::RenetClient::disconnect] otherwise.
@@ -54,7 +140,7 @@ impl NetcodeClientTransport {
 
         match self.netcode_client.disconnect() {
             Ok((addr, packet)) => {
-                if let Err(e) = self.socket.send_to(packet, addr) {
+                if let Err(e) = send_to(&mut self.socket, &mut self.interceptor, packet, addr) {
                     log::error!("Failed to send disconnect packet: {e}");
                 }
             }
@@ -74,17 +160,89 @@ impl NetcodeClientTransport {
             return Err(NetcodeError::Disconnected(reason).into());
         }
 
+        // Packets are kept in the connection until the netcode handshake is done
+        if self.netcode_client.is_connecting() {
+            return Ok(());
+        }
+
         let packets = connection.get_packets_to_send();
-        for packet in packets {
-            let (addr, payload) = self.netcode_client.generate_payload_packet(&packet)?;
-            self.socket.send_to(payload, addr)?;
+        self.send_payloads(connection, &packets)
+    }
+
+    fn send_payloads(&mut self, connection: &mut RenetClient, packets: &[Payload]) -> Result<(), NetcodeTransportError> {
+        for (i, packet) in packets.iter().enumerate() {
+            let (reason, error): (DropReason, NetcodeTransportError) = match self.netcode_client.generate_payload_packet(packet) {
+                Ok((addr, payload)) => {
+                    capture_payload(&mut self.interceptor, PacketDirection::Send, addr, packet);
+                    match send_to(&mut self.socket, &mut self.interceptor, payload, addr) {
+                        Ok(()) => {
+                            connection.record_wire_packet_sent(payload.len());
+                            continue;
+                        }
+                        Err(e) => (DropReason::Socket, e.into()),
+                    }
+                }
+                Err(e) => (DropReason::Encryption, e.into()),
+            };
+
+            // The remaining packets are dropped with the failed one
+            for packet in &packets[i..] {
+                connection.record_dropped_packet(reason, packet);
+            }
+            return Err(error);
         }
 
         Ok(())
     }
 
+    /// Processes a datagram received from the server, for transports created with [`NetcodeClientTransport::new_manual`].
+    /// Datagrams bigger than `NETCODE_MAX_PACKET_BYTES` are discarded.
+    pub fn process_incoming_packet(&mut self, packet: &[u8], client: &mut RenetClient) {
+        let addr = self.netcode_client.server_addr();
+        match self.buffer.copy_from(packet) {
+            Some(len) => self.process_received(addr, len, client),
+            None => {
+                if self.error_log.should_log(RepeatedError::OversizedPacket(addr)) {
+                    log::error!("Discarded packet from {addr}, it is bigger than a netcode packet");
+                }
+            }
+        }
+    }
+
+    /// Returns the encrypted datagrams to send to the server, for transports created with
+    /// [`NetcodeClientTransport::new_manual`]. Always empty for transports with a socket.
+    /// Datagrams are queued by [`NetcodeClientTransport::update`], [`NetcodeClientTransport::send_packets`]
+    /// and [`NetcodeClientTransport::disconnect`].
+    pub fn drain_outgoing_packets(&mut self) -> impl Iterator<Item = Bytes> + '_ {
+        self.socket.drain_outgoing().map(|(_, packet)| packet)
+    }
+
+    // Processes the datagram of the given length at the start of the receive buffer
+    fn process_received(&mut self, addr: SocketAddr, len: usize, client: &mut RenetClient) {
+        if addr != self.netcode_client.server_addr() {
+            log::debug!("Discarded packet from unknown server {:?}", addr);
+            return;
+        }
+
+        let Some(len) = intercept_received(&mut self.interceptor, &mut self.error_log, addr, self.buffer.space(), len) else {
+            return;
+        };
+        let mut datagram = self.buffer.take(len);
+
+        let (datagram_start, datagram_len) = (datagram.as_ptr(), datagram.len());
+        if let Some(payload) = self.netcode_client.process_packet(&mut datagram) {
+            capture_payload(&mut self.interceptor, PacketDirection::Receive, addr, payload);
+            let range = payload_range(datagram_start, datagram_len, payload);
+            client.record_wire_packet_received(datagram_len);
+            client.process_packet_bytes(shared_payload(datagram, range));
+        }
+    }
+
     /// Advances the transport by the duration, and receive packets from the network.
+    /// A zero duration is replaced by the one measured by the client, see `ConnectionConfig::zero_duration_fallback`.
     pub fn update(&mut self, duration: Duration, client: &mut RenetClient) -> Result<(), NetcodeTransportError> {
+        let duration = client.transport_update_duration(duration);
+        self.error_log.update_and_log(duration, log::Level::Error);
         if let Some(reason) = self.netcode_client.disconnect_reason() {
             // Mark the client as disconnected if an error occured in the transport layer
             client.disconnect_due_to_transport();
@@ -94,38 +252,36 @@ impl NetcodeClientTransport {
 
         if let Some(error) = client.disconnect_reason() {
             let (addr, disconnect_packet) = self.netcode_client.disconnect()?;
-            self.socket.send_to(disconnect_packet, addr)?;
+            send_to(&mut self.socket, &mut self.interceptor, disconnect_packet, addr)?;
             return Err(error.into());
         }
 
         if self.netcode_client.is_connected() {
             client.set_connected();
         } else if self.netcode_client.is_connecting() {
-            client.set_connecting();
+            match self.netcode_client.queued_position() {
+                Some(position) => client.set_queued(position),
+                None => client.set_connecting(),
+            }
         }
 
         loop {
-            let packet = match self.socket.recv_from(&mut self.buffer) {
-                Ok((len, addr)) => {
-                    if addr != self.netcode_client.server_addr() {
-                        log::debug!("Discarded packet from unknown server {:?}", addr);
-                        continue;
-                    }
-
-                    &mut self.buffer[..len]
-                }
+            match self.socket.recv_from(self.buffer.space()) {
+                Ok((len, addr)) => self.process_received(addr, len, client),
                 Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
                 Err(ref e) if e.kind() == io::ErrorKind::Interrupted => break,
                 Err(e) => return Err(NetcodeTransportError::IO(e)),
             };
-
-            if let Some(payload) = self.netcode_client.process_packet(packet) {
-                client.process_packet(payload);
-            }
         }
 
         if let Some((packet, addr)) = self.netcode_client.update(duration) {
-            self.socket.send_to(packet, addr)?;
+            send_to(&mut self.socket, &mut self.interceptor, packet, addr)?;
+        }
+
+        // Acks waiting for the next send_packets for too long are sent right away
+        if self.netcode_client.is_connected() {
+            let packets = client.get_ack_packets_to_send();
+            self.send_payloads(client, &packets)?;
         }
 
         Ok(())
@@ -0,0 +1,650 @@
+// Layout of the renet packets, the Wireshark dissector in renet/wireshark/renet.lua is generated from it.
+// The layouts are checked against packets serialized by the encoder, and the shipped dissector against
+// the generated one. Run the tests with RENET_UPDATE_DISSECTOR=1 to write the dissector again.
+use std::fmt::Write;
+
+use super::capture::{LINKTYPE_USER0, PAYLOAD_HEADER_BYTES, PAYLOAD_RECEIVED, PAYLOAD_SENT};
+use crate::packet::{
+    ACK_PACKET, CANCEL_RELIABLE_PACKET, CHANNEL_CONFIG_PACKET, PING_PACKET, PONG_PACKET, QUOTA_EXCEEDED_PACKET, RELIABLE_SLICE_PACKET,
+    SMALL_RELIABLE_PACKET, SMALL_UNRELIABLE_PACKET, TRANSFER_SLICE_PACKET, UNRELIABLE_SLICE_PACKET, V1_CHANNEL_CONFIG_PACKET,
+    V1_SMALL_RELIABLE_PACKET, V1_SMALL_UNRELIABLE_PACKET,
+};
+
+const DISSECTOR_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/wireshark/renet.lua");
+
+#[derive(Debug, Clone, Copy)]
+enum Field {
+    U8(&'static str),
+    U64(&'static str),
+    Varint(&'static str),
+    // Id of a reliable message, the first one of the packet is whole and the next ones
+    // are the zigzag encoded difference with the previous one
+    MessageId,
+    // Bytes prefixed with their varint length
+    Payload(&'static str),
+    // Flag byte, followed by a u8 when it is 1
+    OptionalU8 {
+        flag: &'static str,
+        value: &'static str,
+    },
+    // Varint number of items, followed by the fields of each item
+    Repeat {
+        count: &'static str,
+        item: &'static str,
+        fields: &'static [Field],
+    },
+    // End and size of the last acked range, the number of previous ranges and the gap and size of each one
+    AckRanges,
+}
+
+struct PacketLayout {
+    packet_type: u8,
+    name: &'static str,
+    fields: &'static [Field],
+}
+
+use Field::*;
+
+const LAYOUTS: &[PacketLayout] = &[
+    PacketLayout {
+        packet_type: RELIABLE_SLICE_PACKET,
+        name: "ReliableSlice",
+        fields: &[
+            Varint("sequence"),
+            U8("channel_id"),
+            Varint("message_id"),
+            Varint("slice_index"),
+            Varint("num_slices"),
+            Payload("slice"),
+        ],
+    },
+    PacketLayout {
+        packet_type: UNRELIABLE_SLICE_PACKET,
+        name: "UnreliableSlice",
+        fields: &[
+            Varint("sequence"),
+            U8("channel_id"),
+            Varint("message_id"),
+            Varint("slice_index"),
+            Varint("num_slices"),
+            Payload("slice"),
+        ],
+    },
+    PacketLayout {
+        packet_type: ACK_PACKET,
+        name: "Ack",
+        fields: &[Varint("sequence"), AckRanges],
+    },
+    PacketLayout {
+        packet_type: PING_PACKET,
+        name: "Ping",
+        fields: &[Varint("sequence"), Varint("ping_id")],
+    },
+    PacketLayout {
+        packet_type: PONG_PACKET,
+        name: "Pong",
+        fields: &[Varint("sequence"), Varint("ping_id")],
+    },
+    PacketLayout {
+        packet_type: TRANSFER_SLICE_PACKET,
+        name: "TransferSlice",
+        fields: &[Varint("sequence"), Varint("slice_index"), Varint("num_slices"), Payload("slice")],
+    },
+    PacketLayout {
+        packet_type: QUOTA_EXCEEDED_PACKET,
+        name: "QuotaExceeded",
+        fields: &[
+            Varint("sequence"),
+            OptionalU8 {
+                flag: "quota_kind",
+                value: "channel_id",
+            },
+        ],
+    },
+    PacketLayout {
+        packet_type: CANCEL_RELIABLE_PACKET,
+        name: "CancelReliable",
+        fields: &[Varint("sequence"), U8("channel_id"), Varint("message_id")],
+    },
+    PacketLayout {
+        packet_type: SMALL_RELIABLE_PACKET,
+        name: "SmallReliable",
+        fields: &[
+            Varint("sequence"),
+            U8("channel_id"),
+            Repeat {
+                count: "messages",
+                item: "Message",
+                fields: &[MessageId, Payload("message")],
+            },
+        ],
+    },
+    PacketLayout {
+        packet_type: SMALL_UNRELIABLE_PACKET,
+        name: "SmallUnreliable",
+        fields: &[
+            Varint("sequence"),
+            U8("channel_id"),
+            Repeat {
+                count: "messages",
+                item: "Message",
+                fields: &[Payload("message")],
+            },
+        ],
+    },
+    PacketLayout {
+        packet_type: CHANNEL_CONFIG_PACKET,
+        name: "ChannelConfig",
+        fields: &[
+            Varint("sequence"),
+            U8("protocol_version"),
+            Repeat {
+                count: "channels",
+                item: "Channel",
+                fields: &[
+                    U8("channel_id"),
+                    U8("send_type"),
+                    Varint("max_memory_usage_bytes"),
+                    OptionalU8 {
+                        flag: "has_group",
+                        value: "group",
+                    },
+                    U8("compression"),
+                    U64("compression_dictionary"),
+                    U8("tracing_context"),
+                    U8("message_kinds"),
+                    U8("deduplicate"),
+                ],
+            },
+        ],
+    },
+];
+
+const V1_PACKET_TYPES: [u8; 3] = [V1_SMALL_RELIABLE_PACKET, V1_SMALL_UNRELIABLE_PACKET, V1_CHANNEL_CONFIG_PACKET];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FieldType {
+    U8,
+    U64,
+    Varint,
+    Bytes,
+}
+
+// Fields of the dissector with their type, in the order they appear in the layouts.
+// The same name is the same field in every packet, it must have the same type.
+fn proto_fields() -> Vec<(&'static str, FieldType)> {
+    fn collect(fields: &[Field], out: &mut Vec<(&'static str, FieldType)>) {
+        for field in fields {
+            match *field {
+                U8(name) => push(out, name, FieldType::U8),
+                U64(name) => push(out, name, FieldType::U64),
+                Varint(name) => push(out, name, FieldType::Varint),
+                MessageId => push(out, "message_id", FieldType::Varint),
+                Payload(name) => {
+                    push(out, "length", FieldType::Varint);
+                    push(out, name, FieldType::Bytes);
+                }
+                OptionalU8 { flag, value } => {
+                    push(out, flag, FieldType::U8);
+                    push(out, value, FieldType::U8);
+                }
+                Repeat { count, fields, .. } => {
+                    push(out, count, FieldType::Varint);
+                    collect(fields, out);
+                }
+                AckRanges => {
+                    push(out, "ack_end", FieldType::Varint);
+                    push(out, "ack_size", FieldType::Varint);
+                    push(out, "ack_ranges", FieldType::Varint);
+                    push(out, "ack_gap", FieldType::Varint);
+                }
+            }
+        }
+    }
+
+    fn push(out: &mut Vec<(&'static str, FieldType)>, name: &'static str, field_type: FieldType) {
+        match out.iter().find(|(other, _)| *other == name) {
+            Some((_, other_type)) => assert_eq!(*other_type, field_type, "field {name} used with different types"),
+            None => out.push((name, field_type)),
+        }
+    }
+
+    let mut out = vec![];
+    for layout in LAYOUTS {
+        collect(layout.fields, &mut out);
+    }
+    out
+}
+
+// "channel_id" is shown as "Channel id"
+fn label(name: &str) -> String {
+    let name = name.replace('_', " ");
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => name,
+    }
+}
+
+fn generate_fields(out: &mut String, fields: &[Field], indent: usize) {
+    let pad = "    ".repeat(indent);
+    for field in fields {
+        match *field {
+            U8(name) => writeln!(out, "{pad}_, offset = add_u8(tvb, tree, offset, fields.{name})").unwrap(),
+            U64(name) => writeln!(out, "{pad}offset = add_u64(tvb, tree, offset, fields.{name})").unwrap(),
+            Varint(name) => writeln!(out, "{pad}_, offset = add_varint(tvb, tree, offset, fields.{name})").unwrap(),
+            MessageId => writeln!(out, "{pad}previous_id, offset = add_message_id(tvb, tree, offset, previous_id)").unwrap(),
+            Payload(name) => writeln!(out, "{pad}offset = add_payload(tvb, tree, offset, fields.{name})").unwrap(),
+            OptionalU8 { flag, value } => {
+                writeln!(out, "{pad}local {flag}").unwrap();
+                writeln!(out, "{pad}{flag}, offset = add_u8(tvb, tree, offset, fields.{flag})").unwrap();
+                writeln!(out, "{pad}if {flag} == 1 then").unwrap();
+                writeln!(out, "{pad}    _, offset = add_u8(tvb, tree, offset, fields.{value})").unwrap();
+                writeln!(out, "{pad}end").unwrap();
+            }
+            Repeat { count, item, fields } => {
+                writeln!(out, "{pad}local {count}").unwrap();
+                writeln!(out, "{pad}{count}, offset = add_varint(tvb, tree, offset, fields.{count})").unwrap();
+                writeln!(out, "{pad}local parent, previous_id = tree, nil").unwrap();
+                writeln!(out, "{pad}for i = 1, {count} do").unwrap();
+                writeln!(out, "{pad}    local start = offset").unwrap();
+                writeln!(out, "{pad}    local tree = parent:add(renet, tvb(offset), \"{item} \" .. i)").unwrap();
+                generate_fields(out, fields, indent + 1);
+                writeln!(out, "{pad}    tree:set_len(offset - start)").unwrap();
+                writeln!(out, "{pad}end").unwrap();
+            }
+            AckRanges => writeln!(out, "{pad}offset = add_ack_ranges(tvb, tree, offset)").unwrap(),
+        }
+    }
+}
+
+fn generate() -> String {
+    let mut out = String::new();
+    out.push_str(
+        "-- Generated by renet/src/transport/dissector.rs, do not edit.
+-- Wireshark dissector for the decrypted renet packets written by `set_packet_capture_with_payloads`,
+-- available with the `capture_decrypted` feature. Copy it to the personal Lua plugins folder of Wireshark,
+-- shown in Help > About Wireshark > Folders.
+
+local renet = Proto(\"renet\", \"Renet\")
+
+",
+    );
+
+    writeln!(out, "local packet_types = {{").unwrap();
+    let mut packet_types: Vec<(u8, &str)> = LAYOUTS.iter().map(|layout| (layout.packet_type, layout.name)).collect();
+    packet_types.extend(V1_PACKET_TYPES.iter().map(|&packet_type| (packet_type, "Protocol version 1")));
+    packet_types.sort();
+    for (packet_type, name) in packet_types {
+        writeln!(out, "    [{packet_type}] = \"{name}\",").unwrap();
+    }
+    writeln!(out, "}}\n").unwrap();
+
+    writeln!(out, "local fields = {{").unwrap();
+    writeln!(
+        out,
+        "    direction = ProtoField.uint8(\"renet.direction\", \"Direction\", base.DEC, {{ [{PAYLOAD_SENT}] = \"Sent\", [{PAYLOAD_RECEIVED}] = \"Received\" }}),"
+    )
+    .unwrap();
+    writeln!(out, "    peer = ProtoField.ipv6(\"renet.peer\", \"Peer\"),").unwrap();
+    writeln!(out, "    peer_port = ProtoField.uint16(\"renet.peer_port\", \"Peer port\"),").unwrap();
+    writeln!(
+        out,
+        "    packet_type = ProtoField.uint8(\"renet.packet_type\", \"Packet type\", base.DEC, packet_types),"
+    )
+    .unwrap();
+    writeln!(out, "    ack_range = ProtoField.string(\"renet.ack_range\", \"Acked range\"),").unwrap();
+    for (name, field_type) in proto_fields() {
+        let constructor = match field_type {
+            FieldType::U8 => "uint8",
+            FieldType::U64 => "uint64",
+            FieldType::Varint => "uint64",
+            FieldType::Bytes => "bytes",
+        };
+        writeln!(out, "    {name} = ProtoField.{constructor}(\"renet.{name}\", \"{}\"),", label(name)).unwrap();
+    }
+    writeln!(out, "}}\n").unwrap();
+
+    out.push_str(
+        "local field_list = {}
+for _, field in pairs(fields) do
+    table.insert(field_list, field)
+end
+renet.fields = field_list
+
+-- The two high bits of the first byte give the length of the varint: 1, 2, 4 or 8 bytes
+local function varint(tvb, offset)
+    local first = tvb(offset, 1):uint()
+    local len = ({ 1, 2, 4, 8 })[math.floor(first / 64) + 1]
+    local value = first % 64
+    for i = 1, len - 1 do
+        value = value * 256 + tvb(offset + i, 1):uint()
+    end
+    return value, len
+end
+
+local function zigzag(value)
+    if value % 2 == 0 then
+        return value / 2
+    end
+    return -(value + 1) / 2
+end
+
+local function add_u8(tvb, tree, offset, field)
+    tree:add(field, tvb(offset, 1))
+    return tvb(offset, 1):uint(), offset + 1
+end
+
+local function add_u64(tvb, tree, offset, field)
+    tree:add(field, tvb(offset, 8))
+    return offset + 8
+end
+
+local function add_varint(tvb, tree, offset, field)
+    local value, len = varint(tvb, offset)
+    tree:add(field, tvb(offset, len), value)
+    return value, offset + len
+end
+
+local function add_payload(tvb, tree, offset, field)
+    local len
+    len, offset = add_varint(tvb, tree, offset, fields.length)
+    if len > 0 then
+        tree:add(field, tvb(offset, len))
+    end
+    return offset + len
+end
+
+local function add_message_id(tvb, tree, offset, previous_id)
+    local id, len = varint(tvb, offset)
+    if previous_id ~= nil then
+        id = previous_id + zigzag(id)
+    end
+    tree:add(fields.message_id, tvb(offset, len), id)
+    return id, offset + len
+end
+
+local function add_ack_ranges(tvb, tree, offset)
+    local start = offset
+    local range_end, range_size, ranges
+    range_end, offset = add_varint(tvb, tree, offset, fields.ack_end)
+    range_size, offset = add_varint(tvb, tree, offset, fields.ack_size)
+    ranges, offset = add_varint(tvb, tree, offset, fields.ack_ranges)
+    local range_start = range_end - range_size
+    tree:add(fields.ack_range, tvb(start, offset - start), range_start .. \"..=\" .. range_end)
+    for _ = 1, ranges do
+        local gap, size
+        start = offset
+        gap, offset = add_varint(tvb, tree, offset, fields.ack_gap)
+        size, offset = add_varint(tvb, tree, offset, fields.ack_size)
+        range_end = range_start - gap - 2
+        range_start = range_end - size
+        tree:add(fields.ack_range, tvb(start, offset - start), range_start .. \"..=\" .. range_end)
+    end
+    return offset
+end
+
+local dissectors = {}
+
+",
+    );
+
+    for layout in LAYOUTS {
+        writeln!(out, "-- {}", layout.name).unwrap();
+        writeln!(out, "dissectors[{}] = function(tvb, tree, offset)", layout.packet_type).unwrap();
+        generate_fields(&mut out, layout.fields, 1);
+        writeln!(out, "    return offset\nend\n").unwrap();
+    }
+
+    let header_bytes = PAYLOAD_HEADER_BYTES;
+    writeln!(
+        out,
+        "function renet.dissector(tvb, pinfo, tree)
+    if tvb:len() <= {header_bytes} then
+        return 0
+    end
+
+    pinfo.cols.protocol = \"RENET\"
+    local tree = tree:add(renet, tvb())
+    tree:add(fields.direction, tvb(0, 1))
+    tree:add(fields.peer, tvb(1, 16))
+    tree:add(fields.peer_port, tvb(17, 2))
+    local packet_type = tvb({header_bytes}, 1):uint()
+    tree:add(fields.packet_type, tvb({header_bytes}, 1))
+    pinfo.cols.info = packet_types[packet_type] or \"Unknown\"
+    local dissect = dissectors[packet_type]
+    if dissect ~= nil then
+        dissect(tvb, tree, {})
+    end
+    return tvb:len()
+end
+
+-- Link type {LINKTYPE_USER0}, LINKTYPE_USER0
+DissectorTable.get(\"wtap_encap\"):add((wtap_encaps or wtap).USER0, renet)",
+        header_bytes + 1
+    )
+    .unwrap();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Range;
+
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::packet::{ChannelDescriptor, Packet, Slice};
+    use crate::QuotaKind;
+
+    // Reads the packet as the dissector does, returns the values of the fields and the bytes read.
+    // Payloads are read as their length.
+    fn walk(packet: &[u8]) -> (Vec<(&'static str, u64)>, usize) {
+        // The message ids are relative to the previous one of the same repeated items
+        fn walk_fields(fields: &[Field], b: &mut octets::Octets, values: &mut Vec<(&'static str, u64)>, previous_id: &mut Option<u64>) {
+            for field in fields {
+                match *field {
+                    U8(name) => values.push((name, b.get_u8().unwrap() as u64)),
+                    U64(name) => values.push((name, b.get_u64().unwrap())),
+                    Varint(name) => values.push((name, b.get_varint().unwrap())),
+                    MessageId => {
+                        let value = b.get_varint().unwrap();
+                        let id = match *previous_id {
+                            None => value,
+                            Some(previous_id) => previous_id.wrapping_add(((value >> 1) as i64 ^ -((value & 1) as i64)) as u64),
+                        };
+                        *previous_id = Some(id);
+                        values.push(("message_id", id));
+                    }
+                    Payload(name) => values.push((name, b.get_bytes_with_varint_length().unwrap().len() as u64)),
+                    OptionalU8 { flag, value } => {
+                        let flag_value = b.get_u8().unwrap();
+                        values.push((flag, flag_value as u64));
+                        if flag_value == 1 {
+                            values.push((value, b.get_u8().unwrap() as u64));
+                        }
+                    }
+                    Repeat { count, fields, .. } => {
+                        let items = b.get_varint().unwrap();
+                        values.push((count, items));
+                        let mut previous_id = None;
+                        for _ in 0..items {
+                            walk_fields(fields, b, values, &mut previous_id);
+                        }
+                    }
+                    AckRanges => {
+                        let mut range_end = b.get_varint().unwrap();
+                        let mut range_start = range_end - b.get_varint().unwrap();
+                        values.push(("ack_range_start", range_start));
+                        values.push(("ack_range_end", range_end));
+                        for _ in 0..b.get_varint().unwrap() {
+                            let gap = b.get_varint().unwrap();
+                            range_end = range_start - gap - 2;
+                            range_start = range_end - b.get_varint().unwrap();
+                            values.push(("ack_range_start", range_start));
+                            values.push(("ack_range_end", range_end));
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut b = octets::Octets::with_slice(packet);
+        let packet_type = b.get_u8().unwrap();
+        let layout = LAYOUTS.iter().find(|layout| layout.packet_type == packet_type).unwrap();
+        let mut values = vec![];
+        walk_fields(layout.fields, &mut b, &mut values, &mut None);
+        (values, b.off())
+    }
+
+    fn values_of(values: &[(&'static str, u64)], name: &str) -> Vec<u64> {
+        values.iter().filter(|(other, _)| *other == name).map(|&(_, value)| value).collect()
+    }
+
+    fn slice(message_id: u64) -> Slice {
+        Slice {
+            message_id,
+            slice_index: 2,
+            num_slices: 3,
+            payload: Bytes::from(vec![7; 100]),
+        }
+    }
+
+    #[test]
+    fn layouts_match_encoder() {
+        let ack_ranges: Vec<Range<u64>> = vec![3..5, 10..12, 20000..20010];
+        let packets = vec![
+            Packet::ReliableSlice {
+                sequence: 300,
+                channel_id: 1,
+                slice: slice(70_000),
+            },
+            Packet::UnreliableSlice {
+                sequence: 301,
+                channel_id: 2,
+                slice: slice(5),
+            },
+            Packet::Ack {
+                sequence: 302,
+                ack_ranges: ack_ranges.clone(),
+            },
+            Packet::Ping { sequence: 303, ping_id: 9 },
+            Packet::Pong { sequence: 304, ping_id: 9 },
+            Packet::TransferSlice {
+                sequence: 305,
+                slice: slice(0),
+            },
+            Packet::QuotaExceeded {
+                sequence: 306,
+                kind: QuotaKind::ReceivedMessages { channel_id: 4 },
+            },
+            Packet::CancelReliable {
+                sequence: 307,
+                channel_id: 1,
+                message_id: 12,
+            },
+            Packet::SmallReliable {
+                sequence: 308,
+                channel_id: 0,
+                messages: vec![(100, Bytes::from("a")), (102, Bytes::from("bc")), (101, Bytes::new())],
+            },
+            Packet::SmallUnreliable {
+                sequence: 309,
+                channel_id: 3,
+                messages: vec![Bytes::from("a"), Bytes::from(vec![0; 300])],
+            },
+            Packet::ChannelConfig {
+                sequence: 310,
+                protocol_version: crate::packet::PROTOCOL_VERSION,
+                channels: vec![
+                    ChannelDescriptor {
+                        channel_id: 0,
+                        send_type: 1,
+                        max_memory_usage_bytes: 5 * 1024 * 1024,
+                        group: Some(2),
+                        compression: 1,
+                        compression_dictionary: u64::MAX,
+                        tracing_context: true,
+                        message_kinds: false,
+                        deduplicate: true,
+                    },
+                    ChannelDescriptor {
+                        channel_id: 1,
+                        send_type: 0,
+                        max_memory_usage_bytes: 1000,
+                        group: None,
+                        compression: 0,
+                        compression_dictionary: 0,
+                        tracing_context: false,
+                        message_kinds: true,
+                        deduplicate: false,
+                    },
+                ],
+            },
+        ];
+
+        let mut packet_types = vec![];
+        for packet in packets.iter() {
+            let mut buffer = [0u8; 1400];
+            let len = packet.to_bytes(&mut octets::OctetsMut::with_slice(&mut buffer)).unwrap();
+            let (values, read) = walk(&buffer[..len]);
+            assert_eq!(read, len, "layout of {packet:?} doesn't read the whole packet");
+            assert_eq!(values_of(&values, "sequence"), vec![packet.sequence()]);
+            packet_types.push(buffer[0]);
+
+            match packet {
+                Packet::ReliableSlice { channel_id, slice, .. } | Packet::UnreliableSlice { channel_id, slice, .. } => {
+                    assert_eq!(values_of(&values, "channel_id"), vec![*channel_id as u64]);
+                    assert_eq!(values_of(&values, "message_id"), vec![slice.message_id]);
+                    assert_eq!(values_of(&values, "num_slices"), vec![slice.num_slices as u64]);
+                    assert_eq!(values_of(&values, "slice"), vec![slice.payload.len() as u64]);
+                }
+                Packet::Ack { ack_ranges, .. } => {
+                    let mut starts = values_of(&values, "ack_range_start");
+                    let mut ends = values_of(&values, "ack_range_end");
+                    starts.reverse();
+                    ends.reverse();
+                    let ranges: Vec<Range<u64>> = starts.into_iter().zip(ends).map(|(start, end)| start..end + 1).collect();
+                    assert_eq!(&ranges, ack_ranges);
+                }
+                Packet::QuotaExceeded { .. } => assert_eq!(values_of(&values, "channel_id"), vec![4]),
+                Packet::SmallReliable { messages, .. } => {
+                    let ids: Vec<u64> = messages.iter().map(|(id, _)| *id).collect();
+                    assert_eq!(values_of(&values, "message_id"), ids);
+                    assert_eq!(values_of(&values, "message"), vec![1, 2, 0]);
+                }
+                Packet::SmallUnreliable { .. } => assert_eq!(values_of(&values, "message"), vec![1, 300]),
+                Packet::ChannelConfig { channels, .. } => {
+                    assert_eq!(values_of(&values, "channel_id"), vec![0, 1]);
+                    assert_eq!(values_of(&values, "group"), vec![2]);
+                    assert_eq!(
+                        values_of(&values, "compression_dictionary"),
+                        vec![channels[0].compression_dictionary, 0]
+                    );
+                    assert_eq!(values_of(&values, "deduplicate"), vec![1, 0]);
+                }
+                _ => {}
+            }
+        }
+
+        // Every packet type has a layout, and every layout is checked
+        packet_types.sort();
+        let mut layout_types: Vec<u8> = LAYOUTS.iter().map(|layout| layout.packet_type).collect();
+        layout_types.sort();
+        assert_eq!(packet_types, layout_types);
+    }
+
+    #[test]
+    fn dissector_is_up_to_date() {
+        let generated = generate();
+        if std::env::var_os("RENET_UPDATE_DISSECTOR").is_some() {
+            std::fs::write(DISSECTOR_PATH, &generated).unwrap();
+        }
+
+        let shipped = std::fs::read_to_string(DISSECTOR_PATH).unwrap_or_default();
+        assert!(
+            shipped == generated,
+            "{DISSECTOR_PATH} is out of date, run the tests with RENET_UPDATE_DISSECTOR=1 to generate it"
+        );
+    }
+}
@@ -1,16 +1,309 @@
-use std::{error::Error, fmt};
+use std::{
+    collections::VecDeque,
+    error::Error,
+    fmt, io,
+    net::{SocketAddr, UdpSocket},
+    ops::Range,
+};
+
+use bytes::{Bytes, BytesMut};
+use renetcode::NETCODE_MAX_PACKET_BYTES;
+
+use crate::{log_limiter::LogLimiter, ClientId};
 
+use capture::PacketCapture;
+
+mod capture;
 mod client;
+#[cfg(test)]
+mod dissector;
+mod multiplexer;
+mod self_test;
 mod server;
+mod swarm;
 
 pub use client::*;
+pub use multiplexer::ServerMultiplexer;
+pub use self_test::{SelfTestConfig, SelfTestError, SelfTestReport};
 pub use server::*;
+pub use swarm::{ClientSwarm, SwarmReport};
 
 pub use renetcode::{
-    generate_random_bytes, ClientAuthentication, ConnectToken, DisconnectReason as NetcodeDisconnectReason, NetcodeError,
-    ServerAuthentication, ServerConfig, TokenGenerationError, NETCODE_KEY_BYTES, NETCODE_USER_DATA_BYTES,
+    generate_random_bytes, ClientAuthentication, ConnectQueueConfig, ConnectToken, DisconnectReason as NetcodeDisconnectReason,
+    NetcodeError, ServerAuthentication, ServerConfig, TokenClaims, TokenGenerationError, NETCODE_KEY_BYTES, NETCODE_USER_DATA_BYTES,
 };
 
+/// Direction of a datagram passing through a packet interceptor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketDirection {
+    /// Datagram about to be sent to the socket, already encrypted by netcode.
+    Send,
+    /// Datagram just read from the socket, before netcode decrypts it.
+    Receive,
+}
+
+/// What to do with a datagram after it passed through a packet interceptor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterceptAction {
+    /// Send or process the (possibly modified) datagram.
+    Pass,
+    /// Discard the datagram, for the rest of the stack this is the same as a lost packet.
+    Drop,
+}
+
+type InterceptorFn = dyn FnMut(PacketDirection, SocketAddr, &mut Vec<u8>) -> InterceptAction + Send + Sync;
+
+// Final transformation and capture of datagrams at the socket boundary.
+#[derive(Default)]
+pub(crate) struct PacketInterceptor {
+    interceptor: Option<Box<InterceptorFn>>,
+    capture: Option<PacketCapture>,
+}
+
+impl fmt::Debug for PacketInterceptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PacketInterceptor")
+    }
+}
+
+impl PacketInterceptor {
+    pub(crate) fn set_interceptor<F>(&mut self, interceptor: F)
+    where
+        F: FnMut(PacketDirection, SocketAddr, &mut Vec<u8>) -> InterceptAction + Send + Sync + 'static,
+    {
+        self.interceptor = Some(Box::new(interceptor));
+    }
+
+    pub(crate) fn set_capture(&mut self, capture: PacketCapture) {
+        self.capture = Some(capture);
+    }
+
+    // A capture that fails to write is stopped, the transport keeps working without it
+    fn capture(&mut self, direction: PacketDirection, addr: SocketAddr, datagram: &[u8]) {
+        let Some(capture) = &mut self.capture else {
+            return;
+        };
+
+        let result = match direction {
+            PacketDirection::Send => capture.sent(addr, datagram),
+            PacketDirection::Receive => capture.received(addr, datagram),
+        };
+        if let Err(e) = result {
+            log::error!("Stopped capturing packets, failed to write the capture: {e}");
+            self.capture = None;
+        }
+    }
+
+    // Decrypted payloads are only captured when enabled with the capture_decrypted feature
+    fn capture_payload(&mut self, direction: PacketDirection, addr: SocketAddr, payload: &[u8]) {
+        let Some(capture) = self.capture.as_mut().filter(|capture| capture.has_payloads()) else {
+            return;
+        };
+
+        let result = match direction {
+            PacketDirection::Send => capture.payload_sent(addr, payload),
+            PacketDirection::Receive => capture.payload_received(addr, payload),
+        };
+        if let Err(e) = result {
+            log::error!("Stopped capturing packets, failed to write the capture: {e}");
+            self.capture = None;
+        }
+    }
+}
+
+// Where a transport sends and receives its datagrams.
+// In manual mode there is no socket: sent datagrams are queued until drained by the application,
+// and received datagrams are passed by it.
+#[derive(Debug)]
+pub(crate) enum TransportSocket {
+    Udp(UdpSocket),
+    Manual(VecDeque<(SocketAddr, Bytes)>),
+}
+
+impl TransportSocket {
+    pub(crate) fn manual() -> Self {
+        Self::Manual(VecDeque::new())
+    }
+
+    fn send_to(&mut self, packet: &[u8], addr: SocketAddr) -> io::Result<()> {
+        match self {
+            Self::Udp(socket) => socket.send_to(packet, addr).map(|_| ()),
+            Self::Manual(outgoing) => {
+                outgoing.push_back((addr, Bytes::copy_from_slice(packet)));
+                Ok(())
+            }
+        }
+    }
+
+    // Manual transports never have datagrams to read, they are passed by the application
+    pub(crate) fn recv_from(&self, buffer: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        match self {
+            Self::Udp(socket) => socket.recv_from(buffer),
+            Self::Manual(_) => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+
+    pub(crate) fn local_addr(&self) -> io::Result<SocketAddr> {
+        match self {
+            Self::Udp(socket) => socket.local_addr(),
+            Self::Manual(_) => Err(io::Error::new(io::ErrorKind::Unsupported, "manual transports have no socket")),
+        }
+    }
+
+    pub(crate) fn drain_outgoing(&mut self) -> impl Iterator<Item = (SocketAddr, Bytes)> + '_ {
+        let outgoing = match self {
+            Self::Udp(_) => None,
+            Self::Manual(outgoing) => Some(outgoing.drain(..)),
+        };
+
+        outgoing.into_iter().flatten()
+    }
+}
+
+// Transport errors that can repeat for every packet, their logs are rate limited for each address or client
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub(crate) enum RepeatedError {
+    OversizedPacket(SocketAddr),
+    OversizedInterceptedPacket(SocketAddr),
+    SendPacket(SocketAddr),
+    EncryptPacket(ClientId),
+    ProcessPayload(ClientId),
+}
+
+impl fmt::Display for RepeatedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use RepeatedError::*;
+
+        match *self {
+            OversizedPacket(addr) => write!(f, "discarded packet from {addr}, it is bigger than a netcode packet"),
+            OversizedInterceptedPacket(addr) => write!(
+                f,
+                "discarded packet from {addr}, intercepted packet is bigger than the receive buffer"
+            ),
+            SendPacket(addr) => write!(f, "failed to send packet to {addr}"),
+            EncryptPacket(client_id) => write!(f, "failed to encrypt payload packet for client {client_id}"),
+            ProcessPayload(client_id) => write!(f, "failed to process payload for client {client_id}"),
+        }
+    }
+}
+
+pub(crate) type ErrorLog = LogLimiter<RepeatedError>;
+
+pub(crate) fn send_to(
+    socket: &mut TransportSocket,
+    interceptor: &mut Option<PacketInterceptor>,
+    packet: &[u8],
+    addr: SocketAddr,
+) -> io::Result<()> {
+    let Some(interceptor) = interceptor else {
+        return socket.send_to(packet, addr);
+    };
+
+    let intercepted;
+    let mut packet = packet;
+    if let Some(intercept) = &mut interceptor.interceptor {
+        let mut intercepted_packet = packet.to_vec();
+        if intercept(PacketDirection::Send, addr, &mut intercepted_packet) == InterceptAction::Drop {
+            return Ok(());
+        }
+        intercepted = intercepted_packet;
+        packet = &intercepted;
+    }
+    // The capture sees the datagram as it is sent
+    interceptor.capture(PacketDirection::Send, addr, packet);
+
+    socket.send_to(packet, addr)
+}
+
+// Passes the renet packet, before it is encrypted or after it was decrypted, to the capture
+pub(crate) fn capture_payload(interceptor: &mut Option<PacketInterceptor>, direction: PacketDirection, addr: SocketAddr, payload: &[u8]) {
+    if let Some(interceptor) = interceptor {
+        interceptor.capture_payload(direction, addr, payload);
+    }
+}
+
+// Buffer for received datagrams, each datagram is split from it so its decrypted
+// payload can be passed to the connection as Bytes without copying it.
+// Received messages keep the memory of the buffer alive until they are dropped.
+#[derive(Debug)]
+pub(crate) struct ReceiveBuffer(BytesMut);
+
+impl ReceiveBuffer {
+    pub(crate) fn new() -> Self {
+        Self(BytesMut::with_capacity(NETCODE_MAX_PACKET_BYTES * 16))
+    }
+
+    // Copies a datagram passed by the application, returns None if it is bigger than a netcode packet
+    pub(crate) fn copy_from(&mut self, datagram: &[u8]) -> Option<usize> {
+        let space = self.space();
+        if datagram.len() > space.len() {
+            return None;
+        }
+
+        space[..datagram.len()].copy_from_slice(datagram);
+        Some(datagram.len())
+    }
+
+    // Returns room for a full datagram
+    pub(crate) fn space(&mut self) -> &mut [u8] {
+        self.0.resize(NETCODE_MAX_PACKET_BYTES, 0);
+        &mut self.0[..]
+    }
+
+    // Splits the first `len` bytes of the buffer as a received datagram
+    pub(crate) fn take(&mut self, len: usize) -> BytesMut {
+        self.0.split_to(len)
+    }
+}
+
+// Returns the payload decrypted in place as a slice of the datagram.
+pub(crate) fn shared_payload(datagram: BytesMut, payload_range: Range<usize>) -> Bytes {
+    datagram.freeze().slice(payload_range)
+}
+
+// Range of the payload inside of the datagram it was decrypted from,
+// the start and length of the datagram are taken before it is borrowed by netcode.
+pub(crate) fn payload_range(datagram_start: *const u8, datagram_len: usize, payload: &[u8]) -> Range<usize> {
+    let start = payload.as_ptr() as usize - datagram_start as usize;
+    debug_assert!(start + payload.len() <= datagram_len);
+    start..start + payload.len()
+}
+
+// Passes the received datagram in the buffer through the capture and the interceptor,
+// returns the new length of the datagram or None if it should be discarded.
+pub(crate) fn intercept_received(
+    interceptor: &mut Option<PacketInterceptor>,
+    error_log: &mut ErrorLog,
+    addr: SocketAddr,
+    buffer: &mut [u8],
+    len: usize,
+) -> Option<usize> {
+    let Some(interceptor) = interceptor else {
+        return Some(len);
+    };
+
+    // The capture sees the datagram as it was received
+    interceptor.capture(PacketDirection::Receive, addr, &buffer[..len]);
+    let Some(intercept) = &mut interceptor.interceptor else {
+        return Some(len);
+    };
+
+    let mut packet = buffer[..len].to_vec();
+    if intercept(PacketDirection::Receive, addr, &mut packet) == InterceptAction::Drop {
+        return None;
+    }
+
+    if packet.len() > buffer.len() {
+        if error_log.should_log(RepeatedError::OversizedInterceptedPacket(addr)) {
+            log::error!("Discarded packet from {addr}, intercepted packet is bigger than the receive buffer");
+        }
+        return None;
+    }
+
+    buffer[..packet.len()].copy_from_slice(&packet);
+    Some(packet.len())
+}
+
 #[derive(Debug)]
 #[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Event))]
 pub enum NetcodeTransportError {
@@ -54,3 +347,299 @@ impl From<std::io::Error> for NetcodeTransportError {
         NetcodeTransportError::IO(inner)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+        time::Duration,
+    };
+
+    use super::*;
+    use crate::{ClientId, ConnectionConfig, DefaultChannel, RenetClient, RenetServer};
+
+    const TRAILER: &[u8] = b"TRAILER";
+
+    // Appends a trailer to sent packets, only accepts received packets with the trailer
+    fn trailer_interceptor(verified: Arc<AtomicUsize>) -> impl FnMut(PacketDirection, SocketAddr, &mut Vec<u8>) -> InterceptAction {
+        move |direction, _, packet| match direction {
+            PacketDirection::Send => {
+                packet.extend_from_slice(TRAILER);
+                InterceptAction::Pass
+            }
+            PacketDirection::Receive => {
+                if !packet.ends_with(TRAILER) {
+                    return InterceptAction::Drop;
+                }
+                packet.truncate(packet.len() - TRAILER.len());
+                verified.fetch_add(1, Ordering::Relaxed);
+                InterceptAction::Pass
+            }
+        }
+    }
+
+    // Capture writer that can be read while the transport owns it
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn packet_interceptor_trailer() {
+        let server_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let server_addr = server_socket.local_addr().unwrap();
+        let server_config = ServerConfig {
+            current_time: Duration::ZERO,
+            max_clients: 4,
+            protocol_id: 0,
+            public_addresses: vec![server_addr],
+            authentication: ServerAuthentication::Unsecure,
+        };
+        let mut server_transport = NetcodeServerTransport::new(server_config, server_socket).unwrap();
+        let mut server = RenetServer::new(ConnectionConfig::default());
+
+        let client_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let authentication = ClientAuthentication::Unsecure {
+            protocol_id: 0,
+            client_id: 1,
+            server_addr,
+            user_data: None,
+        };
+        let mut client_transport = NetcodeClientTransport::new(Duration::ZERO, authentication, client_socket).unwrap();
+        let mut client = RenetClient::new(ConnectionConfig::default());
+
+        let server_verified = Arc::new(AtomicUsize::new(0));
+        let client_verified = Arc::new(AtomicUsize::new(0));
+        server_transport.set_packet_interceptor(trailer_interceptor(server_verified.clone()));
+        client_transport.set_packet_interceptor(trailer_interceptor(client_verified.clone()));
+        let capture = SharedBuffer::default();
+        client_transport.set_packet_capture(capture.clone()).unwrap();
+
+        let delta = Duration::from_millis(16);
+        let mut received = None;
+        for _ in 0..100 {
+            client.update(delta);
+            client_transport.update(delta, &mut client).unwrap();
+            server.update(delta);
+            server_transport.update(delta, &mut server).unwrap();
+
+            if client.is_connected() {
+                client.send_message(DefaultChannel::ReliableOrdered, "intercepted");
+            }
+            for client_id in server.clients_id() {
+                if let Some(message) = server.receive_message(client_id, DefaultChannel::ReliableOrdered) {
+                    received = Some(message);
+                }
+            }
+            if received.is_some() {
+                break;
+            }
+
+            client_transport.send_packets(&mut client).unwrap();
+            server_transport.send_packets(&mut server);
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        assert_eq!(received.unwrap(), "intercepted");
+        assert!(server_verified.load(Ordering::Relaxed) > 0);
+        assert!(client_verified.load(Ordering::Relaxed) > 0);
+
+        // The capture has the datagrams as they are on the socket, with the trailer
+        let capture = capture.0.lock().unwrap();
+        let trailers = capture.windows(TRAILER.len()).filter(|window| *window == TRAILER).count();
+        assert!(trailers > client_verified.load(Ordering::Relaxed));
+    }
+
+    fn self_test_server(max_clients: usize, public_addr: Option<SocketAddr>) -> (NetcodeServerTransport, RenetServer) {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let server_config = ServerConfig {
+            current_time: Duration::ZERO,
+            max_clients,
+            protocol_id: 7,
+            public_addresses: vec![public_addr.unwrap_or(socket.local_addr().unwrap())],
+            authentication: ServerAuthentication::Secure {
+                private_key: *b"an example very very secret key.",
+            },
+        };
+        let transport = NetcodeServerTransport::new(server_config, socket).unwrap();
+        (transport, RenetServer::new(ConnectionConfig::default()))
+    }
+
+    fn short_self_test() -> SelfTestConfig {
+        SelfTestConfig {
+            step_timeout: Duration::from_millis(300),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn self_test() {
+        let (mut transport, mut server) = self_test_server(4, None);
+        transport.self_test(&mut server, SelfTestConfig::default()).unwrap();
+
+        // The test client is not visible to the application
+        assert!(!server.has_connections());
+        assert_eq!(server.get_event(), None);
+        assert_eq!(transport.connected_clients(), 0);
+    }
+
+    #[test]
+    fn self_test_failures() {
+        // Public address is not where the server is listening
+        let (mut transport, mut server) = self_test_server(4, Some("127.0.0.1:1".parse().unwrap()));
+        let result = transport.self_test(&mut server, short_self_test());
+        assert!(matches!(result, Err(SelfTestError::HandshakeTimedOut)));
+
+        let (mut transport, mut server) = self_test_server(0, None);
+        let result = transport.self_test(&mut server, short_self_test());
+        assert!(matches!(
+            result,
+            Err(SelfTestError::Disconnected(NetcodeDisconnectReason::ConnectionDenied))
+        ));
+
+        // Token service using a different protocol id
+        let (mut transport, mut server) = self_test_server(4, None);
+        let connect_token = ConnectToken::generate(
+            Duration::ZERO,
+            8,
+            30,
+            1,
+            5,
+            transport.addresses(),
+            None,
+            b"an example very very secret key.",
+        )
+        .unwrap();
+        let config = SelfTestConfig {
+            connect_token: Some(connect_token),
+            ..short_self_test()
+        };
+        let result = transport.self_test(&mut server, config);
+        assert!(matches!(result, Err(SelfTestError::HandshakeTimedOut)));
+        assert_eq!(server.get_event(), None);
+    }
+
+    #[test]
+    fn ignore_foreign_packets() {
+        let (mut transport, mut server) = self_test_server(4, None);
+        transport.set_ignore_foreign_packets(true);
+        assert!(!transport.packet_looks_like_renet(b"legacy protocol datagram"));
+
+        let legacy_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        legacy_socket
+            .send_to(b"legacy protocol datagram", transport.addresses()[0])
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        transport.update(Duration::ZERO, &mut server).unwrap();
+        assert_eq!(transport.foreign_packets_ignored(), 1);
+
+        // Renet packets are still processed
+        transport.self_test(&mut server, SelfTestConfig::default()).unwrap();
+        assert_eq!(transport.foreign_packets_ignored(), 1);
+    }
+
+    #[test]
+    fn update_with_budget() {
+        let (mut transport, mut server) = self_test_server(4, None);
+        transport.set_ignore_foreign_packets(true);
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        for _ in 0..10 {
+            socket.send_to(b"foreign datagram", transport.addresses()[0]).unwrap();
+        }
+        std::thread::sleep(Duration::from_millis(20));
+
+        assert_eq!(transport.update_with_budget(Duration::ZERO, &mut server, 3).unwrap(), 7);
+        assert_eq!(transport.foreign_packets_ignored(), 3);
+        assert_eq!(transport.update_with_budget(Duration::ZERO, &mut server, 3).unwrap(), 4);
+        assert_eq!(transport.foreign_packets_ignored(), 6);
+
+        transport.update(Duration::ZERO, &mut server).unwrap();
+        assert_eq!(transport.overflow_packets(), 0);
+        assert_eq!(transport.foreign_packets_ignored(), 10);
+        assert_eq!(transport.overflow_dropped(), 0);
+    }
+
+    #[test]
+    fn multiplexer_routes_by_protocol_id() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let server_addr = socket.local_addr().unwrap();
+        let mut multiplexer = ServerMultiplexer::new(socket).unwrap();
+        let mut servers = vec![];
+        for protocol_id in [7, 8] {
+            multiplexer.add_shard(ServerConfig {
+                current_time: Duration::ZERO,
+                max_clients: 4,
+                protocol_id,
+                public_addresses: vec![server_addr],
+                authentication: ServerAuthentication::Unsecure,
+            });
+            servers.push(RenetServer::new(ConnectionConfig::default()));
+        }
+
+        // The third client uses a protocol id without shard
+        let mut clients: Vec<(NetcodeClientTransport, RenetClient)> = [7, 8, 9]
+            .into_iter()
+            .map(|protocol_id| {
+                let authentication = ClientAuthentication::Unsecure {
+                    protocol_id,
+                    client_id: protocol_id,
+                    server_addr,
+                    user_data: None,
+                };
+                let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+                let transport = NetcodeClientTransport::new(Duration::ZERO, authentication, socket).unwrap();
+                (transport, RenetClient::new(ConnectionConfig::default()))
+            })
+            .collect();
+
+        let delta = Duration::from_millis(16);
+        let mut received: Vec<Option<Bytes>> = vec![None, None];
+        for _ in 0..100 {
+            for (transport, client) in clients.iter_mut() {
+                let was_connected = client.is_connected();
+                client.update(delta);
+                transport.update(delta, client).unwrap();
+                if !was_connected && client.is_connected() {
+                    client.send_message(DefaultChannel::ReliableOrdered, transport.client_id().to_string());
+                }
+                transport.send_packets(client).unwrap();
+            }
+            for server in servers.iter_mut() {
+                server.update(delta);
+            }
+            multiplexer.update(delta, &mut servers).unwrap();
+            for (server, received) in servers.iter_mut().zip(received.iter_mut()) {
+                for client_id in server.clients_id() {
+                    if let Some(message) = server.receive_message(client_id, DefaultChannel::ReliableOrdered) {
+                        *received = Some(message);
+                    }
+                }
+            }
+            multiplexer.send_packets(&mut servers);
+
+            if received.iter().all(Option::is_some) {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        // Each server only sees the client with its protocol id
+        assert_eq!(servers[0].clients_id(), vec![ClientId::from_raw(7)]);
+        assert_eq!(servers[1].clients_id(), vec![ClientId::from_raw(8)]);
+        assert_eq!(received, vec![Some(Bytes::from("7")), Some(Bytes::from("8"))]);
+        assert!(clients[2].1.is_connecting());
+        assert!(multiplexer.unmatched_packets() > 0);
+    }
+}
@@ -0,0 +1,140 @@
+use std::{
+    io,
+    net::{SocketAddr, UdpSocket},
+    time::Duration,
+};
+
+use renetcode::{NetcodeServer, ServerConfig, NETCODE_USER_DATA_BYTES};
+
+use crate::{ClientId, RenetServer};
+
+use super::{
+    server::{process_datagram, send_server_packets, update_clients},
+    ErrorLog, NetcodeTransportError, ReceiveBuffer, TransportSocket,
+};
+
+/// Server transport shared by several independent servers listening on the same socket.
+/// Each shard has its own netcode configuration, with a distinct protocol id, and its own [`RenetServer`].
+///
+/// Connection requests are routed by their protocol id, other packets to the shard the address is connected to.
+/// Packets that match no shard are dropped and counted in [`ServerMultiplexer::unmatched_packets`].
+#[derive(Debug)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::system::Resource))]
+pub struct ServerMultiplexer {
+    socket: TransportSocket,
+    shards: Vec<NetcodeServer>,
+    buffer: ReceiveBuffer,
+    unmatched_packets: u64,
+    error_log: ErrorLog,
+}
+
+impl ServerMultiplexer {
+    pub fn new(socket: UdpSocket) -> Result<Self, std::io::Error> {
+        socket.set_nonblocking(true)?;
+
+        Ok(Self {
+            socket: TransportSocket::Udp(socket),
+            shards: vec![],
+            buffer: ReceiveBuffer::new(),
+            unmatched_packets: 0,
+            error_log: ErrorLog::default(),
+        })
+    }
+
+    /// Adds a shard and returns its index, the servers passed to the multiplexer are indexed the same way.
+    /// # Panics
+    /// Panics if another shard already uses the protocol id of the config.
+    pub fn add_shard(&mut self, server_config: ServerConfig) -> usize {
+        assert!(
+            self.shards.iter().all(|shard| shard.protocol_id() != server_config.protocol_id),
+            "already exists a shard with protocol id {}",
+            server_config.protocol_id
+        );
+
+        self.shards.push(NetcodeServer::new(server_config));
+        self.shards.len() - 1
+    }
+
+    /// Returns the number of shards.
+    pub fn shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Returns the number of received packets dropped because they did not match any shard.
+    pub fn unmatched_packets(&self) -> u64 {
+        self.unmatched_packets
+    }
+
+    /// Returns the local address of the shared socket.
+    pub fn addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Returns current number of clients connected to the shard.
+    pub fn connected_clients(&self, shard: usize) -> usize {
+        self.shards[shard].connected_clients()
+    }
+
+    /// Returns the user data for client of the shard if connected.
+    pub fn user_data(&self, shard: usize, client_id: ClientId) -> Option<[u8; NETCODE_USER_DATA_BYTES]> {
+        self.shards[shard].user_data(client_id.raw())
+    }
+
+    /// Returns the address of the client of the shard if connected.
+    pub fn client_addr(&self, shard: usize, client_id: ClientId) -> Option<SocketAddr> {
+        self.shards[shard].client_addr(client_id.raw())
+    }
+
+    /// Advances the shards by the duration, and receive packets from the network.
+    /// The servers must be in the same order as the shards.
+    /// # Panics
+    /// Panics if the number of servers is not the number of shards.
+    pub fn update(&mut self, duration: Duration, servers: &mut [RenetServer]) -> Result<(), NetcodeTransportError> {
+        assert_eq!(servers.len(), self.shards.len(), "expected one server per shard");
+        for shard in self.shards.iter_mut() {
+            shard.update(duration);
+        }
+        self.error_log.update_and_log(duration, log::Level::Error);
+
+        loop {
+            match self.socket.recv_from(self.buffer.space()) {
+                Ok((len, addr)) => {
+                    let datagram = self.buffer.take(len);
+                    let Some(index) = self.shards.iter().position(|shard| shard.accepts_packet(addr, &datagram)) else {
+                        self.unmatched_packets += 1;
+                        continue;
+                    };
+                    process_datagram(
+                        &mut self.shards[index],
+                        addr,
+                        datagram,
+                        &mut self.socket,
+                        &mut None,
+                        &mut self.error_log,
+                        &mut servers[index],
+                    );
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => break,
+                Err(ref e) if e.kind() == io::ErrorKind::ConnectionReset => continue,
+                Err(e) => return Err(e.into()),
+            };
+        }
+
+        for (shard, server) in self.shards.iter_mut().zip(servers.iter_mut()) {
+            update_clients(shard, &mut self.socket, &mut None, &mut self.error_log, server);
+        }
+
+        Ok(())
+    }
+
+    /// Send packets to the connected clients of every shard.
+    /// # Panics
+    /// Panics if the number of servers is not the number of shards.
+    pub fn send_packets(&mut self, servers: &mut [RenetServer]) {
+        assert_eq!(servers.len(), self.shards.len(), "expected one server per shard");
+        for (shard, server) in self.shards.iter_mut().zip(servers.iter_mut()) {
+            send_server_packets(shard, &mut self.socket, &mut None, &mut self.error_log, server);
+        }
+    }
+}
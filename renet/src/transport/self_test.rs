@@ -0,0 +1,245 @@
+use std::{
+    error::Error,
+    fmt, io,
+    net::{SocketAddr, UdpSocket},
+    time::{Duration, Instant},
+};
+
+use renetcode::{ClientAuthentication, ConnectToken, DisconnectReason as NetcodeDisconnectReason, NetcodeError, TokenGenerationError};
+
+use crate::{ClientId, ConnectionConfig, RenetClient, RenetServer, SendType};
+
+use super::{NetcodeClientTransport, NetcodeServerTransport, NetcodeTransportError};
+
+const SELF_TEST_PAYLOAD: &[u8] = b"renet self test";
+
+/// Configuration for [`NetcodeServerTransport::self_test`].
+#[derive(Debug)]
+pub struct SelfTestConfig {
+    /// Client id used by the test connection, it should not be used by real clients.
+    pub client_id: u64,
+    /// Maximum duration of each step of the test.
+    pub step_timeout: Duration,
+    /// Token used by the test client. When not set, a token is generated with the server own key and protocol id.
+    /// Set it with a token from your token service to also validate its configuration.
+    pub connect_token: Option<ConnectToken>,
+}
+
+impl Default for SelfTestConfig {
+    fn default() -> Self {
+        Self {
+            client_id: u64::MAX,
+            step_timeout: Duration::from_secs(2),
+            connect_token: None,
+        }
+    }
+}
+
+/// Timings of a successful self test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelfTestReport {
+    /// Time to establish the connection
+    pub handshake: Duration,
+    /// Time for a message to go to the server and back to the client
+    pub payload_round_trip: Duration,
+    /// Time for the server to notice the client disconnection
+    pub disconnect: Duration,
+}
+
+/// Failed step of a self test.
+#[derive(Debug)]
+pub enum SelfTestError {
+    /// Failed to generate the connect token for the test client
+    TokenGeneration(TokenGenerationError),
+    /// Failed to bind the socket for the test client
+    Socket(io::Error),
+    /// The client was disconnected by the netcode protocol, for example when the server is full
+    Disconnected(NetcodeDisconnectReason),
+    /// Error in the server or client transport
+    Transport(NetcodeTransportError),
+    /// No connection was established within the step timeout.
+    /// Usually the server is not reachable from its public addresses or
+    /// the token does not match the server protocol id or key.
+    HandshakeTimedOut,
+    /// The message was not exchanged within the step timeout
+    PayloadTimedOut,
+    /// The server did not notice the client disconnection within the step timeout
+    DisconnectTimedOut,
+}
+
+impl Error for SelfTestError {}
+
+impl fmt::Display for SelfTestError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        use SelfTestError::*;
+
+        match self {
+            TokenGeneration(err) => write!(fmt, "failed to generate connect token: {err}"),
+            Socket(err) => write!(fmt, "failed to bind client socket: {err}"),
+            Disconnected(reason) => write!(fmt, "client disconnected: {reason}"),
+            Transport(err) => write!(fmt, "transport error: {err}"),
+            HandshakeTimedOut => write!(fmt, "connection was not established in time"),
+            PayloadTimedOut => write!(fmt, "message was not exchanged in time"),
+            DisconnectTimedOut => write!(fmt, "server did not notice the disconnection in time"),
+        }
+    }
+}
+
+impl From<NetcodeTransportError> for SelfTestError {
+    fn from(inner: NetcodeTransportError) -> Self {
+        match inner {
+            NetcodeTransportError::Netcode(NetcodeError::Disconnected(reason)) => SelfTestError::Disconnected(reason),
+            inner => SelfTestError::Transport(inner),
+        }
+    }
+}
+
+struct SelfTestClient {
+    id: ClientId,
+    connection: RenetClient,
+    transport: NetcodeClientTransport,
+    last_step: Instant,
+}
+
+impl SelfTestClient {
+    fn step(&mut self, server_transport: &mut NetcodeServerTransport, server: &mut RenetServer) -> Result<(), SelfTestError> {
+        let now = Instant::now();
+        let delta = now - self.last_step;
+        self.last_step = now;
+
+        self.connection.update(delta);
+        self.transport.update(delta, &mut self.connection)?;
+        server.update(delta);
+        server_transport.update(delta, server)?;
+
+        self.transport.send_packets(&mut self.connection)?;
+        server_transport.send_packets(server);
+        std::thread::sleep(Duration::from_millis(1));
+
+        Ok(())
+    }
+
+    // Steps until the condition is true, returns the elapsed time.
+    fn step_until(
+        &mut self,
+        server_transport: &mut NetcodeServerTransport,
+        server: &mut RenetServer,
+        timeout: Duration,
+        timeout_error: SelfTestError,
+        mut condition: impl FnMut(&mut Self, &mut RenetServer) -> bool,
+    ) -> Result<Duration, SelfTestError> {
+        let start = Instant::now();
+        while !condition(self, server) {
+            if start.elapsed() > timeout {
+                return Err(timeout_error);
+            }
+            self.step(server_transport, server)?;
+        }
+
+        Ok(start.elapsed())
+    }
+}
+
+// Reliable channels are preferred so the payload is not lost
+fn test_channel(channels: &[crate::ChannelConfig]) -> u8 {
+    channels
+        .iter()
+        .find(|c| !matches!(c.send_type, SendType::Unreliable | SendType::UnreliableSequenced))
+        .or(channels.first())
+        .map_or(0, |c| c.channel_id.raw())
+}
+
+pub(crate) fn run(
+    server_transport: &mut NetcodeServerTransport,
+    server: &mut RenetServer,
+    connection_config: ConnectionConfig,
+    config: SelfTestConfig,
+) -> Result<SelfTestReport, SelfTestError> {
+    let connect_token = match config.connect_token {
+        Some(connect_token) => connect_token,
+        None => server_transport
+            .generate_connect_token(config.client_id, 30, 5)
+            .map_err(SelfTestError::TokenGeneration)?,
+    };
+
+    let bind_addr: SocketAddr = match connect_token.server_addresses[0] {
+        Some(SocketAddr::V6(_)) => "[::]:0".parse().unwrap(),
+        _ => "0.0.0.0:0".parse().unwrap(),
+    };
+    let socket = UdpSocket::bind(bind_addr).map_err(SelfTestError::Socket)?;
+    let mut client = SelfTestClient {
+        id: ClientId::from_raw(connect_token.client_id),
+        transport: NetcodeClientTransport::new(Duration::ZERO, ClientAuthentication::Secure { connect_token }, socket)
+            .map_err(|e| SelfTestError::Transport(e.into()))?,
+        connection: RenetClient::new(connection_config.clone()),
+        last_step: Instant::now(),
+    };
+
+    let result = run_steps(&mut client, server_transport, server, &connection_config, config.step_timeout);
+
+    // Don't leave the test client connected or its events in the server
+    if server.is_connected(client.id) {
+        server.disconnect(client.id);
+        if let Err(e) = server_transport.update(Duration::ZERO, server) {
+            log::error!("Failed to remove self test client: {e}");
+        }
+    }
+    server.remove_events_of(client.id);
+
+    result
+}
+
+fn run_steps(
+    client: &mut SelfTestClient,
+    server_transport: &mut NetcodeServerTransport,
+    server: &mut RenetServer,
+    connection_config: &ConnectionConfig,
+    timeout: Duration,
+) -> Result<SelfTestReport, SelfTestError> {
+    let handshake = client.step_until(
+        server_transport,
+        server,
+        timeout,
+        SelfTestError::HandshakeTimedOut,
+        |client, server| client.connection.is_connected() && server.is_connected(client.id),
+    )?;
+
+    let client_channel = test_channel(&connection_config.client_channels_config);
+    let server_channel = test_channel(&connection_config.server_channels_config);
+    client.connection.send_message(client_channel, SELF_TEST_PAYLOAD);
+    let payload_round_trip = client.step_until(
+        server_transport,
+        server,
+        timeout,
+        SelfTestError::PayloadTimedOut,
+        |client, server| {
+            while let Some(message) = server.receive_message(client.id, client_channel) {
+                if message == SELF_TEST_PAYLOAD {
+                    server.send_message(client.id, server_channel, message);
+                }
+            }
+            matches!(client.connection.receive_message(server_channel), Some(message) if message == SELF_TEST_PAYLOAD)
+        },
+    )?;
+
+    // Only the server is updated from now on, the client transport is disconnected
+    client.transport.disconnect();
+    let start = Instant::now();
+    let mut last_step = start;
+    while server.is_connected(client.id) {
+        if start.elapsed() > timeout {
+            return Err(SelfTestError::DisconnectTimedOut);
+        }
+        std::thread::sleep(Duration::from_millis(1));
+        let now = Instant::now();
+        server.update(now - last_step);
+        server_transport.update(now - last_step, server)?;
+        last_step = now;
+    }
+
+    Ok(SelfTestReport {
+        handshake,
+        payload_round_trip,
+        disconnect: start.elapsed(),
+    })
+}
@@ -1,35 +1,120 @@
 use std::{
-    io,
+    collections::VecDeque,
+    io::{self, Write},
     net::{SocketAddr, UdpSocket},
     time::Duration,
 };
 
-use renetcode::{NetcodeServer, ServerConfig, ServerResult, NETCODE_MAX_PACKET_BYTES, NETCODE_USER_DATA_BYTES};
+use bytes::{Bytes, BytesMut};
+use renetcode::{
+    ConnectQueueConfig, ConnectToken, NetcodeServer, ServerConfig, ServerResult, TokenGenerationError, NETCODE_USER_DATA_BYTES,
+};
 
-use crate::ClientId;
 use crate::RenetServer;
+use crate::{packet::Payload, ClientId, ClientNotFound, DropReason, EffectiveConfig};
+
+use super::{
+    capture::PacketCapture, capture_payload, intercept_received, payload_range, self_test, send_to, shared_payload, ErrorLog,
+    InterceptAction, NetcodeTransportError, PacketDirection, PacketInterceptor, ReceiveBuffer, RepeatedError, SelfTestConfig,
+    SelfTestError, SelfTestReport, TransportSocket,
+};
 
-use super::NetcodeTransportError;
+// Maximum received packets kept by budgeted updates
+const MAX_OVERFLOW_PACKETS: usize = 4096;
 
 #[derive(Debug)]
 #[cfg_attr(feature = "bevy", derive(bevy_ecs::system::Resource))]
 pub struct NetcodeServerTransport {
-    socket: UdpSocket,
+    socket: TransportSocket,
     netcode_server: NetcodeServer,
-    buffer: [u8; NETCODE_MAX_PACKET_BYTES],
+    buffer: ReceiveBuffer,
+    interceptor: Option<PacketInterceptor>,
+    ignore_foreign_packets: bool,
+    foreign_packets_ignored: u64,
+    error_log: ErrorLog,
+    // Received packets left by budgeted updates
+    overflow: VecDeque<(SocketAddr, BytesMut)>,
+    overflow_dropped: u64,
+    // Clients to disconnect in the next calls to send_packets, and how many each call
+    pending_disconnects: VecDeque<u64>,
+    disconnects_per_send: usize,
 }
 
 impl NetcodeServerTransport {
     pub fn new(server_config: ServerConfig, socket: UdpSocket) -> Result<Self, std::io::Error> {
         socket.set_nonblocking(true)?;
 
-        let netcode_server = NetcodeServer::new(server_config);
+        Ok(Self::with_socket(server_config, TransportSocket::Udp(socket)))
+    }
 
-        Ok(Self {
+    /// Creates a transport without socket, for applications that carry the datagrams themselves,
+    /// like a relay protocol or a demo recorder.
+    /// Received datagrams are passed with [`NetcodeServerTransport::process_incoming_packet`],
+    /// and the encrypted datagrams to send are taken with [`NetcodeServerTransport::drain_outgoing_packets`].
+    pub fn new_manual(server_config: ServerConfig) -> Self {
+        Self::with_socket(server_config, TransportSocket::manual())
+    }
+
+    fn with_socket(server_config: ServerConfig, socket: TransportSocket) -> Self {
+        Self {
             socket,
-            netcode_server,
-            buffer: [0; NETCODE_MAX_PACKET_BYTES],
-        })
+            netcode_server: NetcodeServer::new(server_config),
+            buffer: ReceiveBuffer::new(),
+            interceptor: None,
+            ignore_foreign_packets: false,
+            foreign_packets_ignored: 0,
+            error_log: ErrorLog::default(),
+            overflow: VecDeque::new(),
+            overflow_dropped: 0,
+            pending_disconnects: VecDeque::new(),
+            disconnects_per_send: 0,
+        }
+    }
+
+    /// Sets a hook called with every datagram right before it is sent to the socket
+    /// and right after it is read from it.
+    /// Netcode encryption happens first, the hook receives the already encrypted packet.
+    /// The hook can modify or replace the datagram, or drop it by returning [`InterceptAction::Drop`],
+    /// dropped datagrams are seen as packet loss by the rest of the stack.
+    /// Received datagrams larger than `NETCODE_MAX_PACKET_BYTES` after the hook are discarded.
+    pub fn set_packet_interceptor<F>(&mut self, interceptor: F)
+    where
+        F: FnMut(PacketDirection, SocketAddr, &mut Vec<u8>) -> InterceptAction + Send + Sync + 'static,
+    {
+        self.interceptor
+            .get_or_insert_with(PacketInterceptor::default)
+            .set_interceptor(interceptor);
+    }
+
+    /// Writes every datagram sent or received by the transport to `writer` in the pcapng format,
+    /// the capture can be opened with Wireshark or tcpdump.
+    /// Datagrams are written as they are on the socket: encrypted, with the IP and UDP headers rebuilt from their addresses,
+    /// and with the changes made by the packet interceptor.
+    /// The writer is not buffered by the transport, wrap files in a [`BufWriter`][std::io::BufWriter].
+    /// If writing fails the error is logged and the capture stops.
+    pub fn set_packet_capture<W: Write + Send + Sync + 'static>(&mut self, writer: W) -> io::Result<()> {
+        self.start_capture(writer, false)
+    }
+
+    /// Same as [`NetcodeServerTransport::set_packet_capture`], and also writes the renet packets before they are
+    /// encrypted and after they are decrypted, on a second interface with the `LINKTYPE_USER0` link type.
+    /// They are decoded by the Wireshark dissector in `renet/wireshark/renet.lua`.
+    ///
+    /// The capture exposes the contents of every message, only use it while developing.
+    #[cfg(feature = "capture_decrypted")]
+    pub fn set_packet_capture_with_payloads<W: Write + Send + Sync + 'static>(&mut self, writer: W) -> io::Result<()> {
+        self.start_capture(writer, true)
+    }
+
+    fn start_capture<W: Write + Send + Sync + 'static>(&mut self, writer: W, payloads: bool) -> io::Result<()> {
+        // Sockets bound to an unspecified address are shown with the public address
+        let local_addr = match self.socket.local_addr() {
+            Ok(addr) if !addr.ip().is_unspecified() => addr,
+            _ => self.netcode_server.addresses()[0],
+        };
+        let capture = PacketCapture::new(writer, local_addr, payloads)?;
+        self.interceptor.get_or_insert_with(PacketInterceptor::default).set_capture(capture);
+        Ok(())
     }
 
     /// Returns the server public address
@@ -47,6 +132,46 @@ impl NetcodeServerTransport {
         self.netcode_server.connected_clients()
     }
 
+    /// Enables or disables queueing of connection requests received while the server is full.
+    /// Queued addresses are reported with [`ServerEvent::ClientQueued`][crate::ServerEvent::ClientQueued].
+    pub fn set_connect_queue(&mut self, config: Option<ConnectQueueConfig>) {
+        self.netcode_server.set_connect_queue(config);
+    }
+
+    /// Returns the number of addresses waiting in the connect queue.
+    pub fn queued_clients(&self) -> usize {
+        self.netcode_server.queued_clients()
+    }
+
+    /// Returns the number of clients with a connection handshake in progress, see [`NetcodeServer::pending_clients`].
+    pub fn pending_clients(&self) -> usize {
+        self.netcode_server.pending_clients()
+    }
+
+    /// Returns the number of handshakes in progress evicted to make room for new ones.
+    pub fn evicted_pending_clients(&self) -> u64 {
+        self.netcode_server.evicted_pending_clients()
+    }
+
+    /// Returns whether the packet could be a renet packet for this server.
+    /// Only the unencrypted prefix and the protocol id of connection requests are checked,
+    /// it doesn't allocate or decrypt, use it to filter other protocols sharing the same port.
+    pub fn packet_looks_like_renet(&self, packet: &[u8]) -> bool {
+        self.netcode_server.packet_looks_like_netcode(packet)
+    }
+
+    /// When enabled, received packets that fail [`NetcodeServerTransport::packet_looks_like_renet`]
+    /// are silently dropped instead of being processed and logged as errors.
+    /// Dropped packets are counted in [`NetcodeServerTransport::foreign_packets_ignored`].
+    pub fn set_ignore_foreign_packets(&mut self, ignore: bool) {
+        self.ignore_foreign_packets = ignore;
+    }
+
+    /// Returns the number of packets dropped by [`NetcodeServerTransport::set_ignore_foreign_packets`].
+    pub fn foreign_packets_ignored(&self) -> u64 {
+        self.foreign_packets_ignored
+    }
+
     /// Returns the user data for client if connected.
     pub fn user_data(&self, client_id: ClientId) -> Option<[u8; NETCODE_USER_DATA_BYTES]> {
         self.netcode_server.user_data(client_id.raw())
@@ -57,16 +182,67 @@ impl NetcodeServerTransport {
         self.netcode_server.client_addr(client_id.raw())
     }
 
+    /// Returns the unix timestamp, in seconds, at which the connect token the client connected with expires.
+    /// Returns None if the client is not connected.
+    pub fn client_token_expiry(&self, client_id: ClientId) -> Option<u64> {
+        self.netcode_server.client_token_expire_timestamp(client_id.raw())
+    }
+
+    /// Returns the values in effect for the connection of the client, including the transport ones.
+    pub fn effective_config(&self, server: &RenetServer, client_id: ClientId) -> Result<EffectiveConfig, ClientNotFound> {
+        let config = server.effective_config(client_id)?;
+        Ok(EffectiveConfig {
+            timeout: self.netcode_server.client_timeout(client_id.raw()),
+            keep_alive_interval: Some(self.netcode_server.keep_alive_interval()),
+            token_expire_timestamp: self.netcode_server.client_token_expire_timestamp(client_id.raw()),
+            ..config
+        })
+    }
+
     /// Disconnects all connected clients.
     /// This sends the disconnect packet instantly, use this when closing/exiting games,
     /// should use [RenetServer::disconnect_all][crate::RenetServer::disconnect_all] otherwise.
     pub fn disconnect_all(&mut self, server: &mut RenetServer) {
         for client_id in self.netcode_server.clients_id() {
             let server_result = self.netcode_server.disconnect(client_id);
-            handle_server_result(server_result, &self.socket, server);
+            handle_server_result(server_result, &mut self.socket, &mut self.interceptor, &mut self.error_log, server);
         }
     }
 
+    /// Disconnects all connected clients over the next calls to [`NetcodeServerTransport::send_packets`],
+    /// at most `max_per_send` clients each call.
+    /// Spreads the encryption and sending of the disconnect packets when closing a server with many clients,
+    /// the server should keep updating and sending packets until [`NetcodeServerTransport::pending_disconnects`] is zero.
+    pub fn queue_disconnect_all(&mut self, max_per_send: usize) {
+        self.pending_disconnects = self.netcode_server.clients_id().into();
+        self.disconnects_per_send = max_per_send.max(1);
+    }
+
+    /// Returns the number of clients still to be disconnected, see [`NetcodeServerTransport::queue_disconnect_all`].
+    pub fn pending_disconnects(&self) -> usize {
+        self.pending_disconnects.len()
+    }
+
+    /// Runs a complete connect, message and disconnect cycle against the server with a client
+    /// in a loopback socket, and reports how long each step took.
+    /// Call it once when starting the server, before any client is connected, to detect
+    /// misconfigurations that would only show up as clients timing out.
+    /// The test client connection and its events are removed from the server after the test.
+    pub fn self_test(&mut self, server: &mut RenetServer, config: SelfTestConfig) -> Result<SelfTestReport, SelfTestError> {
+        let connection_config = server.connection_config().clone();
+        self_test::run(self, server, connection_config, config)
+    }
+
+    pub(crate) fn generate_connect_token(
+        &self,
+        client_id: u64,
+        expire_seconds: u64,
+        timeout_seconds: i32,
+    ) -> Result<ConnectToken, TokenGenerationError> {
+        self.netcode_server
+            .generate_connect_token(client_id, expire_seconds, timeout_seconds)
+    }
+
     /// Returns the duration since the connected client last received a packet.
     /// Usefull to detect users that are timing out.
     pub fn time_since_last_received_packet(&self, client_id: ClientId) -> Option<Duration> {
@@ -74,14 +250,53 @@ impl NetcodeServerTransport {
     }
 
     /// Advances the transport by the duration, and receive packets from the network.
+    /// Manual transports only advance, their packets are passed with [`NetcodeServerTransport::process_incoming_packet`].
     pub fn update(&mut self, duration: Duration, server: &mut RenetServer) -> Result<(), NetcodeTransportError> {
+        self.update_with_budget(duration, server, usize::MAX)?;
+        Ok(())
+    }
+
+    /// Same as [`NetcodeServerTransport::update`], but processes at most `max_packets` received packets.
+    /// Returns the number of received packets left for the next updates.
+    ///
+    /// Bursts of packets can take longer to process than the frame budget of the server, the remaining ones are kept,
+    /// up to 4096, and processed first by the next update. Packets received while that many are waiting are dropped
+    /// and counted in [`NetcodeServerTransport::overflow_dropped`].
+    /// The timeouts and keep-alives of the clients are handled even when the budget is exhausted.
+    pub fn update_with_budget(
+        &mut self,
+        duration: Duration,
+        server: &mut RenetServer,
+        max_packets: usize,
+    ) -> Result<usize, NetcodeTransportError> {
+        let duration = server.transport_update_duration(duration);
         self.netcode_server.update(duration);
+        self.error_log.update_and_log(duration, log::Level::Error);
+
+        // Packets left by the previous updates were received first
+        let mut processed = 0;
+        while processed < max_packets {
+            let Some((addr, datagram)) = self.overflow.pop_front() else {
+                break;
+            };
+            self.process_taken(addr, datagram, server);
+            processed += 1;
+        }
 
         loop {
-            match self.socket.recv_from(&mut self.buffer) {
+            match self.socket.recv_from(self.buffer.space()) {
                 Ok((len, addr)) => {
-                    let server_result = self.netcode_server.process_packet(addr, &mut self.buffer[..len]);
-                    handle_server_result(server_result, &self.socket, server);
+                    let Some(datagram) = self.take_received(addr, len) else {
+                        continue;
+                    };
+                    if processed < max_packets {
+                        self.process_taken(addr, datagram, server);
+                        processed += 1;
+                    } else if self.overflow.len() < MAX_OVERFLOW_PACKETS {
+                        self.overflow.push_back((addr, datagram));
+                    } else {
+                        self.overflow_dropped += 1;
+                    }
                 }
                 Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
                 Err(ref e) if e.kind() == io::ErrorKind::Interrupted => break,
@@ -90,45 +305,217 @@ impl NetcodeServerTransport {
             };
         }
 
-        for client_id in self.netcode_server.clients_id() {
-            let server_result = self.netcode_server.update_client(client_id);
-            handle_server_result(server_result, &self.socket, server);
-        }
+        update_clients(
+            &mut self.netcode_server,
+            &mut self.socket,
+            &mut self.interceptor,
+            &mut self.error_log,
+            server,
+        );
+
+        Ok(self.overflow.len())
+    }
 
-        for disconnection_id in server.disconnections_id() {
-            let server_result = self.netcode_server.disconnect(disconnection_id.raw());
-            handle_server_result(server_result, &self.socket, server);
+    /// Returns the number of received packets waiting to be processed, see [`NetcodeServerTransport::update_with_budget`].
+    pub fn overflow_packets(&self) -> usize {
+        self.overflow.len()
+    }
+
+    /// Returns the number of received packets dropped because too many were waiting to be processed,
+    /// see [`NetcodeServerTransport::update_with_budget`].
+    pub fn overflow_dropped(&self) -> u64 {
+        self.overflow_dropped
+    }
+
+    /// Processes a datagram received from the address, for transports created with [`NetcodeServerTransport::new_manual`].
+    /// Datagrams bigger than `NETCODE_MAX_PACKET_BYTES` are discarded.
+    pub fn process_incoming_packet(&mut self, addr: SocketAddr, packet: &[u8], server: &mut RenetServer) {
+        match self.buffer.copy_from(packet) {
+            Some(len) => {
+                if let Some(datagram) = self.take_received(addr, len) {
+                    self.process_taken(addr, datagram, server);
+                }
+            }
+            None => {
+                if self.error_log.should_log(RepeatedError::OversizedPacket(addr)) {
+                    log::error!("Discarded packet from {addr}, it is bigger than a netcode packet");
+                }
+            }
         }
+    }
 
-        Ok(())
+    // Takes the datagram of the given length at the start of the receive buffer, None if the interceptor dropped it
+    fn take_received(&mut self, addr: SocketAddr, len: usize) -> Option<BytesMut> {
+        let len = intercept_received(&mut self.interceptor, &mut self.error_log, addr, self.buffer.space(), len)?;
+        Some(self.buffer.take(len))
+    }
+
+    fn process_taken(&mut self, addr: SocketAddr, datagram: BytesMut, server: &mut RenetServer) {
+        if self.ignore_foreign_packets && !self.netcode_server.packet_looks_like_netcode(&datagram) {
+            self.foreign_packets_ignored += 1;
+            return;
+        }
+        process_datagram(
+            &mut self.netcode_server,
+            addr,
+            datagram,
+            &mut self.socket,
+            &mut self.interceptor,
+            &mut self.error_log,
+            server,
+        );
     }
 
     /// Send packets to connected clients.
     pub fn send_packets(&mut self, server: &mut RenetServer) {
-        'clients: for client_id in server.clients_id() {
-            let packets = server.get_packets_to_send(client_id).unwrap();
-            for packet in packets {
-                match self.netcode_server.generate_payload_packet(client_id.raw(), &packet) {
-                    Ok((addr, payload)) => {
-                        if let Err(e) = self.socket.send_to(payload, addr) {
-                            log::error!("Failed to send packet to client {client_id} ({addr}): {e}");
-                            continue 'clients;
+        let disconnects = self.disconnects_per_send.min(self.pending_disconnects.len());
+        for client_id in self.pending_disconnects.drain(..disconnects) {
+            // Clients that already disconnected are ignored
+            let server_result = self.netcode_server.disconnect(client_id);
+            handle_server_result(server_result, &mut self.socket, &mut self.interceptor, &mut self.error_log, server);
+        }
+
+        send_server_packets(
+            &mut self.netcode_server,
+            &mut self.socket,
+            &mut self.interceptor,
+            &mut self.error_log,
+            server,
+        );
+    }
+
+    /// Returns the encrypted datagrams to send with their destination, for transports created with
+    /// [`NetcodeServerTransport::new_manual`]. Always empty for transports with a socket.
+    /// Datagrams are queued by [`NetcodeServerTransport::update`], [`NetcodeServerTransport::send_packets`]
+    /// and when processing incoming packets.
+    pub fn drain_outgoing_packets(&mut self) -> impl Iterator<Item = (SocketAddr, Bytes)> + '_ {
+        self.socket.drain_outgoing()
+    }
+}
+
+// Processes a datagram received from the address, payloads are passed to the renet server
+// sharing the memory of the datagram.
+pub(crate) fn process_datagram(
+    netcode_server: &mut NetcodeServer,
+    addr: SocketAddr,
+    mut datagram: BytesMut,
+    socket: &mut TransportSocket,
+    interceptor: &mut Option<PacketInterceptor>,
+    error_log: &mut ErrorLog,
+    server: &mut RenetServer,
+) {
+    let (datagram_start, datagram_len) = (datagram.as_ptr(), datagram.len());
+    let server_result = netcode_server.process_packet(addr, &mut datagram);
+    if let ServerResult::Payload { client_id, payload } = server_result {
+        capture_payload(interceptor, PacketDirection::Receive, addr, payload);
+        let range = payload_range(datagram_start, datagram_len, payload);
+        let client_id = ClientId::from_raw(client_id);
+        // Only fails for unknown clients, reported by processing the payload
+        let _ = server.record_wire_packet_received(client_id, datagram_len);
+        if let Err(e) = server.process_packet_bytes_from(shared_payload(datagram, range), client_id) {
+            if error_log.should_log(RepeatedError::ProcessPayload(client_id)) {
+                log::error!("Error while processing payload for {}: {}", client_id, e);
+            }
+        }
+        return;
+    }
+    handle_server_result(server_result, socket, interceptor, error_log, server);
+}
+
+// Sends the keep alive packets and processes the disconnections requested by the renet server.
+pub(crate) fn update_clients(
+    netcode_server: &mut NetcodeServer,
+    socket: &mut TransportSocket,
+    interceptor: &mut Option<PacketInterceptor>,
+    error_log: &mut ErrorLog,
+    server: &mut RenetServer,
+) {
+    for client_id in netcode_server.clients_id() {
+        let server_result = netcode_server.update_client(client_id);
+        handle_server_result(server_result, socket, interceptor, error_log, server);
+    }
+
+    for disconnection_id in server.disconnections_id() {
+        // Last packets of the connection, like the notice of an exceeded quota
+        if let Ok(packets) = server.get_packets_to_send(disconnection_id) {
+            send_client_packets(netcode_server, socket, interceptor, error_log, server, disconnection_id, packets);
+        }
+        let server_result = netcode_server.disconnect(disconnection_id.raw());
+        handle_server_result(server_result, socket, interceptor, error_log, server);
+    }
+
+    // Acks waiting for the next send_packets for too long are sent right away
+    for client_id in server.clients_id() {
+        let packets = server.get_ack_packets_to_send(client_id).unwrap();
+        send_client_packets(netcode_server, socket, interceptor, error_log, server, client_id, packets);
+    }
+}
+
+pub(crate) fn send_server_packets(
+    netcode_server: &mut NetcodeServer,
+    socket: &mut TransportSocket,
+    interceptor: &mut Option<PacketInterceptor>,
+    error_log: &mut ErrorLog,
+    server: &mut RenetServer,
+) {
+    for client_id in server.clients_id() {
+        let packets = server.get_packets_to_send(client_id).unwrap();
+        send_client_packets(netcode_server, socket, interceptor, error_log, server, client_id, packets);
+    }
+}
+
+fn send_client_packets(
+    netcode_server: &mut NetcodeServer,
+    socket: &mut TransportSocket,
+    interceptor: &mut Option<PacketInterceptor>,
+    error_log: &mut ErrorLog,
+    server: &mut RenetServer,
+    client_id: ClientId,
+    packets: Vec<Payload>,
+) {
+    // After a failure the remaining packets of the client are dropped for the same reason
+    let mut failed: Option<DropReason> = None;
+    for packet in packets {
+        if failed.is_none() {
+            match netcode_server.generate_payload_packet(client_id.raw(), &packet) {
+                Ok((addr, payload)) => {
+                    capture_payload(interceptor, PacketDirection::Send, addr, &packet);
+                    match send_to(socket, interceptor, payload, addr) {
+                        Ok(()) => server.record_wire_packet_sent(client_id, payload.len()).unwrap(),
+                        Err(e) => {
+                            if error_log.should_log(RepeatedError::SendPacket(addr)) {
+                                log::error!("Failed to send packet to client {client_id} ({addr}): {e}");
+                            }
+                            failed = Some(DropReason::Socket);
                         }
                     }
-                    Err(e) => {
+                }
+                Err(e) => {
+                    if error_log.should_log(RepeatedError::EncryptPacket(client_id)) {
                         log::error!("Failed to encrypt payload packet for client {client_id}: {e}");
-                        continue 'clients;
                     }
+                    failed = Some(DropReason::Encryption);
                 }
             }
         }
+        if let Some(reason) = failed {
+            server.record_dropped_packet(client_id, reason, &packet).unwrap();
+        }
     }
 }
 
-fn handle_server_result(server_result: ServerResult, socket: &UdpSocket, reliable_server: &mut RenetServer) {
-    let send_packet = |packet: &[u8], addr: SocketAddr| {
-        if let Err(err) = socket.send_to(packet, addr) {
-            log::error!("Failed to send packet to {addr}: {err}");
+pub(crate) fn handle_server_result(
+    server_result: ServerResult,
+    socket: &mut TransportSocket,
+    interceptor: &mut Option<PacketInterceptor>,
+    error_log: &mut ErrorLog,
+    reliable_server: &mut RenetServer,
+) {
+    let mut send_packet = |packet: &[u8], addr: SocketAddr| {
+        if let Err(err) = send_to(socket, interceptor, packet, addr) {
+            if error_log.should_log(RepeatedError::SendPacket(addr)) {
+                log::error!("Failed to send packet to {addr}: {err}");
+            }
         }
     };
 
@@ -140,7 +527,9 @@ fn handle_server_result(server_result: ServerResult, socket: &UdpSocket, reliabl
         ServerResult::Payload { client_id, payload } => {
             let client_id = ClientId::from_raw(client_id);
             if let Err(e) = reliable_server.process_packet_from(payload, client_id) {
-                log::error!("Error while processing payload for {}: {}", client_id, e);
+                if error_log.should_log(RepeatedError::ProcessPayload(client_id)) {
+                    log::error!("Error while processing payload for {}: {}", client_id, e);
+                }
             }
         }
         ServerResult::ClientConnected {
@@ -158,5 +547,9 @@ fn handle_server_result(server_result: ServerResult, socket: &UdpSocket, reliabl
                 send_packet(payload, addr);
             }
         }
+        ServerResult::ClientQueued { addr, position, payload } => {
+            reliable_server.client_queued(addr, position);
+            send_packet(payload, addr);
+        }
     }
 }
@@ -0,0 +1,310 @@
+use std::{
+    net::{SocketAddr, UdpSocket},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use renetcode::{ClientAuthentication, NetcodeError};
+
+use crate::{ChannelId, ClientId, ConnectionConfig, DisconnectReason, RenetClient};
+
+use super::NetcodeClientTransport;
+
+// A client of the swarm with its transport
+#[derive(Debug)]
+struct SwarmClient {
+    connection: RenetClient,
+    transport: NetcodeClientTransport,
+    added_at: Duration,
+    connected_at: Option<Duration>,
+}
+
+/// Many simulated clients stepped together, to load test a server from a single process.
+///
+/// Every client has its own non-blocking socket, the server identifies the clients by their address,
+/// but all of them are updated and flushed by a single [`ClientSwarm::update`] call.
+/// Clients are identified by the index returned by [`ClientSwarm::add_client`].
+#[derive(Debug)]
+pub struct ClientSwarm {
+    connection_config: ConnectionConfig,
+    bind_addr: SocketAddr,
+    clients: Vec<SwarmClient>,
+    current_time: Duration,
+}
+
+/// Aggregated statistics of the clients of a [`ClientSwarm`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SwarmReport {
+    /// Number of clients added to the swarm.
+    pub clients: usize,
+    /// Number of clients currently connected.
+    pub connected: usize,
+    /// Number of clients that were disconnected, including the ones that never connected.
+    pub disconnected: usize,
+    /// Ratio of the added clients that managed to connect, 0 without clients.
+    pub connect_success_rate: f64,
+    /// Average time to establish the connection of the clients that connected.
+    pub average_connect_time: Duration,
+    /// Round-trip times of the connected clients in seconds, sorted from lowest to highest.
+    pub rtts: Vec<f64>,
+    /// Average packet loss of the connected clients.
+    pub average_packet_loss: f64,
+    /// Bytes sent per second by all the connected clients.
+    pub bytes_sent_per_second: f64,
+    /// Bytes received per second by all the connected clients.
+    pub bytes_received_per_second: f64,
+}
+
+impl SwarmReport {
+    /// Returns the round-trip time, in seconds, under which the given fraction of the connected clients are.
+    /// For example 0.5 is the median and 0.99 the 99th percentile. Zero without connected clients.
+    pub fn rtt_percentile(&self, fraction: f64) -> f64 {
+        if self.rtts.is_empty() {
+            return 0.0;
+        }
+
+        let index = ((self.rtts.len() - 1) as f64 * fraction.clamp(0.0, 1.0)).round() as usize;
+        self.rtts[index]
+    }
+}
+
+impl ClientSwarm {
+    /// Creates an empty swarm, the sockets of its clients are bound to the given address with any port.
+    /// The current time is used by the clients like in [`NetcodeClientTransport::new`], usually the unix time.
+    pub fn new(current_time: Duration, connection_config: ConnectionConfig, bind_addr: SocketAddr) -> Self {
+        Self {
+            connection_config,
+            bind_addr: SocketAddr::new(bind_addr.ip(), 0),
+            clients: vec![],
+            current_time,
+        }
+    }
+
+    /// Adds a client that starts connecting right away, returns its index in the swarm.
+    pub fn add_client(&mut self, authentication: ClientAuthentication) -> Result<usize, NetcodeError> {
+        let socket = UdpSocket::bind(self.bind_addr)?;
+        let transport = NetcodeClientTransport::new(self.current_time, authentication, socket)?;
+        self.clients.push(SwarmClient {
+            connection: RenetClient::new(self.connection_config.clone()),
+            transport,
+            added_at: self.current_time,
+            connected_at: None,
+        });
+
+        Ok(self.clients.len() - 1)
+    }
+
+    /// Returns the number of clients added to the swarm.
+    pub fn len(&self) -> usize {
+        self.clients.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+
+    /// Returns the client id of the client.
+    /// # Panics
+    /// Panics if there is no client with the index.
+    pub fn client_id(&self, index: usize) -> ClientId {
+        self.clients[index].transport.client_id()
+    }
+
+    /// Returns the connection of the client.
+    /// # Panics
+    /// Panics if there is no client with the index.
+    pub fn client(&self, index: usize) -> &RenetClient {
+        &self.clients[index].connection
+    }
+
+    /// Returns the connection of the client, to use the whole [`RenetClient`] API.
+    /// # Panics
+    /// Panics if there is no client with the index.
+    pub fn client_mut(&mut self, index: usize) -> &mut RenetClient {
+        &mut self.clients[index].connection
+    }
+
+    /// Returns true if the client is connected.
+    /// # Panics
+    /// Panics if there is no client with the index.
+    pub fn is_connected(&self, index: usize) -> bool {
+        self.clients[index].connection.is_connected()
+    }
+
+    /// Send a message from the client, see [`RenetClient::send_message`].
+    /// # Panics
+    /// Panics if there is no client with the index.
+    pub fn send_message<I: Into<ChannelId>, B: Into<Bytes>>(&mut self, index: usize, channel_id: I, message: B) {
+        self.clients[index].connection.send_message(channel_id, message);
+    }
+
+    /// Receive a message of the client, see [`RenetClient::receive_message`].
+    /// # Panics
+    /// Panics if there is no client with the index.
+    pub fn receive_message<I: Into<ChannelId>>(&mut self, index: usize, channel_id: I) -> Option<Bytes> {
+        self.clients[index].connection.receive_message(channel_id)
+    }
+
+    /// Sends a message from every connected client.
+    pub fn broadcast_message<I: Into<ChannelId>, B: Into<Bytes>>(&mut self, channel_id: I, message: B) {
+        let channel_id = channel_id.into();
+        let message = message.into();
+        for client in self.clients.iter_mut().filter(|client| client.connection.is_connected()) {
+            client.connection.send_message(channel_id, message.clone());
+        }
+    }
+
+    /// Disconnects the client, it's kept in the swarm and counted as disconnected.
+    /// # Panics
+    /// Panics if there is no client with the index.
+    pub fn disconnect(&mut self, index: usize) {
+        let client = &mut self.clients[index];
+        client.connection.disconnect();
+        client.transport.disconnect();
+    }
+
+    /// Returns why the client was disconnected, None while it's connecting or connected.
+    /// # Panics
+    /// Panics if there is no client with the index.
+    pub fn disconnect_reason(&self, index: usize) -> Option<DisconnectReason> {
+        self.clients[index].connection.disconnect_reason()
+    }
+
+    /// Advances every client by the duration: receives their packets, updates them and sends their packets.
+    /// Should be called every tick. Disconnected clients are skipped.
+    pub fn update(&mut self, duration: Duration) {
+        self.current_time += duration;
+        for client in self.clients.iter_mut() {
+            if client.connection.is_disconnected() {
+                continue;
+            }
+
+            client.connection.update(duration);
+            // Errors are reported through the disconnect reason of the client
+            if client.transport.update(duration, &mut client.connection).is_err() {
+                continue;
+            }
+            if client.connected_at.is_none() && client.connection.is_connected() {
+                client.connected_at = Some(self.current_time);
+            }
+            let _ = client.transport.send_packets(&mut client.connection);
+        }
+    }
+
+    /// Returns the statistics of the clients of the swarm.
+    pub fn report(&self) -> SwarmReport {
+        let mut report = SwarmReport {
+            clients: self.clients.len(),
+            ..Default::default()
+        };
+
+        let mut connected_ever = 0;
+        let mut connect_time = Duration::ZERO;
+        let mut packet_loss = 0.0;
+        for client in self.clients.iter() {
+            if let Some(connected_at) = client.connected_at {
+                connected_ever += 1;
+                connect_time += connected_at - client.added_at;
+            }
+            if client.connection.is_disconnected() {
+                report.disconnected += 1;
+                continue;
+            }
+            if !client.connection.is_connected() {
+                continue;
+            }
+
+            let network_info = client.connection.network_info();
+            report.connected += 1;
+            report.rtts.push(network_info.rtt);
+            packet_loss += network_info.packet_loss;
+            report.bytes_sent_per_second += network_info.bytes_sent_per_second;
+            report.bytes_received_per_second += network_info.bytes_received_per_second;
+        }
+
+        if !self.clients.is_empty() {
+            report.connect_success_rate = connected_ever as f64 / self.clients.len() as f64;
+        }
+        if connected_ever > 0 {
+            report.average_connect_time = connect_time / connected_ever;
+        }
+        if report.connected > 0 {
+            report.average_packet_loss = packet_loss / report.connected as f64;
+        }
+        report.rtts.sort_by(f64::total_cmp);
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        transport::{NetcodeServerTransport, ServerAuthentication, ServerConfig},
+        DefaultChannel, RenetServer,
+    };
+
+    #[test]
+    fn swarm_connects_to_local_server() {
+        let server_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let server_addr = server_socket.local_addr().unwrap();
+        let server_config = ServerConfig {
+            current_time: Duration::ZERO,
+            max_clients: 8,
+            protocol_id: 0,
+            public_addresses: vec![server_addr],
+            authentication: ServerAuthentication::Unsecure,
+        };
+        let mut server_transport = NetcodeServerTransport::new(server_config, server_socket).unwrap();
+        let mut server = RenetServer::new(ConnectionConfig::default());
+
+        // One more client than the server accepts
+        let mut swarm = ClientSwarm::new(Duration::ZERO, ConnectionConfig::default(), server_addr);
+        for client_id in 0..9 {
+            let authentication = ClientAuthentication::Unsecure {
+                protocol_id: 0,
+                client_id,
+                server_addr,
+                user_data: None,
+            };
+            swarm.add_client(authentication).unwrap();
+        }
+
+        let delta = Duration::from_millis(16);
+        let mut echoed = 0;
+        for _ in 0..300 {
+            swarm.update(delta);
+            server.update(delta);
+            server_transport.update(delta, &mut server).unwrap();
+
+            swarm.broadcast_message(DefaultChannel::ReliableOrdered, "ping");
+            for client_id in server.clients_id() {
+                while let Some(message) = server.receive_message(client_id, DefaultChannel::ReliableOrdered) {
+                    server.send_message(client_id, DefaultChannel::ReliableOrdered, message);
+                }
+            }
+            for index in 0..swarm.len() {
+                while swarm.receive_message(index, DefaultChannel::ReliableOrdered).is_some() {
+                    echoed += 1;
+                }
+            }
+            server_transport.send_packets(&mut server);
+
+            let report = swarm.report();
+            if report.connected + report.disconnected == swarm.len() && echoed > 100 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        let report = swarm.report();
+        assert_eq!(report.clients, 9);
+        assert_eq!(report.connected, 8);
+        assert_eq!(report.disconnected, 1);
+        assert!((report.connect_success_rate - 8.0 / 9.0).abs() < f64::EPSILON);
+        assert_eq!(report.rtts.len(), 8);
+        assert!(report.rtt_percentile(0.5) <= report.rtt_percentile(1.0));
+        assert!(echoed > 100);
+    }
+}
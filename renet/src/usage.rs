@@ -0,0 +1,152 @@
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+// Consecutive calls after which an unbalanced usage is reported
+pub(crate) const USAGE_CHECK_CALLS: u32 = 300;
+// Consecutive updates with a zero duration after which they are reported
+pub(crate) const ZERO_DURATION_UPDATES: u32 = 100;
+// Undrained events or warnings after which they are reported
+pub(crate) const MAX_UNDRAINED_EVENTS: usize = 1024;
+
+/// Misuse of the API detected at runtime, see [`RenetClient::health_check`][crate::RenetClient::health_check]
+/// and [`RenetServer::health_check`][crate::RenetServer::health_check].
+/// Each misuse is logged once as a warning when it is first detected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UsageWarning {
+    /// `update` is called but the packets to send are not taken, usually because `send_packets` of the transport is not called.
+    /// The connection will time out.
+    UpdateWithoutSend,
+    /// The packets to send are taken but `update` is not called, the time of the connection does not advance.
+    SendWithoutUpdate,
+    /// Server events or channel warnings are never drained, they accumulate without limit.
+    EventsNotDrained,
+    /// Messages accumulate in a receive channel that is not read,
+    /// the channel will drop new messages or disconnect the client once full.
+    ReceiveBacklog { channel_id: u8 },
+    /// `update` is called repeatedly with a zero duration, the resends, timeouts and keep-alives don't advance.
+    /// Usually the frame timer of the game is too coarse, or goes backwards and is clamped to zero,
+    /// see `ConnectionConfig::zero_duration_fallback`.
+    ZeroDurationUpdates,
+}
+
+impl fmt::Display for UsageWarning {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        use UsageWarning::*;
+
+        match *self {
+            UpdateWithoutSend => write!(fmt, "update called {USAGE_CHECK_CALLS} times without sending packets"),
+            SendWithoutUpdate => write!(fmt, "packets sent {USAGE_CHECK_CALLS} times without calling update"),
+            EventsNotDrained => write!(fmt, "more than {MAX_UNDRAINED_EVENTS} events or warnings not drained"),
+            ReceiveBacklog { channel_id } => write!(fmt, "received messages of channel {channel_id} are not read"),
+            ZeroDurationUpdates => write!(fmt, "update called {ZERO_DURATION_UPDATES} times in a row with a zero duration"),
+        }
+    }
+}
+
+// Detects the misuses of a connection or server, each one is reported once.
+#[derive(Debug)]
+pub(crate) struct UsageWatchdog {
+    updates_without_send: u32,
+    sends_without_update: u32,
+    detected: Vec<UsageWarning>,
+    // Watchdogs of the server connections don't log, the server reports their warnings once for all clients
+    log: bool,
+}
+
+impl UsageWatchdog {
+    pub fn new(log: bool) -> Self {
+        Self {
+            updates_without_send: 0,
+            sends_without_update: 0,
+            detected: vec![],
+            log,
+        }
+    }
+
+    pub fn updated(&mut self) {
+        self.sends_without_update = 0;
+        self.updates_without_send += 1;
+        if self.updates_without_send >= USAGE_CHECK_CALLS {
+            self.detect(UsageWarning::UpdateWithoutSend);
+        }
+    }
+
+    pub fn sent(&mut self) {
+        self.updates_without_send = 0;
+        self.sends_without_update += 1;
+        if self.sends_without_update >= USAGE_CHECK_CALLS {
+            self.detect(UsageWarning::SendWithoutUpdate);
+        }
+    }
+
+    pub fn detect(&mut self, warning: UsageWarning) {
+        if self.detected.contains(&warning) {
+            return;
+        }
+        if self.log {
+            log::warn!("Possible misuse of renet: {warning}");
+        }
+        self.detected.push(warning);
+    }
+
+    pub fn detected(&self) -> &[UsageWarning] {
+        &self.detected
+    }
+}
+
+// Detects the updates that don't advance the time. When the fallback is enabled, the time elapsed since the
+// previous update is measured and applied instead once they are reported, so the timers keep advancing.
+#[derive(Debug)]
+pub(crate) struct UpdateClock {
+    zero_duration_updates: u32,
+    fallback: bool,
+    last_update: Option<Instant>,
+    // Duration applied instead of zero by the last update
+    measured: Option<Duration>,
+}
+
+impl UpdateClock {
+    pub fn new(fallback: bool) -> Self {
+        Self {
+            zero_duration_updates: 0,
+            fallback,
+            last_update: None,
+            measured: None,
+        }
+    }
+
+    // Returns the duration to apply for the update
+    pub fn update(&mut self, duration: Duration, usage: &mut UsageWatchdog) -> Duration {
+        let now = self.fallback.then(Instant::now);
+        let last_update = std::mem::replace(&mut self.last_update, now);
+        self.measured = None;
+        if !duration.is_zero() {
+            self.zero_duration_updates = 0;
+            return duration;
+        }
+
+        self.zero_duration_updates = self.zero_duration_updates.saturating_add(1);
+        if self.zero_duration_updates < ZERO_DURATION_UPDATES {
+            return duration;
+        }
+        usage.detect(UsageWarning::ZeroDurationUpdates);
+
+        let (Some(now), Some(last_update)) = (now, last_update) else {
+            return duration;
+        };
+        let elapsed = now.saturating_duration_since(last_update);
+        self.measured = Some(elapsed);
+        elapsed
+    }
+
+    // The transports are updated with the same duration, they use the measured one when given zero
+    #[cfg(feature = "transport")]
+    pub fn transport_duration(&self, duration: Duration) -> Duration {
+        match self.measured {
+            Some(measured) if duration.is_zero() => measured,
+            _ => duration,
+        }
+    }
+}
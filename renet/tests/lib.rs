@@ -1,5 +1,11 @@
+use std::time::Duration;
+
 use bytes::Bytes;
-use renet::{ClientId, ConnectionConfig, DefaultChannel, DisconnectReason, RenetClient, RenetServer, ServerEvent};
+use renet::{
+    BroadcastPolicy, ChannelAlreadyExists, ChannelConfig, ChannelError, ChannelId, ChannelStats, ChannelWarning, ChannelWarningKind,
+    ClientId, ConnectionConfig, DefaultChannel, DisconnectReason, DropReason, FullQueuePolicy, MessageDelivery, MessageId, OverflowRoute,
+    QueuePolicy, QuotaKind, ReceivePolicy, ReceiveQuota, RenetClient, RenetServer, SendError, SendType, ServerEvent, UsageWarning,
+};
 
 pub fn init_log() {
     let _ = env_logger::builder().is_test(true).try_init();
@@ -15,7 +21,14 @@ fn test_remote_connection_reliable_channel() {
     server.add_connection(client_id);
     assert_eq!(server.connected_clients(), 1);
     assert!(server.has_connections());
-    assert_eq!(ServerEvent::ClientConnected { client_id }, server.get_event().unwrap());
+    assert_eq!(
+        ServerEvent::ClientConnected {
+            client_id,
+            occurred_at: Duration::ZERO,
+            sequence: 0
+        },
+        server.get_event().unwrap()
+    );
 
     for _ in 0..200 {
         server.send_message(client_id, DefaultChannel::ReliableOrdered, Bytes::from("test"));
@@ -63,8 +76,2394 @@ fn test_remote_connection_reliable_channel() {
     assert_eq!(
         ServerEvent::ClientDisconnected {
             client_id,
-            reason: DisconnectReason::Transport
+            reason: DisconnectReason::Transport,
+            occurred_at: Duration::ZERO,
+            sequence: 1
         },
         server.get_event().unwrap()
     );
 }
+
+#[test]
+fn channel_id_conversions() {
+    let mut server = RenetServer::new(ConnectionConfig::default());
+    let mut client = RenetClient::new(ConnectionConfig::default());
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+
+    // Raw ids, DefaultChannel and ChannelId all address the same channel
+    let channel_id = ChannelId::from(DefaultChannel::ReliableOrdered);
+    assert_eq!(channel_id, ChannelId::from_raw(2));
+    server.send_message(client_id, 2, Bytes::from("raw"));
+    server.send_message(client_id, DefaultChannel::ReliableOrdered, Bytes::from("default"));
+    server.send_message(client_id, channel_id, Bytes::from("channel id"));
+
+    for packet in server.get_packets_to_send(client_id).unwrap() {
+        client.process_packet(&packet);
+    }
+
+    assert_eq!(client.receive_message(channel_id).unwrap(), "raw");
+    assert_eq!(client.receive_message(2).unwrap(), "default");
+    assert_eq!(client.receive_message(DefaultChannel::ReliableOrdered).unwrap(), "channel id");
+}
+
+#[test]
+fn broadcast_with_policy() {
+    let channels = vec![
+        ChannelConfig {
+            warning_threshold: None,
+            ..ChannelConfig::new(0, 100, SendType::Unreliable)
+        },
+        ChannelConfig {
+            warning_threshold: None,
+            ..ChannelConfig::new(
+                1,
+                100,
+                SendType::ReliableOrdered {
+                    resend_time: std::time::Duration::from_millis(300),
+                },
+            )
+        },
+    ];
+    let mut server = RenetServer::new(ConnectionConfig {
+        server_channels_config: channels.clone(),
+        client_channels_config: channels,
+        ..Default::default()
+    });
+    let idle = ClientId::from_raw(0);
+    let saturated = ClientId::from_raw(1);
+    server.add_connection(idle);
+    server.add_connection(saturated);
+    server.send_message(saturated, 0, vec![0; 80]);
+    server.send_message(saturated, 1, vec![0; 80]);
+
+    for channel_id in [0, 1] {
+        let summary = server.broadcast_message_with_policy(channel_id, vec![1; 30], BroadcastPolicy::Skip);
+        assert_eq!(summary.sent, 1);
+        assert_eq!(summary.skipped, vec![saturated]);
+    }
+
+    // Only unreliable channels can replace queued messages
+    let summary = server.broadcast_message_with_policy(0, vec![2; 40], BroadcastPolicy::Replace);
+    assert_eq!((summary.sent, summary.replaced), (2, 1));
+    assert!(summary.skipped.is_empty());
+    assert_eq!(server.channel_available_memory(saturated, 0), 60);
+
+    let summary = server.broadcast_message_with_policy(1, vec![2; 40], BroadcastPolicy::Replace);
+    assert_eq!((summary.sent, summary.replaced), (1, 0));
+    assert_eq!(summary.skipped, vec![saturated]);
+
+    // Forcing the message exceeds the reliable channel memory of the saturated client only
+    let summary = server.broadcast_message_with_policy(1, vec![3; 30], BroadcastPolicy::Force);
+    assert_eq!(summary.sent, 1);
+    assert!(server.is_connected(idle));
+    assert!(matches!(
+        server.disconnect_reason(saturated),
+        Some(DisconnectReason::SendChannelError { channel_id: 1, .. })
+    ));
+}
+
+#[test]
+fn broadcast_with_policy_traced_channel() {
+    let channels = vec![ChannelConfig {
+        warning_threshold: None,
+        tracing_context: true,
+        ..ChannelConfig::new(
+            0,
+            1000,
+            SendType::ReliableOrdered {
+                resend_time: Duration::from_millis(300),
+            },
+        )
+    }];
+    let mut server = RenetServer::new(ConnectionConfig {
+        server_channels_config: channels.clone(),
+        client_channels_config: channels,
+        ..Default::default()
+    });
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+    server.send_message(client_id, 0, vec![0; 500]);
+    let available = server.channel_available_memory(client_id, 0);
+
+    // The message fits without the trace id prefix, but not with it
+    let summary = server.broadcast_message_with_policy(0, vec![1; available], BroadcastPolicy::Skip);
+    assert_eq!(summary.sent, 0);
+    assert_eq!(summary.skipped, vec![client_id]);
+    assert!(server.is_connected(client_id));
+}
+
+#[test]
+fn server_event_is_small() {
+    // Events only carry ids, reasons or an address and their stamp, user data is available from the transport
+    assert!(std::mem::size_of::<ServerEvent>() <= 64);
+}
+
+// Fails to compile if the types can't be moved to a network thread
+const _: () = {
+    const fn assert_send<T: Send>() {}
+    assert_send::<RenetServer>();
+    assert_send::<RenetClient>();
+    assert_send::<ServerEvent>();
+    assert_send::<DisconnectReason>();
+};
+
+#[test]
+fn server_event_order() {
+    let mut server = RenetServer::new(ConnectionConfig::default());
+    server.add_connection(ClientId::from_raw(0));
+    server.update(Duration::from_millis(100));
+    server.add_connection(ClientId::from_raw(1));
+    server.remove_connection(ClientId::from_raw(0));
+
+    let events: Vec<ServerEvent> = std::iter::from_fn(|| server.get_event()).collect();
+    let stamps: Vec<(Duration, u64)> = events.iter().map(|e| (e.occurred_at(), e.sequence())).collect();
+    assert_eq!(
+        stamps,
+        vec![
+            (Duration::ZERO, 0),
+            (Duration::from_millis(100), 1),
+            (Duration::from_millis(100), 2)
+        ]
+    );
+    assert!(matches!(events[2], ServerEvent::ClientDisconnected { client_id, .. } if client_id == ClientId::from_raw(0)));
+}
+
+#[test]
+fn channel_warnings() {
+    let channels = vec![ChannelConfig::new(
+        0,
+        100,
+        SendType::ReliableOrdered {
+            resend_time: Duration::from_millis(300),
+        },
+    )];
+    let mut server = RenetServer::new(ConnectionConfig {
+        server_channels_config: channels.clone(),
+        client_channels_config: channels,
+        ..Default::default()
+    });
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+
+    server.send_message(client_id, 0, vec![0; 50]);
+    server.update(Duration::ZERO);
+    assert_eq!(server.drain_channel_warnings().count(), 0);
+
+    server.send_message(client_id, 0, vec![0; 30]);
+    server.update(Duration::ZERO);
+    server.update(Duration::ZERO);
+    let warnings: Vec<ChannelWarning> = server.drain_channel_warnings().collect();
+    assert_eq!(
+        warnings,
+        vec![ChannelWarning {
+            client_id: Some(client_id),
+            channel_id: 0,
+            kind: ChannelWarningKind::SendQueue,
+            usage: 80,
+            limit: 100,
+        }]
+    );
+}
+
+#[test]
+fn zero_and_huge_updates() {
+    init_log();
+    let config = ConnectionConfig {
+        unacked_message_timeout: Some(Duration::from_secs(5)),
+        max_update_duration: Some(Duration::from_secs(1)),
+        ..Default::default()
+    };
+    let mut server = RenetServer::new(config.clone());
+    let mut client = RenetClient::new(config);
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+
+    let exchange = |server: &mut RenetServer, client: &mut RenetClient, duration: Duration| {
+        server.update(duration);
+        client.update(duration);
+        for packet in server.get_packets_to_send(client_id).unwrap() {
+            client.process_packet(&packet);
+        }
+        for packet in client.get_packets_to_send() {
+            server.process_packet_from(&packet, client_id).unwrap();
+        }
+    };
+
+    // Repeated zero updates still deliver the messages
+    server.send_message(client_id, DefaultChannel::ReliableOrdered, Bytes::from("first"));
+    for _ in 0..3 {
+        exchange(&mut server, &mut client, Duration::ZERO);
+    }
+    assert_eq!(client.receive_message(DefaultChannel::ReliableOrdered).unwrap(), "first");
+    let network_info = client.network_info();
+    assert!(network_info.bytes_received_per_second.is_finite());
+    assert!(network_info.rtt.is_finite());
+
+    // A message is waiting for its ack when the game loop stalls for a minute
+    server.send_message(client_id, DefaultChannel::ReliableOrdered, Bytes::from("second"));
+    server.get_packets_to_send(client_id).unwrap();
+    exchange(&mut server, &mut client, Duration::from_secs(60));
+    assert!(server.is_connected(client_id));
+    assert!(!client.is_disconnected());
+    assert_eq!(server.clamped_time(), Duration::from_secs(59));
+    assert_eq!(client.clamped_time(), Duration::from_secs(59));
+    assert_eq!(client.receive_message(DefaultChannel::ReliableOrdered).unwrap(), "second");
+
+    // The session keeps working afterwards
+    server.send_message(client_id, DefaultChannel::ReliableOrdered, Bytes::from("third"));
+    exchange(&mut server, &mut client, Duration::from_millis(16));
+    assert_eq!(client.receive_message(DefaultChannel::ReliableOrdered).unwrap(), "third");
+    assert_eq!(server.network_info(client_id).unwrap().oldest_unacked_age, Duration::ZERO);
+}
+
+#[test]
+fn channel_group_order_with_packet_loss() {
+    init_log();
+    let channels = vec![
+        ChannelConfig {
+            warning_threshold: None,
+            group: Some(0),
+            ..ChannelConfig::new(0, 100 * 1024, SendType::Unreliable)
+        },
+        ChannelConfig {
+            warning_threshold: None,
+            group: Some(0),
+            ..ChannelConfig::new(
+                1,
+                100 * 1024,
+                SendType::ReliableOrdered {
+                    resend_time: Duration::from_millis(100),
+                },
+            )
+        },
+    ];
+    let config = ConnectionConfig {
+        server_channels_config: channels.clone(),
+        client_channels_config: channels,
+        ..Default::default()
+    };
+    let mut server = RenetServer::new(config.clone());
+    let mut client = RenetClient::new(config);
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+
+    let mut spawned: Vec<u32> = vec![];
+    let mut updates_received = 0;
+    let mut packet_count = 0;
+    for tick in 0..300u32 {
+        // Spawn an entity every few ticks, and send an update for every spawned entity each tick
+        if tick < 200 && tick % 5 == 0 {
+            server.send_message(client_id, 1, Bytes::from(tick.to_le_bytes().to_vec()));
+        }
+        if tick < 250 {
+            for entity in (0..=tick.min(199)).filter(|entity| entity % 5 == 0) {
+                server.send_message(client_id, 0, Bytes::from(entity.to_le_bytes().to_vec()));
+            }
+        }
+
+        server.update(Duration::from_millis(16));
+        client.update(Duration::from_millis(16));
+        // Drop one of every 3 packets in both directions
+        for packet in server.get_packets_to_send(client_id).unwrap() {
+            packet_count += 1;
+            if packet_count % 3 != 0 {
+                client.process_packet(&packet);
+            }
+        }
+        for packet in client.get_packets_to_send() {
+            packet_count += 1;
+            if packet_count % 3 != 0 {
+                server.process_packet_from(&packet, client_id).unwrap();
+            }
+        }
+
+        // Updates are read first, they are never returned before the spawn of their entity
+        while let Some(message) = client.receive_message(0) {
+            let entity = u32::from_le_bytes(message[..].try_into().unwrap());
+            assert!(spawned.contains(&entity), "update received before spawn of entity {entity}");
+            updates_received += 1;
+        }
+        while let Some(message) = client.receive_message(1) {
+            spawned.push(u32::from_le_bytes(message[..].try_into().unwrap()));
+        }
+    }
+
+    assert_eq!(client.disconnect_reason(), None);
+    assert_eq!(spawned, (0..200).step_by(5).collect::<Vec<u32>>());
+    // Lost updates don't stall the group
+    let updates_sent: u32 = (0..250u32).map(|tick| tick.min(199) / 5 + 1).sum();
+    assert!(updates_received > updates_sent as usize / 2);
+    assert!(updates_received < updates_sent as usize);
+}
+
+#[test]
+fn server_stall_is_not_packet_loss() {
+    // Simulates 500ms without packets from the server, lost in the network or because the server stalled
+    fn simulate(stall: bool) -> RenetClient {
+        let mut server = RenetServer::new(ConnectionConfig::default());
+        let mut client = RenetClient::new(ConnectionConfig::default());
+        let client_id = ClientId::from_raw(0);
+        server.add_connection(client_id);
+
+        for tick in 0..32 {
+            server.update(Duration::from_millis(50));
+            client.update(Duration::from_millis(50));
+            let mut packets = vec![];
+            if stall && tick == 30 {
+                // The server catches up, the packets of its late ticks arrive together
+                for _ in 20..=30 {
+                    server.send_message(client_id, DefaultChannel::Unreliable, Bytes::from("state"));
+                    packets.append(&mut server.get_packets_to_send(client_id).unwrap());
+                }
+            } else if !(20..30).contains(&tick) {
+                server.send_message(client_id, DefaultChannel::Unreliable, Bytes::from("state"));
+                packets = server.get_packets_to_send(client_id).unwrap();
+            } else if !stall {
+                server.send_message(client_id, DefaultChannel::Unreliable, Bytes::from("state"));
+                let _lost = server.get_packets_to_send(client_id).unwrap();
+            }
+
+            for packet in packets {
+                client.process_packet(&packet);
+            }
+            for packet in client.get_packets_to_send() {
+                server.process_packet_from(&packet, client_id).unwrap();
+            }
+            if tick == 19 {
+                // A packet with the message and one with the acks every 50ms
+                assert!((client.server_packet_rate() - 40.0).abs() < 1.0);
+            }
+        }
+
+        assert_eq!(client.disconnect_reason(), None);
+        client
+    }
+
+    let client = simulate(true);
+    assert!(client.server_stall_detected());
+    assert_eq!(client.server_stalls(), 1);
+
+    let client = simulate(false);
+    assert!(!client.server_stall_detected());
+    assert_eq!(client.server_stalls(), 0);
+}
+
+#[test]
+fn health_check_events_not_drained() {
+    let mut server = RenetServer::new(ConnectionConfig::default());
+    for client_id in 0..1000 {
+        server.add_connection(ClientId::from_raw(client_id));
+    }
+    server.update(Duration::from_millis(16));
+    assert!(server.health_check().is_empty());
+
+    for client_id in 1000..1100 {
+        server.add_connection(ClientId::from_raw(client_id));
+    }
+    server.update(Duration::from_millis(16));
+    assert_eq!(server.health_check(), vec![UsageWarning::EventsNotDrained]);
+}
+
+#[test]
+fn memory_usage_after_connection_churn() {
+    let mut server = RenetServer::new(ConnectionConfig::default());
+    for raw_id in 0..10_000 {
+        let client_id = ClientId::from_raw(raw_id);
+        let mut client = RenetClient::new(ConnectionConfig::default());
+        server.add_connection(client_id);
+        server.send_message(client_id, DefaultChannel::ReliableOrdered, vec![0; 100]);
+        client.send_message(DefaultChannel::ReliableOrdered, vec![0; 100]);
+        for packet in client.get_packets_to_send() {
+            server.process_packet_from(&packet, client_id).unwrap();
+        }
+        assert_eq!(server.memory_usage(client_id), 200);
+
+        // Churn of the oldest connections
+        if raw_id >= 100 {
+            server.remove_connection(ClientId::from_raw(raw_id - 100));
+        }
+    }
+    assert_eq!(server.total_memory_usage(), 100 * 200);
+
+    for raw_id in 9_900..10_000 {
+        server.remove_connection(ClientId::from_raw(raw_id));
+    }
+    assert_eq!(server.total_memory_usage(), 0);
+}
+
+#[test]
+fn ack_flush_with_asymmetric_send_rates() {
+    const FRAME: Duration = Duration::from_millis(16);
+    // One way latency of 48ms, 96ms of rtt
+    const LATENCY_FRAMES: usize = 3;
+
+    // The server sends every frame and the client every 6 frames, both receive every frame
+    fn simulate(ack_flush_fraction: Option<f64>) -> (RenetServer, RenetClient) {
+        let config = ConnectionConfig {
+            ack_flush_fraction,
+            ..Default::default()
+        };
+        let mut server = RenetServer::new(config.clone());
+        let mut client = RenetClient::new(config);
+        let client_id = ClientId::from_raw(0);
+        server.add_connection(client_id);
+
+        let mut to_client: Vec<(usize, Vec<u8>)> = vec![];
+        let mut to_server: Vec<(usize, Vec<u8>)> = vec![];
+        for frame in 0..300 {
+            server.update(FRAME);
+            client.update(FRAME);
+
+            for (_, packet) in to_client.iter().filter(|(arrival, _)| *arrival == frame) {
+                client.process_packet(packet);
+            }
+            for (_, packet) in to_server.iter().filter(|(arrival, _)| *arrival == frame) {
+                server.process_packet_from(packet, client_id).unwrap();
+            }
+
+            // Done by the transport update
+            for packet in client.get_ack_packets_to_send() {
+                to_server.push((frame + LATENCY_FRAMES, packet));
+            }
+            for packet in server.get_ack_packets_to_send(client_id).unwrap() {
+                to_client.push((frame + LATENCY_FRAMES, packet));
+            }
+
+            server.send_message(client_id, DefaultChannel::Unreliable, vec![0; 10]);
+            for packet in server.get_packets_to_send(client_id).unwrap() {
+                to_client.push((frame + LATENCY_FRAMES, packet));
+            }
+            if frame % 6 == 0 {
+                client.send_message(DefaultChannel::Unreliable, vec![0; 10]);
+                for packet in client.get_packets_to_send() {
+                    to_server.push((frame + LATENCY_FRAMES, packet));
+                }
+            }
+
+            while client.receive_message(DefaultChannel::Unreliable).is_some() {}
+            while server.receive_message(client_id, DefaultChannel::Unreliable).is_some() {}
+        }
+
+        assert_eq!(client.disconnect_reason(), None);
+        (server, client)
+    }
+
+    // The acks of the client wait at most one frame
+    let (server, client) = simulate(Some(0.5));
+    let rtt = server.rtt(ClientId::from_raw(0));
+    assert!((0.096..0.115).contains(&rtt), "rtt: {rtt}");
+    assert!(client.network_info().ack_delay < 0.020);
+
+    // Without flushing the acks wait for the next packets of the client
+    let (server, client) = simulate(None);
+    let rtt = server.rtt(ClientId::from_raw(0));
+    assert!(rtt > 0.120, "rtt: {rtt}");
+    assert!(client.network_info().ack_delay > 0.030);
+}
+
+#[test]
+fn receive_policy_drops_unread_messages() {
+    let channels = vec![
+        ChannelConfig {
+            warning_threshold: None,
+            receive_policy: ReceivePolicy::WarnAndDrop {
+                max_age: Duration::from_millis(100),
+            },
+            ..ChannelConfig::new(0, 1024, SendType::Unreliable)
+        },
+        ChannelConfig {
+            warning_threshold: None,
+            receive_policy: ReceivePolicy::DropOldest { max_messages: 2 },
+            ..ChannelConfig::new(
+                1,
+                1024,
+                SendType::ReliableOrdered {
+                    resend_time: Duration::from_millis(300),
+                },
+            )
+        },
+    ];
+    let config = ConnectionConfig {
+        server_channels_config: channels.clone(),
+        client_channels_config: channels,
+        ..Default::default()
+    };
+    let mut server = RenetServer::new(config.clone());
+    let mut client = RenetClient::new(config);
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+
+    for i in 0..5u8 {
+        client.send_message(0, vec![i]);
+        client.send_message(1, vec![i]);
+    }
+    for packet in client.get_packets_to_send() {
+        server.process_packet_from(&packet, client_id).unwrap();
+    }
+
+    // The server never reads the channels
+    server.update(Duration::from_millis(200));
+    let info = server.network_info(client_id).unwrap();
+    assert_eq!(info.counters.unread_messages_dropped, 5 + 3);
+    assert_eq!(info.packet_loss, 0.0);
+
+    let warnings: Vec<ChannelWarning> = server.drain_channel_warnings().collect();
+    assert_eq!(
+        warnings,
+        vec![ChannelWarning {
+            client_id: Some(client_id),
+            channel_id: 0,
+            kind: ChannelWarningKind::UnreadMessagesDropped,
+            usage: 5,
+            limit: 100,
+        }]
+    );
+
+    // The newest reliable messages are kept
+    assert_eq!(server.receive_message(client_id, 1).unwrap(), vec![3]);
+    assert_eq!(server.receive_message(client_id, 1).unwrap(), vec![4]);
+
+    // The warning is only emitted once
+    client.send_message(0, vec![5]);
+    for packet in client.get_packets_to_send() {
+        server.process_packet_from(&packet, client_id).unwrap();
+    }
+    server.update(Duration::from_millis(200));
+    assert_eq!(server.network_info(client_id).unwrap().counters.unread_messages_dropped, 9);
+    assert_eq!(server.drain_channel_warnings().count(), 0);
+}
+
+#[test]
+fn ping_is_answered_by_the_server() {
+    let mut server = RenetServer::new(ConnectionConfig::default());
+    let mut client = RenetClient::new(ConnectionConfig::default());
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+
+    let ping_id = client.send_ping();
+    let mut pong = None;
+    for _ in 0..10 {
+        client.update(Duration::from_millis(16));
+        server.update(Duration::from_millis(16));
+        for packet in client.get_packets_to_send() {
+            server.process_packet_from(&packet, client_id).unwrap();
+        }
+        for packet in server.get_packets_to_send(client_id).unwrap() {
+            client.process_packet(&packet);
+        }
+
+        if let Some(rtt) = client.poll_pong(ping_id) {
+            pong = Some(rtt);
+            break;
+        }
+    }
+
+    // The ping was sent and answered in the first frame, the update before sending it is included
+    assert_eq!(pong, Some(Duration::from_millis(16)));
+    assert_eq!(client.poll_pong(ping_id), None);
+    assert!((client.network_info().application_rtt - 0.016).abs() < 1e-9);
+}
+
+#[test]
+fn reliable_unordered_is_not_blocked_by_lost_messages() {
+    let mut server = RenetServer::new(ConnectionConfig::default());
+    let mut client = RenetClient::new(ConnectionConfig::default());
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+    for packet in server.get_packets_to_send(client_id).unwrap() {
+        client.process_packet(&packet);
+    }
+
+    // The packets of the first messages are lost
+    server.send_message(client_id, DefaultChannel::ReliableUnordered, "first");
+    server.send_message(client_id, DefaultChannel::ReliableOrdered, "first");
+    server.get_packets_to_send(client_id).unwrap();
+
+    server.send_message(client_id, DefaultChannel::ReliableUnordered, "second");
+    server.send_message(client_id, DefaultChannel::ReliableOrdered, "second");
+    for packet in server.get_packets_to_send(client_id).unwrap() {
+        client.process_packet(&packet);
+    }
+
+    // The unordered channel delivers the second message, the ordered channel waits for the first one
+    assert_eq!(client.receive_message(DefaultChannel::ReliableUnordered).unwrap(), "second");
+    assert_eq!(client.receive_message(DefaultChannel::ReliableUnordered), None);
+    assert_eq!(client.receive_message(DefaultChannel::ReliableOrdered), None);
+
+    // The first messages are resent and arrive later
+    server.update(Duration::from_millis(300));
+    for packet in server.get_packets_to_send(client_id).unwrap() {
+        client.process_packet(&packet);
+    }
+    assert_eq!(client.receive_message(DefaultChannel::ReliableUnordered).unwrap(), "first");
+    assert_eq!(client.receive_message(DefaultChannel::ReliableUnordered), None);
+    assert_eq!(client.receive_message(DefaultChannel::ReliableOrdered).unwrap(), "first");
+    assert_eq!(client.receive_message(DefaultChannel::ReliableOrdered).unwrap(), "second");
+}
+
+#[test]
+fn reliable_sequenced_delivers_the_final_value() {
+    let channels = vec![ChannelConfig {
+        warning_threshold: None,
+        ..ChannelConfig::new(
+            0,
+            1024,
+            SendType::ReliableSequenced {
+                resend_time: Duration::from_millis(150),
+            },
+        )
+    }];
+    let config = ConnectionConfig {
+        server_channels_config: channels.clone(),
+        client_channels_config: channels,
+        ..Default::default()
+    };
+    let mut server = RenetServer::new(config.clone());
+    let mut client = RenetClient::new(config);
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+
+    let delta = Duration::from_millis(50);
+    let mut received = vec![];
+    for tick in 0..20u8 {
+        client.update(delta);
+        server.update(delta);
+        if tick < 10 {
+            client.send_message(0, vec![tick]);
+        }
+
+        // Every other packet of the client is lost
+        let packets = client.get_packets_to_send();
+        if tick % 2 == 0 {
+            for packet in packets {
+                server.process_packet_from(&packet, client_id).unwrap();
+            }
+        }
+        for packet in server.get_packets_to_send(client_id).unwrap() {
+            client.process_packet(&packet);
+        }
+
+        while let Some(message) = server.receive_message(client_id, 0) {
+            received.push(message[0]);
+        }
+    }
+
+    // Intermediate values are lost, the final one always arrives
+    assert!(received.windows(2).all(|values| values[0] < values[1]));
+    assert!(received.len() < 10);
+    assert_eq!(received.last(), Some(&9));
+    assert_eq!(client.channel_available_memory(0), 1024);
+}
+
+#[test]
+#[cfg(feature = "lz4")]
+fn compressed_channel_sends_fewer_slices() {
+    let channels = vec![ChannelConfig {
+        warning_threshold: None,
+        compression: Some(renet::CompressionKind::Lz4 { min_message_bytes: 64 }),
+        ..ChannelConfig::new(
+            0,
+            1024 * 1024,
+            SendType::ReliableOrdered {
+                resend_time: Duration::from_millis(300),
+            },
+        )
+    }];
+    let config = ConnectionConfig {
+        server_channels_config: channels.clone(),
+        client_channels_config: channels,
+        ..Default::default()
+    };
+    let mut server = RenetServer::new(config.clone());
+    let mut client = RenetClient::new(config);
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+
+    // A snapshot of 100KB that compresses well, it would need more than 80 slices uncompressed
+    let snapshot: Bytes = (0..100 * 1024).map(|i| (i / 512) as u8).collect::<Vec<u8>>().into();
+    server.send_message(client_id, 0, snapshot.clone());
+    server.send_message(client_id, 0, "small");
+
+    let packets = server.get_packets_to_send(client_id).unwrap();
+    assert!(packets.len() < 10);
+    for packet in packets {
+        client.process_packet(&packet);
+    }
+
+    assert_eq!(client.receive_message(0).unwrap(), snapshot);
+    assert_eq!(client.receive_message(0).unwrap(), "small");
+    assert!(!client.is_disconnected());
+}
+
+#[test]
+fn channel_priorities_share_the_bytes_per_tick() {
+    let channel = |channel_id: u8, send_type: SendType, priority: u8| ChannelConfig {
+        warning_threshold: None,
+        priority,
+        ..ChannelConfig::new(channel_id, 1024 * 1024, send_type)
+    };
+    let resend_time = Duration::from_millis(300);
+    let channels = vec![
+        channel(0, SendType::ReliableOrdered { resend_time }, 1),
+        channel(1, SendType::Unreliable, 1),
+        channel(2, SendType::ReliableOrdered { resend_time }, 0),
+    ];
+    let config = ConnectionConfig {
+        available_bytes_per_tick: 2400,
+        server_channels_config: channels.clone(),
+        client_channels_config: channels,
+        ..Default::default()
+    };
+    let mut server = RenetServer::new(config.clone());
+    let mut client = RenetClient::new(config);
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+
+    let step = |server: &mut RenetServer, client: &mut RenetClient, received: &mut [usize; 3]| {
+        server.update(Duration::from_millis(16));
+        client.update(Duration::from_millis(16));
+        for packet in server.get_packets_to_send(client_id).unwrap() {
+            client.process_packet(&packet);
+        }
+        for packet in client.get_packets_to_send() {
+            server.process_packet_from(&packet, client_id).unwrap();
+        }
+        for (channel_id, received) in received.iter_mut().enumerate() {
+            while client.receive_message(channel_id as u8).is_some() {
+                *received += 1;
+            }
+        }
+    };
+
+    // Both saturated channels make progress, the channel without priority waits for them
+    let mut received = [0; 3];
+    server.send_message(client_id, 2, vec![0; 400]);
+    for _ in 0..5 {
+        for _ in 0..10 {
+            server.send_message(client_id, 0, vec![0; 400]);
+            server.send_message(client_id, 1, vec![0; 400]);
+        }
+        step(&mut server, &mut client, &mut received);
+    }
+    assert!(received[0] > 0);
+    assert!(received[1] > 0);
+    assert_eq!(received[2], 0);
+
+    for _ in 0..40 {
+        step(&mut server, &mut client, &mut received);
+    }
+    assert_eq!(received[0], 50);
+    assert_eq!(received[2], 1);
+}
+
+#[test]
+fn trace_ids_survive_sliced_messages() {
+    let channel = |channel_id: u8, send_type: SendType| ChannelConfig {
+        warning_threshold: None,
+        tracing_context: true,
+        ..ChannelConfig::new(channel_id, 1024 * 1024, send_type)
+    };
+    let channels = vec![
+        channel(
+            0,
+            SendType::ReliableOrdered {
+                resend_time: Duration::from_millis(300),
+            },
+        ),
+        channel(1, SendType::UnreliableSequenced),
+    ];
+    let config = ConnectionConfig {
+        server_channels_config: channels.clone(),
+        client_channels_config: channels,
+        ..Default::default()
+    };
+    let mut server = RenetServer::new(config.clone());
+    let mut client = RenetClient::new(config);
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+
+    let trace_id = [7u8; renet::TRACE_ID_BYTES];
+    let big_message = Bytes::from(vec![3u8; 10 * 1024]);
+    server.send_message_traced(client_id, 0, big_message.clone(), trace_id);
+    server.send_message(client_id, 0, "untraced");
+    server.send_message_traced(client_id, 1, "update", trace_id);
+
+    for packet in server.get_packets_to_send(client_id).unwrap() {
+        client.process_packet(&packet);
+    }
+
+    assert_eq!(client.receive_message_traced(0).unwrap(), (big_message, trace_id));
+    assert_eq!(
+        client.receive_message_traced(0).unwrap(),
+        (Bytes::from("untraced"), [0; renet::TRACE_ID_BYTES])
+    );
+    assert_eq!(client.receive_message_traced(1).unwrap(), (Bytes::from("update"), trace_id));
+    assert!(!client.is_disconnected());
+}
+
+#[test]
+fn message_kinds_are_received_and_counted() {
+    let channel = |channel_id: u8, message_kinds: bool| ChannelConfig {
+        warning_threshold: None,
+        tracing_context: true,
+        message_kinds,
+        ..ChannelConfig::new(
+            channel_id,
+            1024 * 1024,
+            SendType::ReliableOrdered {
+                resend_time: Duration::from_millis(300),
+            },
+        )
+    };
+    let channels = vec![channel(0, true), channel(1, false)];
+    let config = ConnectionConfig {
+        server_channels_config: channels.clone(),
+        client_channels_config: channels,
+        ..Default::default()
+    };
+    let mut server = RenetServer::new(config.clone());
+    let mut client = RenetClient::new(config);
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+
+    let big_message = Bytes::from(vec![3u8; 10 * 1024]);
+    server.send_message_kind(client_id, 0, 2, big_message.clone());
+    server.send_message_kind(client_id, 0, 5, "chat");
+    server.send_message(client_id, 0, "default");
+    let trace_id = [7u8; renet::TRACE_ID_BYTES];
+    server.send_message_traced(client_id, 0, "traced", trace_id);
+    server.send_message(client_id, 1, "no kinds");
+
+    for packet in server.get_packets_to_send(client_id).unwrap() {
+        client.process_packet(&packet);
+    }
+
+    assert_eq!(client.receive_message_with_kind(0).unwrap(), (2, big_message));
+    assert_eq!(client.receive_message_with_kind(0).unwrap(), (5, Bytes::from("chat")));
+    assert_eq!(client.receive_message(0).unwrap(), Bytes::from("default"));
+    assert_eq!(client.receive_message_traced(0).unwrap(), (Bytes::from("traced"), trace_id));
+    assert_eq!(client.receive_message_with_kind(1).unwrap(), (0, Bytes::from("no kinds")));
+    assert!(!client.is_disconnected());
+
+    let sent = server.message_kind_stats(client_id, 0).unwrap();
+    let sent: Vec<(u8, u64, u64)> = sent
+        .iter()
+        .map(|(kind, stats)| (*kind, stats.messages_sent, stats.bytes_sent))
+        .collect();
+    assert_eq!(sent, vec![(0, 2, 13), (2, 1, 10 * 1024), (5, 1, 4)]);
+    let received = client.message_kind_stats(0);
+    let received: Vec<(u8, u64, u64)> = received
+        .iter()
+        .map(|(kind, stats)| (*kind, stats.messages_received, stats.bytes_received))
+        .collect();
+    assert_eq!(received, vec![(0, 2, 13), (2, 1, 10 * 1024), (5, 1, 4)]);
+    assert!(client.message_kind_stats(1).is_empty());
+
+    client.reset_statistics();
+    assert!(client.message_kind_stats(0).is_empty());
+}
+
+#[test]
+fn channel_max_kbps_caps_its_rate() {
+    let channel = |channel_id: u8, max_kbps: Option<u32>| ChannelConfig {
+        warning_threshold: None,
+        max_kbps,
+        ..ChannelConfig::new(
+            channel_id,
+            1024 * 1024,
+            SendType::ReliableOrdered {
+                resend_time: Duration::from_millis(300),
+            },
+        )
+    };
+    let channels = vec![channel(0, None), channel(1, Some(64))];
+    let config = ConnectionConfig {
+        server_channels_config: channels.clone(),
+        client_channels_config: channels,
+        ..Default::default()
+    };
+    let mut server = RenetServer::new(config.clone());
+    let mut client = RenetClient::new(config);
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+
+    // A chunk of 40KB, at 64 kbps it takes more than 5 seconds
+    let chunk = Bytes::from(vec![1u8; 40 * 1024]);
+    server.send_message(client_id, 1, chunk.clone());
+
+    let tick = Duration::from_millis(16);
+    let mut elapsed = Duration::ZERO;
+    let received_chunk = loop {
+        server.send_message(client_id, 0, "gameplay");
+        server.update(tick);
+        client.update(tick);
+        elapsed += tick;
+        for packet in server.get_packets_to_send(client_id).unwrap() {
+            client.process_packet(&packet);
+        }
+        for packet in client.get_packets_to_send() {
+            server.process_packet_from(&packet, client_id).unwrap();
+        }
+
+        // The gameplay messages are not held back by the chunk
+        assert_eq!(client.receive_message(0).unwrap(), "gameplay");
+        if let Some(received_chunk) = client.receive_message(1) {
+            break received_chunk;
+        }
+        assert!(elapsed < Duration::from_secs(10));
+    };
+
+    assert_eq!(received_chunk, chunk);
+    let measured_kbps = (chunk.len() * 8) as f64 / 1000.0 / elapsed.as_secs_f64();
+    assert!(measured_kbps <= 64.0, "measured {measured_kbps} kbps");
+    assert!(measured_kbps > 50.0, "measured {measured_kbps} kbps");
+}
+
+#[test]
+fn add_channel_mid_session() {
+    let mut server = RenetServer::new(ConnectionConfig::default());
+    let mut client = RenetClient::new(ConnectionConfig::default());
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+
+    let step = |server: &mut RenetServer, client: &mut RenetClient| {
+        server.update(Duration::from_millis(16));
+        client.update(Duration::from_millis(16));
+        for packet in server.get_packets_to_send(client_id).unwrap() {
+            client.process_packet(&packet);
+        }
+        for packet in client.get_packets_to_send() {
+            server.process_packet_from(&packet, client_id).unwrap();
+        }
+    };
+    step(&mut server, &mut client);
+
+    let mod_channel = ChannelConfig {
+        warning_threshold: None,
+        ..ChannelConfig::new(
+            10,
+            1024 * 1024,
+            SendType::ReliableOrdered {
+                resend_time: Duration::from_millis(100),
+            },
+        )
+    };
+    server.add_channel(mod_channel.clone()).unwrap();
+    assert_eq!(
+        server.add_channel(mod_channel.clone()),
+        Err(ChannelAlreadyExists { channel_id: 10 })
+    );
+    let reliable_channel = ChannelConfig {
+        channel_id: DefaultChannel::ReliableOrdered.into(),
+        ..mod_channel.clone()
+    };
+    assert!(client.add_channel(reliable_channel).is_err());
+
+    // The client has not added the channel yet, the message is not lost and the client stays connected
+    server.send_message(client_id, 10, "mod loaded");
+    step(&mut server, &mut client);
+    assert_eq!(
+        client.drain_channel_errors().collect::<Vec<_>>(),
+        vec![(10, ChannelError::UnknownChannel)]
+    );
+    assert!(!client.is_disconnected());
+
+    client.add_channel(mod_channel).unwrap();
+    client.send_message(10, "hello mod");
+    for _ in 0..10 {
+        step(&mut server, &mut client);
+    }
+    assert_eq!(client.receive_message(10).unwrap(), "mod loaded");
+    assert_eq!(server.receive_message(client_id, 10).unwrap(), "hello mod");
+    assert_eq!(server.drain_channel_errors().count(), 0);
+    assert!(server.is_connected(client_id));
+
+    // New connections have the channel too
+    let new_client_id = ClientId::from_raw(1);
+    server.add_connection(new_client_id);
+    assert!(server.send_message(new_client_id, 10, "welcome").is_some());
+}
+
+#[test]
+fn sequences_wrap_around() {
+    let channel = |channel_id: u8, send_type: SendType| ChannelConfig {
+        warning_threshold: None,
+        ..ChannelConfig::new(channel_id, 1024 * 1024, send_type)
+    };
+    let channels = vec![
+        channel(0, SendType::UnreliableSequenced),
+        channel(
+            1,
+            SendType::ReliableOrdered {
+                resend_time: Duration::from_millis(100),
+            },
+        ),
+    ];
+    let config = ConnectionConfig {
+        server_channels_config: channels.clone(),
+        client_channels_config: channels,
+        ..Default::default()
+    };
+
+    // Starting at different distances from the sequence limits, messages keep flowing across them
+    for offset in [1, 7, 100] {
+        let mut server = RenetServer::new(config.clone());
+        let mut client = RenetClient::new(config.clone());
+        let client_id = ClientId::from_raw(0);
+        server.add_connection(client_id);
+        client.__set_sequences((1 << 62) - 1000 - offset, u16::MAX - offset as u16);
+
+        let mut last_update = None;
+        for i in 0..300u32 {
+            client.send_message(0, i.to_le_bytes().to_vec());
+            client.send_message(1, i.to_le_bytes().to_vec());
+            server.update(Duration::from_millis(16));
+            client.update(Duration::from_millis(16));
+            for packet in client.get_packets_to_send() {
+                server.process_packet_from(&packet, client_id).unwrap();
+            }
+            for packet in server.get_packets_to_send(client_id).unwrap() {
+                client.process_packet(&packet);
+            }
+
+            // Every sequenced update is newer than the previous one
+            let update = server.receive_message(client_id, 0).unwrap();
+            let update = u32::from_le_bytes(update[..].try_into().unwrap());
+            assert!(last_update < Some(update));
+            last_update = Some(update);
+            assert!(server.receive_message(client_id, 0).is_none());
+
+            let reliable = server.receive_message(client_id, 1).unwrap();
+            assert_eq!(reliable[..], i.to_le_bytes());
+        }
+        assert!(server.is_connected(client_id));
+    }
+}
+
+#[test]
+fn receive_quota_disconnects_abusive_client() {
+    let quota = ReceiveQuota {
+        max_bytes_per_sec: 20_000,
+        burst_bytes: 10_000,
+        max_messages_per_sec: Some(100),
+        grace_period: Duration::from_millis(500),
+    };
+    let mut server = RenetServer::new(ConnectionConfig {
+        receive_quota: Some(quota),
+        ..Default::default()
+    });
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+    server.get_event().unwrap();
+
+    let connect = |server: &mut RenetServer| {
+        let mut client = RenetClient::new(ConnectionConfig::default());
+        for packet in server.get_packets_to_send(client_id).unwrap() {
+            client.process_packet(&packet);
+        }
+        client
+    };
+
+    // Spams big reliable messages, above the bytes per second
+    let mut client = connect(&mut server);
+    let step = Duration::from_millis(100);
+    let mut current_time = Duration::ZERO;
+    let mut warned_at = None;
+    for _ in 0..20 {
+        client.send_message(DefaultChannel::ReliableOrdered, vec![0u8; 4000]);
+        for packet in client.get_packets_to_send() {
+            server.process_packet_from(&packet, client_id).unwrap();
+        }
+        client.update(step);
+        server.update(step);
+        current_time += step;
+        while let Some(event) = server.get_event() {
+            match event {
+                ServerEvent::ClientQuotaWarning { kind, occurred_at, .. } => {
+                    assert_eq!(kind, QuotaKind::ReceivedBytes);
+                    warned_at = Some(occurred_at);
+                }
+                event => panic!("unexpected event {event:?}"),
+            }
+        }
+        if !server.is_connected(client_id) {
+            break;
+        }
+    }
+
+    let warned_at = warned_at.unwrap();
+    assert!(current_time - warned_at >= quota.grace_period);
+    assert!(!server.is_connected(client_id));
+    assert_eq!(server.disconnections_id(), vec![client_id]);
+
+    // The client is told why it was disconnected
+    for packet in server.get_packets_to_send(client_id).unwrap() {
+        client.process_packet(&packet);
+    }
+    assert_eq!(
+        client.disconnect_reason(),
+        Some(DisconnectReason::QuotaExceeded {
+            kind: QuotaKind::ReceivedBytes
+        })
+    );
+}
+
+#[test]
+fn concurrent_sliced_messages_with_packet_loss() {
+    init_log();
+    let mut server = RenetServer::new(ConnectionConfig::default());
+    let mut client = RenetClient::new(ConnectionConfig::default());
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+
+    // Two big messages in flight at the same time, each with its own slices
+    let level: Bytes = (0..60_000u32).map(|i| i as u8).collect::<Vec<u8>>().into();
+    let texture: Bytes = (0..20_000u32).map(|i| (i * 7) as u8).collect::<Vec<u8>>().into();
+    server.send_message(client_id, DefaultChannel::ReliableUnordered, level.clone());
+    server.send_message(client_id, DefaultChannel::ReliableUnordered, texture.clone());
+
+    let mut received = vec![];
+    let mut packet_count = 0;
+    for _ in 0..200 {
+        server.update(Duration::from_millis(16));
+        client.update(Duration::from_millis(16));
+        // Drop one of every 4 packets in both directions
+        for packet in server.get_packets_to_send(client_id).unwrap() {
+            packet_count += 1;
+            if packet_count % 4 != 0 {
+                client.process_packet(&packet);
+            }
+        }
+        for packet in client.get_packets_to_send() {
+            packet_count += 1;
+            if packet_count % 4 != 0 {
+                server.process_packet_from(&packet, client_id).unwrap();
+            }
+        }
+
+        while let Some(message) = client.receive_message(DefaultChannel::ReliableUnordered) {
+            received.push(message);
+        }
+    }
+
+    assert_eq!(client.disconnect_reason(), None);
+    assert_eq!(received.len(), 2);
+    assert!(received.contains(&level));
+    assert!(received.contains(&texture));
+}
+
+#[test]
+fn sliced_message_progress() {
+    let mut server = RenetServer::new(ConnectionConfig::default());
+    let mut client = RenetClient::new(ConnectionConfig::default());
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+
+    let channel = ChannelId::from(DefaultChannel::ReliableOrdered);
+    assert_eq!(server.sliced_send_progress(client_id, channel).unwrap(), None);
+    assert!(server.sliced_send_progress(ClientId::from_raw(1), channel).is_err());
+
+    let level = Bytes::from(vec![1u8; 20_000]);
+    server.send_message(client_id, channel, level.clone());
+
+    let mut progress = vec![];
+    while client.receive_message(channel).is_none() {
+        // A few slices per update
+        server.update(Duration::from_millis(16));
+        client.update(Duration::from_millis(16));
+        for packet in server.get_packets_to_send(client_id).unwrap().into_iter().take(4) {
+            client.process_packet(&packet);
+        }
+        for packet in client.get_packets_to_send() {
+            server.process_packet_from(&packet, client_id).unwrap();
+        }
+
+        if let Some((received, total)) = client.sliced_receive_progress(channel) {
+            assert!(received < total);
+            progress.push(received);
+        }
+        if let Some((acked, total)) = server.sliced_send_progress(client_id, channel).unwrap() {
+            assert_eq!(total, level.len() as u64);
+            assert!(acked < total);
+        }
+    }
+
+    assert!(progress.len() > 1);
+    assert!(progress.windows(2).all(|window| window[0] <= window[1]));
+    assert_eq!(client.sliced_receive_progress(channel), None);
+}
+
+#[test]
+fn cancel_sliced_message_with_packet_loss() {
+    init_log();
+    let mut server = RenetServer::new(ConnectionConfig::default());
+    let mut client = RenetClient::new(ConnectionConfig::default());
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+
+    let channel = ChannelId::from(DefaultChannel::ReliableOrdered);
+    let old_map = Bytes::from(vec![1u8; 100_000]);
+    let new_map = Bytes::from(vec![2u8; 20_000]);
+    let old_map_id = server.send_message(client_id, channel, old_map).unwrap();
+
+    let mut received = vec![];
+    let mut packet_count = 0;
+    for tick in 0..300 {
+        // The client asks for another map while the first one is streaming
+        if tick == 10 {
+            assert!(client.sliced_receive_progress(channel).is_some());
+            assert!(server.cancel_message(client_id, channel, old_map_id).unwrap());
+            server.send_message(client_id, channel, new_map.clone());
+            server.send_message(client_id, channel, "ready");
+        }
+
+        server.update(Duration::from_millis(16));
+        client.update(Duration::from_millis(16));
+        // Drop one of every 4 packets in both directions
+        for packet in server.get_packets_to_send(client_id).unwrap() {
+            packet_count += 1;
+            if packet_count % 4 != 0 {
+                client.process_packet(&packet);
+            }
+        }
+        for packet in client.get_packets_to_send() {
+            packet_count += 1;
+            if packet_count % 4 != 0 {
+                server.process_packet_from(&packet, client_id).unwrap();
+            }
+        }
+
+        while let Some(message) = client.receive_message(channel) {
+            received.push(message);
+        }
+    }
+
+    assert_eq!(client.disconnect_reason(), None);
+    assert_eq!(received, vec![new_map, Bytes::from("ready")]);
+    assert_eq!(client.sliced_receive_progress(channel), None);
+    assert_eq!(server.sliced_send_progress(client_id, channel).unwrap(), None);
+    assert_eq!(server.network_info(client_id).unwrap().oldest_unacked_age, Duration::ZERO);
+}
+
+fn overflow_channels(threshold_bytes: usize) -> Vec<ChannelConfig> {
+    let channel = |channel_id: u8, max_memory_usage_bytes: usize, overflow: Option<OverflowRoute>| ChannelConfig {
+        warning_threshold: None,
+        group: Some(0),
+        overflow,
+        ..ChannelConfig::new(
+            channel_id,
+            max_memory_usage_bytes,
+            SendType::ReliableOrdered {
+                resend_time: Duration::from_millis(100),
+            },
+        )
+    };
+    let overflow = OverflowRoute {
+        channel_id: 1.into(),
+        threshold_bytes,
+    };
+    vec![channel(0, 16 * 1024, Some(overflow)), channel(1, 1024 * 1024, None)]
+}
+
+#[test]
+fn overflow_route_keeps_order_with_packet_loss() {
+    init_log();
+    let config = ConnectionConfig {
+        server_channels_config: overflow_channels(1000),
+        client_channels_config: overflow_channels(1000),
+        ..Default::default()
+    };
+    let mut server = RenetServer::new(config.clone());
+    let mut client = RenetClient::new(config);
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+
+    // The big messages don't fit in the channel memory
+    let messages: Vec<Bytes> = (0..20u8)
+        .map(|i| match i % 5 {
+            2 => Bytes::from(vec![i; 40_000]),
+            _ => Bytes::from(vec![i; 10]),
+        })
+        .collect();
+    assert!(server.can_send_message(client_id, 0, 40_000));
+    for message in messages.iter() {
+        server.send_message(client_id, 0, message.clone());
+    }
+    assert_eq!(server.overflowed_messages(client_id, 0).unwrap(), 4);
+    assert_eq!(server.overflowed_messages(client_id, 1).unwrap(), 0);
+
+    let mut received = vec![];
+    let mut packet_count = 0;
+    for _ in 0..300 {
+        server.update(Duration::from_millis(16));
+        client.update(Duration::from_millis(16));
+        // Drop one of every 3 packets in both directions, and deliver the others in reverse order
+        let mut packets = server.get_packets_to_send(client_id).unwrap();
+        packets.reverse();
+        for packet in packets {
+            packet_count += 1;
+            if packet_count % 3 != 0 {
+                client.process_packet(&packet);
+            }
+        }
+        for packet in client.get_packets_to_send() {
+            packet_count += 1;
+            if packet_count % 3 != 0 {
+                server.process_packet_from(&packet, client_id).unwrap();
+            }
+        }
+
+        // The small messages after a big one are held until it's read from the overflow channel
+        loop {
+            if let Some(message) = client.receive_message(0) {
+                received.push(message);
+            } else if let Some(message) = client.receive_message(1) {
+                received.push(message);
+            } else {
+                break;
+            }
+        }
+    }
+
+    assert_eq!(client.disconnect_reason(), None);
+    assert_eq!(received, messages);
+}
+
+#[test]
+#[should_panic(expected = "is not in the same group")]
+fn overflow_route_outside_group() {
+    let mut channels = overflow_channels(1000);
+    channels[1].group = None;
+    let config = ConnectionConfig {
+        server_channels_config: channels.clone(),
+        client_channels_config: channels,
+        ..Default::default()
+    };
+    RenetClient::new(config);
+}
+
+#[test]
+fn message_ttl_drops_stale_messages() {
+    // The channel sends about one message per tick, a burst waits in its queue
+    let channel = ChannelConfig {
+        warning_threshold: None,
+        max_kbps: Some(64),
+        message_ttl: Some(Duration::from_millis(100)),
+        ..ChannelConfig::new(0, 1024 * 1024, SendType::Unreliable)
+    };
+    let config = ConnectionConfig {
+        server_channels_config: vec![channel.clone()],
+        client_channels_config: vec![channel],
+        ..Default::default()
+    };
+    let mut server = RenetServer::new(config.clone());
+    let mut client = RenetClient::new(config);
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+
+    let tick = Duration::from_millis(16);
+    let mut received = 0;
+    for step in 0..60u32 {
+        if step < 20 {
+            for _ in 0..3 {
+                let mut message = vec![0; 100];
+                message[..4].copy_from_slice(&step.to_le_bytes());
+                server.send_message(client_id, 0, message);
+            }
+        }
+
+        server.update(tick);
+        client.update(tick);
+        for packet in server.get_packets_to_send(client_id).unwrap() {
+            client.process_packet(&packet);
+        }
+        for packet in client.get_packets_to_send() {
+            server.process_packet_from(&packet, client_id).unwrap();
+        }
+
+        // Stale messages never reach the client
+        while let Some(message) = client.receive_message(0) {
+            let sent_at = u32::from_le_bytes(message[..4].try_into().unwrap());
+            assert!(tick * (step - sent_at) <= Duration::from_millis(100) + tick);
+            received += 1;
+        }
+    }
+
+    let expired = server.expired_messages(client_id, 0).unwrap();
+    assert!(received > 0);
+    assert!(expired > 0);
+    assert_eq!(received + expired, 60);
+    let dropped = server.network_info(client_id).unwrap().lifetime_counters.dropped;
+    assert_eq!(dropped.get(DropReason::Expired), expired);
+}
+
+#[test]
+fn message_delivery_feedback() {
+    let channel = ChannelConfig {
+        warning_threshold: None,
+        delivery_feedback: true,
+        ..ChannelConfig::new(0, 1024 * 1024, SendType::Unreliable)
+    };
+    let config = ConnectionConfig {
+        server_channels_config: vec![channel.clone()],
+        client_channels_config: vec![channel],
+        ..Default::default()
+    };
+    let mut server = RenetServer::new(config.clone());
+    let mut client = RenetClient::new(config);
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+
+    // Every third packet of the server is lost, the outcomes of the lost ones are known after 3 seconds
+    let tick = Duration::from_millis(16);
+    let mut sent = vec![];
+    let mut delivered = vec![];
+    let mut lost = vec![];
+    let mut received = 0;
+    for step in 0..250 {
+        if step < 20 {
+            sent.push(server.send_message(client_id, 0, vec![step as u8; 100]).unwrap());
+        }
+
+        server.update(tick);
+        client.update(tick);
+        for packet in server.get_packets_to_send(client_id).unwrap() {
+            if step % 3 != 0 {
+                client.process_packet(&packet);
+            }
+        }
+        for packet in client.get_packets_to_send() {
+            server.process_packet_from(&packet, client_id).unwrap();
+        }
+
+        for delivery in server.drain_message_deliveries(client_id, 0) {
+            match delivery {
+                MessageDelivery::Delivered(message_id) => delivered.push(message_id),
+                MessageDelivery::Lost(message_id) => lost.push(message_id),
+            }
+        }
+        while client.receive_message(0).is_some() {
+            received += 1;
+        }
+    }
+
+    assert!(!delivered.is_empty());
+    assert!(!lost.is_empty());
+    assert_eq!(delivered.len(), received);
+
+    // Each message has exactly one outcome
+    let mut outcomes: Vec<MessageId> = delivered.into_iter().chain(lost).collect();
+    outcomes.sort();
+    assert_eq!(outcomes, sent);
+}
+
+#[test]
+fn coalesce_delay_packs_messages() {
+    // Returns the packets carrying the ten messages sent in a tick each
+    let packets_carrying_messages = |send_type: SendType, coalesce_delay: Option<Duration>| {
+        let channel = ChannelConfig {
+            warning_threshold: None,
+            coalesce_delay,
+            ..ChannelConfig::new(0, 1024 * 1024, send_type)
+        };
+        let config = ConnectionConfig {
+            server_channels_config: vec![channel.clone()],
+            client_channels_config: vec![channel],
+            ..Default::default()
+        };
+        let mut server = RenetServer::new(config.clone());
+        let mut client = RenetClient::new(config);
+        let client_id = ClientId::from_raw(0);
+        server.add_connection(client_id);
+
+        let mut packets = 0;
+        let mut received = 0;
+        for step in 0..40 {
+            if step < 10 {
+                server.send_message(client_id, 0, vec![step as u8; 20]);
+            }
+
+            server.update(Duration::from_millis(1));
+            client.update(Duration::from_millis(1));
+            for packet in server.get_packets_to_send(client_id).unwrap() {
+                client.process_packet(&packet);
+                let mut carried = 0;
+                while client.receive_message(0).is_some() {
+                    carried += 1;
+                }
+                if carried > 0 {
+                    packets += 1;
+                    received += carried;
+                }
+            }
+        }
+
+        assert_eq!(received, 10);
+        packets
+    };
+
+    let resend_time = Duration::from_millis(300);
+    for send_type in [SendType::Unreliable, SendType::ReliableOrdered { resend_time }] {
+        assert_eq!(packets_carrying_messages(send_type.clone(), None), 10);
+        assert_eq!(packets_carrying_messages(send_type, Some(Duration::from_millis(20))), 1);
+    }
+}
+
+#[test]
+fn message_size_is_checked_at_send_time() {
+    let channel = |channel_id: u8, send_type: SendType| ChannelConfig {
+        warning_threshold: None,
+        max_message_size: Some(1000),
+        ..ChannelConfig::new(channel_id, 6000, send_type)
+    };
+    let resend_time = Duration::from_millis(300);
+    let mut channels = vec![
+        channel(0, SendType::ReliableOrdered { resend_time }),
+        channel(1, SendType::Unreliable),
+        channel(2, SendType::ReliableOrdered { resend_time }),
+    ];
+    channels[2].max_message_size = None;
+    let config = ConnectionConfig {
+        server_channels_config: channels.clone(),
+        client_channels_config: channels,
+        ..Default::default()
+    };
+    let mut server = RenetServer::new(config.clone());
+    let mut client = RenetClient::new(config);
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+
+    for channel_id in [0, 1] {
+        assert!(server.can_send_message(client_id, channel_id, 1000));
+        assert!(!server.can_send_message(client_id, channel_id, 1001));
+        assert!(server.try_send_message(client_id, channel_id, vec![channel_id; 1000]).is_ok());
+        assert_eq!(
+            server.try_send_message(client_id, channel_id, vec![channel_id; 1001]),
+            Err(SendError::MessageTooLarge {
+                channel_id,
+                size: 1001,
+                limit: 1000
+            })
+        );
+    }
+
+    // Without max_message_size a reliable message can't be bigger than the channel memory
+    assert!(server.try_send_message(client_id, 2, vec![2; 6000]).is_ok());
+    assert_eq!(
+        server.try_send_message(client_id, 2, vec![2; 6001]),
+        Err(SendError::MessageTooLarge {
+            channel_id: 2,
+            size: 6001,
+            limit: 6000
+        })
+    );
+    assert_eq!(server.send_message(client_id, 0, vec![0; 1001]), None);
+    assert_eq!(
+        server.try_send_message(ClientId::from_raw(1), 0, vec![0; 10]),
+        Err(SendError::ClientNotFound)
+    );
+
+    // Only the messages within the limits are received, and the client stays connected
+    for _ in 0..10 {
+        server.update(Duration::from_millis(16));
+        client.update(Duration::from_millis(16));
+        for packet in server.get_packets_to_send(client_id).unwrap() {
+            client.process_packet(&packet);
+        }
+        for packet in client.get_packets_to_send() {
+            server.process_packet_from(&packet, client_id).unwrap();
+        }
+    }
+    assert!(server.is_connected(client_id));
+    assert_eq!(client.disconnect_reason(), None);
+    assert_eq!(client.receive_message(0).unwrap(), vec![0; 1000]);
+    assert_eq!(client.receive_message(1).unwrap(), vec![1; 1000]);
+    assert_eq!(client.receive_message(2).unwrap(), vec![2; 6000]);
+    for channel_id in 0..3 {
+        assert_eq!(client.receive_message(channel_id), None);
+    }
+}
+
+#[test]
+fn try_send_message_leaves_connection_up() {
+    let channel = |channel_id: u8, send_type: SendType| ChannelConfig {
+        warning_threshold: None,
+        ..ChannelConfig::new(channel_id, 1000, send_type)
+    };
+    let resend_time = Duration::from_millis(300);
+    let channels = vec![
+        channel(0, SendType::ReliableOrdered { resend_time }),
+        channel(1, SendType::Unreliable),
+    ];
+    let config = ConnectionConfig {
+        server_channels_config: channels.clone(),
+        client_channels_config: channels,
+        ..Default::default()
+    };
+    let mut client = RenetClient::new(config);
+
+    for channel_id in [0, 1] {
+        assert!(client.try_send_message(channel_id, vec![0; 600]).is_ok());
+        assert_eq!(
+            client.try_send_message(channel_id, vec![0; 600]),
+            Err(SendError::ChannelFull { channel_id })
+        );
+    }
+    assert_eq!(
+        client.try_send_message(5, vec![0; 10]),
+        Err(SendError::InvalidChannel { channel_id: 5 })
+    );
+    assert!(!client.is_disconnected());
+    assert_eq!(client.network_info().lifetime_counters.dropped.total(), 0);
+
+    // send_message drops the unreliable message, and disconnects for the reliable one
+    client.send_message(1, vec![0; 600]);
+    assert_eq!(client.network_info().lifetime_counters.dropped.get(DropReason::ChannelFull), 1);
+    assert!(!client.is_disconnected());
+    client.send_message(0, vec![0; 600]);
+    assert_eq!(
+        client.disconnect_reason(),
+        Some(DisconnectReason::SendChannelError {
+            channel_id: 0,
+            error: ChannelError::ReliableChannelMaxMemoryReached
+        })
+    );
+}
+
+#[test]
+fn channel_capacity() {
+    let mut server = RenetServer::new(ConnectionConfig::default());
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+
+    let config = &DefaultChannel::config()[0];
+    let capacity = server.channel_capacity(client_id, DefaultChannel::Unreliable).unwrap();
+    assert_eq!(capacity.bytes_free, config.max_memory_usage_bytes);
+    server.send_message(client_id, DefaultChannel::Unreliable, vec![0; 1000]);
+    let capacity = server.channel_capacity(client_id, DefaultChannel::Unreliable).unwrap();
+    assert_eq!(capacity.bytes_queued, 1000);
+    assert_eq!(capacity.bytes_free, config.max_memory_usage_bytes - 1000);
+    assert!(server.can_send_message(client_id, DefaultChannel::Unreliable, capacity.max_message_bytes));
+    assert!(!server.can_send_message(client_id, DefaultChannel::Unreliable, capacity.max_message_bytes + 1));
+    assert!(server.channel_capacity(ClientId::from_raw(1), DefaultChannel::Unreliable).is_err());
+}
+
+#[test]
+fn channel_capacity_traced_channel() {
+    let channels = vec![ChannelConfig {
+        warning_threshold: None,
+        tracing_context: true,
+        ..ChannelConfig::new(
+            0,
+            1000,
+            SendType::ReliableOrdered {
+                resend_time: Duration::from_millis(300),
+            },
+        )
+    }];
+    let mut server = RenetServer::new(ConnectionConfig {
+        server_channels_config: channels.clone(),
+        client_channels_config: channels,
+        ..Default::default()
+    });
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+    server.send_message(client_id, 0, vec![0; 500]);
+
+    // The trace id prefixed to each message is not available to the payload
+    let capacity = server.channel_capacity(client_id, 0).unwrap();
+    assert_eq!(capacity.bytes_free, 1000 - 500 - 16);
+    assert_eq!(capacity.max_message_bytes, capacity.bytes_free - 16);
+    assert!(!server.can_send_message(client_id, 0, capacity.max_message_bytes + 1));
+    assert_eq!(
+        server.try_send_message(client_id, 0, vec![0; capacity.max_message_bytes + 1]),
+        Err(SendError::ChannelFull { channel_id: 0 })
+    );
+    assert!(server.can_send_message(client_id, 0, capacity.max_message_bytes));
+    assert!(server.try_send_message(client_id, 0, vec![0; capacity.max_message_bytes]).is_ok());
+    assert_eq!(server.channel_capacity(client_id, 0).unwrap().bytes_free, 0);
+}
+
+#[test]
+fn receive_messages_iterator() {
+    let mut server = RenetServer::new(ConnectionConfig::default());
+    let mut client = RenetClient::new(ConnectionConfig::default());
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+
+    for index in 0..10u8 {
+        server.send_message(client_id, DefaultChannel::ReliableOrdered, vec![index]);
+        client.send_message(DefaultChannel::ReliableOrdered, vec![index]);
+    }
+    server.update(Duration::ZERO);
+    client.update(Duration::ZERO);
+    for packet in server.get_packets_to_send(client_id).unwrap() {
+        client.process_packet(&packet);
+    }
+    for packet in client.get_packets_to_send() {
+        server.process_packet_from(&packet, client_id).unwrap();
+    }
+
+    // Messages not consumed stay in the channel, in order
+    let received: Vec<Bytes> = client.receive_messages(DefaultChannel::ReliableOrdered).take(4).collect();
+    assert_eq!(received, (0..4u8).map(|index| Bytes::from(vec![index])).collect::<Vec<_>>());
+    let received: Vec<Bytes> = client.receive_messages(DefaultChannel::ReliableOrdered).collect();
+    assert_eq!(received, (4..10u8).map(|index| Bytes::from(vec![index])).collect::<Vec<_>>());
+    assert_eq!(client.receive_message(DefaultChannel::ReliableOrdered), None);
+
+    let received: Vec<Bytes> = server
+        .receive_messages(client_id, DefaultChannel::ReliableOrdered)
+        .take(3)
+        .collect();
+    assert_eq!(received.len(), 3);
+    assert_eq!(server.receive_messages(client_id, DefaultChannel::ReliableOrdered).count(), 7);
+    assert_eq!(
+        server
+            .receive_messages(ClientId::from_raw(1), DefaultChannel::ReliableOrdered)
+            .count(),
+        0
+    );
+}
+
+#[test]
+fn channel_stats() {
+    let mut server = RenetServer::new(ConnectionConfig::default());
+    let mut client = RenetClient::new(ConnectionConfig::default());
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+
+    let step = |server: &mut RenetServer, client: &mut RenetClient, deliver: bool| {
+        server.update(Duration::from_millis(300));
+        client.update(Duration::from_millis(300));
+        let packets = server.get_packets_to_send(client_id).unwrap();
+        if deliver {
+            for packet in packets {
+                client.process_packet(&packet);
+            }
+        }
+        for packet in client.get_packets_to_send() {
+            server.process_packet_from(&packet, client_id).unwrap();
+        }
+    };
+
+    server.send_message(client_id, DefaultChannel::ReliableOrdered, vec![1; 100]);
+    server.send_message(client_id, DefaultChannel::ReliableOrdered, vec![2; 50]);
+    server.send_message(client_id, DefaultChannel::Unreliable, vec![3; 10]);
+    // The first packets are lost, the reliable messages are resent
+    step(&mut server, &mut client, false);
+    step(&mut server, &mut client, false);
+    step(&mut server, &mut client, true);
+    while client.receive_message(DefaultChannel::ReliableOrdered).is_some() {}
+
+    let stats = server.channel_stats(client_id, DefaultChannel::ReliableOrdered).unwrap();
+    assert_eq!(stats.messages_sent, 2);
+    assert_eq!(stats.bytes_sent, 150 * (stats.resends / 2 + 1));
+    assert!(stats.resends >= 2);
+    let stats = client.channel_stats(DefaultChannel::ReliableOrdered);
+    assert_eq!(stats.messages_received, 2);
+    assert_eq!(stats.bytes_received, 150);
+    let stats = server.channel_stats(client_id, DefaultChannel::Unreliable).unwrap();
+    assert_eq!(stats.messages_sent, 1);
+    assert_eq!(stats.bytes_sent, 10);
+    assert_eq!(client.channel_stats(DefaultChannel::Unreliable).messages_received, 0);
+
+    server.reset_channel_stats(client_id).unwrap();
+    assert_eq!(
+        server.channel_stats(client_id, DefaultChannel::ReliableOrdered).unwrap(),
+        ChannelStats::default()
+    );
+    assert!(server.channel_stats(ClientId::from_raw(1), DefaultChannel::Unreliable).is_err());
+
+    // Replacing the queued message counts it as dropped, and the new one as sent
+    let large_message = vec![4; 3 * 1024 * 1024];
+    server.send_message(client_id, DefaultChannel::Unreliable, large_message.clone());
+    let summary = server.broadcast_message_with_policy(DefaultChannel::Unreliable, large_message, BroadcastPolicy::Replace);
+    assert_eq!(summary.replaced, 1);
+    let stats = server.channel_stats(client_id, DefaultChannel::Unreliable).unwrap();
+    assert_eq!(stats.messages_sent, 2);
+    assert_eq!(stats.dropped, 1);
+}
+
+#[test]
+fn queue_policy_when_full() {
+    let channel = |channel_id: u8, max_queued_messages: Option<usize>, queue_policy: QueuePolicy| ChannelConfig {
+        warning_threshold: None,
+        max_queued_messages,
+        queue_policy,
+        ..ChannelConfig::new(
+            channel_id,
+            1000,
+            SendType::ReliableOrdered {
+                resend_time: Duration::from_millis(300),
+            },
+        )
+    };
+    let channels = vec![
+        channel(0, Some(5), QueuePolicy::ErrorAndDisconnect),
+        channel(1, Some(5), QueuePolicy::RejectSend),
+        channel(2, None, QueuePolicy::RejectSend),
+    ];
+    let config = ConnectionConfig {
+        server_channels_config: channels.clone(),
+        client_channels_config: channels,
+        ..Default::default()
+    };
+    let mut server = RenetServer::new(config);
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+
+    // Nothing is delivered, the channels fill up with unacked messages
+    for _ in 0..5 {
+        assert!(server.send_message(client_id, 0, vec![0; 10]).is_some());
+        assert!(server.send_message(client_id, 1, vec![0; 10]).is_some());
+    }
+    assert!(server.send_message(client_id, 2, vec![0; 600]).is_some());
+    assert_eq!(server.channel_capacity(client_id, 1).unwrap().max_message_bytes, 0);
+
+    // Refused over the message and the byte limits, the connection stays up
+    assert_eq!(server.send_message(client_id, 1, vec![0; 10]), None);
+    assert_eq!(server.send_message(client_id, 2, vec![0; 600]), None);
+    assert_eq!(
+        server.try_send_message(client_id, 1, vec![0; 10]),
+        Err(SendError::ChannelFull { channel_id: 1 })
+    );
+    assert!(server.is_connected(client_id));
+    let dropped = server.network_info(client_id).unwrap().lifetime_counters.dropped;
+    assert_eq!(dropped.get(DropReason::ChannelFull), 2);
+
+    // The default policy disconnects
+    assert_eq!(server.send_message(client_id, 0, vec![0; 10]), None);
+    assert_eq!(
+        server.disconnect_reason(client_id),
+        Some(DisconnectReason::SendChannelError {
+            channel_id: 0,
+            error: ChannelError::ReliableChannelMaxMemoryReached
+        })
+    );
+}
+
+#[test]
+fn full_queue_policy() {
+    // Returns the first byte of the messages received after sending six of the given size to a saturated channel
+    let survivors = |full_queue_policy: FullQueuePolicy, max_queued_messages: Option<usize>, size: usize| {
+        let channel = ChannelConfig {
+            warning_threshold: None,
+            max_queued_messages,
+            full_queue_policy,
+            ..ChannelConfig::new(0, 1000, SendType::Unreliable)
+        };
+        let config = ConnectionConfig {
+            server_channels_config: vec![channel.clone()],
+            client_channels_config: vec![channel],
+            ..Default::default()
+        };
+        let mut server = RenetServer::new(config.clone());
+        let mut client = RenetClient::new(config);
+        let client_id = ClientId::from_raw(0);
+        server.add_connection(client_id);
+
+        for index in 0..6u8 {
+            server.send_message(client_id, 0, vec![index; size]);
+        }
+        for packet in server.get_packets_to_send(client_id).unwrap() {
+            client.process_packet(&packet);
+        }
+
+        let received: Vec<u8> = client.receive_messages(0).map(|message| message[0]).collect();
+        let dropped = server.network_info(client_id).unwrap().lifetime_counters.dropped;
+        assert_eq!(dropped.get(DropReason::ChannelFull), 6 - received.len() as u64);
+        assert_eq!(server.channel_stats(client_id, 0).unwrap().dropped, 6 - received.len() as u64);
+        received
+    };
+
+    // Over the message limit
+    assert_eq!(survivors(FullQueuePolicy::DropNewest, Some(3), 10), [0, 1, 2]);
+    assert_eq!(survivors(FullQueuePolicy::DropOldest, Some(3), 10), [3, 4, 5]);
+    // Over the byte limit
+    assert_eq!(survivors(FullQueuePolicy::DropNewest, None, 400), [0, 1]);
+    assert_eq!(survivors(FullQueuePolicy::DropOldest, None, 400), [4, 5]);
+}
+
+#[test]
+fn max_packets_in_flight() {
+    // Returns the most bytes sent by the channel in a tick, every other packet of the server is lost
+    let peak_tick_bytes = |max_packets_in_flight: Option<usize>| {
+        let channel = ChannelConfig {
+            warning_threshold: None,
+            max_packets_in_flight,
+            ..ChannelConfig::new(
+                0,
+                1024 * 1024,
+                SendType::ReliableOrdered {
+                    resend_time: Duration::from_millis(100),
+                },
+            )
+        };
+        let config = ConnectionConfig {
+            server_channels_config: vec![channel.clone()],
+            client_channels_config: vec![channel],
+            ..Default::default()
+        };
+        let mut server = RenetServer::new(config.clone());
+        let mut client = RenetClient::new(config);
+        let client_id = ClientId::from_raw(0);
+        server.add_connection(client_id);
+
+        // A packet for each message
+        for index in 0..40u8 {
+            server.send_message(client_id, 0, vec![index; 1000]);
+        }
+
+        let mut received = vec![];
+        let mut sent_packets = 0;
+        let mut peak_tick_bytes = 0;
+        for _ in 0..500 {
+            server.update(Duration::from_millis(16));
+            client.update(Duration::from_millis(16));
+            let bytes_sent = server.channel_stats(client_id, 0).unwrap().bytes_sent;
+            for packet in server.get_packets_to_send(client_id).unwrap() {
+                sent_packets += 1;
+                if sent_packets % 2 == 0 {
+                    client.process_packet(&packet);
+                }
+            }
+            let tick_bytes = server.channel_stats(client_id, 0).unwrap().bytes_sent - bytes_sent;
+            peak_tick_bytes = peak_tick_bytes.max(tick_bytes);
+            for packet in client.get_packets_to_send() {
+                server.process_packet_from(&packet, client_id).unwrap();
+            }
+            received.extend(client.receive_messages(0).map(|message| message[0]));
+        }
+
+        // Delivery still completes
+        assert_eq!(received, (0..40).collect::<Vec<u8>>());
+        peak_tick_bytes
+    };
+
+    let uncapped = peak_tick_bytes(None);
+    let capped = peak_tick_bytes(Some(4));
+    assert!(uncapped >= 40 * 1000);
+    assert!(capped <= 5 * 1000, "{capped}");
+}
+
+#[test]
+fn unreliable_fragments_with_loss() {
+    let channel = ChannelConfig {
+        warning_threshold: None,
+        max_fragments: Some(5),
+        reassembly_timeout: Some(Duration::from_millis(200)),
+        ..ChannelConfig::new(0, 1024 * 1024, SendType::Unreliable)
+    };
+    let config = ConnectionConfig {
+        server_channels_config: vec![channel.clone()],
+        client_channels_config: vec![channel],
+        ..Default::default()
+    };
+    let mut server = RenetServer::new(config.clone());
+    let mut client = RenetClient::new(config);
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+
+    // Above the 5 slices of the channel
+    assert_eq!(
+        server.try_send_message(client_id, 0, vec![0; 1200 * 5 + 1]),
+        Err(SendError::MessageTooLarge {
+            channel_id: 0,
+            size: 1200 * 5 + 1,
+            limit: 1200 * 5
+        })
+    );
+
+    // Every seventh packet is lost, most messages miss a slice
+    let mut received = vec![];
+    let mut sent_packets = 0;
+    for index in 0..20u8 {
+        server.send_message(client_id, 0, vec![index; 5000]);
+        server.update(Duration::from_millis(16));
+        client.update(Duration::from_millis(16));
+        for packet in server.get_packets_to_send(client_id).unwrap() {
+            sent_packets += 1;
+            if sent_packets % 7 != 0 {
+                client.process_packet(&packet);
+            }
+        }
+        received.extend(client.receive_messages(0));
+    }
+
+    assert!(!received.is_empty() && received.len() < 20);
+    for message in received.iter() {
+        assert_eq!(message.len(), 5000);
+        assert!(message.iter().all(|&byte| byte == message[0]));
+    }
+    // The incomplete messages are held until the reassembly timeout
+    assert!(client.memory_usage() > 0);
+
+    for _ in 0..20 {
+        server.update(Duration::from_millis(16));
+        client.update(Duration::from_millis(16));
+        assert!(client.receive_message(0).is_none());
+    }
+    assert_eq!(client.memory_usage(), 0);
+}
+
+#[test]
+fn unreliable_deduplicate() {
+    let received_messages = |deduplicate: Option<u16>| {
+        let channel = ChannelConfig {
+            warning_threshold: None,
+            deduplicate,
+            ..ChannelConfig::new(0, 1024 * 1024, SendType::Unreliable)
+        };
+        let config = ConnectionConfig {
+            server_channels_config: vec![channel.clone()],
+            client_channels_config: vec![channel],
+            ..Default::default()
+        };
+        let mut server = RenetServer::new(config.clone());
+        let mut client = RenetClient::new(config);
+        let client_id = ClientId::from_raw(0);
+        server.add_connection(client_id);
+
+        server.send_message(client_id, 0, vec![1, 2, 3]);
+        server.update(Duration::ZERO);
+        // The network duplicates the packet
+        for packet in server.get_packets_to_send(client_id).unwrap() {
+            client.process_packet(&packet);
+            client.process_packet(&packet);
+        }
+        client.receive_messages(0).collect::<Vec<Bytes>>()
+    };
+
+    assert_eq!(received_messages(None).len(), 2);
+    assert_eq!(received_messages(Some(64)), vec![Bytes::from(vec![1, 2, 3])]);
+}
+
+#[test]
+fn receive_any_message() {
+    let mut server = RenetServer::new(ConnectionConfig::default());
+    let mut client = RenetClient::new(ConnectionConfig::default());
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+    assert_eq!(client.receive_any_message(), None);
+
+    for index in 0..3u8 {
+        server.send_message(client_id, DefaultChannel::ReliableOrdered, vec![index]);
+    }
+    server.send_message(client_id, DefaultChannel::Unreliable, vec![10]);
+    server.update(Duration::ZERO);
+    for packet in server.get_packets_to_send(client_id).unwrap() {
+        client.process_packet(&packet);
+    }
+
+    // The channels take turns
+    let mut received = vec![];
+    while let Some((channel_id, message)) = client.receive_any_message() {
+        received.push((channel_id, message[0]));
+    }
+    let unreliable = u8::from(DefaultChannel::Unreliable);
+    let reliable = u8::from(DefaultChannel::ReliableOrdered);
+    assert_eq!(received.len(), 4);
+    assert!(received[..2].contains(&(unreliable, 10)));
+    let reliable_messages: Vec<u8> = received
+        .iter()
+        .filter(|(channel_id, _)| *channel_id == reliable)
+        .map(|(_, message)| *message)
+        .collect();
+    assert_eq!(reliable_messages, vec![0, 1, 2]);
+
+    // Messages from the client to the server
+    client.send_message(DefaultChannel::ReliableUnordered, vec![20]);
+    for packet in client.get_packets_to_send() {
+        server.process_packet_from(&packet, client_id).unwrap();
+    }
+    assert_eq!(
+        server.receive_any_message(client_id),
+        Some((u8::from(DefaultChannel::ReliableUnordered), Bytes::from(vec![20])))
+    );
+    assert_eq!(server.receive_any_message(client_id), None);
+    assert_eq!(server.receive_any_message(ClientId::from_raw(1)), None);
+}
+
+#[test]
+fn broadcast_message_with() {
+    let mut server = RenetServer::new(ConnectionConfig::default());
+    let mut clients: Vec<(ClientId, RenetClient)> = (0..3)
+        .map(|id| (ClientId::from_raw(id), RenetClient::new(ConnectionConfig::default())))
+        .collect();
+    for (client_id, _) in clients.iter() {
+        server.add_connection(*client_id);
+    }
+
+    let mut calls = vec![];
+    server.broadcast_message_with(DefaultChannel::ReliableOrdered, |client_id| {
+        calls.push(client_id);
+        match client_id.raw() {
+            1 => None,
+            id => Some(Bytes::from(vec![id as u8])),
+        }
+    });
+    calls.sort_by_key(|client_id| client_id.raw());
+    assert_eq!(calls, clients.iter().map(|(client_id, _)| *client_id).collect::<Vec<_>>());
+
+    server.update(Duration::ZERO);
+    for (client_id, client) in clients.iter_mut() {
+        for packet in server.get_packets_to_send(*client_id).unwrap() {
+            client.process_packet(&packet);
+        }
+        let received: Vec<Bytes> = client.receive_messages(DefaultChannel::ReliableOrdered).collect();
+        match client_id.raw() {
+            1 => assert!(received.is_empty()),
+            id => assert_eq!(received, vec![Bytes::from(vec![id as u8])]),
+        }
+    }
+}
+
+#[test]
+fn send_message_to() {
+    let mut server = RenetServer::new(ConnectionConfig::default());
+    let mut clients: Vec<(ClientId, RenetClient)> = (0..3)
+        .map(|id| (ClientId::from_raw(id), RenetClient::new(ConnectionConfig::default())))
+        .collect();
+    for (client_id, _) in clients.iter() {
+        server.add_connection(*client_id);
+    }
+    server.disconnect(ClientId::from_raw(2));
+
+    // Duplicated ids receive the message once
+    let recipients = [0, 2, 5, 0].map(ClientId::from_raw);
+    let not_sent = server.send_message_to(&recipients, DefaultChannel::ReliableOrdered, vec![1, 2, 3]);
+    assert_eq!(not_sent, vec![ClientId::from_raw(2), ClientId::from_raw(5)]);
+
+    server.update(Duration::ZERO);
+    for (client_id, client) in clients.iter_mut() {
+        let Ok(packets) = server.get_packets_to_send(*client_id) else {
+            continue;
+        };
+        for packet in packets {
+            client.process_packet(&packet);
+        }
+        let received: Vec<Bytes> = client.receive_messages(DefaultChannel::ReliableOrdered).collect();
+        match client_id.raw() {
+            0 => assert_eq!(received, vec![Bytes::from(vec![1, 2, 3])]),
+            _ => assert!(received.is_empty()),
+        }
+    }
+}
+
+#[test]
+fn send_message_to_refused() {
+    let channels = vec![ChannelConfig {
+        max_queued_messages: Some(1),
+        queue_policy: QueuePolicy::RejectSend,
+        ..ChannelConfig::new(
+            0,
+            1000,
+            SendType::ReliableOrdered {
+                resend_time: Duration::from_millis(300),
+            },
+        )
+    }];
+    let config = ConnectionConfig {
+        server_channels_config: channels.clone(),
+        client_channels_config: channels,
+        ..Default::default()
+    };
+    let mut server = RenetServer::new(config.clone());
+    let mut clients: Vec<(ClientId, RenetClient)> = (0..2)
+        .map(|id| (ClientId::from_raw(id), RenetClient::new(config.clone())))
+        .collect();
+    for (client_id, _) in clients.iter() {
+        server.add_connection(*client_id);
+    }
+
+    // The queue of the first client is full, the message is refused and it stays connected
+    let full_client = ClientId::from_raw(0);
+    assert!(server.send_message(full_client, 0, vec![0; 10]).is_some());
+    let not_sent = server.send_message_to(&[full_client, ClientId::from_raw(1)], 0, vec![1; 10]);
+    assert_eq!(not_sent, vec![full_client]);
+    assert!(server.is_connected(full_client));
+
+    server.update(Duration::ZERO);
+    for (client_id, client) in clients.iter_mut() {
+        for packet in server.get_packets_to_send(*client_id).unwrap() {
+            client.process_packet(&packet);
+        }
+        let received: Vec<Bytes> = client.receive_messages(0).collect();
+        match client_id.raw() {
+            0 => assert_eq!(received, vec![Bytes::from(vec![0; 10])]),
+            _ => assert_eq!(received, vec![Bytes::from(vec![1; 10])]),
+        }
+    }
+}
+
+#[test]
+fn fast_retransmit() {
+    // Returns the time until the first message is received, the packets carrying it the first time are lost
+    let first_message_delay = |fast_retransmit_threshold: Option<u64>| {
+        let config = ConnectionConfig {
+            fast_retransmit_threshold,
+            ..Default::default()
+        };
+        let mut server = RenetServer::new(config.clone());
+        let mut client = RenetClient::new(config);
+        let client_id = ClientId::from_raw(0);
+        server.add_connection(client_id);
+
+        let exchange = |server: &mut RenetServer, client: &mut RenetClient, lose: bool| {
+            server.update(Duration::from_millis(16));
+            client.update(Duration::from_millis(16));
+            for packet in server.get_packets_to_send(client_id).unwrap() {
+                if !lose {
+                    client.process_packet(&packet);
+                }
+            }
+            for packet in client.get_packets_to_send() {
+                server.process_packet_from(&packet, client_id).unwrap();
+            }
+        };
+        for _ in 0..5 {
+            exchange(&mut server, &mut client, false);
+        }
+
+        server.send_message(client_id, DefaultChannel::ReliableOrdered, vec![0]);
+        exchange(&mut server, &mut client, true);
+        let mut elapsed = Duration::from_millis(16);
+        for index in 1..100u8 {
+            server.send_message(client_id, DefaultChannel::ReliableOrdered, vec![index]);
+            exchange(&mut server, &mut client, false);
+            elapsed += Duration::from_millis(16);
+            if let Some(message) = client.receive_message(DefaultChannel::ReliableOrdered) {
+                assert_eq!(message[0], 0);
+                return elapsed;
+            }
+        }
+        panic!("first message never received");
+    };
+
+    let with_timer = first_message_delay(None);
+    let fast = first_message_delay(Some(3));
+    assert!(with_timer >= Duration::from_millis(300), "{with_timer:?}");
+    assert!(fast <= Duration::from_millis(100), "{fast:?}");
+}
+
+#[test]
+fn small_messages_interleaved_with_large_message() {
+    // The large message takes many ticks, on the first or the last of the default channels
+    let arrangements = [
+        (DefaultChannel::ReliableOrdered, DefaultChannel::ReliableUnordered),
+        (DefaultChannel::ReliableUnordered, DefaultChannel::ReliableOrdered),
+    ];
+    for (large_channel, small_channel) in arrangements {
+        let mut server = RenetServer::new(ConnectionConfig::default());
+        let mut client = RenetClient::new(ConnectionConfig::default());
+        let client_id = ClientId::from_raw(0);
+        server.add_connection(client_id);
+
+        server.send_message(client_id, large_channel, vec![7; 1024 * 1024]);
+        for tick in 0..10u8 {
+            server.send_message(client_id, small_channel, vec![tick]);
+            server.update(Duration::from_millis(16));
+            client.update(Duration::from_millis(16));
+            for packet in server.get_packets_to_send(client_id).unwrap() {
+                client.process_packet(&packet);
+            }
+            for packet in client.get_packets_to_send() {
+                server.process_packet_from(&packet, client_id).unwrap();
+            }
+
+            // Every channel gets its share of the bytes of the tick by priority
+            let received: Vec<Bytes> = client.receive_messages(small_channel).collect();
+            assert_eq!(received, vec![Bytes::from(vec![tick])]);
+        }
+        assert!(client.receive_message(large_channel).is_none());
+        assert!(client.sliced_receive_progress(large_channel).is_some());
+    }
+}
+
+#[test]
+fn urgent_channel_packed_first() {
+    let mut config = ConnectionConfig::default();
+    config.server_channels_config[2].urgent = true;
+    let mut server = RenetServer::new(config.clone());
+    let mut client = RenetClient::new(config);
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+
+    // The reliable ordered channel is the last of the default channels
+    for _ in 0..2 {
+        server.update(Duration::from_millis(16));
+        client.update(Duration::from_millis(16));
+        for packet in server.get_packets_to_send(client_id).unwrap() {
+            client.process_packet(&packet);
+        }
+        for packet in client.get_packets_to_send() {
+            server.process_packet_from(&packet, client_id).unwrap();
+        }
+    }
+
+    server.send_message(client_id, DefaultChannel::Unreliable, vec![0]);
+    server.send_message(client_id, DefaultChannel::ReliableUnordered, vec![1]);
+    server.send_message(client_id, DefaultChannel::ReliableOrdered, vec![2]);
+    server.update(Duration::from_millis(16));
+    let packets = server.get_packets_to_send(client_id).unwrap();
+    assert!(packets.len() >= 3);
+    client.process_packet(&packets[0]);
+
+    assert_eq!(client.receive_message(DefaultChannel::ReliableOrdered), Some(Bytes::from(vec![2])));
+    assert_eq!(client.receive_message(DefaultChannel::Unreliable), None);
+    assert_eq!(client.receive_message(DefaultChannel::ReliableUnordered), None);
+}
+
+#[test]
+fn paused_channel_resends_sent_messages() {
+    let mut server = RenetServer::new(ConnectionConfig::default());
+    let mut client = RenetClient::new(ConnectionConfig::default());
+    let client_id = ClientId::from_raw(0);
+    server.add_connection(client_id);
+
+    let exchange = |server: &mut RenetServer, client: &mut RenetClient, lose: bool| {
+        server.update(Duration::from_millis(16));
+        client.update(Duration::from_millis(16));
+        for packet in server.get_packets_to_send(client_id).unwrap() {
+            if !lose {
+                client.process_packet(&packet);
+            }
+        }
+        for packet in client.get_packets_to_send() {
+            server.process_packet_from(&packet, client_id).unwrap();
+        }
+    };
+    for _ in 0..5 {
+        exchange(&mut server, &mut client, false);
+    }
+
+    // The first message is lost, then the channel is paused while more messages are queued
+    server.send_message(client_id, DefaultChannel::ReliableOrdered, vec![0]);
+    exchange(&mut server, &mut client, true);
+    server.set_channel_paused(client_id, DefaultChannel::ReliableOrdered, true).unwrap();
+    for index in 1..10u8 {
+        server.send_message(client_id, DefaultChannel::ReliableOrdered, vec![index]);
+    }
+
+    // Only the message sent before the pause is resent
+    for _ in 0..40 {
+        exchange(&mut server, &mut client, false);
+    }
+    assert_eq!(client.receive_message(DefaultChannel::ReliableOrdered), Some(Bytes::from(vec![0])));
+    assert_eq!(client.receive_message(DefaultChannel::ReliableOrdered), None);
+
+    server
+        .set_channel_paused(client_id, DefaultChannel::ReliableOrdered, false)
+        .unwrap();
+    for _ in 0..5 {
+        exchange(&mut server, &mut client, false);
+    }
+    for index in 1..10u8 {
+        assert_eq!(
+            client.receive_message(DefaultChannel::ReliableOrdered),
+            Some(Bytes::from(vec![index]))
+        );
+    }
+    assert_eq!(client.receive_message(DefaultChannel::ReliableOrdered), None);
+}
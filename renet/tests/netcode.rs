@@ -0,0 +1,746 @@
+#![cfg(feature = "transport")]
+//! End to end tests of RenetServer and RenetClient through the netcode protocol.
+//! Packets are carried in memory between the endpoints, following the same steps as the netcode transports.
+
+use std::{net::SocketAddr, time::Duration};
+
+use bytes::Bytes;
+use renet::{
+    transport::{
+        ConnectQueueConfig, ConnectToken, NetcodeClientTransport, NetcodeDisconnectReason, NetcodeServerTransport, ServerAuthentication,
+        ServerConfig, NETCODE_KEY_BYTES,
+    },
+    ClientId, ConnectionConfig, DefaultChannel, DisconnectReason, RenetClient, RenetConnectionStatus, RenetServer, ServerEvent,
+    TransferError, UsageWarning, MAX_TRANSFER_TOKEN_BYTES,
+};
+use renetcode::{ClientAuthentication, NetcodeClient, NetcodeServer, ServerResult};
+
+const PRIVATE_KEY: &[u8; NETCODE_KEY_BYTES] = b"an example very very secret key."; // 32-bytes
+const PROTOCOL_ID: u64 = 7;
+const DELTA: Duration = Duration::from_millis(16);
+
+// Fails to compile if the transports can't be moved to a network thread with their connection
+const _: () = {
+    const fn assert_send<T: Send>() {}
+    assert_send::<NetcodeServerTransport>();
+    assert_send::<NetcodeClientTransport>();
+};
+
+fn server_addr() -> SocketAddr {
+    "127.0.0.1:5000".parse().unwrap()
+}
+
+struct TestClient {
+    addr: SocketAddr,
+    connection: RenetClient,
+    netcode: NetcodeClient,
+    // Packets are not delivered to or from this client
+    stalled: bool,
+}
+
+impl TestClient {
+    fn new(client_id: u64, current_time: Duration) -> Self {
+        let connect_token = generate_token(client_id, current_time, 300);
+        Self::with_token(client_id, current_time, connect_token)
+    }
+
+    fn with_token(client_id: u64, current_time: Duration, connect_token: ConnectToken) -> Self {
+        let authentication = ClientAuthentication::Secure { connect_token };
+        Self {
+            addr: format!("127.0.0.1:{}", 6000 + client_id).parse().unwrap(),
+            connection: RenetClient::new(ConnectionConfig::default()),
+            netcode: NetcodeClient::new(current_time, authentication).unwrap(),
+            stalled: false,
+        }
+    }
+
+    fn id(&self) -> ClientId {
+        ClientId::from_raw(self.netcode.client_id())
+    }
+}
+
+// In memory replacement of the netcode transports,
+// drops one of every `drop_every` packets when set.
+struct Shuttle {
+    current_time: Duration,
+    server: RenetServer,
+    netcode_server: NetcodeServer,
+    clients: Vec<TestClient>,
+    drop_every: Option<usize>,
+    packet_count: usize,
+}
+
+fn generate_token(client_id: u64, current_time: Duration, expire_seconds: u64) -> ConnectToken {
+    ConnectToken::generate(
+        current_time,
+        PROTOCOL_ID,
+        expire_seconds,
+        client_id,
+        5,
+        vec![server_addr()],
+        None,
+        PRIVATE_KEY,
+    )
+    .unwrap()
+}
+
+impl Shuttle {
+    fn new(max_clients: usize) -> Self {
+        Self::with_time(max_clients, Duration::ZERO)
+    }
+
+    fn with_time(max_clients: usize, current_time: Duration) -> Self {
+        let config = ServerConfig {
+            current_time,
+            max_clients,
+            protocol_id: PROTOCOL_ID,
+            public_addresses: vec![server_addr()],
+            authentication: ServerAuthentication::Secure { private_key: *PRIVATE_KEY },
+        };
+
+        Self {
+            current_time,
+            server: RenetServer::new(ConnectionConfig::default()),
+            netcode_server: NetcodeServer::new(config),
+            clients: vec![],
+            drop_every: None,
+            packet_count: 0,
+        }
+    }
+
+    fn add_client(&mut self, client_id: u64) -> usize {
+        self.clients.push(TestClient::new(client_id, self.current_time));
+        self.clients.len() - 1
+    }
+
+    fn should_drop(&mut self) -> bool {
+        self.packet_count += 1;
+        matches!(self.drop_every, Some(n) if self.packet_count % n == 0)
+    }
+
+    // Handles the result from the netcode server, returns the packet that should be delivered to a client.
+    fn handle_server_result(server: &mut RenetServer, result: ServerResult) -> Option<(SocketAddr, Vec<u8>)> {
+        match result {
+            ServerResult::None => None,
+            ServerResult::PacketToSend { addr, payload } => Some((addr, payload.to_vec())),
+            ServerResult::Payload { client_id, payload } => {
+                server.process_packet_from(payload, ClientId::from_raw(client_id)).unwrap();
+                None
+            }
+            ServerResult::ClientConnected {
+                client_id, addr, payload, ..
+            } => {
+                server.add_connection(ClientId::from_raw(client_id));
+                Some((addr, payload.to_vec()))
+            }
+            ServerResult::ClientDisconnected { client_id, addr, payload } => {
+                server.remove_connection(ClientId::from_raw(client_id));
+                payload.map(|payload| (addr, payload.to_vec()))
+            }
+            ServerResult::ClientQueued { addr, position, payload } => {
+                server.client_queued(addr, position);
+                Some((addr, payload.to_vec()))
+            }
+        }
+    }
+
+    fn deliver_to_client(&mut self, addr: SocketAddr, mut packet: Vec<u8>) {
+        if self.should_drop() {
+            return;
+        }
+
+        let Some(client) = self.clients.iter_mut().find(|c| c.addr == addr) else {
+            return;
+        };
+        if client.stalled {
+            return;
+        }
+
+        if let Some(payload) = client.netcode.process_packet(&mut packet) {
+            client.connection.process_packet(payload);
+        }
+    }
+
+    fn step(&mut self) {
+        self.current_time += DELTA;
+
+        // Server update
+        self.server.update(DELTA);
+        self.netcode_server.update(DELTA);
+
+        let mut to_clients: Vec<(SocketAddr, Vec<u8>)> = vec![];
+        for client_id in self.netcode_server.clients_id() {
+            let result = self.netcode_server.update_client(client_id);
+            to_clients.extend(Self::handle_server_result(&mut self.server, result));
+        }
+        for client_id in self.server.disconnections_id() {
+            let result = self.netcode_server.disconnect(client_id.raw());
+            to_clients.extend(Self::handle_server_result(&mut self.server, result));
+        }
+
+        // Clients update and send packets
+        let mut to_server: Vec<(SocketAddr, Vec<u8>)> = vec![];
+        for client in self.clients.iter_mut() {
+            client.connection.update(DELTA);
+
+            if client.netcode.is_disconnected() {
+                client.connection.disconnect_due_to_transport();
+                continue;
+            }
+
+            if client.connection.is_disconnected() {
+                let (_, packet) = client.netcode.disconnect().unwrap();
+                to_server.push((client.addr, packet.to_vec()));
+                continue;
+            }
+
+            if client.netcode.is_connected() {
+                client.connection.set_connected();
+            } else if let Some(position) = client.netcode.queued_position() {
+                client.connection.set_queued(position);
+            } else {
+                client.connection.set_connecting();
+            }
+
+            if let Some((packet, _)) = client.netcode.update(DELTA) {
+                to_server.push((client.addr, packet.to_vec()));
+            }
+
+            if client.netcode.is_connected() {
+                for packet in client.connection.get_packets_to_send() {
+                    let (_, payload) = client.netcode.generate_payload_packet(&packet).unwrap();
+                    to_server.push((client.addr, payload.to_vec()));
+                }
+            }
+
+            if client.stalled {
+                to_server.retain(|(addr, _)| *addr != client.addr);
+            }
+        }
+
+        for (addr, mut packet) in to_server {
+            if self.should_drop() {
+                continue;
+            }
+            let result = self.netcode_server.process_packet(addr, &mut packet);
+            to_clients.extend(Self::handle_server_result(&mut self.server, result));
+        }
+
+        // Server sends packets
+        for client_id in self.server.clients_id() {
+            for packet in self.server.get_packets_to_send(client_id).unwrap() {
+                let (addr, payload) = self.netcode_server.generate_payload_packet(client_id.raw(), &packet).unwrap();
+                to_clients.push((addr, payload.to_vec()));
+            }
+        }
+
+        for (addr, packet) in to_clients {
+            self.deliver_to_client(addr, packet);
+        }
+    }
+
+    fn step_for(&mut self, duration: Duration) {
+        let end = self.current_time + duration;
+        while self.current_time < end {
+            self.step();
+        }
+    }
+
+    fn step_until(&mut self, mut condition: impl FnMut(&mut Self) -> bool) {
+        for _ in 0..1000 {
+            if condition(self) {
+                return;
+            }
+            self.step();
+        }
+        panic!("condition not reached");
+    }
+
+    fn connect(&mut self, client_id: u64) -> usize {
+        let index = self.add_client(client_id);
+        self.step_until(|s| s.clients[index].connection.is_connected() && s.server.is_connected(ClientId::from_raw(client_id)));
+        assert!(matches!(
+            self.server.get_event(),
+            Some(ServerEvent::ClientConnected { client_id: id, .. }) if id == ClientId::from_raw(client_id)
+        ));
+        index
+    }
+}
+
+#[test]
+fn connect_and_exchange_messages() {
+    let mut shuttle = Shuttle::new(16);
+    shuttle.drop_every = Some(5);
+    let index = shuttle.connect(1);
+    let client_id = shuttle.clients[index].id();
+
+    let channels: [u8; 2] = [DefaultChannel::ReliableOrdered.into(), DefaultChannel::ReliableUnordered.into()];
+    for (i, channel_id) in channels.into_iter().enumerate() {
+        for j in 0..20u8 {
+            shuttle.server.send_message(client_id, channel_id, Bytes::from(vec![i as u8, j]));
+            shuttle.clients[index]
+                .connection
+                .send_message(channel_id, Bytes::from(vec![i as u8, j]));
+        }
+        // Sliced message
+        shuttle.server.send_message(client_id, channel_id, Bytes::from(vec![7; 5000]));
+    }
+
+    let mut client_received = vec![vec![]; channels.len()];
+    let mut server_received = vec![vec![]; channels.len()];
+    shuttle.step_until(|s| {
+        for (i, channel_id) in channels.into_iter().enumerate() {
+            while let Some(message) = s.clients[index].connection.receive_message(channel_id) {
+                client_received[i].push(message);
+            }
+            while let Some(message) = s.server.receive_message(client_id, channel_id) {
+                server_received[i].push(message);
+            }
+        }
+        client_received.iter().all(|m| m.len() == 21) && server_received.iter().all(|m| m.len() == 20)
+    });
+
+    // Ordered channel keeps the sending order
+    for (j, message) in server_received[0].iter().enumerate() {
+        assert_eq!(message[..], [0, j as u8]);
+    }
+    assert_eq!(client_received[0][20].len(), 5000);
+    assert_eq!(client_received[1].iter().filter(|m| m.len() == 5000).count(), 1);
+
+    // Unreliable messages may be lost, but they arrive
+    shuttle.drop_every = None;
+    shuttle
+        .server
+        .send_message(client_id, DefaultChannel::Unreliable, Bytes::from("unreliable"));
+    shuttle.step();
+    let message = shuttle.clients[index].connection.receive_message(DefaultChannel::Unreliable);
+    assert_eq!(message.unwrap(), "unreliable");
+}
+
+#[test]
+fn server_disconnects_client() {
+    let mut shuttle = Shuttle::new(16);
+    let index = shuttle.connect(1);
+    let client_id = shuttle.clients[index].id();
+
+    shuttle.server.disconnect(client_id);
+    shuttle.step_until(|s| s.clients[index].connection.is_disconnected());
+
+    assert_eq!(
+        shuttle.clients[index].netcode.disconnect_reason(),
+        Some(NetcodeDisconnectReason::DisconnectedByServer)
+    );
+    assert_eq!(
+        shuttle.clients[index].connection.disconnect_reason(),
+        Some(DisconnectReason::Transport)
+    );
+    assert!(matches!(
+        shuttle.server.get_event(),
+        Some(ServerEvent::ClientDisconnected { client_id: id, reason: DisconnectReason::DisconnectedByServer, .. }) if id == client_id
+    ));
+}
+
+#[test]
+fn client_disconnects() {
+    let mut shuttle = Shuttle::new(16);
+    let index = shuttle.connect(1);
+    let client_id = shuttle.clients[index].id();
+
+    shuttle.clients[index].connection.disconnect();
+    shuttle.step_until(|s| !s.server.has_connections());
+
+    assert_eq!(
+        shuttle.clients[index].connection.disconnect_reason(),
+        Some(DisconnectReason::DisconnectedByClient)
+    );
+    // The server only knows that the transport was terminated
+    assert!(matches!(
+        shuttle.server.get_event(),
+        Some(ServerEvent::ClientDisconnected { client_id: id, reason: DisconnectReason::Transport, .. }) if id == client_id
+    ));
+}
+
+#[test]
+fn expired_token_rejected() {
+    // Server clock is ahead of the token expiration
+    let mut shuttle = Shuttle::with_time(16, Duration::from_secs(60));
+    let connect_token = generate_token(1, Duration::ZERO, 30);
+    shuttle.clients.push(TestClient::with_token(1, Duration::ZERO, connect_token));
+
+    shuttle.step_until(|s| s.clients[0].netcode.is_disconnected());
+    assert_eq!(shuttle.server.connected_clients(), 0);
+    assert_eq!(shuttle.server.get_event(), None);
+    assert_eq!(
+        shuttle.clients[0].netcode.disconnect_reason(),
+        Some(NetcodeDisconnectReason::ConnectionRequestTimedOut)
+    );
+}
+
+#[test]
+fn server_full_denied() {
+    let mut shuttle = Shuttle::new(1);
+    shuttle.connect(1);
+
+    let index = shuttle.add_client(2);
+    shuttle.step_until(|s| s.clients[index].netcode.is_disconnected());
+    assert_eq!(
+        shuttle.clients[index].netcode.disconnect_reason(),
+        Some(NetcodeDisconnectReason::ConnectionDenied)
+    );
+    assert_eq!(shuttle.server.connected_clients(), 1);
+}
+
+#[test]
+fn server_full_queued() {
+    let mut shuttle = Shuttle::new(1);
+    shuttle.netcode_server.set_connect_queue(Some(ConnectQueueConfig {
+        max_size: 1,
+        entry_ttl: Duration::from_secs(1),
+    }));
+    let first = shuttle.connect(1);
+
+    let index = shuttle.add_client(2);
+    let addr = shuttle.clients[index].addr;
+    shuttle
+        .step_until(|s| s.clients[index].connection.connection_status() == RenetConnectionStatus::Connecting { queued_position: Some(1) });
+    assert!(matches!(
+        shuttle.server.get_event(),
+        Some(ServerEvent::ClientQueued { addr: queued_addr, position: 1, .. }) if queued_addr == addr
+    ));
+
+    // Queue is full
+    let denied = shuttle.add_client(3);
+    shuttle.step_until(|s| s.clients[denied].netcode.is_disconnected());
+    assert_eq!(
+        shuttle.clients[denied].netcode.disconnect_reason(),
+        Some(NetcodeDisconnectReason::ConnectionDenied)
+    );
+
+    // Queued client takes the freed slot
+    shuttle.clients[first].connection.disconnect();
+    shuttle.step_until(|s| s.clients[index].connection.is_connected());
+    assert!(shuttle.server.is_connected(ClientId::from_raw(2)));
+    assert!(matches!(
+        shuttle.server.get_event(),
+        Some(ServerEvent::ClientDisconnected { client_id: id, reason: DisconnectReason::Transport, .. }) if id == ClientId::from_raw(1)
+    ));
+    assert!(matches!(
+        shuttle.server.get_event(),
+        Some(ServerEvent::ClientConnected { client_id: id, .. }) if id == ClientId::from_raw(2)
+    ));
+}
+
+#[test]
+fn timeout_detected() {
+    let mut shuttle = Shuttle::new(16);
+    let index = shuttle.connect(1);
+    let client_id = shuttle.clients[index].id();
+
+    shuttle.clients[index].stalled = true;
+    // Token timeout is 5 seconds
+    shuttle.step_for(Duration::from_secs(4));
+    assert!(shuttle.server.is_connected(client_id));
+
+    shuttle.step_until(|s| !s.server.has_connections());
+    assert!(matches!(
+        shuttle.server.get_event(),
+        Some(ServerEvent::ClientDisconnected { client_id: id, reason: DisconnectReason::Transport, .. }) if id == client_id
+    ));
+
+    shuttle.step_until(|s| s.clients[index].connection.is_disconnected());
+    assert_eq!(
+        shuttle.clients[index].netcode.disconnect_reason(),
+        Some(NetcodeDisconnectReason::ConnectionTimedOut)
+    );
+}
+
+#[test]
+fn manual_transports() {
+    let server_config = ServerConfig {
+        current_time: Duration::ZERO,
+        max_clients: 4,
+        protocol_id: PROTOCOL_ID,
+        public_addresses: vec![server_addr()],
+        authentication: ServerAuthentication::Secure { private_key: *PRIVATE_KEY },
+    };
+    let mut server = RenetServer::new(ConnectionConfig::default());
+    let mut server_transport = NetcodeServerTransport::new_manual(server_config);
+
+    let client_addr: SocketAddr = "127.0.0.1:6001".parse().unwrap();
+    let connect_token = generate_token(1, Duration::ZERO, 300);
+    let mut client = RenetClient::new(ConnectionConfig::default());
+    let mut client_transport = NetcodeClientTransport::new_manual(Duration::ZERO, ClientAuthentication::Secure { connect_token }).unwrap();
+
+    let mut step = |server: &mut RenetServer, client: &mut RenetClient| {
+        client.update(DELTA);
+        // Both return an error once the client is disconnected
+        let _ = client_transport.update(DELTA, client);
+        let _ = client_transport.send_packets(client);
+        let client_packets: Vec<Bytes> = client_transport.drain_outgoing_packets().collect();
+
+        server.update(DELTA);
+        server_transport.update(DELTA, server).unwrap();
+        for packet in client_packets {
+            server_transport.process_incoming_packet(client_addr, &packet, server);
+        }
+        server_transport.send_packets(server);
+        for (addr, packet) in server_transport.drain_outgoing_packets().collect::<Vec<_>>() {
+            assert_eq!(addr, client_addr);
+            client_transport.process_incoming_packet(&packet, client);
+        }
+    };
+
+    for _ in 0..10 {
+        step(&mut server, &mut client);
+    }
+    assert!(client.is_connected());
+    let client_id = ClientId::from_raw(1);
+    assert!(server.is_connected(client_id));
+
+    server.send_message(client_id, DefaultChannel::ReliableOrdered, "to client");
+    client.send_message(DefaultChannel::ReliableOrdered, "to server");
+    for _ in 0..3 {
+        step(&mut server, &mut client);
+    }
+    assert_eq!(client.receive_message(DefaultChannel::ReliableOrdered).unwrap(), "to client");
+    assert_eq!(
+        server.receive_message(client_id, DefaultChannel::ReliableOrdered).unwrap(),
+        "to server"
+    );
+
+    // Wire bytes include the netcode overhead and the estimated UDP/IP headers
+    let client_counters = client.network_info().counters;
+    let server_counters = server.network_info(client_id).unwrap().counters;
+    assert!(client_counters.wire_bytes_sent > client_counters.bytes_sent + 28 * client_counters.packets_sent);
+    assert!(client_counters.wire_bytes_received > client_counters.bytes_received + 28 * client_counters.packets_received);
+    assert_eq!(client_counters.wire_bytes_sent, server_counters.wire_bytes_received);
+    assert_eq!(client_counters.wire_bytes_received, server_counters.wire_bytes_sent);
+
+    client.disconnect();
+    for _ in 0..3 {
+        step(&mut server, &mut client);
+    }
+    assert!(!server.is_connected(client_id));
+}
+
+#[test]
+fn transfer_client_to_another_server() {
+    let server_config = |addr: SocketAddr| ServerConfig {
+        current_time: Duration::ZERO,
+        max_clients: 4,
+        protocol_id: PROTOCOL_ID,
+        public_addresses: vec![addr],
+        authentication: ServerAuthentication::Secure { private_key: *PRIVATE_KEY },
+    };
+    let addr_a = server_addr();
+    let addr_b: SocketAddr = "127.0.0.1:5001".parse().unwrap();
+    let mut servers = [
+        (
+            RenetServer::new(ConnectionConfig::default()),
+            NetcodeServerTransport::new_manual(server_config(addr_a)),
+        ),
+        (
+            RenetServer::new(ConnectionConfig::default()),
+            NetcodeServerTransport::new_manual(server_config(addr_b)),
+        ),
+    ];
+
+    let client_addr: SocketAddr = "127.0.0.1:6001".parse().unwrap();
+    let client_id = ClientId::from_raw(1);
+    let connect_token = generate_token(1, Duration::ZERO, 300);
+    let mut client = RenetClient::new(ConnectionConfig::default());
+    let mut client_transport = NetcodeClientTransport::new_manual(Duration::ZERO, ClientAuthentication::Secure { connect_token }).unwrap();
+
+    // Packets of the client are only delivered to the current server, both servers are updated
+    let step = |servers: &mut [(RenetServer, NetcodeServerTransport); 2],
+                client_transport: &mut NetcodeClientTransport,
+                client: &mut RenetClient,
+                current: usize| {
+        client.update(DELTA);
+        let _ = client_transport.update(DELTA, client);
+        let _ = client_transport.send_packets(client);
+        let client_packets: Vec<Bytes> = client_transport.drain_outgoing_packets().collect();
+
+        for (index, (server, server_transport)) in servers.iter_mut().enumerate() {
+            server.update(DELTA);
+            server_transport.update(DELTA, server).unwrap();
+            if index == current {
+                for packet in client_packets.iter() {
+                    server_transport.process_incoming_packet(client_addr, packet, server);
+                }
+            }
+            server_transport.send_packets(server);
+            for (_, packet) in server_transport.drain_outgoing_packets().collect::<Vec<_>>() {
+                client_transport.process_incoming_packet(&packet, client);
+            }
+        }
+    };
+
+    for _ in 0..10 {
+        step(&mut servers, &mut client_transport, &mut client, 0);
+    }
+    assert!(client.is_connected());
+    assert!(servers[0].0.is_connected(client_id));
+
+    let oversized = vec![0u8; MAX_TRANSFER_TOKEN_BYTES + 1];
+    assert_eq!(
+        servers[0].0.transfer_client(client_id, oversized),
+        Err(TransferError::InvalidTokenSize {
+            len: MAX_TRANSFER_TOKEN_BYTES + 1
+        })
+    );
+    assert_eq!(
+        servers[0].0.transfer_client(ClientId::from_raw(2), vec![0u8; 8]),
+        Err(TransferError::ClientNotFound)
+    );
+
+    let mut token_b = ConnectToken::generate(Duration::ZERO, PROTOCOL_ID, 300, 1, 5, vec![addr_b], None, PRIVATE_KEY).unwrap();
+    let mut token_bytes = Vec::new();
+    token_b.write(&mut token_bytes).unwrap();
+    servers[0].0.transfer_client(client_id, token_bytes).unwrap();
+    assert_eq!(
+        servers[0].0.transfer_client(client_id, vec![0u8; 8]),
+        Err(TransferError::AlreadyTransferring)
+    );
+
+    for _ in 0..10 {
+        step(&mut servers, &mut client_transport, &mut client, 0);
+    }
+    assert!(client.is_disconnected());
+    assert_eq!(client.disconnect_reason(), Some(DisconnectReason::Transferred));
+    assert!(!servers[0].0.is_connected(client_id));
+    let mut disconnected = None;
+    while let Some(event) = servers[0].0.get_event() {
+        if let ServerEvent::ClientDisconnected { reason, .. } = event {
+            disconnected = Some(reason);
+        }
+    }
+    assert_eq!(disconnected, Some(DisconnectReason::Transferred));
+
+    // The client reconnects to the other server with the received token
+    let token = client.take_transfer_token().unwrap();
+    assert_eq!(client.take_transfer_token(), None);
+    token_b = ConnectToken::read(&mut token.as_ref()).unwrap();
+    assert_eq!(token_b.server_addresses[0], Some(addr_b));
+    client_transport
+        .connect(Duration::ZERO, ClientAuthentication::Secure { connect_token: token_b })
+        .unwrap();
+    let mut client = RenetClient::new(ConnectionConfig::default());
+    for _ in 0..10 {
+        step(&mut servers, &mut client_transport, &mut client, 1);
+    }
+    assert!(client.is_connected());
+    assert!(servers[1].0.is_connected(client_id));
+}
+
+#[test]
+fn zero_duration_updates_fall_back_to_measured_time() {
+    let config = ConnectionConfig {
+        zero_duration_fallback: true,
+        ..Default::default()
+    };
+    let server_config = ServerConfig {
+        current_time: Duration::ZERO,
+        max_clients: 4,
+        protocol_id: PROTOCOL_ID,
+        public_addresses: vec![server_addr()],
+        authentication: ServerAuthentication::Secure { private_key: *PRIVATE_KEY },
+    };
+    let mut server = RenetServer::new(config.clone());
+    let mut server_transport = NetcodeServerTransport::new_manual(server_config);
+
+    let client_addr: SocketAddr = "127.0.0.1:6001".parse().unwrap();
+    let connect_token = generate_token(1, Duration::ZERO, 300);
+    let mut client = RenetClient::new(config);
+    let mut client_transport = NetcodeClientTransport::new_manual(Duration::ZERO, ClientAuthentication::Secure { connect_token }).unwrap();
+
+    // Returns the number of packets sent by the client
+    let mut step = |server: &mut RenetServer, client: &mut RenetClient, duration: Duration| {
+        client.update(duration);
+        client_transport.update(duration, client).unwrap();
+        client_transport.send_packets(client).unwrap();
+        let client_packets: Vec<Bytes> = client_transport.drain_outgoing_packets().collect();
+
+        server.update(duration);
+        server_transport.update(duration, server).unwrap();
+        for packet in client_packets.iter() {
+            server_transport.process_incoming_packet(client_addr, packet, server);
+        }
+        server_transport.send_packets(server);
+        for (_, packet) in server_transport.drain_outgoing_packets().collect::<Vec<_>>() {
+            client_transport.process_incoming_packet(&packet, client);
+        }
+        client_packets.len()
+    };
+
+    for _ in 0..10 {
+        step(&mut server, &mut client, DELTA);
+    }
+    assert!(client.is_connected());
+
+    // Only keep-alives are sent by the idle client, they need the time to advance
+    let mut client_packets = 0;
+    for _ in 0..1000 {
+        std::thread::sleep(Duration::from_millis(1));
+        client_packets += step(&mut server, &mut client, Duration::ZERO);
+    }
+    assert!(client_packets >= 3);
+    assert!(client.health_check().contains(&UsageWarning::ZeroDurationUpdates));
+    assert!(server.health_check().contains(&UsageWarning::ZeroDurationUpdates));
+    assert!(client.is_connected());
+    assert!(server.is_connected(ClientId::from_raw(1)));
+}
+
+#[test]
+fn queued_disconnect_all() {
+    let server_config = ServerConfig {
+        current_time: Duration::ZERO,
+        max_clients: 4,
+        protocol_id: PROTOCOL_ID,
+        public_addresses: vec![server_addr()],
+        authentication: ServerAuthentication::Secure { private_key: *PRIVATE_KEY },
+    };
+    let mut server = RenetServer::new(ConnectionConfig::default());
+    let mut server_transport = NetcodeServerTransport::new_manual(server_config);
+
+    let mut clients: Vec<(SocketAddr, RenetClient, NetcodeClientTransport)> = (0..3)
+        .map(|i| {
+            let addr: SocketAddr = format!("127.0.0.1:{}", 6010 + i).parse().unwrap();
+            let connect_token = generate_token(i, Duration::ZERO, 300);
+            let transport = NetcodeClientTransport::new_manual(Duration::ZERO, ClientAuthentication::Secure { connect_token }).unwrap();
+            (addr, RenetClient::new(ConnectionConfig::default()), transport)
+        })
+        .collect();
+
+    let mut step = |server: &mut RenetServer, server_transport: &mut NetcodeServerTransport| {
+        server.update(DELTA);
+        server_transport.update(DELTA, server).unwrap();
+        for (addr, client, transport) in clients.iter_mut() {
+            client.update(DELTA);
+            let _ = transport.update(DELTA, client);
+            let _ = transport.send_packets(client);
+            for packet in transport.drain_outgoing_packets().collect::<Vec<_>>() {
+                server_transport.process_incoming_packet(*addr, &packet, server);
+            }
+        }
+        server_transport.send_packets(server);
+        for (addr, packet) in server_transport.drain_outgoing_packets().collect::<Vec<_>>() {
+            let (_, client, transport) = clients.iter_mut().find(|(client_addr, ..)| *client_addr == addr).unwrap();
+            transport.process_incoming_packet(&packet, client);
+        }
+    };
+
+    for _ in 0..10 {
+        step(&mut server, &mut server_transport);
+    }
+    assert_eq!(server.connected_clients(), 3);
+
+    // One client is disconnected each time the packets are sent
+    server_transport.queue_disconnect_all(1);
+    assert_eq!(server_transport.pending_disconnects(), 3);
+    for pending in (0..3).rev() {
+        step(&mut server, &mut server_transport);
+        assert_eq!(server_transport.pending_disconnects(), pending);
+        assert_eq!(server.connected_clients(), pending);
+    }
+}
@@ -0,0 +1,214 @@
+//! Long-running randomized sessions over a lossy link, with the duplicate delivery verifier of the reliable
+//! channels active (tests are built with debug assertions). Seeded so a failure can be reproduced.
+
+use std::time::Duration;
+
+use bytes::Bytes;
+use renet::{ClientId, ConnectionConfig, DefaultChannel, MessageId, RenetClient, RenetServer};
+
+const TICK: Duration = Duration::from_millis(16);
+
+// Xorshift generator, enough for reproducible network conditions
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, max: u64) -> u64 {
+        self.next() % max
+    }
+
+    fn chance(&mut self, percent: u64) -> bool {
+        self.below(100) < percent
+    }
+}
+
+// One direction of the network: drops, delays, reorders and duplicates packets
+struct Link {
+    loss_percent: u64,
+    duplicate_percent: u64,
+    max_delay_ticks: u64,
+    in_flight: Vec<(u64, Vec<u8>)>,
+}
+
+impl Link {
+    fn new(loss_percent: u64, duplicate_percent: u64, max_delay_ticks: u64) -> Self {
+        Self {
+            loss_percent,
+            duplicate_percent,
+            max_delay_ticks,
+            in_flight: vec![],
+        }
+    }
+
+    fn send(&mut self, rng: &mut Rng, tick: u64, packet: Vec<u8>) {
+        if rng.chance(self.loss_percent) {
+            return;
+        }
+        if rng.chance(self.duplicate_percent) {
+            let delay = rng.below(self.max_delay_ticks + 1);
+            self.in_flight.push((tick + delay, packet.clone()));
+        }
+        let delay = rng.below(self.max_delay_ticks + 1);
+        self.in_flight.push((tick + delay, packet));
+    }
+
+    // Packets arriving in the tick, in random order
+    fn receive(&mut self, rng: &mut Rng, tick: u64) -> Vec<Vec<u8>> {
+        let mut arrived = vec![];
+        let mut index = 0;
+        while index < self.in_flight.len() {
+            if self.in_flight[index].0 <= tick {
+                let (_, packet) = self.in_flight.swap_remove(index);
+                arrived.push(packet);
+            } else {
+                index += 1;
+            }
+        }
+        for i in (1..arrived.len()).rev() {
+            arrived.swap(i, rng.below(i as u64 + 1) as usize);
+        }
+        arrived
+    }
+}
+
+// Message with its index, of a random size so some of them are sliced
+fn message(rng: &mut Rng, index: u32) -> Bytes {
+    let len = match rng.chance(10) {
+        true => 1200 + rng.below(8000) as usize,
+        false => 4 + rng.below(100) as usize,
+    };
+    let mut message = vec![(index % 251) as u8; len];
+    message[..4].copy_from_slice(&index.to_le_bytes());
+    message.into()
+}
+
+fn index(message: &[u8]) -> u32 {
+    u32::from_le_bytes(message[..4].try_into().unwrap())
+}
+
+#[derive(Default)]
+struct Received {
+    ordered: Vec<u32>,
+    unordered: Vec<u32>,
+}
+
+impl Received {
+    fn read_client(&mut self, client: &mut RenetClient) {
+        while let Some(message) = client.receive_message(DefaultChannel::ReliableOrdered) {
+            self.ordered.push(index(&message));
+        }
+        while let Some(message) = client.receive_message(DefaultChannel::ReliableUnordered) {
+            self.unordered.push(index(&message));
+        }
+    }
+
+    fn read_server(&mut self, server: &mut RenetServer, client_id: ClientId) {
+        while let Some(message) = server.receive_message(client_id, DefaultChannel::ReliableOrdered) {
+            self.ordered.push(index(&message));
+        }
+        while let Some(message) = server.receive_message(client_id, DefaultChannel::ReliableUnordered) {
+            self.unordered.push(index(&message));
+        }
+    }
+
+    // Every message is received exactly once, in order for the ordered channel
+    fn verify(mut self, sent: u32) {
+        let expected: Vec<u32> = (0..sent).collect();
+        assert_eq!(self.ordered, expected);
+        self.unordered.sort_unstable();
+        assert_eq!(self.unordered, expected);
+    }
+}
+
+// A session between a new client and the server, messages are sent both ways while the link
+// drops, delays, reorders and duplicates packets, then the link is drained before verifying the messages.
+fn soak_session(rng: &mut Rng, server: &mut RenetServer, client_id: ClientId, ticks: u64, loss_percent: u64) {
+    let mut client = RenetClient::new(ConnectionConfig::default());
+    server.add_connection(client_id);
+    client.set_connected();
+
+    let mut to_client = Link::new(loss_percent, 10, 6);
+    let mut to_server = Link::new(loss_percent, 10, 6);
+    let mut client_received = Received::default();
+    let mut server_received = Received::default();
+    let mut sent_to_client = 0;
+    let mut sent_to_server = 0;
+    // Ids of the messages sent to the client, and the ones acked by it
+    let mut sent_ids: Vec<MessageId> = vec![];
+    let mut acked_ids: Vec<MessageId> = vec![];
+
+    let drain_ticks = 600;
+    for tick in 0..ticks + drain_ticks {
+        if tick < ticks {
+            for _ in 0..rng.below(3) {
+                let message_id = server.send_message(client_id, DefaultChannel::ReliableOrdered, message(rng, sent_to_client));
+                sent_ids.extend(message_id);
+                server.send_message(client_id, DefaultChannel::ReliableUnordered, message(rng, sent_to_client));
+                sent_to_client += 1;
+            }
+            for _ in 0..rng.below(3) {
+                client.send_message(DefaultChannel::ReliableOrdered, message(rng, sent_to_server));
+                client.send_message(DefaultChannel::ReliableUnordered, message(rng, sent_to_server));
+                sent_to_server += 1;
+            }
+        } else if tick == ticks {
+            // The link keeps reordering and duplicating while the last messages are delivered
+            to_client.loss_percent = 0;
+            to_server.loss_percent = 0;
+        }
+
+        server.update(TICK);
+        client.update(TICK);
+        for packet in server.get_packets_to_send(client_id).unwrap() {
+            to_client.send(rng, tick, packet);
+        }
+        for packet in client.get_packets_to_send() {
+            to_server.send(rng, tick, packet);
+        }
+        for packet in to_client.receive(rng, tick) {
+            client.process_packet(&packet);
+        }
+        for packet in to_server.receive(rng, tick) {
+            server.process_packet_from(&packet, client_id).unwrap();
+        }
+
+        client_received.read_client(&mut client);
+        server_received.read_server(server, client_id);
+        acked_ids.extend(server.drain_acked_messages(client_id, DefaultChannel::ReliableOrdered));
+    }
+
+    assert_eq!(client.disconnect_reason(), None);
+    assert!(server.is_connected(client_id));
+    assert_eq!(client.network_info().lifetime_counters.duplicate_deliveries, 0);
+    client_received.verify(sent_to_client);
+    server_received.verify(sent_to_server);
+    // Every message is acked exactly once, even when resent or its acks are duplicated
+    acked_ids.sort_unstable();
+    assert_eq!(acked_ids, sent_ids);
+
+    // Reconnects as a new client, nothing of the previous session must be delivered again
+    client.disconnect();
+    server.remove_connection(client_id);
+}
+
+#[test]
+fn soak_reliable_channels_with_reconnects() {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    let mut server = RenetServer::new(ConnectionConfig::default());
+    for session in 0..3 {
+        soak_session(&mut rng, &mut server, ClientId::from_raw(session % 2), 400, 20);
+    }
+}
+
+#[test]
+fn soak_reliable_channels_heavy_loss() {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    let mut server = RenetServer::new(ConnectionConfig::default());
+    soak_session(&mut rng, &mut server, ClientId::from_raw(0), 1000, 40);
+}
@@ -0,0 +1,292 @@
+-- Generated by renet/src/transport/dissector.rs, do not edit.
+-- Wireshark dissector for the decrypted renet packets written by `set_packet_capture_with_payloads`,
+-- available with the `capture_decrypted` feature. Copy it to the personal Lua plugins folder of Wireshark,
+-- shown in Help > About Wireshark > Folders.
+
+local renet = Proto("renet", "Renet")
+
+local packet_types = {
+    [0] = "Protocol version 1",
+    [1] = "Protocol version 1",
+    [2] = "ReliableSlice",
+    [3] = "UnreliableSlice",
+    [4] = "Ack",
+    [5] = "Protocol version 1",
+    [6] = "Ping",
+    [7] = "Pong",
+    [8] = "TransferSlice",
+    [9] = "QuotaExceeded",
+    [10] = "CancelReliable",
+    [11] = "SmallReliable",
+    [12] = "SmallUnreliable",
+    [13] = "ChannelConfig",
+}
+
+local fields = {
+    direction = ProtoField.uint8("renet.direction", "Direction", base.DEC, { [0] = "Sent", [1] = "Received" }),
+    peer = ProtoField.ipv6("renet.peer", "Peer"),
+    peer_port = ProtoField.uint16("renet.peer_port", "Peer port"),
+    packet_type = ProtoField.uint8("renet.packet_type", "Packet type", base.DEC, packet_types),
+    ack_range = ProtoField.string("renet.ack_range", "Acked range"),
+    sequence = ProtoField.uint64("renet.sequence", "Sequence"),
+    channel_id = ProtoField.uint8("renet.channel_id", "Channel id"),
+    message_id = ProtoField.uint64("renet.message_id", "Message id"),
+    slice_index = ProtoField.uint64("renet.slice_index", "Slice index"),
+    num_slices = ProtoField.uint64("renet.num_slices", "Num slices"),
+    length = ProtoField.uint64("renet.length", "Length"),
+    slice = ProtoField.bytes("renet.slice", "Slice"),
+    ack_end = ProtoField.uint64("renet.ack_end", "Ack end"),
+    ack_size = ProtoField.uint64("renet.ack_size", "Ack size"),
+    ack_ranges = ProtoField.uint64("renet.ack_ranges", "Ack ranges"),
+    ack_gap = ProtoField.uint64("renet.ack_gap", "Ack gap"),
+    ping_id = ProtoField.uint64("renet.ping_id", "Ping id"),
+    quota_kind = ProtoField.uint8("renet.quota_kind", "Quota kind"),
+    messages = ProtoField.uint64("renet.messages", "Messages"),
+    message = ProtoField.bytes("renet.message", "Message"),
+    protocol_version = ProtoField.uint8("renet.protocol_version", "Protocol version"),
+    channels = ProtoField.uint64("renet.channels", "Channels"),
+    send_type = ProtoField.uint8("renet.send_type", "Send type"),
+    max_memory_usage_bytes = ProtoField.uint64("renet.max_memory_usage_bytes", "Max memory usage bytes"),
+    has_group = ProtoField.uint8("renet.has_group", "Has group"),
+    group = ProtoField.uint8("renet.group", "Group"),
+    compression = ProtoField.uint8("renet.compression", "Compression"),
+    compression_dictionary = ProtoField.uint64("renet.compression_dictionary", "Compression dictionary"),
+    tracing_context = ProtoField.uint8("renet.tracing_context", "Tracing context"),
+    message_kinds = ProtoField.uint8("renet.message_kinds", "Message kinds"),
+    deduplicate = ProtoField.uint8("renet.deduplicate", "Deduplicate"),
+}
+
+local field_list = {}
+for _, field in pairs(fields) do
+    table.insert(field_list, field)
+end
+renet.fields = field_list
+
+-- The two high bits of the first byte give the length of the varint: 1, 2, 4 or 8 bytes
+local function varint(tvb, offset)
+    local first = tvb(offset, 1):uint()
+    local len = ({ 1, 2, 4, 8 })[math.floor(first / 64) + 1]
+    local value = first % 64
+    for i = 1, len - 1 do
+        value = value * 256 + tvb(offset + i, 1):uint()
+    end
+    return value, len
+end
+
+local function zigzag(value)
+    if value % 2 == 0 then
+        return value / 2
+    end
+    return -(value + 1) / 2
+end
+
+local function add_u8(tvb, tree, offset, field)
+    tree:add(field, tvb(offset, 1))
+    return tvb(offset, 1):uint(), offset + 1
+end
+
+local function add_u64(tvb, tree, offset, field)
+    tree:add(field, tvb(offset, 8))
+    return offset + 8
+end
+
+local function add_varint(tvb, tree, offset, field)
+    local value, len = varint(tvb, offset)
+    tree:add(field, tvb(offset, len), value)
+    return value, offset + len
+end
+
+local function add_payload(tvb, tree, offset, field)
+    local len
+    len, offset = add_varint(tvb, tree, offset, fields.length)
+    if len > 0 then
+        tree:add(field, tvb(offset, len))
+    end
+    return offset + len
+end
+
+local function add_message_id(tvb, tree, offset, previous_id)
+    local id, len = varint(tvb, offset)
+    if previous_id ~= nil then
+        id = previous_id + zigzag(id)
+    end
+    tree:add(fields.message_id, tvb(offset, len), id)
+    return id, offset + len
+end
+
+local function add_ack_ranges(tvb, tree, offset)
+    local start = offset
+    local range_end, range_size, ranges
+    range_end, offset = add_varint(tvb, tree, offset, fields.ack_end)
+    range_size, offset = add_varint(tvb, tree, offset, fields.ack_size)
+    ranges, offset = add_varint(tvb, tree, offset, fields.ack_ranges)
+    local range_start = range_end - range_size
+    tree:add(fields.ack_range, tvb(start, offset - start), range_start .. "..=" .. range_end)
+    for _ = 1, ranges do
+        local gap, size
+        start = offset
+        gap, offset = add_varint(tvb, tree, offset, fields.ack_gap)
+        size, offset = add_varint(tvb, tree, offset, fields.ack_size)
+        range_end = range_start - gap - 2
+        range_start = range_end - size
+        tree:add(fields.ack_range, tvb(start, offset - start), range_start .. "..=" .. range_end)
+    end
+    return offset
+end
+
+local dissectors = {}
+
+-- ReliableSlice
+dissectors[2] = function(tvb, tree, offset)
+    _, offset = add_varint(tvb, tree, offset, fields.sequence)
+    _, offset = add_u8(tvb, tree, offset, fields.channel_id)
+    _, offset = add_varint(tvb, tree, offset, fields.message_id)
+    _, offset = add_varint(tvb, tree, offset, fields.slice_index)
+    _, offset = add_varint(tvb, tree, offset, fields.num_slices)
+    offset = add_payload(tvb, tree, offset, fields.slice)
+    return offset
+end
+
+-- UnreliableSlice
+dissectors[3] = function(tvb, tree, offset)
+    _, offset = add_varint(tvb, tree, offset, fields.sequence)
+    _, offset = add_u8(tvb, tree, offset, fields.channel_id)
+    _, offset = add_varint(tvb, tree, offset, fields.message_id)
+    _, offset = add_varint(tvb, tree, offset, fields.slice_index)
+    _, offset = add_varint(tvb, tree, offset, fields.num_slices)
+    offset = add_payload(tvb, tree, offset, fields.slice)
+    return offset
+end
+
+-- Ack
+dissectors[4] = function(tvb, tree, offset)
+    _, offset = add_varint(tvb, tree, offset, fields.sequence)
+    offset = add_ack_ranges(tvb, tree, offset)
+    return offset
+end
+
+-- Ping
+dissectors[6] = function(tvb, tree, offset)
+    _, offset = add_varint(tvb, tree, offset, fields.sequence)
+    _, offset = add_varint(tvb, tree, offset, fields.ping_id)
+    return offset
+end
+
+-- Pong
+dissectors[7] = function(tvb, tree, offset)
+    _, offset = add_varint(tvb, tree, offset, fields.sequence)
+    _, offset = add_varint(tvb, tree, offset, fields.ping_id)
+    return offset
+end
+
+-- TransferSlice
+dissectors[8] = function(tvb, tree, offset)
+    _, offset = add_varint(tvb, tree, offset, fields.sequence)
+    _, offset = add_varint(tvb, tree, offset, fields.slice_index)
+    _, offset = add_varint(tvb, tree, offset, fields.num_slices)
+    offset = add_payload(tvb, tree, offset, fields.slice)
+    return offset
+end
+
+-- QuotaExceeded
+dissectors[9] = function(tvb, tree, offset)
+    _, offset = add_varint(tvb, tree, offset, fields.sequence)
+    local quota_kind
+    quota_kind, offset = add_u8(tvb, tree, offset, fields.quota_kind)
+    if quota_kind == 1 then
+        _, offset = add_u8(tvb, tree, offset, fields.channel_id)
+    end
+    return offset
+end
+
+-- CancelReliable
+dissectors[10] = function(tvb, tree, offset)
+    _, offset = add_varint(tvb, tree, offset, fields.sequence)
+    _, offset = add_u8(tvb, tree, offset, fields.channel_id)
+    _, offset = add_varint(tvb, tree, offset, fields.message_id)
+    return offset
+end
+
+-- SmallReliable
+dissectors[11] = function(tvb, tree, offset)
+    _, offset = add_varint(tvb, tree, offset, fields.sequence)
+    _, offset = add_u8(tvb, tree, offset, fields.channel_id)
+    local messages
+    messages, offset = add_varint(tvb, tree, offset, fields.messages)
+    local parent, previous_id = tree, nil
+    for i = 1, messages do
+        local start = offset
+        local tree = parent:add(renet, tvb(offset), "Message " .. i)
+        previous_id, offset = add_message_id(tvb, tree, offset, previous_id)
+        offset = add_payload(tvb, tree, offset, fields.message)
+        tree:set_len(offset - start)
+    end
+    return offset
+end
+
+-- SmallUnreliable
+dissectors[12] = function(tvb, tree, offset)
+    _, offset = add_varint(tvb, tree, offset, fields.sequence)
+    _, offset = add_u8(tvb, tree, offset, fields.channel_id)
+    local messages
+    messages, offset = add_varint(tvb, tree, offset, fields.messages)
+    local parent, previous_id = tree, nil
+    for i = 1, messages do
+        local start = offset
+        local tree = parent:add(renet, tvb(offset), "Message " .. i)
+        offset = add_payload(tvb, tree, offset, fields.message)
+        tree:set_len(offset - start)
+    end
+    return offset
+end
+
+-- ChannelConfig
+dissectors[13] = function(tvb, tree, offset)
+    _, offset = add_varint(tvb, tree, offset, fields.sequence)
+    _, offset = add_u8(tvb, tree, offset, fields.protocol_version)
+    local channels
+    channels, offset = add_varint(tvb, tree, offset, fields.channels)
+    local parent, previous_id = tree, nil
+    for i = 1, channels do
+        local start = offset
+        local tree = parent:add(renet, tvb(offset), "Channel " .. i)
+        _, offset = add_u8(tvb, tree, offset, fields.channel_id)
+        _, offset = add_u8(tvb, tree, offset, fields.send_type)
+        _, offset = add_varint(tvb, tree, offset, fields.max_memory_usage_bytes)
+        local has_group
+        has_group, offset = add_u8(tvb, tree, offset, fields.has_group)
+        if has_group == 1 then
+            _, offset = add_u8(tvb, tree, offset, fields.group)
+        end
+        _, offset = add_u8(tvb, tree, offset, fields.compression)
+        offset = add_u64(tvb, tree, offset, fields.compression_dictionary)
+        _, offset = add_u8(tvb, tree, offset, fields.tracing_context)
+        _, offset = add_u8(tvb, tree, offset, fields.message_kinds)
+        _, offset = add_u8(tvb, tree, offset, fields.deduplicate)
+        tree:set_len(offset - start)
+    end
+    return offset
+end
+
+function renet.dissector(tvb, pinfo, tree)
+    if tvb:len() <= 19 then
+        return 0
+    end
+
+    pinfo.cols.protocol = "RENET"
+    local tree = tree:add(renet, tvb())
+    tree:add(fields.direction, tvb(0, 1))
+    tree:add(fields.peer, tvb(1, 16))
+    tree:add(fields.peer_port, tvb(17, 2))
+    local packet_type = tvb(19, 1):uint()
+    tree:add(fields.packet_type, tvb(19, 1))
+    pinfo.cols.info = packet_types[packet_type] or "Unknown"
+    local dissect = dissectors[packet_type]
+    if dissect ~= nil then
+        dissect(tvb, tree, 20)
+    end
+    return tvb:len()
+end
+
+-- Link type 147, LINKTYPE_USER0
+DissectorTable.get("wtap_encap"):add((wtap_encaps or wtap).USER0, renet)
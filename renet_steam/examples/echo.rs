@@ -92,12 +92,18 @@ fn run_server(steam_client: Client<ClientManager>, single: SingleClient, with_lo
 
         while let Some(event) = server.get_event() {
             match event {
-                ServerEvent::ClientConnected { client_id } => {
+                ServerEvent::ClientConnected { client_id, .. } => {
                     println!("Client {} connected.", client_id)
                 }
-                ServerEvent::ClientDisconnected { client_id, reason } => {
+                ServerEvent::ClientDisconnected { client_id, reason, .. } => {
                     println!("Client {} disconnected: {}", client_id, reason);
                 }
+                ServerEvent::ClientQueued { addr, position, .. } => {
+                    println!("Client {} queued at position {}.", addr, position);
+                }
+                ServerEvent::ClientQuotaWarning { client_id, kind, .. } => {
+                    println!("Client {} exceeded its quota of {}.", client_id, kind);
+                }
             }
         }
 
@@ -114,6 +114,10 @@ fn handle_server_result(
                 socket.send_to(payload, addr).unwrap();
             }
         }
+        ServerResult::ClientQueued { addr, position, payload } => {
+            println!("Client {} queued at position {}.", addr, position);
+            socket.send_to(payload, addr).unwrap();
+        }
         ServerResult::None => {}
     }
 }
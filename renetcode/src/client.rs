@@ -2,7 +2,8 @@ use std::{fmt, net::SocketAddr, time::Duration};
 
 use crate::{
     packet::Packet, replay_protection::ReplayProtection, token::ConnectToken, NetcodeError, NETCODE_CHALLENGE_TOKEN_BYTES,
-    NETCODE_KEY_BYTES, NETCODE_MAX_PACKET_BYTES, NETCODE_MAX_PAYLOAD_BYTES, NETCODE_SEND_RATE, NETCODE_USER_DATA_BYTES,
+    NETCODE_CONNECT_TOKEN_PRIVATE_BYTES, NETCODE_KEY_BYTES, NETCODE_MAC_BYTES, NETCODE_MAX_PACKET_BYTES, NETCODE_MAX_PAYLOAD_BYTES,
+    NETCODE_SEND_RATE, NETCODE_USER_DATA_BYTES,
 };
 
 /// The reason why a client is in error state
@@ -26,7 +27,7 @@ enum ClientState {
 }
 
 /// Configuration to establish a secure or unsecure connection with the server.
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 #[allow(clippy::large_enum_variant)]
 pub enum ClientAuthentication {
     /// Establishes a safe connection with the server using the [crate::ConnectToken].
@@ -64,8 +65,12 @@ pub struct NetcodeClient {
     challenge_token_data: [u8; NETCODE_CHALLENGE_TOKEN_BYTES],
     max_clients: u32,
     client_index: u32,
+    queued_position: Option<u32>,
     send_rate: Duration,
     replay_protection: ReplayProtection,
+    // MACs of the private data of the tokens already used by this client
+    used_tokens: Vec<[u8; NETCODE_MAC_BYTES]>,
+    invalid_packets: u64,
     out: [u8; NETCODE_MAX_PACKET_BYTES],
 }
 
@@ -121,14 +126,41 @@ impl NetcodeClient {
             current_time,
             max_clients: 0,
             client_index: 0,
+            queued_position: None,
             send_rate: NETCODE_SEND_RATE,
             challenge_token_data: [0u8; NETCODE_CHALLENGE_TOKEN_BYTES],
-            connect_token,
             replay_protection: ReplayProtection::new(),
+            used_tokens: vec![token_mac(&connect_token)],
+            connect_token,
+            invalid_packets: 0,
             out: [0u8; NETCODE_MAX_PACKET_BYTES],
         })
     }
 
+    /// Starts a new connection with the server, the client must be disconnected.
+    /// Returns [`NetcodeError::AlreadyConnecting`] or [`NetcodeError::AlreadyConnected`] otherwise,
+    /// and [`NetcodeError::ConnectTokenAlreadyUsed`] if the token was already used by this client.
+    pub fn connect(&mut self, current_time: Duration, authentication: ClientAuthentication) -> Result<(), NetcodeError> {
+        match self.state {
+            ClientState::SendingConnectionRequest | ClientState::SendingConnectionResponse => return Err(NetcodeError::AlreadyConnecting),
+            ClientState::Connected => return Err(NetcodeError::AlreadyConnected),
+            ClientState::Disconnected(_) => {}
+        }
+
+        if let ClientAuthentication::Secure { connect_token } = &authentication {
+            if self.used_tokens.contains(&token_mac(connect_token)) {
+                return Err(NetcodeError::ConnectTokenAlreadyUsed);
+            }
+        }
+
+        let mut client = NetcodeClient::new(current_time, authentication)?;
+        client.used_tokens.extend_from_slice(&self.used_tokens);
+        client.invalid_packets = self.invalid_packets;
+        *self = client;
+
+        Ok(())
+    }
+
     pub fn is_connecting(&self) -> bool {
         matches!(
             self.state,
@@ -148,6 +180,17 @@ impl NetcodeClient {
         self.current_time
     }
 
+    /// Returns the position in the server connect queue, if the server is full and queued this client.
+    /// Position 1 is the next client to be accepted.
+    pub fn queued_position(&self) -> Option<u32> {
+        self.queued_position
+    }
+
+    /// Returns the number of received packets discarded because they could not be decoded.
+    pub fn invalid_packets(&self) -> u64 {
+        self.invalid_packets
+    }
+
     pub fn client_id(&self) -> u64 {
         self.client_id
     }
@@ -155,7 +198,23 @@ impl NetcodeClient {
     /// Returns the duration since the client last received a packet.
     /// Usefull to detect timeouts.
     pub fn time_since_last_received_packet(&self) -> Duration {
-        self.current_time - self.last_packet_received_time
+        self.current_time.saturating_sub(self.last_packet_received_time)
+    }
+
+    /// Returns the time without receiving packets after which the connection times out, from the connect token.
+    /// None when the timeout is disabled.
+    pub fn timeout(&self) -> Option<Duration> {
+        (self.connect_token.timeout_seconds > 0).then(|| Duration::from_secs(self.connect_token.timeout_seconds as u64))
+    }
+
+    /// Returns the unix timestamp, in seconds, at which the connect token expires.
+    pub fn token_expire_timestamp(&self) -> u64 {
+        self.connect_token.expire_timestamp
+    }
+
+    /// Returns the interval of the keep-alive packets sent while there is no payload to send.
+    pub fn keep_alive_interval(&self) -> Duration {
+        self.send_rate
     }
 
     /// Returns the reason that the client was disconnected for.
@@ -172,8 +231,13 @@ impl NetcodeClient {
     }
 
     /// Disconnect the client from the server.
-    /// Returns a disconnect packet that should be sent to the server.
+    /// Returns a disconnect packet that should be sent to the server,
+    /// or [`NetcodeError::Disconnected`] if the client is already disconnected.
     pub fn disconnect(&mut self) -> Result<(SocketAddr, &mut [u8]), NetcodeError> {
+        if let ClientState::Disconnected(reason) = self.state {
+            return Err(NetcodeError::Disconnected(reason));
+        }
+
         self.state = ClientState::Disconnected(DisconnectReason::DisconnectedByClient);
         let packet = Packet::Disconnect;
         let len = packet.encode(
@@ -197,7 +261,8 @@ impl NetcodeClient {
         ) {
             Ok((_, packet)) => packet,
             Err(e) => {
-                log::error!("Failed to decode packet: {}", e);
+                log::debug!("Failed to decode packet: {}", e);
+                self.invalid_packets += 1;
                 return None;
             }
         };
@@ -208,6 +273,10 @@ impl NetcodeClient {
                 self.state = ClientState::Disconnected(DisconnectReason::ConnectionDenied);
                 self.last_packet_received_time = self.current_time;
             }
+            (Packet::ConnectionQueued { position }, ClientState::SendingConnectionRequest) => {
+                self.queued_position = Some(position);
+                self.last_packet_received_time = self.current_time;
+            }
             (
                 Packet::Challenge {
                     token_data,
@@ -216,6 +285,7 @@ impl NetcodeClient {
                 ClientState::SendingConnectionRequest,
             ) => {
                 self.challenge_token_sequence = token_sequence;
+                self.queued_position = None;
                 self.last_packet_received_time = self.current_time;
                 self.last_packet_send_time = None;
                 self.challenge_token_data = token_data;
@@ -286,7 +356,7 @@ impl NetcodeClient {
         match self.state {
             ClientState::SendingConnectionRequest | ClientState::SendingConnectionResponse => {
                 let expire_seconds = self.connect_token.expire_timestamp - self.connect_token.create_timestamp;
-                let connection_expired = (self.current_time - self.connect_start_time).as_secs() >= expire_seconds;
+                let connection_expired = self.current_time.saturating_sub(self.connect_start_time).as_secs() >= expire_seconds;
                 if connection_expired {
                     self.state = ClientState::Disconnected(DisconnectReason::ConnectTokenExpired);
                     return Err(NetcodeError::Expired);
@@ -312,6 +382,7 @@ impl NetcodeClient {
                             self.last_packet_send_time = None;
                             self.last_packet_received_time = self.current_time;
                             self.challenge_token_sequence = 0;
+                            self.queued_position = None;
 
                             return Ok(());
                         }
@@ -333,7 +404,7 @@ impl NetcodeClient {
 
     fn generate_packet(&mut self) -> Option<(&mut [u8], SocketAddr)> {
         if let Some(last_packet_send_time) = self.last_packet_send_time {
-            if self.current_time - last_packet_send_time < self.send_rate {
+            if self.current_time.saturating_sub(last_packet_send_time) < self.send_rate {
                 return None;
             }
         }
@@ -372,6 +443,12 @@ impl NetcodeClient {
     }
 }
 
+fn token_mac(connect_token: &ConnectToken) -> [u8; NETCODE_MAC_BYTES] {
+    let mut mac = [0u8; NETCODE_MAC_BYTES];
+    mac.copy_from_slice(&connect_token.private_data[NETCODE_CONNECT_TOKEN_PRIVATE_BYTES - NETCODE_MAC_BYTES..]);
+    mac
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{crypto::generate_random_bytes, NETCODE_MAX_PACKET_BYTES};
@@ -444,4 +521,61 @@ mod tests {
             _ => unreachable!(),
         }
     }
+
+    #[test]
+    fn connect_state_transitions() {
+        let private_key = b"an example very very secret key."; // 32-bytes
+        let server_addresses: Vec<SocketAddr> = vec!["127.0.0.1:8080".parse().unwrap()];
+        let generate_token = || ConnectToken::generate(Duration::ZERO, 2, 30, 4, 5, server_addresses.clone(), None, private_key).unwrap();
+
+        let connect_token = generate_token();
+        let mut token_bytes: Vec<u8> = vec![];
+        connect_token.write(&mut token_bytes).unwrap();
+
+        let mut client = NetcodeClient::new(Duration::ZERO, ClientAuthentication::Secure { connect_token }).unwrap();
+        let result = client.connect(
+            Duration::ZERO,
+            ClientAuthentication::Secure {
+                connect_token: generate_token(),
+            },
+        );
+        assert!(matches!(result, Err(NetcodeError::AlreadyConnecting)));
+
+        client.state = ClientState::Connected;
+        let result = client.connect(
+            Duration::ZERO,
+            ClientAuthentication::Secure {
+                connect_token: generate_token(),
+            },
+        );
+        assert!(matches!(result, Err(NetcodeError::AlreadyConnected)));
+
+        client.disconnect().unwrap();
+        assert!(matches!(
+            client.disconnect(),
+            Err(NetcodeError::Disconnected(DisconnectReason::DisconnectedByClient))
+        ));
+
+        // A copy of the consumed token can't be used again
+        let copied_token = ConnectToken::read(&mut token_bytes.as_slice()).unwrap();
+        let result = client.connect(
+            Duration::ZERO,
+            ClientAuthentication::Secure {
+                connect_token: copied_token,
+            },
+        );
+        assert!(matches!(result, Err(NetcodeError::ConnectTokenAlreadyUsed)));
+        assert!(client.is_disconnected());
+
+        client
+            .connect(
+                Duration::ZERO,
+                ClientAuthentication::Secure {
+                    connect_token: generate_token(),
+                },
+            )
+            .unwrap();
+        assert!(client.is_connecting());
+        assert_eq!(client.used_tokens.len(), 2);
+    }
 }
@@ -34,6 +34,12 @@ pub enum NetcodeError {
     ClientNotFound,
     /// Client is not connected.
     ClientNotConnected,
+    /// The client is already trying to connect.
+    AlreadyConnecting,
+    /// The client is already connected.
+    AlreadyConnected,
+    /// The connect token was already used by this client.
+    ConnectTokenAlreadyUsed,
     /// IO error.
     IoError(io::Error),
     /// An error occured while generating the connect token.
@@ -59,6 +65,9 @@ impl fmt::Display for NetcodeError {
             NotInHostList => write!(fmt, "token does not contain the server address"),
             ClientNotFound => write!(fmt, "client was not found"),
             ClientNotConnected => write!(fmt, "client is disconnected or connecting"),
+            AlreadyConnecting => write!(fmt, "client is already connecting"),
+            AlreadyConnected => write!(fmt, "client is already connected"),
+            ConnectTokenAlreadyUsed => write!(fmt, "connect token was already used"),
             IoError(ref err) => write!(fmt, "{}", err),
             TokenGenerationError(ref err) => write!(fmt, "{}", err),
         }
@@ -28,8 +28,8 @@ mod token;
 pub use client::{ClientAuthentication, DisconnectReason, NetcodeClient};
 pub use crypto::generate_random_bytes;
 pub use error::NetcodeError;
-pub use server::{NetcodeServer, ServerAuthentication, ServerConfig, ServerResult};
-pub use token::{ConnectToken, TokenGenerationError};
+pub use server::{ConnectQueueConfig, NetcodeServer, ServerAuthentication, ServerConfig, ServerResult};
+pub use token::{ConnectToken, TokenClaims, TokenGenerationError};
 
 use std::time::Duration;
 
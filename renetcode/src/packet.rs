@@ -5,7 +5,7 @@ use crate::replay_protection::ReplayProtection;
 use crate::token::ConnectToken;
 use crate::{
     serialize::*, NetcodeError, NETCODE_CHALLENGE_TOKEN_BYTES, NETCODE_CONNECT_TOKEN_PRIVATE_BYTES, NETCODE_CONNECT_TOKEN_XNONCE_BYTES,
-    NETCODE_KEY_BYTES, NETCODE_MAC_BYTES,
+    NETCODE_KEY_BYTES, NETCODE_MAC_BYTES, NETCODE_MAX_PACKET_BYTES,
 };
 use crate::{NETCODE_USER_DATA_BYTES, NETCODE_VERSION_INFO};
 
@@ -19,6 +19,7 @@ pub enum PacketType {
     KeepAlive = 4,
     Payload = 5,
     Disconnect = 6,
+    ConnectionQueued = 7,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -46,6 +47,9 @@ pub enum Packet<'a> {
     },
     Payload(&'a [u8]),
     Disconnect,
+    ConnectionQueued {
+        position: u32,
+    },
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -66,6 +70,7 @@ impl PacketType {
             4 => KeepAlive,
             5 => Payload,
             6 => Disconnect,
+            7 => ConnectionQueued,
             _ => return Err(NetcodeError::InvalidPacketType),
         };
         Ok(packet_type)
@@ -88,6 +93,7 @@ impl<'a> Packet<'a> {
             Packet::KeepAlive { .. } => PacketType::KeepAlive,
             Packet::Payload { .. } => PacketType::Payload,
             Packet::Disconnect => PacketType::Disconnect,
+            Packet::ConnectionQueued { .. } => PacketType::ConnectionQueued,
         }
     }
 
@@ -155,6 +161,9 @@ impl<'a> Packet<'a> {
             Packet::Payload(p) => {
                 writer.write_all(p)?;
             }
+            Packet::ConnectionQueued { position } => {
+                writer.write_all(&position.to_le_bytes())?;
+            }
             Packet::ConnectionDenied | Packet::Disconnect => {}
         }
 
@@ -208,6 +217,11 @@ impl<'a> Packet<'a> {
 
                 Ok(Packet::KeepAlive { client_index, max_clients })
             }
+            PacketType::ConnectionQueued => {
+                let position = read_u32(src)?;
+
+                Ok(Packet::ConnectionQueued { position })
+            }
             PacketType::ConnectionDenied => Ok(Packet::ConnectionDenied),
             PacketType::Disconnect => Ok(Packet::Disconnect),
             PacketType::Payload => unreachable!(),
@@ -334,6 +348,34 @@ impl ChallengeToken {
     }
 }
 
+// Size of a connection request, from the prefix byte to the end of the token private data
+const CONNECTION_REQUEST_BYTES: usize = 1 + 13 + 8 + 8 + NETCODE_CONNECT_TOKEN_XNONCE_BYTES + NETCODE_CONNECT_TOKEN_PRIVATE_BYTES;
+
+/// Cheap check of the unencrypted parts of a packet, without decrypting it.
+/// Connection requests must match the version info and protocol id,
+/// other packets must have a valid prefix and be large enough for their sequence and MAC.
+pub fn looks_like_netcode(buffer: &[u8], protocol_id: u64) -> bool {
+    if buffer.len() < 2 + NETCODE_MAC_BYTES || buffer.len() > NETCODE_MAX_PACKET_BYTES {
+        return false;
+    }
+
+    let (packet_type, sequence_len) = decode_prefix(buffer[0]);
+    match PacketType::from_u8(packet_type) {
+        Ok(PacketType::ConnectionRequest) => {
+            buffer.len() >= CONNECTION_REQUEST_BYTES
+                && &buffer[1..14] == NETCODE_VERSION_INFO
+                && buffer[14..22] == protocol_id.to_le_bytes()
+        }
+        Ok(_) => sequence_len <= 8 && buffer.len() >= 1 + sequence_len + NETCODE_MAC_BYTES,
+        Err(_) => false,
+    }
+}
+
+/// Returns whether the unencrypted prefix of the packet is the one of a connection request.
+pub fn is_connection_request(buffer: &[u8]) -> bool {
+    matches!(buffer.first(), Some(&prefix) if decode_prefix(prefix).0 == PacketType::ConnectionRequest as u8)
+}
+
 fn get_additional_data(prefix: u8, protocol_id: u64) -> [u8; 13 + 8 + 1] {
     let mut buffer = [0; 13 + 8 + 1];
     buffer[..13].copy_from_slice(NETCODE_VERSION_INFO);
@@ -378,7 +420,9 @@ fn read_sequence(source: &mut impl io::Read, len: usize) -> Result<u64, io::Erro
 
 #[cfg(test)]
 mod tests {
-    use crate::{crypto::generate_random_bytes, NETCODE_MAX_PACKET_BYTES, NETCODE_MAX_PAYLOAD_BYTES};
+    use std::time::Duration;
+
+    use crate::{crypto::generate_random_bytes, NETCODE_MAX_PAYLOAD_BYTES};
 
     use super::*;
 
@@ -426,6 +470,39 @@ mod tests {
         assert_eq!(deserialized, connection_keep_alive);
     }
 
+    #[test]
+    fn looks_like_netcode_packet() {
+        let protocol_id = 7;
+        let key = b"an example very very secret key."; // 32-bytes
+        let mut buffer = [0u8; NETCODE_MAX_PACKET_BYTES];
+
+        let packet = Packet::Payload(b"payload");
+        let len = packet.encode(&mut buffer, protocol_id, Some((300, key))).unwrap();
+        assert!(looks_like_netcode(&buffer[..len], protocol_id));
+
+        let connect_token = ConnectToken::generate(
+            Duration::ZERO,
+            protocol_id,
+            30,
+            1,
+            15,
+            vec!["127.0.0.1:5000".parse().unwrap()],
+            None,
+            key,
+        )
+        .unwrap();
+        let packet = Packet::connection_request_from_token(&connect_token);
+        let len = packet.encode(&mut buffer, protocol_id, None).unwrap();
+        assert!(looks_like_netcode(&buffer[..len], protocol_id));
+        assert!(!looks_like_netcode(&buffer[..len], protocol_id + 1));
+        assert!(!looks_like_netcode(&buffer[..len - 1], protocol_id));
+
+        // Foreign packets
+        assert!(!looks_like_netcode(b"legacy", protocol_id));
+        assert!(!looks_like_netcode(&[0x0F; 64], protocol_id));
+        assert!(!looks_like_netcode(&[0x95; 20], protocol_id));
+    }
+
     #[test]
     fn prefix_sequence() {
         let packet_type = Packet::Disconnect.id();
@@ -22,7 +22,8 @@ impl ReplayProtection {
     }
 
     pub fn already_received(&self, sequence: u64) -> bool {
-        if sequence + NETCODE_REPLAY_BUFFER_SIZE as u64 <= self.most_recent_sequence {
+        // The sequence comes from the packet, it can be anything
+        if sequence.saturating_add(NETCODE_REPLAY_BUFFER_SIZE as u64) <= self.most_recent_sequence {
             return true;
         }
 
@@ -80,4 +81,18 @@ mod tests {
             assert!(replay_protection.already_received(i));
         }
     }
+
+    #[test]
+    fn replay_protection_near_max_sequence() {
+        let mut replay_protection = ReplayProtection::new();
+        let start = u64::MAX - 2 * NETCODE_REPLAY_BUFFER_SIZE as u64;
+        for i in start..u64::MAX {
+            assert!(!replay_protection.already_received(i));
+            replay_protection.advance_sequence(i);
+            assert!(replay_protection.already_received(i));
+        }
+
+        assert!(replay_protection.already_received(start));
+        assert!(replay_protection.already_received(0));
+    }
 }
@@ -1,10 +1,14 @@
-use std::{collections::HashMap, net::SocketAddr, time::Duration};
+use std::{
+    collections::{HashMap, VecDeque},
+    net::SocketAddr,
+    time::Duration,
+};
 
 use crate::{
     crypto::generate_random_bytes,
-    packet::{ChallengeToken, Packet},
+    packet::{is_connection_request, looks_like_netcode, ChallengeToken, Packet},
     replay_protection::ReplayProtection,
-    token::PrivateConnectToken,
+    token::{ConnectToken, PrivateConnectToken, TokenGenerationError},
     NetcodeError, NETCODE_CONNECT_TOKEN_PRIVATE_BYTES, NETCODE_CONNECT_TOKEN_XNONCE_BYTES, NETCODE_KEY_BYTES, NETCODE_MAC_BYTES,
     NETCODE_MAX_CLIENTS, NETCODE_MAX_PACKET_BYTES, NETCODE_MAX_PAYLOAD_BYTES, NETCODE_MAX_PENDING_CLIENTS, NETCODE_SEND_RATE,
     NETCODE_USER_DATA_BYTES, NETCODE_VERSION_INFO,
@@ -41,6 +45,74 @@ struct ConnectTokenEntry {
     mac: [u8; NETCODE_MAC_BYTES],
 }
 
+/// Configuration of the queue that holds connection requests received while the server is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectQueueConfig {
+    /// Maximum number of queued addresses, requests received when the queue is full are denied.
+    pub max_size: usize,
+    /// Time an address stays queued without retrying its connection request.
+    /// This is also the window the address has to retry once a slot is available for it.
+    pub entry_ttl: Duration,
+}
+
+#[derive(Debug)]
+struct QueuedAddr {
+    addr: SocketAddr,
+    last_request_time: Duration,
+    reported_position: u32,
+}
+
+#[derive(Debug)]
+struct ConnectQueue {
+    config: ConnectQueueConfig,
+    entries: VecDeque<QueuedAddr>,
+}
+
+enum QueueDecision {
+    Accept,
+    Queued { position: u32, changed: bool },
+    Full,
+}
+
+impl ConnectQueue {
+    // Queued addresses are accepted in order as slots become available,
+    // new addresses are queued behind them.
+    fn decide(&mut self, addr: SocketAddr, free_slots: usize, current_time: Duration) -> QueueDecision {
+        let index = self.entries.iter().position(|e| e.addr == addr);
+        if index.unwrap_or(self.entries.len()) < free_slots {
+            if let Some(index) = index {
+                self.entries[index].last_request_time = current_time;
+            }
+            return QueueDecision::Accept;
+        }
+
+        let index = match index {
+            Some(index) => index,
+            None if self.entries.len() >= self.config.max_size => return QueueDecision::Full,
+            None => {
+                self.entries.push_back(QueuedAddr {
+                    addr,
+                    last_request_time: current_time,
+                    reported_position: 0,
+                });
+                self.entries.len() - 1
+            }
+        };
+
+        let entry = &mut self.entries[index];
+        let position = (index + 1) as u32;
+        let changed = entry.reported_position != position;
+        entry.reported_position = position;
+        entry.last_request_time = current_time;
+
+        QueueDecision::Queued { position, changed }
+    }
+
+    fn remove(&mut self, addr: SocketAddr) {
+        self.entries.retain(|e| e.addr != addr);
+    }
+}
+
 /// A server that can generate packets from connect clients, that are encrypted, or process
 /// incoming encrypted packets from clients. The server is agnostic from the transport layer, only
 /// consuming and generating bytes that can be transported in any way desired.
@@ -48,6 +120,8 @@ struct ConnectTokenEntry {
 pub struct NetcodeServer {
     clients: Box<[Option<Connection>]>,
     pending_clients: HashMap<SocketAddr, Connection>,
+    // Addresses of the pending clients, oldest first.
+    pending_order: VecDeque<SocketAddr>,
     connect_token_entries: Box<[Option<ConnectTokenEntry>; NETCODE_MAX_CLIENTS * 2]>,
     protocol_id: u64,
     connect_key: [u8; NETCODE_KEY_BYTES],
@@ -58,6 +132,8 @@ pub struct NetcodeServer {
     current_time: Duration,
     global_sequence: u64,
     secure: bool,
+    connect_queue: Option<ConnectQueue>,
+    evicted_pending_clients: u64,
     out: [u8; NETCODE_MAX_PACKET_BYTES],
 }
 
@@ -83,6 +159,13 @@ pub enum ServerResult<'a, 's> {
         addr: SocketAddr,
         payload: Option<&'s mut [u8]>,
     },
+    /// The server is full and the address was queued, or its position in the queue changed.
+    /// The payload with the position should be sent back to the address.
+    ClientQueued {
+        addr: SocketAddr,
+        position: u32,
+        payload: &'s mut [u8],
+    },
 }
 
 /// Configuration to establish a secure or unsecure connection with the server.
@@ -136,6 +219,7 @@ impl NetcodeServer {
             clients,
             connect_token_entries: Box::new([None; NETCODE_MAX_CLIENTS * 2]),
             pending_clients: HashMap::new(),
+            pending_order: VecDeque::new(),
             protocol_id: config.protocol_id,
             connect_key,
             max_clients: config.max_clients,
@@ -145,6 +229,8 @@ impl NetcodeServer {
             public_addresses: config.public_addresses,
             current_time: config.current_time,
             secure,
+            connect_queue: None,
+            evicted_pending_clients: 0,
             out: [0u8; NETCODE_MAX_PACKET_BYTES],
         }
     }
@@ -169,6 +255,26 @@ impl NetcodeServer {
         self.current_time
     }
 
+    /// Generates a connect token for the server public addresses, using its own protocol id and key.
+    /// Useful to validate the server setup, connect tokens for clients should be generated by a separate service.
+    pub fn generate_connect_token(
+        &self,
+        client_id: u64,
+        expire_seconds: u64,
+        timeout_seconds: i32,
+    ) -> Result<ConnectToken, TokenGenerationError> {
+        ConnectToken::generate(
+            self.current_time,
+            self.protocol_id,
+            expire_seconds,
+            client_id,
+            timeout_seconds,
+            self.public_addresses.clone(),
+            None,
+            &self.connect_key,
+        )
+    }
+
     fn find_or_add_connect_token_entry(&mut self, new_entry: ConnectTokenEntry) -> bool {
         let mut min = Duration::MAX;
         let mut oldest_entry = 0;
@@ -203,6 +309,39 @@ impl NetcodeServer {
         true
     }
 
+    // The address of a pending client is not verified until it answers the challenge,
+    // evicting the oldest keeps a flood of requests from spoofed addresses from locking out new clients.
+    fn evict_oldest_pending_client(&mut self) {
+        if let Some(addr) = self.pending_order.pop_front() {
+            log::debug!(
+                "Evicted pending client {}: reached max amount allowed of pending clients ({}).",
+                addr,
+                NETCODE_MAX_PENDING_CLIENTS
+            );
+            self.pending_clients.remove(&addr);
+            self.evicted_pending_clients += 1;
+        }
+    }
+
+    fn remove_pending_client(&mut self, addr: &SocketAddr) -> Option<Connection> {
+        let pending = self.pending_clients.remove(addr)?;
+        if let Some(index) = self.pending_order.iter().position(|a| a == addr) {
+            self.pending_order.remove(index);
+        }
+        Some(pending)
+    }
+
+    /// Returns the number of clients with a connection handshake in progress.
+    /// Limited to 4096, the oldest handshake is evicted when a new one would exceed it.
+    pub fn pending_clients(&self) -> usize {
+        self.pending_clients.len()
+    }
+
+    /// Returns the number of handshakes in progress that were evicted to make room for new ones.
+    pub fn evicted_pending_clients(&self) -> u64 {
+        self.evicted_pending_clients
+    }
+
     /// Returns the user data from the connected client.
     pub fn user_data(&self, client_id: u64) -> Option<[u8; NETCODE_USER_DATA_BYTES]> {
         if let Some(client) = find_client_by_id(&self.clients, client_id) {
@@ -216,7 +355,7 @@ impl NetcodeServer {
     /// Usefull to detect users that are timing out.
     pub fn time_since_last_received_packet(&self, client_id: u64) -> Option<Duration> {
         if let Some(client) = find_client_by_id(&self.clients, client_id) {
-            let time = self.current_time - client.last_packet_received_time;
+            let time = self.current_time.saturating_sub(client.last_packet_received_time);
             return Some(time);
         }
 
@@ -232,6 +371,23 @@ impl NetcodeServer {
         None
     }
 
+    /// Returns the time without receiving packets after which the connected client times out, from its connect token.
+    /// None if the client is not connected or its timeout is disabled.
+    pub fn client_timeout(&self, client_id: u64) -> Option<Duration> {
+        let client = find_client_by_id(&self.clients, client_id)?;
+        (client.timeout_seconds > 0).then(|| Duration::from_secs(client.timeout_seconds as u64))
+    }
+
+    /// Returns the unix timestamp, in seconds, at which the connect token of the connected client expires.
+    pub fn client_token_expire_timestamp(&self, client_id: u64) -> Option<u64> {
+        find_client_by_id(&self.clients, client_id).map(|client| client.expire_timestamp)
+    }
+
+    /// Returns the interval of the keep-alive packets sent to clients while there is no payload to send.
+    pub fn keep_alive_interval(&self) -> Duration {
+        NETCODE_SEND_RATE
+    }
+
     fn handle_connection_request<'a>(
         &mut self,
         addr: SocketAddr,
@@ -279,14 +435,6 @@ impl NetcodeServer {
             return Ok(ServerResult::None);
         }
 
-        if !self.pending_clients.contains_key(&addr) && self.pending_clients.len() >= NETCODE_MAX_PENDING_CLIENTS {
-            log::warn!(
-                "Connection request denied: reached max amount allowed of pending clients ({}).",
-                NETCODE_MAX_PENDING_CLIENTS
-            );
-            return Ok(ServerResult::None);
-        }
-
         let mut mac = [0u8; NETCODE_MAC_BYTES];
         mac.copy_from_slice(&data[NETCODE_CONNECT_TOKEN_PRIVATE_BYTES - NETCODE_MAC_BYTES..]);
         let connect_token_entry = ConnectTokenEntry {
@@ -300,8 +448,31 @@ impl NetcodeServer {
             return Ok(ServerResult::None);
         }
 
-        if self.clients.iter().flatten().count() >= self.max_clients {
-            self.pending_clients.remove(&addr);
+        let free_slots = self.max_clients.saturating_sub(self.connected_clients());
+        let queue_decision = match &mut self.connect_queue {
+            Some(queue) => queue.decide(addr, free_slots, self.current_time),
+            None => QueueDecision::Accept,
+        };
+
+        if let QueueDecision::Queued { position, changed } = queue_decision {
+            self.remove_pending_client(&addr);
+            let packet = Packet::ConnectionQueued { position };
+            let len = packet.encode(
+                &mut self.out,
+                self.protocol_id,
+                Some((self.global_sequence, &connect_token.server_to_client_key)),
+            )?;
+            self.global_sequence += 1;
+            let payload = &mut self.out[..len];
+            if changed {
+                log::debug!("Connection request from {} queued at position {}", addr, position);
+                return Ok(ServerResult::ClientQueued { addr, position, payload });
+            }
+            return Ok(ServerResult::PacketToSend { addr, payload });
+        }
+
+        if matches!(queue_decision, QueueDecision::Full) || free_slots == 0 {
+            self.remove_pending_client(&addr);
             let packet = Packet::ConnectionDenied;
             let len = packet.encode(
                 &mut self.out,
@@ -332,20 +503,28 @@ impl NetcodeServer {
 
         log::trace!("Connection request from Client {}", connect_token.client_id);
 
-        let pending = self.pending_clients.entry(addr).or_insert_with(|| Connection {
-            confirmed: false,
-            sequence: 0,
-            client_id: connect_token.client_id,
-            last_packet_received_time: self.current_time,
-            last_packet_send_time: self.current_time,
-            addr,
-            state: ConnectionState::PendingResponse,
-            send_key: connect_token.server_to_client_key,
-            receive_key: connect_token.client_to_server_key,
-            timeout_seconds: connect_token.timeout_seconds,
-            expire_timestamp,
-            user_data: connect_token.user_data,
-            replay_protection: ReplayProtection::new(),
+        if !self.pending_clients.contains_key(&addr) && self.pending_clients.len() >= NETCODE_MAX_PENDING_CLIENTS {
+            self.evict_oldest_pending_client();
+        }
+
+        let pending_order = &mut self.pending_order;
+        let pending = self.pending_clients.entry(addr).or_insert_with(|| {
+            pending_order.push_back(addr);
+            Connection {
+                confirmed: false,
+                sequence: 0,
+                client_id: connect_token.client_id,
+                last_packet_received_time: self.current_time,
+                last_packet_send_time: self.current_time,
+                addr,
+                state: ConnectionState::PendingResponse,
+                send_key: connect_token.server_to_client_key,
+                receive_key: connect_token.client_to_server_key,
+                timeout_seconds: connect_token.timeout_seconds,
+                expire_timestamp,
+                user_data: connect_token.user_data,
+                replay_protection: ReplayProtection::new(),
+            }
         });
         pending.last_packet_received_time = self.current_time;
         pending.last_packet_send_time = self.current_time;
@@ -379,7 +558,7 @@ impl NetcodeServer {
     pub fn process_packet<'a, 's>(&'s mut self, addr: SocketAddr, buffer: &'a mut [u8]) -> ServerResult<'a, 's> {
         match self.process_packet_internal(addr, buffer) {
             Err(e) => {
-                log::error!("Failed to process packet: {}", e);
+                log::debug!("Failed to process packet from {}: {}", addr, e);
                 ServerResult::None
             }
             Ok(r) => r,
@@ -467,7 +646,7 @@ impl NetcodeServer {
                     token_sequence,
                 } => {
                     let challenge_token = ChallengeToken::decode(token_data, token_sequence, &self.challenge_key)?;
-                    let mut pending = self.pending_clients.remove(&addr).unwrap();
+                    let mut pending = self.remove_pending_client(&addr).unwrap();
                     if find_client_slot_by_id(&self.clients, challenge_token.client_id).is_some() {
                         log::debug!(
                             "Ignored connection response for Client {}, already connected.",
@@ -502,6 +681,9 @@ impl NetcodeServer {
                             let client_id: u64 = pending.client_id;
                             let user_data: [u8; NETCODE_USER_DATA_BYTES] = pending.user_data;
                             self.clients[client_index] = Some(pending);
+                            if let Some(queue) = &mut self.connect_queue {
+                                queue.remove(addr);
+                            }
 
                             return Ok(ServerResult::ClientConnected {
                                 client_id,
@@ -561,6 +743,47 @@ impl NetcodeServer {
         self.max_clients = max_clients;
     }
 
+    /// Enables or disables the connect queue.
+    ///
+    /// When enabled, connection requests received while the server is full are queued instead of denied,
+    /// and the client is informed of its position. When a slot becomes available the oldest queued address
+    /// has [`ConnectQueueConfig::entry_ttl`] to retry before losing its place.
+    /// Disabling the queue drops all queued addresses.
+    pub fn set_connect_queue(&mut self, config: Option<ConnectQueueConfig>) {
+        self.connect_queue = config.map(|config| ConnectQueue {
+            config,
+            entries: VecDeque::new(),
+        });
+    }
+
+    /// Returns the number of addresses waiting in the connect queue.
+    pub fn queued_clients(&self) -> usize {
+        self.connect_queue.as_ref().map_or(0, |queue| queue.entries.len())
+    }
+
+    /// Returns whether the packet could be a netcode packet for this server.
+    /// Only the unencrypted prefix and the protocol id of connection requests are checked,
+    /// it does not allocate or decrypt, use it to filter other protocols sharing the socket.
+    pub fn packet_looks_like_netcode(&self, buffer: &[u8]) -> bool {
+        looks_like_netcode(buffer, self.protocol_id)
+    }
+
+    /// Returns whether the packet received from the address is meant for this server: it is a connection request
+    /// with the server protocol id, or the address is connected or has a handshake in progress with the server.
+    /// Used to route packets between servers sharing the same socket, the packet is not decrypted.
+    pub fn accepts_packet(&self, addr: SocketAddr, buffer: &[u8]) -> bool {
+        if !looks_like_netcode(buffer, self.protocol_id) {
+            return false;
+        }
+
+        is_connection_request(buffer) || self.pending_clients.contains_key(&addr) || self.clients.iter().flatten().any(|c| c.addr == addr)
+    }
+
+    /// Returns the protocol id of the server.
+    pub fn protocol_id(&self) -> u64 {
+        self.protocol_id
+    }
+
     /// Returns current number of clients connected.
     pub fn connected_clients(&self) -> usize {
         self.clients.iter().filter(|slot| slot.is_some()).count()
@@ -574,10 +797,24 @@ impl NetcodeServer {
             if self.current_time.as_secs() > client.expire_timestamp {
                 log::debug!("Pending Client {} disconnected, connection token expired.", client.client_id);
                 client.state = ConnectionState::Disconnected;
+            } else if client.timeout_seconds > 0
+                && self.current_time.saturating_sub(client.last_packet_received_time) > Duration::from_secs(client.timeout_seconds as u64)
+            {
+                log::debug!("Pending Client {} disconnected, handshake timed out.", client.client_id);
+                client.state = ConnectionState::Disconnected;
             }
         }
 
         self.pending_clients.retain(|_, c| c.state != ConnectionState::Disconnected);
+        let pending_clients = &self.pending_clients;
+        self.pending_order.retain(|addr| pending_clients.contains_key(addr));
+
+        if let Some(queue) = &mut self.connect_queue {
+            let current_time = self.current_time;
+            queue
+                .entries
+                .retain(|e| current_time.saturating_sub(e.last_request_time) <= queue.config.entry_ttl);
+        }
     }
 
     /// Updates the client, returns a ServerResult.
@@ -852,4 +1089,146 @@ mod tests {
         // Don't allow same token with different address
         assert!(!server.find_or_add_connect_token_entry(connect_token));
     }
+
+    #[test]
+    fn connect_queue() {
+        let mut server = new_server();
+        server.set_max_clients(1);
+        server.set_connect_queue(Some(ConnectQueueConfig {
+            max_size: 1,
+            entry_ttl: Duration::from_secs(1),
+        }));
+
+        let new_client = |client_id: u64| {
+            let connect_token = ConnectToken::generate(
+                Duration::ZERO,
+                TEST_PROTOCOL_ID,
+                30,
+                client_id,
+                5,
+                server.addresses(),
+                None,
+                TEST_KEY,
+            )
+            .unwrap();
+            NetcodeClient::new(Duration::ZERO, ClientAuthentication::Secure { connect_token }).unwrap()
+        };
+        let mut client_a = new_client(0);
+        let mut client_b = new_client(1);
+        let mut client_c = new_client(2);
+        let addr_a: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+        let addr_b: SocketAddr = "127.0.0.1:3001".parse().unwrap();
+        let addr_c: SocketAddr = "127.0.0.1:3002".parse().unwrap();
+
+        // Runs the handshake until the client stops sending requests or responses
+        let exchange = |server: &mut NetcodeServer, client: &mut NetcodeClient, addr: SocketAddr| {
+            let (packet, _) = client.update(NETCODE_SEND_RATE).unwrap();
+            match server.process_packet(addr, packet) {
+                ServerResult::PacketToSend { payload, .. }
+                | ServerResult::ClientConnected { payload, .. }
+                | ServerResult::ClientQueued { payload, .. } => {
+                    client.process_packet(payload);
+                }
+                _ => unreachable!(),
+            }
+        };
+
+        exchange(&mut server, &mut client_a, addr_a);
+        exchange(&mut server, &mut client_a, addr_a);
+        assert!(client_a.is_connected());
+
+        let (packet, _) = client_b.update(Duration::ZERO).unwrap();
+        match server.process_packet(addr_b, packet) {
+            ServerResult::ClientQueued { addr, position, payload } => {
+                assert_eq!(addr, addr_b);
+                assert_eq!(position, 1);
+                client_b.process_packet(payload);
+            }
+            _ => unreachable!(),
+        }
+        assert!(client_b.is_connecting());
+        assert_eq!(client_b.queued_position(), Some(1));
+        assert_eq!(server.queued_clients(), 1);
+
+        // Same position is not reported again
+        let (packet, _) = client_b.update(NETCODE_SEND_RATE).unwrap();
+        assert!(matches!(server.process_packet(addr_b, packet), ServerResult::PacketToSend { .. }));
+
+        // Queue is full, request is denied
+        exchange(&mut server, &mut client_c, addr_c);
+        assert!(client_c.is_disconnected());
+
+        server.disconnect(0);
+        exchange(&mut server, &mut client_b, addr_b);
+        assert_eq!(client_b.queued_position(), None);
+        exchange(&mut server, &mut client_b, addr_b);
+        assert!(client_b.is_connected());
+        assert_eq!(server.queued_clients(), 0);
+    }
+
+    #[test]
+    fn pending_clients_flood() {
+        let mut server = new_server();
+        let flood = 10_000;
+        for i in 0..flood {
+            let connect_token =
+                ConnectToken::generate(Duration::ZERO, TEST_PROTOCOL_ID, 30, i, 5, server.addresses(), None, TEST_KEY).unwrap();
+            let mut client = NetcodeClient::new(Duration::ZERO, ClientAuthentication::Secure { connect_token }).unwrap();
+            let addr = SocketAddr::from(([10, 0, (i >> 8) as u8, i as u8], 5000));
+            let (packet, _) = client.update(Duration::ZERO).unwrap();
+            assert!(matches!(server.process_packet(addr, packet), ServerResult::PacketToSend { .. }));
+        }
+        assert_eq!(server.pending_clients(), NETCODE_MAX_PENDING_CLIENTS);
+        assert_eq!(server.evicted_pending_clients(), flood - NETCODE_MAX_PENDING_CLIENTS as u64);
+        assert_eq!(server.pending_order.len(), NETCODE_MAX_PENDING_CLIENTS);
+        // The oldest requests were the ones evicted
+        let first_kept = flood - NETCODE_MAX_PENDING_CLIENTS as u64;
+        let addr_of = |i: u64| SocketAddr::from(([10, 0, (i >> 8) as u8, i as u8], 5000));
+        assert!(!server.pending_clients.contains_key(&addr_of(first_kept - 1)));
+        assert_eq!(server.pending_order.front(), Some(&addr_of(first_kept)));
+
+        // A real client still connects
+        let connect_token =
+            ConnectToken::generate(Duration::ZERO, TEST_PROTOCOL_ID, 30, flood, 5, server.addresses(), None, TEST_KEY).unwrap();
+        let mut client = NetcodeClient::new(Duration::ZERO, ClientAuthentication::Secure { connect_token }).unwrap();
+        let addr: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+        for _ in 0..2 {
+            let (packet, _) = client.update(NETCODE_SEND_RATE).unwrap();
+            match server.process_packet(addr, packet) {
+                ServerResult::PacketToSend { payload, .. } | ServerResult::ClientConnected { payload, .. } => {
+                    client.process_packet(payload);
+                }
+                _ => unreachable!(),
+            }
+        }
+        assert!(client.is_connected());
+
+        // Handshakes that are not completed time out
+        server.update(Duration::from_secs(6));
+        assert_eq!(server.pending_clients(), 0);
+        assert!(server.pending_order.is_empty());
+    }
+
+    #[test]
+    fn connect_queue_entry_expires() {
+        let mut server = new_server();
+        server.set_max_clients(0);
+        server.set_connect_queue(Some(ConnectQueueConfig {
+            max_size: 4,
+            entry_ttl: Duration::from_secs(1),
+        }));
+
+        let connect_token = ConnectToken::generate(Duration::ZERO, TEST_PROTOCOL_ID, 30, 0, 5, server.addresses(), None, TEST_KEY).unwrap();
+        let mut client = NetcodeClient::new(Duration::ZERO, ClientAuthentication::Secure { connect_token }).unwrap();
+        let (packet, _) = client.update(Duration::ZERO).unwrap();
+        assert!(matches!(
+            server.process_packet("127.0.0.1:3000".parse().unwrap(), packet),
+            ServerResult::ClientQueued { position: 1, .. }
+        ));
+
+        server.update(Duration::from_secs(1));
+        assert_eq!(server.queued_clients(), 1);
+        server.update(Duration::from_millis(1));
+        assert_eq!(server.queued_clients(), 0);
+    }
 }
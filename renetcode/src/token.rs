@@ -18,7 +18,8 @@ use chacha20poly1305::aead::Error as CryptoError;
 /// A public connect token that the client receives to start connecting to the server.
 /// How the client receives ConnectToken is up to you, could be from a matchmaking
 /// system or from a call to a REST API as an example.
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// The token is consumed when a client connects with it, so it can't be used twice.
+#[derive(Debug, PartialEq, Eq)]
 pub struct ConnectToken {
     // NOTE: On the netcode standard the client id is not available in the public part of the
     // ConnectToken. But having it acessible here makes it easier to consume the token, and the
@@ -46,6 +47,24 @@ pub(crate) struct PrivateConnectToken {
     pub user_data: [u8; NETCODE_USER_DATA_BYTES], // user defined data specific to this protocol id
 }
 
+/// Contents of a connect token, decrypted with the server private key, see [`ConnectToken::decrypt_private`].
+/// Meant for inspection by tools, like decoding a token from a log when debugging authentication issues.
+/// The encryption keys are not included.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenClaims {
+    /// Client id from the private part of the token, the one used by the server.
+    pub client_id: u64,
+    pub protocol_id: u64,
+    /// Unix timestamp, in seconds, at which the token was created.
+    pub create_timestamp: u64,
+    /// Unix timestamp, in seconds, at which the token expires.
+    pub expire_timestamp: u64,
+    /// Timeout of the connection in seconds, negative values disable the timeout.
+    pub timeout_seconds: i32,
+    pub server_addresses: Vec<SocketAddr>,
+    pub user_data: [u8; NETCODE_USER_DATA_BYTES],
+}
+
 #[derive(Debug)]
 pub enum TokenGenerationError {
     /// The maximum number of address in the token is 32
@@ -166,6 +185,71 @@ impl ConnectToken {
             timeout_seconds,
         })
     }
+
+    /// Decrypts the private part of the token with the server private key and returns its contents.
+    /// Fails if the key, the protocol id or the expire timestamp don't match the ones the token was generated with.
+    pub fn decrypt_private(&self, private_key: &[u8; NETCODE_KEY_BYTES]) -> Result<TokenClaims, NetcodeError> {
+        let private_token = PrivateConnectToken::decode(
+            &self.private_data,
+            self.protocol_id,
+            self.expire_timestamp,
+            &self.xnonce,
+            private_key,
+        )?;
+
+        Ok(TokenClaims {
+            client_id: private_token.client_id,
+            protocol_id: self.protocol_id,
+            create_timestamp: self.create_timestamp,
+            expire_timestamp: self.expire_timestamp,
+            timeout_seconds: private_token.timeout_seconds,
+            server_addresses: private_token.server_addresses.iter().flatten().copied().collect(),
+            user_data: private_token.user_data,
+        })
+    }
+}
+
+impl fmt::Display for TokenClaims {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmt,
+            "client {} for protocol {}, created at {} and expiring at {}, timeout of {} seconds, servers [",
+            self.client_id, self.protocol_id, self.create_timestamp, self.expire_timestamp, self.timeout_seconds
+        )?;
+        for (i, addr) in self.server_addresses.iter().enumerate() {
+            if i > 0 {
+                write!(fmt, ", ")?;
+            }
+            write!(fmt, "{addr}")?;
+        }
+        // Trailing zeros are usually unused user data
+        let user_data_len = self.user_data.iter().rposition(|&byte| byte != 0).map_or(0, |i| i + 1);
+        write!(fmt, "], user data ")?;
+        for byte in &self.user_data[..user_data_len] {
+            write!(fmt, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for TokenClaims {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("TokenClaims", 7)?;
+        state.serialize_field("client_id", &self.client_id)?;
+        state.serialize_field("protocol_id", &self.protocol_id)?;
+        state.serialize_field("create_timestamp", &self.create_timestamp)?;
+        state.serialize_field("expire_timestamp", &self.expire_timestamp)?;
+        state.serialize_field("timeout_seconds", &self.timeout_seconds)?;
+        state.serialize_field("server_addresses", &self.server_addresses)?;
+        state.serialize_field("user_data", &self.user_data[..])?;
+        state.end()
+    }
 }
 
 impl PrivateConnectToken {
@@ -416,4 +500,52 @@ mod tests {
         assert_eq!(token.client_to_server_key, private.client_to_server_key);
         assert_eq!(token.server_to_client_key, private.server_to_client_key);
     }
+
+    #[test]
+    fn decrypt_private_claims() {
+        let server_addresses: Vec<SocketAddr> = vec!["127.0.0.1:8080".parse().unwrap(), "[::1]:3000".parse().unwrap()];
+        let mut user_data = [0u8; NETCODE_USER_DATA_BYTES];
+        user_data[..3].copy_from_slice(&[1, 2, 255]);
+        let private_key = b"an example very very secret key."; // 32-bytes
+        let token = ConnectToken::generate(
+            Duration::from_secs(100),
+            7,
+            30,
+            42,
+            15,
+            server_addresses.clone(),
+            Some(&user_data),
+            private_key,
+        )
+        .unwrap();
+
+        // Round trip through the serialized token, as read from a log
+        let mut buffer: Vec<u8> = vec![];
+        token.write(&mut buffer).unwrap();
+        let token = ConnectToken::read(&mut buffer.as_slice()).unwrap();
+
+        let claims = token.decrypt_private(private_key).unwrap();
+        assert_eq!(
+            claims,
+            TokenClaims {
+                client_id: 42,
+                protocol_id: 7,
+                create_timestamp: 100,
+                expire_timestamp: 130,
+                timeout_seconds: 15,
+                server_addresses,
+                user_data,
+            }
+        );
+        assert_eq!(
+            claims.to_string(),
+            "client 42 for protocol 7, created at 100 and expiring at 130, timeout of 15 seconds, servers [127.0.0.1:8080, [::1]:3000], user data 0102ff"
+        );
+
+        let wrong_key = b"another example very secret key.";
+        assert!(matches!(
+            token.decrypt_private(wrong_key),
+            Err(NetcodeError::TokenGenerationError(_))
+        ));
+    }
 }